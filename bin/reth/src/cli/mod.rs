@@ -164,6 +164,8 @@ impl<C: ChainSpecParser<ChainSpec = ChainSpec>, Ext: clap::Args + fmt::Debug> Cl
             Commands::Debug(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
             Commands::Recover(command) => runner.run_command_until_exit(|ctx| command.execute(ctx)),
             Commands::Prune(command) => runner.run_until_ctrl_c(command.execute()),
+            #[cfg(feature = "narwhal")]
+            Commands::Narwhal(command) => runner.run_blocking_until_ctrl_c(command.execute()),
         }
     }
 
@@ -219,6 +221,10 @@ pub enum Commands<C: ChainSpecParser, Ext: clap::Args + fmt::Debug> {
     /// Prune according to the configuration without any limits
     #[command(name = "prune")]
     Prune(prune::PruneCommand<C>),
+    /// Narwhal consensus utilities
+    #[cfg(feature = "narwhal")]
+    #[command(name = "narwhal")]
+    Narwhal(reth_cli_commands::narwhal::Command<C>),
 }
 
 #[cfg(test)]
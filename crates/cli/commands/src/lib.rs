@@ -15,6 +15,8 @@ pub mod dump_genesis;
 pub mod import;
 pub mod init_cmd;
 pub mod init_state;
+#[cfg(feature = "narwhal")]
+pub mod narwhal;
 pub mod node;
 pub mod p2p;
 pub mod prune;
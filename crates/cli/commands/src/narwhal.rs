@@ -0,0 +1,765 @@
+//! Narwhal consensus utilities.
+
+use crate::common::{AccessRights, Environment, EnvironmentArgs};
+use clap::{Parser, Subcommand, ValueEnum};
+use reth_chainspec::ChainSpec;
+use reth_cli::chainspec::ChainSpecParser;
+use reth_cli_util::get_secret_key;
+use reth_narwhal_consensus::{
+    committee::Committee,
+    committee_history::CommitteeHistory,
+    dag_store::{DagStore, DatabaseDagStore},
+    deployment::{Deployment, DeploymentConfig, P2P_SECRET_KEY_FILE},
+    dot::DagDot,
+    epoch_snapshot::EpochSnapshots,
+    gc::DEFAULT_GC_DEPTH,
+    keys::{AuthorityKeys, KeyProvider, Keystore},
+    record::{read_recording, Replayer},
+    report::EpochReport,
+    scaffold::{ChainManifest, Scaffold, GENESIS_FILE},
+    state_snapshot::ConsensusSnapshot,
+    NarwhalConfig,
+};
+use reth_network_peers::pk2id;
+use reth_primitives::hex;
+use reth_provider::{BlockNumReader, ConsensusMetadataProvider, ProviderResult};
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use tracing::info;
+
+/// `reth narwhal` command
+#[derive(Debug, Parser)]
+pub struct Command<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    #[command(subcommand)]
+    command: Subcommands,
+}
+
+/// `reth narwhal` subcommands
+#[derive(Debug, Subcommand)]
+pub enum Subcommands {
+    /// Reports the contribution of every validator of an epoch, from the indexed consensus
+    /// metadata of its blocks
+    Report(ReportCommand),
+    /// Rolls a stopped validator back to the end of an epoch, from the snapshot taken before the
+    /// change to the next epoch
+    RollbackEpoch(RollbackEpochCommand),
+    /// Generates the keys of a validator into an encrypted keystore
+    Keygen(KeygenCommand),
+    /// Scaffolds a new chain: its genesis, its committee, and the keys and configuration of every
+    /// validator
+    InitChain(InitChainCommand),
+    /// Generates a docker compose file, or kubernetes manifests, that run the validators of a
+    /// scaffolded chain and a follower RPC node
+    GenDeployment(GenDeploymentCommand),
+    /// Replays a recording of the inbound consensus messages of a node through the DAG and the
+    /// commit rule, and reports the committed leaders
+    Replay(ReplayCommand),
+    /// Renders the stored DAG as a Graphviz DOT graph, with the committed certificates filled and
+    /// the leaders highlighted
+    DagDot(DagDotCommand),
+    /// Exports or imports the consensus state, to bootstrap a new validator from a synced one
+    #[command(subcommand)]
+    Snapshot(SnapshotCommand),
+}
+
+/// `reth narwhal report` command
+#[derive(Debug, Parser)]
+pub struct ReportCommand {
+    /// The epoch to report
+    #[arg(long, value_name = "EPOCH")]
+    epoch: u64,
+
+    /// The directory of the committee history
+    ///
+    /// Defaults to `<DATADIR>/narwhal/committees`.
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    committees: Option<PathBuf>,
+
+    /// The output format
+    #[arg(long, value_enum, default_value_t = ReportFormat::Json)]
+    format: ReportFormat,
+
+    /// Write the report to a file instead of stdout
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+/// `reth narwhal rollback-epoch` command
+#[derive(Debug, Parser)]
+pub struct RollbackEpochCommand {
+    /// The epoch to roll back to, the epoch of the latest snapshot if not set
+    #[arg(long, value_name = "EPOCH")]
+    epoch: Option<u64>,
+
+    /// The directory of the epoch snapshots
+    ///
+    /// Defaults to `<DATADIR>/narwhal/snapshots`.
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    snapshots: Option<PathBuf>,
+
+    /// The directory of the committee history
+    ///
+    /// Defaults to `<DATADIR>/narwhal/committees`.
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    committees: Option<PathBuf>,
+}
+
+/// `reth narwhal keygen` command
+#[derive(Debug, Parser)]
+pub struct KeygenCommand {
+    /// The number of workers to generate network keys for
+    #[arg(long, value_name = "COUNT", default_value_t = 1)]
+    workers: usize,
+
+    /// The path of the keystore
+    ///
+    /// Defaults to `<DATADIR>/narwhal/keystore.json`.
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    keystore: Option<PathBuf>,
+
+    /// Path to the file with the password the keystore is encrypted with
+    #[arg(long, value_name = "PATH")]
+    password_file: PathBuf,
+}
+
+/// `reth narwhal init-chain` command
+#[derive(Debug, Parser)]
+pub struct InitChainCommand {
+    /// The manifest of the chain, in TOML or JSON format
+    ///
+    /// Prompts for the chain id and the number of validators and workers if not set.
+    #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+    manifest: Option<PathBuf>,
+
+    /// The directory to write the chain into, which must not contain a chain yet
+    #[arg(long, short, value_name = "DIR")]
+    output: PathBuf,
+}
+
+/// `reth narwhal gen-deployment` command
+#[derive(Debug, Parser)]
+pub struct GenDeploymentCommand {
+    /// The committee file of a chain written by `reth narwhal init-chain`
+    ///
+    /// The directory of the committee file is mounted into the containers.
+    #[arg(long, value_name = "FILE", verbatim_doc_comment)]
+    committee: PathBuf,
+
+    /// The directory to write the deployment into
+    #[arg(long, short, value_name = "DIR")]
+    output: PathBuf,
+
+    /// The docker image of reth
+    #[arg(long, value_name = "IMAGE", default_value_t = DeploymentConfig::default().image)]
+    image: String,
+
+    /// Don't run a follower node that serves RPC
+    #[arg(long)]
+    no_follower: bool,
+
+    /// The HTTP RPC port of the follower node
+    #[arg(long, value_name = "PORT", default_value_t = DeploymentConfig::default().rpc_port)]
+    rpc_port: u16,
+
+    /// Also generate kubernetes manifests
+    #[arg(long)]
+    kubernetes: bool,
+
+    /// The size of the datadir volume of every node on kubernetes
+    #[arg(long, value_name = "SIZE", default_value_t = DeploymentConfig::default().storage)]
+    storage: String,
+}
+
+/// `reth narwhal replay` command
+#[derive(Debug, Parser)]
+pub struct ReplayCommand {
+    /// The recording, written by a node with a `record.path` in its narwhal config
+    #[arg(long, value_name = "FILE")]
+    recording: PathBuf,
+
+    /// The committee of the recorded epoch
+    #[arg(long, value_name = "FILE")]
+    committee: PathBuf,
+
+    /// The narwhal config of the recording node, for its leader schedule
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Number of rounds below the last committed round the recording node kept
+    #[arg(long, value_name = "ROUNDS", default_value_t = DEFAULT_GC_DEPTH)]
+    gc_depth: u64,
+
+    /// Write the report to a file instead of stdout
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+/// `reth narwhal dag-dot` command
+#[derive(Debug, Parser)]
+pub struct DagDotCommand {
+    /// The first round to render
+    #[arg(long, value_name = "ROUND")]
+    from_round: u64,
+
+    /// The last round to render
+    #[arg(long, value_name = "ROUND")]
+    to_round: u64,
+
+    /// Write the graph to a file instead of stdout
+    #[arg(long, short, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+/// `reth narwhal snapshot` subcommands
+#[derive(Debug, Subcommand)]
+pub enum SnapshotCommand {
+    /// Writes the DAG store, the committed sub-dags and the committees of all epochs into a
+    /// snapshot file
+    Export {
+        /// The file to write the snapshot to
+        #[arg(long, short, value_name = "FILE")]
+        output: PathBuf,
+
+        /// The directory of the committee history
+        ///
+        /// Defaults to `<DATADIR>/narwhal/committees`.
+        #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+        committees: Option<PathBuf>,
+    },
+    /// Imports a snapshot file into the empty DAG store of a stopped validator
+    Import {
+        /// The snapshot file to import
+        #[arg(long, short, value_name = "FILE")]
+        input: PathBuf,
+
+        /// The directory of the committee history
+        ///
+        /// Defaults to `<DATADIR>/narwhal/committees`.
+        #[arg(long, value_name = "PATH", verbatim_doc_comment)]
+        committees: Option<PathBuf>,
+    },
+}
+
+/// Output format of the `reth narwhal report` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// The full report as JSON
+    Json,
+    /// One row per validator
+    Csv,
+}
+
+impl<C: ChainSpecParser<ChainSpec = ChainSpec>> Command<C> {
+    /// Execute `narwhal` command
+    pub async fn execute(self) -> eyre::Result<()> {
+        let access = match self.command {
+            Subcommands::Report(_) => AccessRights::RO,
+            Subcommands::RollbackEpoch(_) => AccessRights::RW,
+            Subcommands::DagDot(_) => AccessRights::RO,
+            Subcommands::Snapshot(SnapshotCommand::Export { .. }) => AccessRights::RO,
+            Subcommands::Snapshot(SnapshotCommand::Import { .. }) => AccessRights::RW,
+            Subcommands::Keygen(command) => {
+                // the keys don't depend on the database
+                let data_dir = self.env.datadir.resolve_datadir(self.env.chain.chain);
+                return command.execute(data_dir.data_dir())
+            }
+            Subcommands::InitChain(command) => return command.execute(),
+            Subcommands::GenDeployment(command) => return command.execute(),
+            Subcommands::Replay(command) => return command.execute(),
+        };
+        let Environment { provider_factory, data_dir, .. } = self.env.init(access)?;
+
+        match self.command {
+            Subcommands::Report(command) => {
+                let dir = command
+                    .committees
+                    .clone()
+                    .unwrap_or_else(|| data_dir.data_dir().join("narwhal").join("committees"));
+                eyre::ensure!(dir.is_dir(), "Committee history does not exist: {:?}", dir);
+                let history = CommitteeHistory::<Committee>::open(&dir)?;
+                let record = history.epoch(command.epoch).ok_or_else(|| {
+                    eyre::eyre!("No committee recorded for epoch {} in {:?}", command.epoch, dir)
+                })?;
+
+                // the latest epoch ends at the tip
+                let tip = provider_factory.last_block_number()?;
+                let last_block =
+                    history.last_block(command.epoch).map_or(tip, |last| last.min(tip));
+                eyre::ensure!(
+                    record.first_block <= last_block,
+                    "Epoch {} starts at block {}, after the tip {}",
+                    command.epoch,
+                    record.first_block,
+                    tip
+                );
+
+                info!(
+                    target: "reth::cli",
+                    epoch = command.epoch,
+                    first_block = record.first_block,
+                    last_block,
+                    "Reading consensus metadata"
+                );
+                let blocks = (record.first_block..=last_block)
+                    .map(|number| Ok((number, provider_factory.consensus_metadata(number)?)))
+                    .collect::<ProviderResult<Vec<_>>>()?;
+                let report = EpochReport::new(&record.committee, record.first_block, blocks);
+
+                command.write(&report)?;
+            }
+            Subcommands::Keygen(_) |
+            Subcommands::InitChain(_) |
+            Subcommands::GenDeployment(_) |
+            Subcommands::Replay(_) => {
+                unreachable!(
+                    "keygen, init-chain, gen-deployment and replay don't open the database"
+                )
+            }
+            Subcommands::DagDot(command) => {
+                eyre::ensure!(
+                    command.from_round <= command.to_round,
+                    "--from-round {} is after --to-round {}",
+                    command.from_round,
+                    command.to_round
+                );
+                let store = DatabaseDagStore::new(provider_factory.db_ref().clone());
+                let dot = DagDot::from_store(&store, command.from_round..=command.to_round)?;
+                match &command.output {
+                    Some(path) => fs::write(path, dot.render())?,
+                    None => io::stdout().lock().write_all(dot.render().as_bytes())?,
+                }
+            }
+            Subcommands::Snapshot(SnapshotCommand::Export { output, committees }) => {
+                let dir = committees
+                    .unwrap_or_else(|| data_dir.data_dir().join("narwhal").join("committees"));
+                eyre::ensure!(dir.is_dir(), "Committee history does not exist: {:?}", dir);
+                let history = CommitteeHistory::<Committee>::open(&dir)?;
+                let store = DatabaseDagStore::new(provider_factory.db_ref().clone());
+                let snapshot = ConsensusSnapshot::export(&store, history.epochs())?;
+                snapshot.validate()?;
+                snapshot.write(&output)?;
+
+                info!(
+                    target: "reth::cli",
+                    path = ?output,
+                    epoch = snapshot.epoch(),
+                    highest_round = snapshot.highest_round(),
+                    next_sub_dag = snapshot.next_sub_dag(),
+                    certificates = snapshot.certificates.len(),
+                    "Exported consensus snapshot"
+                );
+            }
+            Subcommands::Snapshot(SnapshotCommand::Import { input, committees }) => {
+                let snapshot = ConsensusSnapshot::read(&input)?;
+                let dir = committees
+                    .unwrap_or_else(|| data_dir.data_dir().join("narwhal").join("committees"));
+                let mut history = CommitteeHistory::<Committee>::open(&dir)?;
+                let store = DatabaseDagStore::new(provider_factory.db_ref().clone());
+                snapshot.import(&store, &mut history)?;
+
+                info!(
+                    target: "reth::cli",
+                    path = ?input,
+                    epoch = snapshot.epoch(),
+                    highest_round = snapshot.highest_round(),
+                    next_sub_dag = snapshot.next_sub_dag(),
+                    certificates = snapshot.certificates.len(),
+                    "Imported consensus snapshot"
+                );
+                info!(
+                    target: "reth::cli",
+                    "Sync the chain up to the block of the last sub-dag of the snapshot before \
+                     starting the validator"
+                );
+            }
+            Subcommands::RollbackEpoch(command) => {
+                let dir = command
+                    .snapshots
+                    .unwrap_or_else(|| data_dir.data_dir().join("narwhal").join("snapshots"));
+                eyre::ensure!(dir.is_dir(), "Epoch snapshots do not exist: {:?}", dir);
+                let snapshots = EpochSnapshots::open(&dir)?;
+                let snapshot = match command.epoch {
+                    Some(epoch) => snapshots.get(epoch)?,
+                    None => snapshots.latest()?,
+                }
+                .ok_or_else(|| eyre::eyre!("No epoch snapshot to roll back to in {:?}", dir))?;
+                let epoch = snapshot.epoch();
+
+                // the blocks of the later epochs are unwound separately
+                let mut unwind_to = None;
+                let committees = command
+                    .committees
+                    .unwrap_or_else(|| data_dir.data_dir().join("narwhal").join("committees"));
+                if committees.is_dir() {
+                    let mut history = CommitteeHistory::<Committee>::open(&committees)?;
+                    unwind_to = history.epoch(epoch + 1).map(|record| record.first_block - 1);
+                    history.truncate(epoch)?;
+                }
+
+                DatabaseDagStore::new(provider_factory.db_ref().clone())
+                    .reset(&snapshot.last_committed)?;
+                snapshots.truncate(epoch)?;
+
+                info!(
+                    target: "reth::cli",
+                    epoch,
+                    last_leader_round = snapshot.last_leader_round,
+                    next_sub_dag = snapshot.next_sub_dag,
+                    "Rolled back to the end of epoch"
+                );
+                info!(
+                    target: "reth::cli",
+                    next_epoch = snapshot.change.committee.epoch,
+                    boundary_round = snapshot.change.boundary_round,
+                    "Remove the epoch change from the committee source before restarting the node"
+                );
+                if let Some(block) = unwind_to {
+                    info!(
+                        target: "reth::cli",
+                        block,
+                        "Unwind the rolled back blocks with `reth stage unwind to-block`"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl KeygenCommand {
+    /// Generates the keys and writes them to a new keystore, and prints the public key and its
+    /// proof of possession for the committee file.
+    fn execute(self, data_dir: &Path) -> eyre::Result<()> {
+        let path = self.keystore.unwrap_or_else(|| data_dir.join("narwhal").join("keystore.json"));
+        eyre::ensure!(!path.exists(), "Keystore already exists: {:?}", path);
+        let password = fs::read_to_string(&self.password_file)?;
+        let keys = AuthorityKeys::generate(self.workers);
+        Keystore::new(&path).create(&keys, password.trim_end().as_bytes())?;
+
+        info!(target: "reth::cli", ?path, workers = self.workers, "Generated validator keys");
+        // the entry of the authority in the committee file
+        let proof_of_possession = keys.authority_key().proof_of_possession();
+        println!(
+            "publicKey = \"{}\"",
+            hex::encode_prefixed(keys.authority_public_key().to_bytes())
+        );
+        println!(
+            "proofOfPossession = \"{}\"",
+            hex::encode_prefixed(proof_of_possession.to_bytes())
+        );
+        Ok(())
+    }
+}
+
+impl InitChainCommand {
+    /// Generates the chain and writes it to the output directory.
+    fn execute(self) -> eyre::Result<()> {
+        let manifest = match &self.manifest {
+            Some(path) => ChainManifest::load(path)?,
+            None => Self::prompt_manifest(&mut io::stdin().lock())?,
+        };
+        let genesis_path = self.output.join(GENESIS_FILE);
+        eyre::ensure!(!genesis_path.exists(), "Chain already exists: {:?}", self.output);
+
+        let genesis = manifest.genesis()?;
+        let scaffold = Scaffold::generate(&manifest)?;
+        scaffold.write(&self.output)?;
+        fs::write(&genesis_path, serde_json::to_string_pretty(&genesis)?)?;
+
+        info!(
+            target: "reth::cli",
+            path = ?self.output,
+            chain_id = manifest.chain_id,
+            validators = manifest.validators,
+            workers = manifest.workers,
+            "Scaffolded chain"
+        );
+        for validator in 0..manifest.validators {
+            let dir = Scaffold::validator_dir(&self.output, validator);
+            println!("reth node $(cat {})", dir.join("reth.args").display());
+        }
+        Ok(())
+    }
+
+    /// Reads a manifest from the answers to prompts for its main settings.
+    fn prompt_manifest(input: &mut impl BufRead) -> eyre::Result<ChainManifest> {
+        let default = ChainManifest::default();
+        Ok(ChainManifest {
+            chain_id: prompt(input, "Chain id", default.chain_id)?,
+            validators: prompt(input, "Number of validators", default.validators)?,
+            workers: prompt(input, "Number of workers per validator", default.workers)?,
+            ..default
+        })
+    }
+}
+
+impl GenDeploymentCommand {
+    /// Generates the deployment and writes it to the output directory.
+    ///
+    /// Creates the devp2p secret keys of the validators that don't have one yet, so that the
+    /// follower node can connect to them.
+    fn execute(self) -> eyre::Result<()> {
+        let config = DeploymentConfig {
+            image: self.image,
+            follower: !self.no_follower,
+            rpc_port: self.rpc_port,
+            storage: self.storage,
+        };
+        let committee = fs::canonicalize(&self.committee)?;
+        let chain_dir = committee.parent().expect("files have a parent directory");
+        let mut deployment = Deployment::load(&committee, config)?;
+        for validator in 0..deployment.committee().authorities.len() {
+            let path = Scaffold::validator_dir(chain_dir, validator).join(P2P_SECRET_KEY_FILE);
+            let key = get_secret_key(&path)?;
+            let id = pk2id(&key.public_key(secp256k1::SECP256K1));
+            deployment = deployment.with_p2p_id(validator, hex::encode(id));
+        }
+
+        fs::create_dir_all(&self.output)?;
+        let compose = self.output.join("compose.json");
+        fs::write(&compose, serde_json::to_string_pretty(&deployment.compose(chain_dir))?)?;
+        info!(target: "reth::cli", path = ?compose, "Generated docker compose file");
+        if self.kubernetes {
+            let manifests = self.output.join("kubernetes.json");
+            fs::write(
+                &manifests,
+                serde_json::to_string_pretty(&deployment.kubernetes(chain_dir)?)?,
+            )?;
+            info!(target: "reth::cli", path = ?manifests, "Generated kubernetes manifests");
+        }
+        Ok(())
+    }
+}
+
+impl ReplayCommand {
+    /// Replays the recording and writes the report.
+    fn execute(self) -> eyre::Result<()> {
+        let committee = Committee::load(&self.committee)?;
+        let config: NarwhalConfig = match &self.config {
+            Some(path) => toml::from_str(&fs::read_to_string(path)?)?,
+            None => NarwhalConfig::default(),
+        };
+        let recording = read_recording(&self.recording)?;
+        info!(target: "reth::cli", messages = recording.len(), "Replaying recording");
+        let report = Replayer::new(committee, config.leader_schedule)
+            .with_gc_depth(self.gc_depth)
+            .replay_all(&recording);
+        info!(
+            target: "reth::cli",
+            certificates = report.certificates,
+            commits = report.commits.len(),
+            rejected = report.rejected.len(),
+            pending = report.pending.len(),
+            "Replayed recording"
+        );
+
+        let mut out: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+        serde_json::to_writer_pretty(&mut out, &report)?;
+        writeln!(out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Prompts for a value on stdout and parses the answer, the default if the answer is empty.
+fn prompt<T>(input: &mut impl BufRead, question: &str, default: T) -> eyre::Result<T>
+where
+    T: FromStr + std::fmt::Display,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    print!("{question} [{default}]: ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    match answer.trim() {
+        "" => Ok(default),
+        answer => Ok(answer.parse()?),
+    }
+}
+
+impl ReportCommand {
+    /// Writes the report in the configured format.
+    fn write(&self, report: &EpochReport) -> eyre::Result<()> {
+        let mut out: Box<dyn Write> = match &self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+        match self.format {
+            ReportFormat::Json => {
+                serde_json::to_writer_pretty(&mut out, report)?;
+                writeln!(out)?;
+            }
+            ReportFormat::Csv => report.write_csv(&mut out)?,
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_node_core::args::utils::DefaultChainSpecParser;
+
+    #[test]
+    fn parse_report_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth", "report", "--epoch", "3", "--format", "csv",
+        ]);
+        let Subcommands::Report(report) = command.command else {
+            panic!("expected report command")
+        };
+        assert_eq!(report.epoch, 3);
+        assert_eq!(report.format, ReportFormat::Csv);
+        assert_eq!(report.output, None);
+    }
+
+    #[test]
+    fn parse_rollback_epoch_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "rollback-epoch",
+            "--epoch",
+            "4",
+        ]);
+        let Subcommands::RollbackEpoch(rollback) = command.command else {
+            panic!("expected rollback-epoch command")
+        };
+        assert_eq!(rollback.epoch, Some(4));
+        assert_eq!(rollback.snapshots, None);
+    }
+
+    #[test]
+    fn parse_keygen_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "keygen",
+            "--workers",
+            "4",
+            "--password-file",
+            "password.txt",
+        ]);
+        let Subcommands::Keygen(keygen) = command.command else {
+            panic!("expected keygen command")
+        };
+        assert_eq!(keygen.workers, 4);
+        assert_eq!(keygen.keystore, None);
+        assert_eq!(keygen.password_file, PathBuf::from("password.txt"));
+    }
+
+    #[test]
+    fn parse_init_chain_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "init-chain",
+            "--manifest",
+            "chain.toml",
+            "-o",
+            "chain",
+        ]);
+        let Subcommands::InitChain(init) = command.command else {
+            panic!("expected init-chain command")
+        };
+        assert_eq!(init.manifest, Some(PathBuf::from("chain.toml")));
+        assert_eq!(init.output, PathBuf::from("chain"));
+    }
+
+    #[test]
+    fn parse_gen_deployment_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "gen-deployment",
+            "--committee",
+            "chain/committee.toml",
+            "-o",
+            "deploy",
+            "--kubernetes",
+        ]);
+        let Subcommands::GenDeployment(deployment) = command.command else {
+            panic!("expected gen-deployment command")
+        };
+        assert_eq!(deployment.committee, PathBuf::from("chain/committee.toml"));
+        assert!(deployment.kubernetes);
+        assert!(!deployment.no_follower);
+        assert_eq!(deployment.rpc_port, 8545);
+    }
+
+    #[test]
+    fn parse_replay_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "replay",
+            "--recording",
+            "messages.jsonl",
+            "--committee",
+            "committee.toml",
+        ]);
+        let Subcommands::Replay(replay) = command.command else {
+            panic!("expected replay command")
+        };
+        assert_eq!(replay.recording, PathBuf::from("messages.jsonl"));
+        assert_eq!(replay.config, None);
+        assert_eq!(replay.gc_depth, 50);
+    }
+
+    #[test]
+    fn parse_dag_dot_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "dag-dot",
+            "--from-round",
+            "10",
+            "--to-round",
+            "20",
+        ]);
+        let Subcommands::DagDot(dot) = command.command else { panic!("expected dag-dot command") };
+        assert_eq!((dot.from_round, dot.to_round), (10, 20));
+        assert_eq!(dot.output, None);
+    }
+
+    #[test]
+    fn prompt_init_chain_manifest() {
+        let manifest =
+            InitChainCommand::prompt_manifest(&mut io::Cursor::new("42\n\n2\n")).unwrap();
+        assert_eq!(manifest.chain_id, 42);
+        assert_eq!(manifest.validators, ChainManifest::default().validators);
+        assert_eq!(manifest.workers, 2);
+    }
+
+    #[test]
+    fn parse_snapshot_command() {
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "snapshot",
+            "export",
+            "--output",
+            "snapshot.json",
+        ]);
+        let Subcommands::Snapshot(SnapshotCommand::Export { output, committees }) = command.command
+        else {
+            panic!("expected snapshot export command")
+        };
+        assert_eq!(output, PathBuf::from("snapshot.json"));
+        assert_eq!(committees, None);
+
+        let command = Command::<DefaultChainSpecParser>::parse_from([
+            "reth",
+            "snapshot",
+            "import",
+            "-i",
+            "snapshot.json",
+        ]);
+        assert!(matches!(command.command, Subcommands::Snapshot(SnapshotCommand::Import { .. })));
+    }
+}
@@ -8,7 +8,8 @@ use reth_primitives::{
         MAXIMUM_EXTRA_DATA_SIZE,
     },
     eip4844::calculate_excess_blob_gas,
-    EthereumHardfork, GotExpected, Header, SealedBlock, SealedHeader,
+    gas_spent_by_transactions, Bloom, EthereumHardfork, GotExpected, Header, Receipt, SealedBlock,
+    SealedHeader, B256,
 };
 
 /// Gas used needs to be less than gas limit. Gas used is going to be checked after execution.
@@ -297,6 +298,70 @@ pub fn validate_against_parent_4844(
     Ok(())
 }
 
+/// Validates that the gas used in the header matches the cumulative gas used of the last receipt of
+/// the block.
+pub fn validate_block_gas_used(
+    header: &Header,
+    receipts: &[Receipt],
+) -> Result<(), ConsensusError> {
+    let cumulative_gas_used =
+        receipts.last().map(|receipt| receipt.cumulative_gas_used).unwrap_or(0);
+    if header.gas_used != cumulative_gas_used {
+        return Err(ConsensusError::BlockGasUsed {
+            gas: GotExpected { got: cumulative_gas_used, expected: header.gas_used },
+            gas_spent_by_tx: gas_spent_by_transactions(receipts),
+        })
+    }
+    Ok(())
+}
+
+/// Calculate the receipts root, and compare it against the expected receipts root and logs
+/// bloom.
+pub fn verify_receipts(
+    expected_receipts_root: B256,
+    expected_logs_bloom: Bloom,
+    receipts: &[Receipt],
+) -> Result<(), ConsensusError> {
+    // Calculate receipts root.
+    let receipts_with_bloom = receipts.iter().map(Receipt::with_bloom_ref).collect::<Vec<_>>();
+    let receipts_root = reth_primitives::proofs::calculate_receipt_root_ref(&receipts_with_bloom);
+
+    // Calculate header logs bloom.
+    let logs_bloom = receipts_with_bloom.iter().fold(Bloom::ZERO, |bloom, r| bloom | r.bloom);
+
+    compare_receipts_root_and_logs_bloom(
+        receipts_root,
+        logs_bloom,
+        expected_receipts_root,
+        expected_logs_bloom,
+    )?;
+
+    Ok(())
+}
+
+/// Compare the calculated receipts root with the expected receipts root, also compare
+/// the calculated logs bloom with the expected logs bloom.
+fn compare_receipts_root_and_logs_bloom(
+    calculated_receipts_root: B256,
+    calculated_logs_bloom: Bloom,
+    expected_receipts_root: B256,
+    expected_logs_bloom: Bloom,
+) -> Result<(), ConsensusError> {
+    if calculated_receipts_root != expected_receipts_root {
+        return Err(ConsensusError::BodyReceiptRootDiff(
+            GotExpected { got: calculated_receipts_root, expected: expected_receipts_root }.into(),
+        ))
+    }
+
+    if calculated_logs_bloom != expected_logs_bloom {
+        return Err(ConsensusError::BodyBloomLogDiff(
+            GotExpected { got: calculated_logs_bloom, expected: expected_logs_bloom }.into(),
+        ))
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -561,4 +626,110 @@ mod tests {
             }))
         );
     }
+
+    #[test]
+    fn test_verify_receipts_success() {
+        // Create a vector of 5 default Receipt instances
+        let receipts = vec![Receipt::default(); 5];
+
+        // Compare against expected values
+        assert!(verify_receipts(
+            B256::from(hex!("61353b4fb714dc1fccacbf7eafc4273e62f3d1eed716fe41b2a0cd2e12c63ebc")),
+            Bloom::from(hex!("00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000")),
+            &receipts
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_verify_receipts_incorrect_root() {
+        // Generate random expected values to produce a failure
+        let expected_receipts_root = B256::random();
+        let expected_logs_bloom = Bloom::random();
+
+        // Create a vector of 5 random Receipt instances
+        let receipts = vec![Receipt::default(); 5];
+
+        assert!(verify_receipts(expected_receipts_root, expected_logs_bloom, &receipts).is_err());
+    }
+
+    #[test]
+    fn test_compare_receipts_root_and_logs_bloom_success() {
+        let calculated_receipts_root = B256::random();
+        let calculated_logs_bloom = Bloom::random();
+
+        let expected_receipts_root = calculated_receipts_root;
+        let expected_logs_bloom = calculated_logs_bloom;
+
+        assert!(compare_receipts_root_and_logs_bloom(
+            calculated_receipts_root,
+            calculated_logs_bloom,
+            expected_receipts_root,
+            expected_logs_bloom
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_compare_receipts_root_failure() {
+        let calculated_receipts_root = B256::random();
+        let calculated_logs_bloom = Bloom::random();
+
+        let expected_receipts_root = B256::random();
+        let expected_logs_bloom = calculated_logs_bloom;
+
+        assert_eq!(
+            compare_receipts_root_and_logs_bloom(
+                calculated_receipts_root,
+                calculated_logs_bloom,
+                expected_receipts_root,
+                expected_logs_bloom
+            ),
+            Err(ConsensusError::BodyReceiptRootDiff(
+                GotExpected { got: calculated_receipts_root, expected: expected_receipts_root }
+                    .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_compare_log_bloom_failure() {
+        let calculated_receipts_root = B256::random();
+        let calculated_logs_bloom = Bloom::random();
+
+        let expected_receipts_root = calculated_receipts_root;
+        let expected_logs_bloom = Bloom::random();
+
+        assert_eq!(
+            compare_receipts_root_and_logs_bloom(
+                calculated_receipts_root,
+                calculated_logs_bloom,
+                expected_receipts_root,
+                expected_logs_bloom
+            ),
+            Err(ConsensusError::BodyBloomLogDiff(
+                GotExpected { got: calculated_logs_bloom, expected: expected_logs_bloom }.into()
+            ))
+        );
+    }
+
+    #[test]
+    fn block_gas_used() {
+        let receipts = vec![
+            Receipt { cumulative_gas_used: 21_000, ..Default::default() },
+            Receipt { cumulative_gas_used: 42_000, ..Default::default() },
+        ];
+        let header = Header { gas_used: 42_000, ..Default::default() };
+        assert_eq!(validate_block_gas_used(&header, &receipts), Ok(()));
+
+        let header = Header { gas_used: 21_000, ..Default::default() };
+        assert_eq!(
+            validate_block_gas_used(&header, &receipts),
+            Err(ConsensusError::BlockGasUsed {
+                gas: GotExpected { got: 42_000, expected: 21_000 },
+                gas_spent_by_tx: gas_spent_by_transactions(&receipts),
+            })
+        );
+        assert!(validate_block_gas_used(&header, &[]).is_err());
+    }
 }
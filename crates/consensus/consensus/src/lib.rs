@@ -180,6 +180,11 @@ pub enum ConsensusError {
     #[display("mismatched block requests root: {_0}")]
     BodyRequestsRootDiff(GotExpectedBoxed<B256>),
 
+    /// Error when the extra data of a narwhal block is different from the commitment to its
+    /// sub-dag and the root of the outbound messages sent in the block.
+    #[display("mismatched block commitment: {_0}")]
+    BlockCommitmentDiff(GotExpectedBoxed<B256>),
+
     /// Error when a block with a specific hash and number is already known.
     #[display("block with [hash={hash}, number={number}] is already known")]
     BlockKnown {
@@ -257,6 +262,71 @@ pub enum ConsensusError {
     #[display("missing requests root")]
     RequestsRootMissing,
 
+    /// Error when a narwhal block commits to a different sub-dag than the one committed after the
+    /// sub-dag of its parent, by the position of the sub-dags in the sequence of all commits.
+    #[display("mismatched sub-dag index: {_0}")]
+    SubDagIndexDiff(GotExpected<u64>),
+
+    /// Error when a narwhal block commits to a different round than the round of the leader of
+    /// its sub-dag.
+    #[display("mismatched sub-dag round: {_0}")]
+    SubDagRoundDiff(GotExpected<u64>),
+
+    /// Error when a narwhal block is not the block after its parent among the blocks of its
+    /// sub-dag.
+    #[display("mismatched position in the blocks of the sub-dag: {_0}")]
+    SubDagPartDiff(GotExpected<u64>),
+
+    /// Error when the transactions of a sub-dag are split across more blocks than allowed.
+    #[display("sub-dag exceeds {max} blocks")]
+    SubDagBlocksExceeded {
+        /// The maximum number of blocks of a sub-dag.
+        max: u64,
+    },
+
+    /// Error when the sub-dag a narwhal block commits to is not recorded in the DAG store.
+    #[display("sub-dag {_0} is not recorded")]
+    SubDagUnknown(B256),
+
+    /// Error when the recorded sub-dags can't be read from the DAG store.
+    #[display("failed to read the recorded sub-dags")]
+    SubDagStoreUnavailable,
+
+    /// Error when the extra data of a narwhal block is not a commitment to its sub-dag.
+    #[display("missing block commitment")]
+    BlockCommitmentMissing,
+
+    /// Error when a narwhal block includes a transaction rejected by the chain's sequencing
+    /// filter.
+    #[display("transaction {hash} is rejected by the sequencing filter")]
+    TransactionFiltered {
+        /// The hash of the rejected transaction.
+        hash: B256,
+    },
+
+    /// Error when a narwhal block includes a transaction that exceeds the chain's size limits.
+    #[display("transaction {hash} exceeds the transaction size limits")]
+    TransactionTooLarge {
+        /// The hash of the oversized transaction.
+        hash: B256,
+    },
+
+    /// Error when a narwhal block orders a transaction differently than the chain's nonce gap
+    /// policy orders the transactions of its commit.
+    #[display("transaction {hash} is out of sequencing order")]
+    TransactionOutOfOrder {
+        /// The hash of the misplaced transaction.
+        hash: B256,
+    },
+
+    /// Error when a narwhal block includes a deposit transaction after a transaction that is not a
+    /// deposit.
+    #[display("deposit {hash} is not at the front of the block")]
+    DepositOutOfOrder {
+        /// The hash of the misplaced deposit transaction.
+        hash: B256,
+    },
+
     /// Error when an unexpected withdrawals root is encountered.
     #[display("unexpected withdrawals root")]
     WithdrawalsRootUnexpected,
@@ -0,0 +1,40 @@
+//! Client of narwhal chains for browsers and edge runtimes.
+//!
+//! The crate compiles to `wasm32` and performs no I/O itself, the host brings its own transport,
+//! e.g. `fetch` in a browser. It knows everything else a client needs to submit a transaction and
+//! follow it until a quorum of the committee certified its block:
+//!
+//! - [`submission`] encodes transactions for the gRPC `Transactions` service of the workers, and
+//!   decodes the status the workers reply with.
+//! - [`rpc`] builds the JSON-RPC requests of the `eth_` and `narwhal_` methods a client calls, and
+//!   defines their responses and notifications.
+//! - [`tracker`] follows a transaction through its receipt and the certified checkpoint of its
+//!   block.
+//!
+//! Checkpoints are verified with the rules of `reth-narwhal-verifier`, with the signature scheme of
+//! the chain supplied by the host, so that the client doesn't depend on a native BLS library:
+//!
+//! ```ignore
+//! let mut tracker = TransactionTracker::new(hash);
+//! tracker.on_receipt(&receipt);
+//! let range = checkpoint.verify::<Bls>(&committee, &domain)?;
+//! tracker.on_checkpoint(&range, &block_hashes);
+//! assert!(tracker.status().is_final());
+//! ```
+
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/paradigmxyz/reth/main/assets/reth-docs.png",
+    html_favicon_url = "https://avatars0.githubusercontent.com/u/97369466?s=256",
+    issue_tracker_base_url = "https://github.com/paradigmxyz/reth/issues/"
+)]
+#![cfg_attr(not(test), warn(unused_crate_dependencies))]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+pub mod rpc;
+pub mod submission;
+pub mod tracker;
+
+pub use reth_narwhal_verifier::{
+    abi::CheckpointRange, SignatureScheme, SigningDomain, VerificationError, VerifierCommittee,
+};
+pub use tracker::{TransactionStatus, TransactionTracker};
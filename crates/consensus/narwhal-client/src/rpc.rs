@@ -0,0 +1,395 @@
+//! JSON-RPC methods to submit and track transactions.
+//!
+//! The functions of this module return the requests a client sends, e.g. as the body of a `POST`
+//! or a WebSocket message, and [`JsonRpcResponse::into_result`] decodes the response into the
+//! types of this module. Only the fields a client needs are decoded, so the types stay compatible
+//! when the node adds fields.
+
+use alloy_primitives::{hex, BlockNumber, Bytes, B256, U64};
+use reth_narwhal_verifier::{
+    abi::{verify_checkpoint_range_proof, CheckpointRange, CheckpointRangeProof},
+    AuthorityIndex, Epoch, SignatureScheme, SigningDomain, Stake, VerificationError,
+    VerifierAuthority, VerifierCommittee,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A JSON-RPC 2.0 request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JsonRpcRequest {
+    jsonrpc: &'static str,
+    /// The identifier of the request, which the response repeats.
+    pub id: u64,
+    /// The called method.
+    pub method: &'static str,
+    /// The positional parameters of the method.
+    pub params: Value,
+}
+
+impl JsonRpcRequest {
+    /// Creates a request of the method with the given positional parameters.
+    pub const fn new(id: u64, method: &'static str, params: Value) -> Self {
+        Self { jsonrpc: "2.0", id, method, params }
+    }
+
+    /// Returns the JSON encoding of the request.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("request is valid JSON")
+    }
+}
+
+/// Returns the request that submits the EIP-2718 encoding of a signed transaction to the pool of
+/// the node, which returns the hash of the transaction.
+pub fn send_raw_transaction(id: u64, transaction: &[u8]) -> JsonRpcRequest {
+    JsonRpcRequest::new(id, "eth_sendRawTransaction", json!([hex::encode_prefixed(transaction)]))
+}
+
+/// Returns the request of the [`TransactionReceipt`] of a transaction, which is `null` while the
+/// transaction is pending.
+pub fn transaction_receipt(id: u64, hash: B256) -> JsonRpcRequest {
+    JsonRpcRequest::new(id, "eth_getTransactionReceipt", json!([hash]))
+}
+
+/// Returns the request of the [`Block`] with the given number, without its transactions.
+pub fn block_by_number(id: u64, number: BlockNumber) -> JsonRpcRequest {
+    JsonRpcRequest::new(id, "eth_getBlockByNumber", json!([U64::from(number), false]))
+}
+
+/// Returns the request of the [`BlockAttribution`] of the block with the given number.
+pub fn block_attribution(id: u64, number: BlockNumber) -> JsonRpcRequest {
+    JsonRpcRequest::new(id, "narwhal_getBlockAttribution", json!([U64::from(number)]))
+}
+
+/// Returns the request of the [`CommitteeInfo`] of the current epoch.
+pub const fn committee(id: u64) -> JsonRpcRequest {
+    JsonRpcRequest::new(id, "narwhal_committee", json!([]))
+}
+
+/// Returns the request that subscribes to the [`CertifiedCheckpoint`]s of the chain, over a
+/// WebSocket.
+///
+/// The response is the identifier of the subscription, and every checkpoint is a
+/// [`SubscriptionNotification`].
+pub const fn subscribe_checkpoints(id: u64) -> JsonRpcRequest {
+    JsonRpcRequest::new(id, "narwhal_subscribeCheckpoints", json!([]))
+}
+
+/// A JSON-RPC 2.0 response.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct JsonRpcResponse {
+    /// The identifier of the request.
+    pub id: Option<u64>,
+    /// The result of a successful call.
+    #[serde(default)]
+    pub result: Value,
+    /// The error of a failed call.
+    pub error: Option<JsonRpcError>,
+}
+
+impl JsonRpcResponse {
+    /// Decodes the result of the call, `T` is an `Option` for methods that return `null`.
+    pub fn into_result<T: DeserializeOwned>(self) -> Result<T, RpcError> {
+        if let Some(error) = self.error {
+            return Err(RpcError::Rpc(error))
+        }
+        serde_json::from_value(self.result).map_err(|err| RpcError::Decode(err.to_string()))
+    }
+}
+
+/// The error object of a failed call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, derive_more::Display)]
+#[display("{message} ({code})")]
+pub struct JsonRpcError {
+    /// The error code, `narwhal_` methods use the range reserved for narwhal errors.
+    pub code: i64,
+    /// The message of the error.
+    pub message: String,
+    /// The details of the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+/// A call that failed.
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display)]
+pub enum RpcError {
+    /// The node returned an error.
+    #[display("call failed: {_0}")]
+    Rpc(JsonRpcError),
+    /// The result doesn't match the type of the method.
+    #[display("invalid result: {_0}")]
+    Decode(String),
+}
+
+impl std::error::Error for RpcError {}
+
+/// A notification of a subscription.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SubscriptionNotification<T> {
+    /// The notification method, e.g. `narwhal_checkpoint`.
+    pub method: String,
+    /// The subscription and its item.
+    pub params: SubscriptionParams<T>,
+}
+
+/// The parameters of a [`SubscriptionNotification`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SubscriptionParams<T> {
+    /// The identifier of the subscription, as returned by the subscribe call.
+    pub subscription: Value,
+    /// The item.
+    pub result: T,
+}
+
+/// The receipt of an executed transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    /// The hash of the transaction.
+    pub transaction_hash: B256,
+    /// The hash of the block of the transaction.
+    pub block_hash: B256,
+    /// The number of the block of the transaction.
+    pub block_number: U64,
+    /// `1` if the transaction succeeded, `0` if it reverted.
+    #[serde(default)]
+    pub status: Option<U64>,
+}
+
+impl TransactionReceipt {
+    /// Returns `true` if the transaction didn't revert.
+    pub fn succeeded(&self) -> bool {
+        self.status.map_or(true, |status| status == U64::from(1))
+    }
+}
+
+/// The header fields of a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Block {
+    /// The number of the block.
+    pub number: U64,
+    /// The hash of the block.
+    pub hash: B256,
+}
+
+/// The committee of an epoch, with the public keys and stakes of its authorities.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitteeInfo {
+    /// The epoch of the committee.
+    pub epoch: Epoch,
+    /// The authorities, the position of an authority is its [`AuthorityIndex`].
+    pub authorities: Vec<AuthorityInfo>,
+}
+
+/// An authority of a [`CommitteeInfo`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorityInfo {
+    /// The encoded public key the authority signs with.
+    pub public_key: Bytes,
+    /// The voting power of the authority.
+    pub stake: Stake,
+}
+
+impl CommitteeInfo {
+    /// Returns the committee with the public keys decoded for the signature scheme of the chain.
+    ///
+    /// Returns the index of the first authority whose key fails to decode as the error.
+    pub fn verifier_committee<K>(&self) -> Result<VerifierCommittee<K>, AuthorityIndex>
+    where
+        for<'a> K: TryFrom<&'a [u8]>,
+    {
+        let authorities = self
+            .authorities
+            .iter()
+            .enumerate()
+            .map(|(index, authority)| {
+                let public_key =
+                    K::try_from(&authority.public_key[..]).map_err(|_| index as AuthorityIndex)?;
+                Ok(VerifierAuthority { public_key, stake: authority.stake })
+            })
+            .collect::<Result<_, AuthorityIndex>>()?;
+        Ok(VerifierCommittee::new(self.epoch, authorities))
+    }
+}
+
+/// The commit of the DAG a block was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockAttribution {
+    /// The number of the block.
+    pub number: BlockNumber,
+    /// The hash of the block.
+    pub hash: B256,
+    /// The epoch of the committee that committed the block.
+    pub epoch: u64,
+    /// The index of the leader authority in the committee of the epoch, which produced the block.
+    pub leader: u64,
+    /// The lowest round of the certificates of the commit.
+    pub first_round: u64,
+    /// The round of the leader.
+    pub last_round: u64,
+    /// The number of certificates of the commit.
+    pub certificates: u64,
+}
+
+/// A range of blocks whose [`CheckpointRangeProof`] was signed by a quorum of the committee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertifiedCheckpoint {
+    /// The epoch of the committee that signed the range.
+    pub epoch: Epoch,
+    /// The number of the first block of the range.
+    pub first_block: BlockNumber,
+    /// The number of the last block of the range.
+    pub last_block: BlockNumber,
+    /// The root of the tree over the hashes of the blocks of the range.
+    pub blocks_root: B256,
+    /// The state root of the last block of the range.
+    pub state_root: B256,
+    /// The ABI encoded proof, `abi.encode(proof)`, as the bridge contracts decode it.
+    pub proof: Bytes,
+}
+
+impl CertifiedCheckpoint {
+    /// Verifies that the proof was signed by a quorum of the committee on the network of the
+    /// domain, and returns the signed range.
+    ///
+    /// The fields of the checkpoint are only a summary of the proof, a proof of another range is
+    /// rejected as malformed.
+    pub fn verify<S>(
+        &self,
+        committee: &VerifierCommittee<S::PublicKey>,
+        domain: &SigningDomain,
+    ) -> Result<CheckpointRange, VerificationError>
+    where
+        S: SignatureScheme,
+        for<'a> S::Signature: TryFrom<&'a [u8]>,
+    {
+        let range = verify_checkpoint_range_proof::<S>(committee, domain, &self.proof)?;
+        let summary = (range.epoch, range.firstBlock, range.lastBlock, range.blocksRoot);
+        if summary != (self.epoch, self.first_block, self.last_block, self.blocks_root) ||
+            range.stateRoot != self.state_root
+        {
+            return Err(VerificationError::MalformedProof)
+        }
+        Ok(range)
+    }
+}
+
+impl From<&CheckpointRangeProof> for CertifiedCheckpoint {
+    fn from(proof: &CheckpointRangeProof) -> Self {
+        Self {
+            epoch: proof.range.epoch,
+            first_block: proof.range.firstBlock,
+            last_block: proof.range.lastBlock,
+            blocks_root: proof.range.blocksRoot,
+            state_root: proof.range.stateRoot,
+            proof: proof.encode().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_requests() {
+        assert_eq!(
+            send_raw_transaction(1, &[0x02, 0xf8]).to_json(),
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_sendRawTransaction","params":["0x02f8"]}"#
+        );
+        assert_eq!(
+            block_attribution(2, 255).to_json(),
+            r#"{"jsonrpc":"2.0","id":2,"method":"narwhal_getBlockAttribution","params":["0xff"]}"#
+        );
+    }
+
+    #[test]
+    fn decode_responses() {
+        let pending: JsonRpcResponse =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"result":null}"#).unwrap();
+        assert_eq!(pending.into_result::<Option<TransactionReceipt>>(), Ok(None));
+
+        let receipt: JsonRpcResponse = serde_json::from_value(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": {
+                "transactionHash": B256::with_last_byte(1),
+                "blockHash": B256::with_last_byte(2),
+                "blockNumber": "0x10",
+                "status": "0x1",
+                "logs": [],
+            },
+        }))
+        .unwrap();
+        let receipt = receipt.into_result::<Option<TransactionReceipt>>().unwrap().unwrap();
+        assert_eq!(receipt.block_number, U64::from(16));
+        assert!(receipt.succeeded());
+
+        let failed: JsonRpcResponse = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32601,"message":"Method not found"}}"#,
+        )
+        .unwrap();
+        assert!(
+            matches!(failed.into_result::<u64>(), Err(RpcError::Rpc(err)) if err.code == -32601)
+        );
+    }
+
+    #[test]
+    fn decode_committee() {
+        let committee: CommitteeInfo = serde_json::from_value(json!({
+            "epoch": 3,
+            "authorities": [
+                { "publicKey": "0x01", "stake": 1, "primaryAddress": "127.0.0.1:30400" },
+                { "publicKey": "0x0203", "stake": 2, "primaryAddress": "127.0.0.1:30410" },
+            ],
+        }))
+        .unwrap();
+        let verifier = committee.verifier_committee::<Vec<u8>>().unwrap();
+        assert_eq!(verifier.epoch(), 3);
+        assert_eq!(verifier.authority(1).unwrap().public_key, [2, 3]);
+        assert_eq!(committee.verifier_committee::<[u8; 1]>().unwrap_err(), 1);
+    }
+
+    /// A scheme whose "signature" is the concatenation of the signers' keys and the message.
+    struct ConcatScheme;
+
+    impl SignatureScheme for ConcatScheme {
+        type PublicKey = u8;
+        type Signature = Vec<u8>;
+
+        fn verify_aggregate(signers: &[&u8], message: &[u8], signature: &Vec<u8>) -> bool {
+            signers.iter().map(|key| **key).chain(message.iter().copied()).eq(signature.clone())
+        }
+    }
+
+    #[test]
+    fn verify_checkpoint() {
+        let committee = VerifierCommittee::new(
+            1,
+            (0..4).map(|key| VerifierAuthority { public_key: key, stake: 1 }).collect(),
+        );
+        let domain = SigningDomain::new(1337, B256::ZERO);
+        let range = CheckpointRange {
+            epoch: 1,
+            firstBlock: 1,
+            lastBlock: 8,
+            blocksRoot: B256::with_last_byte(1),
+            stateRoot: B256::with_last_byte(2),
+        };
+        let signature = [&[0, 1, 2][..], range.signing_message(&domain).as_slice()].concat();
+        let proof = CheckpointRangeProof::new(range.clone(), [0, 1, 2], signature.into()).unwrap();
+        let checkpoint = CertifiedCheckpoint::from(&proof);
+        assert_eq!(checkpoint.verify::<ConcatScheme>(&committee, &domain), Ok(range));
+
+        // the summary must match the signed range
+        let forged = CertifiedCheckpoint { last_block: 9, ..checkpoint };
+        assert_eq!(
+            forged.verify::<ConcatScheme>(&committee, &domain),
+            Err(VerificationError::MalformedProof)
+        );
+    }
+}
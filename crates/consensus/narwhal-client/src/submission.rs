@@ -0,0 +1,176 @@
+//! Messages of the gRPC `Transactions` service of the workers.
+//!
+//! A worker serves the service over HTTP/2 if its submission address is configured. A client posts
+//! the body of [`encode_transaction`] to [`SUBMIT_TRANSACTION_PATH`], or the body of
+//! [`encode_transactions`] to [`SUBMIT_TRANSACTION_STREAM_PATH`], with the [`CONTENT_TYPE`] and a
+//! `te: trailers` header, and reads the outcome from the `grpc-status` trailer, or header if the
+//! response has no body, with [`SubmissionStatus::parse`]. A transaction that must not be executed
+//! after a consensus round or a block timestamp is encoded with [`encode_expiring_transaction`].
+//! A client that wants to correlate its request logs with the inclusion of the transactions sets
+//! the [`TRACE_ID_HEADER`], which the node logs with every status change of the transactions of the
+//! request.
+//!
+//! Browsers can't read trailers, so browser clients submit with `eth_sendRawTransaction` instead,
+//! see [`rpc::send_raw_transaction`](crate::rpc::send_raw_transaction).
+
+/// The path of the method that submits a single transaction.
+pub const SUBMIT_TRANSACTION_PATH: &str = "/narwhal.Transactions/SubmitTransaction";
+
+/// The path of the method that submits a stream of transactions.
+pub const SUBMIT_TRANSACTION_STREAM_PATH: &str = "/narwhal.Transactions/SubmitTransactionStream";
+
+/// The content type of the requests.
+pub const CONTENT_TYPE: &str = "application/grpc";
+
+/// The request header of the optional trace id of the submitted transactions, 1 to 64 printable
+/// ASCII characters.
+pub const TRACE_ID_HEADER: &str = "x-narwhal-trace-id";
+
+/// The length of the prefix of a gRPC message, a compression flag and the length.
+pub const MESSAGE_PREFIX_LEN: usize = 5;
+
+/// The protobuf key of the `transaction` field, field 1 with the length delimited wire type.
+const TRANSACTION_KEY: u8 = (1 << 3) | 2;
+
+/// The protobuf key of the `expiry_round` field, field 2 with the varint wire type.
+pub const EXPIRY_ROUND_KEY: u8 = 2 << 3;
+
+/// The protobuf key of the `expiry_timestamp` field, field 3 with the varint wire type.
+pub const EXPIRY_TIMESTAMP_KEY: u8 = 3 << 3;
+
+/// The point after which a submitted transaction must no longer be executed, the `expiry` of the
+/// `Transaction` message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionExpiry {
+    /// The last consensus round whose commit may include the transaction.
+    Round(u64),
+    /// The last block timestamp that may include the transaction.
+    Timestamp(u64),
+}
+
+/// Returns the gRPC message of a `Transaction` with the EIP-2718 encoding of a signed
+/// transaction, with its length prefix.
+pub fn encode_transaction(transaction: &[u8]) -> Vec<u8> {
+    encode_message(transaction, None)
+}
+
+/// Returns the gRPC message of a `Transaction` that is dropped instead of executed once it
+/// expired, with its length prefix.
+pub fn encode_expiring_transaction(transaction: &[u8], expiry: SubmissionExpiry) -> Vec<u8> {
+    encode_message(transaction, Some(expiry))
+}
+
+/// Returns the gRPC message of a `Transaction`, with its length prefix.
+fn encode_message(transaction: &[u8], expiry: Option<SubmissionExpiry>) -> Vec<u8> {
+    let mut message = vec![TRANSACTION_KEY];
+    push_varint(&mut message, transaction.len() as u64);
+    message.extend_from_slice(transaction);
+    if let Some(expiry) = expiry {
+        let (key, value) = match expiry {
+            SubmissionExpiry::Round(round) => (EXPIRY_ROUND_KEY, round),
+            SubmissionExpiry::Timestamp(timestamp) => (EXPIRY_TIMESTAMP_KEY, timestamp),
+        };
+        message.push(key);
+        push_varint(&mut message, value);
+    }
+
+    let mut encoded = Vec::with_capacity(MESSAGE_PREFIX_LEN + message.len());
+    // uncompressed
+    encoded.push(0);
+    encoded.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    encoded.extend_from_slice(&message);
+    encoded
+}
+
+/// Appends a protobuf varint.
+fn push_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Returns the body of a request to the streaming method with the given transactions.
+pub fn encode_transactions<'a>(transactions: impl IntoIterator<Item = &'a [u8]>) -> Vec<u8> {
+    transactions.into_iter().flat_map(encode_transaction).collect()
+}
+
+/// The outcome of a submission, by its gRPC status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionStatus {
+    /// The worker queued the transactions for its next batch.
+    Ok,
+    /// A message is not a valid `Transaction` message.
+    InvalidArgument,
+    /// A message exceeds the size limit of the worker.
+    ResourceExhausted,
+    /// The method doesn't exist, or the messages are compressed.
+    Unimplemented,
+    /// The worker is shutting down or failed to read the request, the submission can be retried.
+    Unavailable,
+    /// Any other status code.
+    Other(u16),
+}
+
+impl SubmissionStatus {
+    /// Returns the status of a gRPC status code.
+    pub const fn from_code(code: u16) -> Self {
+        match code {
+            0 => Self::Ok,
+            3 => Self::InvalidArgument,
+            8 => Self::ResourceExhausted,
+            12 => Self::Unimplemented,
+            14 => Self::Unavailable,
+            code => Self::Other(code),
+        }
+    }
+
+    /// Parses the value of a `grpc-status` trailer.
+    pub fn parse(grpc_status: &str) -> Option<Self> {
+        grpc_status.trim().parse().ok().map(Self::from_code)
+    }
+
+    /// Returns `true` if the worker queued the transactions.
+    pub const fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok)
+    }
+
+    /// Returns `true` if the same submission can succeed later.
+    pub const fn is_retryable(&self) -> bool {
+        matches!(self, Self::Unavailable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_messages() {
+        assert_eq!(encode_transaction(&[0xaa, 0xbb]), [0, 0, 0, 0, 4, 0x0a, 2, 0xaa, 0xbb]);
+
+        // lengths of 128 bytes and more take several bytes of varint
+        let transaction = [7; 300];
+        let encoded = encode_transaction(&transaction);
+        assert_eq!(encoded[..MESSAGE_PREFIX_LEN + 3], [0, 0, 0, 1, 47, 0x0a, 0xac, 0x02]);
+        assert_eq!(encoded.len(), MESSAGE_PREFIX_LEN + 303);
+
+        let stream = encode_transactions([&[1u8][..], &[2, 3][..]]);
+        assert_eq!(stream, [encode_transaction(&[1]), encode_transaction(&[2, 3])].concat());
+
+        assert_eq!(
+            encode_expiring_transaction(&[0xaa], SubmissionExpiry::Timestamp(300)),
+            [0, 0, 0, 0, 6, 0x0a, 1, 0xaa, 0x18, 0xac, 0x02]
+        );
+    }
+
+    #[test]
+    fn parse_status() {
+        assert_eq!(SubmissionStatus::parse("0"), Some(SubmissionStatus::Ok));
+        assert_eq!(SubmissionStatus::parse("14"), Some(SubmissionStatus::Unavailable));
+        assert!(SubmissionStatus::parse("14").unwrap().is_retryable());
+        assert_eq!(SubmissionStatus::parse("2"), Some(SubmissionStatus::Other(2)));
+        assert_eq!(SubmissionStatus::parse("ok"), None);
+    }
+}
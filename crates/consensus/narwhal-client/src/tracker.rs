@@ -0,0 +1,165 @@
+//! Tracking of a submitted transaction until its block is certified.
+//!
+//! A narwhal chain has no forks, a block is final once it's executed. A client that doesn't trust
+//! the node it talks to waits for a [`CertifiedCheckpoint`](crate::rpc::CertifiedCheckpoint) of the
+//! block instead: the signatures of a quorum of the committee over a range of blocks, which the
+//! client verifies itself, and the hashes of the blocks of the range, which the client checks
+//! against the signed root.
+
+use crate::rpc::TransactionReceipt;
+use alloy_primitives::{BlockNumber, B256};
+use reth_narwhal_verifier::{abi::CheckpointRange, blocks::blocks_root, Epoch};
+
+/// The progress of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// The transaction has no receipt yet.
+    Pending,
+    /// The node executed the transaction in a block.
+    Included {
+        /// The number of the block.
+        block_number: BlockNumber,
+        /// The hash of the block.
+        block_hash: B256,
+        /// Whether the transaction succeeded or reverted.
+        succeeded: bool,
+    },
+    /// A quorum of the committee certified the block of the transaction.
+    Certified {
+        /// The number of the block.
+        block_number: BlockNumber,
+        /// The hash of the block.
+        block_hash: B256,
+        /// Whether the transaction succeeded or reverted.
+        succeeded: bool,
+        /// The epoch of the committee that certified the block.
+        epoch: Epoch,
+    },
+}
+
+impl TransactionStatus {
+    /// Returns `true` if the status can't change anymore.
+    pub const fn is_final(&self) -> bool {
+        matches!(self, Self::Certified { .. })
+    }
+}
+
+/// Follows a transaction through its receipt and the certified checkpoint of its block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionTracker {
+    hash: B256,
+    status: TransactionStatus,
+}
+
+impl TransactionTracker {
+    /// Creates a tracker of the pending transaction with the given hash.
+    pub const fn new(hash: B256) -> Self {
+        Self { hash, status: TransactionStatus::Pending }
+    }
+
+    /// Returns the hash of the transaction.
+    pub const fn hash(&self) -> B256 {
+        self.hash
+    }
+
+    /// Returns the status of the transaction.
+    pub const fn status(&self) -> TransactionStatus {
+        self.status
+    }
+
+    /// Returns the number of the block of the transaction, once it's included.
+    pub const fn block_number(&self) -> Option<BlockNumber> {
+        match self.status {
+            TransactionStatus::Pending => None,
+            TransactionStatus::Included { block_number, .. } |
+            TransactionStatus::Certified { block_number, .. } => Some(block_number),
+        }
+    }
+
+    /// Records the receipt of the transaction, receipts of other transactions are ignored.
+    ///
+    /// Returns the new status.
+    pub fn on_receipt(&mut self, receipt: &TransactionReceipt) -> TransactionStatus {
+        if receipt.transaction_hash == self.hash && !self.status.is_final() {
+            self.status = TransactionStatus::Included {
+                block_number: receipt.block_number.to(),
+                block_hash: receipt.block_hash,
+                succeeded: receipt.succeeded(),
+            };
+        }
+        self.status
+    }
+
+    /// Records a verified checkpoint range, with the hashes of all blocks of the range in order.
+    ///
+    /// The transaction is certified if its block is part of the range and the block hashes match
+    /// the signed root. Returns `true` if the transaction is certified.
+    pub fn on_checkpoint(&mut self, range: &CheckpointRange, block_hashes: &[B256]) -> bool {
+        let TransactionStatus::Included { block_number, block_hash, succeeded } = self.status
+        else {
+            return self.status.is_final()
+        };
+        if !range.contains(block_number) ||
+            block_hashes.len() as u64 != range.num_blocks() ||
+            block_hashes[(block_number - range.firstBlock) as usize] != block_hash ||
+            blocks_root(block_hashes) != range.blocksRoot
+        {
+            return false
+        }
+        self.status = TransactionStatus::Certified {
+            block_number,
+            block_hash,
+            succeeded,
+            epoch: range.epoch,
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U64;
+
+    #[test]
+    fn certify_included_transaction() {
+        let hash = B256::with_last_byte(1);
+        let block_hashes = (10..14).map(B256::with_last_byte).collect::<Vec<_>>();
+        let range = CheckpointRange {
+            epoch: 2,
+            firstBlock: 10,
+            lastBlock: 13,
+            blocksRoot: blocks_root(&block_hashes),
+            stateRoot: B256::ZERO,
+        };
+        let mut tracker = TransactionTracker::new(hash);
+
+        // a pending transaction isn't certified
+        assert!(!tracker.on_checkpoint(&range, &block_hashes));
+
+        let receipt = TransactionReceipt {
+            transaction_hash: hash,
+            block_hash: block_hashes[2],
+            block_number: U64::from(12),
+            status: Some(U64::from(1)),
+        };
+        assert_eq!(
+            tracker.on_receipt(&receipt),
+            TransactionStatus::Included {
+                block_number: 12,
+                block_hash: block_hashes[2],
+                succeeded: true
+            }
+        );
+
+        // the block hashes must match the signed root and the receipt
+        let mut forged = block_hashes.clone();
+        forged[2] = B256::ZERO;
+        assert!(!tracker.on_checkpoint(&range, &forged));
+        assert!(!tracker.on_checkpoint(&range, &block_hashes[..3]));
+
+        assert!(tracker.on_checkpoint(&range, &block_hashes));
+        assert!(tracker.status().is_final());
+        assert_eq!(tracker.block_number(), Some(12));
+    }
+}
@@ -0,0 +1,125 @@
+//! Narwhal settings of the node's command line.
+
+use eyre::WrapErr;
+use reth_narwhal_consensus::{
+    chaos::ChaosConfig,
+    committee::{Committee, CommitteeError},
+    keys::{AuthorityKeys, Keystore},
+    self_check::SelfCheckTargets,
+    NarwhalConfig,
+};
+use reth_node_core::args::NarwhalArgs;
+use std::path::Path;
+
+/// A trait that turns the [`NarwhalArgs`] into the narwhal types.
+pub trait RethNarwhalConfig {
+    /// Loads the committee from the committee file, `None` if no file is configured.
+    fn committee(&self) -> Result<Option<Committee>, CommitteeError>;
+
+    /// Loads the keys of the validator from the keystore, `None` if no keystore password is
+    /// configured.
+    ///
+    /// The keystore defaults to `narwhal/keystore.json` in the datadir, and is created with keys
+    /// for the configured number of workers if it doesn't exist.
+    fn keys(&self, data_dir: &Path) -> eyre::Result<Option<AuthorityKeys>>;
+
+    /// Applies the batch and chaos settings of the command line to the node's configuration.
+    fn narwhal_config(&self, config: NarwhalConfig) -> NarwhalConfig;
+
+    /// Loads the node's configuration from the configuration file, or the default configuration
+    /// if no file is configured, and applies the settings of the command line to it.
+    fn load_narwhal_config(&self) -> eyre::Result<NarwhalConfig>;
+
+    /// Returns the targets of the hardware self-check for the committee, `None` if the self-check
+    /// is disabled.
+    fn self_check_targets(
+        &self,
+        committee: &Committee,
+        config: &NarwhalConfig,
+    ) -> Option<SelfCheckTargets>;
+}
+
+impl RethNarwhalConfig for NarwhalArgs {
+    fn committee(&self) -> Result<Option<Committee>, CommitteeError> {
+        self.committee_file.as_deref().map(Committee::load).transpose()
+    }
+
+    fn keys(&self, data_dir: &Path) -> eyre::Result<Option<AuthorityKeys>> {
+        let Some(password_file) = &self.keystore_password_file else { return Ok(None) };
+        let password = std::fs::read_to_string(password_file).wrap_err_with(|| {
+            format!("failed to read narwhal keystore password file {}", password_file.display())
+        })?;
+        let path =
+            self.keystore.clone().unwrap_or_else(|| data_dir.join("narwhal").join("keystore.json"));
+        let keys = Keystore::new(path)
+            .load_or_create(password.trim_end().as_bytes(), self.worker_count)?;
+        Ok(Some(keys))
+    }
+
+    fn narwhal_config(&self, mut config: NarwhalConfig) -> NarwhalConfig {
+        config.batch.max_batch_bytes = self.batch_size;
+        config.batch.max_batch_delay = self.max_batch_delay;
+        if self.chaos {
+            config.chaos = ChaosConfig::staging();
+        }
+        config
+    }
+
+    fn load_narwhal_config(&self) -> eyre::Result<NarwhalConfig> {
+        let config = match &self.config {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path).wrap_err_with(|| {
+                    format!("failed to read narwhal config {}", path.display())
+                })?;
+                toml::from_str(&contents)
+                    .wrap_err_with(|| format!("invalid narwhal config {}", path.display()))?
+            }
+            None => NarwhalConfig::default(),
+        };
+        Ok(self.narwhal_config(config))
+    }
+
+    fn self_check_targets(
+        &self,
+        committee: &Committee,
+        config: &NarwhalConfig,
+    ) -> Option<SelfCheckTargets> {
+        self.self_check.then(|| {
+            SelfCheckTargets::new(committee.authorities.len(), &config.primary, self.target_tps)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn apply_batch_args() {
+        let args = NarwhalArgs {
+            batch_size: 1_000,
+            max_batch_delay: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let config = args.narwhal_config(NarwhalConfig::default());
+        assert_eq!(config.batch.max_batch_bytes, 1_000);
+        assert_eq!(config.batch.max_batch_delay, Duration::from_secs(1));
+        assert!(!config.chaos.is_enabled());
+        let chaos = NarwhalArgs { chaos: true, ..Default::default() };
+        assert_eq!(chaos.narwhal_config(NarwhalConfig::default()).chaos, ChaosConfig::staging());
+        assert!(args.committee().unwrap().is_none());
+        assert!(args.keys(Path::new("datadir")).unwrap().is_none());
+    }
+
+    #[test]
+    fn load_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("narwhal.toml");
+        std::fs::write(&path, "[submission]\naddr = \"127.0.0.1:30402\"\n").unwrap();
+        let args = NarwhalArgs { config: Some(path), batch_size: 1_000, ..Default::default() };
+        let config = args.load_narwhal_config().unwrap();
+        assert_eq!(config.submission.addr, Some("127.0.0.1:30402".parse().unwrap()));
+        assert_eq!(config.batch.max_batch_bytes, 1_000);
+    }
+}
@@ -0,0 +1,66 @@
+//! The deposits of the L1 bridge, read from an L1 node over JSON-RPC.
+
+use alloy_sol_types::SolEvent;
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::{HttpClient, HttpClientBuilder},
+    rpc_params,
+};
+use reth_narwhal_consensus::deposits::{Deposit, DepositInitiated, DepositSource};
+use reth_primitives::{Address, BlockNumber};
+use reth_rpc_types::{Block, BlockNumberOrTag, Filter, Log};
+use std::{future::Future, io, ops::RangeInclusive, pin::Pin};
+
+/// A [`DepositSource`] that reads the `DepositInitiated` events of the bridge from an L1 node.
+#[derive(Debug, Clone)]
+pub struct RpcDepositSource {
+    client: HttpClient,
+    bridge: Address,
+}
+
+impl RpcDepositSource {
+    /// Creates a source that reads the deposits of the bridge from the L1 node at the given HTTP
+    /// endpoint.
+    pub fn new(url: &str, bridge: Address) -> eyre::Result<Self> {
+        Ok(Self { client: HttpClientBuilder::default().build(url)?, bridge })
+    }
+}
+
+impl DepositSource for RpcDepositSource {
+    fn finalized_block(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = io::Result<BlockNumber>> + Send + '_>> {
+        Box::pin(async move {
+            let block: Option<Block> = self
+                .client
+                .request("eth_getBlockByNumber", rpc_params![BlockNumberOrTag::Finalized, false])
+                .await
+                .map_err(io::Error::other)?;
+            let block = block.ok_or_else(|| io::Error::other("L1 node has no finalized block"))?;
+            Ok(block.header.number)
+        })
+    }
+
+    fn deposits(
+        &self,
+        blocks: RangeInclusive<BlockNumber>,
+    ) -> Pin<Box<dyn Future<Output = io::Result<Vec<Deposit>>> + Send + '_>> {
+        Box::pin(async move {
+            let filter = Filter::new()
+                .address(self.bridge)
+                .event_signature(DepositInitiated::SIGNATURE_HASH)
+                .from_block(*blocks.start())
+                .to_block(*blocks.end());
+            let logs: Vec<Log> = self
+                .client
+                .request("eth_getLogs", rpc_params![filter])
+                .await
+                .map_err(io::Error::other)?;
+            Ok(logs
+                .iter()
+                .filter(|log| !log.removed)
+                .filter_map(|log| Deposit::from_log(&log.inner, log.block_number?))
+                .collect())
+        })
+    }
+}
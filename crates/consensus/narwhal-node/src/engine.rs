@@ -0,0 +1,43 @@
+//! Engine types of a narwhal node.
+
+use reth_chainspec::ChainSpec;
+use reth_engine_primitives::EngineTypes;
+use reth_ethereum_engine_primitives::{
+    ExecutionPayloadEnvelopeV2, ExecutionPayloadEnvelopeV3, ExecutionPayloadEnvelopeV4,
+    ExecutionPayloadV1,
+};
+use reth_narwhal_consensus::payload::{NarwhalPayloadAttributes, NarwhalPayloadBuilderAttributes};
+use reth_payload_builder::EthBuiltPayload;
+use reth_payload_primitives::{
+    validate_version_specific_fields, EngineApiMessageVersion, EngineObjectValidationError,
+    PayloadOrAttributes, PayloadTypes,
+};
+
+/// The engine types of a narwhal node.
+///
+/// Payloads are Ethereum payloads, but their attributes carry the sequenced transactions of a
+/// sub-dag, see [`NarwhalPayloadAttributes`].
+#[derive(Debug, Default, Clone, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub struct NarwhalEngineTypes;
+
+impl PayloadTypes for NarwhalEngineTypes {
+    type BuiltPayload = EthBuiltPayload;
+    type PayloadAttributes = NarwhalPayloadAttributes;
+    type PayloadBuilderAttributes = NarwhalPayloadBuilderAttributes;
+}
+
+impl EngineTypes for NarwhalEngineTypes {
+    type ExecutionPayloadV1 = ExecutionPayloadV1;
+    type ExecutionPayloadV2 = ExecutionPayloadEnvelopeV2;
+    type ExecutionPayloadV3 = ExecutionPayloadEnvelopeV3;
+    type ExecutionPayloadV4 = ExecutionPayloadEnvelopeV4;
+
+    fn validate_version_specific_fields(
+        chain_spec: &ChainSpec,
+        version: EngineApiMessageVersion,
+        payload_or_attrs: PayloadOrAttributes<'_, NarwhalPayloadAttributes>,
+    ) -> Result<(), EngineObjectValidationError> {
+        validate_version_specific_fields(chain_spec, version, payload_or_attrs)
+    }
+}
@@ -0,0 +1,68 @@
+//! Consensus events for execution extensions.
+//!
+//! An ExEx of a narwhal node receives the executed blocks through its [`ExExContext`] like on any
+//! other node. [`narwhal_exex`] additionally hands it the [`NarwhalEvent`]s of the consensus
+//! service, so that it can index batches, certificates and commits alongside the chain:
+//!
+//! ```ignore
+//! let events = NarwhalEvents::new();
+//! let handle = NodeBuilder::new(config)
+//!     .with_database(db)
+//!     .node(NarwhalNode::default())
+//!     .install_exex("consensus-indexer", narwhal_exex(events.clone(), |ctx| async move {
+//!         Ok(index_consensus(ctx))
+//!     }))
+//!     .launch()
+//!     .await?;
+//! // the consensus tasks publish to the same channel, e.g. `Primary::with_events(events)`
+//! ```
+
+use reth_exex::ExExContext;
+use reth_narwhal_consensus::{NarwhalEvent, NarwhalEvents};
+use reth_node_builder::FullNodeComponents;
+use std::ops::{Deref, DerefMut};
+use tokio::sync::broadcast;
+
+/// The [`ExExContext`] of an ExEx installed with [`narwhal_exex`], with a subscription to the
+/// consensus events.
+///
+/// Dereferences to the [`ExExContext`].
+#[derive(Debug)]
+pub struct NarwhalExExContext<Node: FullNodeComponents> {
+    /// The context of the ExEx.
+    pub ctx: ExExContext<Node>,
+    /// The consensus events published since the ExEx was launched.
+    ///
+    /// An ExEx that falls behind misses the oldest events, see [`NarwhalEvents`]. The blocks of
+    /// the chain are still delivered through the notifications of the [`ExExContext`].
+    pub consensus_events: broadcast::Receiver<NarwhalEvent>,
+}
+
+impl<Node: FullNodeComponents> Deref for NarwhalExExContext<Node> {
+    type Target = ExExContext<Node>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.ctx
+    }
+}
+
+impl<Node: FullNodeComponents> DerefMut for NarwhalExExContext<Node> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.ctx
+    }
+}
+
+/// Wraps an ExEx that takes a [`NarwhalExExContext`], so that it can be installed with
+/// [`install_exex`](reth_node_builder::NodeBuilderWithComponents::install_exex).
+///
+/// The ExEx subscribes to the events when the node launches it.
+pub fn narwhal_exex<Node, F, R>(
+    events: NarwhalEvents,
+    exex: F,
+) -> impl FnOnce(ExExContext<Node>) -> R + Send + 'static
+where
+    Node: FullNodeComponents,
+    F: FnOnce(NarwhalExExContext<Node>) -> R + Send + 'static,
+{
+    move |ctx| exex(NarwhalExExContext { ctx, consensus_events: events.subscribe() })
+}
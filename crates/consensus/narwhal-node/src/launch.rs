@@ -0,0 +1,1100 @@
+//! Launch of the consensus tasks of a narwhal node.
+//!
+//! The [`NarwhalLaunchHook`] hooks into the
+//! [`DefaultNodeLauncher`](reth_node_builder::DefaultNodeLauncher). Once the engine is spawned it
+//! starts the [`ConsensusOutputExecutor`], which replays the sub-dags that were committed but not
+//! executed before a restart, and then the [`EpochManager`], whose [`LocalEpochTasks`] run the
+//! primaries, the workers, the batch maker and the [`Committer`] of every epoch, and the
+//! [`StallDetector`], which logs when consensus stops making progress. A node that gossips its
+//! block hashes with [`NarwhalLaunchHook::with_block_hash_gossip`] runs the
+//! [`BlockHashCrossCheck`], which halts the executor on a mismatch if configured to. The executor
+//! reads the [`HotStateSummary`] the node saved at its last shutdown before it executes the first
+//! block. The [`NarwhalNodeLauncher`] installs the hook with the narwhal settings of the node's
+//! command line, together with the narwhal RPC namespace. If the [submission
+//! address](SubmissionConfig::addr) is configured, every worker of the node serves the gRPC
+//! [`TransactionSubmissionServer`]. With a [lease
+//! file](reth_narwhal_consensus::failover::FailoverConfig::lease_file), the primary of the node
+//! only proposes the headers the [`SigningLease`] authorizes, so that a standby node of the
+//! validator can take over without proposing a round twice.
+//!
+//! The primaries of different processes don't exchange headers yet, so the node runs every
+//! authority of the committee itself, with the keys it's launched with, see
+//! [`DevCommittee::with_keys`]. The workers of the authorities connect over [`LocalTransport`]s,
+//! and the connections of the local workers use the highest wire version both sides offer, up to
+//! the [`WireConfig::max_version`](reth_narwhal_consensus::wire::WireConfig::max_version) of the
+//! node. A node whose committee has an authority whose key the node doesn't have fails to launch,
+//! and so does a node with the committee file of more than one validator.
+
+use crate::{
+    args::RethNarwhalConfig, install_narwhal_rpc, install_recent_receipts,
+    install_transaction_status, RpcDepositSource,
+};
+use eyre::WrapErr;
+use reth_beacon_consensus::BeaconConsensusEngineHandle;
+use reth_chainspec::ChainSpecProvider;
+use reth_db::Database;
+use reth_narwhal_consensus::{
+    backlog::ExecutionBacklog,
+    backpressure::ExecutionLag,
+    chaos::{Chaos, ChaosTransport},
+    committee::{Committee, CommitteeProvider},
+    committee_history::CommitteeHistory,
+    committer::Committer,
+    crosscheck::{BlockHashCrossCheck, BlockHashGossip},
+    dag_store::{DagStore, DatabaseDagStore},
+    deposits::{DepositIngestion, DepositSigner},
+    dev::{DevCommittee, DevPrimaries, LocalTransport, DEFAULT_DEV_COMMITTEE_SIZE},
+    epoch::{
+        CommitteeSource, EpochCommitteeProvider, EpochManager, EpochStart, EpochTasks,
+        FileCommitteeSource, UncommittedBatches,
+    },
+    executor::ConsensusOutputExecutor,
+    failover::{FileLeaseStore, SigningLease},
+    fast_path::FastPath,
+    gc::DagPruner,
+    keys::KeyProvider,
+    memory::{MemoryBudget, MemoryComponent},
+    record::MessageRecorder,
+    recovery::CommittedSubDags,
+    rpc::ConsensusState,
+    self_check::run_self_check,
+    sequencing::{ChainSequencingFilter, SenderAllowlist, SequencingFilter},
+    shutdown::{NarwhalShutdown, ShutdownStage},
+    signature::{AggregateScheme, Bls12381, BlsPublicKey, BlsSecretKey},
+    stall::{NodeHealth, StallDetector},
+    trace::TraceIds,
+    types::{OrderedSubDag, Round, WorkerId},
+    verifier::{AuthorityIndex, SigningDomain, VerifierCommittee},
+    warm_start::{HotStateSummary, HotStateTracker},
+    wire::{NarwhalMessage, VersionRange},
+    worker::{
+        BatchDedupConfig, BatchDeduplicator, BatchMaker, BatchQuota, SenderRateLimiter,
+        SequencedTransactions, SubmissionConfig, TransactionSubmissionServer, WorkerHandle,
+        WorkerMessage, WorkerNetwork, WorkerTransport,
+    },
+    DroppedTransactions, NarwhalChainInfo, NarwhalConfig, NarwhalEvents, RecentReceipts,
+};
+use reth_node_builder::{
+    rpc::RpcContext, DefaultNodeLauncher, EngineSpawnContext, FullNodeComponents, FullNodeTypes,
+    LaunchNode, LaunchStageHook, NodeAdapter, NodeAddOns, NodeBuilderWithComponents,
+    NodeComponentsBuilder, NodeConfig,
+};
+use reth_node_core::dirs::{ChainPath, DataDirPath};
+use reth_primitives::{Bytes, SealedHeader, TransactionSignedEcRecovered, B256};
+use reth_provider::{
+    ConsensusMetadataProvider, ConsensusMetadataWriter, HeaderProvider, ProviderError,
+    StateProviderFactory,
+};
+use reth_rpc_eth_api::helpers::{EthTransactions, LoadReceipt};
+use reth_tasks::{shutdown::GracefulShutdown, TaskExecutor};
+use reth_tracing::tracing::{error, info, warn};
+use reth_transaction_pool::{PoolTransaction, TransactionPool};
+use std::{
+    fmt::Debug,
+    future::Future,
+    path::PathBuf,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, oneshot, watch, Mutex},
+};
+
+/// The number of committed sub-dags that can wait for the executor.
+const SUB_DAG_CHANNEL_CAPACITY: usize = 64;
+
+/// The number of committed sub-dags the execution backlog keeps in memory, the rest are spilled to
+/// disk.
+const EXECUTION_BACKLOG_IN_MEMORY: usize = 256;
+
+/// The number of submitted transactions that can wait for the batch maker.
+const SUBMISSION_CHANNEL_CAPACITY: usize = 1_024;
+
+/// The number of submitted transactions whose trace ids the node keeps.
+const TRACE_ID_CAPACITY: usize = 65_536;
+
+/// The number of sealed batches that can wait for the primary.
+const SEALED_BATCH_CHANNEL_CAPACITY: usize = 64;
+
+/// Launches a [`NarwhalNode`](crate::NarwhalNode) with the narwhal settings of its
+/// [`NodeConfig`].
+///
+/// In dev mode the node runs a [`DevCommittee`] of `--dev.narwhal-committee-size` authorities,
+/// which seals the blocks instead of the auto-seal miner. Otherwise it runs the committee of
+/// `--narwhal.committee-file` with the key of its narwhal keystore, which must be the committee of
+/// that single validator until the nodes of a committee exchange headers over the network. A
+/// node without a committee or
+/// without keys doesn't take part in consensus and follows the chain of its peers. The other
+/// `--narwhal.*` flags configure the consensus of the node, see
+/// [`RethNarwhalConfig`](crate::RethNarwhalConfig), and the hardware self-check runs before the
+/// node is launched if it's enabled.
+///
+/// A node that takes part in consensus serves the `narwhal` RPC namespace, and the receipts and
+/// dropped transactions of its executor, see [`install_narwhal_rpc`], [`install_recent_receipts`]
+/// and [`install_transaction_status`]. They are installed
+/// before the [`extend_rpc_modules`](NodeBuilderWithComponents::extend_rpc_modules) hook of the
+/// builder, which may override their methods.
+///
+/// ```ignore
+/// let node = NarwhalNode::default();
+/// let sequenced = node.sequenced_index();
+/// let handle = NodeBuilder::new(config)
+///     .with_database(db)
+///     .with_launch_context(task_executor)
+///     .node(node)
+///     .launch_with_fn(|builder| {
+///         let launcher = NarwhalNodeLauncher::new(
+///             builder.task_executor().clone(),
+///             builder.config().datadir(),
+///         )
+///         .with_sequenced_index(sequenced);
+///         builder.launch_with(launcher)
+///     })
+///     .await?;
+/// ```
+#[derive(Debug)]
+pub struct NarwhalNodeLauncher {
+    task_executor: TaskExecutor,
+    data_dir: ChainPath<DataDirPath>,
+    sequenced: Option<SequencedTransactions>,
+}
+
+impl NarwhalNodeLauncher {
+    /// Creates a launcher of a node with the given data directory.
+    pub const fn new(task_executor: TaskExecutor, data_dir: ChainPath<DataDirPath>) -> Self {
+        Self { task_executor, data_dir, sequenced: None }
+    }
+
+    /// Records the transactions the workers store in the index the pool of the node checks, see
+    /// [`NarwhalNode::sequenced_index`](crate::NarwhalNode::sequenced_index).
+    pub fn with_sequenced_index(mut self, sequenced: SequencedTransactions) -> Self {
+        self.sequenced = Some(sequenced);
+        self
+    }
+
+    /// Creates the consensus of the node, `None` if the node doesn't take part in consensus.
+    ///
+    /// In dev mode, the size of the dev committee is set in the config if it's not configured.
+    fn launch_hook<DB>(
+        &self,
+        config: &mut NodeConfig,
+        db: DB,
+    ) -> eyre::Result<Option<NarwhalLaunchHook>>
+    where
+        DB: Database + Debug + 'static,
+    {
+        let args = &config.narwhal;
+        let (committee, keys) = if config.dev.dev {
+            let size = *config.dev.narwhal_committee_size.get_or_insert(DEFAULT_DEV_COMMITTEE_SIZE);
+            let dev = DevCommittee::new(size);
+            let keys = (0..size as AuthorityIndex)
+                .filter_map(|authority| dev.secret_key(authority).cloned())
+                .collect();
+            (dev.committee().clone(), keys)
+        } else {
+            let Some(committee) = args.committee()? else { return Ok(None) };
+            // the primaries of different nodes don't exchange headers yet
+            eyre::ensure!(
+                committee.authorities.len() == 1,
+                "the narwhal committee has {} validators, but a node can only run the committee \
+                 of a single validator",
+                committee.authorities.len()
+            );
+            let Some(keys) = args.keys(self.data_dir.data_dir())? else {
+                info!(
+                    target: "reth::cli",
+                    "No narwhal keystore password configured, following the chain"
+                );
+                return Ok(None)
+            };
+            (committee, vec![keys.authority_key().clone()])
+        };
+        let narwhal_config = args.load_narwhal_config()?;
+        narwhal_config
+            .routing
+            .validate(args.worker_count)
+            .wrap_err("invalid narwhal transaction routing")?;
+        if let Some(targets) = args.self_check_targets(&committee, &narwhal_config) {
+            run_self_check(self.data_dir.data_dir(), &targets)
+                .wrap_err("failed to run the narwhal self-check")?;
+        }
+        let source = FileCommitteeSource::new(
+            self.data_dir.data_dir().join("narwhal").join("epoch-change.toml"),
+        );
+        let history =
+            CommitteeHistory::open(self.data_dir.data_dir().join("narwhal").join("committees"))
+                .wrap_err("failed to open the narwhal committee history")?;
+        let backlog = ExecutionBacklog::new(
+            self.data_dir.data_dir().join("narwhal"),
+            EXECUTION_BACKLOG_IN_MEMORY,
+        )
+        .wrap_err("failed to open the narwhal execution backlog")?;
+        let hook = NarwhalLaunchHook::new(
+            committee,
+            keys,
+            Arc::new(DatabaseDagStore::new(db)),
+            Arc::new(source),
+            narwhal_config,
+            args.gc_depth,
+        );
+        let mut hook = hook
+            .with_worker_count(args.worker_count)
+            .with_committee_history(Arc::new(RwLock::new(history)))
+            .with_execution_backlog(backlog)
+            .with_warm_start(self.data_dir.data_dir().join("narwhal").join("warm-start.json"));
+        if let Some(sequenced) = self.sequenced.clone() {
+            hook = hook.with_sequenced_index(sequenced);
+        }
+        Ok(Some(hook))
+    }
+}
+
+impl<T, CB, AO> LaunchNode<NodeBuilderWithComponents<T, CB, AO>> for NarwhalNodeLauncher
+where
+    T: FullNodeTypes<DB: Debug, Provider: ConsensusMetadataProvider>,
+    CB: NodeComponentsBuilder<T>,
+    AO: NodeAddOns<NodeAdapter<T, CB::Components>, EthApi: EthTransactions + LoadReceipt>,
+    DefaultNodeLauncher: LaunchNode<NodeBuilderWithComponents<T, CB, AO>>,
+    DefaultNodeLauncher<NarwhalLaunchHook>: LaunchNode<
+        NodeBuilderWithComponents<T, CB, AO>,
+        Node = <DefaultNodeLauncher as LaunchNode<NodeBuilderWithComponents<T, CB, AO>>>::Node,
+    >,
+{
+    type Node = <DefaultNodeLauncher as LaunchNode<NodeBuilderWithComponents<T, CB, AO>>>::Node;
+
+    async fn launch_node(
+        self,
+        mut target: NodeBuilderWithComponents<T, CB, AO>,
+    ) -> eyre::Result<Self::Node> {
+        let hook = self.launch_hook(&mut target.config, target.adapter.database.clone())?;
+        let launcher = DefaultNodeLauncher::new(self.task_executor, self.data_dir);
+        let Some(hook) = hook else { return launcher.launch_node(target).await };
+
+        let (state, receipts, dropped) =
+            (hook.consensus_state(), hook.recent_receipts(), hook.dropped_transactions());
+        let rpc_hooks = &mut target.add_ons.rpc.hooks;
+        let extend_rpc_modules = std::mem::replace(&mut rpc_hooks.extend_rpc_modules, Box::new(()));
+        rpc_hooks.extend_rpc_modules =
+            Box::new(move |mut ctx: RpcContext<'_, NodeAdapter<T, CB::Components>, AO::EthApi>| {
+                install_narwhal_rpc(&mut ctx, state)?;
+                install_recent_receipts(&mut ctx, receipts)?;
+                install_transaction_status(&mut ctx, dropped)?;
+                extend_rpc_modules.extend_rpc_modules(ctx)
+            });
+        launcher.with_stage_hook(hook).launch_node(target).await
+    }
+}
+
+/// The consensus of a narwhal node, which the [`NarwhalLaunchHook`] starts once the engine is
+/// spawned.
+///
+/// The components that outlive the launch are created beforehand, so that they can be handed to
+/// the RPC of the node, see [`install_narwhal_rpc`](crate::install_narwhal_rpc),
+/// [`install_recent_receipts`](crate::install_recent_receipts) and
+/// [`install_transaction_status`](crate::install_transaction_status).
+#[derive(Debug)]
+pub struct NarwhalLaunchHook {
+    store: Arc<dyn DagStore>,
+    committees: EpochCommitteeProvider,
+    source: Arc<dyn CommitteeSource>,
+    keys: Vec<BlsSecretKey>,
+    config: NarwhalConfig,
+    gc_depth: Round,
+    worker_count: usize,
+    state: ConsensusState,
+    /// The memory budget of the committer and of the caches of the executor.
+    memory: MemoryBudget,
+    receipts: RecentReceipts,
+    dropped: DroppedTransactions,
+    events: NarwhalEvents,
+    trace_ids: TraceIds,
+    submissions: Option<mpsc::Receiver<Bytes>>,
+    committee_history: Option<Arc<RwLock<CommitteeHistory<Committee>>>>,
+    backlog: Option<ExecutionBacklog<OrderedSubDag>>,
+    gossip: Option<BlockHashGossip>,
+    warm_start: Option<PathBuf>,
+    sequenced: Option<SequencedTransactions>,
+}
+
+impl NarwhalLaunchHook {
+    /// Creates the consensus of the committee of the first epoch, whose DAG is kept in the store.
+    ///
+    /// The node runs the authorities of all given keys, and the committee of every epoch must
+    /// consist of them, otherwise the launch fails. The first key is the authority of this node,
+    /// whose primary the RPC reports.
+    pub fn new(
+        committee: Committee,
+        keys: Vec<BlsSecretKey>,
+        store: Arc<dyn DagStore>,
+        source: Arc<dyn CommitteeSource>,
+        config: NarwhalConfig,
+        gc_depth: Round,
+    ) -> Self {
+        let committees = EpochCommitteeProvider::new(committee);
+        let state = ConsensusState::new(Arc::new(committees.clone()), Arc::clone(&store));
+        let memory = MemoryBudget::new(config.memory);
+        let results = memory.handle(MemoryComponent::ExecutionResults);
+        Self {
+            store,
+            committees,
+            source,
+            keys,
+            config,
+            gc_depth,
+            worker_count: 1,
+            state,
+            receipts: RecentReceipts::default().with_memory_budget(results.clone()),
+            dropped: DroppedTransactions::default().with_memory_budget(results),
+            memory,
+            events: NarwhalEvents::new(),
+            trace_ids: TraceIds::new(TRACE_ID_CAPACITY),
+            submissions: None,
+            committee_history: None,
+            backlog: None,
+            gossip: None,
+            warm_start: None,
+            sequenced: None,
+        }
+    }
+
+    /// Runs the given number of workers for the authority of this node, which batch the
+    /// transactions the [routing](NarwhalConfig::routing) assigns to them. One by default.
+    ///
+    /// # Panics
+    ///
+    /// If `worker_count` is zero.
+    pub fn with_worker_count(mut self, worker_count: usize) -> Self {
+        assert!(worker_count > 0, "a narwhal node needs at least one worker");
+        self.worker_count = worker_count;
+        self
+    }
+
+    /// Batches the given transactions next to the transactions of the pool, e.g. the transactions
+    /// of the application the node is embedded in.
+    pub fn with_submissions(mut self, submissions: mpsc::Receiver<Bytes>) -> Self {
+        self.submissions = Some(submissions);
+        self
+    }
+
+    /// Records the committee of every epoch in the given history, which the RPC serves.
+    pub fn with_committee_history(
+        mut self,
+        history: Arc<RwLock<CommitteeHistory<Committee>>>,
+    ) -> Self {
+        self.state = self.state.with_committee_history(history.clone());
+        self.committee_history = Some(history);
+        self
+    }
+
+    /// Queues the committed sub-dags in the given backlog while the executor is busy, so that
+    /// consensus doesn't wait for execution.
+    ///
+    /// Without a backlog, consensus waits for the executor once its channel is full.
+    pub fn with_execution_backlog(mut self, backlog: ExecutionBacklog<OrderedSubDag>) -> Self {
+        self.backlog = Some(backlog);
+        self
+    }
+
+    /// Gossips the hashes of the executed blocks with the other authorities over the given
+    /// channels, and cross-checks them as the [crosscheck config](NarwhalConfig::crosscheck)
+    /// says.
+    pub fn with_block_hash_gossip(mut self, gossip: BlockHashGossip) -> Self {
+        self.gossip = Some(gossip);
+        self
+    }
+
+    /// Saves the hottest state of the executed blocks to the given file when the node shuts down,
+    /// and reads it ahead of the first block after the next launch.
+    pub fn with_warm_start(mut self, path: PathBuf) -> Self {
+        self.warm_start = Some(path);
+        self
+    }
+
+    /// Records the transactions of the batches the workers store in the given index, with which
+    /// the pool of the node rejects the transactions peers keep relaying until their batches are
+    /// committed.
+    pub fn with_sequenced_index(mut self, sequenced: SequencedTransactions) -> Self {
+        self.sequenced = Some(sequenced);
+        self
+    }
+
+    /// Returns the state of the consensus, which the RPC serves.
+    pub fn consensus_state(&self) -> ConsensusState {
+        self.state.clone()
+    }
+
+    /// Returns the memory budget of the consensus caches, see [`NarwhalConfig::memory`].
+    pub fn memory_budget(&self) -> MemoryBudget {
+        self.memory.clone()
+    }
+
+    /// Returns the receipts of the blocks the executor executed recently.
+    pub fn recent_receipts(&self) -> RecentReceipts {
+        self.receipts.clone()
+    }
+
+    /// Returns the transactions the executor dropped from the blocks of their commits recently.
+    pub fn dropped_transactions(&self) -> DroppedTransactions {
+        self.dropped.clone()
+    }
+
+    /// Returns the events of the consensus.
+    pub fn events(&self) -> NarwhalEvents {
+        self.events.clone()
+    }
+
+    /// Returns the trace ids of the submitted transactions, which the workers and the executor log
+    /// with the status changes of the transactions.
+    ///
+    /// The gRPC submission service records the trace ids of its requests, the application records
+    /// the ids of the transactions of [`Self::with_submissions`] itself.
+    pub fn trace_ids(&self) -> TraceIds {
+        self.trace_ids.clone()
+    }
+}
+
+impl<Node> LaunchStageHook<Node> for NarwhalLaunchHook
+where
+    Node: FullNodeComponents,
+    Node::Provider: ConsensusMetadataWriter,
+    Node::Pool: 'static,
+    <Node::Pool as TransactionPool>::Transaction:
+        PoolTransaction<Consensus = TransactionSignedEcRecovered>,
+{
+    /// The committee seals the blocks, also in dev mode.
+    fn seals_blocks(&self) -> bool {
+        true
+    }
+
+    fn on_engine_spawned(
+        &mut self,
+        ctx: EngineSpawnContext<'_, Node>,
+        engine_handle: &BeaconConsensusEngineHandle<Node::Engine>,
+    ) -> eyre::Result<()> {
+        let committee = self.committees.current_committee();
+        eyre::ensure!(
+            DevCommittee::with_keys(Committee::clone(&committee), &self.keys).is_some(),
+            "missing keys of the authorities of the narwhal committee of epoch {}",
+            committee.epoch
+        );
+        let node = ctx.node;
+        let chain_spec = node.provider().chain_spec();
+        let chain_info = NarwhalChainInfo::from_genesis(&chain_spec.genesis)?;
+        let parent = node
+            .provider()
+            .sealed_header(ctx.head.number)?
+            .ok_or(ProviderError::HeaderNotFound(ctx.head.number.into()))?;
+        let committed = CommittedSubDags::new(Arc::clone(&self.store));
+        let mut executor = ConsensusOutputExecutor::new(
+            Arc::clone(&chain_spec),
+            node.provider().clone(),
+            node.block_executor().clone(),
+            engine_handle.clone(),
+            parent,
+        )
+        .with_committed_sub_dags(committed.clone())
+        .with_recent_receipts(self.receipts.clone())
+        .with_dropped_transactions(self.dropped.clone())
+        .with_events(self.events.clone())
+        .with_trace_ids(self.trace_ids.clone());
+        if let Some(history) = &self.committee_history {
+            executor = executor
+                .with_committee_history(Arc::clone(history), Arc::new(self.committees.clone()));
+        }
+        let warm_start = match self.warm_start.clone() {
+            Some(path) => {
+                let tracker =
+                    Arc::new(std::sync::Mutex::new(HotStateTracker::new(self.config.warm_start)));
+                executor = executor.with_hot_state_tracker(Arc::clone(&tracker));
+                let summary = HotStateSummary::load(&path).unwrap_or_else(|err| {
+                    // the summary only affects performance
+                    warn!(target: "consensus::narwhal", %err, "Failed to read the warm start");
+                    None
+                });
+                // saved once the node shuts down
+                node.task_executor().spawn_with_graceful_shutdown_signal(|shutdown| async move {
+                    let _guard = shutdown.await;
+                    let summary = tracker.lock().unwrap_or_else(PoisonError::into_inner).summary();
+                    if let Err(err) = summary.save(&path) {
+                        warn!(target: "consensus::narwhal", %err, "Failed to save the warm start");
+                    }
+                });
+                summary
+            }
+            None => None,
+        };
+        // the workers check the transactions against the filter of the block after the head
+        let mut sequencing_filter: Vec<Arc<dyn SequencingFilter>> =
+            vec![Arc::new(ChainSequencingFilter::new(chain_info.sequencing_filters))];
+        if let Some(permissioned) = chain_info.permissioned {
+            // the allowlist is rebuilt from the receipts of the canonical blocks after a restart
+            let allowlist =
+                SenderAllowlist::new(permissioned).with_source(Arc::new(node.provider().clone()));
+            allowlist
+                .sync_to(ctx.head.number)
+                .wrap_err("failed to rebuild the allowlist of senders")?;
+            executor = executor.with_sender_allowlist(allowlist.clone());
+            sequencing_filter.push(Arc::new(allowlist));
+        }
+        let sequencing_filter = Arc::new(sequencing_filter);
+        let head = executor.subscribe_head();
+        let crosscheck = match self.gossip.take() {
+            Some(gossip) => {
+                // the first key is the authority of this node
+                let public_key = Bls12381::public_key(&self.keys[0]).to_bytes();
+                let authority = committee
+                    .authorities
+                    .iter()
+                    .position(|authority| authority.public_key[..] == public_key)
+                    .ok_or_else(|| {
+                        eyre::eyre!("the authority of the node is not in the committee")
+                    })?;
+                let crosscheck = BlockHashCrossCheck::new(self.config.crosscheck.clone());
+                Some((crosscheck, authority as AuthorityIndex, gossip))
+            }
+            None => None,
+        };
+
+        if self.config.chaos.is_enabled() {
+            warn!(
+                target: "consensus::narwhal",
+                chaos = ?self.config.chaos,
+                "Chaos is enabled, this node must not run as a production validator"
+            );
+        }
+        let recorder = MessageRecorder::from_config(&self.config.record)
+            .wrap_err("failed to open the narwhal message recording")?;
+        let signing_lease = match self.config.failover.lease_file.clone() {
+            Some(path) => {
+                let failover = &self.config.failover;
+                eyre::ensure!(!failover.node_id.is_empty(), "the failover needs a node id");
+                let store = Arc::new(FileLeaseStore::new(path));
+                let lease = SigningLease::new(store, &failover.node_id, failover.clone());
+                // renewed for as long as the node runs
+                let (holder, held) = watch::channel(false);
+                let renewal = lease.clone();
+                node.task_executor().spawn(async move {
+                    let _held = held;
+                    renewal.run(holder).await
+                });
+                Some(lease)
+            }
+            None => None,
+        };
+        // the transactions of the application and the deposits are batched by the first worker
+        let submissions = self
+            .submissions
+            .take()
+            .into_iter()
+            .chain(self.spawn_deposit_ingestion(node)?)
+            .map(|submissions| (0, submissions))
+            .chain(self.spawn_submission_servers(node)?)
+            .map(|(worker, submissions)| (worker, Arc::new(Mutex::new(submissions))))
+            .collect();
+
+        let (committed_round, committed_round_rx) = watch::channel(0);
+        let (pruner, _) =
+            DagPruner::new(Arc::clone(&self.store), self.gc_depth, committed_round_rx);
+        let uncommitted =
+            UncommittedBatches::new(self.gc_depth).with_store(Arc::clone(&self.store));
+        let mut tasks = LocalEpochTasks {
+            executor: node.task_executor().clone(),
+            pool: node.pool().clone(),
+            store: Arc::clone(&self.store),
+            keys: self.keys.clone(),
+            domain: SigningDomain::new(chain_spec.chain.id(), chain_spec.genesis_hash()),
+            config: self.config.clone(),
+            gc_depth: self.gc_depth,
+            worker_count: self.worker_count,
+            // the workers of this authority must not batch a transaction twice
+            deduplicator: (self.worker_count > 1)
+                .then(|| BatchDeduplicator::new(BatchDedupConfig::default())),
+            sequencing_filter,
+            batch_quota: chain_info.batch_quota.map(BatchQuota::new),
+            fast_path_commit: chain_info.fast_path_commit,
+            head,
+            state: self.state.clone(),
+            memory: self.memory.clone(),
+            signing_lease,
+            events: self.events.clone(),
+            trace_ids: self.trace_ids.clone(),
+            execution_lag: None,
+            committed_round,
+            recorder,
+            submissions,
+            uncommitted: uncommitted.clone(),
+            sequenced: self.sequenced.clone(),
+        };
+        let committees = self.committees.clone();
+        let source = Arc::clone(&self.source);
+        let store = Arc::clone(&self.store);
+        let state = self.state.clone();
+        let events = self.events.clone();
+        let stall_config = self.config.stall;
+        let backlog = self.backlog.take();
+        let task_executor = node.task_executor().clone();
+
+        node.task_executor().spawn_blocking(pruner.run());
+        node.task_executor().spawn_critical("narwhal consensus", async move {
+            if let Some(summary) = warm_start {
+                match executor.warm_start(&summary) {
+                    Ok(entries) => {
+                        info!(target: "consensus::narwhal", entries, "Warmed up execution state")
+                    }
+                    Err(err) => warn!(
+                        target: "consensus::narwhal",
+                        %err,
+                        "Failed to warm up execution state"
+                    ),
+                }
+            }
+            // the sub-dags committed before the restart are executed before consensus resumes
+            let next_sub_dag = match executor.replay_committed().await {
+                Ok(next_sub_dag) => next_sub_dag.unwrap_or_default(),
+                Err(err) => {
+                    error!(
+                        target: "consensus::narwhal",
+                        %err,
+                        "Failed to replay committed sub-dags"
+                    );
+                    return
+                }
+            };
+            let execution_lag = ExecutionLag::new(next_sub_dag);
+            tasks.execution_lag = Some(execution_lag.clone());
+            let health = {
+                let (committees, execution_lag) = (committees.clone(), execution_lag.clone());
+                // the node runs every authority of the committee, so it's connected to all of them
+                move || {
+                    let committee = committees.current_committee();
+                    NodeHealth {
+                        connected_stake: committee.total_stake(),
+                        quorum_threshold: committee.quorum_threshold(),
+                        clock_skew: Duration::ZERO,
+                        pending_store_write: None,
+                        execution_backlog: execution_lag.lag() as usize,
+                    }
+                }
+            };
+            let stall = StallDetector::new(stall_config, Instant::now());
+            task_executor.spawn(stall.run(events.subscribe(), health, |event| event.log()));
+            let (halt, halted) = oneshot::channel();
+            if let Some((crosscheck, authority, gossip)) = crosscheck {
+                task_executor.spawn(crosscheck.run(authority, events.subscribe(), gossip, halt));
+            }
+            let (output, mut sub_dags) = mpsc::channel(SUB_DAG_CHANNEL_CAPACITY);
+            if let Some(backlog) = backlog {
+                let (to_executor, queued) = mpsc::channel(SUB_DAG_CHANNEL_CAPACITY);
+                task_executor.spawn(backlog.relay(sub_dags, to_executor));
+                sub_dags = queued;
+            }
+            let manager = EpochManager::from_provider(committees, source, tasks, output)
+                .with_next_sub_dag(next_sub_dag)
+                .with_consensus_state(state)
+                .with_committed_sub_dags(committed)
+                .with_execution_lag(execution_lag.clone())
+                .with_events(events)
+                .with_uncommitted_batches(uncommitted)
+                .with_votes(store);
+            task_executor
+                .spawn_critical_with_graceful_shutdown_signal("narwhal epochs", |shutdown| {
+                    manager.run(shutdown)
+                });
+            // without the cross-check, `halted` fails and only the executor is awaited
+            tokio::select! {
+                _ = executor.with_execution_lag(execution_lag).run(sub_dags) => {}
+                Ok(()) = halted => {}
+            }
+        });
+        Ok(())
+    }
+}
+
+impl NarwhalLaunchHook {
+    /// Spawns the [`DepositIngestion`] of the node if it's configured with an L1 node and the key
+    /// of the deposit sender, and returns the deposit transactions it submits.
+    fn spawn_deposit_ingestion<Node>(
+        &self,
+        node: &Node,
+    ) -> eyre::Result<Option<mpsc::Receiver<Bytes>>>
+    where
+        Node: FullNodeComponents,
+    {
+        let ingestion = &self.config.deposits;
+        let (Some(url), Some(key_file)) = (&ingestion.l1_rpc_url, &ingestion.key_file) else {
+            return Ok(None)
+        };
+        let chain_spec = node.provider().chain_spec();
+        let Some(deposits) = NarwhalChainInfo::from_genesis(&chain_spec.genesis)?.deposits else {
+            warn!(target: "consensus::narwhal", "Chain doesn't accept deposits, ignoring L1 node");
+            return Ok(None)
+        };
+        let key = std::fs::read_to_string(key_file)
+            .wrap_err_with(|| format!("failed to read deposit key file {}", key_file.display()))?;
+        let key = key
+            .trim()
+            .parse::<B256>()
+            .wrap_err_with(|| format!("invalid deposit key in {}", key_file.display()))?;
+        let signer = DepositSigner::new(deposits, chain_spec.chain.id(), key)?;
+        let source = RpcDepositSource::new(url, deposits.l1_bridge)?;
+
+        // the nonce of the deposit sender at the head is the next deposit the chain executes
+        let provider = node.provider().clone();
+        let executed = move || -> Result<u64, ProviderError> {
+            Ok(provider.latest()?.account_nonce(deposits.sender)?.unwrap_or_default())
+        };
+        let (submissions, submissions_rx) = mpsc::channel(SUBMISSION_CHANNEL_CAPACITY);
+        let ingestion =
+            DepositIngestion::new(source, signer, ingestion.clone(), executed, submissions);
+        node.task_executor().spawn(ingestion.run());
+        Ok(Some(submissions_rx))
+    }
+
+    /// Spawns the [`TransactionSubmissionServer`] of every worker of the node if the
+    /// [submission address](SubmissionConfig::addr) is configured, and returns the transactions
+    /// submitted to each worker.
+    fn spawn_submission_servers<Node>(
+        &self,
+        node: &Node,
+    ) -> eyre::Result<Vec<(WorkerId, mpsc::Receiver<Bytes>)>>
+    where
+        Node: FullNodeComponents,
+    {
+        let config = &self.config.submission;
+        if config.addr.is_none() {
+            return Ok(Vec::new())
+        }
+        let mut submissions = Vec::with_capacity(self.worker_count);
+        for worker in 0..self.worker_count as WorkerId {
+            let addr = config.worker_addr(worker).ok_or_else(|| {
+                eyre::eyre!("the submission port of narwhal worker {worker} is out of range")
+            })?;
+            // bound before the launch completes, so that a port in use fails the launch
+            let listener = std::net::TcpListener::bind(addr)
+                .and_then(|listener| {
+                    listener.set_nonblocking(true)?;
+                    TcpListener::from_std(listener)
+                })
+                .wrap_err_with(|| {
+                    format!("failed to bind the submission service of worker {worker} to {addr}")
+                })?;
+            let (server, submitted) = TransactionSubmissionServer::new(config);
+            let server = server.with_trace_ids(self.trace_ids.clone());
+            node.task_executor().spawn(server.serve(listener, node.task_executor().clone()));
+            info!(
+                target: "consensus::narwhal",
+                worker,
+                %addr,
+                "Serving gRPC transaction submissions"
+            );
+            submissions.push((worker, submitted));
+        }
+        Ok(submissions)
+    }
+}
+
+/// The [`EpochTasks`] of a node that runs every authority of the committee itself.
+///
+/// The authorities certify each other's headers in process with the
+/// [`LocalCertifier`](reth_narwhal_consensus::dev::LocalCertifier), and their workers replicate
+/// batches over a [`LocalTransport`] per worker id. Only the workers of the authority of the
+/// node's first key seal the transactions of the pool into batches, the others propose empty
+/// headers, so that no transaction is sequenced twice. The faults of the [chaos
+/// config](NarwhalConfig::chaos) are injected into the primary and the worker of this authority.
+#[derive(Debug)]
+pub struct LocalEpochTasks<Pool> {
+    executor: TaskExecutor,
+    pool: Pool,
+    store: Arc<dyn DagStore>,
+    keys: Vec<BlsSecretKey>,
+    domain: SigningDomain,
+    config: NarwhalConfig,
+    gc_depth: Round,
+    worker_count: usize,
+    /// Shared by the workers of this authority if it has more than one.
+    deduplicator: Option<BatchDeduplicator>,
+    /// The filter of the chain, which the batch makers apply.
+    sequencing_filter: Arc<dyn SequencingFilter>,
+    /// The batch quota of the chain, if it has one, which the primaries propose within and the
+    /// certifier enforces on the votes.
+    batch_quota: Option<BatchQuota>,
+    /// Whether the chain commits the leaders of unanimous rounds with the [`FastPath`].
+    fast_path_commit: bool,
+    /// The head of the executor, whose next block the batched transactions are checked for.
+    head: watch::Receiver<SealedHeader>,
+    state: ConsensusState,
+    /// The memory budget the committers are charged to.
+    memory: MemoryBudget,
+    /// The lease the primary of this authority proposes its headers with, if it has a standby.
+    signing_lease: Option<SigningLease>,
+    events: NarwhalEvents,
+    /// The trace ids the batch makers log.
+    trace_ids: TraceIds,
+    execution_lag: Option<ExecutionLag>,
+    committed_round: watch::Sender<Round>,
+    /// Records the certificates the primary of this authority receives.
+    recorder: Option<MessageRecorder>,
+    /// The transactions batched next to the pool's by the given worker, shared by the batch makers
+    /// of all epochs.
+    submissions: Vec<(WorkerId, Arc<Mutex<mpsc::Receiver<Bytes>>>)>,
+    /// The batches of this authority that weren't committed yet.
+    uncommitted: UncommittedBatches,
+    /// The index of the transactions of the stored batches, which the pool checks.
+    sequenced: Option<SequencedTransactions>,
+}
+
+impl<Pool> LocalEpochTasks<Pool>
+where
+    Pool: TransactionPool<Transaction: PoolTransaction<Consensus = TransactionSignedEcRecovered>>
+        + Clone
+        + 'static,
+{
+    /// Spawns the tasks of the epoch, which send the committed sub-dags to `output`.
+    fn spawn(
+        &self,
+        committee: Arc<Committee>,
+        first_sub_dag: u64,
+        halt: &NarwhalShutdown,
+        output: mpsc::Sender<OrderedSubDag>,
+    ) -> eyre::Result<()> {
+        let epoch = committee.epoch;
+        let committee_size = committee.authorities.len();
+        let dev = DevCommittee::with_keys(Committee::clone(&committee), &self.keys)
+            .ok_or_else(|| eyre::eyre!("missing keys of the authorities of epoch {epoch}"))?;
+        // the first key is the authority of this node
+        let local = self.keys.first().and_then(|key| {
+            let public_key = Bls12381::public_key(key).to_bytes();
+            committee
+                .authorities
+                .iter()
+                .position(|authority| authority.public_key[..] == public_key)
+        });
+        let verifier = committee.verifier_committee::<BlsPublicKey>()?;
+        let DevPrimaries { primaries, handles, batches, mut certifier } =
+            dev.primaries(self.domain, self.config.primary);
+        if let Some(quota) = self.batch_quota {
+            certifier = certifier.with_batch_quota(quota);
+        }
+        let backpressure = self
+            .execution_lag
+            .as_ref()
+            .map(|execution_lag| execution_lag.backpressure(self.config.backpressure));
+
+        let fast_path = self
+            .fast_path_commit
+            .then(|| FastPath::new(Committee::clone(&committee), self.config.fast_path));
+        let mut committer = Committer::new(
+            committee,
+            self.config.leader_schedule,
+            Arc::clone(&self.store),
+            self.gc_depth,
+            certifier.subscribe(),
+            output,
+            first_sub_dag,
+        )?
+        .with_committed_round(self.committed_round.clone())
+        .with_consensus_state(self.state.clone())
+        .with_uncommitted_batches(self.uncommitted.clone())
+        .with_memory_budget(&self.memory);
+        if let Some(fast_path) = fast_path {
+            committer = committer.with_fast_path(fast_path);
+        }
+        self.spawn_until(halt.on_shutdown(ShutdownStage::Committer), committer.run());
+        if let Some(recorder) = self.recorder.clone() {
+            // every certificate of the committee is an inbound message of this authority
+            let mut certificates = certifier.subscribe();
+            self.executor.spawn(async move {
+                while let Some(certificate) = certificates.recv().await {
+                    recorder
+                        .record(certificate.author(), &NarwhalMessage::Certificate(certificate));
+                }
+            });
+        }
+        self.executor.spawn(certifier.run());
+
+        for (author, primary) in primaries.into_iter().enumerate() {
+            let mut primary = primary.with_store(Arc::clone(&self.store))?;
+            if let Some(quota) = self.batch_quota {
+                primary = primary.with_batch_quota(quota);
+            }
+            if Some(author) == local {
+                primary = primary.with_events(self.events.clone());
+                if self.config.chaos.is_enabled() {
+                    primary = primary.with_chaos(Chaos::new(self.config.chaos));
+                }
+                if let Some(backpressure) = backpressure.clone() {
+                    primary = primary.with_backpressure(backpressure);
+                }
+                if let Some(lease) = self.signing_lease.clone() {
+                    primary = primary.with_signing_lease(lease);
+                }
+                self.state.set_primary(handles[author].clone());
+            }
+            self.spawn_until(halt.on_shutdown(ShutdownStage::Primary), primary.run());
+        }
+
+        // the workers with the same id of all authorities replicate each other's batches, the
+        // workers of the local authority only offer the wire versions of its config
+        let authorities = (0..committee_size)
+            .map(|author| {
+                let versions = if Some(author) == local {
+                    self.config.wire.versions()
+                } else {
+                    VersionRange::SUPPORTED
+                };
+                (author as AuthorityIndex, versions)
+            })
+            .collect::<Vec<_>>();
+        let mut networks = Vec::with_capacity(self.worker_count);
+        for worker in 0..self.worker_count as WorkerId {
+            for (author, (transport, inbound)) in
+                LocalTransport::mesh_with_versions(&authorities)?.into_iter().enumerate()
+            {
+                let authority = author as AuthorityIndex;
+                let handle = if Some(author) == local && self.config.chaos.is_enabled() {
+                    let transport = ChaosTransport::new(transport, Chaos::new(self.config.chaos));
+                    self.spawn_worker(halt, &verifier, authority, worker, transport, inbound)
+                } else {
+                    self.spawn_worker(halt, &verifier, authority, worker, transport, inbound)
+                };
+                if Some(author) == local {
+                    networks.push(handle);
+                }
+            }
+        }
+
+        let Some(to_primary) = local.and_then(|author| batches.into_iter().nth(author)) else {
+            return Ok(())
+        };
+        // the batches of earlier epochs are proposed before the new ones, and the forwarder
+        // only completes once the batch makers stopped, so that no sealed batch is lost
+        let (sealed, sealed_rx) = mpsc::channel(SEALED_BATCH_CHANNEL_CAPACITY);
+        let forward = self.uncommitted.clone().forward(sealed_rx, to_primary);
+        let forward_halt = halt.on_shutdown(ShutdownStage::Workers);
+        self.executor.spawn(async move {
+            forward.await;
+            drop(forward_halt.await);
+        });
+        for (worker, network) in networks.into_iter().enumerate() {
+            let worker = worker as WorkerId;
+            let config = self.config.routing.batch_config(worker, self.config.batch);
+            let mut batch_maker =
+                BatchMaker::new(self.pool.clone(), config, worker, sealed.clone())
+                    .with_routing(self.config.routing.clone())
+                    .with_store(Arc::clone(&self.store))
+                    .with_network(network)
+                    .with_sequencing_filter(Arc::clone(&self.sequencing_filter), self.head.clone())
+                    .with_events(self.events.clone())
+                    .with_trace_ids(self.trace_ids.clone());
+            if let Some(deduplicator) = &self.deduplicator {
+                batch_maker = batch_maker.with_deduplicator(deduplicator.clone());
+            }
+            if let Some(sequenced) = &self.sequenced {
+                batch_maker = batch_maker.with_sequenced_index(sequenced.clone());
+            }
+            if let Some(backpressure) = backpressure.clone() {
+                batch_maker = batch_maker.with_backpressure(backpressure);
+            }
+            if let (Some(rate_limit), Some(author)) = (self.config.rate_limit, local) {
+                // every worker budgets the senders of its own batches
+                batch_maker = batch_maker.with_rate_limiter(
+                    SenderRateLimiter::new(rate_limit),
+                    handles[author].subscribe(),
+                );
+            }
+            let submissions = self
+                .submissions
+                .iter()
+                .filter(|(submitted_to, _)| *submitted_to == worker)
+                .map(|(_, submissions)| submissions)
+                .collect::<Vec<_>>();
+            if !submissions.is_empty() {
+                let (submitted, submitted_rx) = mpsc::channel(SUBMISSION_CHANNEL_CAPACITY);
+                batch_maker = batch_maker.with_submissions(submitted_rx);
+                for submissions in submissions {
+                    let submissions = Arc::clone(submissions);
+                    let submitted = submitted.clone();
+                    self.spawn_until(halt.on_shutdown(ShutdownStage::Workers), async move {
+                        let mut submissions = submissions.lock().await;
+                        while let Some(transaction) = submissions.recv().await {
+                            if submitted.send(transaction).await.is_err() {
+                                return
+                            }
+                        }
+                    });
+                }
+            }
+            self.spawn_until(halt.on_shutdown(ShutdownStage::Workers), batch_maker.run());
+        }
+        Ok(())
+    }
+
+    /// Spawns the network of a worker of an authority, and returns the handle its batch maker
+    /// broadcasts batches with.
+    fn spawn_worker<T: WorkerTransport>(
+        &self,
+        halt: &NarwhalShutdown,
+        committee: &VerifierCommittee<BlsPublicKey>,
+        authority: AuthorityIndex,
+        worker: WorkerId,
+        transport: T,
+        inbound: mpsc::Receiver<(AuthorityIndex, WorkerMessage)>,
+    ) -> WorkerHandle {
+        let (mut network, handle) = WorkerNetwork::new(
+            committee,
+            authority,
+            worker,
+            self.config.network,
+            transport,
+            Arc::clone(&self.store),
+            inbound,
+        );
+        if let Some(sequenced) = &self.sequenced {
+            network = network.with_sequenced_index(sequenced.clone());
+        }
+        // the workers of the other authorities have no batch maker, and a network without
+        // handles stops
+        let idle = handle.clone();
+        self.spawn_until(halt.on_shutdown(ShutdownStage::Workers), async move {
+            let _idle = idle;
+            network.run().await
+        });
+        handle
+    }
+
+    /// Spawns a task of the epoch that is stopped once the signal of its stage fires.
+    fn spawn_until(
+        &self,
+        mut halt: GracefulShutdown,
+        task: impl Future<Output = ()> + Send + 'static,
+    ) {
+        self.executor.spawn(async move {
+            tokio::select! {
+                guard = &mut halt => drop(guard),
+                () = task => {
+                    // the epoch is only over once it's halted
+                    drop(halt.await)
+                }
+            }
+        });
+    }
+}
+
+impl<Pool> EpochTasks for LocalEpochTasks<Pool>
+where
+    Pool: TransactionPool<Transaction: PoolTransaction<Consensus = TransactionSignedEcRecovered>>
+        + Clone
+        + 'static,
+{
+    fn start(&mut self, epoch: EpochStart<'_>) -> mpsc::Receiver<OrderedSubDag> {
+        let EpochStart { committee, first_sub_dag, halt } = epoch;
+        let (output, sub_dags) = mpsc::channel(SUB_DAG_CHANNEL_CAPACITY);
+        let epoch = committee.epoch;
+        // without tasks the sender is dropped, which stops the manager
+        if let Err(err) = self.spawn(committee, first_sub_dag, halt, output) {
+            error!(target: "consensus::narwhal", %err, epoch, "Failed to start epoch");
+        }
+        sub_dags
+    }
+}
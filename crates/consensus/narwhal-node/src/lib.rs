@@ -0,0 +1,60 @@
+//! Node builder preset for narwhal chains.
+//!
+//! Blocks of a narwhal chain are derived from consensus output instead of the transaction pool,
+//! and validated with the [`NarwhalConsensus`](reth_narwhal_consensus::NarwhalConsensus).
+//! [`NarwhalNode`] configures the node builder accordingly, with a payload builder that builds the
+//! blocks of sub-dags from their [`NarwhalEngineTypes`] payload attributes. The
+//! [`NarwhalNodeLauncher`] launches the node together with the consensus of its committee:
+//!
+//! ```ignore
+//! let node = NarwhalNode::default();
+//! let sequenced = node.sequenced_index();
+//! let handle = NodeBuilder::new(config)
+//!     .with_database(db)
+//!     .with_launch_context(task_executor)
+//!     .node(node)
+//!     .launch_with_fn(|builder| {
+//!         let launcher = NarwhalNodeLauncher::new(
+//!             builder.task_executor().clone(),
+//!             builder.config().datadir(),
+//!         )
+//!         .with_sequenced_index(sequenced);
+//!         builder.launch_with(launcher)
+//!     })
+//!     .await?;
+//! ```
+
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/paradigmxyz/reth/main/assets/reth-docs.png",
+    html_favicon_url = "https://avatars0.githubusercontent.com/u/97369466?s=256",
+    issue_tracker_base_url = "https://github.com/paradigmxyz/reth/issues/"
+)]
+#![cfg_attr(not(test), warn(unused_crate_dependencies))]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+pub mod args;
+pub use args::RethNarwhalConfig;
+
+pub mod deposits;
+pub use deposits::RpcDepositSource;
+
+pub mod engine;
+pub use engine::NarwhalEngineTypes;
+
+pub mod exex;
+pub use exex::{narwhal_exex, NarwhalExExContext};
+
+pub mod launch;
+pub use launch::{LocalEpochTasks, NarwhalLaunchHook, NarwhalNodeLauncher};
+
+pub mod node;
+pub use node::NarwhalNode;
+
+pub mod rpc;
+pub use rpc::{
+    install_narwhal_rpc, install_recent_receipts, install_transaction_status, NarwhalAdmin,
+    NarwhalAdminApiServer, NarwhalApiServer, NarwhalIntrospection, NarwhalTransactionsApiServer,
+};
+
+pub mod service;
+pub use service::{NarwhalService, NarwhalServiceBuilder};
@@ -0,0 +1,302 @@
+//! Narwhal Node types config.
+
+use std::sync::Arc;
+
+use reth_basic_payload_builder::{BasicPayloadJobGenerator, BasicPayloadJobGeneratorConfig};
+use reth_chainspec::ChainSpec;
+use reth_narwhal_consensus::{
+    pool_maintenance::maintain_narwhal_pool,
+    worker::{SequencedTransactionValidator, SequencedTransactions},
+    NarwhalChainInfo, NarwhalConsensus,
+};
+use reth_network::NetworkHandle;
+use reth_node_builder::{
+    components::{
+        ComponentsBuilder, ConsensusBuilder, NetworkBuilder, PayloadServiceBuilder, PoolBuilder,
+    },
+    node::{FullNodeTypes, NodeTypes, NodeTypesWithEngine},
+    BuilderContext, ConfigureEvm, Node,
+};
+use reth_node_ethereum::{
+    node::{EthereumAddOns, EthereumExecutorBuilder},
+    EthEvmConfig, EthExecutorProvider,
+};
+use reth_payload_builder::{PayloadBuilderHandle, PayloadBuilderService};
+use reth_provider::CanonStateSubscriptions;
+use reth_tracing::tracing::{debug, info};
+use reth_transaction_pool::{
+    blobstore::DiskFileBlobStore,
+    maintain::{backup_local_transactions_task, LocalTransactionBackupConfig},
+    CoinbaseTipOrdering, EthPooledTransaction, EthTransactionValidator, Pool, TransactionPool,
+    TransactionValidationTaskExecutor,
+};
+
+use crate::NarwhalEngineTypes;
+
+/// Type configuration for a narwhal node.
+///
+/// Narwhal nodes execute Ethereum transactions and speak the Ethereum engine API, but their blocks
+/// are derived from consensus output, so their payloads are built from the sequenced transactions
+/// of a sub-dag instead of the pool, and blocks are validated with the [`NarwhalConsensus`].
+///
+/// The pool of the node rejects the transactions its workers already stored in the DAG, see
+/// [`NarwhalNode::sequenced_index`].
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct NarwhalNode {
+    sequenced: SequencedTransactions,
+}
+
+impl NarwhalNode {
+    /// Returns the index of the transactions sequenced in the DAG, which the pool of the node
+    /// checks and the workers of the node must fill, see the
+    /// [`with_sequenced_index`](crate::NarwhalNodeLauncher::with_sequenced_index) of the launcher.
+    pub fn sequenced_index(&self) -> SequencedTransactions {
+        self.sequenced.clone()
+    }
+
+    /// Returns a [`ComponentsBuilder`] configured for a narwhal node.
+    pub fn components<Node>() -> ComponentsBuilder<
+        Node,
+        NarwhalPoolBuilder,
+        NarwhalPayloadBuilder,
+        NarwhalNetworkBuilder,
+        EthereumExecutorBuilder,
+        NarwhalConsensusBuilder,
+    >
+    where
+        Node: FullNodeTypes<Engine = NarwhalEngineTypes, ChainSpec = ChainSpec>,
+    {
+        ComponentsBuilder::default()
+            .node_types::<Node>()
+            .pool(NarwhalPoolBuilder::default())
+            .payload(NarwhalPayloadBuilder::default())
+            .network(NarwhalNetworkBuilder::default())
+            .executor(EthereumExecutorBuilder::default())
+            .consensus(NarwhalConsensusBuilder::default())
+    }
+}
+
+impl NodeTypes for NarwhalNode {
+    type Primitives = ();
+    type ChainSpec = ChainSpec;
+}
+
+impl NodeTypesWithEngine for NarwhalNode {
+    type Engine = NarwhalEngineTypes;
+}
+
+impl<N> Node<N> for NarwhalNode
+where
+    N: FullNodeTypes<Engine = NarwhalEngineTypes, ChainSpec = ChainSpec>,
+{
+    type ComponentsBuilder = ComponentsBuilder<
+        N,
+        NarwhalPoolBuilder,
+        NarwhalPayloadBuilder,
+        NarwhalNetworkBuilder,
+        EthereumExecutorBuilder,
+        NarwhalConsensusBuilder,
+    >;
+
+    type AddOns = EthereumAddOns;
+
+    fn components_builder(&self) -> Self::ComponentsBuilder {
+        Self::components().pool(NarwhalPoolBuilder::new(self.sequenced.clone()))
+    }
+}
+
+/// The transaction pool of a narwhal node, an Ethereum pool whose validator rejects the external
+/// transactions that are already sequenced in the DAG.
+pub type NarwhalTransactionPool<Client, S> = Pool<
+    SequencedTransactionValidator<
+        TransactionValidationTaskExecutor<EthTransactionValidator<Client, EthPooledTransaction>>,
+    >,
+    CoinbaseTipOrdering<EthPooledTransaction>,
+    S,
+>;
+
+/// The transaction pool of a narwhal node.
+///
+/// Workers seal the pending transactions of the pool into batches, so the pool validates
+/// transactions like an Ethereum pool. Transactions that peers keep relaying until their batch is
+/// committed are rejected with the index of sequenced transactions, which the workers of the node
+/// fill. It is maintained with
+/// [`maintain_narwhal_pool`](reth_narwhal_consensus::pool_maintenance::maintain_narwhal_pool)
+/// instead of the maintenance task of the Ethereum pool, since blocks of a narwhal chain are final.
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct NarwhalPoolBuilder {
+    sequenced: SequencedTransactions,
+}
+
+impl NarwhalPoolBuilder {
+    /// Creates the builder of a pool that rejects the transactions of the given index.
+    pub const fn new(sequenced: SequencedTransactions) -> Self {
+        Self { sequenced }
+    }
+}
+
+impl<Node> PoolBuilder<Node> for NarwhalPoolBuilder
+where
+    Node: FullNodeTypes<ChainSpec = ChainSpec>,
+{
+    type Pool = NarwhalTransactionPool<Node::Provider, DiskFileBlobStore>;
+
+    async fn build_pool(self, ctx: &BuilderContext<Node>) -> eyre::Result<Self::Pool> {
+        let data_dir = ctx.config().datadir();
+        let pool_config = ctx.pool_config();
+        let blob_store = DiskFileBlobStore::open(data_dir.blobstore(), Default::default())?;
+        let validator = TransactionValidationTaskExecutor::eth_builder(ctx.chain_spec())
+            .with_head_timestamp(ctx.head().timestamp)
+            .kzg_settings(ctx.kzg_settings()?)
+            .with_local_transactions_config(pool_config.local_transactions_config.clone())
+            .with_additional_tasks(ctx.config().txpool.additional_validation_tasks)
+            .build_with_tasks(
+                ctx.provider().clone(),
+                ctx.task_executor().clone(),
+                blob_store.clone(),
+            );
+        let validator = SequencedTransactionValidator::new(validator, self.sequenced);
+
+        let transaction_pool =
+            Pool::new(validator, CoinbaseTipOrdering::default(), blob_store, pool_config);
+        info!(target: "reth::cli", "Transaction pool initialized");
+
+        let pool = transaction_pool.clone();
+        let transactions_backup_config =
+            LocalTransactionBackupConfig::with_local_txs_backup(data_dir.txpool_transactions());
+        ctx.task_executor().spawn_critical_with_graceful_shutdown_signal(
+            "local transactions backup task",
+            |shutdown| {
+                backup_local_transactions_task(shutdown, pool.clone(), transactions_backup_config)
+            },
+        );
+
+        ctx.task_executor().spawn_critical(
+            "txpool maintenance task",
+            maintain_narwhal_pool(
+                ctx.provider().clone(),
+                transaction_pool.clone(),
+                ctx.chain_spec(),
+                ctx.provider().canonical_state_stream(),
+            ),
+        );
+        debug!(target: "reth::cli", "Spawned narwhal txpool maintenance task");
+
+        Ok(transaction_pool)
+    }
+}
+
+/// The payload service of a narwhal node.
+///
+/// Builds the payloads of sub-dags with the
+/// [`NarwhalPayloadBuilder`](reth_narwhal_consensus::payload::NarwhalPayloadBuilder), which
+/// executes the transactions of the payload attributes instead of the best transactions of the
+/// pool.
+///
+/// The blocks must be built with the EVM the node executes them with, so a node with a custom
+/// [`ExecutorBuilder`](reth_node_builder::components::ExecutorBuilder) must build its payloads
+/// with the same EVM configuration.
+#[derive(Debug, Default, Clone)]
+#[non_exhaustive]
+pub struct NarwhalPayloadBuilder<Evm = EthEvmConfig> {
+    /// The EVM configuration to execute the payloads with.
+    pub evm_config: Evm,
+}
+
+impl<Evm> NarwhalPayloadBuilder<Evm> {
+    /// Creates a payload builder that executes the payloads with the given EVM configuration.
+    pub const fn new(evm_config: Evm) -> Self {
+        Self { evm_config }
+    }
+}
+
+impl<Node, Evm, Pool> PayloadServiceBuilder<Node, Pool> for NarwhalPayloadBuilder<Evm>
+where
+    Node: FullNodeTypes<Engine = NarwhalEngineTypes, ChainSpec = ChainSpec>,
+    Evm: ConfigureEvm,
+    Pool: TransactionPool + Unpin + 'static,
+{
+    async fn spawn_payload_service(
+        self,
+        ctx: &BuilderContext<Node>,
+        pool: Pool,
+    ) -> eyre::Result<PayloadBuilderHandle<Node::Engine>> {
+        let payload_builder = reth_narwhal_consensus::payload::NarwhalPayloadBuilder::new(
+            EthExecutorProvider::new(ctx.chain_spec(), self.evm_config),
+        );
+        let conf = ctx.payload_builder_config();
+
+        // the builder sets the extra data of narwhal blocks to the root of their outbound
+        // messages, the extra data of the job config is unused
+        let payload_job_config = BasicPayloadJobGeneratorConfig::default()
+            .interval(conf.interval())
+            .deadline(conf.deadline())
+            .max_payload_tasks(conf.max_payload_tasks());
+
+        let payload_generator = BasicPayloadJobGenerator::with_builder(
+            ctx.provider().clone(),
+            pool,
+            ctx.task_executor().clone(),
+            payload_job_config,
+            ctx.chain_spec(),
+            payload_builder,
+        );
+        let (payload_service, payload_builder) =
+            PayloadBuilderService::new(payload_generator, ctx.provider().canonical_state_stream());
+
+        ctx.task_executor().spawn_critical("payload builder service", Box::pin(payload_service));
+
+        Ok(payload_builder)
+    }
+}
+
+/// The network of a narwhal node.
+///
+/// The devp2p network syncs blocks and gossips transactions between nodes, consensus messages are
+/// exchanged by the narwhal primaries and workers.
+#[derive(Debug, Default, Clone, Copy)]
+#[non_exhaustive]
+pub struct NarwhalNetworkBuilder;
+
+impl<Node, Pool> NetworkBuilder<Node, Pool> for NarwhalNetworkBuilder
+where
+    Node: FullNodeTypes,
+    Pool: TransactionPool + Unpin + 'static,
+{
+    async fn build_network(
+        self,
+        ctx: &BuilderContext<Node>,
+        pool: Pool,
+    ) -> eyre::Result<NetworkHandle> {
+        let network = ctx.network_builder().await?;
+        let handle = ctx.start_network(network, pool);
+
+        Ok(handle)
+    }
+}
+
+/// Builds the [`NarwhalConsensus`] of the node's chain spec.
+///
+/// The allowlist of a permissioned chain catches up from the receipts of the node's database.
+#[derive(Debug, Default, Clone, Copy)]
+#[non_exhaustive]
+pub struct NarwhalConsensusBuilder;
+
+impl<Node> ConsensusBuilder<Node> for NarwhalConsensusBuilder
+where
+    Node: FullNodeTypes<ChainSpec = ChainSpec>,
+{
+    type Consensus = Arc<dyn reth_consensus::Consensus>;
+
+    async fn build_consensus(self, ctx: &BuilderContext<Node>) -> eyre::Result<Self::Consensus> {
+        let chain_spec = ctx.chain_spec();
+        // reject a malformed narwhal section of the genesis instead of panicking
+        NarwhalChainInfo::from_genesis(&chain_spec.genesis)?;
+
+        let consensus = NarwhalConsensus::new(chain_spec)
+            .with_allowlist_source(Arc::new(ctx.provider().clone()));
+        Ok(Arc::new(consensus))
+    }
+}
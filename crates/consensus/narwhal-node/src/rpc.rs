@@ -0,0 +1,844 @@
+//! The `narwhal_` RPC namespace.
+//!
+//! The [`NarwhalApiServer`] methods let operators inspect the progress of the DAG, and the
+//! [`NarwhalBlocksApiServer`] methods let explorers attribute blocks to the authorities that led
+//! their commits, and bridges prove the messages sent in blocks. Both are served on all configured
+//! transports by [`install_narwhal_rpc`], and the [`NarwhalTransactionsApiServer`] methods, which
+//! report the transactions the executor dropped, by [`install_transaction_status`]. Clients
+//! of the WS and IPC transports can also subscribe to the consensus events with
+//! `narwhal_subscribeEvents`, and to the certified checkpoints with `narwhal_subscribeCheckpoints`.
+//! The [`NarwhalAdminApiServer`] methods change the behavior of the node, so they are only served
+//! by the authenticated server of the engine API:
+//!
+//! ```ignore
+//! let handle = NodeBuilder::new(config)
+//!     .with_database(db)
+//!     .node(NarwhalNode::default())
+//!     .extend_rpc_modules(move |mut ctx| {
+//!         if let Some(filter) = reth_tracing::log_filter_handle() {
+//!             ctx.auth_module.merge_auth_methods(NarwhalAdmin::new(filter).into_rpc())?;
+//!         }
+//!         install_narwhal_rpc(&mut ctx, consensus_state)
+//!     })
+//!     .launch()
+//!     .await?;
+//! ```
+//!
+//! The namespace is described by the [OpenRPC](https://spec.open-rpc.org) document of
+//! [`openrpc_document`](reth_narwhal_consensus::rpc::openrpc_document) for external tooling, and
+//! the `client` feature generates the Rust clients of the traits, e.g. `NarwhalApiClient`.
+
+use jsonrpsee::{
+    core::{async_trait, RpcResult, SubscriptionResult},
+    proc_macros::rpc,
+    server::SubscriptionMessage,
+    types::{error::INTERNAL_ERROR_CODE, ErrorObjectOwned},
+    PendingSubscriptionSink, SubscriptionSink,
+};
+use reth_narwhal_consensus::{
+    commit_log::{CommitAuditEntry, CommitDecision},
+    committee::Committee,
+    messages::MessageProof,
+    rpc::{
+        CertifiedCheckpoint, CommittedSubDag, ConsensusEvent, ConsensusEventFilter,
+        ConsensusEvents, ConsensusState, NarwhalRpcError, RoundInfo, RpcLimitsConfig,
+        RECENT_DECISIONS,
+    },
+    types::{BatchRef, Certificate, CertificateDigest, Round},
+    verifier::Epoch,
+    DroppedTransactions, RecentReceipts, TransactionStatus,
+};
+use reth_node_builder::{rpc::RpcContext, EthApiTypes, FullNodeComponents};
+use reth_primitives::{BlockNumberOrTag, B256};
+use reth_provider::{
+    BlockHashReader, BlockIdReader, ConsensusMetadataProvider, ProviderResult, ReceiptProvider,
+    TransactionsProvider,
+};
+use reth_rpc_eth_api::helpers::{EthTransactions, LoadReceipt};
+use reth_rpc_eth_types::ReceiptBuilder;
+use reth_rpc_types::AnyTransactionReceipt;
+use reth_tracing::{
+    tracing::{debug, info},
+    LogFilterHandle,
+};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::broadcast;
+
+pub use reth_narwhal_client::rpc::BlockAttribution;
+
+/// The target of the consensus logs, the log filter can only be changed for it and the targets
+/// below it.
+pub const CONSENSUS_LOG_TARGET: &str = "consensus";
+
+/// Installs the [`NarwhalApiServer`] and [`NarwhalBlocksApiServer`] methods on all configured
+/// transports, from the
+/// [`extend_rpc_modules`](reth_node_builder::NodeBuilderWithComponents::extend_rpc_modules) hook of
+/// a narwhal node.
+pub fn install_narwhal_rpc<Node, EthApi>(
+    ctx: &mut RpcContext<'_, Node, EthApi>,
+    state: ConsensusState,
+) -> eyre::Result<()>
+where
+    Node: FullNodeComponents,
+    Node::Provider: ConsensusMetadataProvider,
+    EthApi: EthApiTypes,
+{
+    let blocks = NarwhalBlocks::new(ctx.provider().clone());
+    ctx.modules.merge_configured(NarwhalIntrospection::new(state).into_rpc())?;
+    ctx.modules.merge_configured(blocks.into_rpc())?;
+    Ok(())
+}
+
+/// Replaces `eth_getTransactionReceipt` on all configured transports with the
+/// [`NarwhalReceiptsApiServer`], which serves the receipts recorded by the executor of a narwhal
+/// node.
+pub fn install_recent_receipts<Node, EthApi>(
+    ctx: &mut RpcContext<'_, Node, EthApi>,
+    receipts: RecentReceipts,
+) -> eyre::Result<()>
+where
+    Node: FullNodeComponents,
+    EthApi: EthTransactions + LoadReceipt + 'static,
+{
+    let api = NarwhalReceipts::new(receipts, ctx.registry.eth_api().clone());
+    ctx.modules.remove_method_from_configured("eth_getTransactionReceipt");
+    ctx.modules.merge_configured(api.into_rpc())?;
+    Ok(())
+}
+
+/// Installs the [`NarwhalTransactionsApiServer`] methods on all configured transports, which serve
+/// the transactions the executor of a narwhal node dropped next to the transactions of the
+/// database.
+pub fn install_transaction_status<Node, EthApi>(
+    ctx: &mut RpcContext<'_, Node, EthApi>,
+    dropped: DroppedTransactions,
+) -> eyre::Result<()>
+where
+    Node: FullNodeComponents,
+    EthApi: EthApiTypes,
+{
+    let api = NarwhalTransactions::new(ctx.provider().clone(), dropped);
+    ctx.modules.merge_configured(api.into_rpc())?;
+    Ok(())
+}
+
+/// `eth_getTransactionReceipt` of a narwhal node.
+#[rpc(server, namespace = "eth")]
+pub trait NarwhalReceiptsApi {
+    /// Returns the receipt of a transaction, from the receipts of the recently executed blocks or
+    /// from the database.
+    #[method(name = "getTransactionReceipt")]
+    async fn transaction_receipt(&self, hash: B256) -> RpcResult<Option<AnyTransactionReceipt>>;
+}
+
+/// Implementation of the [`NarwhalReceiptsApiServer`].
+///
+/// The receipts the executor recorded are served without reading the database, so a receipt is
+/// available as soon as its block was executed. Commits are final, so the recorded receipts are
+/// the receipts the database serves once the engine wrote the block.
+#[derive(Debug, Clone)]
+pub struct NarwhalReceipts<EthApi> {
+    receipts: RecentReceipts,
+    eth_api: EthApi,
+}
+
+impl<EthApi> NarwhalReceipts<EthApi> {
+    /// Creates the API that serves the given receipts, and the receipts of the database with the
+    /// given `eth_` API.
+    pub const fn new(receipts: RecentReceipts, eth_api: EthApi) -> Self {
+        Self { receipts, eth_api }
+    }
+}
+
+#[async_trait]
+impl<EthApi> NarwhalReceiptsApiServer for NarwhalReceipts<EthApi>
+where
+    EthApi: EthTransactions + LoadReceipt + 'static,
+{
+    async fn transaction_receipt(&self, hash: B256) -> RpcResult<Option<AnyTransactionReceipt>> {
+        if let Some(recent) = self.receipts.get(&hash) {
+            let receipt = ReceiptBuilder::new(
+                &recent.transaction,
+                recent.meta,
+                &recent.receipt,
+                &recent.block_receipts,
+            )?;
+            return Ok(Some(receipt.build()))
+        }
+        EthTransactions::transaction_receipt(&self.eth_api, hash).await.map_err(Into::into)
+    }
+}
+
+/// Block attribution `narwhal_` RPC methods.
+#[cfg_attr(not(feature = "client"), rpc(server, namespace = "narwhal"))]
+#[cfg_attr(feature = "client", rpc(server, client, namespace = "narwhal"))]
+pub trait NarwhalBlocksApi {
+    /// Returns the commit the block was built from, `None` if the block doesn't exist or was built
+    /// before the node recorded the commits of its blocks.
+    #[method(name = "getBlockAttribution")]
+    async fn block_attribution(
+        &self,
+        block: BlockNumberOrTag,
+    ) -> RpcResult<Option<BlockAttribution>>;
+
+    /// Returns the proof that a message with the given hash was sent in the block, against the
+    /// message root its header commits to, `None` if the block doesn't exist or sent no such
+    /// message.
+    #[method(name = "getMessageProof")]
+    async fn message_proof(
+        &self,
+        block: BlockNumberOrTag,
+        message_hash: B256,
+    ) -> RpcResult<Option<MessageProof>>;
+}
+
+/// Implementation of the [`NarwhalBlocksApiServer`], which reads the consensus metadata the
+/// executor stores with every block, and the receipts of the blocks.
+#[derive(Debug, Clone)]
+pub struct NarwhalBlocks<Provider> {
+    provider: Provider,
+}
+
+impl<Provider> NarwhalBlocks<Provider>
+where
+    Provider: BlockIdReader + ConsensusMetadataProvider + ReceiptProvider,
+{
+    /// Creates the API that reads the blocks of the given provider.
+    pub const fn new(provider: Provider) -> Self {
+        Self { provider }
+    }
+
+    /// Returns the attribution of the block.
+    fn attribution(&self, block: BlockNumberOrTag) -> ProviderResult<Option<BlockAttribution>> {
+        let Some(number) = self.provider.convert_block_number(block)? else { return Ok(None) };
+        let Some(metadata) = self.provider.consensus_metadata(number)? else { return Ok(None) };
+        let Some(hash) = self.provider.block_hash(number)? else { return Ok(None) };
+        Ok(Some(BlockAttribution {
+            number,
+            hash,
+            epoch: metadata.epoch,
+            leader: metadata.leader,
+            first_round: metadata.first_round,
+            last_round: metadata.last_round,
+            certificates: metadata.certificates,
+        }))
+    }
+
+    /// Returns the proof of the first message with the given hash sent in the block.
+    fn prove_message(
+        &self,
+        block: BlockNumberOrTag,
+        message_hash: B256,
+    ) -> ProviderResult<Option<MessageProof>> {
+        let Some(number) = self.provider.convert_block_number(block)? else { return Ok(None) };
+        let Some(receipts) = self.provider.receipts_by_block(number.into())? else {
+            return Ok(None)
+        };
+        Ok(MessageProof::new(&receipts, message_hash))
+    }
+}
+
+#[async_trait]
+impl<Provider> NarwhalBlocksApiServer for NarwhalBlocks<Provider>
+where
+    Provider: BlockIdReader + ConsensusMetadataProvider + ReceiptProvider + Clone + 'static,
+{
+    async fn block_attribution(
+        &self,
+        block: BlockNumberOrTag,
+    ) -> RpcResult<Option<BlockAttribution>> {
+        let this = self.clone();
+        // the metadata is read with blocking I/O
+        let attribution = tokio::task::spawn_blocking(move || this.attribution(block))
+            .await
+            .map_err(|err| internal_error(err.to_string()))?;
+        attribution.map_err(|err| internal_error(err.to_string()))
+    }
+
+    async fn message_proof(
+        &self,
+        block: BlockNumberOrTag,
+        message_hash: B256,
+    ) -> RpcResult<Option<MessageProof>> {
+        let this = self.clone();
+        // the receipts are read with blocking I/O
+        let proof = tokio::task::spawn_blocking(move || this.prove_message(block, message_hash))
+            .await
+            .map_err(|err| internal_error(err.to_string()))?;
+        proof.map_err(|err| internal_error(err.to_string()))
+    }
+}
+
+/// Transaction `narwhal_` RPC methods.
+#[cfg_attr(not(feature = "client"), rpc(server, namespace = "narwhal"))]
+#[cfg_attr(feature = "client", rpc(server, client, namespace = "narwhal"))]
+pub trait NarwhalTransactionsApi {
+    /// Returns whether a sequenced transaction was included in a block or dropped from the block
+    /// of its commit, `None` if the node doesn't know the transaction.
+    ///
+    /// Dropped transactions are only remembered for a while and not across restarts, see
+    /// [`DroppedTransactions`].
+    #[method(name = "txStatus")]
+    async fn tx_status(&self, hash: B256) -> RpcResult<Option<TransactionStatus>>;
+}
+
+/// Implementation of the [`NarwhalTransactionsApiServer`].
+///
+/// A dropped transaction can be resubmitted and included later, so the database is read before the
+/// dropped transactions.
+#[derive(Debug, Clone)]
+pub struct NarwhalTransactions<Provider> {
+    provider: Provider,
+    dropped: DroppedTransactions,
+}
+
+impl<Provider> NarwhalTransactions<Provider>
+where
+    Provider: TransactionsProvider,
+{
+    /// Creates the API that reads the transactions of the given provider and the given dropped
+    /// transactions.
+    pub const fn new(provider: Provider, dropped: DroppedTransactions) -> Self {
+        Self { provider, dropped }
+    }
+
+    /// Returns the status of the transaction.
+    fn status(&self, hash: B256) -> ProviderResult<Option<TransactionStatus>> {
+        if let Some((_, meta)) = self.provider.transaction_by_hash_with_meta(hash)? {
+            return Ok(Some(TransactionStatus::Included {
+                block_number: meta.block_number,
+                block_hash: meta.block_hash,
+            }))
+        }
+        Ok(self.dropped.status(&hash))
+    }
+}
+
+#[async_trait]
+impl<Provider> NarwhalTransactionsApiServer for NarwhalTransactions<Provider>
+where
+    Provider: TransactionsProvider + Clone + 'static,
+{
+    async fn tx_status(&self, hash: B256) -> RpcResult<Option<TransactionStatus>> {
+        let this = self.clone();
+        // the transaction is read with blocking I/O
+        let status = tokio::task::spawn_blocking(move || this.status(hash))
+            .await
+            .map_err(|err| internal_error(err.to_string()))?;
+        status.map_err(|err| internal_error(err.to_string()))
+    }
+}
+
+/// Introspection `narwhal_` RPC methods.
+#[cfg_attr(not(feature = "client"), rpc(server, namespace = "narwhal"))]
+#[cfg_attr(feature = "client", rpc(server, client, namespace = "narwhal"))]
+pub trait NarwhalApi {
+    /// Returns the current round of the node's primary.
+    #[method(name = "currentRound")]
+    async fn current_round(&self) -> RpcResult<Round>;
+
+    /// Returns the committee of the current epoch.
+    #[method(name = "committee")]
+    async fn committee(&self) -> RpcResult<Committee>;
+
+    /// Returns the committee of the given epoch, `None` if the epoch is neither recorded in the
+    /// committee history of the node nor one of the epochs since the node started.
+    #[method(name = "getCommittee")]
+    async fn epoch_committee(&self, epoch: Epoch) -> RpcResult<Option<Committee>>;
+
+    /// Returns the last sub-dag committed since the node started, without its batches.
+    #[method(name = "lastCommittedSubDag")]
+    async fn last_committed_sub_dag(&self) -> RpcResult<Option<CommittedSubDag>>;
+
+    /// Returns the certificate with the given digest, if it's stored and not pruned.
+    #[method(name = "certificate")]
+    async fn certificate(&self, digest: CertificateDigest) -> RpcResult<Option<Certificate>>;
+
+    /// Returns the batches of the node's workers that are waiting to be referenced by a header.
+    #[method(name = "pendingBatches")]
+    async fn pending_batches(&self) -> RpcResult<Vec<BatchRef>>;
+
+    /// Returns which authorities produced certificates in the round, which votes certified the
+    /// node's header of the round, the elected leader, and whether the leader was committed.
+    #[method(name = "getRoundInfo")]
+    async fn round_info(&self, round: Round) -> RpcResult<RoundInfo>;
+
+    /// Returns why the leader of the round was or was not committed, `None` if the round has no
+    /// leader, its commit rule didn't run yet, or it's not one of the last [`RECENT_DECISIONS`]
+    /// leader rounds.
+    #[method(name = "getCommitDecision")]
+    async fn commit_decision(&self, round: Round) -> RpcResult<Option<CommitDecision>>;
+
+    /// Returns the entries of the commit audit log with indices from `from_index` to `to_index`,
+    /// both inclusive, but at most [`RpcLimitsConfig::max_page_size`] entries.
+    ///
+    /// Clients fetch the rest of the range starting after the last returned entry. The entries
+    /// form a hash chain, see
+    /// [`CommitAuditLog`](reth_narwhal_consensus::commit_log::CommitAuditLog).
+    #[method(name = "commitLog")]
+    async fn commit_log(&self, from_index: u64, to_index: u64) -> RpcResult<Vec<CommitAuditEntry>>;
+
+    /// Subscribes to the consensus events that match the filter, or to all events if no filter is
+    /// given.
+    #[subscription(
+        name = "subscribeEvents" => "event",
+        unsubscribe = "unsubscribeEvents",
+        item = ConsensusEvent
+    )]
+    async fn subscribe_events(&self, filter: Option<ConsensusEventFilter>) -> SubscriptionResult;
+
+    /// Subscribes to the checkpoint ranges signed by a quorum of the committee, with their ABI
+    /// encoded proofs.
+    #[subscription(
+        name = "subscribeCheckpoints" => "checkpoint",
+        unsubscribe = "unsubscribeCheckpoints",
+        item = CertifiedCheckpoint
+    )]
+    async fn subscribe_checkpoints(&self) -> SubscriptionResult;
+}
+
+/// Implementation of the [`NarwhalApiServer`].
+#[derive(Debug, Clone)]
+pub struct NarwhalIntrospection {
+    state: ConsensusState,
+    limits: RpcLimitsConfig,
+}
+
+impl NarwhalIntrospection {
+    /// Creates the API that serves the given state with the default limits.
+    pub fn new(state: ConsensusState) -> Self {
+        Self { state, limits: RpcLimitsConfig::default() }
+    }
+
+    /// Sets the limits of the queries.
+    pub const fn with_limits(mut self, limits: RpcLimitsConfig) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+#[async_trait]
+impl NarwhalApiServer for NarwhalIntrospection {
+    async fn current_round(&self) -> RpcResult<Round> {
+        Ok(self.state.current_round())
+    }
+
+    async fn committee(&self) -> RpcResult<Committee> {
+        Ok(Committee::clone(&self.state.committee()))
+    }
+
+    async fn epoch_committee(&self, epoch: Epoch) -> RpcResult<Option<Committee>> {
+        Ok(self.state.epoch_committee(epoch))
+    }
+
+    async fn last_committed_sub_dag(&self) -> RpcResult<Option<CommittedSubDag>> {
+        Ok(self.state.last_committed())
+    }
+
+    async fn certificate(&self, digest: CertificateDigest) -> RpcResult<Option<Certificate>> {
+        let state = self.state.clone();
+        // the store is read with blocking I/O
+        let certificate = tokio::task::spawn_blocking(move || state.certificate(digest))
+            .await
+            .map_err(|err| internal_error(err.to_string()))?;
+        certificate.map_err(|err| internal_error(err.to_string()))
+    }
+
+    async fn pending_batches(&self) -> RpcResult<Vec<BatchRef>> {
+        Ok(self.state.pending_batches())
+    }
+
+    async fn round_info(&self, round: Round) -> RpcResult<RoundInfo> {
+        let state = self.state.clone();
+        let info = tokio::task::spawn_blocking(move || state.round_info(round))
+            .await
+            .map_err(|err| internal_error(err.to_string()))?;
+        info.map_err(|err| internal_error(err.to_string()))
+    }
+
+    async fn commit_decision(&self, round: Round) -> RpcResult<Option<CommitDecision>> {
+        Ok(self.state.commit_decision(round))
+    }
+
+    async fn commit_log(&self, from_index: u64, to_index: u64) -> RpcResult<Vec<CommitAuditEntry>> {
+        let Some(len) = to_index.checked_sub(from_index) else { return Ok(Vec::new()) };
+        let limit = usize::try_from(len).unwrap_or(usize::MAX).saturating_add(1);
+        let limit = limit.min(self.limits.max_page_size);
+        let state = self.state.clone();
+        let entries = tokio::task::spawn_blocking(move || state.commit_log(from_index, limit))
+            .await
+            .map_err(|err| internal_error(err.to_string()))?;
+        entries.map_err(|err| internal_error(err.to_string()))
+    }
+
+    async fn subscribe_events(
+        &self,
+        pending: PendingSubscriptionSink,
+        filter: Option<ConsensusEventFilter>,
+    ) -> SubscriptionResult {
+        let sink = pending.accept().await?;
+        let events = self.state.subscribe_events(filter.unwrap_or_default());
+        tokio::spawn(pipe_events(sink, events));
+        Ok(())
+    }
+
+    async fn subscribe_checkpoints(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
+        let sink = pending.accept().await?;
+        let checkpoints = self.state.subscribe_checkpoints();
+        tokio::spawn(pipe_checkpoints(sink, checkpoints));
+        Ok(())
+    }
+}
+
+/// Sends the events to the subscriber until it unsubscribes or the consensus state is dropped.
+async fn pipe_events(sink: SubscriptionSink, mut events: ConsensusEvents) {
+    loop {
+        tokio::select! {
+            _ = sink.closed() => break,
+            event = events.recv() => {
+                let Some(event) = event else { break };
+                let Ok(message) = SubscriptionMessage::from_json(&event) else { break };
+                if sink.send(message).await.is_err() {
+                    break
+                }
+            }
+        }
+    }
+    if events.missed() > 0 {
+        debug!(
+            target: "consensus::narwhal",
+            missed = events.missed(),
+            "Subscriber of consensus events fell behind"
+        );
+    }
+}
+
+/// Sends the checkpoints to the subscriber until it unsubscribes or the consensus state is dropped.
+async fn pipe_checkpoints(
+    sink: SubscriptionSink,
+    mut checkpoints: broadcast::Receiver<CertifiedCheckpoint>,
+) {
+    loop {
+        tokio::select! {
+            _ = sink.closed() => break,
+            checkpoint = checkpoints.recv() => {
+                let checkpoint = match checkpoint {
+                    Ok(checkpoint) => checkpoint,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        debug!(
+                            target: "consensus::narwhal",
+                            missed,
+                            "Subscriber of checkpoints fell behind"
+                        );
+                        continue
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let Ok(message) = SubscriptionMessage::from_json(&checkpoint) else { break };
+                if sink.send(message).await.is_err() {
+                    break
+                }
+            }
+        }
+    }
+}
+
+/// Returns an internal JSON-RPC error with the given message.
+fn internal_error(message: String) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, message, None::<()>)
+}
+
+/// Administrative `narwhal_` RPC methods.
+#[cfg_attr(not(feature = "client"), rpc(server, namespace = "narwhal"))]
+#[cfg_attr(feature = "client", rpc(server, client, namespace = "narwhal"))]
+pub trait NarwhalAdminApi {
+    /// Adds directives for consensus targets to the log filter of the node, e.g.
+    /// `consensus::narwhal=debug`, replacing the directives of previous calls.
+    ///
+    /// The filter is reset after `duration` seconds, if given, and empty directives reset it
+    /// immediately.
+    #[method(name = "setLogFilter")]
+    async fn set_log_filter(&self, directives: String, duration: Option<u64>) -> RpcResult<()>;
+}
+
+/// Implementation of the [`NarwhalAdminApiServer`].
+#[derive(Debug, Clone)]
+pub struct NarwhalAdmin {
+    filter: LogFilterHandle,
+    /// Incremented by every change of the filter, so that a pending reset doesn't undo a later
+    /// change.
+    generation: Arc<AtomicU64>,
+}
+
+impl NarwhalAdmin {
+    /// Creates the API that changes the filters of the given handle.
+    pub fn new(filter: LogFilterHandle) -> Self {
+        Self { filter, generation: Arc::new(AtomicU64::new(0)) }
+    }
+}
+
+#[async_trait]
+impl NarwhalAdminApiServer for NarwhalAdmin {
+    async fn set_log_filter(&self, directives: String, duration: Option<u64>) -> RpcResult<()> {
+        check_directives(&directives)?;
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.filter
+            .set_directives(&directives)
+            .map_err(|err| NarwhalRpcError::InvalidLogFilter { reason: err.to_string() })?;
+        info!(target: "consensus::narwhal", %directives, ?duration, "Changed log filter");
+
+        if let Some(duration) = duration.filter(|_| !directives.is_empty()) {
+            let (filter, current) = (self.filter.clone(), Arc::clone(&self.generation));
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(duration)).await;
+                if current.load(Ordering::SeqCst) == generation && filter.reset().is_ok() {
+                    info!(target: "consensus::narwhal", "Reset log filter");
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Checks that every directive only applies to consensus targets.
+fn check_directives(directives: &str) -> Result<(), NarwhalRpcError> {
+    for directive in directives.split(',').filter(|directive| !directive.is_empty()) {
+        let target = directive.split(['[', '=']).next().unwrap_or_default();
+        let is_consensus = target
+            .strip_prefix(CONSENSUS_LOG_TARGET)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"));
+        if !is_consensus {
+            return Err(NarwhalRpcError::InvalidLogFilter {
+                reason: format!("directive {directive:?} is not limited to consensus targets"),
+            })
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_sol_types::SolEvent;
+    use reth_db::{
+        models::{StoredBlockBodyIndices, StoredConsensusMetadata},
+        tables,
+        transaction::DbTxMut,
+    };
+    use reth_narwhal_consensus::{
+        commit_log::CommitOutcome,
+        committee::StaticCommitteeProvider,
+        committee_history::CommitteeHistory,
+        dag_store::MemoryDagStore,
+        messages::{messages_root, MessageSent},
+        predeploys::MESSAGE_QUEUE,
+        rpc::openrpc_document,
+        sequencing::{SkipReason, SkippedTransaction},
+    };
+    use reth_primitives::{Address, Block, Header, Log, Receipt, TransactionSigned, TxType};
+    use reth_provider::{
+        test_utils::{create_test_provider_factory, MockEthProvider},
+        ConsensusMetadataWriter,
+    };
+    use std::sync::RwLock;
+
+    #[test]
+    fn block_attribution() {
+        let factory = create_test_provider_factory();
+        let hash = B256::with_last_byte(5);
+        let metadata = StoredConsensusMetadata {
+            leader: 2,
+            first_round: 7,
+            last_round: 10,
+            certificates: 9,
+            batches: 3,
+            batch_bytes: 1_000,
+            epoch: 1,
+        };
+        let provider = factory.provider_rw().unwrap();
+        provider.tx_ref().put::<tables::CanonicalHeaders>(5, hash).unwrap();
+        provider.insert_consensus_metadata(5, hash, metadata).unwrap();
+        provider.commit().unwrap();
+
+        let blocks = NarwhalBlocks::new(factory);
+        assert_eq!(
+            blocks.attribution(BlockNumberOrTag::Number(5)).unwrap(),
+            Some(BlockAttribution {
+                number: 5,
+                hash,
+                epoch: 1,
+                leader: 2,
+                first_round: 7,
+                last_round: 10,
+                certificates: 9
+            })
+        );
+        assert_eq!(blocks.attribution(BlockNumberOrTag::Number(6)).unwrap(), None);
+    }
+
+    #[test]
+    fn message_proof() {
+        let factory = create_test_provider_factory();
+        let hashes = (1..=3).map(B256::with_last_byte).collect::<Vec<_>>();
+        let receipts = hashes
+            .iter()
+            .map(|hash| Receipt {
+                tx_type: TxType::Eip1559,
+                success: true,
+                logs: vec![Log::new_unchecked(
+                    MESSAGE_QUEUE,
+                    vec![MessageSent::SIGNATURE_HASH, *hash],
+                    Default::default(),
+                )],
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let provider = factory.provider_rw().unwrap();
+        let indices = StoredBlockBodyIndices { first_tx_num: 0, tx_count: 3 };
+        provider.tx_ref().put::<tables::BlockBodyIndices>(5, indices).unwrap();
+        for (number, receipt) in receipts.iter().enumerate() {
+            provider.tx_ref().put::<tables::Receipts>(number as u64, receipt.clone()).unwrap();
+        }
+        provider.commit().unwrap();
+
+        let blocks = NarwhalBlocks::new(factory);
+        let proof = blocks.prove_message(BlockNumberOrTag::Number(5), hashes[1]).unwrap().unwrap();
+        assert_eq!((proof.message_hash, proof.index), (hashes[1], 1));
+        assert_eq!(proof.root, messages_root(&receipts));
+        assert_eq!(blocks.prove_message(BlockNumberOrTag::Number(5), B256::ZERO).unwrap(), None);
+        assert_eq!(blocks.prove_message(BlockNumberOrTag::Number(6), hashes[1]).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn epoch_committee() {
+        let committee = |epoch| Committee { epoch, authorities: Vec::new() };
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = CommitteeHistory::open(dir.path()).unwrap();
+        history.insert(0, 0, committee(0)).unwrap();
+        let state = ConsensusState::new(
+            Arc::new(StaticCommitteeProvider::new(committee(1))),
+            Arc::new(MemoryDagStore::default()),
+        )
+        .with_committee_history(Arc::new(RwLock::new(history)));
+        let module = NarwhalIntrospection::new(state).into_rpc();
+
+        // the past epoch is served from the history, the current one from the committees
+        for epoch in [0, 1] {
+            let result: Option<Committee> =
+                module.call("narwhal_getCommittee", [epoch]).await.unwrap();
+            assert_eq!(result, Some(committee(epoch)));
+        }
+        let result: Option<Committee> = module.call("narwhal_getCommittee", [2]).await.unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[tokio::test]
+    async fn commit_decision() {
+        let committee = Committee { epoch: 0, authorities: Vec::new() };
+        let state = ConsensusState::new(
+            Arc::new(StaticCommitteeProvider::new(committee)),
+            Arc::new(MemoryDagStore::default()),
+        );
+        let decision = CommitDecision {
+            round: 1042,
+            leader: 3,
+            outcome: CommitOutcome::InsufficientSupport { support: 2, threshold: 3 },
+        };
+        state.record_decision(&decision);
+        let module = NarwhalIntrospection::new(state).into_rpc();
+
+        let result: Option<CommitDecision> =
+            module.call("narwhal_getCommitDecision", [1042]).await.unwrap();
+        assert_eq!(result, Some(decision));
+        let result: Option<CommitDecision> =
+            module.call("narwhal_getCommitDecision", [1044]).await.unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn transaction_status() {
+        let provider = MockEthProvider::default();
+        let included = TransactionSigned::default();
+        let block_hash = B256::with_last_byte(3);
+        let block = Block {
+            header: Header { number: 3, ..Default::default() },
+            body: vec![included.clone()],
+            ..Default::default()
+        };
+        provider.add_block(block_hash, block);
+
+        let dropped = DroppedTransactions::default();
+        let skipped = |hash| SkippedTransaction {
+            hash,
+            sender: Address::ZERO,
+            nonce: 1,
+            reason: SkipReason::NonceGap { expected: 0 },
+        };
+        let (unknown, resubmitted) = (B256::with_last_byte(1), B256::with_last_byte(2));
+        dropped.insert(4, [skipped(resubmitted), skipped(included.hash())]);
+        let transactions = NarwhalTransactions::new(provider, dropped);
+
+        // a dropped transaction that was included later is reported as included
+        assert_eq!(
+            transactions.status(included.hash()).unwrap(),
+            Some(TransactionStatus::Included { block_number: 3, block_hash })
+        );
+        assert_eq!(
+            transactions.status(resubmitted).unwrap(),
+            Some(TransactionStatus::Dropped {
+                block_number: 4,
+                reason: "nonce gap, next nonce is 0".to_string()
+            })
+        );
+        assert_eq!(transactions.status(unknown).unwrap(), None);
+    }
+
+    #[test]
+    fn methods_are_documented() {
+        let document = openrpc_document();
+        let documented = document["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|method| method["name"].as_str().unwrap())
+            .collect::<Vec<_>>();
+
+        let committee = Committee { epoch: 0, authorities: Vec::new() };
+        let state = ConsensusState::new(
+            Arc::new(StaticCommitteeProvider::new(committee)),
+            Arc::new(MemoryDagStore::default()),
+        );
+        let mut module = NarwhalIntrospection::new(state).into_rpc();
+        module.merge(NarwhalBlocks::new(create_test_provider_factory()).into_rpc()).unwrap();
+        let transactions =
+            NarwhalTransactions::new(MockEthProvider::default(), DroppedTransactions::default());
+        module.merge(transactions.into_rpc()).unwrap();
+        for method in module.method_names() {
+            assert!(documented.contains(&method), "{method} is missing in the OpenRPC document");
+        }
+        // the admin methods need the log filter of the node
+        assert!(documented.contains(&"narwhal_setLogFilter"));
+    }
+
+    #[test]
+    fn consensus_directives() {
+        assert!(check_directives("").is_ok());
+        assert!(check_directives("consensus=debug").is_ok());
+        assert!(
+            check_directives("consensus::narwhal=trace,consensus::narwhal[round]=debug").is_ok()
+        );
+
+        for directive in ["debug", "net=trace", "consensusx=debug", "consensus::narwhal=info,rpc"] {
+            assert!(matches!(
+                check_directives(directive),
+                Err(NarwhalRpcError::InvalidLogFilter { .. })
+            ));
+        }
+    }
+}
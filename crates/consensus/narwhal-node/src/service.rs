@@ -0,0 +1,255 @@
+//! Narwhal consensus embedded in another binary.
+//!
+//! [`NarwhalService`] runs the whole pipeline of a narwhal node, from the batches of the workers
+//! to the execution of the committed sub-dags, inside a host application instead of the `reth`
+//! binary. Nothing is read from the command line: the host passes the storage path, the chain,
+//! the committee and its keys, and the transactions of the application, and is called back with
+//! every block the node seals:
+//!
+//! ```ignore
+//! let (transactions, transactions_rx) = tokio::sync::mpsc::channel(1024);
+//! let service = NarwhalService::builder()
+//!     .datadir("/var/lib/appchain")
+//!     .chain_spec(chain_spec)
+//!     .committee(committee, keys)
+//!     .transactions(transactions_rx)
+//!     .on_block(|block| println!("sealed block {}", block.number))
+//!     .launch(task_executor)
+//!     .await?;
+//! transactions.send(raw_transaction).await?;
+//! service.wait().await?;
+//! ```
+
+use crate::{
+    install_narwhal_rpc, install_recent_receipts, install_transaction_status, NarwhalLaunchHook,
+    NarwhalNode,
+};
+use reth_chainspec::ChainSpec;
+use reth_db::init_db;
+use reth_narwhal_consensus::{
+    committee::Committee,
+    dag_store::DatabaseDagStore,
+    epoch::{CommitteeSource, FileCommitteeSource},
+    gc::DEFAULT_GC_DEPTH,
+    rpc::ConsensusState,
+    signature::BlsSecretKey,
+    types::Round,
+    NarwhalConfig, NarwhalEvents,
+};
+use reth_node_builder::{DefaultNodeLauncher, NodeBuilder, NodeConfig};
+use reth_node_core::{args::DatadirArgs, exit::NodeExitFuture};
+use reth_primitives::{Bytes, SealedBlockWithSenders};
+use reth_provider::CanonStateSubscriptions;
+use reth_tasks::TaskExecutor;
+use reth_tracing::tracing::warn;
+use std::{fmt, path::PathBuf, sync::Arc};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+
+/// A callback for every block the node seals.
+type OnBlock = Box<dyn FnMut(&SealedBlockWithSenders) + Send>;
+
+/// A narwhal node running inside a host application, see the [module docs](self).
+#[derive(Debug)]
+pub struct NarwhalService {
+    state: ConsensusState,
+    events: NarwhalEvents,
+    exit: NodeExitFuture,
+}
+
+impl NarwhalService {
+    /// Returns a builder of the service.
+    pub fn builder() -> NarwhalServiceBuilder {
+        NarwhalServiceBuilder::default()
+    }
+
+    /// Returns the state of the consensus, e.g. the current round and the last commits.
+    pub fn consensus_state(&self) -> ConsensusState {
+        self.state.clone()
+    }
+
+    /// Returns the events of the consensus.
+    pub fn events(&self) -> NarwhalEvents {
+        self.events.clone()
+    }
+
+    /// Waits until the node exits.
+    pub async fn wait(self) -> eyre::Result<()> {
+        self.exit.await
+    }
+}
+
+/// A builder of a [`NarwhalService`].
+#[derive(Default)]
+pub struct NarwhalServiceBuilder {
+    datadir: Option<PathBuf>,
+    chain_spec: Option<Arc<ChainSpec>>,
+    node_config: Option<NodeConfig>,
+    committee: Option<(Committee, Vec<BlsSecretKey>)>,
+    committee_source: Option<Arc<dyn CommitteeSource>>,
+    config: NarwhalConfig,
+    gc_depth: Option<Round>,
+    transactions: Option<mpsc::Receiver<Bytes>>,
+    on_block: Option<OnBlock>,
+}
+
+impl NarwhalServiceBuilder {
+    /// Sets the directory of the database and all other files of the node.
+    pub fn datadir(mut self, datadir: impl Into<PathBuf>) -> Self {
+        self.datadir = Some(datadir.into());
+        self
+    }
+
+    /// Sets the chain, whose genesis must have a narwhal section.
+    pub fn chain_spec(mut self, chain_spec: Arc<ChainSpec>) -> Self {
+        self.chain_spec = Some(chain_spec);
+        self
+    }
+
+    /// Sets the configuration of the node's network and RPC servers, by default the defaults of
+    /// the `reth` binary.
+    ///
+    /// Its chain and datadir are replaced by the ones of the builder.
+    pub fn node_config(mut self, node_config: NodeConfig) -> Self {
+        self.node_config = Some(node_config);
+        self
+    }
+
+    /// Sets the committee of the first epoch and the keys of its authorities, which the node all
+    /// runs itself.
+    ///
+    /// The first key is the authority of this node.
+    pub fn committee(mut self, committee: Committee, keys: Vec<BlsSecretKey>) -> Self {
+        self.committee = Some((committee, keys));
+        self
+    }
+
+    /// Sets the source of the committees of later epochs.
+    ///
+    /// Defaults to the [`FileCommitteeSource`] of `narwhal/epoch-change.toml` in the datadir.
+    pub fn committee_source(mut self, source: Arc<dyn CommitteeSource>) -> Self {
+        self.committee_source = Some(source);
+        self
+    }
+
+    /// Sets the configuration of the consensus.
+    pub fn config(mut self, config: NarwhalConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sets the number of rounds below the last commit that are kept, [`DEFAULT_GC_DEPTH`] by
+    /// default.
+    pub const fn gc_depth(mut self, gc_depth: Round) -> Self {
+        self.gc_depth = Some(gc_depth);
+        self
+    }
+
+    /// Sequences the signed transactions of the application, next to the transactions the node's
+    /// pool receives over RPC and from peers.
+    ///
+    /// The transactions are not validated before they are sequenced, transactions that fail at
+    /// execution are skipped.
+    pub fn transactions(mut self, transactions: mpsc::Receiver<Bytes>) -> Self {
+        self.transactions = Some(transactions);
+        self
+    }
+
+    /// Calls `on_block` with every block the node seals, in order.
+    ///
+    /// A callback that blocks for long misses blocks, which is logged.
+    pub fn on_block(
+        mut self,
+        on_block: impl FnMut(&SealedBlockWithSenders) + Send + 'static,
+    ) -> Self {
+        self.on_block = Some(Box::new(on_block));
+        self
+    }
+
+    /// Opens the database and launches the node on the executor.
+    pub async fn launch(self, executor: TaskExecutor) -> eyre::Result<NarwhalService> {
+        let datadir = self.datadir.ok_or_else(|| eyre::eyre!("missing datadir"))?;
+        let chain_spec = self.chain_spec.ok_or_else(|| eyre::eyre!("missing chain spec"))?;
+        let (committee, keys) = self.committee.ok_or_else(|| eyre::eyre!("missing committee"))?;
+
+        let node_config = self
+            .node_config
+            .unwrap_or_default()
+            .with_chain(chain_spec)
+            .with_datadir_args(DatadirArgs { datadir: datadir.into(), ..Default::default() });
+        let data_dir = node_config.datadir();
+        let db = Arc::new(init_db(data_dir.db(), node_config.db.database_args())?);
+        let source = self.committee_source.unwrap_or_else(|| {
+            let path = data_dir.data_dir().join("narwhal").join("epoch-change.toml");
+            Arc::new(FileCommitteeSource::new(path))
+        });
+
+        let node = NarwhalNode::default();
+        let mut hook = NarwhalLaunchHook::new(
+            committee,
+            keys,
+            Arc::new(DatabaseDagStore::new(Arc::clone(&db))),
+            source,
+            self.config,
+            self.gc_depth.unwrap_or(DEFAULT_GC_DEPTH),
+        )
+        .with_sequenced_index(node.sequenced_index());
+        if let Some(transactions) = self.transactions {
+            hook = hook.with_submissions(transactions);
+        }
+        let state = hook.consensus_state();
+        let receipts = hook.recent_receipts();
+        let dropped = hook.dropped_transactions();
+        let events = hook.events();
+
+        let launcher = DefaultNodeLauncher::new(executor.clone(), data_dir).with_stage_hook(hook);
+        let rpc_state = state.clone();
+        let handle = NodeBuilder::new(node_config)
+            .with_database(db)
+            .with_launch_context(executor.clone())
+            .node(node)
+            .extend_rpc_modules(move |mut ctx| {
+                install_narwhal_rpc(&mut ctx, rpc_state)?;
+                install_recent_receipts(&mut ctx, receipts)?;
+                install_transaction_status(&mut ctx, dropped)
+            })
+            .launch_with(launcher)
+            .await?;
+
+        if let Some(mut on_block) = self.on_block {
+            let mut notifications = handle.node.provider.subscribe_to_canonical_state();
+            executor.spawn(async move {
+                loop {
+                    match notifications.recv().await {
+                        Ok(notification) => {
+                            for block in notification.committed().blocks_iter() {
+                                on_block(block)
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!(
+                                target: "consensus::narwhal",
+                                skipped,
+                                "Block callback fell behind"
+                            );
+                        }
+                        Err(RecvError::Closed) => return,
+                    }
+                }
+            });
+        }
+
+        Ok(NarwhalService { state, events, exit: handle.node_exit_future })
+    }
+}
+
+impl fmt::Debug for NarwhalServiceBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NarwhalServiceBuilder")
+            .field("datadir", &self.datadir)
+            .field("chain_spec", &self.chain_spec)
+            .field("committee", &self.committee.as_ref().map(|(committee, _)| committee))
+            .field("config", &self.config)
+            .field("gc_depth", &self.gc_depth)
+            .finish_non_exhaustive()
+    }
+}
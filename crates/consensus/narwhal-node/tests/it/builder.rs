@@ -0,0 +1,13 @@
+//! Node builder setup tests.
+
+use reth_db::test_utils::create_test_rw_db;
+use reth_node_builder::{NodeBuilder, NodeConfig};
+use reth_node_narwhal::NarwhalNode;
+
+#[test]
+fn test_basic_setup() {
+    let config = NodeConfig::test();
+    let db = create_test_rw_db();
+    let _builder =
+        NodeBuilder::new(config).with_database(db).node(NarwhalNode::default()).check_launch();
+}
@@ -0,0 +1,239 @@
+//! The Ethereum JSON-RPC of a node that follows the chain of a narwhal committee.
+//!
+//! A committee of four validators runs in dev mode, and a follower without a committee imports
+//! its blocks through the engine API, like a node that is driven by a consensus client. The same
+//! transactions are sent to the committee and to a single-authority dev node, and the follower
+//! must serve the same responses as the dev node, up to the fields that depend on the blocks the
+//! transactions landed in.
+
+use crate::utils::{http_client, launch, node_config, NarwhalTestNode};
+use jsonrpsee::{
+    core::{client::ClientT, params::ArrayParams},
+    http_client::HttpClient,
+    rpc_params,
+};
+use reth_chainspec::DEV;
+use reth_primitives::{
+    address, b256, hex, sign_message, Address, BlockNumber, Bytes, Transaction, TransactionSigned,
+    TxEip1559, TxKind, B256, U256,
+};
+use reth_provider::{BlockHashReader, BlockNumReader, BlockReader};
+use reth_rpc_types::engine::PayloadStatus;
+use reth_rpc_types_compat::engine::payload::block_to_payload_v3;
+use reth_tasks::TaskManager;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// The key of the first funded account of the dev chain.
+const DEV_KEY: B256 = b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+
+/// The first funded account of the dev chain.
+const DEV_ACCOUNT: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+/// The recipient of the transfer.
+const RECIPIENT: Address = Address::with_last_byte(0x42);
+
+/// Init code that emits a log with the topic `1` and deploys a contract that returns `42`.
+const INIT_CODE: [u8; 29] = hex!("600160006000a1600a6013600039600a6000f3602a60005260206000f3");
+
+/// The fields of transactions, receipts and logs that depend on the block the transaction
+/// landed in, and on the base fee of the block.
+const BLOCK_FIELDS: [&str; 8] = [
+    "blockHash",
+    "blockNumber",
+    "blockTimestamp",
+    "transactionIndex",
+    "logIndex",
+    "cumulativeGasUsed",
+    "effectiveGasPrice",
+    "gasPrice",
+];
+
+/// How long the committee and the dev node may take to include the transactions.
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Signs an EIP-1559 transaction of the dev account, and returns its network encoding.
+fn sign(nonce: u64, to: TxKind, value: U256, input: Bytes, gas_limit: u64) -> Bytes {
+    let transaction = Transaction::Eip1559(TxEip1559 {
+        chain_id: DEV.chain.id(),
+        nonce,
+        gas_limit,
+        max_fee_per_gas: 20_000_000_000,
+        max_priority_fee_per_gas: 1_000_000_000,
+        to,
+        value,
+        input,
+        ..Default::default()
+    });
+    let signature = sign_message(DEV_KEY, transaction.signature_hash()).unwrap();
+    TransactionSigned::from_transaction_and_signature(transaction, signature).envelope_encoded()
+}
+
+fn params(values: Vec<Value>) -> ArrayParams {
+    let mut params = ArrayParams::new();
+    for value in values {
+        params.insert(value).expect("json values serialize");
+    }
+    params
+}
+
+/// Removes the given fields from every object of the value.
+fn without(mut value: Value, fields: &[&str]) -> Value {
+    fn strip(value: &mut Value, fields: &[&str]) {
+        match value {
+            Value::Object(object) => {
+                for field in fields {
+                    object.remove(*field);
+                }
+                object.values_mut().for_each(|value| strip(value, fields));
+            }
+            Value::Array(values) => values.iter_mut().for_each(|value| strip(value, fields)),
+            _ => {}
+        }
+    }
+    strip(&mut value, fields);
+    value
+}
+
+/// Polls the receipt of a transaction until the node included it.
+async fn wait_for_receipt(client: &HttpClient, hash: B256) -> Value {
+    tokio::time::timeout(INCLUSION_TIMEOUT, async {
+        loop {
+            let receipt: Value =
+                client.request("eth_getTransactionReceipt", rpc_params![hash]).await.unwrap();
+            if !receipt.is_null() {
+                return receipt
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("transaction included in time")
+}
+
+/// Imports the blocks of the committee up to the given number into the follower, with a new
+/// payload and a forkchoice update per block.
+async fn follow(
+    committee: &NarwhalTestNode,
+    follower: &NarwhalTestNode,
+    head: BlockNumber,
+) -> eyre::Result<()> {
+    for number in 1..=head {
+        let block = committee.inner.provider.block_by_number(number)?.expect("block exists");
+        let block = block.seal_slow();
+        let hash = block.hash();
+        let parent_beacon_block_root =
+            block.parent_beacon_block_root.expect("blocks of the dev chain are cancun blocks");
+        let versioned_hashes = block.blob_versioned_hashes_iter().copied().collect::<Vec<_>>();
+        let status: PayloadStatus = follower
+            .engine_api
+            .engine_api_client
+            .request(
+                "engine_newPayloadV3",
+                rpc_params![block_to_payload_v3(block), versioned_hashes, parent_beacon_block_root],
+            )
+            .await?;
+        assert!(status.status.is_valid(), "block {number} rejected: {status:?}");
+        follower.engine_api.update_forkchoice(hash, hash).await?;
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn follower_serves_the_chain_of_the_committee() -> eyre::Result<()> {
+    reth_tracing::init_test_tracing();
+    let tasks = TaskManager::current();
+    let exec = tasks.executor();
+
+    let committee = launch(exec.clone(), node_config(Some(4))).await?;
+    let dev = launch(exec.clone(), node_config(Some(1))).await?;
+    let follower = launch(exec, node_config(None)).await?;
+    let (committee_rpc, dev_rpc, follower_rpc) =
+        (http_client(&committee), http_client(&dev), http_client(&follower));
+
+    // the follower is notified of every block it imports
+    let block_filter: Value = follower_rpc.request("eth_newBlockFilter", rpc_params![]).await?;
+
+    let transactions = [
+        sign(0, TxKind::Create, U256::ZERO, Bytes::from_static(&INIT_CODE), 100_000),
+        sign(1, TxKind::Call(RECIPIENT), U256::from(10u64.pow(18)), Bytes::new(), 21_000),
+    ];
+    let mut hashes = Vec::new();
+    for transaction in &transactions {
+        let hash: B256 =
+            committee_rpc.request("eth_sendRawTransaction", rpc_params![transaction]).await?;
+        let dev_hash: B256 =
+            dev_rpc.request("eth_sendRawTransaction", rpc_params![transaction]).await?;
+        assert_eq!(hash, dev_hash);
+        hashes.push(hash);
+    }
+    let mut receipts = Vec::new();
+    for hash in &hashes {
+        receipts.push(wait_for_receipt(&committee_rpc, *hash).await);
+        wait_for_receipt(&dev_rpc, *hash).await;
+    }
+    let contract: Address = serde_json::from_value(receipts[0]["contractAddress"].clone())?;
+
+    let head = committee.inner.provider.best_block_number()?;
+    follow(&committee, &follower, head).await?;
+
+    // the follower serves the blocks of the committee
+    let number: U256 = follower_rpc.request("eth_blockNumber", rpc_params![]).await?;
+    assert_eq!(number, U256::from(head));
+    for number in 0..=head {
+        let params = || rpc_params![format!("{number:#x}"), true];
+        let block: Value = follower_rpc.request("eth_getBlockByNumber", params()).await?;
+        let expected: Value = committee_rpc.request("eth_getBlockByNumber", params()).await?;
+        assert_eq!(block, expected, "block {number}");
+    }
+    let imported: Vec<B256> =
+        follower_rpc.request("eth_getFilterChanges", rpc_params![block_filter]).await?;
+    let expected = (1..=head)
+        .map(|number| Ok(committee.inner.provider.block_hash(number)?.expect("block exists")))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    assert_eq!(imported, expected);
+
+    // and the same state, transactions, receipts and logs as the dev node
+    let calls = [
+        ("eth_chainId", vec![]),
+        ("eth_getBalance", vec![json!(RECIPIENT), json!("latest")]),
+        ("eth_getTransactionCount", vec![json!(DEV_ACCOUNT), json!("latest")]),
+        ("eth_getCode", vec![json!(contract), json!("latest")]),
+        ("eth_call", vec![json!({ "to": contract }), json!("latest")]),
+    ];
+    for (method, values) in calls {
+        let response: Value = follower_rpc.request(method, params(values.clone())).await?;
+        let expected: Value = dev_rpc.request(method, params(values)).await?;
+        assert_eq!(response, expected, "{method}");
+    }
+    let call: Bytes =
+        follower_rpc.request("eth_call", rpc_params![json!({ "to": contract }), "latest"]).await?;
+    assert_eq!(call[..], B256::with_last_byte(42)[..]);
+    for (hash, receipt) in hashes.iter().zip(&receipts) {
+        for method in ["eth_getTransactionByHash", "eth_getTransactionReceipt"] {
+            let response: Value = follower_rpc.request(method, rpc_params![hash]).await?;
+            let expected: Value = dev_rpc.request(method, rpc_params![hash]).await?;
+            assert_eq!(without(response, &BLOCK_FIELDS), without(expected, &BLOCK_FIELDS));
+        }
+        let response: Value =
+            follower_rpc.request("eth_getTransactionReceipt", rpc_params![hash]).await?;
+        assert_eq!(&response, receipt);
+    }
+
+    let filter = json!({ "fromBlock": "earliest", "toBlock": "latest", "address": contract });
+    let logs: Value = follower_rpc.request("eth_getLogs", rpc_params![filter.clone()]).await?;
+    assert_eq!(logs.as_array().map(Vec::len), Some(1));
+    let committee_logs: Value =
+        committee_rpc.request("eth_getLogs", rpc_params![filter.clone()]).await?;
+    assert_eq!(logs, committee_logs);
+    let dev_logs: Value = dev_rpc.request("eth_getLogs", rpc_params![filter.clone()]).await?;
+    assert_eq!(without(logs.clone(), &BLOCK_FIELDS), without(dev_logs, &BLOCK_FIELDS));
+
+    let id: Value = follower_rpc.request("eth_newFilter", rpc_params![filter]).await?;
+    let filter_logs: Value = follower_rpc.request("eth_getFilterLogs", rpc_params![&id]).await?;
+    assert_eq!(filter_logs, logs);
+    let uninstalled: bool = follower_rpc.request("eth_uninstallFilter", rpc_params![id]).await?;
+    assert!(uninstalled);
+
+    Ok(())
+}
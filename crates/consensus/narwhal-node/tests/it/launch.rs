@@ -0,0 +1,38 @@
+//! Committees a node launched with the `NarwhalNodeLauncher` can't run.
+
+use crate::utils::{launch, node_config};
+use reth_narwhal_consensus::dev::DevCommittee;
+use reth_tasks::TaskManager;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn committee_of_several_validators_is_rejected() {
+    let tasks = TaskManager::current();
+    let dir = tempfile::tempdir().unwrap();
+    let committee_file = dir.path().join("committee.json");
+    let committee = DevCommittee::new(4).committee().clone();
+    std::fs::write(&committee_file, serde_json::to_string(&committee).unwrap()).unwrap();
+
+    let mut config = node_config(None);
+    config.narwhal.committee_file = Some(committee_file);
+    let err = launch(tasks.executor(), config).await.err().expect("launch fails");
+    assert!(err.to_string().contains("4 validators"), "{err}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn committee_without_the_key_of_the_node_is_rejected() {
+    let tasks = TaskManager::current();
+    let dir = tempfile::tempdir().unwrap();
+    let committee_file = dir.path().join("committee.json");
+    let committee = DevCommittee::new(1).committee().clone();
+    std::fs::write(&committee_file, serde_json::to_string(&committee).unwrap()).unwrap();
+    let password_file = dir.path().join("password");
+    std::fs::write(&password_file, "password").unwrap();
+
+    // the keystore of the node is created with a new key, which isn't the key of the committee
+    let mut config = node_config(None);
+    config.narwhal.committee_file = Some(committee_file);
+    config.narwhal.keystore = Some(dir.path().join("keystore.json"));
+    config.narwhal.keystore_password_file = Some(password_file);
+    let err = launch(tasks.executor(), config).await.err().expect("launch fails");
+    assert!(err.to_string().contains("missing keys"), "{err}");
+}
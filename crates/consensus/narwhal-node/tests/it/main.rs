@@ -0,0 +1,10 @@
+mod builder;
+mod follower;
+mod launch;
+mod permissioned;
+mod pool;
+mod rpc;
+mod submission;
+mod utils;
+
+const fn main() {}
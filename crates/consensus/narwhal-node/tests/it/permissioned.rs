@@ -0,0 +1,49 @@
+//! A dev node of a permissioned chain, which only sequences the transactions of allowed senders.
+
+use crate::utils::{http_client, included, launch, node_config, transfer};
+use jsonrpsee::{core::client::ClientT, http_client::HttpClient, rpc_params};
+use reth_chainspec::DEV;
+use reth_narwhal_consensus::NARWHAL_GENESIS_KEY;
+use reth_primitives::{address, b256, Address, Bytes, B256};
+use reth_tasks::TaskManager;
+use serde_json::{json, Value};
+
+/// The key of the first funded account of the dev chain, the only allowed sender.
+const ALLOWED_KEY: B256 = b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+
+/// The first funded account of the dev chain.
+const ALLOWED: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+
+/// The key of the second funded account of the dev chain, which is not allowed.
+const DENIED_KEY: B256 = b256!("59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d");
+
+/// Sends a transaction and returns its hash.
+async fn send(client: &HttpClient, transaction: Bytes) -> B256 {
+    client.request("eth_sendRawTransaction", rpc_params![transaction]).await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn only_allowed_senders_are_sequenced() -> eyre::Result<()> {
+    reth_tracing::init_test_tracing();
+    let tasks = TaskManager::current();
+
+    let mut chain_spec = (**DEV).clone();
+    chain_spec.genesis.config.extra_fields.insert(
+        NARWHAL_GENESIS_KEY.to_string(),
+        json!({ "permissioned": { "fromBlock": 1, "genesisSenders": [ALLOWED] } }),
+    );
+    let node = launch(tasks.executor(), node_config(Some(1)).with_chain(chain_spec)).await?;
+    let client = http_client(&node);
+
+    // every block is checked against the allowlist of its parent, which the node keeps in sync
+    let first = included(&client, send(&client, transfer(ALLOWED_KEY, 0)).await).await;
+    let second = included(&client, send(&client, transfer(ALLOWED_KEY, 1)).await).await;
+    assert!(second > first, "the chain halted after block {first}");
+
+    // the batch maker skips the transaction of the sender that isn't allowed
+    let denied = send(&client, transfer(DENIED_KEY, 0)).await;
+    included(&client, send(&client, transfer(ALLOWED_KEY, 2)).await).await;
+    let receipt: Value = client.request("eth_getTransactionReceipt", rpc_params![denied]).await?;
+    assert!(receipt.is_null(), "{receipt}");
+    Ok(())
+}
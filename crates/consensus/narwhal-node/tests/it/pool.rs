@@ -0,0 +1,30 @@
+//! A dev node whose pool shares the sequenced index of its workers.
+
+use crate::utils::{http_client, included, launch_node, node_config, transfer};
+use jsonrpsee::{core::client::ClientT, rpc_params};
+use reth_node_narwhal::NarwhalNode;
+use reth_primitives::{b256, keccak256, B256};
+use reth_tasks::TaskManager;
+
+/// The key of the first funded account of the dev chain.
+const FUNDED_KEY: B256 = b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batched_transaction_is_indexed() -> eyre::Result<()> {
+    reth_tracing::init_test_tracing();
+    let tasks = TaskManager::current();
+    let node = NarwhalNode::default();
+    let sequenced = node.sequenced_index();
+    let node = launch_node(tasks.executor(), node_config(Some(1)), node).await?;
+
+    let transaction = transfer(FUNDED_KEY, 0);
+    let hash = keccak256(&transaction);
+    assert!(!sequenced.contains(&hash));
+    let client = http_client(&node);
+    let _: B256 = client.request("eth_sendRawTransaction", rpc_params![transaction]).await?;
+    included(&client, hash).await;
+
+    // the worker recorded the batched transaction, so the pool rejects it when it is relayed
+    assert!(sequenced.contains(&hash));
+    Ok(())
+}
@@ -0,0 +1,44 @@
+//! The narwhal RPC namespace of a node launched with the `NarwhalNodeLauncher`.
+
+use crate::utils::{http_client, launch, node_config};
+use jsonrpsee::{core::client::ClientT, rpc_params};
+use reth_tasks::TaskManager;
+use serde_json::Value;
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn validator_serves_the_narwhal_namespace() -> eyre::Result<()> {
+    reth_tracing::init_test_tracing();
+    let tasks = TaskManager::current();
+    let exec = tasks.executor();
+
+    let validator = launch(exec.clone(), node_config(Some(4))).await?;
+    let follower = launch(exec, node_config(None)).await?;
+    let (validator_rpc, follower_rpc) = (http_client(&validator), http_client(&follower));
+
+    let committee: Value = validator_rpc.request("narwhal_committee", rpc_params![]).await?;
+    assert_eq!(committee["authorities"].as_array().map(Vec::len), Some(4));
+    tokio::time::timeout(Duration::from_secs(30), async {
+        loop {
+            let round: u64 =
+                validator_rpc.request("narwhal_currentRound", rpc_params![]).await.unwrap();
+            let last: Value =
+                validator_rpc.request("narwhal_lastCommittedSubDag", rpc_params![]).await.unwrap();
+            if round > 0 && !last.is_null() {
+                return
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("consensus advances");
+    let _pending: Vec<Value> =
+        validator_rpc.request("narwhal_pendingBatches", rpc_params![]).await?;
+
+    // a follower has no consensus to report
+    let response: Result<Value, _> =
+        follower_rpc.request("narwhal_currentRound", rpc_params![]).await;
+    assert!(response.is_err());
+
+    Ok(())
+}
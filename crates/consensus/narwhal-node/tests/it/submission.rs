@@ -0,0 +1,58 @@
+//! A dev node that serves the gRPC transaction submission service of its workers.
+
+use crate::utils::{http_client, included, launch, node_config, transfer};
+use http_body_util::{BodyExt, Full};
+use hyper::{body::Bytes, client::conn::http2, header, Request};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use reth_narwhal_client::submission::{
+    encode_transaction, SubmissionStatus, CONTENT_TYPE, SUBMIT_TRANSACTION_PATH,
+};
+use reth_primitives::{b256, keccak256, B256};
+use reth_tasks::TaskManager;
+use std::net::{SocketAddr, TcpListener};
+use tokio::net::TcpStream;
+
+/// The key of the first funded account of the dev chain.
+const FUNDED_KEY: B256 = b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+
+/// Submits a transaction to the `Transactions` service at the address, and returns the status of
+/// the submission.
+async fn submit(addr: SocketAddr, transaction: &[u8]) -> SubmissionStatus {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (mut sender, connection) =
+        http2::handshake(TokioExecutor::new(), TokioIo::new(stream)).await.unwrap();
+    tokio::spawn(connection);
+
+    let request = Request::post(format!("http://{addr}{SUBMIT_TRANSACTION_PATH}"))
+        .header(header::CONTENT_TYPE, CONTENT_TYPE)
+        .header(header::TE, "trailers")
+        .body(Full::new(Bytes::from(encode_transaction(transaction))))
+        .unwrap();
+    let response = sender.send_request(request).await.unwrap();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap();
+    // a response without a message carries the status in its headers
+    let status = body.trailers().unwrap_or(&headers).get("grpc-status").unwrap();
+    SubmissionStatus::parse(status.to_str().unwrap()).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn submitted_transaction_is_sequenced() -> eyre::Result<()> {
+    reth_tracing::init_test_tracing();
+    let tasks = TaskManager::current();
+    let dir = tempfile::tempdir()?;
+
+    let addr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+    let config_file = dir.path().join("narwhal.toml");
+    std::fs::write(&config_file, format!("[submission]\naddr = \"{addr}\"\n"))?;
+    let mut config = node_config(Some(1));
+    config.narwhal.config = Some(config_file);
+    let node = launch(tasks.executor(), config).await?;
+
+    // the transaction skips the pool, the first worker batches it
+    let transaction = transfer(FUNDED_KEY, 0);
+    assert_eq!(submit(addr, &transaction).await, SubmissionStatus::Ok);
+    included(&http_client(&node), keccak256(&transaction)).await;
+    assert_eq!(submit(addr, &[]).await, SubmissionStatus::InvalidArgument);
+    Ok(())
+}
@@ -0,0 +1,108 @@
+//! Launch of narwhal test nodes.
+
+use jsonrpsee::{core::client::ClientT, http_client::HttpClient, rpc_params};
+use reth_chainspec::DEV;
+use reth_e2e_test_utils::{node::NodeTestContext, NodeHelperType};
+use reth_node_builder::{NodeBuilder, NodeConfig, NodeHandle};
+use reth_node_core::args::{DiscoveryArgs, NetworkArgs, RpcServerArgs};
+use reth_node_ethereum::node::EthereumAddOns;
+use reth_node_narwhal::{NarwhalNode, NarwhalNodeLauncher};
+use reth_primitives::{
+    sign_message, Address, Bytes, Transaction, TransactionSigned, TxEip1559, TxKind, B256, U256,
+};
+use reth_tasks::TaskExecutor;
+use serde_json::Value;
+use std::time::Duration;
+
+/// How long the node may take to include a transaction.
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Narwhal Node Helper type
+pub(crate) type NarwhalTestNode = NodeHelperType<NarwhalNode, EthereumAddOns>;
+
+/// The config of a node of the dev chain, a dev node with a committee of the given size or a
+/// follower without a committee.
+pub(crate) fn node_config(committee_size: Option<usize>) -> NodeConfig {
+    let network = NetworkArgs {
+        discovery: DiscoveryArgs { disable_discovery: true, ..DiscoveryArgs::default() },
+        ..NetworkArgs::default()
+    };
+    let mut config = NodeConfig::test()
+        .with_chain(DEV.clone())
+        .with_network(network)
+        .with_unused_ports()
+        .with_rpc(RpcServerArgs::default().with_unused_ports().with_http());
+    if let Some(size) = committee_size {
+        config = config.dev();
+        config.dev.narwhal_committee_size = Some(size);
+    }
+    config
+}
+
+/// Launches a narwhal node, which runs a dev committee in dev mode and follows the chain
+/// otherwise.
+pub(crate) async fn launch(
+    exec: TaskExecutor,
+    config: NodeConfig,
+) -> eyre::Result<NarwhalTestNode> {
+    launch_node(exec, config, NarwhalNode::default()).await
+}
+
+/// Launches the narwhal node with the given node type, sharing its sequenced index with the
+/// workers.
+pub(crate) async fn launch_node(
+    exec: TaskExecutor,
+    config: NodeConfig,
+    node: NarwhalNode,
+) -> eyre::Result<NarwhalTestNode> {
+    let sequenced = node.sequenced_index();
+    let NodeHandle { node, node_exit_future: _ } = NodeBuilder::new(config)
+        .testing_node(exec)
+        .node(node)
+        .launch_with_fn(|builder| {
+            let launcher = NarwhalNodeLauncher::new(
+                builder.task_executor().clone(),
+                builder.config().datadir(),
+            )
+            .with_sequenced_index(sequenced);
+            builder.launch_with(launcher)
+        })
+        .await?;
+    NodeTestContext::new(node).await
+}
+
+pub(crate) fn http_client(node: &NarwhalTestNode) -> HttpClient {
+    node.inner.rpc_server_handles.rpc.http_client().expect("http is enabled")
+}
+
+/// Signs a transfer on the dev chain, and returns its network encoding.
+pub(crate) fn transfer(key: B256, nonce: u64) -> Bytes {
+    let transaction = Transaction::Eip1559(TxEip1559 {
+        chain_id: DEV.chain.id(),
+        nonce,
+        gas_limit: 21_000,
+        max_fee_per_gas: 20_000_000_000,
+        max_priority_fee_per_gas: 1_000_000_000,
+        to: TxKind::Call(Address::with_last_byte(0x42)),
+        value: U256::from(1),
+        ..Default::default()
+    });
+    let signature = sign_message(key, transaction.signature_hash()).unwrap();
+    TransactionSigned::from_transaction_and_signature(transaction, signature).envelope_encoded()
+}
+
+/// Waits for the receipt of a transaction, and returns the number of its block.
+pub(crate) async fn included(client: &HttpClient, hash: B256) -> u64 {
+    tokio::time::timeout(INCLUSION_TIMEOUT, async {
+        loop {
+            let receipt: Value =
+                client.request("eth_getTransactionReceipt", rpc_params![hash]).await.unwrap();
+            if let Some(number) = receipt["blockNumber"].as_str() {
+                return u64::from_str_radix(number.trim_start_matches("0x"), 16).unwrap()
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("transaction included in time")
+}
@@ -0,0 +1,177 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+pragma solidity ^0.8.20;
+
+import {NarwhalMessages} from "./NarwhalMessages.sol";
+
+/// @title NarwhalCheckpoint
+/// @notice Decoding and quorum checks of narwhal checkpoint proofs.
+/// @dev Mirrors `reth_narwhal_verifier::abi`. Verification of the aggregate signature depends on
+///      the signature scheme of the committee and is left to the caller.
+library NarwhalCheckpoint {
+    /// @notice A block finalized by the committee of an epoch.
+    struct Checkpoint {
+        uint64 epoch;
+        uint64 blockNumber;
+        bytes32 blockHash;
+        bytes32 stateRoot;
+    }
+
+    /// @notice Proof that a quorum of the committee of the checkpoint's epoch signed the
+    ///         checkpoint.
+    struct CheckpointProof {
+        Checkpoint checkpoint;
+        /// @dev Bit `i` is set if the authority with index `i` signed.
+        uint256 signers;
+        bytes signature;
+    }
+
+    /// @notice A contiguous range of blocks finalized by the committee of an epoch.
+    struct CheckpointRange {
+        uint64 epoch;
+        uint64 firstBlock;
+        uint64 lastBlock;
+        /// @dev The root of the tree over the hashes of the blocks of the range.
+        bytes32 blocksRoot;
+        /// @dev The state root of the last block of the range.
+        bytes32 stateRoot;
+    }
+
+    /// @notice Proof that a quorum of the committee of the range's epoch signed the range.
+    struct CheckpointRangeProof {
+        CheckpointRange range;
+        /// @dev Bit `i` is set if the authority with index `i` signed.
+        uint256 signers;
+        bytes signature;
+    }
+
+    /// @notice The intent of checkpoints, see `reth_narwhal_verifier::Intent`.
+    uint8 internal constant CHECKPOINT_INTENT = 2;
+    /// @notice The intent of checkpoint ranges, see `reth_narwhal_verifier::Intent`.
+    uint8 internal constant CHECKPOINT_RANGE_INTENT = 3;
+
+    error EpochMismatch(uint64 expected, uint64 got);
+    error UnknownSigner(uint32 index);
+    error InsufficientStake(uint64 stake, uint64 threshold);
+    error InvalidRange(uint64 firstBlock, uint64 lastBlock);
+
+    /// @notice Decodes a proof encoded with `abi.encode(proof)`.
+    function decode(bytes memory encoded) internal pure returns (CheckpointProof memory) {
+        return abi.decode(encoded, (CheckpointProof));
+    }
+
+    /// @notice The digest of a checkpoint.
+    function digest(Checkpoint memory checkpoint) internal pure returns (bytes32) {
+        return keccak256(abi.encode(checkpoint));
+    }
+
+    /// @notice The message signed by the committee of the network with the given chain id and
+    ///         genesis hash, so that proofs of other networks are rejected.
+    function signingMessage(Checkpoint memory checkpoint, uint64 chainId, bytes32 genesisHash)
+        internal
+        pure
+        returns (bytes32)
+    {
+        return keccak256(abi.encode(chainId, genesisHash, CHECKPOINT_INTENT, digest(checkpoint)));
+    }
+
+    /// @notice Checks that the signers of the proof are a quorum of the committee of `epoch`,
+    ///         whose stakes are given by authority index.
+    /// @return The message the signers must have signed on the network with the given chain id
+    ///         and genesis hash.
+    function checkQuorum(
+        CheckpointProof memory proof,
+        uint64 epoch,
+        uint64[] memory stakes,
+        uint64 chainId,
+        bytes32 genesisHash
+    ) internal pure returns (bytes32) {
+        if (proof.checkpoint.epoch != epoch) {
+            revert EpochMismatch(epoch, proof.checkpoint.epoch);
+        }
+        _checkSigners(proof.signers, stakes);
+
+        return signingMessage(proof.checkpoint, chainId, genesisHash);
+    }
+
+    /// @notice Decodes a range proof encoded with `abi.encode(proof)`.
+    function decodeRange(bytes memory encoded) internal pure returns (CheckpointRangeProof memory) {
+        CheckpointRangeProof memory proof = abi.decode(encoded, (CheckpointRangeProof));
+        if (proof.range.lastBlock < proof.range.firstBlock) {
+            revert InvalidRange(proof.range.firstBlock, proof.range.lastBlock);
+        }
+        return proof;
+    }
+
+    /// @notice The digest of a range.
+    function digest(CheckpointRange memory range) internal pure returns (bytes32) {
+        return keccak256(abi.encode(range));
+    }
+
+    /// @notice The message signed by the committee of the network with the given chain id and
+    ///         genesis hash, so that proofs of other networks are rejected.
+    function signingMessage(CheckpointRange memory range, uint64 chainId, bytes32 genesisHash)
+        internal
+        pure
+        returns (bytes32)
+    {
+        return keccak256(abi.encode(chainId, genesisHash, CHECKPOINT_RANGE_INTENT, digest(range)));
+    }
+
+    /// @notice Checks that the signers of the range proof are a quorum of the committee of
+    ///         `epoch`, whose stakes are given by authority index.
+    /// @return The message the signers must have signed on the network with the given chain id
+    ///         and genesis hash.
+    function checkQuorum(
+        CheckpointRangeProof memory proof,
+        uint64 epoch,
+        uint64[] memory stakes,
+        uint64 chainId,
+        bytes32 genesisHash
+    ) internal pure returns (bytes32) {
+        if (proof.range.epoch != epoch) {
+            revert EpochMismatch(epoch, proof.range.epoch);
+        }
+        _checkSigners(proof.signers, stakes);
+
+        return signingMessage(proof.range, chainId, genesisHash);
+    }
+
+    /// @notice Returns true if the block with the given number and hash is part of a verified
+    ///         range, given the sibling hashes on its path to the blocks root.
+    function verifyBlock(
+        CheckpointRange memory range,
+        uint64 blockNumber,
+        bytes32 blockHash,
+        bytes32[] memory proof
+    ) internal pure returns (bool) {
+        if (blockNumber < range.firstBlock || blockNumber > range.lastBlock) {
+            return false;
+        }
+        return NarwhalMessages.verify(
+            range.blocksRoot, blockHash, blockNumber - range.firstBlock, proof
+        );
+    }
+
+    /// @dev Reverts unless the signers are members of the committee with a quorum of its stake.
+    function _checkSigners(uint256 signers, uint64[] memory stakes) private pure {
+        for (uint256 index = stakes.length; index < 256; index++) {
+            if (signers & (1 << index) != 0) {
+                revert UnknownSigner(uint32(index));
+            }
+        }
+
+        uint64 total;
+        uint64 stake;
+        for (uint256 index = 0; index < stakes.length; index++) {
+            total += stakes[index];
+            if (signers & (1 << index) != 0) {
+                stake += stakes[index];
+            }
+        }
+
+        uint64 threshold = 2 * total / 3 + 1;
+        if (stake < threshold) {
+            revert InsufficientStake(stake, threshold);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+pragma solidity ^0.8.20;
+
+/// @title NarwhalMessages
+/// @notice Verification of messages sent from a narwhal chain.
+/// @dev Mirrors `reth_narwhal_verifier::messages`. The root is the message root of a block whose
+///      checkpoint was verified with `NarwhalCheckpoint`.
+library NarwhalMessages {
+    /// @notice Returns true if `messageHash` is the leaf at `index` of the message tree with the
+    ///         given root.
+    function verify(bytes32 root, bytes32 messageHash, uint256 index, bytes32[] memory proof)
+        internal
+        pure
+        returns (bool)
+    {
+        if (proof.length < 256 && index >> proof.length != 0) {
+            return false;
+        }
+
+        bytes32 node = messageHash;
+        for (uint256 level = 0; level < proof.length; level++) {
+            if ((index >> level) & 1 == 0) {
+                node = keccak256(abi.encodePacked(node, proof[level]));
+            } else {
+                node = keccak256(abi.encodePacked(proof[level], node));
+            }
+        }
+        return node == root;
+    }
+}
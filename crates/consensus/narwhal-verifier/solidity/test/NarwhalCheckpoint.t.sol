@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {NarwhalCheckpoint} from "../src/NarwhalCheckpoint.sol";
+
+/// @dev Checks the golden vectors shared with the rust tests of `reth_narwhal_verifier::abi`.
+contract NarwhalCheckpointTest is Test {
+    function test_goldenVectors() public view {
+        string memory json = vm.readFile("../testdata/checkpoint_proofs.json");
+
+        uint256 i = 0;
+        while (vm.keyExistsJson(json, _key(i, "encoded"))) {
+            bytes memory encoded = vm.parseJsonBytes(json, _key(i, "encoded"));
+            NarwhalCheckpoint.CheckpointProof memory proof = NarwhalCheckpoint.decode(encoded);
+
+            assertEq(abi.encode(proof), encoded);
+            bytes32 digest = vm.parseJsonBytes32(json, _key(i, "digest"));
+            assertEq(NarwhalCheckpoint.digest(proof.checkpoint), digest);
+            assertEq(proof.checkpoint.blockHash, vm.parseJsonBytes32(json, _key(i, "blockHash")));
+            assertEq(proof.checkpoint.stateRoot, vm.parseJsonBytes32(json, _key(i, "stateRoot")));
+            assertEq(proof.signature, vm.parseJsonBytes(json, _key(i, "signature")));
+
+            uint256[] memory signers = vm.parseJsonUintArray(json, _key(i, "signers"));
+            uint256 bitmap;
+            for (uint256 j = 0; j < signers.length; j++) {
+                bitmap |= 1 << signers[j];
+            }
+            assertEq(proof.signers, bitmap);
+
+            // the vectors are signed by a committee of four authorities with equal stake
+            uint64[] memory stakes = new uint64[](4);
+            for (uint256 j = 0; j < stakes.length; j++) {
+                stakes[j] = 1;
+            }
+            uint64 chainId = uint64(vm.parseJsonUint(json, _key(i, "chainId")));
+            bytes32 genesisHash = vm.parseJsonBytes32(json, _key(i, "genesisHash"));
+            assertEq(
+                NarwhalCheckpoint.checkQuorum(
+                    proof, proof.checkpoint.epoch, stakes, chainId, genesisHash
+                ),
+                vm.parseJsonBytes32(json, _key(i, "signingMessage"))
+            );
+
+            i++;
+        }
+        assertGt(i, 0);
+    }
+
+    function test_rangeGoldenVectors() public view {
+        string memory json = vm.readFile("../testdata/checkpoint_range_proofs.json");
+
+        uint256 i = 0;
+        while (vm.keyExistsJson(json, _key(i, "encoded"))) {
+            bytes memory encoded = vm.parseJsonBytes(json, _key(i, "encoded"));
+            NarwhalCheckpoint.CheckpointRangeProof memory proof =
+                NarwhalCheckpoint.decodeRange(encoded);
+
+            assertEq(abi.encode(proof), encoded);
+            bytes32 digest = vm.parseJsonBytes32(json, _key(i, "digest"));
+            assertEq(NarwhalCheckpoint.digest(proof.range), digest);
+            assertEq(proof.range.blocksRoot, vm.parseJsonBytes32(json, _key(i, "blocksRoot")));
+            assertEq(proof.range.stateRoot, vm.parseJsonBytes32(json, _key(i, "stateRoot")));
+            assertEq(proof.signature, vm.parseJsonBytes(json, _key(i, "signature")));
+
+            uint64[] memory stakes = new uint64[](4);
+            for (uint256 j = 0; j < stakes.length; j++) {
+                stakes[j] = 1;
+            }
+            uint64 chainId = uint64(vm.parseJsonUint(json, _key(i, "chainId")));
+            bytes32 genesisHash = vm.parseJsonBytes32(json, _key(i, "genesisHash"));
+            assertEq(
+                NarwhalCheckpoint.checkQuorum(
+                    proof, proof.range.epoch, stakes, chainId, genesisHash
+                ),
+                vm.parseJsonBytes32(json, _key(i, "signingMessage"))
+            );
+
+            // every block of the range is proven against the range
+            bytes32[] memory blockHashes = vm.parseJsonBytes32Array(json, _key(i, "blockHashes"));
+            for (uint256 j = 0; j < blockHashes.length; j++) {
+                string memory key =
+                    string.concat(_key(i, "blockProofs"), "[", vm.toString(j), "]");
+                bytes32[] memory blockProof = vm.parseJsonBytes32Array(json, key);
+                uint64 blockNumber = proof.range.firstBlock + uint64(j);
+                assertTrue(
+                    NarwhalCheckpoint.verifyBlock(
+                        proof.range, blockNumber, blockHashes[j], blockProof
+                    )
+                );
+                assertFalse(
+                    NarwhalCheckpoint.verifyBlock(
+                        proof.range, proof.range.lastBlock + 1, blockHashes[j], blockProof
+                    )
+                );
+            }
+
+            i++;
+        }
+        assertGt(i, 0);
+    }
+
+    function _key(uint256 index, string memory field) private pure returns (string memory) {
+        return string.concat("$[", vm.toString(index), "].", field);
+    }
+}
@@ -0,0 +1,464 @@
+//! ABI encoding of checkpoint proofs.
+//!
+//! Checkpoints of a narwhal chain are exported to other chains, e.g. to an L1 bridge contract, as
+//! ABI encoded [`CheckpointProof`]s. The layout is chosen so that a Solidity contract can check a
+//! proof with `abi.decode` and `keccak256` alone:
+//!
+//! - the digest of a checkpoint is `keccak256(abi.encode(checkpoint))`, see [`Checkpoint::digest`],
+//!   and the committee signs its [`signing message`](crate::SigningDomain::signing_message) with
+//!   the [`Checkpoint`](crate::Intent::Checkpoint) intent.
+//! - the signers are encoded as a bitmap over the committee, so every set of signers has exactly
+//!   one encoding without any ordering checks.
+//!
+//! Bridges that follow every block export a [`CheckpointRangeProof`] instead: the committee signs
+//! a contiguous range of blocks of one epoch together with the root of the
+//! [`blocks`](crate::blocks) tree over their hashes. The range is verified with one committee
+//! lookup and one aggregate signature, and each block of the range is then proven with a Merkle
+//! proof, see [`CheckpointRange::verify_block`].
+//!
+//! The Solidity counterpart lives in `solidity/src/NarwhalCheckpoint.sol`. The vectors in
+//! `testdata/checkpoint_proofs.json` and `testdata/checkpoint_range_proofs.json` are checked by
+//! the tests of both, so the two sides can't drift apart.
+
+use crate::{
+    blocks::verify_block_proof, verify_commit_proof, AuthorityIndex, CommitProof, Intent,
+    SignatureScheme, SigningDomain, VerificationError, VerifierCommittee,
+};
+use alloc::vec::Vec;
+use alloy_primitives::{keccak256, BlockNumber, Bytes, B256, U256};
+use alloy_sol_types::{sol, SolValue};
+
+/// The maximum number of authorities whose signatures fit into a [`CheckpointProof`].
+pub const MAX_CHECKPOINT_SIGNERS: usize = 256;
+
+sol! {
+    /// A checkpoint of a narwhal chain, i.e. a block finalized by the committee of an epoch.
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct Checkpoint {
+        /// The epoch of the committee that finalized the block.
+        uint64 epoch;
+        /// The number of the block.
+        uint64 blockNumber;
+        /// The hash of the block.
+        bytes32 blockHash;
+        /// The state root of the block.
+        bytes32 stateRoot;
+    }
+
+    /// Proof that a quorum of the committee of the checkpoint's epoch signed the checkpoint.
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct CheckpointProof {
+        /// The signed checkpoint.
+        Checkpoint checkpoint;
+        /// Bitmap of the signers, bit `i` is set if the authority with index `i` signed.
+        uint256 signers;
+        /// The aggregate signature of all signers over the signing message of the checkpoint.
+        bytes signature;
+    }
+
+    /// A contiguous range of blocks finalized by the committee of an epoch.
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct CheckpointRange {
+        /// The epoch of the committee that finalized the blocks.
+        uint64 epoch;
+        /// The number of the first block of the range.
+        uint64 firstBlock;
+        /// The number of the last block of the range, not before the first block.
+        uint64 lastBlock;
+        /// The root of the tree over the hashes of the blocks of the range, in block order.
+        bytes32 blocksRoot;
+        /// The state root of the last block of the range.
+        bytes32 stateRoot;
+    }
+
+    /// Proof that a quorum of the committee of the range's epoch signed the range.
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct CheckpointRangeProof {
+        /// The signed range.
+        CheckpointRange range;
+        /// Bitmap of the signers, bit `i` is set if the authority with index `i` signed.
+        uint256 signers;
+        /// The aggregate signature of all signers over the signing message of the range.
+        bytes signature;
+    }
+}
+
+/// Returns the bitmap of the given signers, or `None` if an index doesn't fit into the bitmap.
+fn signer_bitmap(signers: impl IntoIterator<Item = AuthorityIndex>) -> Option<U256> {
+    let mut bitmap = U256::ZERO;
+    for index in signers {
+        if index as usize >= MAX_CHECKPOINT_SIGNERS {
+            return None
+        }
+        bitmap.set_bit(index as usize, true);
+    }
+    Some(bitmap)
+}
+
+/// Returns the indices of the signers of a bitmap in ascending order.
+fn bitmap_signers(bitmap: U256) -> Vec<AuthorityIndex> {
+    (0..MAX_CHECKPOINT_SIGNERS)
+        .filter(|index| bitmap.bit(*index))
+        .map(|index| index as AuthorityIndex)
+        .collect()
+}
+
+impl Checkpoint {
+    /// Returns the digest of the checkpoint, `keccak256(abi.encode(checkpoint))`.
+    pub fn digest(&self) -> B256 {
+        keccak256(self.abi_encode())
+    }
+
+    /// Returns the message the committee signs on the network of the given domain.
+    pub fn signing_message(&self, domain: &SigningDomain) -> B256 {
+        domain.signing_message(Intent::Checkpoint, self.digest())
+    }
+}
+
+impl CheckpointProof {
+    /// Creates a new proof for the checkpoint.
+    ///
+    /// Returns `None` if a signer index doesn't fit into the bitmap, see
+    /// [`MAX_CHECKPOINT_SIGNERS`].
+    pub fn new(
+        checkpoint: Checkpoint,
+        signers: impl IntoIterator<Item = AuthorityIndex>,
+        signature: Bytes,
+    ) -> Option<Self> {
+        Some(Self { checkpoint, signers: signer_bitmap(signers)?, signature })
+    }
+
+    /// Returns the indices of the signers in ascending order.
+    pub fn signer_indices(&self) -> Vec<AuthorityIndex> {
+        bitmap_signers(self.signers)
+    }
+
+    /// Decodes a proof from its ABI encoding, `abi.encode(proof)`.
+    ///
+    /// Non-canonical encodings are rejected.
+    pub fn decode(encoded: &[u8]) -> Result<Self, VerificationError> {
+        <Self as SolValue>::abi_decode(encoded, true).map_err(|_| VerificationError::MalformedProof)
+    }
+
+    /// Returns the ABI encoding of the proof, `abi.encode(proof)`.
+    pub fn encode(&self) -> Vec<u8> {
+        self.abi_encode()
+    }
+}
+
+/// Decodes an ABI encoded [`CheckpointProof`] and verifies that it was signed by a quorum of the
+/// given committee on the network of the given domain.
+///
+/// Returns the verified checkpoint.
+pub fn verify_checkpoint_proof<S>(
+    committee: &VerifierCommittee<S::PublicKey>,
+    domain: &SigningDomain,
+    encoded: &[u8],
+) -> Result<Checkpoint, VerificationError>
+where
+    S: SignatureScheme,
+    for<'a> S::Signature: TryFrom<&'a [u8]>,
+{
+    let proof = CheckpointProof::decode(encoded)?;
+    let signature = S::Signature::try_from(proof.signature.as_ref())
+        .map_err(|_| VerificationError::MalformedProof)?;
+    let commit_proof = CommitProof {
+        epoch: proof.checkpoint.epoch,
+        digest: proof.checkpoint.digest(),
+        signers: proof.signer_indices(),
+        signature,
+    };
+    verify_commit_proof::<S>(committee, domain, Intent::Checkpoint, &commit_proof)?;
+    Ok(proof.checkpoint)
+}
+
+impl CheckpointRange {
+    /// Returns the number of blocks of the range.
+    pub const fn num_blocks(&self) -> u64 {
+        self.lastBlock.saturating_sub(self.firstBlock).saturating_add(1)
+    }
+
+    /// Returns `true` if the block with the given number is part of the range.
+    pub const fn contains(&self, number: BlockNumber) -> bool {
+        self.firstBlock <= number && number <= self.lastBlock
+    }
+
+    /// Returns the digest of the range, `keccak256(abi.encode(range))`.
+    pub fn digest(&self) -> B256 {
+        keccak256(self.abi_encode())
+    }
+
+    /// Returns the message the committee signs on the network of the given domain.
+    pub fn signing_message(&self, domain: &SigningDomain) -> B256 {
+        domain.signing_message(Intent::CheckpointRange, self.digest())
+    }
+
+    /// Verifies that the block with the given number and hash is part of the range, given the
+    /// sibling hashes on its path to the [`blocksRoot`](Self::blocksRoot).
+    pub fn verify_block(&self, number: BlockNumber, hash: B256, proof: &[B256]) -> bool {
+        self.contains(number) &&
+            verify_block_proof(self.blocksRoot, hash, number - self.firstBlock, proof)
+    }
+}
+
+impl CheckpointRangeProof {
+    /// Creates a new proof for the range.
+    ///
+    /// Returns `None` if a signer index doesn't fit into the bitmap, see
+    /// [`MAX_CHECKPOINT_SIGNERS`].
+    pub fn new(
+        range: CheckpointRange,
+        signers: impl IntoIterator<Item = AuthorityIndex>,
+        signature: Bytes,
+    ) -> Option<Self> {
+        Some(Self { range, signers: signer_bitmap(signers)?, signature })
+    }
+
+    /// Returns the indices of the signers in ascending order.
+    pub fn signer_indices(&self) -> Vec<AuthorityIndex> {
+        bitmap_signers(self.signers)
+    }
+
+    /// Decodes a proof from its ABI encoding, `abi.encode(proof)`.
+    ///
+    /// Non-canonical encodings and ranges whose last block precedes their first block are
+    /// rejected.
+    pub fn decode(encoded: &[u8]) -> Result<Self, VerificationError> {
+        let proof = <Self as SolValue>::abi_decode(encoded, true)
+            .map_err(|_| VerificationError::MalformedProof)?;
+        if proof.range.lastBlock < proof.range.firstBlock {
+            return Err(VerificationError::MalformedProof)
+        }
+        Ok(proof)
+    }
+
+    /// Returns the ABI encoding of the proof, `abi.encode(proof)`.
+    pub fn encode(&self) -> Vec<u8> {
+        self.abi_encode()
+    }
+}
+
+/// Decodes an ABI encoded [`CheckpointRangeProof`] and verifies that it was signed by a quorum of
+/// the given committee on the network of the given domain.
+///
+/// Returns the verified range, whose blocks can then be proven with
+/// [`CheckpointRange::verify_block`].
+pub fn verify_checkpoint_range_proof<S>(
+    committee: &VerifierCommittee<S::PublicKey>,
+    domain: &SigningDomain,
+    encoded: &[u8],
+) -> Result<CheckpointRange, VerificationError>
+where
+    S: SignatureScheme,
+    for<'a> S::Signature: TryFrom<&'a [u8]>,
+{
+    let proof = CheckpointRangeProof::decode(encoded)?;
+    let signature = S::Signature::try_from(proof.signature.as_ref())
+        .map_err(|_| VerificationError::MalformedProof)?;
+    let commit_proof = CommitProof {
+        epoch: proof.range.epoch,
+        digest: proof.range.digest(),
+        signers: proof.signer_indices(),
+        signature,
+    };
+    verify_commit_proof::<S>(committee, domain, Intent::CheckpointRange, &commit_proof)?;
+    Ok(proof.range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VerifierAuthority;
+    use alloc::vec;
+    use alloy_primitives::hex;
+    use serde_json::Value;
+
+    /// A scheme whose "signature" is the concatenation of the signers' keys and the message.
+    struct ConcatScheme;
+
+    impl SignatureScheme for ConcatScheme {
+        type PublicKey = u8;
+        type Signature = Vec<u8>;
+
+        fn verify_aggregate(
+            signers: &[&Self::PublicKey],
+            message: &[u8],
+            signature: &Self::Signature,
+        ) -> bool {
+            let expected = signers.iter().map(|key| **key).chain(message.iter().copied());
+            expected.eq(signature.iter().copied())
+        }
+    }
+
+    fn committee(epoch: u64) -> VerifierCommittee<u8> {
+        VerifierCommittee::new(
+            epoch,
+            (0..4).map(|key| VerifierAuthority { public_key: key, stake: 1 }).collect(),
+        )
+    }
+
+    const DOMAIN: SigningDomain = SigningDomain::new(1337, B256::ZERO);
+
+    fn bytes(value: &Value) -> Vec<u8> {
+        hex::decode(value.as_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn golden_vectors() {
+        let vectors: Value =
+            serde_json::from_str(include_str!("../testdata/checkpoint_proofs.json")).unwrap();
+        for vector in vectors.as_array().unwrap() {
+            let checkpoint = Checkpoint {
+                epoch: vector["epoch"].as_u64().unwrap(),
+                blockNumber: vector["blockNumber"].as_u64().unwrap(),
+                blockHash: B256::from_slice(&bytes(&vector["blockHash"])),
+                stateRoot: B256::from_slice(&bytes(&vector["stateRoot"])),
+            };
+            let signers = vector["signers"].as_array().unwrap().iter();
+            let proof = CheckpointProof::new(
+                checkpoint.clone(),
+                signers.map(|index| index.as_u64().unwrap() as AuthorityIndex),
+                bytes(&vector["signature"]).into(),
+            )
+            .unwrap();
+
+            let domain = SigningDomain::new(
+                vector["chainId"].as_u64().unwrap(),
+                B256::from_slice(&bytes(&vector["genesisHash"])),
+            );
+
+            assert_eq!(checkpoint.digest().as_slice(), bytes(&vector["digest"]));
+            assert_eq!(
+                checkpoint.signing_message(&domain).as_slice(),
+                bytes(&vector["signingMessage"])
+            );
+            assert_eq!(proof.encode(), bytes(&vector["encoded"]));
+            assert_eq!(CheckpointProof::decode(&bytes(&vector["encoded"])), Ok(proof.clone()));
+            assert_eq!(
+                verify_checkpoint_proof::<ConcatScheme>(
+                    &committee(checkpoint.epoch),
+                    &domain,
+                    &proof.encode()
+                ),
+                Ok(checkpoint.clone())
+            );
+            // the proof can't be replayed on another network
+            assert_eq!(
+                verify_checkpoint_proof::<ConcatScheme>(
+                    &committee(checkpoint.epoch),
+                    &SigningDomain::new(domain.chain_id.wrapping_add(1), domain.genesis_hash),
+                    &proof.encode()
+                ),
+                Err(VerificationError::InvalidSignature)
+            );
+        }
+    }
+
+    fn hashes(value: &Value) -> Vec<B256> {
+        value.as_array().unwrap().iter().map(|hash| B256::from_slice(&bytes(hash))).collect()
+    }
+
+    #[test]
+    fn range_golden_vectors() {
+        let vectors: Value =
+            serde_json::from_str(include_str!("../testdata/checkpoint_range_proofs.json")).unwrap();
+        for vector in vectors.as_array().unwrap() {
+            let block_hashes = hashes(&vector["blockHashes"]);
+            let range = CheckpointRange {
+                epoch: vector["epoch"].as_u64().unwrap(),
+                firstBlock: vector["firstBlock"].as_u64().unwrap(),
+                lastBlock: vector["lastBlock"].as_u64().unwrap(),
+                blocksRoot: crate::blocks::blocks_root(&block_hashes),
+                stateRoot: B256::from_slice(&bytes(&vector["stateRoot"])),
+            };
+            let signers = vector["signers"].as_array().unwrap().iter();
+            let proof = CheckpointRangeProof::new(
+                range.clone(),
+                signers.map(|index| index.as_u64().unwrap() as AuthorityIndex),
+                bytes(&vector["signature"]).into(),
+            )
+            .unwrap();
+
+            let domain = SigningDomain::new(
+                vector["chainId"].as_u64().unwrap(),
+                B256::from_slice(&bytes(&vector["genesisHash"])),
+            );
+
+            assert_eq!(range.num_blocks(), block_hashes.len() as u64);
+            assert_eq!(range.blocksRoot.as_slice(), bytes(&vector["blocksRoot"]));
+            assert_eq!(range.digest().as_slice(), bytes(&vector["digest"]));
+            assert_eq!(range.signing_message(&domain).as_slice(), bytes(&vector["signingMessage"]));
+            assert_eq!(proof.encode(), bytes(&vector["encoded"]));
+            assert_eq!(CheckpointRangeProof::decode(&bytes(&vector["encoded"])), Ok(proof.clone()));
+            let verified = verify_checkpoint_range_proof::<ConcatScheme>(
+                &committee(range.epoch),
+                &domain,
+                &proof.encode(),
+            );
+            assert_eq!(verified, Ok(range.clone()));
+
+            // every block of the range is proven against the verified range
+            let proofs = vector["blockProofs"].as_array().unwrap();
+            for ((number, hash), proof) in (range.firstBlock..).zip(&block_hashes).zip(proofs) {
+                let proof = hashes(proof);
+                assert!(range.verify_block(number, *hash, &proof));
+                assert!(!range.verify_block(number, B256::ZERO, &proof));
+                assert!(!range.verify_block(range.lastBlock + 1, *hash, &proof));
+            }
+
+            // a range proof is not a checkpoint proof
+            assert_eq!(
+                verify_checkpoint_proof::<ConcatScheme>(
+                    &committee(range.epoch),
+                    &domain,
+                    &proof.encode()
+                ),
+                Err(VerificationError::MalformedProof)
+            );
+        }
+    }
+
+    #[test]
+    fn reject_inverted_range() {
+        let range = CheckpointRange { epoch: 1, firstBlock: 2, lastBlock: 1, ..Default::default() };
+        let proof = CheckpointRangeProof::new(range, [0, 1, 2], Bytes::new()).unwrap();
+        assert_eq!(
+            CheckpointRangeProof::decode(&proof.encode()),
+            Err(VerificationError::MalformedProof)
+        );
+    }
+
+    #[test]
+    fn signer_bitmap() {
+        let proof =
+            CheckpointProof::new(Checkpoint::default(), [255, 0, 3, 0], Bytes::new()).unwrap();
+        assert_eq!(proof.signer_indices(), vec![0, 3, 255]);
+        assert_eq!(CheckpointProof::new(Checkpoint::default(), [256], Bytes::new()), None);
+    }
+
+    #[test]
+    fn reject_malformed_proofs() {
+        let checkpoint = Checkpoint { epoch: 1, ..Default::default() };
+        let mut signature = vec![0, 1];
+        signature.extend_from_slice(checkpoint.signing_message(&DOMAIN).as_slice());
+        let proof = CheckpointProof::new(checkpoint, [0, 1], signature.into()).unwrap();
+        assert_eq!(
+            verify_checkpoint_proof::<ConcatScheme>(&committee(1), &DOMAIN, &proof.encode()),
+            Err(VerificationError::InsufficientStake { stake: 2, threshold: 3 })
+        );
+
+        let mut encoded = proof.encode();
+        encoded.truncate(encoded.len() - 1);
+        assert_eq!(
+            verify_checkpoint_proof::<ConcatScheme>(&committee(1), &DOMAIN, &encoded),
+            Err(VerificationError::MalformedProof)
+        );
+
+        // a signer outside of the committee
+        let proof = CheckpointProof::new(proof.checkpoint, [0, 1, 2, 4], Bytes::new()).unwrap();
+        assert_eq!(
+            verify_checkpoint_proof::<ConcatScheme>(&committee(1), &DOMAIN, &proof.encode()),
+            Err(VerificationError::UnknownSigner { index: 4 })
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Merkle accumulator over the hashes of a range of blocks.
+//!
+//! A [`CheckpointRange`](crate::abi::CheckpointRange) commits to all of its blocks with the root of
+//! a tree over their hashes in block order, so a single signed range proves every block of the
+//! range. The tree is the tree of the [`messages`](crate::messages) module. Block hashes are
+//! hashes of header encodings longer than 64 bytes, so a leaf can't be mistaken for an inner node
+//! either.
+
+use crate::messages::{message_proof, message_root, verify_message_proof};
+use alloc::vec::Vec;
+use alloy_primitives::B256;
+
+/// Returns the root of the tree over the given block hashes.
+pub fn blocks_root(block_hashes: &[B256]) -> B256 {
+    message_root(block_hashes)
+}
+
+/// Returns the sibling hashes on the path from the block hash at `index` to the root, starting
+/// with the sibling of the block hash.
+///
+/// Returns `None` if there is no block hash at `index`.
+pub fn block_proof(block_hashes: &[B256], index: usize) -> Option<Vec<B256>> {
+    message_proof(block_hashes, index)
+}
+
+/// Verifies that `block_hash` is the leaf at `index` of the tree with the given root.
+pub fn verify_block_proof(root: B256, block_hash: B256, index: u64, proof: &[B256]) -> bool {
+    verify_message_proof(root, block_hash, index, proof)
+}
@@ -0,0 +1,102 @@
+//! The committee a commit proof is verified against.
+
+use alloc::vec::Vec;
+
+/// The epoch of a committee.
+pub type Epoch = u64;
+
+/// The voting power of an authority.
+pub type Stake = u64;
+
+/// The index of an authority in its committee.
+pub type AuthorityIndex = u32;
+
+/// The public key and stake of a committee member.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierAuthority<PublicKey> {
+    /// The public key the authority signs with.
+    pub public_key: PublicKey,
+    /// The voting power of the authority.
+    pub stake: Stake,
+}
+
+/// The minimal view of a committee that is needed to verify commit proofs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifierCommittee<PublicKey> {
+    epoch: Epoch,
+    authorities: Vec<VerifierAuthority<PublicKey>>,
+    total_stake: Stake,
+}
+
+impl<PublicKey> VerifierCommittee<PublicKey> {
+    /// Creates the committee of the given epoch.
+    ///
+    /// The position of an authority in `authorities` is its [`AuthorityIndex`].
+    pub fn new(epoch: Epoch, authorities: Vec<VerifierAuthority<PublicKey>>) -> Self {
+        let total_stake = authorities
+            .iter()
+            .fold(0, |total: Stake, authority| total.saturating_add(authority.stake));
+        Self { epoch, authorities, total_stake }
+    }
+
+    /// The epoch of the committee.
+    pub const fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// The members of the committee, ordered by their [`AuthorityIndex`].
+    pub fn authorities(&self) -> &[VerifierAuthority<PublicKey>] {
+        &self.authorities
+    }
+
+    /// Returns the member with the given index.
+    pub fn authority(&self, index: AuthorityIndex) -> Option<&VerifierAuthority<PublicKey>> {
+        self.authorities.get(index as usize)
+    }
+
+    /// The sum of the stake of all members.
+    pub const fn total_stake(&self) -> Stake {
+        self.total_stake
+    }
+
+    /// The stake required for a quorum, i.e. `2f + 1` for a committee of `3f + 1`.
+    pub const fn quorum_threshold(&self) -> Stake {
+        2 * self.total_stake / 3 + 1
+    }
+
+    /// The stake required to guarantee that at least one honest member is included, i.e.
+    /// `f + 1` for a committee of `3f + 1`.
+    pub const fn validity_threshold(&self) -> Stake {
+        self.total_stake.div_ceil(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn committee_with(stakes: &[Stake]) -> VerifierCommittee<()> {
+        VerifierCommittee::new(
+            0,
+            stakes
+                .iter()
+                .map(|stake| VerifierAuthority { public_key: (), stake: *stake })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn thresholds() {
+        let committee = committee_with(&[1, 1, 1, 1]);
+        assert_eq!(committee.quorum_threshold(), 3);
+        assert_eq!(committee.validity_threshold(), 2);
+
+        let committee = committee_with(&[1; 7]);
+        assert_eq!(committee.quorum_threshold(), 5);
+        assert_eq!(committee.validity_threshold(), 3);
+
+        let committee = committee_with(&[10, 20, 30, 40]);
+        assert_eq!(committee.quorum_threshold(), 67);
+        assert_eq!(committee.validity_threshold(), 34);
+    }
+}
@@ -0,0 +1,77 @@
+//! Signing domains of consensus artifacts.
+//!
+//! Authorities may use the same keys on several networks, e.g. on a test network and in
+//! production. So that an artifact signed on one network can never be replayed on another, the
+//! message signed for an artifact is not its digest but [`SigningDomain::signing_message`], which
+//! binds the digest to the chain id and genesis hash of the network and to the kind of artifact.
+
+use alloy_primitives::{keccak256, B256};
+use alloy_sol_types::{sol_data, SolType};
+
+/// `abi.encode(uint64 chainId, bytes32 genesisHash, uint8 intent, bytes32 digest)`.
+type SigningMessageEncoding =
+    (sol_data::Uint<64>, sol_data::FixedBytes<32>, sol_data::Uint<8>, sol_data::FixedBytes<32>);
+
+/// The kind of a signed artifact, so that a signature over one kind is never valid for another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Intent {
+    /// A header, signed by its author.
+    Header = 0,
+    /// A vote for a header. A certificate aggregates the votes of a quorum.
+    Vote = 1,
+    /// A checkpoint exported to other chains.
+    Checkpoint = 2,
+    /// A checkpoint of a range of blocks exported to other chains.
+    CheckpointRange = 3,
+}
+
+/// The network an artifact is signed for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SigningDomain {
+    /// The chain id of the network.
+    pub chain_id: u64,
+    /// The hash of the genesis block of the network.
+    pub genesis_hash: B256,
+}
+
+impl SigningDomain {
+    /// Creates the domain of the network with the given chain id and genesis hash.
+    pub const fn new(chain_id: u64, genesis_hash: B256) -> Self {
+        Self { chain_id, genesis_hash }
+    }
+
+    /// Returns the message signed for an artifact of the given kind with the given digest,
+    /// `keccak256(abi.encode(chainId, genesisHash, intent, digest))`.
+    pub fn signing_message(&self, intent: Intent, digest: B256) -> B256 {
+        keccak256(SigningMessageEncoding::abi_encode(&(
+            self.chain_id,
+            self.genesis_hash,
+            intent as u8,
+            digest,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_differ_by_network_and_intent() {
+        let digest = B256::with_last_byte(1);
+        let domain = SigningDomain::new(1, B256::with_last_byte(2));
+        let message = domain.signing_message(Intent::Vote, digest);
+
+        assert_ne!(message, digest);
+        assert_ne!(
+            message,
+            SigningDomain::new(2, domain.genesis_hash).signing_message(Intent::Vote, digest)
+        );
+        assert_ne!(
+            message,
+            SigningDomain::new(1, B256::ZERO).signing_message(Intent::Vote, digest)
+        );
+        assert_ne!(message, domain.signing_message(Intent::Header, digest));
+    }
+}
@@ -0,0 +1,45 @@
+//! Errors of commit proof verification.
+
+use crate::{AuthorityIndex, Epoch, Stake};
+
+/// Reasons a commit proof is rejected.
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display)]
+pub enum VerificationError {
+    /// The proof was signed by the committee of another epoch.
+    #[display("proof of epoch {got} can't be verified with the committee of epoch {expected}")]
+    EpochMismatch {
+        /// The epoch of the committee.
+        expected: Epoch,
+        /// The epoch of the proof.
+        got: Epoch,
+    },
+    /// A signer is not a member of the committee.
+    #[display("signer {index} is not a member of the committee")]
+    UnknownSigner {
+        /// The index of the signer.
+        index: AuthorityIndex,
+    },
+    /// The signers are not in strictly ascending order.
+    #[display("signer {index} is duplicated or out of order")]
+    UnorderedSigners {
+        /// The index of the first misplaced signer.
+        index: AuthorityIndex,
+    },
+    /// The signers don't form a quorum.
+    #[display("signers have {stake} stake, a quorum requires {threshold}")]
+    InsufficientStake {
+        /// The combined stake of the signers.
+        stake: Stake,
+        /// The stake required for a quorum.
+        threshold: Stake,
+    },
+    /// The proof is not a canonical encoding of a proof.
+    #[display("malformed proof")]
+    MalformedProof,
+    /// The aggregate signature is invalid.
+    #[display("invalid aggregate signature")]
+    InvalidSignature,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for VerificationError {}
@@ -0,0 +1,223 @@
+//! Verification rules for narwhal commit proofs.
+//!
+//! A commit proof attests that a quorum of the committee of an epoch signed a digest, e.g. the
+//! digest of a certificate or of a checkpoint of the chain. This crate contains the rules to
+//! verify such a proof and nothing else, so that the exact same rules can be reused by the node,
+//! bridges and light clients, including targets without `std` like `wasm` or zk circuits.
+//!
+//! The signature scheme is abstracted by [`SignatureScheme`], only the quorum rules are fixed.
+//! Signatures are over messages bound to the network with a [`SigningDomain`].
+//! The [`abi`] module defines how checkpoint proofs are encoded for verification by Solidity
+//! contracts, [`blocks`] how the blocks of a checkpointed range are proven against the range, and
+//! [`messages`] how messages sent to other chains are proven against a block.
+
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/paradigmxyz/reth/main/assets/reth-docs.png",
+    html_favicon_url = "https://avatars0.githubusercontent.com/u/97369466?s=256",
+    issue_tracker_base_url = "https://github.com/paradigmxyz/reth/issues/"
+)]
+#![cfg_attr(not(test), warn(unused_crate_dependencies))]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use alloy_primitives::B256;
+
+pub mod abi;
+pub mod blocks;
+pub mod messages;
+
+mod committee;
+pub use committee::{AuthorityIndex, Epoch, Stake, VerifierAuthority, VerifierCommittee};
+
+mod domain;
+pub use domain::{Intent, SigningDomain};
+
+mod error;
+pub use error::VerificationError;
+
+/// A signature scheme whose signatures of multiple signers over the same message can be
+/// aggregated into a single signature.
+pub trait SignatureScheme {
+    /// The public key of a signer.
+    type PublicKey;
+    /// A signature, possibly aggregated from the signatures of multiple signers.
+    type Signature;
+
+    /// Returns `true` if `signature` is the aggregate of the signatures of all `signers` over
+    /// `message`.
+    fn verify_aggregate(
+        signers: &[&Self::PublicKey],
+        message: &[u8],
+        signature: &Self::Signature,
+    ) -> bool;
+}
+
+/// Proof that a quorum of the committee of an epoch signed a digest.
+///
+/// The signers sign the [`SigningDomain::signing_message`] of the digest, not the digest itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitProof<Signature> {
+    /// The epoch of the committee that signed the digest.
+    pub epoch: Epoch,
+    /// The signed digest.
+    pub digest: B256,
+    /// Indices of the signing authorities in the committee, in strictly ascending order.
+    pub signers: Vec<AuthorityIndex>,
+    /// The aggregate signature of all signers over the signing message of the digest.
+    pub signature: Signature,
+}
+
+/// Verifies that the proof was signed by a quorum of the given committee, for an artifact of the
+/// given kind on the network of the given domain.
+///
+/// The signers must be listed in strictly ascending order, so that every set of signers has exactly
+/// one valid encoding.
+pub fn verify_commit_proof<S: SignatureScheme>(
+    committee: &VerifierCommittee<S::PublicKey>,
+    domain: &SigningDomain,
+    intent: Intent,
+    proof: &CommitProof<S::Signature>,
+) -> Result<(), VerificationError> {
+    let public_keys = quorum_signers(committee, proof.epoch, &proof.signers)?;
+
+    let message = domain.signing_message(intent, proof.digest);
+    if !S::verify_aggregate(&public_keys, message.as_slice(), &proof.signature) {
+        return Err(VerificationError::InvalidSignature)
+    }
+
+    Ok(())
+}
+
+/// Checks that the signers of a proof of the given epoch form a quorum of the committee, and
+/// returns their public keys.
+///
+/// This applies all rules of [`verify_commit_proof`] except for the signature check, for callers
+/// that verify the signatures of many proofs at once.
+pub fn quorum_signers<'a, PublicKey>(
+    committee: &'a VerifierCommittee<PublicKey>,
+    epoch: Epoch,
+    signers: &[AuthorityIndex],
+) -> Result<Vec<&'a PublicKey>, VerificationError> {
+    if epoch != committee.epoch() {
+        return Err(VerificationError::EpochMismatch { expected: committee.epoch(), got: epoch })
+    }
+
+    let mut stake: Stake = 0;
+    let mut public_keys = Vec::with_capacity(signers.len());
+    let mut previous = None;
+    for &index in signers {
+        if previous.is_some_and(|previous| index <= previous) {
+            return Err(VerificationError::UnorderedSigners { index })
+        }
+        previous = Some(index);
+
+        let authority =
+            committee.authority(index).ok_or(VerificationError::UnknownSigner { index })?;
+        stake = stake.saturating_add(authority.stake);
+        public_keys.push(&authority.public_key);
+    }
+
+    let threshold = committee.quorum_threshold();
+    if stake < threshold {
+        return Err(VerificationError::InsufficientStake { stake, threshold })
+    }
+
+    Ok(public_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// A scheme whose "signature" is the list of signers and the signed message.
+    struct TestScheme;
+
+    impl SignatureScheme for TestScheme {
+        type PublicKey = u8;
+        type Signature = (Vec<u8>, Vec<u8>);
+
+        fn verify_aggregate(
+            signers: &[&Self::PublicKey],
+            message: &[u8],
+            signature: &Self::Signature,
+        ) -> bool {
+            signers.iter().map(|key| **key).eq(signature.0.iter().copied()) &&
+                message == signature.1.as_slice()
+        }
+    }
+
+    fn committee() -> VerifierCommittee<u8> {
+        VerifierCommittee::new(
+            1,
+            (0..4).map(|key| VerifierAuthority { public_key: key, stake: 1 }).collect(),
+        )
+    }
+
+    const DOMAIN: SigningDomain = SigningDomain::new(1337, B256::ZERO);
+
+    fn proof(signers: Vec<AuthorityIndex>) -> CommitProof<(Vec<u8>, Vec<u8>)> {
+        let digest = B256::with_last_byte(42);
+        let keys = signers.iter().map(|index| *index as u8).collect();
+        let message = DOMAIN.signing_message(Intent::Vote, digest);
+        CommitProof { epoch: 1, digest, signers, signature: (keys, message.to_vec()) }
+    }
+
+    fn verify(proof: &CommitProof<(Vec<u8>, Vec<u8>)>) -> Result<(), VerificationError> {
+        verify_commit_proof::<TestScheme>(&committee(), &DOMAIN, Intent::Vote, proof)
+    }
+
+    #[test]
+    fn verify_quorum() {
+        assert_eq!(verify(&proof(vec![0, 1, 3])), Ok(()));
+        assert_eq!(verify(&proof(vec![0, 1, 2, 3])), Ok(()));
+    }
+
+    #[test]
+    fn reject_insufficient_stake() {
+        assert_eq!(
+            verify(&proof(vec![0, 2])),
+            Err(VerificationError::InsufficientStake { stake: 2, threshold: 3 })
+        );
+    }
+
+    #[test]
+    fn reject_malformed_signers() {
+        assert_eq!(
+            verify(&proof(vec![0, 0, 1, 2])),
+            Err(VerificationError::UnorderedSigners { index: 0 })
+        );
+        assert_eq!(
+            verify(&proof(vec![0, 1, 4])),
+            Err(VerificationError::UnknownSigner { index: 4 })
+        );
+    }
+
+    #[test]
+    fn reject_wrong_epoch_domain_or_signature() {
+        let mut wrong_epoch = proof(vec![0, 1, 2]);
+        wrong_epoch.epoch = 2;
+        assert_eq!(
+            verify(&wrong_epoch),
+            Err(VerificationError::EpochMismatch { expected: 1, got: 2 })
+        );
+
+        let other_network = SigningDomain::new(1, B256::ZERO);
+        assert_eq!(
+            verify_commit_proof::<TestScheme>(
+                &committee(),
+                &other_network,
+                Intent::Vote,
+                &proof(vec![0, 1, 2])
+            ),
+            Err(VerificationError::InvalidSignature)
+        );
+
+        let mut wrong_digest = proof(vec![0, 1, 2]);
+        wrong_digest.digest = B256::ZERO;
+        assert_eq!(verify(&wrong_digest), Err(VerificationError::InvalidSignature));
+    }
+}
@@ -0,0 +1,123 @@
+//! Merkle tree over the outbound messages of a block.
+//!
+//! The leaves are the hashes of the messages sent in a block, in the order they were sent. The
+//! tree is a binary tree whose leaves are padded with zero hashes to the next power of two, and
+//! every inner node is `keccak256(left ++ right)`. The root of a block without messages is zero.
+//!
+//! Leaves are hashes of message encodings longer than 64 bytes, so a leaf can't be mistaken for
+//! an inner node.
+
+use alloc::vec::Vec;
+use alloy_primitives::{keccak256, B256};
+
+/// Hashes two sibling nodes into their parent.
+fn hash_pair(left: &B256, right: &B256) -> B256 {
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(left.as_slice());
+    buf[32..].copy_from_slice(right.as_slice());
+    keccak256(buf)
+}
+
+/// Hashes one level of the tree into the next one, padding it with `zero`, the root of an empty
+/// subtree of this level.
+fn next_level(level: &[B256], zero: &B256) -> Vec<B256> {
+    level.chunks(2).map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(zero))).collect()
+}
+
+/// Returns the root of the tree over the given message hashes.
+pub fn message_root(leaves: &[B256]) -> B256 {
+    if leaves.is_empty() {
+        return B256::ZERO
+    }
+
+    let mut level = leaves.to_vec();
+    let mut zero = B256::ZERO;
+    while level.len() > 1 {
+        level = next_level(&level, &zero);
+        zero = hash_pair(&zero, &zero);
+    }
+    level[0]
+}
+
+/// Returns the sibling hashes on the path from the leaf at `index` to the root, starting with the
+/// sibling of the leaf.
+///
+/// Returns `None` if there is no leaf at `index`.
+pub fn message_proof(leaves: &[B256], index: usize) -> Option<Vec<B256>> {
+    if index >= leaves.len() {
+        return None
+    }
+
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    let mut zero = B256::ZERO;
+    let mut index = index;
+    while level.len() > 1 {
+        proof.push(level.get(index ^ 1).copied().unwrap_or(zero));
+        level = next_level(&level, &zero);
+        zero = hash_pair(&zero, &zero);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Verifies that `leaf` is the leaf at `index` of the tree with the given root.
+pub fn verify_message_proof(root: B256, leaf: B256, index: u64, proof: &[B256]) -> bool {
+    if proof.len() < 64 && index >> proof.len() != 0 {
+        return false
+    }
+
+    let mut node = leaf;
+    for (level, sibling) in proof.iter().enumerate() {
+        node = if (index >> level) & 1 == 0 {
+            hash_pair(&node, sibling)
+        } else {
+            hash_pair(sibling, &node)
+        };
+    }
+    node == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u8) -> Vec<B256> {
+        (1..=count).map(B256::with_last_byte).collect()
+    }
+
+    #[test]
+    fn small_trees() {
+        assert_eq!(message_root(&[]), B256::ZERO);
+
+        let [a, b, c] = [1, 2, 3].map(B256::with_last_byte);
+        assert_eq!(message_root(&[a]), a);
+        assert_eq!(message_root(&[a, b]), hash_pair(&a, &b));
+        assert_eq!(
+            message_root(&[a, b, c]),
+            hash_pair(&hash_pair(&a, &b), &hash_pair(&c, &B256::ZERO))
+        );
+    }
+
+    #[test]
+    fn proofs_of_all_leaves() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let root = message_root(&leaves);
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = message_proof(&leaves, index).unwrap();
+                assert!(verify_message_proof(root, *leaf, index as u64, &proof));
+                assert!(!verify_message_proof(root, B256::ZERO, index as u64, &proof));
+                assert!(!verify_message_proof(root, *leaf, (index ^ 1) as u64, &proof));
+            }
+            assert_eq!(message_proof(&leaves, leaves.len()), None);
+        }
+    }
+
+    #[test]
+    fn reject_index_beyond_proof() {
+        let leaves = leaves(2);
+        let proof = message_proof(&leaves, 0).unwrap();
+        assert!(!verify_message_proof(message_root(&leaves), leaves[0], 2, &proof));
+    }
+}
@@ -0,0 +1,47 @@
+#![allow(missing_docs)]
+//! Compares the cost of encrypting a batch with the cost of hashing it, which every batch pays
+//! anyway.
+
+use alloy_primitives::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use reth_narwhal_consensus::{
+    dev::DevCommittee,
+    types::Batch,
+    worker::{BatchCipher, BATCH_ENCRYPTION_SECRET_LEN},
+};
+
+/// The size of a transfer transaction in bytes.
+const TRANSACTION_SIZE: usize = 200;
+
+fn batch(size: usize) -> Batch {
+    Batch::new(
+        (0..size / TRANSACTION_SIZE)
+            .map(|index| Bytes::from(vec![index as u8; TRANSACTION_SIZE]))
+            .collect(),
+    )
+}
+
+fn batch_encryption(c: &mut Criterion) {
+    let committee = DevCommittee::new(4);
+    let cipher = BatchCipher::new(&[7; BATCH_ENCRYPTION_SECRET_LEN], committee.committee());
+
+    let mut group = c.benchmark_group("narwhal | batch encryption");
+    for size in [10_000, 100_000, 500_000] {
+        let batch = batch(size);
+        let encrypted = cipher.encrypt(0, 0, &batch);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("digest", size), &batch, |b, batch| {
+            b.iter(|| black_box(batch.digest()))
+        });
+        group.bench_with_input(BenchmarkId::new("encrypt", size), &batch, |b, batch| {
+            b.iter(|| black_box(cipher.encrypt(0, 0, batch)))
+        });
+        group.bench_with_input(BenchmarkId::new("decrypt", size), &encrypted, |b, encrypted| {
+            b.iter(|| black_box(cipher.decrypt(0, 0, encrypted).unwrap()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, batch_encryption);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+#![allow(missing_docs)]
+//! Measures the latency of inserting a round into the in-memory DAG and of committing a leader,
+//! for committees of up to 1000 authorities whose certificates reference a quorum of parents.
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use reth_narwhal_consensus::{
+    dag::Dag,
+    types::{Certificate, CertificateDigest, DagVertex, Header, Round},
+};
+
+/// Builds the genesis round and `rounds` rounds of a committee of `authorities`, where every
+/// certificate references a quorum of the previous round.
+fn rounds(authorities: u32, rounds: Round) -> Vec<Vec<DagVertex>> {
+    let quorum = authorities as usize * 2 / 3 + 1;
+    let genesis = Certificate::genesis(0, 0..authorities).into_iter().map(DagVertex::new);
+    let mut layers = vec![genesis.collect::<Vec<_>>()];
+    for round in 1..=rounds {
+        let previous = &layers[layers.len() - 1];
+        let layer = (0..authorities)
+            .map(|author| {
+                // every authority references a different quorum
+                let mut parents = (0..quorum)
+                    .map(|index| previous[(author as usize + index) % previous.len()].digest())
+                    .collect::<Vec<CertificateDigest>>();
+                parents.sort_unstable();
+                DagVertex::new(Certificate {
+                    header: Header { round, author, parents, ..Default::default() },
+                    ..Default::default()
+                })
+            })
+            .collect();
+        layers.push(layer);
+    }
+    layers
+}
+
+fn dag(layers: &[Vec<DagVertex>]) -> Dag {
+    let mut dag = Dag::new(0);
+    for vertex in layers.iter().flatten() {
+        dag.insert(vertex.clone()).unwrap();
+    }
+    dag
+}
+
+fn dag_commit(c: &mut Criterion) {
+    let mut group = c.benchmark_group("narwhal | dag");
+    group.sample_size(10);
+    for authorities in [100, 1_000] {
+        // the leader of round 2 is committed by the certificates of round 3
+        let layers = rounds(authorities, 3);
+        let dag = dag(&layers[..3]);
+        let leader = layers[2][0].digest();
+        group.throughput(Throughput::Elements(authorities as u64));
+
+        group.bench_with_input(BenchmarkId::new("insert round", authorities), &dag, |b, dag| {
+            b.iter_batched(
+                || (dag.clone(), layers[3].clone()),
+                |(mut dag, round)| {
+                    for vertex in round {
+                        dag.insert(vertex).unwrap();
+                    }
+                    black_box(dag)
+                },
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("commit leader", authorities), &dag, |b, dag| {
+            b.iter_batched(
+                || dag.clone(),
+                |mut dag| black_box(dag.commit(&leader)),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("is linked", authorities), &dag, |b, dag| {
+            let from = layers[2][authorities as usize - 1].digest();
+            let to = layers[0][0].digest();
+            b.iter(|| black_box(dag.is_linked(&from, &to)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, dag_commit);
+criterion_main!(benches);
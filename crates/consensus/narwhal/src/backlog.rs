@@ -0,0 +1,244 @@
+//! Bounded-memory queue of commits waiting for execution.
+//!
+//! Committed sub-dags are executed in commit order. If execution is paused or can't keep up with
+//! consensus, the commits waiting for it would accumulate in memory for as long as the incident
+//! lasts. [`ExecutionBacklog`] keeps only a bounded number of them in memory and spills the rest to
+//! a file in arrival order, reading them back once execution catches up. A node places it between
+//! consensus and the executor with [`ExecutionBacklog::relay`], so that commits never wait for
+//! execution.
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// Name of the file spilled items are written to.
+const SPILL_FILE_NAME: &str = "execution-backlog.jsonl";
+
+/// A FIFO queue that keeps at most a fixed number of items in memory and spills the rest to disk.
+///
+/// Items on disk are always newer than the items in memory, so the queue preserves the order
+/// items were pushed in.
+#[derive(Debug)]
+pub struct ExecutionBacklog<T> {
+    /// The oldest items of the queue.
+    in_memory: VecDeque<T>,
+    /// The maximum number of items kept in memory.
+    max_in_memory: usize,
+    /// Path of the spill file.
+    path: PathBuf,
+    /// The items on disk, if any.
+    spilled: Option<SpillFile>,
+}
+
+/// A file of newline delimited JSON items, read from the front and appended to at the back.
+#[derive(Debug)]
+struct SpillFile {
+    writer: BufWriter<File>,
+    reader: BufReader<File>,
+    len: usize,
+}
+
+impl<T: Serialize + DeserializeOwned> ExecutionBacklog<T> {
+    /// Creates a new backlog that spills to a file in the given directory.
+    ///
+    /// A spill file left over from a previous run is removed, the items it contained were never
+    /// acknowledged as executed and are recovered from the consensus store instead.
+    pub fn new(dir: impl Into<PathBuf>, max_in_memory: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(SPILL_FILE_NAME);
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        Ok(Self { in_memory: VecDeque::new(), max_in_memory, path, spilled: None })
+    }
+
+    /// Returns the number of items in the backlog.
+    pub fn len(&self) -> usize {
+        self.in_memory.len() + self.spilled_len()
+    }
+
+    /// Returns `true` if the backlog is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of items on disk.
+    pub fn spilled_len(&self) -> usize {
+        self.spilled.as_ref().map_or(0, |spilled| spilled.len)
+    }
+
+    /// Appends an item to the back of the backlog.
+    pub fn push(&mut self, item: T) -> io::Result<()> {
+        if self.spilled.is_none() && self.in_memory.len() < self.max_in_memory {
+            self.in_memory.push_back(item);
+            return Ok(())
+        }
+
+        let spilled = match &mut self.spilled {
+            Some(spilled) => spilled,
+            None => self.spilled.insert(SpillFile::create(&self.path)?),
+        };
+        serde_json::to_writer(&mut spilled.writer, &item)?;
+        spilled.writer.write_all(b"\n")?;
+        spilled.len += 1;
+        Ok(())
+    }
+
+    /// Removes the item at the front of the backlog.
+    pub fn pop(&mut self) -> io::Result<Option<T>> {
+        if self.in_memory.is_empty() {
+            self.load_spilled()?;
+        }
+        Ok(self.in_memory.pop_front())
+    }
+
+    /// Relays the items of `input` to `output` in order, and queues them in the backlog while
+    /// `output` is full, until `input` is closed and the backlog is drained, or `output` is
+    /// closed.
+    ///
+    /// Stops if the spill file fails. The items in the backlog are lost then, and are recovered
+    /// from the consensus store once the node restarts.
+    pub async fn relay(mut self, mut input: mpsc::Receiver<T>, output: mpsc::Sender<T>) {
+        let mut input_open = true;
+        loop {
+            tokio::select! {
+                item = input.recv(), if input_open => match item {
+                    Some(item) => {
+                        if let Err(err) = self.push(item) {
+                            error!(
+                                target: "consensus::narwhal",
+                                %err,
+                                "Failed to queue commit for execution"
+                            );
+                            return
+                        }
+                    }
+                    None => input_open = false,
+                },
+                permit = output.reserve(), if !self.is_empty() => {
+                    let Ok(permit) = permit else { return };
+                    match self.pop() {
+                        Ok(Some(item)) => permit.send(item),
+                        Ok(None) => {}
+                        Err(err) => {
+                            error!(
+                                target: "consensus::narwhal",
+                                %err,
+                                "Failed to read queued commit"
+                            );
+                            return
+                        }
+                    }
+                }
+                else => return,
+            }
+        }
+    }
+
+    /// Moves up to `max_in_memory` items from disk into memory, removing the spill file once it
+    /// has been read completely.
+    fn load_spilled(&mut self) -> io::Result<()> {
+        let Some(spilled) = &mut self.spilled else { return Ok(()) };
+        spilled.writer.flush()?;
+
+        let mut line = String::new();
+        while spilled.len > 0 && self.in_memory.len() < self.max_in_memory.max(1) {
+            line.clear();
+            if spilled.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated spill file"))
+            }
+            self.in_memory.push_back(serde_json::from_str(&line)?);
+            spilled.len -= 1;
+        }
+
+        if spilled.len == 0 {
+            self.spilled = None;
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl SpillFile {
+    fn create(path: &Path) -> io::Result<Self> {
+        let writer = OpenOptions::new().create(true).truncate(true).write(true).open(path)?;
+        let reader = File::open(path)?;
+        Ok(Self { writer: BufWriter::new(writer), reader: BufReader::new(reader), len: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spill_and_restore_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut backlog = ExecutionBacklog::new(dir.path(), 2).unwrap();
+
+        for item in 0..5u64 {
+            backlog.push(item).unwrap();
+        }
+        assert_eq!(backlog.len(), 5);
+        assert_eq!(backlog.spilled_len(), 3);
+        assert!(dir.path().join(SPILL_FILE_NAME).exists());
+
+        let mut popped = Vec::new();
+        for _ in 0..3 {
+            popped.push(backlog.pop().unwrap().unwrap());
+        }
+        // items pushed while there are items on disk go to disk as well
+        backlog.push(5).unwrap();
+        while let Some(item) = backlog.pop().unwrap() {
+            popped.push(item);
+        }
+
+        assert_eq!(popped, vec![0, 1, 2, 3, 4, 5]);
+        assert!(backlog.is_empty());
+        assert!(!dir.path().join(SPILL_FILE_NAME).exists());
+
+        // back to memory only
+        backlog.push(6).unwrap();
+        assert_eq!(backlog.spilled_len(), 0);
+    }
+
+    #[tokio::test]
+    async fn relay_while_output_is_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let backlog = ExecutionBacklog::new(dir.path(), 2).unwrap();
+        let (input, input_rx) = mpsc::channel(1);
+        let (output, mut output_rx) = mpsc::channel(1);
+        let relay = tokio::spawn(backlog.relay(input_rx, output));
+
+        // the input never waits for the output
+        for item in 0..10u64 {
+            input.send(item).await.unwrap();
+        }
+        drop(input);
+        let mut relayed = Vec::new();
+        while let Some(item) = output_rx.recv().await {
+            relayed.push(item);
+        }
+        assert_eq!(relayed, (0..10).collect::<Vec<_>>());
+        relay.await.unwrap();
+        assert!(!dir.path().join(SPILL_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn remove_stale_spill_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(SPILL_FILE_NAME), "1\n2\n").unwrap();
+
+        let mut backlog = ExecutionBacklog::<u64>::new(dir.path(), 2).unwrap();
+        assert!(!dir.path().join(SPILL_FILE_NAME).exists());
+        assert_eq!(backlog.pop().unwrap(), None);
+    }
+}
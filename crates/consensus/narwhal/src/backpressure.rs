@@ -0,0 +1,203 @@
+//! Back-pressure from execution into batch production.
+//!
+//! Consensus commits sub-dags independently of how fast they are executed. If execution falls
+//! behind, e.g. on slow storage, the committed sub-dags and the batches they reference pile up in
+//! memory while the workers keep sealing new batches. The [`ExecutionLag`] tracks the sub-dags
+//! that were committed but not executed yet: the [`EpochManager`] reports every commit and the
+//! `ConsensusOutputExecutor` every executed sub-dag.
+//!
+//! While the lag exceeds [`BackpressureConfig::max_execution_lag`], the [`Backpressure`] handles
+//! throttle batch production: the batch makers stop sealing batches, which leaves the
+//! transactions in the pool, and the primary stops proposing headers. Once enough validators
+//! throttle, the committee commits more slowly until execution catches up.
+//!
+//! [`EpochManager`]: crate::epoch::EpochManager
+
+use reth_metrics::{metrics::Gauge, Metrics};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// The default number of committed sub-dags that may wait for execution.
+pub const DEFAULT_MAX_EXECUTION_LAG: u64 = 16;
+
+/// Configuration of the [`Backpressure`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BackpressureConfig {
+    /// The number of committed sub-dags that may wait for execution before batch production is
+    /// throttled.
+    pub max_execution_lag: u64,
+}
+
+impl Default for BackpressureConfig {
+    fn default() -> Self {
+        Self { max_execution_lag: DEFAULT_MAX_EXECUTION_LAG }
+    }
+}
+
+/// The progress of execution relative to the commits of consensus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionProgress {
+    /// The index of the next sub-dag to be committed.
+    pub next_committed: u64,
+    /// The index of the next sub-dag to be executed.
+    pub next_executed: u64,
+}
+
+impl ExecutionProgress {
+    /// Returns the number of committed sub-dags that were not executed yet.
+    pub const fn lag(&self) -> u64 {
+        self.next_committed.saturating_sub(self.next_executed)
+    }
+}
+
+/// Metrics of the [`ExecutionLag`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.backpressure")]
+struct ExecutionLagMetrics {
+    /// Number of committed sub-dags that were not executed yet
+    execution_lag: Gauge,
+}
+
+/// Tracks the committed sub-dags that were not executed yet.
+///
+/// Cloning is cheap, all clones track the same progress.
+#[derive(Debug, Clone)]
+pub struct ExecutionLag {
+    progress: Arc<watch::Sender<ExecutionProgress>>,
+    metrics: Arc<ExecutionLagMetrics>,
+}
+
+impl ExecutionLag {
+    /// Creates the lag of a node that resumes at the given sub-dag, the index after the last
+    /// executed one.
+    pub fn new(next_sub_dag: u64) -> Self {
+        let progress =
+            ExecutionProgress { next_committed: next_sub_dag, next_executed: next_sub_dag };
+        Self {
+            progress: Arc::new(watch::Sender::new(progress)),
+            metrics: Arc::new(ExecutionLagMetrics::default()),
+        }
+    }
+
+    /// Records that the sub-dag with the given index was committed.
+    pub fn on_committed(&self, index: u64) {
+        self.update(|progress| {
+            let modified = index >= progress.next_committed;
+            progress.next_committed = progress.next_committed.max(index + 1);
+            modified
+        });
+    }
+
+    /// Records that the sub-dag with the given index was executed.
+    pub fn on_executed(&self, index: u64) {
+        self.update(|progress| {
+            let modified = index >= progress.next_executed;
+            progress.next_executed = progress.next_executed.max(index + 1);
+            // a sub-dag can only be executed once it was committed
+            progress.next_committed = progress.next_committed.max(progress.next_executed);
+            modified
+        });
+    }
+
+    /// Returns the current progress of execution.
+    pub fn progress(&self) -> ExecutionProgress {
+        *self.progress.borrow()
+    }
+
+    /// Returns the number of committed sub-dags that were not executed yet.
+    pub fn lag(&self) -> u64 {
+        self.progress().lag()
+    }
+
+    /// Returns a handle that throttles while the lag exceeds the configured maximum.
+    pub fn backpressure(&self, config: BackpressureConfig) -> Backpressure {
+        Backpressure { progress: self.progress.subscribe(), max_lag: config.max_execution_lag }
+    }
+
+    fn update(&self, modify: impl FnOnce(&mut ExecutionProgress) -> bool) {
+        self.progress.send_if_modified(modify);
+        self.metrics.execution_lag.set(self.lag() as f64);
+    }
+}
+
+/// Throttles a producer of batches or headers while execution lags behind consensus.
+///
+/// Cloning is cheap, all clones observe the same [`ExecutionLag`].
+#[derive(Debug, Clone)]
+pub struct Backpressure {
+    progress: watch::Receiver<ExecutionProgress>,
+    max_lag: u64,
+}
+
+impl Backpressure {
+    /// Returns the number of committed sub-dags that were not executed yet.
+    pub fn lag(&self) -> u64 {
+        self.progress.borrow().lag()
+    }
+
+    /// Returns `true` if the lag exceeds the configured maximum.
+    pub fn is_throttled(&self) -> bool {
+        self.lag() > self.max_lag
+    }
+
+    /// Waits until the lag is at most the configured maximum, returns immediately if it already
+    /// is.
+    ///
+    /// Never returns if every [`ExecutionLag`] was dropped while throttled, since nothing is
+    /// executed anymore.
+    pub async fn released(&mut self) {
+        let max_lag = self.max_lag;
+        if self.progress.wait_for(|progress| progress.lag() <= max_lag).await.is_err() {
+            std::future::pending::<()>().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn track_lag() {
+        let lag = ExecutionLag::new(10);
+        let backpressure = lag.backpressure(BackpressureConfig { max_execution_lag: 2 });
+        assert_eq!(lag.lag(), 0);
+
+        for index in 10..13 {
+            lag.on_committed(index);
+        }
+        assert_eq!(lag.progress(), ExecutionProgress { next_committed: 13, next_executed: 10 });
+        assert!(backpressure.is_throttled());
+
+        // a sub-dag that is reported again is only counted once
+        lag.on_committed(11);
+        lag.on_executed(10);
+        assert_eq!(backpressure.lag(), 2);
+        assert!(!backpressure.is_throttled());
+
+        // the executor may report a sub-dag before the consensus task does
+        lag.on_executed(13);
+        assert_eq!(lag.progress(), ExecutionProgress { next_committed: 14, next_executed: 14 });
+    }
+
+    #[tokio::test]
+    async fn release_when_caught_up() {
+        let lag = ExecutionLag::new(0);
+        let mut backpressure = lag.backpressure(BackpressureConfig { max_execution_lag: 1 });
+        backpressure.released().await;
+
+        for index in 0..3 {
+            lag.on_committed(index);
+        }
+        let released = tokio::spawn(async move { backpressure.released().await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!released.is_finished());
+
+        lag.on_executed(0);
+        lag.on_executed(1);
+        tokio::time::timeout(Duration::from_secs(1), released).await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,325 @@
+//! The Bullshark commit rule.
+//!
+//! Every even round has a leader, elected by the [`LeaderElector`]. The leader of round `r` is
+//! decided once the DAG reaches round `r + 2`, since a certificate of round `r + 1` that arrives
+//! late may still add the missing support, see [`direct_decision`]. A supported leader is
+//! committed together with the earlier uncommitted leaders it links to, oldest first, and every
+//! committed leader orders its causal history with [`Dag::commit`].
+//!
+//! Every validator inserts the same certificates into its DAG, so every validator commits the same
+//! leaders in the same order, and orders the same certificates for each of them.
+//!
+//! With the [`FastPath`](crate::fast_path::FastPath), a leader whose following round was
+//! certified by every authority is decided without waiting for the round after, see
+//! [`Bullshark::try_commit_unanimous`].
+//!
+//! Besides the commits, the commit rule keeps the [`LeaderDecision`] of every leader round it
+//! decides, including the skipped ones, for the
+//! [`CommitAuditLog`](crate::commit_log::CommitAuditLog) and the RPC.
+
+use crate::{
+    commit_log::{CommitDecision, CommitOutcome},
+    committee::Committee,
+    dag::Dag,
+    fast_path::direct_decision,
+    leader::LeaderElector,
+    types::{Certificate, CertificateDigest, DagVertex, Round},
+};
+use reth_narwhal_verifier::AuthorityIndex;
+
+/// A leader committed by [`Bullshark`], with the certificates it orders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedLeader {
+    /// The round of the leader.
+    pub round: Round,
+    /// The leader.
+    pub leader: AuthorityIndex,
+    /// The newly committed certificates in commit order, ending with the leader's.
+    pub certificates: Vec<Certificate>,
+}
+
+/// The decision of [`Bullshark`] on the leader of a round, with the certificates it was taken on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderDecision {
+    /// The decision.
+    pub decision: CommitDecision,
+    /// The digest of the leader's certificate, `None` if the DAG has none.
+    pub leader_certificate: Option<CertificateDigest>,
+    /// The certificates of the following round a direct decision was taken on, empty for an
+    /// indirect commit.
+    pub next_round: Vec<Certificate>,
+}
+
+/// Runs the commit rule over a [`Dag`], see the [module docs](self).
+#[derive(Debug)]
+pub struct Bullshark {
+    elector: LeaderElector,
+    /// The last leader round the commit rule decided.
+    decided: Round,
+    /// The round of the last committed leader.
+    last_committed: Round,
+    /// The decisions since the last [`Bullshark::take_decisions`].
+    decisions: Vec<LeaderDecision>,
+}
+
+impl Bullshark {
+    /// Creates the commit rule of an epoch that starts at the genesis round, with the elector of
+    /// the leaders of the epoch's committee.
+    pub const fn new(elector: LeaderElector) -> Self {
+        Self::resume(elector, 0)
+    }
+
+    /// Creates the commit rule of an epoch whose leaders were committed up to the given round,
+    /// e.g. after a restart.
+    ///
+    /// The elector must have recorded the commits of the epoch up to that round.
+    pub const fn resume(elector: LeaderElector, last_committed: Round) -> Self {
+        // leader rounds are even
+        let decided = last_committed - last_committed % 2;
+        Self { elector, decided, last_committed: decided, decisions: Vec::new() }
+    }
+
+    /// Returns the round of the last committed leader.
+    pub const fn last_committed(&self) -> Round {
+        self.last_committed
+    }
+
+    /// Returns the last leader round the commit rule decided.
+    pub const fn decided(&self) -> Round {
+        self.decided
+    }
+
+    /// Returns the decisions taken since the last call, in the order they were taken.
+    ///
+    /// A leader that was skipped and later committed indirectly has two decisions, the later one
+    /// replaces the earlier one.
+    pub fn take_decisions(&mut self) -> Vec<LeaderDecision> {
+        std::mem::take(&mut self.decisions)
+    }
+
+    /// Returns the leader of a round, `None` for rounds without a leader.
+    pub fn leader(&self, round: Round) -> Option<AuthorityIndex> {
+        self.elector.leader(round)
+    }
+
+    /// Decides the leaders whose following round is complete, and commits the supported ones
+    /// together with the earlier leaders they link to.
+    ///
+    /// Returns the committed leaders in commit order.
+    pub fn try_commit(&mut self, committee: &Committee, dag: &mut Dag) -> Vec<CommittedLeader> {
+        let mut committed = Vec::new();
+        let highest = dag.highest_round().unwrap_or_default();
+        while self.decided + 4 <= highest {
+            self.decide(committee, dag, self.decided + 2, &mut committed);
+        }
+        committed
+    }
+
+    /// Decides the leader of the given round before the DAG reaches the round after next, because
+    /// every authority certified the following round, see [`crate::fast_path`].
+    ///
+    /// The leader is only decided if its round is the next one to decide and the DAG has the
+    /// certificates of every authority of the following round, so that no late certificate can
+    /// change the decision. Returns the committed leaders in commit order.
+    pub fn try_commit_unanimous(
+        &mut self,
+        committee: &Committee,
+        dag: &mut Dag,
+        round: Round,
+    ) -> Vec<CommittedLeader> {
+        let mut committed = Vec::new();
+        let unanimous = dag.round(round + 1).count() == committee.authorities.len();
+        if round == self.decided + 2 && unanimous {
+            self.decide(committee, dag, round, &mut committed);
+        }
+        committed
+    }
+
+    /// Decides the leader of the round after the last decided one, and commits it together with
+    /// the earlier leaders it links to if it's supported.
+    fn decide(
+        &mut self,
+        committee: &Committee,
+        dag: &mut Dag,
+        round: Round,
+        committed: &mut Vec<CommittedLeader>,
+    ) {
+        self.decided = round;
+        let Some(leader) = self.elector.leader(round) else { return };
+        let leader_certificate = dag.vertex(round, leader).map(DagVertex::digest);
+        let next_round =
+            dag.round(round + 1).map(DagVertex::certificate).cloned().collect::<Vec<_>>();
+        let decision = direct_decision(committee, round, leader, leader_certificate, &next_round);
+        self.decisions.push(LeaderDecision { decision, leader_certificate, next_round });
+        let Some(digest) = leader_certificate.filter(|_| decision.outcome.is_committed()) else {
+            return
+        };
+
+        let mut leaders = vec![(round, leader, digest)];
+        let (mut linked, mut earlier) = (digest, round - 2);
+        while earlier > self.last_committed {
+            let vertex = self
+                .elector
+                .leader(earlier)
+                .and_then(|leader| dag.vertex(earlier, leader))
+                .filter(|vertex| dag.is_linked(&linked, &vertex.digest()));
+            if let Some(vertex) = vertex {
+                linked = vertex.digest();
+                leaders.push((earlier, vertex.author(), linked));
+            }
+            earlier -= 2;
+        }
+        for (earlier, leader, digest) in leaders.into_iter().rev() {
+            let certificates =
+                dag.commit(&digest).into_iter().map(DagVertex::into_certificate).collect();
+            self.elector.record_commit(earlier);
+            if earlier != round {
+                self.decisions.push(LeaderDecision {
+                    decision: CommitDecision {
+                        round: earlier,
+                        leader,
+                        outcome: CommitOutcome::CommittedIndirectly { by_round: round },
+                    },
+                    leader_certificate: Some(digest),
+                    next_round: Vec::new(),
+                });
+            }
+            committed.push(CommittedLeader { round: earlier, leader, certificates });
+        }
+        self.last_committed = round;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dev::DevCommittee, leader::LeaderSchedule, types::Header};
+
+    /// Inserts `rounds` rounds in which the given authorities reference every certificate of the
+    /// previous round.
+    fn extend(dag: &mut Dag, rounds: std::ops::RangeInclusive<Round>, authors: &[AuthorityIndex]) {
+        for round in rounds {
+            let mut parents = dag.round(round - 1).map(DagVertex::digest).collect::<Vec<_>>();
+            parents.sort_unstable();
+            for &author in authors {
+                let header =
+                    Header { round, author, parents: parents.clone(), ..Default::default() };
+                let certificate = Certificate { header, ..Default::default() };
+                dag.insert(DagVertex::new(certificate)).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn commits_supported_leaders() {
+        let dev = DevCommittee::new(4);
+        let committee = dev.committee();
+        let mut bullshark =
+            Bullshark::new(LeaderElector::new(LeaderSchedule::RoundRobin, committee));
+        let mut dag = Dag::new(0);
+        for certificate in Certificate::genesis(committee.epoch, 0..4) {
+            dag.insert(DagVertex::new(certificate)).unwrap();
+        }
+
+        // the leader of round 2 is decided once the DAG reaches round 4
+        extend(&mut dag, 1..=3, &[0, 1, 2, 3]);
+        assert!(bullshark.try_commit(committee, &mut dag).is_empty());
+        extend(&mut dag, 4..=4, &[0, 1, 2, 3]);
+        let committed = bullshark.try_commit(committee, &mut dag);
+        assert_eq!(committed.len(), 1);
+        assert_eq!(committed[0].round, 2);
+        // the genesis round, round 1 and the leader
+        assert_eq!(committed[0].certificates.len(), 4 + 4 + 1);
+        assert_eq!(committed[0].certificates.last().unwrap().author(), committed[0].leader);
+        assert_eq!(bullshark.last_committed(), 2);
+        let decisions = bullshark.take_decisions();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].decision.outcome, CommitOutcome::Committed { support: 4 });
+        assert_eq!(decisions[0].next_round.len(), 4);
+        assert!(bullshark.take_decisions().is_empty());
+
+        // a resumed commit rule continues after the committed leader
+        let mut resumed =
+            Bullshark::resume(LeaderElector::new(LeaderSchedule::RoundRobin, committee), 3);
+        assert_eq!((resumed.decided(), resumed.last_committed()), (2, 2));
+        extend(&mut dag, 5..=6, &[0, 1, 2, 3]);
+        let committed = resumed.try_commit(committee, &mut dag);
+        assert_eq!(committed.iter().map(|commit| commit.round).collect::<Vec<_>>(), [4]);
+    }
+
+    #[test]
+    fn commits_unanimous_round() {
+        let dev = DevCommittee::new(4);
+        let committee = dev.committee();
+        let mut bullshark =
+            Bullshark::new(LeaderElector::new(LeaderSchedule::RoundRobin, committee));
+        let mut dag = Dag::new(0);
+        for certificate in Certificate::genesis(committee.epoch, 0..4) {
+            dag.insert(DagVertex::new(certificate)).unwrap();
+        }
+
+        // round 3 is not unanimous yet
+        extend(&mut dag, 1..=2, &[0, 1, 2, 3]);
+        extend(&mut dag, 3..=3, &[0, 1, 2]);
+        assert!(bullshark.try_commit_unanimous(committee, &mut dag, 2).is_empty());
+        assert_eq!(bullshark.decided(), 0);
+
+        let parents = dag.round(2).map(DagVertex::digest).collect();
+        let header = Header { round: 3, author: 3, parents, ..Default::default() };
+        dag.insert(DagVertex::new(Certificate { header, ..Default::default() })).unwrap();
+        let committed = bullshark.try_commit_unanimous(committee, &mut dag, 2);
+        assert_eq!(committed.iter().map(|commit| commit.round).collect::<Vec<_>>(), [2]);
+        assert_eq!((bullshark.decided(), bullshark.last_committed()), (2, 2));
+
+        // the regular commit rule continues with the next leader
+        extend(&mut dag, 4..=4, &[0, 1, 2, 3]);
+        assert!(bullshark.try_commit(committee, &mut dag).is_empty());
+    }
+
+    #[test]
+    fn commits_linked_earlier_leader() {
+        let dev = DevCommittee::new(4);
+        let committee = dev.committee();
+        let mut bullshark =
+            Bullshark::new(LeaderElector::new(LeaderSchedule::RoundRobin, committee));
+        let mut dag = Dag::new(0);
+        for certificate in Certificate::genesis(committee.epoch, 0..4) {
+            dag.insert(DagVertex::new(certificate)).unwrap();
+        }
+
+        // only the leader of round 2 references its own certificate in round 3
+        let leader = LeaderElector::new(LeaderSchedule::RoundRobin, committee).leader(2).unwrap();
+        extend(&mut dag, 1..=2, &[0, 1, 2, 3]);
+        extend(&mut dag, 3..=3, &[leader]);
+        let mut parents = dag
+            .round(2)
+            .filter(|vertex| vertex.author() != leader)
+            .map(DagVertex::digest)
+            .collect::<Vec<_>>();
+        parents.sort_unstable();
+        for author in (0..4).filter(|author| *author != leader) {
+            let header =
+                Header { round: 3, author, parents: parents.clone(), ..Default::default() };
+            dag.insert(DagVertex::new(Certificate { header, ..Default::default() })).unwrap();
+        }
+        extend(&mut dag, 4..=6, &[0, 1, 2, 3]);
+
+        // the leader of round 2 lacks support, but the leader of round 4 links to it
+        let committed = bullshark.try_commit(committee, &mut dag);
+        assert_eq!(committed.iter().map(|commit| commit.round).collect::<Vec<_>>(), [2, 4]);
+        assert_eq!(bullshark.last_committed(), 4);
+        let outcomes = bullshark
+            .take_decisions()
+            .into_iter()
+            .map(|decided| (decided.decision.round, decided.decision.outcome))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            [
+                (2, CommitOutcome::InsufficientSupport { support: 1, threshold: 2 }),
+                (4, CommitOutcome::Committed { support: 4 }),
+                (2, CommitOutcome::CommittedIndirectly { by_round: 4 }),
+            ]
+        );
+    }
+}
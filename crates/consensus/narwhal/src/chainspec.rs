@@ -0,0 +1,169 @@
+//! Narwhal specific chain parameters.
+
+use crate::{
+    deposits::DepositConfig,
+    sequencing::{NonceGapPolicy, PermissionedConfig, SequencingFilterRules},
+    timestamp::TimestampPolicy,
+    worker::{BatchQuotaConfig, TransactionSizeLimits},
+};
+use reth_primitives::Genesis;
+use serde::{Deserialize, Serialize};
+
+/// The key of the narwhal section in the genesis `config` object.
+pub const NARWHAL_GENESIS_KEY: &str = "narwhal";
+
+/// Narwhal specific chain parameters.
+///
+/// These are part of the chain specification because every validator must apply them identically
+/// when turning consensus output into blocks. They are read from the `narwhal` object of the
+/// genesis `config`:
+///
+/// ```json
+/// {
+///   "config": {
+///     "chainId": 1337,
+///     "narwhal": {
+///       "nonceGapPolicy": "defer",
+///       "batchQuota": { "batchesPerRound": 100 },
+///       "transactionSizeLimits": { "maxTransactionSize": 131072, "maxCalldataSize": 65536 },
+///       "sequencingFilters": [
+///         { "fromBlock": 0, "senders": ["0x000000000000000000000000000000000000dead"] }
+///       ],
+///       "permissioned": { "fromBlock": 1, "genesisSenders": [] },
+///       "timestampPolicy": "medianCertificates",
+///       "fastPathCommit": true,
+///       "blockGasLimit": 30000000,
+///       "deposits": {
+///         "l1Bridge": "0x00000000000000000000000000000000000000b1",
+///         "sender": "0x00000000000000000000000000000000000000d1"
+///       }
+///     }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NarwhalChainInfo {
+    /// How sequenced transactions with a future nonce are handled at execution time.
+    pub nonce_gap_policy: NonceGapPolicy,
+    /// Caps the batches referenced by a header at the author's share of stake, disabled if
+    /// `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_quota: Option<BatchQuotaConfig>,
+    /// Caps the size of every sequenced transaction, unlimited by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_size_limits: Option<TransactionSizeLimits>,
+    /// Versions of the rules of the
+    /// [`ChainSequencingFilter`](crate::sequencing::ChainSequencingFilter), transactions are not
+    /// filtered if empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sequencing_filters: Vec<SequencingFilterRules>,
+    /// Restricts the senders to the allowlist of the
+    /// [`SENDER_REGISTRY`](crate::predeploys::SENDER_REGISTRY), disabled if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permissioned: Option<PermissionedConfig>,
+    /// How the timestamps of the blocks are derived from the committed certificates.
+    pub timestamp_policy: TimestampPolicy,
+    /// Lets the committer commit the leaders of unanimous rounds one round earlier, see
+    /// [`crate::fast_path`].
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub fast_path_commit: bool,
+    /// The gas limit of every block built from consensus output, the gas limit of the parent
+    /// block if `None`.
+    ///
+    /// The transactions of a commit that exceed it are split across consecutive blocks, see
+    /// [`split_by_gas_limit`](crate::sequencing::split_by_gas_limit).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_gas_limit: Option<u64>,
+    /// The deposits of the L1 bridge, see [`crate::deposits`], disabled if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deposits: Option<DepositConfig>,
+}
+
+impl NarwhalChainInfo {
+    /// Extracts the narwhal chain parameters from the genesis config.
+    ///
+    /// Returns the default parameters if the genesis does not have a `narwhal` section.
+    pub fn from_genesis(genesis: &Genesis) -> Result<Self, serde_json::Error> {
+        genesis
+            .config
+            .extra_fields
+            .get_deserialized::<Self>(NARWHAL_GENESIS_KEY)
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequencing::FilterMode;
+    use reth_primitives::Address;
+
+    #[test]
+    fn parse_narwhal_chain_info() {
+        let genesis: Genesis = serde_json::from_str(
+            r#"{
+                "config": {
+                    "chainId": 1337,
+                    "narwhal": {
+                        "nonceGapPolicy": "drop",
+                        "batchQuota": { "batchesPerRound": 100 },
+                        "transactionSizeLimits": { "maxCalldataSize": 65536 },
+                        "sequencingFilters": [{ "fromBlock": 10, "mode": "allow" }],
+                        "timestampPolicy": "leader",
+                        "fastPathCommit": true,
+                        "blockGasLimit": 30000000,
+                        "deposits": {
+                            "l1Bridge": "0x00000000000000000000000000000000000000b1",
+                            "sender": "0x00000000000000000000000000000000000000d1"
+                        }
+                    }
+                },
+                "difficulty": "0x0",
+                "gasLimit": "0x1c9c380",
+                "alloc": {}
+            }"#,
+        )
+        .unwrap();
+
+        let info = NarwhalChainInfo::from_genesis(&genesis).unwrap();
+        assert_eq!(info.nonce_gap_policy, NonceGapPolicy::Drop);
+        assert_eq!(info.batch_quota, Some(BatchQuotaConfig { batches_per_round: 100 }));
+        assert_eq!(
+            info.transaction_size_limits,
+            Some(TransactionSizeLimits {
+                max_transaction_size: None,
+                max_calldata_size: Some(65536)
+            })
+        );
+        assert_eq!(
+            info.sequencing_filters,
+            vec![SequencingFilterRules {
+                from_block: 10,
+                mode: FilterMode::Allow,
+                ..Default::default()
+            }]
+        );
+        assert_eq!(info.timestamp_policy, TimestampPolicy::Leader);
+        assert!(info.fast_path_commit);
+        assert_eq!(info.block_gas_limit, Some(30_000_000));
+        let deposits = info.deposits.unwrap();
+        assert_eq!(deposits.sender, Address::with_last_byte(0xd1));
+        assert_eq!(deposits.gas_limit, 200_000);
+    }
+
+    #[test]
+    fn missing_narwhal_chain_info() {
+        let info = NarwhalChainInfo::from_genesis(&Genesis::default()).unwrap();
+        assert_eq!(info, NarwhalChainInfo::default());
+        assert_eq!(info.nonce_gap_policy, NonceGapPolicy::Defer);
+        assert_eq!(info.batch_quota, None);
+        assert_eq!(info.transaction_size_limits, None);
+        assert_eq!(info.permissioned, None);
+        assert_eq!(info.timestamp_policy, TimestampPolicy::MedianCertificates);
+        assert!(!info.fast_path_commit);
+        assert_eq!(info.block_gas_limit, None);
+        assert_eq!(info.deposits, None);
+    }
+}
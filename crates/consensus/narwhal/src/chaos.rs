@@ -0,0 +1,224 @@
+//! Fault injection for resilience testing.
+//!
+//! With a [`ChaosConfig`], a node randomly misbehaves at low probability: the [`ChaosTransport`]
+//! delays and drops the messages of its workers, and the [`Primary`](crate::primary::Primary)
+//! simulates crashes by restarting from its [`DagStore`](crate::dag_store::DagStore). This lets
+//! operators check on a staging network that their monitoring notices the faults and that the
+//! node recovers from them. The committee tolerates the faults like those of any other faulty
+//! authority, but a node with chaos enabled should never run as a production validator.
+
+use crate::worker::{WorkerMessage, WorkerTransport};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+use std::{future::Future, io, pin::Pin, time::Duration};
+use tracing::trace;
+
+/// One million, the unit of the probabilities of the [`ChaosConfig`].
+const PPM: u32 = 1_000_000;
+
+/// The faults a node injects, disabled by default.
+///
+/// Probabilities are given in parts per million.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ChaosConfig {
+    /// The probability that a message of a worker is delayed.
+    pub delay_ppm: u32,
+    /// The maximum time a message is delayed, the delay is chosen uniformly up to it.
+    #[serde(with = "humantime_serde")]
+    pub max_delay: Duration,
+    /// The probability that a message of a worker is dropped.
+    pub drop_ppm: u32,
+    /// The probability that the primary crashes after handling a batch, a certificate or a
+    /// timeout.
+    pub crash_ppm: u32,
+    /// The time a crashed primary stays down before it restarts.
+    #[serde(with = "humantime_serde")]
+    pub restart_delay: Duration,
+    /// The seed of the random faults, which makes them reproducible, random if not set.
+    pub seed: Option<u64>,
+}
+
+impl ChaosConfig {
+    /// Returns the faults of `--narwhal.chaos`, which are rare enough to keep a staging network
+    /// running.
+    pub const fn staging() -> Self {
+        Self {
+            delay_ppm: 10_000,
+            max_delay: Duration::from_millis(200),
+            drop_ppm: 1_000,
+            crash_ppm: 10,
+            restart_delay: Duration::from_secs(2),
+            seed: None,
+        }
+    }
+
+    /// Returns `true` if the node injects any faults.
+    pub const fn is_enabled(&self) -> bool {
+        self.delay_ppm > 0 || self.drop_ppm > 0 || self.crash_ppm > 0
+    }
+}
+
+/// Draws the faults of a [`ChaosConfig`].
+#[derive(Debug, Clone)]
+pub struct Chaos {
+    config: ChaosConfig,
+    rng: StdRng,
+    metrics: ChaosMetrics,
+}
+
+impl Chaos {
+    /// Creates the faults of the configuration, seeded from the configuration if it has a seed.
+    pub fn new(config: ChaosConfig) -> Self {
+        let rng = config.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        Self { config, rng, metrics: ChaosMetrics::default() }
+    }
+
+    /// Returns `true` with a probability in parts per million.
+    fn happens(&mut self, ppm: u32) -> bool {
+        ppm > 0 && self.rng.gen_range(0..PPM) < ppm
+    }
+
+    /// Returns `true` if the next message is dropped.
+    pub fn drop_message(&mut self) -> bool {
+        let drop = self.happens(self.config.drop_ppm);
+        if drop {
+            self.metrics.dropped_messages.increment(1);
+        }
+        drop
+    }
+
+    /// Returns the delay of the next message, `None` if it's sent right away.
+    pub fn delay(&mut self) -> Option<Duration> {
+        if !self.happens(self.config.delay_ppm) {
+            return None
+        }
+        self.metrics.delayed_messages.increment(1);
+        Some(self.config.max_delay.mul_f64(self.rng.gen()))
+    }
+
+    /// Returns `true` if the primary crashes now.
+    pub fn crash(&mut self) -> bool {
+        let crash = self.happens(self.config.crash_ppm);
+        if crash {
+            self.metrics.crashes.increment(1);
+        }
+        crash
+    }
+
+    /// Returns the time a crashed primary stays down.
+    pub const fn restart_delay(&self) -> Duration {
+        self.config.restart_delay
+    }
+}
+
+/// A [`WorkerTransport`] that delays and drops the messages of the transport it wraps.
+///
+/// A delayed message holds up the messages sent after it, like a congested link.
+#[derive(Debug)]
+pub struct ChaosTransport<T> {
+    inner: T,
+    chaos: Chaos,
+}
+
+impl<T> ChaosTransport<T> {
+    /// Wraps the transport.
+    pub const fn new(inner: T, chaos: Chaos) -> Self {
+        Self { inner, chaos }
+    }
+}
+
+impl<T: WorkerTransport> WorkerTransport for ChaosTransport<T> {
+    fn send<'a>(
+        &'a mut self,
+        to: AuthorityIndex,
+        message: &'a WorkerMessage,
+    ) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>> {
+        if self.chaos.drop_message() {
+            trace!(target: "consensus::narwhal", to, "Chaos dropped worker message");
+            return Box::pin(async { Ok(()) })
+        }
+        let delay = self.chaos.delay();
+        Box::pin(async move {
+            if let Some(delay) = delay {
+                trace!(target: "consensus::narwhal", to, ?delay, "Chaos delayed worker message");
+                tokio::time::sleep(delay).await;
+            }
+            self.inner.send(to, message).await
+        })
+    }
+}
+
+/// Metrics of the injected faults.
+#[derive(Clone, Metrics)]
+#[metrics(scope = "narwhal.chaos")]
+struct ChaosMetrics {
+    /// Number of delayed worker messages
+    delayed_messages: Counter,
+    /// Number of dropped worker messages
+    dropped_messages: Counter,
+    /// Number of simulated primary crashes
+    crashes: Counter,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BatchDigest;
+
+    /// Records the messages it sends.
+    #[derive(Debug, Default)]
+    struct Recorder(Vec<AuthorityIndex>);
+
+    impl WorkerTransport for Recorder {
+        fn send<'a>(
+            &'a mut self,
+            to: AuthorityIndex,
+            _message: &'a WorkerMessage,
+        ) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>> {
+            self.0.push(to);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let mut chaos = Chaos::new(ChaosConfig::default());
+        assert!(!ChaosConfig::default().is_enabled());
+        assert!(ChaosConfig::staging().is_enabled());
+        for _ in 0..1_000 {
+            assert!(!chaos.drop_message());
+            assert!(!chaos.crash());
+            assert_eq!(chaos.delay(), None);
+        }
+    }
+
+    #[test]
+    fn seeded_faults_are_reproducible() {
+        let config = ChaosConfig { drop_ppm: PPM / 2, seed: Some(7), ..Default::default() };
+        let draws = |mut chaos: Chaos| (0..64).map(|_| chaos.drop_message()).collect::<Vec<_>>();
+        let first = draws(Chaos::new(config));
+        assert_eq!(first, draws(Chaos::new(config)));
+        assert!(first.contains(&true) && first.contains(&false));
+    }
+
+    #[tokio::test]
+    async fn drop_and_delay_messages() {
+        let message = WorkerMessage::Ack { digest: BatchDigest::default() };
+        let config = ChaosConfig { drop_ppm: PPM, ..Default::default() };
+        let mut transport = ChaosTransport::new(Recorder::default(), Chaos::new(config));
+        transport.send(1, &message).await.unwrap();
+        assert!(transport.inner.0.is_empty());
+
+        let config = ChaosConfig {
+            delay_ppm: PPM,
+            max_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let mut transport = ChaosTransport::new(Recorder::default(), Chaos::new(config));
+        transport.send(2, &message).await.unwrap();
+        assert_eq!(transport.inner.0, [2]);
+    }
+}
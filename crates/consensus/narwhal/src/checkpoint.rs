@@ -0,0 +1,215 @@
+//! Proofs of block ranges that are exported to other chains.
+//!
+//! Bridges that verify every block of a narwhal chain would pay for one proof verification per
+//! block. Instead, the committee of an epoch signs a [`CheckpointRange`], which commits to the
+//! hashes of a contiguous range of blocks with the root of the
+//! [`blocks`](reth_narwhal_verifier::blocks) tree. The signatures are aggregated by a
+//! [`CheckpointRangeAggregator`] into a single [`CheckpointRangeProof`] that is verified with one
+//! committee lookup, and every block of the range is then proven with a [`RangeBlockProof`].
+
+use crate::signature::{AggregateScheme, VoteError};
+use alloy_primitives::{BlockNumber, B256};
+use reth_narwhal_verifier::{
+    abi::{CheckpointRange, CheckpointRangeProof},
+    blocks::{block_proof, blocks_root},
+    AuthorityIndex, Epoch, SigningDomain, Stake, VerifierCommittee,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// Returns the range of the given blocks of an epoch, starting at `first_block`.
+///
+/// The block hashes must be given in block order and the state root is the state root of the last
+/// block. Returns `None` if there are no blocks.
+pub fn checkpoint_range(
+    epoch: Epoch,
+    first_block: BlockNumber,
+    block_hashes: &[B256],
+    state_root: B256,
+) -> Option<CheckpointRange> {
+    let last_block = first_block.checked_add(block_hashes.len().checked_sub(1)? as u64)?;
+    Some(CheckpointRange {
+        epoch,
+        firstBlock: first_block,
+        lastBlock: last_block,
+        blocksRoot: blocks_root(block_hashes),
+        stateRoot: state_root,
+    })
+}
+
+/// Proof that a block is part of a [`CheckpointRange`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeBlockProof {
+    /// The number of the block.
+    pub number: BlockNumber,
+    /// The hash of the block.
+    pub hash: B256,
+    /// The sibling hashes on the path from the block hash to the blocks root of the range,
+    /// starting with the sibling of the block hash.
+    pub proof: Vec<B256>,
+}
+
+impl RangeBlockProof {
+    /// Returns the proof of the block with the given number, given the hashes of all blocks of
+    /// the range in block order.
+    ///
+    /// Returns `None` if the block is not part of the range.
+    pub fn new(
+        range: &CheckpointRange,
+        block_hashes: &[B256],
+        number: BlockNumber,
+    ) -> Option<Self> {
+        if !range.contains(number) {
+            return None
+        }
+        let index = usize::try_from(number - range.firstBlock).ok()?;
+        let hash = *block_hashes.get(index)?;
+        Some(Self { number, hash, proof: block_proof(block_hashes, index)? })
+    }
+
+    /// Returns `true` if the block is part of the range.
+    pub fn verify(&self, range: &CheckpointRange) -> bool {
+        range.verify_block(self.number, self.hash, &self.proof)
+    }
+}
+
+/// Collects the signatures of the committee over a [`CheckpointRange`] until they form a quorum,
+/// and aggregates them into a [`CheckpointRangeProof`].
+///
+/// The committee must be the committee of the range's epoch.
+pub struct CheckpointRangeAggregator<S: AggregateScheme> {
+    range: CheckpointRange,
+    /// The message the signers sign.
+    message: B256,
+    signatures: BTreeMap<AuthorityIndex, S::Signature>,
+    stake: Stake,
+    proven: bool,
+}
+
+impl<S: AggregateScheme> CheckpointRangeAggregator<S> {
+    /// Creates an aggregator for the signatures over the range on the network of the domain.
+    pub fn new(range: CheckpointRange, domain: &SigningDomain) -> Self {
+        let message = range.signing_message(domain);
+        Self { range, message, signatures: BTreeMap::new(), stake: 0, proven: false }
+    }
+
+    /// Returns the message the signers sign.
+    pub const fn message(&self) -> B256 {
+        self.message
+    }
+
+    /// Verifies and records the signature of an authority.
+    ///
+    /// Returns the proof once the signers form a quorum of the committee. Signatures that arrive
+    /// after that are ignored.
+    pub fn add_signature(
+        &mut self,
+        committee: &VerifierCommittee<S::PublicKey>,
+        signer: AuthorityIndex,
+        signature: S::Signature,
+    ) -> Result<Option<CheckpointRangeProof>, VoteError> {
+        if self.proven {
+            return Ok(None)
+        }
+        let authority =
+            committee.authority(signer).ok_or(VoteError::UnknownVoter { voter: signer })?;
+        if self.signatures.contains_key(&signer) {
+            return Err(VoteError::DuplicateVote { voter: signer })
+        }
+        if !S::verify_aggregate(&[&authority.public_key], self.message.as_slice(), &signature) {
+            return Err(VoteError::InvalidSignature { voter: signer })
+        }
+
+        self.signatures.insert(signer, signature);
+        self.stake = self.stake.saturating_add(authority.stake);
+        if self.stake < committee.quorum_threshold() {
+            return Ok(None)
+        }
+
+        self.proven = true;
+        let signatures = std::mem::take(&mut self.signatures);
+        let signers = signatures.keys().copied().collect::<Vec<_>>();
+        let signatures = signatures.into_values().collect::<Vec<_>>();
+        let Some(signature) = S::aggregate(&signatures) else { return Ok(None) };
+        Ok(CheckpointRangeProof::new(
+            self.range.clone(),
+            signers,
+            S::encode_signature(&signature).into(),
+        ))
+    }
+}
+
+impl<S: AggregateScheme> fmt::Debug for CheckpointRangeAggregator<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckpointRangeAggregator")
+            .field("range", &self.range)
+            .field("signers", &self.signatures.keys().collect::<Vec<_>>())
+            .field("stake", &self.stake)
+            .field("proven", &self.proven)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{Bls12381, BlsSecretKey};
+    use alloy_primitives::keccak256;
+    use reth_narwhal_verifier::{
+        abi::verify_checkpoint_range_proof, VerificationError, VerifierAuthority,
+    };
+
+    #[test]
+    fn prove_block_range() {
+        let secret_keys =
+            (0..4u8).map(|seed| BlsSecretKey::from_seed(&[seed; 32]).unwrap()).collect::<Vec<_>>();
+        let committee = VerifierCommittee::new(
+            2,
+            secret_keys
+                .iter()
+                .map(|key| VerifierAuthority { public_key: Bls12381::public_key(key), stake: 1 })
+                .collect(),
+        );
+        let domain = SigningDomain::new(1337, B256::ZERO);
+
+        let block_hashes = (100..110u64).map(|n| keccak256(n.to_be_bytes())).collect::<Vec<_>>();
+        assert_eq!(checkpoint_range(2, 100, &[], B256::ZERO), None);
+        let range = checkpoint_range(2, 100, &block_hashes, B256::with_last_byte(1)).unwrap();
+        assert_eq!((range.firstBlock, range.lastBlock), (100, 109));
+
+        let mut aggregator = CheckpointRangeAggregator::<Bls12381>::new(range.clone(), &domain);
+        let sign =
+            |signer: usize| Bls12381::sign(&secret_keys[signer], aggregator.message().as_slice());
+        let (signature_0, signature_2, signature_3) = (sign(0), sign(2), sign(3));
+        assert_eq!(aggregator.add_signature(&committee, 3, signature_3), Ok(None));
+        assert_eq!(
+            aggregator.add_signature(&committee, 2, signature_0),
+            Err(VoteError::InvalidSignature { voter: 2 })
+        );
+        assert_eq!(aggregator.add_signature(&committee, 0, signature_0), Ok(None));
+        let proof = aggregator.add_signature(&committee, 2, signature_2).unwrap().unwrap();
+        assert_eq!(proof.signer_indices(), vec![0, 2, 3]);
+
+        // one verification for the whole range
+        let verified =
+            verify_checkpoint_range_proof::<Bls12381>(&committee, &domain, &proof.encode());
+        assert_eq!(verified, Ok(range.clone()));
+        let other_epoch = VerifierCommittee::new(3, committee.authorities().to_vec());
+        assert_eq!(
+            verify_checkpoint_range_proof::<Bls12381>(&other_epoch, &domain, &proof.encode()),
+            Err(VerificationError::EpochMismatch { expected: 3, got: 2 })
+        );
+
+        for number in 100..110 {
+            let block = RangeBlockProof::new(&range, &block_hashes, number).unwrap();
+            assert_eq!(block.hash, block_hashes[(number - 100) as usize]);
+            assert!(block.verify(&range));
+        }
+        assert_eq!(RangeBlockProof::new(&range, &block_hashes, 110), None);
+
+        let mut forged = RangeBlockProof::new(&range, &block_hashes, 105).unwrap();
+        forged.number = 106;
+        assert!(!forged.verify(&range));
+    }
+}
@@ -0,0 +1,278 @@
+//! Synchronous hooks that observe every block built from consensus output.
+//!
+//! Custom indexes that must not lag behind the chain can't be fed from the asynchronous
+//! notifications of execution extensions. A [`CommitHook`] registered with the
+//! [`ConsensusOutputExecutor`](crate::executor::ConsensusOutputExecutor) instead runs inline, after
+//! the block of a commit is executed and before it's sealed and submitted to the engine, with the
+//! ordered transactions, their receipts and the consensus metadata of the commit.
+//!
+//! Hooks only observe the block, they can't change it, and a failing hook doesn't affect the
+//! chain: errors and panics are logged, and a hook that fails or exceeds its time budget
+//! `max_failures` times in a row is disabled until the node restarts. Hooks block the executor
+//! while they run, so they should only do bounded work, e.g. write to a local database.
+
+use crate::types::OrderedSubDag;
+use reth_db_api::models::StoredConsensusMetadata;
+use reth_metrics::{
+    metrics::{Counter, Gauge, Histogram},
+    Metrics,
+};
+use reth_primitives::{Address, Header, Receipt, TransactionSigned};
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{error, warn};
+
+/// The error returned by a [`CommitHook`].
+pub type CommitHookError = Box<dyn std::error::Error + Send + Sync>;
+
+/// The block of a commit as seen by a [`CommitHook`].
+#[derive(Debug, Clone, Copy)]
+pub struct CommitHookInput<'a> {
+    /// The committed sub-dag the block was built from.
+    pub sub_dag: &'a OrderedSubDag,
+    /// The consensus metadata that is stored with the block.
+    pub metadata: &'a StoredConsensusMetadata,
+    /// The header of the block, complete except for its hash.
+    pub header: &'a Header,
+    /// The transactions of the block in execution order.
+    pub transactions: &'a [TransactionSigned],
+    /// The sender of every transaction.
+    pub senders: &'a [Address],
+    /// The receipt of every transaction.
+    pub receipts: &'a [Receipt],
+}
+
+/// Observes every block built from consensus output.
+pub trait CommitHook: fmt::Debug + Send + Sync {
+    /// Returns the name of the hook, which labels its logs and metrics.
+    fn name(&self) -> &str;
+
+    /// Called with every block before it's sealed and submitted to the engine.
+    ///
+    /// Blocks are passed in order. A block that isn't accepted by the engine stops the executor,
+    /// so the last block passed to a hook may not become canonical if the node stopped with an
+    /// execution error.
+    fn on_commit(&self, input: &CommitHookInput<'_>) -> Result<(), CommitHookError>;
+}
+
+/// The limits of every [`CommitHook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitHookConfig {
+    /// The time a hook may take per block. Hooks can't be interrupted, a call that exceeds the
+    /// budget counts as failed.
+    pub time_budget: Duration,
+    /// The number of consecutive failed calls after which a hook is disabled.
+    pub max_failures: u32,
+}
+
+impl Default for CommitHookConfig {
+    fn default() -> Self {
+        Self { time_budget: Duration::from_millis(50), max_failures: 3 }
+    }
+}
+
+/// Metrics of a [`CommitHook`], labeled by the name of the hook.
+#[derive(Clone, Metrics)]
+#[metrics(scope = "narwhal.commit_hooks")]
+struct CommitHookMetrics {
+    /// The time the hook took per block
+    duration: Histogram,
+    /// Number of calls that returned an error or panicked
+    errors: Counter,
+    /// Number of calls that exceeded the time budget
+    overruns: Counter,
+    /// Whether the hook was disabled after repeated failures
+    disabled: Gauge,
+}
+
+/// A registered hook with its failure state.
+#[derive(Debug)]
+struct RegisteredHook {
+    hook: Arc<dyn CommitHook>,
+    /// The number of consecutive failed calls.
+    failures: AtomicU32,
+    disabled: AtomicBool,
+    metrics: CommitHookMetrics,
+}
+
+/// The hooks registered with an executor.
+#[derive(Debug, Default)]
+pub struct CommitHooks {
+    config: CommitHookConfig,
+    hooks: Vec<RegisteredHook>,
+}
+
+impl CommitHooks {
+    /// Creates an empty registry with the given limits.
+    pub const fn new(config: CommitHookConfig) -> Self {
+        Self { config, hooks: Vec::new() }
+    }
+
+    /// Registers a hook, which is called after the hooks registered before it.
+    pub fn register(&mut self, hook: Arc<dyn CommitHook>) {
+        let metrics = CommitHookMetrics::new_with_labels(&[("hook", hook.name().to_string())]);
+        self.hooks.push(RegisteredHook {
+            hook,
+            failures: AtomicU32::new(0),
+            disabled: AtomicBool::new(false),
+            metrics,
+        });
+    }
+
+    /// Returns `true` if no hooks are registered.
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Returns the names of the hooks that were disabled after repeated failures.
+    pub fn disabled(&self) -> Vec<&str> {
+        self.hooks
+            .iter()
+            .filter(|registered| registered.disabled.load(Ordering::Relaxed))
+            .map(|registered| registered.hook.name())
+            .collect()
+    }
+
+    /// Calls every enabled hook with the block of a commit.
+    pub fn on_commit(&self, input: &CommitHookInput<'_>) {
+        for registered in &self.hooks {
+            if registered.disabled.load(Ordering::Relaxed) {
+                continue
+            }
+            let name = registered.hook.name();
+            let started = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| registered.hook.on_commit(input)));
+            let elapsed = started.elapsed();
+            registered.metrics.duration.record(elapsed.as_secs_f64());
+
+            let failed = match result {
+                Ok(Ok(())) => false,
+                Ok(Err(err)) => {
+                    registered.metrics.errors.increment(1);
+                    warn!(
+                        target: "consensus::narwhal",
+                        hook = name,
+                        %err,
+                        number = input.header.number,
+                        "Commit hook failed"
+                    );
+                    true
+                }
+                Err(_) => {
+                    registered.metrics.errors.increment(1);
+                    warn!(
+                        target: "consensus::narwhal",
+                        hook = name,
+                        number = input.header.number,
+                        "Commit hook panicked"
+                    );
+                    true
+                }
+            };
+            let overrun = elapsed > self.config.time_budget;
+            if overrun {
+                registered.metrics.overruns.increment(1);
+                warn!(
+                    target: "consensus::narwhal",
+                    hook = name,
+                    ?elapsed,
+                    budget = ?self.config.time_budget,
+                    number = input.header.number,
+                    "Commit hook exceeded its time budget"
+                );
+            }
+
+            if !failed && !overrun {
+                registered.failures.store(0, Ordering::Relaxed);
+                continue
+            }
+            let failures = registered.failures.fetch_add(1, Ordering::Relaxed) + 1;
+            if failures >= self.config.max_failures {
+                registered.disabled.store(true, Ordering::Relaxed);
+                registered.metrics.disabled.set(1.0);
+                error!(
+                    target: "consensus::narwhal",
+                    hook = name,
+                    failures,
+                    "Disabled commit hook after repeated failures"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Debug)]
+    enum Behavior {
+        Record(Mutex<Vec<u64>>),
+        Fail,
+        Panic,
+        Sleep(Duration),
+    }
+
+    #[derive(Debug)]
+    struct TestHook(&'static str, Behavior);
+
+    impl CommitHook for TestHook {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn on_commit(&self, input: &CommitHookInput<'_>) -> Result<(), CommitHookError> {
+            match &self.1 {
+                Behavior::Record(numbers) => numbers.lock().unwrap().push(input.header.number),
+                Behavior::Fail => return Err("index unavailable".into()),
+                Behavior::Panic => panic!("index corrupted"),
+                Behavior::Sleep(duration) => std::thread::sleep(*duration),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failure_isolation() {
+        let config = CommitHookConfig { time_budget: Duration::from_millis(20), max_failures: 2 };
+        let mut hooks = CommitHooks::new(config);
+        let recorder = Arc::new(TestHook("recorder", Behavior::Record(Mutex::default())));
+        hooks.register(Arc::new(TestHook("failing", Behavior::Fail)));
+        hooks.register(Arc::new(TestHook("panicking", Behavior::Panic)));
+        hooks.register(Arc::new(TestHook("slow", Behavior::Sleep(Duration::from_millis(30)))));
+        hooks.register(recorder.clone());
+
+        let sub_dag = OrderedSubDag {
+            index: 0,
+            leader: Default::default(),
+            certificates: Vec::new(),
+            batches: Vec::new(),
+            timestamp: 0,
+        };
+        let metadata = StoredConsensusMetadata::default();
+        for number in 1..=3 {
+            let header = Header { number, ..Default::default() };
+            hooks.on_commit(&CommitHookInput {
+                sub_dag: &sub_dag,
+                metadata: &metadata,
+                header: &header,
+                transactions: &[],
+                senders: &[],
+                receipts: &[],
+            });
+        }
+
+        // the failing hooks are disabled after two blocks, the others keep running
+        assert_eq!(hooks.disabled(), vec!["failing", "panicking", "slow"]);
+        let Behavior::Record(numbers) = &recorder.1 else { unreachable!() };
+        assert_eq!(*numbers.lock().unwrap(), vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,675 @@
+//! Structured log of the commit decisions of leader rounds.
+//!
+//! The commit rule elects a leader for every even round and commits its certificate once enough
+//! certificates of the following round support it. When the chain stalls, the question is always
+//! why the leaders of the recent rounds were not committed. The commit rule records a
+//! [`CommitDecision`] with a machine-readable [`CommitOutcome`] for every leader round in the
+//! [`CommitDecisionLog`], which keeps the decisions of the most recent rounds for lookup by round,
+//! e.g. with `narwhal_getCommitDecision`.
+//!
+//! Every decision is also appended to the [`CommitAuditLog`] in the [`DagStore`], together with
+//! the certificates that support the leader. Its entries are never pruned and form a hash chain,
+//! so that external auditors can fetch them with `narwhal_commitLog` and check that the commit
+//! rule was followed, see [`verify_audit_chain`] and [`CommitAuditEntry::verify_support`].
+
+use crate::{
+    committee::Committee,
+    dag_store::{DagStore, DagStoreError},
+    types::{Certificate, CertificateDigest},
+};
+use alloy_primitives::{keccak256, B256};
+use alloy_rlp::{Decodable, Encodable, RlpDecodable, RlpEncodable};
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use reth_narwhal_verifier::{AuthorityIndex, Epoch, Stake};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet},
+    sync::Arc,
+};
+use tracing::{debug, info};
+
+/// Why the leader of a round was or was not committed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum CommitOutcome {
+    /// The leader's certificate was committed.
+    Committed {
+        /// The stake of the certificates of the next round that reference the leader.
+        support: Stake,
+    },
+    /// The leader's certificate was committed indirectly, through the commit of a later leader
+    /// whose causal history contains it.
+    CommittedIndirectly {
+        /// The round of the later leader.
+        by_round: u64,
+    },
+    /// There is no certificate of the leader in the round.
+    MissingCertificate,
+    /// Not enough certificates of the next round reference the leader.
+    InsufficientSupport {
+        /// The stake of the certificates of the next round that reference the leader.
+        support: Stake,
+        /// The stake required to commit.
+        threshold: Stake,
+    },
+    /// The round timed out before the leader's certificate arrived, and the leader was skipped.
+    TimeoutSkip,
+}
+
+impl CommitOutcome {
+    /// Returns `true` if the leader was committed.
+    pub const fn is_committed(&self) -> bool {
+        matches!(self, Self::Committed { .. } | Self::CommittedIndirectly { .. })
+    }
+}
+
+/// The commit decision of a leader round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitDecision {
+    /// The leader round.
+    pub round: u64,
+    /// The leader of the round.
+    pub leader: AuthorityIndex,
+    /// Why the leader was or was not committed.
+    #[serde(flatten)]
+    pub outcome: CommitOutcome,
+}
+
+/// The RLP encoding of a [`CommitOutcome`], its kind and up to two values.
+#[derive(RlpEncodable, RlpDecodable)]
+struct OutcomeFields {
+    kind: u8,
+    first: u64,
+    second: u64,
+}
+
+impl From<&CommitOutcome> for OutcomeFields {
+    fn from(outcome: &CommitOutcome) -> Self {
+        let (kind, first, second) = match *outcome {
+            CommitOutcome::Committed { support } => (0, support, 0),
+            CommitOutcome::CommittedIndirectly { by_round } => (1, by_round, 0),
+            CommitOutcome::MissingCertificate => (2, 0, 0),
+            CommitOutcome::InsufficientSupport { support, threshold } => (3, support, threshold),
+            CommitOutcome::TimeoutSkip => (4, 0, 0),
+        };
+        Self { kind, first, second }
+    }
+}
+
+impl Encodable for CommitOutcome {
+    fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
+        OutcomeFields::from(self).encode(out)
+    }
+
+    fn length(&self) -> usize {
+        OutcomeFields::from(self).length()
+    }
+}
+
+impl Decodable for CommitOutcome {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        let OutcomeFields { kind, first, second } = OutcomeFields::decode(buf)?;
+        Ok(match kind {
+            0 => Self::Committed { support: first },
+            1 => Self::CommittedIndirectly { by_round: first },
+            2 => Self::MissingCertificate,
+            3 => Self::InsufficientSupport { support: first, threshold: second },
+            4 => Self::TimeoutSkip,
+            _ => return Err(alloy_rlp::Error::Custom("unknown commit outcome")),
+        })
+    }
+}
+
+/// Metrics of the [`CommitDecisionLog`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.commit_decisions")]
+struct CommitDecisionMetrics {
+    /// Number of leader rounds that were committed
+    committed: Counter,
+    /// Number of leader rounds that were not committed
+    skipped: Counter,
+}
+
+/// The commit decisions of the most recent leader rounds.
+#[derive(Debug)]
+pub struct CommitDecisionLog {
+    /// The maximum number of decisions kept.
+    capacity: usize,
+    decisions: BTreeMap<u64, CommitDecision>,
+    metrics: CommitDecisionMetrics,
+}
+
+impl CommitDecisionLog {
+    /// Creates a log that keeps the decisions of the given number of leader rounds.
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, decisions: BTreeMap::new(), metrics: Default::default() }
+    }
+
+    /// Records the decision of a leader round, replacing an earlier decision of the round.
+    ///
+    /// A round that was first skipped can later be committed indirectly.
+    pub fn record(&mut self, decision: CommitDecision) {
+        if decision.outcome.is_committed() {
+            self.metrics.committed.increment(1);
+            debug!(
+                target: "consensus::narwhal",
+                round = decision.round,
+                leader = decision.leader,
+                outcome = ?decision.outcome,
+                "Leader committed"
+            );
+        } else {
+            self.metrics.skipped.increment(1);
+            info!(
+                target: "consensus::narwhal",
+                round = decision.round,
+                leader = decision.leader,
+                outcome = ?decision.outcome,
+                "Leader not committed"
+            );
+        }
+
+        self.decisions.insert(decision.round, decision);
+        while self.decisions.len() > self.capacity {
+            self.decisions.pop_first();
+        }
+    }
+
+    /// Returns the decision of the given leader round, if it is still kept.
+    pub fn get(&self, round: u64) -> Option<&CommitDecision> {
+        self.decisions.get(&round)
+    }
+
+    /// Returns the decisions of the leader rounds in the given range, in ascending order.
+    pub fn range(
+        &self,
+        rounds: impl std::ops::RangeBounds<u64>,
+    ) -> impl Iterator<Item = &CommitDecision> {
+        self.decisions.range(rounds).map(|(_, decision)| decision)
+    }
+}
+
+/// A certificate of the round after a leader round that references the leader's certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, RlpEncodable, RlpDecodable)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitSupporter {
+    /// The author of the certificate.
+    pub author: AuthorityIndex,
+    /// The digest of the certificate.
+    pub certificate: CertificateDigest,
+}
+
+/// An entry of the [`CommitAuditLog`], a commit decision with the certificates it was taken on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, RlpEncodable, RlpDecodable)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitAuditEntry {
+    /// The position of the entry in the log.
+    pub index: u64,
+    /// The digest of the previous entry, zero for the first entry.
+    pub parent: B256,
+    /// The epoch of the committee that took the decision.
+    pub epoch: Epoch,
+    /// The leader round.
+    pub round: u64,
+    /// The leader of the round.
+    pub leader: AuthorityIndex,
+    /// The digest of the leader's certificate, zero if the DAG has none.
+    pub leader_certificate: CertificateDigest,
+    /// Why the leader was or was not committed.
+    #[serde(flatten)]
+    pub outcome: CommitOutcome,
+    /// The certificates of the next round that reference the leader's certificate, by author.
+    pub supporters: Vec<CommitSupporter>,
+}
+
+impl CommitAuditEntry {
+    /// Returns the digest of the entry, the `keccak256` of its RLP encoding, which the next entry
+    /// references as its parent.
+    pub fn digest(&self) -> B256 {
+        keccak256(alloy_rlp::encode(self))
+    }
+
+    /// Returns the decision the entry records.
+    pub const fn decision(&self) -> CommitDecision {
+        CommitDecision { round: self.round, leader: self.leader, outcome: self.outcome }
+    }
+
+    /// Checks that the supporters justify the outcome, given the committee of the entry's epoch.
+    ///
+    /// The supporters must be distinct members of the committee. A direct decision must match
+    /// their stake, and a missing certificate can't have supporters. Indirect commits and skips are
+    /// justified by the entries of later rounds instead. That the supporters reference the leader
+    /// can be checked with their certificates, e.g. from `narwhal_certificate`.
+    pub fn verify_support(&self, committee: &Committee) -> Result<(), CommitAuditError> {
+        let index = self.index;
+        if self.epoch != committee.epoch {
+            return Err(CommitAuditError::WrongEpoch {
+                index,
+                epoch: self.epoch,
+                expected: committee.epoch,
+            })
+        }
+
+        let mut support: Stake = 0;
+        let mut authors = BTreeSet::new();
+        for supporter in &self.supporters {
+            let author = supporter.author;
+            let Some(authority) = committee.authority(author) else {
+                return Err(CommitAuditError::UnknownSupporter { index, author })
+            };
+            if !authors.insert(author) {
+                return Err(CommitAuditError::DuplicateSupporter { index, author })
+            }
+            support = support.saturating_add(authority.stake);
+        }
+
+        let threshold = committee.validity_threshold();
+        let has_certificate = self.leader_certificate != CertificateDigest::default();
+        let justified = match self.outcome {
+            CommitOutcome::Committed { support: recorded } => {
+                has_certificate && recorded == support && support >= threshold
+            }
+            CommitOutcome::InsufficientSupport { support: recorded, threshold: required } => {
+                has_certificate &&
+                    recorded == support &&
+                    required == threshold &&
+                    support < threshold
+            }
+            CommitOutcome::MissingCertificate => !has_certificate && self.supporters.is_empty(),
+            CommitOutcome::CommittedIndirectly { by_round } => by_round > self.round,
+            CommitOutcome::TimeoutSkip => true,
+        };
+        if justified {
+            Ok(())
+        } else {
+            Err(CommitAuditError::UnjustifiedOutcome { index, support })
+        }
+    }
+}
+
+/// Errors found by the checks of the [`CommitAuditLog`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CommitAuditError {
+    /// An entry doesn't follow its predecessor.
+    #[error("expected entry {expected}, found entry {index}")]
+    UnexpectedIndex {
+        /// The index following the predecessor.
+        expected: u64,
+        /// The index of the entry.
+        index: u64,
+    },
+    /// The parent of an entry is not the digest of its predecessor.
+    #[error("entry {index} doesn't reference the digest of its predecessor")]
+    BrokenChain {
+        /// The index of the entry.
+        index: u64,
+    },
+    /// The entry was decided in a different epoch than the committee's.
+    #[error("entry {index} was decided in epoch {epoch}, not in epoch {expected}")]
+    WrongEpoch {
+        /// The index of the entry.
+        index: u64,
+        /// The epoch of the entry.
+        epoch: Epoch,
+        /// The epoch of the committee.
+        expected: Epoch,
+    },
+    /// A supporter is not a member of the committee.
+    #[error("supporter {author} of entry {index} is not a member of the committee")]
+    UnknownSupporter {
+        /// The index of the entry.
+        index: u64,
+        /// The author of the supporting certificate.
+        author: AuthorityIndex,
+    },
+    /// An authority supports the leader more than once.
+    #[error("authority {author} supports the leader of entry {index} more than once")]
+    DuplicateSupporter {
+        /// The index of the entry.
+        index: u64,
+        /// The author of the supporting certificates.
+        author: AuthorityIndex,
+    },
+    /// The supporters don't justify the outcome of the entry.
+    #[error("outcome of entry {index} is not justified by the support {support}")]
+    UnjustifiedOutcome {
+        /// The index of the entry.
+        index: u64,
+        /// The stake of the supporters.
+        support: Stake,
+    },
+}
+
+/// Checks that the entries are consecutive entries of the audit log, each referencing the digest
+/// of its predecessor.
+///
+/// The entries may start at any index, the parent of the first entry is only checked if it's the
+/// first entry of the log.
+pub fn verify_audit_chain<'a>(
+    entries: impl IntoIterator<Item = &'a CommitAuditEntry>,
+) -> Result<(), CommitAuditError> {
+    let mut previous: Option<&CommitAuditEntry> = None;
+    for entry in entries {
+        let (expected, parent) = match previous {
+            Some(previous) => (previous.index + 1, previous.digest()),
+            None if entry.index == 0 => (0, B256::ZERO),
+            None => (entry.index, entry.parent),
+        };
+        if entry.index != expected {
+            return Err(CommitAuditError::UnexpectedIndex { expected, index: entry.index })
+        }
+        if entry.parent != parent {
+            return Err(CommitAuditError::BrokenChain { index: entry.index })
+        }
+        previous = Some(entry);
+    }
+    Ok(())
+}
+
+/// Metrics of the [`CommitAuditLog`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.commit_audit")]
+struct CommitAuditMetrics {
+    /// Number of entries appended to the audit log
+    entries: Counter,
+    /// Number of entries that failed to be written
+    write_failures: Counter,
+    /// The index of the last entry of the audit log
+    head_index: Gauge,
+    /// The leader round of the last entry of the audit log
+    head_round: Gauge,
+}
+
+/// The append-only audit log of the commit decisions in a [`DagStore`].
+///
+/// Every [`CommitAuditEntry`] records a decision with the digest of the leader's certificate and
+/// the certificates of the next round that support it, and references the digest of the previous
+/// entry. Rewriting an entry changes the digests of all later entries, so an auditor that kept the
+/// digest of an entry notices any change of the log before it. A round can have several entries,
+/// e.g. a leader that was skipped and later committed indirectly.
+///
+/// The log is neither pruned nor reset, it also keeps the decisions of epochs that were rolled
+/// back.
+#[derive(Debug)]
+pub struct CommitAuditLog {
+    store: Arc<dyn DagStore>,
+    /// The index and digest of the last entry.
+    head: Option<(u64, B256)>,
+    metrics: CommitAuditMetrics,
+}
+
+impl CommitAuditLog {
+    /// Opens the audit log in the store, appending after its last entry.
+    pub fn new(store: Arc<dyn DagStore>) -> Result<Self, DagStoreError> {
+        let head = store.last_audit_entry()?.map(|entry| (entry.index, entry.digest()));
+        Ok(Self { store, head, metrics: Default::default() })
+    }
+
+    /// Returns the index of the next entry.
+    pub fn next_index(&self) -> u64 {
+        self.head.map_or(0, |(index, _)| index + 1)
+    }
+
+    /// Appends the decision of a leader round, taken by the given committee, and returns the
+    /// written entry.
+    ///
+    /// `leader_certificate` is the digest of the leader's certificate, `None` if the DAG has none,
+    /// and `next_round` the certificates the decision was taken on, as for
+    /// [`direct_decision`](crate::fast_path::direct_decision). The certificates of other rounds
+    /// are ignored.
+    pub fn append<'a>(
+        &mut self,
+        committee: &Committee,
+        decision: &CommitDecision,
+        leader_certificate: Option<CertificateDigest>,
+        next_round: impl IntoIterator<Item = &'a Certificate>,
+    ) -> Result<CommitAuditEntry, DagStoreError> {
+        // an authority has at most one certificate per round, repeated certificates count once
+        let mut supporters = BTreeMap::new();
+        if let Some(leader_certificate) = leader_certificate {
+            for certificate in next_round {
+                if certificate.round() == decision.round + 1 &&
+                    certificate.header.parents.contains(&leader_certificate) &&
+                    committee.authority(certificate.author()).is_some()
+                {
+                    if let Entry::Vacant(entry) = supporters.entry(certificate.author()) {
+                        entry.insert(certificate.digest());
+                    }
+                }
+            }
+        }
+
+        let entry = CommitAuditEntry {
+            index: self.next_index(),
+            parent: self.head.map_or(B256::ZERO, |(_, digest)| digest),
+            epoch: committee.epoch,
+            round: decision.round,
+            leader: decision.leader,
+            leader_certificate: leader_certificate.unwrap_or_default(),
+            outcome: decision.outcome,
+            supporters: supporters
+                .into_iter()
+                .map(|(author, certificate)| CommitSupporter { author, certificate })
+                .collect(),
+        };
+        if let Err(err) = self.store.write_audit_entry(&entry) {
+            self.metrics.write_failures.increment(1);
+            return Err(err)
+        }
+
+        self.head = Some((entry.index, entry.digest()));
+        self.metrics.entries.increment(1);
+        self.metrics.head_index.set(entry.index as f64);
+        self.metrics.head_round.set(entry.round as f64);
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        committee::Authority, dag_store::MemoryDagStore, fast_path::direct_decision, types::Header,
+    };
+    use alloy_primitives::Bytes;
+
+    #[test]
+    fn record_and_lookup() {
+        let mut log = CommitDecisionLog::new(2);
+        log.record(CommitDecision { round: 2, leader: 0, outcome: CommitOutcome::TimeoutSkip });
+        log.record(CommitDecision {
+            round: 4,
+            leader: 1,
+            outcome: CommitOutcome::InsufficientSupport { support: 2, threshold: 3 },
+        });
+        log.record(CommitDecision {
+            round: 2,
+            leader: 0,
+            outcome: CommitOutcome::CommittedIndirectly { by_round: 6 },
+        });
+        log.record(CommitDecision {
+            round: 6,
+            leader: 2,
+            outcome: CommitOutcome::Committed { support: 3 },
+        });
+
+        assert_eq!(log.get(2), None);
+        assert_eq!(log.range(..).map(|decision| decision.round).collect::<Vec<_>>(), vec![4, 6]);
+        assert!(log.get(6).unwrap().outcome.is_committed());
+    }
+
+    #[test]
+    fn machine_readable_json() {
+        let decision = CommitDecision {
+            round: 1042,
+            leader: 3,
+            outcome: CommitOutcome::InsufficientSupport { support: 2, threshold: 3 },
+        };
+        let json = serde_json::to_value(decision).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "round": 1042,
+                "leader": 3,
+                "reason": "insufficientSupport",
+                "support": 2,
+                "threshold": 3
+            })
+        );
+        assert_eq!(serde_json::from_value::<CommitDecision>(json).unwrap(), decision);
+    }
+
+    fn committee(size: usize) -> Committee {
+        let authorities = (0..size)
+            .map(|index| Authority {
+                public_key: Bytes::from(vec![index as u8]),
+                proof_of_possession: Bytes::new(),
+                stake: 1,
+                primary_address: ([127, 0, 0, 1], 30400).into(),
+                worker_addresses: Vec::new(),
+            })
+            .collect();
+        Committee { epoch: 1, authorities }
+    }
+
+    /// A certificate of the given round with the given parents.
+    fn certificate(
+        round: u64,
+        author: AuthorityIndex,
+        parents: &[CertificateDigest],
+    ) -> Certificate {
+        let header =
+            Header { epoch: 1, round, author, parents: parents.to_vec(), ..Default::default() };
+        Certificate { header, ..Default::default() }
+    }
+
+    #[test]
+    fn audit_log_chain() {
+        let committee = committee(4);
+        let store = Arc::new(MemoryDagStore::default());
+        let mut log = CommitAuditLog::new(store.clone()).unwrap();
+
+        // the leader of round 2 is supported by authorities 0 and 2, and by an equivocation of 2
+        let leader = certificate(2, 0, &[]).digest();
+        let other = certificate(2, 1, &[]).digest();
+        let next_round = [
+            certificate(3, 0, &[leader]),
+            certificate(3, 1, &[other]),
+            certificate(3, 2, &[leader, other]),
+            certificate(3, 2, &[leader]),
+            certificate(4, 3, &[leader]),
+        ];
+        let decision = direct_decision(&committee, 2, 0, Some(leader), &next_round);
+        let first = log.append(&committee, &decision, Some(leader), &next_round).unwrap();
+        assert_eq!(first.outcome, CommitOutcome::Committed { support: 2 });
+        assert_eq!(first.parent, B256::ZERO);
+        assert_eq!(
+            first.supporters,
+            vec![
+                CommitSupporter { author: 0, certificate: next_round[0].digest() },
+                CommitSupporter { author: 2, certificate: next_round[2].digest() },
+            ]
+        );
+
+        let decision = direct_decision(&committee, 4, 1, Some(other), []);
+        log.append(&committee, &decision, Some(other), []).unwrap();
+        let decision = direct_decision(&committee, 6, 2, None, []);
+        log.append(&committee, &decision, None, []).unwrap();
+
+        // the log continues after its last entry when it's opened again
+        let mut log = CommitAuditLog::new(store.clone()).unwrap();
+        assert_eq!(log.next_index(), 3);
+        let decision = CommitDecision {
+            round: 4,
+            leader: 1,
+            outcome: CommitOutcome::CommittedIndirectly { by_round: 8 },
+        };
+        log.append(&committee, &decision, Some(other), []).unwrap();
+
+        let entries = store.audit_entries(0, 10).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.round).collect::<Vec<_>>(), [2, 4, 6, 4]);
+        assert_eq!(store.audit_entries(1, 2).unwrap(), entries[1..3]);
+        assert_eq!(verify_audit_chain(&entries), Ok(()));
+        assert_eq!(verify_audit_chain(&entries[2..]), Ok(()));
+        for entry in &entries {
+            assert_eq!(entry.verify_support(&committee), Ok(()));
+        }
+
+        // rewriting an entry breaks the chain at the next entry
+        let mut tampered = entries.clone();
+        tampered[1].outcome = CommitOutcome::Committed { support: 0 };
+        assert_eq!(
+            tampered[1].verify_support(&committee),
+            Err(CommitAuditError::UnjustifiedOutcome { index: 1, support: 0 })
+        );
+        assert_eq!(verify_audit_chain(&tampered), Err(CommitAuditError::BrokenChain { index: 2 }));
+        assert_eq!(
+            verify_audit_chain([&entries[0], &entries[2]]),
+            Err(CommitAuditError::UnexpectedIndex { expected: 1, index: 2 })
+        );
+
+        let mut forged = entries[0].clone();
+        forged.supporters.push(forged.supporters[0]);
+        assert_eq!(
+            forged.verify_support(&committee),
+            Err(CommitAuditError::DuplicateSupporter { index: 0, author: 0 })
+        );
+        forged.supporters[2].author = 4;
+        assert_eq!(
+            forged.verify_support(&committee),
+            Err(CommitAuditError::UnknownSupporter { index: 0, author: 4 })
+        );
+    }
+
+    #[test]
+    fn audit_entry_encoding() {
+        let outcomes = [
+            CommitOutcome::Committed { support: 3 },
+            CommitOutcome::CommittedIndirectly { by_round: 12 },
+            CommitOutcome::MissingCertificate,
+            CommitOutcome::InsufficientSupport { support: 1, threshold: 2 },
+            CommitOutcome::TimeoutSkip,
+        ];
+        for outcome in outcomes {
+            let entry = CommitAuditEntry {
+                index: 7,
+                parent: B256::repeat_byte(1),
+                epoch: 1,
+                round: 10,
+                leader: 2,
+                leader_certificate: CertificateDigest(B256::repeat_byte(2)),
+                outcome,
+                supporters: vec![CommitSupporter {
+                    author: 3,
+                    certificate: CertificateDigest(B256::repeat_byte(3)),
+                }],
+            };
+            let encoded = alloy_rlp::encode(&entry);
+            assert_eq!(CommitAuditEntry::decode(&mut encoded.as_slice()).unwrap(), entry);
+            assert_eq!(entry.decision().outcome, outcome);
+        }
+        assert!(CommitOutcome::decode(
+            &mut alloy_rlp::encode(OutcomeFields { kind: 5, first: 0, second: 0 }).as_slice()
+        )
+        .is_err());
+
+        let entry = CommitAuditEntry {
+            index: 0,
+            parent: B256::ZERO,
+            epoch: 1,
+            round: 2,
+            leader: 0,
+            leader_certificate: CertificateDigest::default(),
+            outcome: CommitOutcome::MissingCertificate,
+            supporters: Vec::new(),
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["reason"], "missingCertificate");
+        assert_eq!(json["leaderCertificate"], serde_json::json!(B256::ZERO));
+        assert_eq!(serde_json::from_value::<CommitAuditEntry>(json).unwrap(), entry);
+    }
+}
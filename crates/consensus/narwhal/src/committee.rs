@@ -0,0 +1,397 @@
+//! The validator set of a narwhal chain.
+//!
+//! The [`Committee`] of an epoch lists every authority with its public key, stake and the network
+//! addresses of its primary and workers. It's loaded from a TOML or JSON file, e.g.
+//!
+//! ```toml
+//! epoch = 0
+//!
+//! [[authorities]]
+//! publicKey = "0x8f2d…"
+//! proofOfPossession = "0xa41c…"
+//! stake = 1
+//! primaryAddress = "10.0.0.1:30400"
+//! workerAddresses = ["10.0.0.1:30401"]
+//! ```
+//!
+//! The public keys are BLS keys, whose signatures are aggregated into the certificates. An
+//! aggregate signature is only sound if no authority registered a rogue key derived from the keys
+//! of the others, so every authority proves that it knows the secret key of its public key with a
+//! proof of possession, as printed by `reth narwhal keygen`, and committees without valid proofs
+//! are rejected.
+//!
+//! Components look up the committee through a [`CommitteeProvider`], so that the committee can be
+//! swapped at epoch boundaries without restarting them.
+
+use crate::{
+    signature::{BlsPublicKey, BlsSignature},
+    types::WorkerId,
+};
+use alloy_primitives::Bytes;
+use reth_narwhal_verifier::{AuthorityIndex, Epoch, Stake, VerifierAuthority, VerifierCommittee};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A member of a [`Committee`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Authority {
+    /// The encoded public key the authority signs with.
+    pub public_key: Bytes,
+    /// The signature of the public key with its secret key, see
+    /// [`BlsSecretKey::proof_of_possession`](crate::signature::BlsSecretKey::proof_of_possession).
+    #[serde(default)]
+    pub proof_of_possession: Bytes,
+    /// The voting power of the authority.
+    pub stake: Stake,
+    /// The address of the authority's primary.
+    pub primary_address: SocketAddr,
+    /// The addresses of the authority's workers, indexed by [`WorkerId`].
+    #[serde(default)]
+    pub worker_addresses: Vec<SocketAddr>,
+}
+
+impl Authority {
+    /// Returns the address of the given worker of the authority.
+    pub fn worker_address(&self, worker: WorkerId) -> Option<SocketAddr> {
+        self.worker_addresses.get(worker as usize).copied()
+    }
+}
+
+/// Errors when loading a [`Committee`].
+#[derive(Debug, thiserror::Error)]
+pub enum CommitteeError {
+    /// The committee file couldn't be read.
+    #[error("failed to read committee file {path}: {err}")]
+    Read {
+        /// The path of the file.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        err: io::Error,
+    },
+    /// The committee file is malformed.
+    #[error("failed to parse committee file {path}: {message}")]
+    Parse {
+        /// The path of the file.
+        path: PathBuf,
+        /// The parser error.
+        message: String,
+    },
+    /// The committee has no authorities.
+    #[error("committee has no authorities")]
+    Empty,
+    /// An authority has no stake.
+    #[error("authority {index} has no stake")]
+    ZeroStake {
+        /// The index of the authority.
+        index: AuthorityIndex,
+    },
+    /// Two authorities have the same public key.
+    #[error("authorities {first} and {second} have the same public key")]
+    DuplicatePublicKey {
+        /// The index of the first authority with the key.
+        first: AuthorityIndex,
+        /// The index of the second authority with the key.
+        second: AuthorityIndex,
+    },
+    /// The public key of an authority is malformed.
+    #[error("authority {index} has a malformed public key")]
+    InvalidPublicKey {
+        /// The index of the authority.
+        index: AuthorityIndex,
+    },
+    /// An authority has no proof of possession of its key.
+    #[error("authority {index} has no proof of possession")]
+    MissingProofOfPossession {
+        /// The index of the authority.
+        index: AuthorityIndex,
+    },
+    /// The proof of possession of an authority is not a signature of its key.
+    #[error("authority {index} has an invalid proof of possession")]
+    InvalidProofOfPossession {
+        /// The index of the authority.
+        index: AuthorityIndex,
+    },
+}
+
+/// The authorities of an epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Committee {
+    /// The epoch of the committee.
+    pub epoch: Epoch,
+    /// The members of the committee, the position of an authority is its [`AuthorityIndex`].
+    pub authorities: Vec<Authority>,
+}
+
+impl Committee {
+    /// Loads and validates the committee from a file.
+    ///
+    /// Files with a `.toml` extension are parsed as TOML, all others as JSON.
+    pub fn load(path: &Path) -> Result<Self, CommitteeError> {
+        let committee: Self = load_file(path)?;
+        committee.validate()?;
+        Ok(committee)
+    }
+
+    /// Checks that the committee is not empty, every authority has stake and a valid BLS public
+    /// key with a valid proof of possession, and no public key is used twice.
+    pub fn validate(&self) -> Result<(), CommitteeError> {
+        if self.authorities.is_empty() {
+            return Err(CommitteeError::Empty)
+        }
+        let mut keys = HashMap::with_capacity(self.authorities.len());
+        for (index, authority) in self.authorities.iter().enumerate() {
+            let index = index as AuthorityIndex;
+            if authority.stake == 0 {
+                return Err(CommitteeError::ZeroStake { index })
+            }
+            if let Some(first) = keys.insert(&authority.public_key, index) {
+                return Err(CommitteeError::DuplicatePublicKey { first, second: index })
+            }
+            let public_key = BlsPublicKey::try_from(authority.public_key.as_ref())
+                .map_err(|_| CommitteeError::InvalidPublicKey { index })?;
+            if authority.proof_of_possession.is_empty() {
+                return Err(CommitteeError::MissingProofOfPossession { index })
+            }
+            let proof = BlsSignature::try_from(authority.proof_of_possession.as_ref())
+                .map_err(|_| CommitteeError::InvalidProofOfPossession { index })?;
+            if !public_key.verify_proof_of_possession(&proof) {
+                return Err(CommitteeError::InvalidProofOfPossession { index })
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the authority with the given index.
+    pub fn authority(&self, index: AuthorityIndex) -> Option<&Authority> {
+        self.authorities.get(index as usize)
+    }
+
+    /// Returns the index of the authority with the given public key.
+    pub fn index_of(&self, public_key: &[u8]) -> Option<AuthorityIndex> {
+        self.authorities
+            .iter()
+            .position(|authority| authority.public_key.as_ref() == public_key)
+            .map(|index| index as AuthorityIndex)
+    }
+
+    /// Returns the sum of the stake of all authorities.
+    pub fn total_stake(&self) -> Stake {
+        self.authorities
+            .iter()
+            .fold(0, |total: Stake, authority| total.saturating_add(authority.stake))
+    }
+
+    /// Returns the stake required for a quorum, i.e. `2f + 1` for a committee of `3f + 1`.
+    pub fn quorum_threshold(&self) -> Stake {
+        2 * self.total_stake() / 3 + 1
+    }
+
+    /// Returns the stake required to guarantee that at least one honest authority is included,
+    /// i.e. `f + 1` for a committee of `3f + 1`.
+    pub fn validity_threshold(&self) -> Stake {
+        self.total_stake().div_ceil(3)
+    }
+
+    /// Returns the view of the committee that is needed to verify signatures, with the public keys
+    /// decoded as `K`.
+    pub fn verifier_committee<K>(&self) -> Result<VerifierCommittee<K>, CommitteeError>
+    where
+        for<'a> K: TryFrom<&'a [u8]>,
+    {
+        let authorities = self
+            .authorities
+            .iter()
+            .enumerate()
+            .map(|(index, authority)| {
+                let public_key = K::try_from(authority.public_key.as_ref()).map_err(|_| {
+                    CommitteeError::InvalidPublicKey { index: index as AuthorityIndex }
+                })?;
+                Ok(VerifierAuthority { public_key, stake: authority.stake })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(VerifierCommittee::new(self.epoch, authorities))
+    }
+}
+
+/// Parses a TOML file if it has a `.toml` extension, and a JSON file otherwise.
+pub(crate) fn load_file<T: DeserializeOwned>(path: &Path) -> Result<T, CommitteeError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| CommitteeError::Read { path: path.to_path_buf(), err })?;
+    let parse_error = |message: String| CommitteeError::Parse { path: path.to_path_buf(), message };
+    if path.extension().is_some_and(|extension| extension == "toml") {
+        toml::from_str(&contents).map_err(|err| parse_error(err.to_string()))
+    } else {
+        serde_json::from_str(&contents).map_err(|err| parse_error(err.to_string()))
+    }
+}
+
+/// Provides the committee of the current and past epochs.
+///
+/// Components hold a provider instead of a [`Committee`], so that they pick up the committee of
+/// a new epoch once it's known.
+pub trait CommitteeProvider: Debug + Send + Sync {
+    /// Returns the committee of the current epoch.
+    fn current_committee(&self) -> Arc<Committee>;
+
+    /// Returns the committee of the given epoch, if it's known.
+    fn committee(&self, epoch: Epoch) -> Option<Arc<Committee>>;
+}
+
+/// A [`CommitteeProvider`] with a single committee that never changes.
+#[derive(Debug, Clone)]
+pub struct StaticCommitteeProvider {
+    committee: Arc<Committee>,
+}
+
+impl StaticCommitteeProvider {
+    /// Creates a provider of the given committee.
+    pub fn new(committee: Committee) -> Self {
+        Self { committee: Arc::new(committee) }
+    }
+}
+
+impl CommitteeProvider for StaticCommitteeProvider {
+    fn current_committee(&self) -> Arc<Committee> {
+        Arc::clone(&self.committee)
+    }
+
+    fn committee(&self, epoch: Epoch) -> Option<Arc<Committee>> {
+        (epoch == self.committee.epoch).then(|| Arc::clone(&self.committee))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{AggregateScheme, Bls12381, BlsSecretKey};
+
+    const COMMITTEE_TOML: &str = r#"
+        epoch = 3
+
+        [[authorities]]
+        publicKey = "0x01"
+        stake = 2
+        primaryAddress = "127.0.0.1:30400"
+        workerAddresses = ["127.0.0.1:30401", "127.0.0.1:30402"]
+
+        [[authorities]]
+        publicKey = "0x02"
+        stake = 1
+        primaryAddress = "127.0.0.1:30500"
+    "#;
+
+    /// Returns the committee of [`COMMITTEE_TOML`] with BLS keys and their proofs of possession.
+    fn signed_committee() -> Committee {
+        let mut committee: Committee = toml::from_str(COMMITTEE_TOML).unwrap();
+        for (index, authority) in committee.authorities.iter_mut().enumerate() {
+            let secret_key = BlsSecretKey::from_seed(&[index as u8; 32]).unwrap();
+            let public_key = Bls12381::public_key(&secret_key).to_bytes();
+            authority.public_key = Bytes::copy_from_slice(&public_key);
+            authority.proof_of_possession =
+                Bytes::copy_from_slice(&secret_key.proof_of_possession().to_bytes());
+        }
+        committee
+    }
+
+    #[test]
+    fn load_committee_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("committee.toml");
+        fs::write(&toml_path, toml::to_string(&signed_committee()).unwrap()).unwrap();
+        let committee = Committee::load(&toml_path).unwrap();
+        assert_eq!(committee, signed_committee());
+        assert_eq!(committee.epoch, 3);
+        assert_eq!(committee.total_stake(), 3);
+        assert_eq!(committee.index_of(&committee.authorities[1].public_key), Some(1));
+        assert_eq!(
+            committee.authority(0).unwrap().worker_address(1),
+            Some("127.0.0.1:30402".parse().unwrap())
+        );
+        assert_eq!(committee.authority(1).unwrap().worker_address(0), None);
+
+        let json_path = dir.path().join("committee.json");
+        fs::write(&json_path, serde_json::to_string(&committee).unwrap()).unwrap();
+        assert_eq!(Committee::load(&json_path).unwrap(), committee);
+
+        assert!(matches!(
+            Committee::load(&dir.path().join("missing.json")),
+            Err(CommitteeError::Read { .. })
+        ));
+        fs::write(&json_path, "{}").unwrap();
+        assert!(matches!(Committee::load(&json_path), Err(CommitteeError::Parse { .. })));
+
+        // the committee of the example has no valid keys
+        fs::write(&toml_path, COMMITTEE_TOML).unwrap();
+        assert!(matches!(
+            Committee::load(&toml_path),
+            Err(CommitteeError::InvalidPublicKey { index: 0 })
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_proof_of_possession() {
+        let mut committee = signed_committee();
+        committee.authorities[1].proof_of_possession = Bytes::new();
+        assert!(matches!(
+            committee.validate(),
+            Err(CommitteeError::MissingProofOfPossession { index: 1 })
+        ));
+
+        // the proof of another key
+        committee.authorities[1].proof_of_possession =
+            committee.authorities[0].proof_of_possession.clone();
+        assert!(matches!(
+            committee.validate(),
+            Err(CommitteeError::InvalidProofOfPossession { index: 1 })
+        ));
+        committee.authorities[1].proof_of_possession = Bytes::from_static(&[1; 96]);
+        assert!(matches!(
+            committee.validate(),
+            Err(CommitteeError::InvalidProofOfPossession { index: 1 })
+        ));
+    }
+
+    #[test]
+    fn validate_committee() {
+        let mut committee = signed_committee();
+        assert!(committee.validate().is_ok());
+
+        committee.authorities[1].public_key = committee.authorities[0].public_key.clone();
+        assert!(matches!(
+            committee.validate(),
+            Err(CommitteeError::DuplicatePublicKey { first: 0, second: 1 })
+        ));
+        committee.authorities[0].stake = 0;
+        assert!(matches!(committee.validate(), Err(CommitteeError::ZeroStake { index: 0 })));
+        committee.authorities.clear();
+        assert!(matches!(committee.validate(), Err(CommitteeError::Empty)));
+    }
+
+    #[test]
+    fn verifier_committee() {
+        let committee: Committee = toml::from_str(COMMITTEE_TOML).unwrap();
+        let verifier = committee.verifier_committee::<[u8; 1]>().unwrap();
+        assert_eq!(verifier.epoch(), 3);
+        assert_eq!(verifier.quorum_threshold(), 3);
+        assert_eq!(verifier.authority(1).unwrap().public_key, [2]);
+        assert!(matches!(
+            committee.verifier_committee::<[u8; 2]>(),
+            Err(CommitteeError::InvalidPublicKey { index: 0 })
+        ));
+
+        let provider = StaticCommitteeProvider::new(committee);
+        assert_eq!(provider.current_committee().epoch, 3);
+        assert!(provider.committee(3).is_some());
+        assert!(provider.committee(4).is_none());
+    }
+}
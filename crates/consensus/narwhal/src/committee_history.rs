@@ -0,0 +1,224 @@
+//! Committees of past epochs.
+//!
+//! Commit proofs of old blocks are verified against the committee of the epoch they were signed
+//! in, and explorers attribute old blocks to the authorities of that committee. The committee of
+//! every epoch is therefore kept after the epoch ended, together with the first block of the
+//! epoch, see [`CommitteeHistory`]. The executor of a node records the committee of every epoch at
+//! the first block built from its commits, and `narwhal_getCommittee` serves them.
+
+use alloy_primitives::BlockNumber;
+use reth_narwhal_verifier::Epoch;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Resolves the committee of past and current epochs.
+pub trait CommitteeHistoryProvider: Debug + Send + Sync {
+    /// The committee type.
+    type Committee;
+
+    /// Returns the committee of the given epoch.
+    fn committee(&self, epoch: Epoch) -> Option<&Self::Committee>;
+
+    /// Returns the epoch and committee that finalized the given block.
+    fn committee_at_block(&self, number: BlockNumber) -> Option<(Epoch, &Self::Committee)>;
+}
+
+/// The committee of an epoch and the first block finalized by it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochCommittee<C> {
+    /// The epoch.
+    pub epoch: Epoch,
+    /// The first block of the epoch.
+    pub first_block: BlockNumber,
+    /// The committee of the epoch.
+    pub committee: C,
+}
+
+/// Errors when recording the committee of an epoch.
+#[derive(Debug, thiserror::Error)]
+pub enum CommitteeHistoryError {
+    /// The epoch doesn't directly follow the latest recorded epoch.
+    #[error("epoch {epoch} does not follow the latest recorded epoch {latest}")]
+    NonConsecutiveEpoch {
+        /// The epoch that was recorded.
+        epoch: Epoch,
+        /// The latest recorded epoch.
+        latest: Epoch,
+    },
+    /// The epoch starts at or before the first block of the latest recorded epoch.
+    #[error("epoch {epoch} starts at block {first_block}, before the previous epoch")]
+    FirstBlockNotAscending {
+        /// The epoch that was recorded.
+        epoch: Epoch,
+        /// Its first block.
+        first_block: BlockNumber,
+    },
+    /// Reading or writing the history failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// The committees of all epochs, persisted as one JSON file per epoch.
+#[derive(Debug)]
+pub struct CommitteeHistory<C> {
+    dir: PathBuf,
+    epochs: BTreeMap<Epoch, EpochCommittee<C>>,
+    /// The epochs by their first block.
+    first_blocks: BTreeMap<BlockNumber, Epoch>,
+}
+
+impl<C: Serialize + DeserializeOwned> CommitteeHistory<C> {
+    /// Opens the history stored in the given directory, creating the directory if it doesn't
+    /// exist.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, CommitteeHistoryError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut history = Self { dir, epochs: BTreeMap::new(), first_blocks: BTreeMap::new() };
+        let mut records = Vec::new();
+        for entry in fs::read_dir(&history.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                let record: EpochCommittee<C> =
+                    serde_json::from_slice(&fs::read(&path)?).map_err(io::Error::from)?;
+                records.push(record);
+            }
+        }
+        records.sort_unstable_by_key(|record| record.epoch);
+        for record in records {
+            history.check_next(record.epoch, record.first_block)?;
+            history.first_blocks.insert(record.first_block, record.epoch);
+            history.epochs.insert(record.epoch, record);
+        }
+        Ok(history)
+    }
+
+    /// Records and persists the committee of the epoch following the latest recorded one.
+    pub fn insert(
+        &mut self,
+        epoch: Epoch,
+        first_block: BlockNumber,
+        committee: C,
+    ) -> Result<(), CommitteeHistoryError> {
+        self.check_next(epoch, first_block)?;
+        let record = EpochCommittee { epoch, first_block, committee };
+
+        // write to a temporary file first, so a crash never leaves a partial record behind
+        let path = self.dir.join(format!("epoch-{epoch}.json"));
+        let tmp = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp)?;
+        serde_json::to_writer(&mut file, &record).map_err(io::Error::from)?;
+        file.write_all(b"\n")?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+
+        self.first_blocks.insert(first_block, epoch);
+        self.epochs.insert(epoch, record);
+        Ok(())
+    }
+
+    /// Removes the records of all epochs after the given epoch, e.g. to roll back an epoch change.
+    pub fn truncate(&mut self, epoch: Epoch) -> Result<(), CommitteeHistoryError> {
+        let Some(next) = epoch.checked_add(1) else { return Ok(()) };
+        for record in self.epochs.split_off(&next).into_values() {
+            fs::remove_file(self.dir.join(format!("epoch-{}.json", record.epoch)))?;
+            self.first_blocks.remove(&record.first_block);
+        }
+        Ok(())
+    }
+
+    /// Returns the latest recorded epoch.
+    pub fn latest(&self) -> Option<&EpochCommittee<C>> {
+        self.epochs.values().next_back()
+    }
+
+    /// Returns the records of all epochs, in ascending order.
+    pub fn epochs(&self) -> impl Iterator<Item = &EpochCommittee<C>> + '_ {
+        self.epochs.values()
+    }
+
+    /// Returns the record of the given epoch.
+    pub fn epoch(&self, epoch: Epoch) -> Option<&EpochCommittee<C>> {
+        self.epochs.get(&epoch)
+    }
+
+    /// Returns the last block of the given epoch, or `None` if the epoch is not followed by a
+    /// recorded epoch.
+    pub fn last_block(&self, epoch: Epoch) -> Option<BlockNumber> {
+        let (_, next) = self.epochs.range(epoch.checked_add(1)?..).next()?;
+        Some(next.first_block - 1)
+    }
+
+    fn check_next(
+        &self,
+        epoch: Epoch,
+        first_block: BlockNumber,
+    ) -> Result<(), CommitteeHistoryError> {
+        let Some(latest) = self.latest() else { return Ok(()) };
+        if latest.epoch.checked_add(1) != Some(epoch) {
+            return Err(CommitteeHistoryError::NonConsecutiveEpoch { epoch, latest: latest.epoch })
+        }
+        if first_block <= latest.first_block {
+            return Err(CommitteeHistoryError::FirstBlockNotAscending { epoch, first_block })
+        }
+        Ok(())
+    }
+}
+
+impl<C: Debug + Send + Sync> CommitteeHistoryProvider for CommitteeHistory<C> {
+    type Committee = C;
+
+    fn committee(&self, epoch: Epoch) -> Option<&C> {
+        self.epochs.get(&epoch).map(|record| &record.committee)
+    }
+
+    fn committee_at_block(&self, number: BlockNumber) -> Option<(Epoch, &C)> {
+        let (_, epoch) = self.first_blocks.range(..=number).next_back()?;
+        Some((*epoch, self.committee(*epoch)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn persist_and_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = CommitteeHistory::<Vec<u64>>::open(dir.path()).unwrap();
+        assert_eq!(history.committee_at_block(0), None);
+
+        history.insert(0, 0, vec![1, 2, 3, 4]).unwrap();
+        history.insert(1, 100, vec![2, 3, 4, 5]).unwrap();
+        assert!(matches!(
+            history.insert(3, 200, vec![]),
+            Err(CommitteeHistoryError::NonConsecutiveEpoch { epoch: 3, latest: 1 })
+        ));
+        assert!(matches!(
+            history.insert(2, 100, vec![]),
+            Err(CommitteeHistoryError::FirstBlockNotAscending { epoch: 2, first_block: 100 })
+        ));
+
+        let mut history = CommitteeHistory::<Vec<u64>>::open(dir.path()).unwrap();
+        assert_eq!(history.committee(1), Some(&vec![2, 3, 4, 5]));
+        assert_eq!(history.committee_at_block(99), Some((0, &vec![1, 2, 3, 4])));
+        assert_eq!(history.committee_at_block(100), Some((1, &vec![2, 3, 4, 5])));
+        assert_eq!(history.committee_at_block(u64::MAX), Some((1, &vec![2, 3, 4, 5])));
+        assert_eq!(history.latest().map(|latest| latest.epoch), Some(1));
+        assert_eq!(history.epoch(1).map(|record| record.first_block), Some(100));
+        assert_eq!(history.last_block(0), Some(99));
+        assert_eq!(history.last_block(1), None);
+
+        history.truncate(0).unwrap();
+        let history = CommitteeHistory::<Vec<u64>>::open(dir.path()).unwrap();
+        assert_eq!(history.latest().map(|latest| latest.epoch), Some(0));
+        assert_eq!(history.committee_at_block(100), Some((0, &vec![1, 2, 3, 4])));
+    }
+}
@@ -0,0 +1,610 @@
+//! The committer of an epoch, which orders the certificates of the primary into sub-dags.
+//!
+//! The [`Committer`] inserts every certificate the primary receives into its [`Dag`], runs the
+//! [`Bullshark`] commit rule after each of them, and assembles the [`OrderedSubDag`] of every
+//! committed leader from the certificates and the batches in the [`DagStore`]. Every decision of
+//! the commit rule is appended to the [`CommitAuditLog`], and the last committed round of every
+//! authority is written to the store, so that the committer resumes after a restart without
+//! committing a certificate twice.
+//!
+//! With a [`FastPath`], the committer commits the leader of a round as soon as every authority
+//! certified the following round, see [`crate::fast_path`].
+//!
+//! With a [`MemoryBudget`], the DAG and the pending certificates are charged to the
+//! [`Dag`](MemoryComponent::Dag) and [`PendingCertificates`](MemoryComponent::PendingCertificates)
+//! components. The commit rule needs every certificate above the GC round, so they are never
+//! evicted for the budget, their usage makes the other components evict more instead.
+
+use crate::{
+    bullshark::{Bullshark, CommittedLeader},
+    commit_log::CommitAuditLog,
+    committee::Committee,
+    dag::{Dag, DagError},
+    dag_store::{DagStore, DagStoreError},
+    determinism::{Clock, SystemClock},
+    epoch::UncommittedBatches,
+    fast_path::FastPath,
+    gc::gc_round,
+    leader::{LeaderElector, LeaderSchedule},
+    memory::{MemoryBudget, MemoryComponent, MemoryHandle},
+    rpc::ConsensusState,
+    timestamp::TimestampPolicy,
+    types::{BatchDigest, Certificate, DagVertex, OrderedSubDag, Round},
+};
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use std::{collections::BTreeMap, mem, sync::Arc};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, error, warn};
+
+/// The number of audit entries read from the store at once.
+const AUDIT_PAGE: usize = 1_000;
+
+/// The estimated size of a certificate held by the [`Committer`].
+const CERTIFICATE_SIZE: usize = mem::size_of::<DagVertex>();
+
+/// Errors that stop the [`Committer`].
+#[derive(Debug, thiserror::Error)]
+pub enum CommitterError {
+    /// The store failed.
+    #[error(transparent)]
+    Store(#[from] DagStoreError),
+    /// A batch of a committed certificate is not stored, so the sub-dag can't be executed.
+    #[error("batch {digest} committed in round {round} is not stored")]
+    MissingBatch {
+        /// The round of the committed leader.
+        round: Round,
+        /// The digest of the batch.
+        digest: BatchDigest,
+    },
+}
+
+/// Metrics of the [`Committer`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.committer")]
+struct CommitterMetrics {
+    /// The round of the last committed leader
+    committed_round: Gauge,
+    /// Number of committed leaders
+    committed_leaders: Counter,
+    /// Number of certificates waiting for their parents
+    pending_certificates: Gauge,
+    /// Number of rejected equivocating certificates
+    equivocations: Counter,
+    /// Number of commit decisions that couldn't be appended to the audit log
+    failed_audit_entries: Counter,
+}
+
+/// The task that commits the certificates of an epoch, see the [module docs](self).
+#[derive(Debug)]
+pub struct Committer {
+    committee: Arc<Committee>,
+    store: Arc<dyn DagStore>,
+    dag: Dag,
+    bullshark: Bullshark,
+    audit_log: CommitAuditLog,
+    gc_depth: Round,
+    /// The certificates that arrived before their parents.
+    pending: Vec<Certificate>,
+    certificates: mpsc::Receiver<Certificate>,
+    output: mpsc::Sender<OrderedSubDag>,
+    /// The index of the next committed sub-dag.
+    next_sub_dag: u64,
+    /// Receives the round of every committed leader, e.g. for the
+    /// [`DagPruner`](crate::gc::DagPruner).
+    committed_round: Option<watch::Sender<Round>>,
+    state: Option<ConsensusState>,
+    uncommitted: Option<UncommittedBatches>,
+    fast_path: Option<FastPath>,
+    /// The handles of the DAG and of the pending certificates to the memory budget.
+    memory: Option<(MemoryHandle, MemoryHandle)>,
+    metrics: CommitterMetrics,
+}
+
+impl Committer {
+    /// Creates the committer of the committee's epoch, which commits the certificates it receives
+    /// and sends the sub-dags to `output`, starting at the index `first_sub_dag`.
+    ///
+    /// The committer resumes from the DAG and the commit decisions of the epoch in the store, and
+    /// starts at the genesis round if the epoch has no committed leader yet.
+    pub fn new(
+        committee: Arc<Committee>,
+        schedule: LeaderSchedule,
+        store: Arc<dyn DagStore>,
+        gc_depth: Round,
+        certificates: mpsc::Receiver<Certificate>,
+        output: mpsc::Sender<OrderedSubDag>,
+        first_sub_dag: u64,
+    ) -> Result<Self, DagStoreError> {
+        let audit_log = CommitAuditLog::new(Arc::clone(&store))?;
+        let mut elector = LeaderElector::new(schedule, &committee);
+        let committed = committed_rounds(store.as_ref(), &committee)?;
+        for &round in &committed {
+            elector.record_commit(round);
+        }
+        let last_committed = committed.last().copied().unwrap_or_default();
+        let bullshark = Bullshark::resume(elector, last_committed);
+
+        let mut committer = Self {
+            dag: Dag::new(gc_round(last_committed, gc_depth)),
+            bullshark,
+            audit_log,
+            gc_depth,
+            pending: Vec::new(),
+            certificates,
+            output,
+            next_sub_dag: first_sub_dag,
+            committed_round: None,
+            state: None,
+            uncommitted: None,
+            fast_path: None,
+            memory: None,
+            metrics: CommitterMetrics::default(),
+            committee,
+            store,
+        };
+        committer.recover(last_committed)?;
+        Ok(committer)
+    }
+
+    /// Sends the round of every committed leader to the channel.
+    pub fn with_committed_round(mut self, committed_round: watch::Sender<Round>) -> Self {
+        self.committed_round = Some(committed_round);
+        self
+    }
+
+    /// Records the certificates and the commit decisions in the state served by the RPC.
+    pub fn with_consensus_state(mut self, state: ConsensusState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Records the round of every certificate in the batches of the node that aren't committed
+    /// yet.
+    pub fn with_uncommitted_batches(mut self, uncommitted: UncommittedBatches) -> Self {
+        self.uncommitted = Some(uncommitted);
+        self
+    }
+
+    /// Commits the leaders of unanimous rounds with the fast path, which must belong to the same
+    /// committee.
+    pub fn with_fast_path(mut self, fast_path: FastPath) -> Self {
+        self.fast_path = Some(fast_path);
+        self
+    }
+
+    /// Charges the DAG and the pending certificates to the memory budget, see the
+    /// [module docs](self).
+    pub fn with_memory_budget(mut self, budget: &MemoryBudget) -> Self {
+        self.memory = Some((
+            budget.handle(MemoryComponent::Dag),
+            budget.handle(MemoryComponent::PendingCertificates),
+        ));
+        self.account_memory();
+        self
+    }
+
+    /// Returns the round of the last committed leader.
+    pub const fn last_committed(&self) -> Round {
+        self.bullshark.last_committed()
+    }
+
+    /// Rebuilds the DAG of the epoch from the store.
+    ///
+    /// Without a committed leader, the DAG starts with the genesis certificates. Otherwise, the
+    /// certificates that were committed before are only restored as parents of later ones.
+    fn recover(&mut self, last_committed: Round) -> Result<(), DagStoreError> {
+        let epoch = self.committee.epoch;
+        if last_committed == 0 {
+            let authorities = 0..self.committee.authorities.len() as u32;
+            for certificate in Certificate::genesis(epoch, authorities) {
+                self.dag.insert(DagVertex::new(certificate)).expect("genesis has no parents");
+            }
+        }
+
+        let recovered = self.store.recover()?;
+        for certificate in recovered.certificates {
+            if certificate.header.epoch != epoch {
+                continue
+            }
+            let committed = recovered
+                .last_committed
+                .get(&certificate.author())
+                .is_some_and(|&committed| certificate.round() <= committed);
+            if last_committed > 0 && committed {
+                if let Err(err) = self.dag.insert_committed(&DagVertex::new(certificate)) {
+                    warn!(target: "consensus::narwhal", %err, "Skipping recovered certificate");
+                }
+            } else {
+                self.insert(certificate);
+            }
+        }
+        debug!(
+            target: "consensus::narwhal",
+            epoch,
+            last_committed,
+            vertices = self.dag.len(),
+            pending = self.pending.len(),
+            "Recovered committer"
+        );
+        Ok(())
+    }
+
+    /// Inserts a certificate into the DAG, together with the pending certificates whose parents
+    /// are now complete.
+    fn insert(&mut self, certificate: Certificate) {
+        self.pending.push(certificate);
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for certificate in std::mem::take(&mut self.pending) {
+                match self.dag.insert(DagVertex::new(certificate.clone())) {
+                    Ok(inserted) => progress |= inserted,
+                    Err(DagError::MissingParent { .. }) => self.pending.push(certificate),
+                    Err(err @ DagError::Equivocation { .. }) => {
+                        self.metrics.equivocations.increment(1);
+                        warn!(target: "consensus::narwhal", %err, "Rejected certificate");
+                    }
+                }
+            }
+        }
+        self.metrics.pending_certificates.set(self.pending.len() as f64);
+        self.account_memory();
+    }
+
+    /// Updates the usage of the DAG and of the pending certificates in the memory budget.
+    fn account_memory(&self) {
+        let Some((dag, pending)) = &self.memory else { return };
+        for (handle, len) in [(dag, self.dag.len()), (pending, self.pending.len())] {
+            let (usage, bytes) = (handle.usage(), len * CERTIFICATE_SIZE);
+            if bytes > usage {
+                handle.charge(bytes - usage);
+            } else {
+                handle.release(usage - bytes);
+            }
+        }
+    }
+
+    /// Commits the certificates it receives until the primary stops or the receiver of the
+    /// sub-dags is dropped.
+    pub async fn run(mut self) {
+        while let Some(certificate) = self.certificates.recv().await {
+            if certificate.header.epoch != self.committee.epoch {
+                continue
+            }
+            if let Some(state) = &self.state {
+                state.record_certificate(&certificate);
+            }
+            if let Some(uncommitted) = &self.uncommitted {
+                uncommitted.on_certified(&certificate);
+            }
+            let unanimous = self.fast_path(&certificate);
+            self.insert(certificate);
+
+            let mut committed = self.bullshark.try_commit(&self.committee, &mut self.dag);
+            if let Some(round) = unanimous {
+                committed.extend(self.bullshark.try_commit_unanimous(
+                    &self.committee,
+                    &mut self.dag,
+                    round,
+                ));
+            }
+            if let Some(fast_path) = &mut self.fast_path {
+                // the certificates of decided leaders are of no use to the fast path anymore
+                fast_path.prune(self.bullshark.decided() + 2);
+            }
+            self.audit();
+            for leader in committed {
+                let sub_dag = match self.assemble(leader) {
+                    Ok(Some(sub_dag)) => sub_dag,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        error!(target: "consensus::narwhal", %err, "Stopping committer");
+                        return
+                    }
+                };
+                if self.output.send(sub_dag).await.is_err() {
+                    return
+                }
+            }
+        }
+    }
+
+    /// Adds a certificate to the fast path, and returns the leader round it can decide.
+    fn fast_path(&mut self, certificate: &Certificate) -> Option<Round> {
+        let fast_path = self.fast_path.as_mut()?;
+        let (bullshark, dag) = (&self.bullshark, &self.dag);
+        let decision =
+            fast_path.add_certificate(certificate, SystemClock.now_millis(), |round| {
+                let leader = bullshark.leader(round)?;
+                Some((leader, dag.vertex(round, leader).map(DagVertex::digest)))
+            })?;
+        Some(decision.round)
+    }
+
+    /// Appends the decisions of the commit rule to the audit log.
+    ///
+    /// A decision that can't be appended is still acted upon, the commit rule is deterministic.
+    fn audit(&mut self) {
+        for decided in self.bullshark.take_decisions() {
+            if let Some(state) = &self.state {
+                state.record_decision(&decided.decision);
+            }
+            if let Err(err) = self.audit_log.append(
+                &self.committee,
+                &decided.decision,
+                decided.leader_certificate,
+                &decided.next_round,
+            ) {
+                self.metrics.failed_audit_entries.increment(1);
+                error!(
+                    target: "consensus::narwhal",
+                    %err,
+                    round = decided.decision.round,
+                    "Failed to append commit decision"
+                );
+            }
+        }
+    }
+
+    /// Assembles the sub-dag of a committed leader, marks its certificates as committed and
+    /// prunes the DAG below the new GC round.
+    ///
+    /// Returns `None` for a commit of only the genesis certificates.
+    fn assemble(
+        &mut self,
+        leader: CommittedLeader,
+    ) -> Result<Option<OrderedSubDag>, CommitterError> {
+        let round = leader.round;
+        let certificates = leader
+            .certificates
+            .into_iter()
+            .filter(|certificate| certificate.round() > 0)
+            .collect::<Vec<_>>();
+        let mut last_committed = BTreeMap::new();
+        for certificate in &certificates {
+            let committed = last_committed.entry(certificate.author()).or_default();
+            *committed = certificate.round().max(*committed);
+        }
+        for (author, round) in last_committed {
+            self.store.write_last_committed(author, round)?;
+        }
+
+        self.metrics.committed_leaders.increment(1);
+        self.metrics.committed_round.set(round as f64);
+        if let Some(committed_round) = &self.committed_round {
+            committed_round.send_replace(round);
+        }
+        let gc = gc_round(round, self.gc_depth);
+        if gc > self.dag.gc_round() {
+            self.dag.prune(gc);
+            self.pending.retain(|certificate| certificate.round() >= gc);
+            self.account_memory();
+        }
+
+        let Some(leader) = certificates.last().cloned() else { return Ok(None) };
+        let batches = OrderedSubDag::batch_order(&certificates)
+            .into_iter()
+            .map(|batch| {
+                self.store
+                    .batch(batch.digest)?
+                    .ok_or(CommitterError::MissingBatch { round, digest: batch.digest })
+            })
+            .collect::<Result<_, _>>()?;
+        let mut sub_dag =
+            OrderedSubDag { index: self.next_sub_dag, leader, certificates, batches, timestamp: 0 };
+        sub_dag.timestamp =
+            TimestampPolicy::MedianCertificates.block_timestamp(&sub_dag, 0, &SystemClock);
+        self.next_sub_dag += 1;
+        debug!(
+            target: "consensus::narwhal",
+            round,
+            index = sub_dag.index,
+            certificates = sub_dag.certificates.len(),
+            batches = sub_dag.batches.len(),
+            "Committed leader"
+        );
+        Ok(Some(sub_dag))
+    }
+}
+
+/// Returns the leader rounds of the committee's epoch that were committed according to the audit
+/// log, in commit order.
+fn committed_rounds(
+    store: &dyn DagStore,
+    committee: &Committee,
+) -> Result<Vec<Round>, DagStoreError> {
+    let mut rounds = Vec::new();
+    let mut from_index = 0;
+    loop {
+        let entries = store.audit_entries(from_index, AUDIT_PAGE)?;
+        let Some(last) = entries.last() else { break };
+        from_index = last.index + 1;
+        rounds.extend(
+            entries
+                .iter()
+                .filter(|entry| entry.epoch == committee.epoch && entry.outcome.is_committed())
+                .map(|entry| entry.round),
+        );
+    }
+    rounds.sort_unstable();
+    rounds.dedup();
+    Ok(rounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dag_store::MemoryDagStore,
+        dev::DevCommittee,
+        types::{Batch, BatchRef, Header},
+    };
+    use alloy_primitives::Bytes;
+
+    /// Returns the certificates of `rounds` rounds in which every authority references every
+    /// certificate of the previous round and one batch, and stores the batches.
+    fn rounds(committee: &Committee, store: &dyn DagStore, rounds: Round) -> Vec<Certificate> {
+        let size = committee.authorities.len() as u32;
+        let mut parents = Certificate::genesis(committee.epoch, 0..size)
+            .iter()
+            .map(Certificate::digest)
+            .collect::<Vec<_>>();
+        let mut certificates = Vec::new();
+        for round in 1..=rounds {
+            let round_certificates = (0..size)
+                .map(|author| {
+                    let batch = Batch::new(vec![Bytes::from(vec![round as u8, author as u8])]);
+                    store.write_batch(batch.digest(), &batch).unwrap();
+                    let header = Header {
+                        epoch: committee.epoch,
+                        round,
+                        author,
+                        created_at: round * 1_000,
+                        parents: parents.clone(),
+                        payload: vec![BatchRef { digest: batch.digest(), worker: 0 }],
+                    };
+                    Certificate { header, ..Default::default() }
+                })
+                .collect::<Vec<_>>();
+            parents = round_certificates.iter().map(Certificate::digest).collect();
+            certificates.extend(round_certificates);
+        }
+        certificates
+    }
+
+    fn start(
+        committee: &Committee,
+        store: &Arc<MemoryDagStore>,
+        first_sub_dag: u64,
+    ) -> (Committer, mpsc::Sender<Certificate>, mpsc::Receiver<OrderedSubDag>) {
+        let (certificates, certificates_rx) = mpsc::channel(100);
+        let (output, output_rx) = mpsc::channel(100);
+        let committer = Committer::new(
+            Arc::new(committee.clone()),
+            LeaderSchedule::RoundRobin,
+            store.clone(),
+            50,
+            certificates_rx,
+            output,
+            first_sub_dag,
+        )
+        .unwrap();
+        (committer, certificates, output_rx)
+    }
+
+    #[tokio::test]
+    async fn commit_and_resume() {
+        let committee = DevCommittee::new(4).committee().clone();
+        let store = Arc::new(MemoryDagStore::default());
+        let certificates = rounds(&committee, store.as_ref(), 8);
+
+        let (committer, sender, mut sub_dags) = start(&committee, &store, 3);
+        let task = tokio::spawn(committer.run());
+        // the primary stores the certificates before it forwards them
+        for certificate in certificates.iter().filter(|certificate| certificate.round() <= 5) {
+            store.write_certificate(certificate).unwrap();
+            sender.send(certificate.clone()).await.unwrap();
+        }
+        drop(sender);
+        task.await.unwrap();
+
+        let first = sub_dags.recv().await.unwrap();
+        assert_eq!((first.index, first.leader_round()), (3, 2));
+        // the genesis round is not part of the sub-dag
+        assert_eq!(first.certificates.len(), 4 + 1);
+        assert_eq!(first.batches.len(), 5);
+        assert_eq!(first.timestamp, 1);
+        assert!(sub_dags.recv().await.is_none());
+        assert_eq!(store.last_committed().unwrap()[&first.leader.author()], 2);
+        let entries = store.audit_entries(0, 10).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.round).collect::<Vec<_>>(), [2]);
+
+        // after a restart, the committer continues with the next leader
+        let (committer, sender, mut sub_dags) = start(&committee, &store, 4);
+        assert_eq!(committer.last_committed(), 2);
+        let task = tokio::spawn(committer.run());
+        for certificate in certificates.iter().filter(|certificate| certificate.round() > 5) {
+            store.write_certificate(certificate).unwrap();
+            sender.send(certificate.clone()).await.unwrap();
+        }
+        drop(sender);
+        task.await.unwrap();
+
+        for (index, round) in [(4, 4), (5, 6)] {
+            let sub_dag = sub_dags.recv().await.unwrap();
+            assert_eq!((sub_dag.index, sub_dag.leader_round()), (index, round));
+            // the certificates of the previous round that were not committed with the previous
+            // leader are committed with this one
+            assert_eq!(sub_dag.certificates.len(), 3 + 4 + 1);
+        }
+        assert!(sub_dags.recv().await.is_none());
+        let entries = store.audit_entries(0, 10).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.round).collect::<Vec<_>>(), [2, 4, 6]);
+    }
+
+    #[test]
+    fn charge_memory_budget() {
+        let committee = DevCommittee::new(4).committee().clone();
+        let store = Arc::new(MemoryDagStore::default());
+        let certificates = rounds(&committee, store.as_ref(), 2);
+        let budget = MemoryBudget::default();
+
+        let (committer, _sender, _sub_dags) = start(&committee, &store, 0);
+        let mut committer = committer.with_memory_budget(&budget);
+        assert_eq!(budget.usage(MemoryComponent::Dag), 4 * CERTIFICATE_SIZE);
+
+        // a certificate of the second round waits for its parents
+        committer.insert(certificates[4].clone());
+        assert_eq!(budget.usage(MemoryComponent::PendingCertificates), CERTIFICATE_SIZE);
+        for certificate in &certificates[..4] {
+            committer.insert(certificate.clone());
+        }
+        assert_eq!(budget.usage(MemoryComponent::PendingCertificates), 0);
+        assert_eq!(budget.usage(MemoryComponent::Dag), 9 * CERTIFICATE_SIZE);
+        assert_eq!(budget.total(), 9 * CERTIFICATE_SIZE);
+    }
+
+    #[tokio::test]
+    async fn fast_path_commits_unanimous_round() {
+        let committee = DevCommittee::new(4).committee().clone();
+        let store = Arc::new(MemoryDagStore::default());
+        let certificates = rounds(&committee, store.as_ref(), 3);
+
+        let (committer, sender, mut sub_dags) = start(&committee, &store, 0);
+        let fast_path = FastPath::new(committee.clone(), Default::default());
+        let task = tokio::spawn(committer.with_fast_path(fast_path).run());
+        for certificate in certificates {
+            store.write_certificate(&certificate).unwrap();
+            sender.send(certificate).await.unwrap();
+        }
+        drop(sender);
+        task.await.unwrap();
+
+        // without the fast path, the leader of round 2 waits for round 4
+        let sub_dag = sub_dags.recv().await.unwrap();
+        assert_eq!(sub_dag.leader_round(), 2);
+        assert!(sub_dags.recv().await.is_none());
+        let entries = store.audit_entries(0, 10).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.round).collect::<Vec<_>>(), [2]);
+    }
+
+    #[tokio::test]
+    async fn missing_batch_stops_committer() {
+        let committee = DevCommittee::new(4).committee().clone();
+        let store = Arc::new(MemoryDagStore::default());
+        let certificates = rounds(&committee, &MemoryDagStore::default(), 4);
+
+        let (committer, sender, mut sub_dags) = start(&committee, &store, 0);
+        let task = tokio::spawn(committer.run());
+        for certificate in certificates {
+            // the committer stops at the commit of round 2
+            let _ = sender.send(certificate).await;
+        }
+        drop(sender);
+        task.await.unwrap();
+        assert!(sub_dags.recv().await.is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Node-local configuration of narwhal.
+//!
+//! Unlike the chain specification, which every validator must agree on, these settings only tune
+//! the behavior of a single node and can differ between validators.
+
+use crate::{
+    backpressure::BackpressureConfig,
+    chaos::ChaosConfig,
+    crosscheck::CrossCheckConfig,
+    deposits::DepositIngestionConfig,
+    failover::FailoverConfig,
+    fast_path::FastPathConfig,
+    gas_report::GasReportConfig,
+    leader::LeaderSchedule,
+    memory::MemoryBudgetConfig,
+    primary::PrimaryConfig,
+    record::MessageRecordConfig,
+    rpc::RpcLimitsConfig,
+    shadow::ValidatorMode,
+    stall::StallDetectorConfig,
+    warm_start::WarmStartConfig,
+    wire::WireConfig,
+    worker::{
+        BatchConfig, BatchEncryptionConfig, SenderRateLimitConfig, SubmissionConfig,
+        TransactionRouting, WorkerNetworkConfig,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of a narwhal node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NarwhalConfig {
+    /// How the workers seal transactions into batches.
+    pub batch: BatchConfig,
+    /// How the workers replicate their batches.
+    pub network: WorkerNetworkConfig,
+    /// Which workers batch which classes of transactions.
+    pub routing: TransactionRouting,
+    /// How many transactions of a single sender the workers batch, unlimited by default.
+    pub rate_limit: Option<SenderRateLimitConfig>,
+    /// How the workers encrypt the batches they exchange.
+    pub encryption: BatchEncryptionConfig,
+    /// Where the workers accept transactions submitted over gRPC.
+    pub submission: SubmissionConfig,
+    /// Which versions of the wire format the node offers to its peers.
+    pub wire: WireConfig,
+    /// How a standby node takes over signing from the active node of the validator.
+    pub failover: FailoverConfig,
+    /// How the leaders of the commit rule are elected, which must be the same on every validator.
+    pub leader_schedule: LeaderSchedule,
+    /// When a round counts as unanimous for the fast path of the commit rule, if the chain
+    /// enables it.
+    pub fast_path: FastPathConfig,
+    /// The memory budget of the consensus caches.
+    pub memory: MemoryBudgetConfig,
+    /// How the primary proposes headers.
+    pub primary: PrimaryConfig,
+    /// When batch production is throttled because execution lags behind consensus.
+    pub backpressure: BackpressureConfig,
+    /// When the node reports that consensus stalled.
+    pub stall: StallDetectorConfig,
+    /// What the node does when another authority reports a different block hash, if the node
+    /// gossips its block hashes.
+    pub crosscheck: CrossCheckConfig,
+    /// How many of the hottest accounts and storage slots the node reads ahead after a restart.
+    pub warm_start: WarmStartConfig,
+    /// Where the gas throughput reports of the executed commits are written.
+    pub gas_report: GasReportConfig,
+    /// The limits of the narwhal RPC namespace.
+    pub rpc: RpcLimitsConfig,
+    /// Whether the node participates in consensus or only logs what it would have sent.
+    pub mode: ValidatorMode,
+    /// Where the node reads the deposits of the L1 bridge, if the chain accepts deposits.
+    pub deposits: DepositIngestionConfig,
+    /// Which faults the node injects for resilience testing, none by default.
+    pub chaos: ChaosConfig,
+    /// Where the node records its inbound consensus messages for offline replay, nowhere by
+    /// default.
+    pub record: MessageRecordConfig,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worker::TransactionClass;
+    use std::time::Duration;
+
+    #[test]
+    fn parse_config() {
+        let config: NarwhalConfig = serde_json::from_str(
+            r#"{"batch":{"maxBatchBytes":1000,"maxBatchDelay":"1s","maxTransactions":10}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.batch,
+            BatchConfig {
+                max_batch_bytes: 1_000,
+                max_batch_delay: Duration::from_secs(1),
+                max_transactions: 10
+            }
+        );
+        assert_eq!(serde_json::from_str::<NarwhalConfig>("{}").unwrap(), NarwhalConfig::default());
+
+        let config: NarwhalConfig = serde_json::from_str(
+            r#"{"leaderSchedule":{"strategy":"reputation","maxFailures":5,"exclusionRounds":50}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.leader_schedule,
+            LeaderSchedule::Reputation { max_failures: 5, exclusion_rounds: 50 }
+        );
+
+        let config: NarwhalConfig = serde_json::from_str(
+            r#"{"routing":{"lanes":[
+                {"class":"blob","workers":[1],"batch":{"maxTransactions":6}}
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.routing.lanes[0].class, TransactionClass::Blob);
+        assert_eq!(config.routing.batch_config(1, config.batch).max_transactions, 6);
+        assert_eq!(config.routing.batch_config(0, config.batch), config.batch);
+
+        let config: NarwhalConfig =
+            serde_json::from_str(r#"{"submission":{"addr":"127.0.0.1:9000"}}"#).unwrap();
+        assert_eq!(config.submission.worker_addr(2), Some("127.0.0.1:9002".parse().unwrap()));
+        assert_eq!(NarwhalConfig::default().submission.worker_addr(0), None);
+
+        let config: NarwhalConfig =
+            serde_json::from_str(r#"{"rateLimit":{"maxTransactionsPerBatch":4}}"#).unwrap();
+        let rate_limit = config.rate_limit.unwrap();
+        assert_eq!(rate_limit.max_transactions_per_batch, 4);
+        assert_eq!(
+            rate_limit.max_transactions_per_round,
+            SenderRateLimitConfig::default().max_transactions_per_round
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! [`Consensus`] implementation for narwhal chains.
+
+use crate::{
+    dag_store::DagStore,
+    recovery::CommittedSubDags,
+    sequencing::{AllowlistSource, ChainSequencingFilter, SenderAllowlist, SequencingFilter},
+    types::TransactionExpiry,
+    validation,
+    verifier::SigningDomain,
+    NarwhalChainInfo,
+};
+use reth_chainspec::ChainSpec;
+use reth_consensus::{Consensus, ConsensusError, PostExecutionInput};
+use reth_consensus_common::validation::{validate_block_gas_used, verify_receipts};
+use reth_primitives::{
+    keccak256, BlockWithSenders, Header, SealedBlock, SealedHeader, TxHash, U256,
+};
+use std::{collections::HashSet, sync::Arc};
+use tracing::warn;
+
+/// A consensus implementation for blocks built from narwhal consensus output.
+#[derive(Debug, Clone)]
+pub struct NarwhalConsensus {
+    /// Configuration
+    chain_spec: Arc<ChainSpec>,
+    /// Narwhal specific chain parameters
+    chain_info: NarwhalChainInfo,
+    /// Filter the transactions of every block must pass
+    sequencing_filter: Arc<dyn SequencingFilter>,
+    /// Allowed senders, if the chain is permissioned
+    sender_allowlist: Option<SenderAllowlist>,
+    /// Store of the recorded sub-dags the blocks must commit to
+    dag_store: Option<Arc<dyn DagStore>>,
+}
+
+impl NarwhalConsensus {
+    /// Create a new instance of [`NarwhalConsensus`]
+    ///
+    /// # Panics
+    ///
+    /// If the narwhal section of the chain spec's genesis config is malformed.
+    pub fn new(chain_spec: Arc<ChainSpec>) -> Self {
+        let chain_info = NarwhalChainInfo::from_genesis(&chain_spec.genesis)
+            .expect("invalid narwhal chain info in genesis");
+        let sequencing_filter =
+            Arc::new(ChainSequencingFilter::new(chain_info.sequencing_filters.clone()));
+        let sender_allowlist = chain_info.permissioned.clone().map(SenderAllowlist::new);
+        Self { chain_spec, chain_info, sequencing_filter, sender_allowlist, dag_store: None }
+    }
+
+    /// Replaces the sequencing filter configured by the chain spec.
+    ///
+    /// Workers must be configured with the same filter.
+    pub fn with_sequencing_filter(mut self, filter: Arc<dyn SequencingFilter>) -> Self {
+        self.sequencing_filter = filter;
+        self
+    }
+
+    /// Catches the allowlist of a permissioned chain up with the canonical chain from the
+    /// receipts of the source, see [`SenderAllowlist::with_source`].
+    ///
+    /// Without a source, the node must apply every canonical block to the allowlist.
+    pub fn with_allowlist_source(mut self, source: Arc<dyn AllowlistSource>) -> Self {
+        self.sender_allowlist =
+            self.sender_allowlist.map(|allowlist| allowlist.with_source(source));
+        self
+    }
+
+    /// Validates that every block commits to the sub-dag the local DAG committed after the sub-dag
+    /// of its parent, see [`validation::validate_sub_dag_commitment`]. Blocks of sub-dags that are
+    /// not recorded in the store are rejected.
+    ///
+    /// Must be the store the [`EpochManager`](crate::epoch::EpochManager) records the committed
+    /// sub-dags in.
+    pub fn with_dag_store(mut self, store: Arc<dyn DagStore>) -> Self {
+        self.dag_store = Some(store);
+        self
+    }
+
+    /// Returns the chain spec this consensus validates against.
+    pub const fn chain_spec(&self) -> &Arc<ChainSpec> {
+        &self.chain_spec
+    }
+
+    /// Returns the narwhal specific chain parameters.
+    pub const fn chain_info(&self) -> &NarwhalChainInfo {
+        &self.chain_info
+    }
+
+    /// Returns the domain every consensus artifact of this chain is signed in.
+    pub fn signing_domain(&self) -> SigningDomain {
+        SigningDomain::new(self.chain_spec.chain.id(), self.chain_spec.genesis_hash())
+    }
+
+    /// Returns the allowlist of senders if the chain is permissioned.
+    pub const fn sender_allowlist(&self) -> Option<&SenderAllowlist> {
+        self.sender_allowlist.as_ref()
+    }
+
+    /// Returns the hashes of the transactions of the commit the block was built from, in
+    /// sequencing order, if the commit is recorded in the DAG store.
+    fn sequenced_transactions(&self, block: &BlockWithSenders) -> Option<Vec<TxHash>> {
+        let store = self.dag_store.clone()?;
+        // a commit that can't be assembled leaves the order unchecked, like a missing record
+        match CommittedSubDags::new(store).sub_dag(block.mix_hash) {
+            Ok(sub_dag) => Some(
+                sub_dag?
+                    .transactions()
+                    .map(|encoded| keccak256(TransactionExpiry::split_batched(encoded).1))
+                    .collect(),
+            ),
+            Err(err) => {
+                warn!(target: "consensus::narwhal", %err, "Failed to read the commit of the block");
+                None
+            }
+        }
+    }
+}
+
+impl Consensus for NarwhalConsensus {
+    fn validate_header(&self, header: &SealedHeader) -> Result<(), ConsensusError> {
+        validation::validate_block_commitment_present(header)?;
+
+        Ok(())
+    }
+
+    fn validate_header_against_parent(
+        &self,
+        header: &SealedHeader,
+        parent: &SealedHeader,
+    ) -> Result<(), ConsensusError> {
+        validation::validate_timestamp(header, parent)?;
+        validation::validate_block_commitment_against_parent(header, parent)?;
+        if let Some(store) = &self.dag_store {
+            validation::validate_sub_dag_commitment(header, parent, &**store)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_header_with_total_difficulty(
+        &self,
+        _header: &Header,
+        _total_difficulty: U256,
+    ) -> Result<(), ConsensusError> {
+        Ok(())
+    }
+
+    fn validate_block_pre_execution(&self, block: &SealedBlock) -> Result<(), ConsensusError> {
+        if let Some(limits) = &self.chain_info.transaction_size_limits {
+            validation::validate_transaction_sizes(block, limits)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_block_post_execution(
+        &self,
+        block: &BlockWithSenders,
+        input: PostExecutionInput<'_>,
+    ) -> Result<(), ConsensusError> {
+        validate_block_gas_used(&block.header, input.receipts)?;
+        verify_receipts(block.header.receipts_root, block.header.logs_bloom, input.receipts)?;
+        let mut sequenced = self.sequenced_transactions(block);
+        if let Some(deposits) = &self.chain_info.deposits {
+            validation::validate_deposit_order(block, deposits.sender)?;
+            // deposits are moved to the front of the block instead of being ordered by the nonce
+            // gap policy
+            if let Some(sequenced) = &mut sequenced {
+                let moved = block
+                    .transactions_with_sender()
+                    .filter(|(sender, _)| **sender == deposits.sender)
+                    .map(|(_, transaction)| transaction.hash())
+                    .collect::<HashSet<_>>();
+                sequenced.retain(|hash| !moved.contains(hash));
+            }
+        }
+        validation::validate_sender_nonces(
+            block,
+            self.chain_info.nonce_gap_policy,
+            sequenced.as_deref(),
+        )?;
+        validation::validate_sequencing_filter(block, &*self.sequencing_filter)?;
+        if let Some(allowlist) = &self.sender_allowlist {
+            validation::validate_sequencing_filter(block, allowlist)?;
+        }
+        validation::validate_block_commitment(&block.header, input.receipts)?;
+
+        Ok(())
+    }
+}
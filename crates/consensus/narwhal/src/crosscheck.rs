@@ -0,0 +1,479 @@
+//! Cross-checking of block hashes between validators.
+//!
+//! Every validator executes the committed sub-dags on its own, so a determinism bug in execution
+//! makes validators diverge without consensus noticing. Validators that enable the cross-check
+//! gossip the hash of every block they execute, and [`BlockHashCrossCheck`] compares the hashes
+//! reported by other authorities with the local ones.
+//!
+//! A [`BlockHashMismatch`] is reported for every authority that disagrees with the local hash. The
+//! caller alerts the operator, captures the evidence with [`BlockHashMismatch::write_evidence`],
+//! and halts execution if [`CrossCheckConfig::halt_on_mismatch`] is set. A node runs the
+//! cross-check with [`BlockHashCrossCheck::run`], which takes the local hashes from the
+//! [`NarwhalEvents`](crate::NarwhalEvents) and exchanges [`BlockHashReport`]s over the
+//! [`BlockHashGossip`] of the node.
+
+use crate::{events::NarwhalEvent, rpc::CommittedSubDag};
+use alloy_primitives::{BlockNumber, B256};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc::{self, error::TrySendError},
+    oneshot,
+};
+use tracing::{debug, error, info};
+
+/// Configuration of the [`BlockHashCrossCheck`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CrossCheckConfig {
+    /// Whether execution halts when a mismatch is detected.
+    pub halt_on_mismatch: bool,
+    /// Number of blocks below and above the local tip for which hashes are kept.
+    ///
+    /// Reports of other authorities outside of this window are ignored.
+    pub window: u64,
+    /// The directory the evidence of every mismatch is written to, none by default.
+    pub evidence_dir: Option<PathBuf>,
+}
+
+impl Default for CrossCheckConfig {
+    fn default() -> Self {
+        Self { halt_on_mismatch: false, window: 128, evidence_dir: None }
+    }
+}
+
+/// The hash of a block executed by an authority, gossiped to the other authorities.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHashReport {
+    /// The authority that executed the block.
+    pub authority: AuthorityIndex,
+    /// The number of the block.
+    pub number: BlockNumber,
+    /// The hash the authority computed.
+    pub hash: B256,
+}
+
+/// The channels the [`BlockHashReport`]s are gossiped over, provided by the node.
+///
+/// The node broadcasts the reports of `outbound` to the other authorities of the committee, and
+/// hands the reports it receives to `inbound`. It must only hand over reports of the authority it
+/// received them from, otherwise any peer could raise mismatches in the name of others.
+#[derive(Debug)]
+pub struct BlockHashGossip {
+    /// The reports of this authority.
+    pub outbound: mpsc::Sender<BlockHashReport>,
+    /// The reports of the other authorities.
+    pub inbound: mpsc::Receiver<BlockHashReport>,
+}
+
+/// A locally executed block, the evidence of a mismatch.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecutedBlock {
+    sub_dag: u64,
+    part: usize,
+    transactions: usize,
+    gas_used: u64,
+}
+
+/// Metrics of the [`BlockHashCrossCheck`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.crosscheck")]
+struct CrossCheckMetrics {
+    /// Number of block hashes reported by other authorities that were compared
+    compared_hashes: Counter,
+    /// Number of block hashes reported by other authorities that differ from the local hash
+    mismatches: Counter,
+}
+
+/// A block hash reported by another authority that differs from the locally computed one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHashMismatch {
+    /// The number of the block.
+    pub number: BlockNumber,
+    /// The locally computed hash.
+    pub local: B256,
+    /// The hash reported by the authority.
+    pub remote: B256,
+    /// The reporting authority.
+    pub authority: AuthorityIndex,
+}
+
+impl BlockHashMismatch {
+    /// Logs the mismatch as an error.
+    pub fn log(&self) {
+        error!(
+            target: "consensus::narwhal",
+            number = self.number,
+            local = %self.local,
+            remote = %self.remote,
+            authority = self.authority,
+            "Block hash differs from the hash of another authority"
+        );
+    }
+
+    /// Writes the mismatch and its evidence, e.g. the local block and the commit it was built
+    /// from, as JSON to a file in the given directory.
+    ///
+    /// Returns the path of the file.
+    pub fn write_evidence<E: Serialize>(&self, dir: &Path, evidence: &E) -> io::Result<PathBuf> {
+        #[derive(Serialize)]
+        struct Evidence<'a, E> {
+            mismatch: &'a BlockHashMismatch,
+            evidence: &'a E,
+        }
+
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("block-hash-mismatch-{}-{}.json", self.number, self.authority));
+        let mut file = fs::File::create(&path)?;
+        serde_json::to_writer_pretty(&mut file, &Evidence { mismatch: self, evidence })?;
+        file.write_all(b"\n")?;
+        Ok(path)
+    }
+}
+
+/// Compares the block hashes reported by other authorities with the locally computed ones.
+///
+/// Reports may arrive before the block was executed locally, they are kept until the local hash is
+/// known. Each authority is compared at most once per block.
+#[derive(Debug)]
+pub struct BlockHashCrossCheck {
+    config: CrossCheckConfig,
+    /// Hashes of the locally executed blocks within the window.
+    local: BTreeMap<BlockNumber, B256>,
+    /// Reports of blocks that were not executed locally yet.
+    pending: BTreeMap<BlockNumber, HashMap<AuthorityIndex, B256>>,
+    /// Authorities whose report was compared, by block.
+    compared: BTreeMap<BlockNumber, Vec<AuthorityIndex>>,
+    /// The highest locally executed block.
+    tip: BlockNumber,
+    metrics: CrossCheckMetrics,
+}
+
+impl BlockHashCrossCheck {
+    /// Creates a new cross-check.
+    pub fn new(config: CrossCheckConfig) -> Self {
+        Self {
+            config,
+            local: BTreeMap::new(),
+            pending: BTreeMap::new(),
+            compared: BTreeMap::new(),
+            tip: 0,
+            metrics: Default::default(),
+        }
+    }
+
+    /// Returns the configuration.
+    pub const fn config(&self) -> &CrossCheckConfig {
+        &self.config
+    }
+
+    /// Records the hash of a locally executed block.
+    ///
+    /// Returns the mismatches with reports that arrived before the block was executed.
+    pub fn on_local_block(&mut self, number: BlockNumber, hash: B256) -> Vec<BlockHashMismatch> {
+        self.local.insert(number, hash);
+        if number > self.tip {
+            self.tip = number;
+            self.prune();
+        }
+
+        let reports = self.pending.remove(&number).unwrap_or_default();
+        let mut reports = reports.into_iter().collect::<Vec<_>>();
+        reports.sort_unstable_by_key(|(authority, _)| *authority);
+        reports
+            .into_iter()
+            .filter_map(|(authority, remote)| self.compare(number, hash, authority, remote))
+            .collect()
+    }
+
+    /// Records the hash of a block reported by another authority.
+    ///
+    /// Returns the mismatch if the block was executed locally and its hash differs.
+    pub fn on_remote_block(
+        &mut self,
+        authority: AuthorityIndex,
+        number: BlockNumber,
+        hash: B256,
+    ) -> Option<BlockHashMismatch> {
+        if !self.in_window(number) ||
+            self.compared.get(&number).is_some_and(|compared| compared.contains(&authority))
+        {
+            return None
+        }
+
+        match self.local.get(&number) {
+            Some(local) => self.compare(number, *local, authority, hash),
+            None => {
+                self.pending.entry(number).or_default().entry(authority).or_insert(hash);
+                None
+            }
+        }
+    }
+
+    fn compare(
+        &mut self,
+        number: BlockNumber,
+        local: B256,
+        authority: AuthorityIndex,
+        remote: B256,
+    ) -> Option<BlockHashMismatch> {
+        self.compared.entry(number).or_default().push(authority);
+        self.metrics.compared_hashes.increment(1);
+        if local == remote {
+            return None
+        }
+        self.metrics.mismatches.increment(1);
+        Some(BlockHashMismatch { number, local, remote, authority })
+    }
+
+    const fn in_window(&self, number: BlockNumber) -> bool {
+        number >= self.tip.saturating_sub(self.config.window) &&
+            number <= self.tip.saturating_add(self.config.window)
+    }
+
+    /// Forgets everything below the window.
+    fn prune(&mut self) {
+        let oldest = self.tip.saturating_sub(self.config.window);
+        self.local = self.local.split_off(&oldest);
+        self.pending = self.pending.split_off(&oldest);
+        self.compared = self.compared.split_off(&oldest);
+    }
+
+    /// Gossips the hashes of the blocks this authority executes and compares them with the
+    /// hashes reported by the other authorities, until the events are closed.
+    ///
+    /// Every mismatch is logged, and written together with the local block and the commit it was
+    /// built from to the [evidence directory](CrossCheckConfig::evidence_dir). On the first
+    /// mismatch, `halt` is signaled and the cross-check stops if
+    /// [`CrossCheckConfig::halt_on_mismatch`] is set.
+    pub async fn run(
+        mut self,
+        authority: AuthorityIndex,
+        mut events: broadcast::Receiver<NarwhalEvent>,
+        mut gossip: BlockHashGossip,
+        halt: oneshot::Sender<()>,
+    ) {
+        let mut blocks = BTreeMap::<BlockNumber, ExecutedBlock>::new();
+        let mut commits = BTreeMap::<u64, CommittedSubDag>::new();
+        loop {
+            let mismatches = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(NarwhalEvent::SubDagCommitted(sub_dag)) => {
+                        commits.insert(sub_dag.index, sub_dag);
+                        continue
+                    }
+                    Ok(NarwhalEvent::BlockExecuted {
+                        sub_dag,
+                        part,
+                        number,
+                        hash,
+                        transactions,
+                        gas_used,
+                    }) => {
+                        let report = BlockHashReport { authority, number, hash };
+                        if let Err(TrySendError::Full(_)) = gossip.outbound.try_send(report) {
+                            debug!(
+                                target: "consensus::narwhal",
+                                number,
+                                "Gossip of block hashes is full, dropping report"
+                            );
+                        }
+                        let block = ExecutedBlock { sub_dag, part, transactions, gas_used };
+                        blocks.insert(number, block);
+                        self.on_local_block(number, hash)
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+                Some(report) = gossip.inbound.recv() => {
+                    if report.authority == authority {
+                        continue
+                    }
+                    self.on_remote_block(report.authority, report.number, report.hash)
+                        .into_iter()
+                        .collect::<Vec<_>>()
+                }
+            };
+
+            // the evidence is kept for the blocks within the window
+            blocks = blocks.split_off(&self.tip.saturating_sub(self.config.window));
+            if let Some(oldest) = blocks.values().next() {
+                commits = commits.split_off(&oldest.sub_dag);
+            }
+
+            for mismatch in &mismatches {
+                mismatch.log();
+                if let Some(dir) = &self.config.evidence_dir {
+                    let block = blocks.get(&mismatch.number);
+                    let evidence = Evidence {
+                        block,
+                        commit: block.and_then(|block| commits.get(&block.sub_dag)),
+                    };
+                    match mismatch.write_evidence(dir, &evidence) {
+                        Ok(path) => info!(
+                            target: "consensus::narwhal",
+                            path = %path.display(),
+                            "Wrote evidence of block hash mismatch"
+                        ),
+                        Err(err) => error!(
+                            target: "consensus::narwhal",
+                            %err,
+                            "Failed to write evidence of block hash mismatch"
+                        ),
+                    }
+                }
+            }
+            if self.config.halt_on_mismatch && !mismatches.is_empty() {
+                error!(target: "consensus::narwhal", "Halting execution after block hash mismatch");
+                let _ = halt.send(());
+                return
+            }
+        }
+    }
+}
+
+/// The evidence of a mismatch written by [`BlockHashCrossCheck::run`].
+#[derive(Serialize)]
+struct Evidence<'a> {
+    block: Option<&'a ExecutedBlock>,
+    commit: Option<&'a CommittedSubDag>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::NarwhalEvents,
+        types::{BatchDigest, CertificateDigest},
+    };
+
+    #[test]
+    fn detect_mismatches() {
+        let mut check = BlockHashCrossCheck::new(CrossCheckConfig::default());
+        let [a, b] = [1, 2].map(B256::with_last_byte);
+
+        // reported before the local execution
+        assert_eq!(check.on_remote_block(1, 1, a), None);
+        assert_eq!(check.on_remote_block(2, 1, b), None);
+        assert_eq!(
+            check.on_local_block(1, a),
+            vec![BlockHashMismatch { number: 1, local: a, remote: b, authority: 2 }]
+        );
+
+        // reported after the local execution
+        assert_eq!(check.on_remote_block(3, 1, a), None);
+        assert_eq!(
+            check.on_remote_block(4, 1, b),
+            Some(BlockHashMismatch { number: 1, local: a, remote: b, authority: 4 })
+        );
+        // every authority is compared once
+        assert_eq!(check.on_remote_block(4, 1, b), None);
+    }
+
+    #[test]
+    fn ignore_reports_outside_window() {
+        let mut check =
+            BlockHashCrossCheck::new(CrossCheckConfig { window: 2, ..Default::default() });
+        let [a, b] = [1, 2].map(B256::with_last_byte);
+        check.on_local_block(10, a);
+
+        assert_eq!(check.on_remote_block(1, 7, b), None);
+        assert_eq!(check.on_remote_block(1, 13, b), None);
+        assert_eq!(check.on_remote_block(1, 12, b), None);
+        assert!(check.on_local_block(13, a).is_empty());
+        assert_eq!(check.on_local_block(12, a).len(), 1);
+
+        // the window moved past block 10
+        check.on_local_block(14, a);
+        assert!(!check.local.contains_key(&10));
+        assert_eq!(check.on_remote_block(1, 10, b), None);
+    }
+
+    #[test]
+    fn write_evidence() {
+        let dir = tempfile::tempdir().unwrap();
+        let mismatch = BlockHashMismatch {
+            number: 5,
+            local: B256::with_last_byte(1),
+            remote: B256::with_last_byte(2),
+            authority: 3,
+        };
+        let path = mismatch.write_evidence(dir.path(), &"block and commit").unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(written["mismatch"]["number"], 5);
+        assert_eq!(written["evidence"], "block and commit");
+    }
+
+    #[tokio::test]
+    async fn gossip_and_halt_on_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CrossCheckConfig {
+            halt_on_mismatch: true,
+            evidence_dir: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let events = NarwhalEvents::new();
+        let (outbound, mut gossiped) = mpsc::channel(8);
+        let (reports, inbound) = mpsc::channel(8);
+        let (halt, halted) = oneshot::channel();
+        let task = tokio::spawn(BlockHashCrossCheck::new(config).run(
+            0,
+            events.subscribe(),
+            BlockHashGossip { outbound, inbound },
+            halt,
+        ));
+
+        let [a, b] = [1, 2].map(B256::with_last_byte);
+        let digest = CertificateDigest(a);
+        events.publish(NarwhalEvent::SubDagCommitted(CommittedSubDag {
+            index: 3,
+            epoch: 0,
+            leader_round: 2,
+            leader: 1,
+            leader_digest: digest,
+            certificates: vec![digest],
+            batches: vec![BatchDigest(b)],
+            timestamp: 1_700_000_000,
+        }));
+        events.publish(NarwhalEvent::BlockExecuted {
+            sub_dag: 3,
+            part: 0,
+            number: 1,
+            hash: a,
+            transactions: 1,
+            gas_used: 21_000,
+        });
+        assert_eq!(
+            gossiped.recv().await,
+            Some(BlockHashReport { authority: 0, number: 1, hash: a })
+        );
+
+        // reports of this authority and matching reports are fine
+        reports.send(BlockHashReport { authority: 0, number: 1, hash: b }).await.unwrap();
+        reports.send(BlockHashReport { authority: 1, number: 1, hash: a }).await.unwrap();
+        reports.send(BlockHashReport { authority: 2, number: 1, hash: b }).await.unwrap();
+        halted.await.unwrap();
+        task.await.unwrap();
+
+        let evidence: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(dir.path().join("block-hash-mismatch-1-2.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(evidence["mismatch"]["remote"], serde_json::to_value(b).unwrap());
+        assert_eq!(evidence["evidence"]["block"]["gasUsed"], 21_000);
+        assert_eq!(evidence["evidence"]["commit"]["index"], 3);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,408 @@
+//! The in-memory DAG of certificates that the commit rule traverses.
+//!
+//! The commit rule looks up the leader of every round, counts the certificates of the next round
+//! that reference it, and orders the causal history of a committed leader. The [`DagStore`]
+//! persists certificates, but reading them back on the hot path would put the database between
+//! two commits. The [`Dag`] therefore indexes the vertices of the recent rounds by digest and by
+//! round and author, and keeps the children of every vertex next to its parents, so that every
+//! step of a traversal is a hash map lookup.
+//!
+//! Memory is bounded in two ways. A committed vertex is dropped as soon as no uncommitted vertex
+//! references it, because traversals never continue past committed vertices: the DAG counts the
+//! uncommitted children of every vertex. Vertices that are never committed, e.g. those that no
+//! leader references, are dropped by [`Dag::prune`] below the garbage collection round.
+//!
+//! [`DagStore`]: crate::dag_store::DagStore
+
+use crate::types::{CertificateDigest, DagVertex, Round};
+use reth_narwhal_verifier::AuthorityIndex;
+use std::collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque};
+
+/// Errors of the [`Dag`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DagError {
+    /// A parent of the vertex is not in the DAG.
+    #[error("parent {parent} of certificate {digest} is not in the DAG")]
+    MissingParent {
+        /// The digest of the vertex.
+        digest: CertificateDigest,
+        /// The digest of the missing parent.
+        parent: CertificateDigest,
+    },
+    /// The author already has another vertex in the round.
+    #[error("authority {author} has two certificates in round {round}")]
+    Equivocation {
+        /// The round of the vertices.
+        round: Round,
+        /// The author of the vertices.
+        author: AuthorityIndex,
+    },
+}
+
+/// A vertex of the [`Dag`] with its adjacency.
+#[derive(Debug, Clone)]
+struct Node {
+    vertex: DagVertex,
+    /// The vertices that reference the vertex.
+    children: Vec<CertificateDigest>,
+    /// The number of children that are not committed yet.
+    uncommitted_children: usize,
+    committed: bool,
+}
+
+/// The vertices of the recent rounds, indexed for the traversals of the commit rule, see the
+/// [module docs](self).
+///
+/// Every vertex must be inserted after its parents, which the primary guarantees by only accepting
+/// certificates whose parents it has.
+#[derive(Debug, Clone, Default)]
+pub struct Dag {
+    nodes: HashMap<CertificateDigest, Node>,
+    /// The digests of the vertices by round and author, including the dropped committed vertices,
+    /// which are still valid parents.
+    rounds: BTreeMap<Round, HashMap<AuthorityIndex, CertificateDigest>>,
+    /// The committed vertices that were dropped.
+    released: HashSet<CertificateDigest>,
+    /// The vertices below this round were pruned.
+    gc_round: Round,
+}
+
+impl Dag {
+    /// Creates an empty DAG whose first round is `gc_round`, e.g. the genesis round of an epoch.
+    pub fn new(gc_round: Round) -> Self {
+        Self { gc_round, ..Default::default() }
+    }
+
+    /// Returns the number of vertices in the DAG, without the dropped committed ones.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns `true` if the DAG has no vertices.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the round below which vertices were pruned.
+    pub const fn gc_round(&self) -> Round {
+        self.gc_round
+    }
+
+    /// Returns the highest round with a vertex.
+    pub fn highest_round(&self) -> Option<Round> {
+        self.rounds.keys().next_back().copied()
+    }
+
+    /// Inserts a vertex, and returns `false` if it's already in the DAG or below the garbage
+    /// collection round.
+    ///
+    /// The parents must be in the DAG, unless the vertex is of the garbage collection round or
+    /// they were committed and dropped.
+    pub fn insert(&mut self, vertex: DagVertex) -> Result<bool, DagError> {
+        let (round, author, digest) = (vertex.round(), vertex.author(), vertex.digest());
+        if round < self.gc_round {
+            return Ok(false)
+        }
+        match self.rounds.get(&round).and_then(|authors| authors.get(&author)) {
+            Some(existing) if *existing == digest => return Ok(false),
+            Some(_) => return Err(DagError::Equivocation { round, author }),
+            None => {}
+        }
+        if round > self.gc_round {
+            let missing = vertex
+                .parents()
+                .iter()
+                .find(|parent| !self.nodes.contains_key(parent) && !self.released.contains(parent));
+            if let Some(parent) = missing {
+                return Err(DagError::MissingParent { digest, parent: *parent })
+            }
+        }
+
+        for parent in vertex.parents() {
+            if let Some(node) = self.nodes.get_mut(parent) {
+                node.children.push(digest);
+                node.uncommitted_children += 1;
+            }
+        }
+        self.rounds.entry(round).or_default().insert(author, digest);
+        let node = Node { vertex, children: Vec::new(), uncommitted_children: 0, committed: false };
+        self.nodes.insert(digest, node);
+        Ok(true)
+    }
+
+    /// Inserts a vertex that was committed before, e.g. before a restart, and returns `false` if
+    /// it's already in the DAG or below the garbage collection round.
+    ///
+    /// The vertex is only kept as a valid parent of later vertices, traversals never continue past
+    /// it.
+    pub fn insert_committed(&mut self, vertex: &DagVertex) -> Result<bool, DagError> {
+        let (round, author, digest) = (vertex.round(), vertex.author(), vertex.digest());
+        if round < self.gc_round {
+            return Ok(false)
+        }
+        match self.rounds.entry(round).or_default().entry(author) {
+            Entry::Occupied(existing) if *existing.get() == digest => Ok(false),
+            Entry::Occupied(_) => Err(DagError::Equivocation { round, author }),
+            Entry::Vacant(entry) => {
+                entry.insert(digest);
+                self.released.insert(digest);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Returns the vertex with the given digest.
+    pub fn get(&self, digest: &CertificateDigest) -> Option<&DagVertex> {
+        self.nodes.get(digest).map(|node| &node.vertex)
+    }
+
+    /// Returns the vertex of an author in a round.
+    pub fn vertex(&self, round: Round, author: AuthorityIndex) -> Option<&DagVertex> {
+        self.get(self.rounds.get(&round)?.get(&author)?)
+    }
+
+    /// Returns the vertices of a round, in no particular order.
+    pub fn round(&self, round: Round) -> impl Iterator<Item = &DagVertex> + '_ {
+        self.rounds
+            .get(&round)
+            .into_iter()
+            .flat_map(|authors| authors.values().filter_map(|digest| self.get(digest)))
+    }
+
+    /// Returns the vertices that reference the vertex.
+    pub fn children(&self, digest: &CertificateDigest) -> impl Iterator<Item = &DagVertex> + '_ {
+        self.nodes
+            .get(digest)
+            .into_iter()
+            .flat_map(|node| node.children.iter().filter_map(|child| self.get(child)))
+    }
+
+    /// Returns the parents of the vertex that are still in the DAG.
+    pub fn parents(&self, digest: &CertificateDigest) -> impl Iterator<Item = &DagVertex> + '_ {
+        self.nodes
+            .get(digest)
+            .into_iter()
+            .flat_map(|node| node.vertex.parents().iter().filter_map(|parent| self.get(parent)))
+    }
+
+    /// Returns `true` if the vertex was committed, including the dropped vertices.
+    pub fn is_committed(&self, digest: &CertificateDigest) -> bool {
+        self.released.contains(digest) || self.nodes.get(digest).is_some_and(|node| node.committed)
+    }
+
+    /// Returns `true` if a path of parent references leads from one vertex to another through
+    /// uncommitted vertices only.
+    ///
+    /// The commit rule uses it to find the earlier leaders that a committed leader commits
+    /// indirectly.
+    pub fn is_linked(&self, from: &CertificateDigest, to: &CertificateDigest) -> bool {
+        let (Some(from_node), Some(to_node)) = (self.nodes.get(from), self.nodes.get(to)) else {
+            return false
+        };
+        let (from_round, to_round) = (from_node.vertex.round(), to_node.vertex.round());
+        let mut frontier = HashSet::from([*from]);
+        for _ in to_round..from_round {
+            frontier = frontier
+                .iter()
+                .filter_map(|digest| self.nodes.get(digest))
+                .flat_map(|node| node.vertex.parents().iter().copied())
+                .filter(|parent| self.nodes.get(parent).is_some_and(|node| !node.committed))
+                .collect();
+        }
+        from_round >= to_round && frontier.contains(to)
+    }
+
+    /// Commits the causal history of a leader that is not committed yet, and returns it in commit
+    /// order: by round, then by author, ending with the leader.
+    ///
+    /// Returns nothing if the leader is not in the DAG or already committed.
+    pub fn commit(&mut self, leader: &CertificateDigest) -> Vec<DagVertex> {
+        let mut history = Vec::new();
+        let mut queue = VecDeque::new();
+        let mut seen = HashSet::new();
+        if self.nodes.get(leader).is_some_and(|node| !node.committed) {
+            queue.push_back(*leader);
+            seen.insert(*leader);
+        }
+        while let Some(digest) = queue.pop_front() {
+            let vertex = &self.nodes[&digest].vertex;
+            for parent in vertex.parents() {
+                let uncommitted = self.nodes.get(parent).is_some_and(|node| !node.committed);
+                if uncommitted && seen.insert(*parent) {
+                    queue.push_back(*parent);
+                }
+            }
+            history.push(vertex.clone());
+        }
+        history.sort_unstable_by_key(|vertex| (vertex.round(), vertex.author()));
+
+        for vertex in &history {
+            self.mark_committed(vertex);
+        }
+        history
+    }
+
+    /// Marks a vertex as committed, and drops it and its parents once no uncommitted vertex
+    /// references them.
+    fn mark_committed(&mut self, vertex: &DagVertex) {
+        let digest = vertex.digest();
+        let Some(node) = self.nodes.get_mut(&digest) else { return };
+        node.committed = true;
+        if node.uncommitted_children == 0 {
+            self.nodes.remove(&digest);
+            self.released.insert(digest);
+        }
+        for parent in vertex.parents() {
+            if let Entry::Occupied(mut entry) = self.nodes.entry(*parent) {
+                let node = entry.get_mut();
+                node.uncommitted_children -= 1;
+                if node.committed && node.uncommitted_children == 0 {
+                    entry.remove();
+                    self.released.insert(*parent);
+                }
+            }
+        }
+    }
+
+    /// Drops the vertices below the given round, committed or not.
+    ///
+    /// The vertices of the garbage collection round can then be inserted without their parents.
+    pub fn prune(&mut self, gc_round: Round) {
+        if gc_round <= self.gc_round {
+            return
+        }
+        let kept = self.rounds.split_off(&gc_round);
+        for digest in self.rounds.values().flat_map(HashMap::values) {
+            self.nodes.remove(digest);
+            self.released.remove(digest);
+        }
+        self.rounds = kept;
+        self.gc_round = gc_round;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Certificate, Header};
+
+    fn vertex(round: Round, author: AuthorityIndex, parents: Vec<CertificateDigest>) -> DagVertex {
+        DagVertex::new(Certificate {
+            header: Header { round, author, parents, ..Default::default() },
+            ..Default::default()
+        })
+    }
+
+    /// Builds the rounds up to `rounds` of a committee of `authorities`, where every vertex
+    /// references all vertices of the previous round.
+    fn full_dag(authorities: AuthorityIndex, rounds: Round) -> (Dag, Vec<Vec<DagVertex>>) {
+        let genesis = Certificate::genesis(0, 0..authorities).into_iter().map(DagVertex::new);
+        let mut layers = vec![genesis.collect::<Vec<_>>()];
+        for round in 1..=rounds {
+            let mut parents =
+                layers[layers.len() - 1].iter().map(DagVertex::digest).collect::<Vec<_>>();
+            parents.sort_unstable();
+            layers.push(
+                (0..authorities).map(|author| vertex(round, author, parents.clone())).collect(),
+            );
+        }
+        let mut dag = Dag::new(0);
+        for vertex in layers.iter().flatten() {
+            assert_eq!(dag.insert(vertex.clone()), Ok(true));
+        }
+        (dag, layers)
+    }
+
+    #[test]
+    fn insert_and_lookup() {
+        let (mut dag, layers) = full_dag(4, 2);
+        assert_eq!(dag.len(), 12);
+        assert_eq!(dag.highest_round(), Some(2));
+        assert_eq!(dag.vertex(1, 2), Some(&layers[1][2]));
+        assert_eq!(dag.round(2).count(), 4);
+        assert_eq!(dag.children(&layers[1][0].digest()).count(), 4);
+        assert_eq!(dag.parents(&layers[2][3].digest()).count(), 4);
+
+        // repeated vertices are ignored, equivocations and orphans rejected
+        assert_eq!(dag.insert(layers[2][0].clone()), Ok(false));
+        let equivocation = vertex(2, 0, vec![layers[1][1].digest()]);
+        assert_eq!(dag.insert(equivocation), Err(DagError::Equivocation { round: 2, author: 0 }));
+        let orphan = vertex(3, 0, vec![CertificateDigest::default()]);
+        assert!(matches!(dag.insert(orphan), Err(DagError::MissingParent { .. })));
+    }
+
+    #[test]
+    fn commit_causal_history() {
+        let (mut dag, layers) = full_dag(4, 4);
+        let leader = layers[2][1].digest();
+
+        // the genesis round, round 1 and the leader
+        let history = dag.commit(&leader);
+        assert_eq!(history.len(), 4 + 4 + 1);
+        assert_eq!(history.last().map(DagVertex::digest), Some(leader));
+        assert!(history.windows(2).all(|pair| {
+            (pair[0].round(), pair[0].author()) < (pair[1].round(), pair[1].author())
+        }));
+        assert!(dag.commit(&leader).is_empty());
+
+        // the genesis round has no uncommitted children left, round 1 still has
+        assert!(dag.is_committed(&layers[0][0].digest()));
+        assert_eq!(dag.get(&layers[0][0].digest()), None);
+        assert!(dag.get(&layers[1][0].digest()).is_some());
+        assert_eq!(dag.len(), 20 - 4);
+        assert!(dag.is_linked(&layers[4][0].digest(), &layers[2][0].digest()));
+        assert!(!dag.is_linked(&layers[4][0].digest(), &leader));
+        assert!(!dag.is_linked(&layers[2][0].digest(), &layers[4][0].digest()));
+
+        // the next leader commits the rest of its history, only round 3 is still referenced
+        let history = dag.commit(&layers[4][0].digest());
+        assert_eq!(history.len(), 3 + 4 + 1);
+        assert_eq!(dag.len(), 4 + 3);
+        assert!(dag.is_committed(&layers[1][0].digest()));
+        assert!(dag.is_committed(&leader));
+
+        // late vertices may still reference dropped committed vertices
+        let late = vertex(3, 4, vec![layers[2][0].digest()]);
+        assert_eq!(dag.insert(late), Ok(true));
+    }
+
+    #[test]
+    fn prune_rounds() {
+        let (mut dag, layers) = full_dag(4, 3);
+        dag.prune(2);
+        assert_eq!(dag.gc_round(), 2);
+        assert_eq!(dag.len(), 8);
+        assert_eq!(dag.get(&layers[1][0].digest()), None);
+        assert_eq!(dag.children(&layers[2][0].digest()).count(), 4);
+
+        // vertices of the garbage collection round don't need their parents
+        let mut dag = Dag::new(2);
+        assert_eq!(dag.insert(layers[2][0].clone()), Ok(true));
+        assert_eq!(dag.insert(layers[1][0].clone()), Ok(false));
+        assert_eq!(dag.commit(&layers[2][0].digest()).len(), 1);
+        assert!(dag.is_empty());
+    }
+
+    #[test]
+    fn restore_committed_vertices() {
+        let (_, layers) = full_dag(4, 2);
+        let mut dag = Dag::new(0);
+        for vertex in layers[..2].iter().flatten() {
+            assert_eq!(dag.insert_committed(vertex), Ok(true));
+        }
+        assert_eq!(dag.insert_committed(&layers[1][0]), Ok(false));
+        assert!(dag.is_empty());
+        assert!(dag.is_committed(&layers[1][0].digest()));
+
+        // uncommitted vertices reference the committed ones, which are never committed again
+        for vertex in &layers[2] {
+            assert_eq!(dag.insert(vertex.clone()), Ok(true));
+        }
+        assert_eq!(dag.commit(&layers[2][0].digest()).len(), 1);
+        let equivocation = vertex(1, 0, Vec::new());
+        assert_eq!(
+            dag.insert_committed(&equivocation),
+            Err(DagError::Equivocation { round: 1, author: 0 })
+        );
+    }
+}
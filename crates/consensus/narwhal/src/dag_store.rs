@@ -0,0 +1,719 @@
+//! Persistence of the DAG.
+//!
+//! Certificates, the batches they reference, the votes the node cast and the last committed round
+//! of every authority are written to a [`DagStore`], so that the DAG survives restarts. On startup
+//! the store is read back with [`DagStore::recover`], and the primary resumes from the last round
+//! of the stored DAG instead of round 1. The committed sub-dags that were not executed yet are
+//! stored as well, see [`recovery`](crate::recovery), and so is the append-only audit log of the
+//! commit decisions, see [`commit_log`](crate::commit_log).
+//!
+//! With the `execution` feature, [`DatabaseDagStore`] keeps the DAG in the `Narwhal*` tables of
+//! the node's database.
+
+use crate::{
+    commit_log::CommitAuditEntry,
+    types::{
+        Batch, BatchDigest, Certificate, CertificateDigest, Header, HeaderDigest, OrderedSubDag,
+        Round, SubDagDigest,
+    },
+};
+use alloy_rlp::{RlpDecodable, RlpEncodable};
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    sync::Mutex,
+};
+
+#[cfg(feature = "execution")]
+pub use database::DatabaseDagStore;
+
+/// Errors of a [`DagStore`].
+#[derive(Debug, thiserror::Error)]
+pub enum DagStoreError {
+    /// A stored value can't be decoded.
+    #[error("failed to decode stored value: {0}")]
+    Decode(#[from] alloy_rlp::Error),
+    /// The underlying storage failed.
+    #[error(transparent)]
+    Storage(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// The DAG read back from a [`DagStore`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveredDag {
+    /// All stored certificates, ordered by round and author.
+    pub certificates: Vec<Certificate>,
+    /// The round of the last committed certificate of each authority.
+    pub last_committed: BTreeMap<AuthorityIndex, Round>,
+}
+
+impl RecoveredDag {
+    /// Returns the certificates that were not committed yet, ordered by round and author.
+    pub fn uncommitted(&self) -> impl Iterator<Item = &Certificate> {
+        self.certificates.iter().filter(|certificate| {
+            self.last_committed
+                .get(&certificate.author())
+                .map_or(true, |&committed| certificate.round() > committed)
+        })
+    }
+}
+
+/// A committed sub-dag in a [`DagStore`], the digests of its certificates in commit order.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, RlpEncodable, RlpDecodable,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredSubDag {
+    /// The position of the sub-dag in the sequence of all commits.
+    pub index: u64,
+    /// The round of the committed leader.
+    pub round: Round,
+    /// The digest of the certificate of the committed leader.
+    pub leader: CertificateDigest,
+    /// The digests of the newly committed certificates in commit order, ending with the leader.
+    pub certificates: Vec<CertificateDigest>,
+    /// The commit timestamp in seconds.
+    pub timestamp: u64,
+}
+
+impl From<&OrderedSubDag> for StoredSubDag {
+    fn from(sub_dag: &OrderedSubDag) -> Self {
+        Self {
+            index: sub_dag.index,
+            round: sub_dag.leader_round(),
+            leader: sub_dag.leader.digest(),
+            certificates: sub_dag.certificates.iter().map(Certificate::digest).collect(),
+            timestamp: sub_dag.timestamp,
+        }
+    }
+}
+
+impl StoredSubDag {
+    /// Returns the digest of the sub-dag, see [`OrderedSubDag::digest`].
+    pub fn digest(&self) -> SubDagDigest {
+        SubDagDigest::of(self.round, self.certificates.iter().copied())
+    }
+}
+
+/// What [`DagStore::prune`] removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrunedDag {
+    /// The number of removed certificates.
+    pub certificates: usize,
+    /// The number of removed votes.
+    pub votes: usize,
+    /// The number of removed batches.
+    pub batches: usize,
+}
+
+/// Persistent storage of the DAG.
+pub trait DagStore: Debug + Send + Sync {
+    /// Stores a certificate, replacing a stored certificate of the same round and author.
+    fn write_certificate(&self, certificate: &Certificate) -> Result<(), DagStoreError>;
+
+    /// Returns the stored certificates of `from_round` and later rounds, ordered by round and
+    /// author.
+    fn certificates(&self, from_round: Round) -> Result<Vec<Certificate>, DagStoreError>;
+
+    /// Returns the stored certificate with the given digest, if any.
+    ///
+    /// Only the rounds above the GC round are stored, so the default implementation scans all
+    /// stored certificates.
+    fn certificate(&self, digest: CertificateDigest) -> Result<Option<Certificate>, DagStoreError> {
+        Ok(self.certificates(0)?.into_iter().find(|certificate| certificate.digest() == digest))
+    }
+
+    /// Stores a batch.
+    fn write_batch(&self, digest: BatchDigest, batch: &Batch) -> Result<(), DagStoreError>;
+
+    /// Returns the batch with the given digest, if it's stored.
+    fn batch(&self, digest: BatchDigest) -> Result<Option<Batch>, DagStoreError>;
+
+    /// Records that the node voted for the header.
+    ///
+    /// A node must not vote for two headers of the same round and author, so the vote has to be
+    /// stored before it's sent.
+    fn write_vote(&self, header: &Header) -> Result<(), DagStoreError>;
+
+    /// Returns the digest of the header of the round and author the node voted for, if any.
+    fn vote(
+        &self,
+        round: Round,
+        author: AuthorityIndex,
+    ) -> Result<Option<HeaderDigest>, DagStoreError>;
+
+    /// Removes all votes once their epoch ended, since the rounds of the next epoch start over.
+    fn clear_votes(&self) -> Result<(), DagStoreError>;
+
+    /// Stores the round of the last committed certificate of an authority.
+    fn write_last_committed(
+        &self,
+        author: AuthorityIndex,
+        round: Round,
+    ) -> Result<(), DagStoreError>;
+
+    /// Returns the round of the last committed certificate of each authority.
+    fn last_committed(&self) -> Result<BTreeMap<AuthorityIndex, Round>, DagStoreError>;
+
+    /// Stores a committed sub-dag, replacing a stored sub-dag with the same index.
+    fn write_sub_dag(&self, sub_dag: &StoredSubDag) -> Result<(), DagStoreError>;
+
+    /// Returns the stored sub-dags with index `from_index` and later indices, in commit order.
+    fn sub_dags(&self, from_index: u64) -> Result<Vec<StoredSubDag>, DagStoreError>;
+
+    /// Returns the stored sub-dag with the given digest, the `mix_hash` of its blocks, if any.
+    fn sub_dag(&self, digest: SubDagDigest) -> Result<Option<StoredSubDag>, DagStoreError>;
+
+    /// Removes the sub-dags with indices below `index` and returns their number.
+    fn prune_sub_dags(&self, index: u64) -> Result<usize, DagStoreError>;
+
+    /// Appends an entry to the commit audit log, see
+    /// [`CommitAuditLog`](crate::commit_log::CommitAuditLog).
+    ///
+    /// The log is neither pruned nor reset.
+    fn write_audit_entry(&self, entry: &CommitAuditEntry) -> Result<(), DagStoreError>;
+
+    /// Returns at most `limit` entries of the commit audit log, starting at index `from_index`.
+    fn audit_entries(
+        &self,
+        from_index: u64,
+        limit: usize,
+    ) -> Result<Vec<CommitAuditEntry>, DagStoreError>;
+
+    /// Returns the last entry of the commit audit log.
+    fn last_audit_entry(&self) -> Result<Option<CommitAuditEntry>, DagStoreError>;
+
+    /// Removes the certificates and votes of all rounds below `round`, and the batches referenced
+    /// by the removed certificates.
+    fn prune(&self, round: Round) -> Result<PrunedDag, DagStoreError>;
+
+    /// Removes all certificates and committed sub-dags and replaces the last committed rounds,
+    /// e.g. to roll back to an [`EpochSnapshot`](crate::epoch_snapshot::EpochSnapshot).
+    ///
+    /// Votes are kept, so that the node never votes for two headers of the same round and author
+    /// after the reset.
+    fn reset(&self, last_committed: &BTreeMap<AuthorityIndex, Round>) -> Result<(), DagStoreError>;
+
+    /// Reads back the stored DAG.
+    fn recover(&self) -> Result<RecoveredDag, DagStoreError> {
+        Ok(RecoveredDag {
+            certificates: self.certificates(0)?,
+            last_committed: self.last_committed()?,
+        })
+    }
+}
+
+/// A [`DagStore`] in memory, e.g. for simulations and tests.
+#[derive(Debug, Default)]
+pub struct MemoryDagStore {
+    inner: Mutex<MemoryDagStoreInner>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryDagStoreInner {
+    certificates: BTreeMap<(Round, AuthorityIndex), Certificate>,
+    batches: HashMap<BatchDigest, Batch>,
+    votes: BTreeMap<(Round, AuthorityIndex), HeaderDigest>,
+    last_committed: BTreeMap<AuthorityIndex, Round>,
+    sub_dags: BTreeMap<u64, StoredSubDag>,
+    sub_dag_digests: HashMap<SubDagDigest, u64>,
+    audit_log: BTreeMap<u64, CommitAuditEntry>,
+}
+
+impl DagStore for MemoryDagStore {
+    fn write_certificate(&self, certificate: &Certificate) -> Result<(), DagStoreError> {
+        let key = (certificate.round(), certificate.author());
+        self.inner.lock().unwrap().certificates.insert(key, certificate.clone());
+        Ok(())
+    }
+
+    fn certificates(&self, from_round: Round) -> Result<Vec<Certificate>, DagStoreError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.certificates.range((from_round, 0)..).map(|(_, cert)| cert.clone()).collect())
+    }
+
+    fn write_batch(&self, digest: BatchDigest, batch: &Batch) -> Result<(), DagStoreError> {
+        self.inner.lock().unwrap().batches.insert(digest, batch.clone());
+        Ok(())
+    }
+
+    fn batch(&self, digest: BatchDigest) -> Result<Option<Batch>, DagStoreError> {
+        Ok(self.inner.lock().unwrap().batches.get(&digest).cloned())
+    }
+
+    fn write_vote(&self, header: &Header) -> Result<(), DagStoreError> {
+        let key = (header.round, header.author);
+        self.inner.lock().unwrap().votes.insert(key, header.digest());
+        Ok(())
+    }
+
+    fn vote(
+        &self,
+        round: Round,
+        author: AuthorityIndex,
+    ) -> Result<Option<HeaderDigest>, DagStoreError> {
+        Ok(self.inner.lock().unwrap().votes.get(&(round, author)).copied())
+    }
+
+    fn clear_votes(&self) -> Result<(), DagStoreError> {
+        self.inner.lock().unwrap().votes.clear();
+        Ok(())
+    }
+
+    fn write_last_committed(
+        &self,
+        author: AuthorityIndex,
+        round: Round,
+    ) -> Result<(), DagStoreError> {
+        self.inner.lock().unwrap().last_committed.insert(author, round);
+        Ok(())
+    }
+
+    fn last_committed(&self) -> Result<BTreeMap<AuthorityIndex, Round>, DagStoreError> {
+        Ok(self.inner.lock().unwrap().last_committed.clone())
+    }
+
+    fn write_sub_dag(&self, sub_dag: &StoredSubDag) -> Result<(), DagStoreError> {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(replaced) = inner.sub_dags.insert(sub_dag.index, sub_dag.clone()) {
+            inner.sub_dag_digests.remove(&replaced.digest());
+        }
+        inner.sub_dag_digests.insert(sub_dag.digest(), sub_dag.index);
+        Ok(())
+    }
+
+    fn sub_dags(&self, from_index: u64) -> Result<Vec<StoredSubDag>, DagStoreError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.sub_dags.range(from_index..).map(|(_, sub_dag)| sub_dag.clone()).collect())
+    }
+
+    fn sub_dag(&self, digest: SubDagDigest) -> Result<Option<StoredSubDag>, DagStoreError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.sub_dag_digests.get(&digest).and_then(|index| inner.sub_dags.get(index)).cloned())
+    }
+
+    fn prune_sub_dags(&self, index: u64) -> Result<usize, DagStoreError> {
+        let mut inner = self.inner.lock().unwrap();
+        let sub_dags = inner.sub_dags.split_off(&index);
+        let pruned = std::mem::replace(&mut inner.sub_dags, sub_dags);
+        for sub_dag in pruned.values() {
+            inner.sub_dag_digests.remove(&sub_dag.digest());
+        }
+        Ok(pruned.len())
+    }
+
+    fn write_audit_entry(&self, entry: &CommitAuditEntry) -> Result<(), DagStoreError> {
+        self.inner.lock().unwrap().audit_log.insert(entry.index, entry.clone());
+        Ok(())
+    }
+
+    fn audit_entries(
+        &self,
+        from_index: u64,
+        limit: usize,
+    ) -> Result<Vec<CommitAuditEntry>, DagStoreError> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner
+            .audit_log
+            .range(from_index..)
+            .take(limit)
+            .map(|(_, entry)| entry.clone())
+            .collect())
+    }
+
+    fn last_audit_entry(&self) -> Result<Option<CommitAuditEntry>, DagStoreError> {
+        Ok(self.inner.lock().unwrap().audit_log.last_key_value().map(|(_, entry)| entry.clone()))
+    }
+
+    fn prune(&self, round: Round) -> Result<PrunedDag, DagStoreError> {
+        let mut inner = self.inner.lock().unwrap();
+        let certificates = inner.certificates.split_off(&(round, 0));
+        let pruned = std::mem::replace(&mut inner.certificates, certificates);
+        let votes = inner.votes.split_off(&(round, 0));
+        let pruned_votes = std::mem::replace(&mut inner.votes, votes).len();
+
+        let mut batches = 0;
+        for certificate in pruned.values() {
+            for batch in &certificate.header.payload {
+                batches += usize::from(inner.batches.remove(&batch.digest).is_some());
+            }
+        }
+        Ok(PrunedDag { certificates: pruned.len(), votes: pruned_votes, batches })
+    }
+
+    fn reset(&self, last_committed: &BTreeMap<AuthorityIndex, Round>) -> Result<(), DagStoreError> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.certificates.clear();
+        inner.sub_dags.clear();
+        inner.sub_dag_digests.clear();
+        inner.last_committed.clone_from(last_committed);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "execution")]
+mod database {
+    use super::*;
+    use alloy_primitives::B256;
+    use alloy_rlp::Decodable;
+    use reth_db::tables;
+    use reth_db_api::{
+        cursor::{DbCursorRO, DbCursorRW},
+        database::Database,
+        models::RoundAuthority,
+        transaction::{DbTx, DbTxMut},
+        DatabaseError,
+    };
+
+    impl From<DatabaseError> for DagStoreError {
+        fn from(err: DatabaseError) -> Self {
+            Self::Storage(Box::new(err))
+        }
+    }
+
+    /// Decodes an RLP encoded value of a table.
+    fn decode<T: Decodable>(mut value: &[u8]) -> Result<T, DagStoreError> {
+        Ok(T::decode(&mut value)?)
+    }
+
+    /// A [`DagStore`] in the `Narwhal*` tables of the node's database.
+    ///
+    /// Certificates and batches are stored RLP encoded.
+    #[derive(Debug, Clone)]
+    pub struct DatabaseDagStore<DB> {
+        db: DB,
+    }
+
+    impl<DB> DatabaseDagStore<DB> {
+        /// Creates a store in the given database.
+        pub const fn new(db: DB) -> Self {
+            Self { db }
+        }
+    }
+
+    impl<DB: Database + Debug> DagStore for DatabaseDagStore<DB> {
+        fn write_certificate(&self, certificate: &Certificate) -> Result<(), DagStoreError> {
+            let key = RoundAuthority((certificate.round(), certificate.author().into()));
+            let value = alloy_rlp::encode(certificate);
+            Ok(self.db.update(|tx| tx.put::<tables::NarwhalCertificates>(key, value))??)
+        }
+
+        fn certificates(&self, from_round: Round) -> Result<Vec<Certificate>, DagStoreError> {
+            self.db.view(|tx| {
+                tx.cursor_read::<tables::NarwhalCertificates>()?
+                    .walk(Some(RoundAuthority::first_of_round(from_round)))?
+                    .map(|entry| decode(&entry?.1))
+                    .collect()
+            })?
+        }
+
+        fn write_batch(&self, digest: BatchDigest, batch: &Batch) -> Result<(), DagStoreError> {
+            let value = alloy_rlp::encode(batch);
+            Ok(self.db.update(|tx| tx.put::<tables::NarwhalBatches>(digest.0, value))??)
+        }
+
+        fn batch(&self, digest: BatchDigest) -> Result<Option<Batch>, DagStoreError> {
+            let value = self.db.view(|tx| tx.get::<tables::NarwhalBatches>(digest.0))??;
+            value.map(|value| decode(&value)).transpose()
+        }
+
+        fn write_vote(&self, header: &Header) -> Result<(), DagStoreError> {
+            let key = RoundAuthority((header.round, header.author.into()));
+            let digest = header.digest().0;
+            Ok(self.db.update(|tx| tx.put::<tables::NarwhalVotes>(key, digest))??)
+        }
+
+        fn vote(
+            &self,
+            round: Round,
+            author: AuthorityIndex,
+        ) -> Result<Option<HeaderDigest>, DagStoreError> {
+            let key = RoundAuthority((round, author.into()));
+            let digest: Option<B256> = self.db.view(|tx| tx.get::<tables::NarwhalVotes>(key))??;
+            Ok(digest.map(HeaderDigest))
+        }
+
+        fn clear_votes(&self) -> Result<(), DagStoreError> {
+            Ok(self.db.update(|tx| tx.clear::<tables::NarwhalVotes>())??)
+        }
+
+        fn write_last_committed(
+            &self,
+            author: AuthorityIndex,
+            round: Round,
+        ) -> Result<(), DagStoreError> {
+            Ok(self
+                .db
+                .update(|tx| tx.put::<tables::NarwhalLastCommitted>(author.into(), round))??)
+        }
+
+        fn last_committed(&self) -> Result<BTreeMap<AuthorityIndex, Round>, DagStoreError> {
+            self.db.view(|tx| {
+                tx.cursor_read::<tables::NarwhalLastCommitted>()?
+                    .walk(None)?
+                    .map(|entry| {
+                        let (author, round) = entry?;
+                        let author =
+                            AuthorityIndex::try_from(author).map_err(|_| DatabaseError::Decode)?;
+                        Ok((author, round))
+                    })
+                    .collect()
+            })?
+        }
+
+        fn write_sub_dag(&self, sub_dag: &StoredSubDag) -> Result<(), DagStoreError> {
+            let value = alloy_rlp::encode(sub_dag);
+            self.db.update(|tx| {
+                if let Some(replaced) = tx.get::<tables::NarwhalSubDags>(sub_dag.index)? {
+                    let replaced: StoredSubDag = decode(&replaced)?;
+                    tx.delete::<tables::NarwhalSubDagDigests>(replaced.digest().0, None)?;
+                }
+                tx.put::<tables::NarwhalSubDagDigests>(sub_dag.digest().0, sub_dag.index)?;
+                tx.put::<tables::NarwhalSubDags>(sub_dag.index, value)?;
+                Ok(())
+            })?
+        }
+
+        fn sub_dags(&self, from_index: u64) -> Result<Vec<StoredSubDag>, DagStoreError> {
+            self.db.view(|tx| {
+                tx.cursor_read::<tables::NarwhalSubDags>()?
+                    .walk(Some(from_index))?
+                    .map(|entry| decode(&entry?.1))
+                    .collect()
+            })?
+        }
+
+        fn sub_dag(&self, digest: SubDagDigest) -> Result<Option<StoredSubDag>, DagStoreError> {
+            let value = self.db.view(|tx| {
+                match tx.get::<tables::NarwhalSubDagDigests>(digest.0)? {
+                    Some(index) => tx.get::<tables::NarwhalSubDags>(index),
+                    None => Ok(None),
+                }
+            })??;
+            value.map(|value| decode(&value)).transpose()
+        }
+
+        fn prune_sub_dags(&self, index: u64) -> Result<usize, DagStoreError> {
+            self.db.update(|tx| {
+                let mut pruned = 0;
+                let mut cursor = tx.cursor_write::<tables::NarwhalSubDags>()?;
+                let mut walker = cursor.walk_range(..index)?;
+                while let Some((_, value)) = walker.next().transpose()? {
+                    let sub_dag: StoredSubDag = decode(&value)?;
+                    tx.delete::<tables::NarwhalSubDagDigests>(sub_dag.digest().0, None)?;
+                    walker.delete_current()?;
+                    pruned += 1;
+                }
+                Ok(pruned)
+            })?
+        }
+
+        fn write_audit_entry(&self, entry: &CommitAuditEntry) -> Result<(), DagStoreError> {
+            let value = alloy_rlp::encode(entry);
+            Ok(self.db.update(|tx| tx.put::<tables::NarwhalCommitAudit>(entry.index, value))??)
+        }
+
+        fn audit_entries(
+            &self,
+            from_index: u64,
+            limit: usize,
+        ) -> Result<Vec<CommitAuditEntry>, DagStoreError> {
+            self.db.view(|tx| {
+                tx.cursor_read::<tables::NarwhalCommitAudit>()?
+                    .walk(Some(from_index))?
+                    .take(limit)
+                    .map(|entry| decode(&entry?.1))
+                    .collect()
+            })?
+        }
+
+        fn last_audit_entry(&self) -> Result<Option<CommitAuditEntry>, DagStoreError> {
+            let last =
+                self.db.view(|tx| tx.cursor_read::<tables::NarwhalCommitAudit>()?.last())??;
+            last.map(|(_, value)| decode(&value)).transpose()
+        }
+
+        fn prune(&self, round: Round) -> Result<PrunedDag, DagStoreError> {
+            let end = RoundAuthority::first_of_round(round);
+            self.db.update(|tx| {
+                let mut pruned = PrunedDag::default();
+
+                let mut cursor = tx.cursor_write::<tables::NarwhalCertificates>()?;
+                let mut walker = cursor.walk_range(..end)?;
+                while let Some((_, value)) = walker.next().transpose()? {
+                    let certificate: Certificate = decode(&value)?;
+                    for batch in &certificate.header.payload {
+                        if tx.delete::<tables::NarwhalBatches>(batch.digest.0, None)? {
+                            pruned.batches += 1;
+                        }
+                    }
+                    walker.delete_current()?;
+                    pruned.certificates += 1;
+                }
+
+                let mut cursor = tx.cursor_write::<tables::NarwhalVotes>()?;
+                let mut walker = cursor.walk_range(..end)?;
+                while walker.next().transpose()?.is_some() {
+                    walker.delete_current()?;
+                    pruned.votes += 1;
+                }
+                Ok(pruned)
+            })?
+        }
+
+        fn reset(
+            &self,
+            last_committed: &BTreeMap<AuthorityIndex, Round>,
+        ) -> Result<(), DagStoreError> {
+            Ok(self.db.update(|tx| {
+                tx.clear::<tables::NarwhalCertificates>()?;
+                tx.clear::<tables::NarwhalSubDags>()?;
+                tx.clear::<tables::NarwhalSubDagDigests>()?;
+                tx.clear::<tables::NarwhalLastCommitted>()?;
+                for (author, round) in last_committed {
+                    tx.put::<tables::NarwhalLastCommitted>((*author).into(), *round)?;
+                }
+                Ok::<_, DatabaseError>(())
+            })??)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_log::CommitOutcome;
+
+    fn certificate(round: Round, author: AuthorityIndex) -> Certificate {
+        Certificate {
+            header: Header { epoch: 1, round, author, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn recover_dag() {
+        let store = MemoryDagStore::default();
+        for round in 1..=3 {
+            for author in (0..3).rev() {
+                store.write_certificate(&certificate(round, author)).unwrap();
+            }
+        }
+        store.write_last_committed(0, 2).unwrap();
+        store.write_last_committed(1, 1).unwrap();
+
+        assert_eq!(
+            store.certificates(3).unwrap(),
+            (0..3).map(|author| certificate(3, author)).collect::<Vec<_>>()
+        );
+
+        let digest = certificate(2, 1).digest();
+        assert_eq!(store.certificate(digest).unwrap(), Some(certificate(2, 1)));
+        assert_eq!(store.certificate(CertificateDigest::default()).unwrap(), None);
+
+        let recovered = store.recover().unwrap();
+        assert_eq!(recovered.certificates.len(), 9);
+        assert_eq!(recovered.certificates[0], certificate(1, 0));
+        assert_eq!(
+            recovered.uncommitted().map(|cert| (cert.round(), cert.author())).collect::<Vec<_>>(),
+            vec![(1, 2), (2, 1), (2, 2), (3, 0), (3, 1), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn batches_and_votes() {
+        let store = MemoryDagStore::default();
+        let batch = Batch::new(vec![alloy_primitives::Bytes::from_static(b"tx")]);
+        store.write_batch(batch.digest(), &batch).unwrap();
+        assert_eq!(store.batch(batch.digest()).unwrap(), Some(batch));
+        assert_eq!(store.batch(BatchDigest::default()).unwrap(), None);
+
+        let header = certificate(4, 2).header;
+        store.write_vote(&header).unwrap();
+        assert_eq!(store.vote(4, 2).unwrap(), Some(header.digest()));
+        assert_eq!(store.vote(4, 1).unwrap(), None);
+
+        store.clear_votes().unwrap();
+        assert_eq!(store.vote(4, 2).unwrap(), None);
+    }
+
+    #[test]
+    fn prune_rounds() {
+        let store = MemoryDagStore::default();
+        let batch = |byte| Batch::new(vec![alloy_primitives::Bytes::from(vec![byte])]);
+        for round in 1..=4 {
+            let batch = batch(round as u8);
+            store.write_batch(batch.digest(), &batch).unwrap();
+            let mut certificate = certificate(round, 0);
+            certificate.header.payload =
+                vec![crate::types::BatchRef { digest: batch.digest(), worker: 0 }];
+            store.write_vote(&certificate.header).unwrap();
+            store.write_certificate(&certificate).unwrap();
+        }
+
+        assert_eq!(store.prune(3).unwrap(), PrunedDag { certificates: 2, votes: 2, batches: 2 });
+        assert_eq!(store.certificates(0).unwrap().len(), 2);
+        assert_eq!(store.batch(batch(2).digest()).unwrap(), None);
+        assert!(store.batch(batch(3).digest()).unwrap().is_some());
+        assert_eq!(store.vote(2, 0).unwrap(), None);
+        assert!(store.vote(3, 0).unwrap().is_some());
+        assert_eq!(store.prune(3).unwrap(), PrunedDag::default());
+    }
+
+    #[test]
+    fn prune_sub_dags() {
+        let store = MemoryDagStore::default();
+        for index in 0..4 {
+            let sub_dag = StoredSubDag {
+                index,
+                round: index * 2,
+                leader: certificate(index * 2, 0).digest(),
+                certificates: vec![certificate(index * 2, 0).digest()],
+                timestamp: index,
+            };
+            store.write_sub_dag(&sub_dag).unwrap();
+        }
+
+        assert_eq!(store.sub_dags(2).unwrap().iter().map(|s| s.index).collect::<Vec<_>>(), [2, 3]);
+        let first = store.sub_dags(0).unwrap()[0].digest();
+        assert_eq!(store.sub_dag(first).unwrap().map(|sub_dag| sub_dag.index), Some(0));
+        assert_eq!(store.prune_sub_dags(3).unwrap(), 3);
+        assert_eq!(store.sub_dags(0).unwrap().len(), 1);
+        assert_eq!(store.sub_dag(first).unwrap(), None);
+        assert_eq!(store.prune_sub_dags(3).unwrap(), 0);
+    }
+
+    #[test]
+    fn reset_dag() {
+        let store = MemoryDagStore::default();
+        store.write_certificate(&certificate(5, 0)).unwrap();
+        store.write_vote(&certificate(5, 1).header).unwrap();
+        store.write_last_committed(0, 4).unwrap();
+        store.write_last_committed(1, 4).unwrap();
+        store.write_sub_dag(&StoredSubDag { index: 7, ..Default::default() }).unwrap();
+        let entry = CommitAuditEntry {
+            index: 0,
+            parent: Default::default(),
+            epoch: 1,
+            round: 4,
+            leader: 0,
+            leader_certificate: certificate(4, 0).digest(),
+            outcome: CommitOutcome::TimeoutSkip,
+            supporters: Vec::new(),
+        };
+        store.write_audit_entry(&entry).unwrap();
+
+        store.reset(&BTreeMap::from([(0, 2)])).unwrap();
+        assert_eq!(
+            store.recover().unwrap(),
+            RecoveredDag { certificates: Vec::new(), last_committed: BTreeMap::from([(0, 2)]) }
+        );
+        assert!(store.sub_dags(0).unwrap().is_empty());
+        // votes and the audit log survive the reset
+        assert!(store.vote(5, 1).unwrap().is_some());
+        store.prune(10).unwrap();
+        assert_eq!(store.last_audit_entry().unwrap(), Some(entry));
+    }
+}
@@ -0,0 +1,515 @@
+//! Deployments of the validators of a chain.
+//!
+//! A [`Deployment`] is generated from the committee and the narwhal configurations of a chain
+//! scaffolded with [`Scaffold`](crate::scaffold::Scaffold), with the same types the validators
+//! read at runtime, so that the ports and paths of a deployment can't drift from the
+//! configuration of the nodes. It runs every validator and a follower node that serves RPC, as a
+//! docker compose file or as kubernetes manifests. Both are written as JSON, which docker compose
+//! and kubectl read like YAML.
+//!
+//! The committee addresses are the addresses of the hosts of the validators. Validators of a
+//! committee on the local host share the network of the host and get distinct reth ports with
+//! `--instance`, like the scaffold configures them. Other validators run on their own hosts with
+//! their ports published.
+
+use crate::{
+    committee::{Committee, CommitteeError},
+    config::NarwhalConfig,
+    scaffold::{Scaffold, COMMITTEE_FILE, CONFIG_FILE, GENESIS_FILE, KEYSTORE_FILE, PASSWORD_FILE},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+/// The default devp2p port of reth.
+pub const DEFAULT_P2P_PORT: u16 = 30303;
+
+/// The name of the file of a validator with its devp2p secret key.
+pub const P2P_SECRET_KEY_FILE: &str = "p2p-secret.key";
+
+/// The directory the chain is mounted at in the containers.
+const CHAIN_MOUNT: &str = "/chain";
+
+/// The datadir of the node in the containers.
+const DATA_MOUNT: &str = "/data";
+
+/// Errors when loading a [`Deployment`].
+#[derive(Debug, thiserror::Error)]
+pub enum DeploymentError {
+    /// The committee couldn't be loaded.
+    #[error(transparent)]
+    Committee(#[from] CommitteeError),
+    /// A file of the chain couldn't be read.
+    #[error("failed to read {path}: {err}")]
+    Read {
+        /// The path of the file.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        err: io::Error,
+    },
+    /// The narwhal configuration of a validator is invalid.
+    #[error("invalid narwhal config {path}: {message}")]
+    Config {
+        /// The path of the configuration.
+        path: PathBuf,
+        /// The parser error.
+        message: String,
+    },
+}
+
+/// The settings of a [`Deployment`] that aren't part of the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeploymentConfig {
+    /// The docker image of reth.
+    pub image: String,
+    /// Whether to run a follower node that serves RPC.
+    pub follower: bool,
+    /// The HTTP RPC port of the follower node.
+    pub rpc_port: u16,
+    /// The size of the volume of the datadir of every node on kubernetes.
+    pub storage: String,
+}
+
+impl Default for DeploymentConfig {
+    fn default() -> Self {
+        Self {
+            image: "ghcr.io/paradigmxyz/reth:latest".to_string(),
+            follower: true,
+            rpc_port: 8545,
+            storage: "100Gi".to_string(),
+        }
+    }
+}
+
+/// A docker compose file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComposeFile {
+    /// The containers, by name.
+    pub services: BTreeMap<String, ComposeService>,
+    /// The named volumes, with the default settings.
+    pub volumes: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// A container of a [`ComposeFile`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComposeService {
+    /// The image of the container.
+    pub image: String,
+    /// The arguments of the entrypoint of the image.
+    pub command: Vec<String>,
+    /// The volumes mounted into the container.
+    pub volumes: Vec<String>,
+    /// The published ports.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<String>,
+    /// The network of the container, `host` if it shares the network of the host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_mode: Option<String>,
+    /// When the container is restarted.
+    pub restart: String,
+}
+
+/// The deployment of the validators of a chain, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct Deployment {
+    committee: Committee,
+    configs: Vec<NarwhalConfig>,
+    config: DeploymentConfig,
+    p2p_ids: Vec<Option<String>>,
+}
+
+impl Deployment {
+    /// Creates the deployment of the committee, with the narwhal configuration of every validator
+    /// in the order of the committee.
+    ///
+    /// # Panics
+    ///
+    /// If the number of configurations doesn't match the committee.
+    pub fn new(
+        committee: Committee,
+        configs: Vec<NarwhalConfig>,
+        config: DeploymentConfig,
+    ) -> Self {
+        assert_eq!(committee.authorities.len(), configs.len(), "one config per validator");
+        let p2p_ids = vec![None; configs.len()];
+        Self { committee, configs, config, p2p_ids }
+    }
+
+    /// Loads the committee file of a chain scaffolded with [`Scaffold::write`], and the narwhal
+    /// configurations of the validators from the validator directories next to it.
+    ///
+    /// Validators without a configuration file use the default configuration.
+    pub fn load(committee_file: &Path, config: DeploymentConfig) -> Result<Self, DeploymentError> {
+        let committee = Committee::load(committee_file)?;
+        let dir = committee_file.parent().unwrap_or_else(|| Path::new(""));
+        let configs = (0..committee.authorities.len())
+            .map(|validator| {
+                let path = Scaffold::validator_dir(dir, validator).join(CONFIG_FILE);
+                match fs::read_to_string(&path) {
+                    Ok(contents) => toml::from_str(&contents)
+                        .map_err(|err| DeploymentError::Config { path, message: err.to_string() }),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        Ok(NarwhalConfig::default())
+                    }
+                    Err(err) => Err(DeploymentError::Read { path, err }),
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(committee, configs, config))
+    }
+
+    /// Sets the devp2p node id of a validator, in hex, which the follower node connects to.
+    pub fn with_p2p_id(mut self, validator: usize, id: String) -> Self {
+        self.p2p_ids[validator] = Some(id);
+        self
+    }
+
+    /// Returns the committee of the deployment.
+    pub const fn committee(&self) -> &Committee {
+        &self.committee
+    }
+
+    /// Returns whether the validators share the network of the local host.
+    pub fn host_network(&self) -> bool {
+        self.committee
+            .authorities
+            .iter()
+            .all(|authority| authority.primary_address.ip().is_loopback())
+    }
+
+    /// Returns the reth instance of a validator, `None` if it runs on its own host.
+    fn instance(&self, validator: usize) -> Option<u16> {
+        self.host_network().then(|| validator as u16 + 1)
+    }
+
+    /// Returns the devp2p address of a validator.
+    pub fn p2p_addr(&self, validator: usize) -> SocketAddr {
+        let port = DEFAULT_P2P_PORT + self.instance(validator).map_or(0, |instance| instance - 1);
+        SocketAddr::new(self.committee.authorities[validator].primary_address.ip(), port)
+    }
+
+    /// Returns the narwhal ports of a validator: the ports of the primary, of the workers and of
+    /// the transaction submission services.
+    pub fn narwhal_ports(&self, validator: usize) -> Vec<u16> {
+        let authority = &self.committee.authorities[validator];
+        let submission = &self.configs[validator].submission;
+        std::iter::once(authority.primary_address.port())
+            .chain(authority.worker_addresses.iter().map(SocketAddr::port))
+            .chain(
+                (0..authority.worker_addresses.len())
+                    .filter_map(|worker| submission.worker_addr(worker as _))
+                    .map(|addr| addr.port()),
+            )
+            .collect()
+    }
+
+    /// Returns the arguments of the node of a validator.
+    pub fn validator_args(&self, validator: usize) -> Vec<String> {
+        let workers = self.committee.authorities[validator].worker_addresses.len();
+        let mut args = vec!["node".to_string()];
+        args.extend(Scaffold::validator_args(Path::new(CHAIN_MOUNT), validator, workers));
+        let validator_dir = Scaffold::validator_dir(Path::new(CHAIN_MOUNT), validator);
+        args.extend([
+            "--datadir".to_string(),
+            DATA_MOUNT.to_string(),
+            "--p2p-secret-key".to_string(),
+            validator_dir.join(P2P_SECRET_KEY_FILE).display().to_string(),
+        ]);
+        if let Some(instance) = self.instance(validator) {
+            args.extend(["--instance".to_string(), instance.to_string()]);
+        }
+        args
+    }
+
+    /// Returns the arguments of the follower node.
+    pub fn follower_args(&self) -> Vec<String> {
+        let chain = Path::new(CHAIN_MOUNT);
+        let mut args = vec![
+            "node".to_string(),
+            "--chain".to_string(),
+            chain.join(GENESIS_FILE).display().to_string(),
+            "--narwhal.committee-file".to_string(),
+            chain.join(COMMITTEE_FILE).display().to_string(),
+            "--datadir".to_string(),
+            DATA_MOUNT.to_string(),
+            "--http".to_string(),
+            "--http.addr".to_string(),
+            "0.0.0.0".to_string(),
+            "--http.api".to_string(),
+            "eth,net,web3,narwhal".to_string(),
+        ];
+        let peers = self
+            .p2p_ids
+            .iter()
+            .enumerate()
+            .filter_map(|(validator, id)| {
+                Some(format!("enode://{}@{}", id.as_ref()?, self.p2p_addr(validator)))
+            })
+            .collect::<Vec<_>>();
+        if !peers.is_empty() {
+            args.extend(["--trusted-peers".to_string(), peers.join(",")]);
+        }
+        match self.instance(self.committee.authorities.len()) {
+            Some(instance) => {
+                // the instance lowers the HTTP port by one for every instance before it
+                let port = self.config.rpc_port + instance - 1;
+                args.extend([
+                    "--http.port".to_string(),
+                    port.to_string(),
+                    "--instance".to_string(),
+                    instance.to_string(),
+                ]);
+            }
+            None => args.extend(["--http.port".to_string(), self.config.rpc_port.to_string()]),
+        }
+        args
+    }
+
+    /// Returns the docker compose file of the deployment, which mounts the directory of the chain
+    /// into the containers.
+    pub fn compose(&self, chain_dir: &Path) -> ComposeFile {
+        let host_network = self.host_network();
+        let chain_volume = format!("{}:{CHAIN_MOUNT}:ro", chain_dir.display());
+        let mut services = BTreeMap::new();
+        let mut volumes = BTreeMap::new();
+        let mut add = |name: String, command: Vec<String>, ports: Vec<String>| {
+            let data = format!("{name}-data");
+            services.insert(
+                name,
+                ComposeService {
+                    image: self.config.image.clone(),
+                    command,
+                    volumes: vec![chain_volume.clone(), format!("{data}:{DATA_MOUNT}")],
+                    ports: if host_network { Vec::new() } else { ports },
+                    network_mode: host_network.then(|| "host".to_string()),
+                    restart: "unless-stopped".to_string(),
+                },
+            );
+            volumes.insert(data, BTreeMap::new());
+        };
+
+        for validator in 0..self.committee.authorities.len() {
+            let p2p = self.p2p_addr(validator).port();
+            let ports = [format!("{p2p}:{p2p}/tcp"), format!("{p2p}:{p2p}/udp")]
+                .into_iter()
+                .chain(
+                    self.narwhal_ports(validator).into_iter().map(|port| format!("{port}:{port}")),
+                )
+                .collect();
+            add(validator_name(validator), self.validator_args(validator), ports);
+        }
+        if self.config.follower {
+            let port = self.config.rpc_port;
+            add("rpc".to_string(), self.follower_args(), vec![format!("{port}:{port}")]);
+        }
+        ComposeFile { services, volumes }
+    }
+
+    /// Returns the kubernetes manifests of the deployment as a `List`.
+    ///
+    /// The files of the chain are embedded into a config map, and the files of every validator
+    /// into a secret. The validators use the network of their kubernetes nodes, since the
+    /// committee addresses are the addresses of the hosts.
+    pub fn kubernetes(&self, chain_dir: &Path) -> Result<serde_json::Value, DeploymentError> {
+        let read = |path: PathBuf| {
+            fs::read_to_string(&path).map_err(|err| DeploymentError::Read { path, err })
+        };
+        let mut items = vec![serde_json::json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": { "name": "narwhal-chain" },
+            "data": {
+                GENESIS_FILE: read(chain_dir.join(GENESIS_FILE))?,
+                COMMITTEE_FILE: toml::to_string(&self.committee).expect("committees serialize to TOML"),
+            },
+        })];
+        let chain_items = serde_json::json!([
+            { "key": GENESIS_FILE, "path": GENESIS_FILE },
+            { "key": COMMITTEE_FILE, "path": COMMITTEE_FILE },
+        ]);
+
+        for validator in 0..self.committee.authorities.len() {
+            let name = validator_name(validator);
+            let dir = Scaffold::validator_dir(chain_dir, validator);
+            let mut data = serde_json::Map::new();
+            for file in [KEYSTORE_FILE, PASSWORD_FILE, CONFIG_FILE, P2P_SECRET_KEY_FILE] {
+                data.insert(file.to_string(), read(dir.join(file))?.into());
+            }
+            let secret_items = data
+                .keys()
+                .map(|file| serde_json::json!({ "key": file, "path": format!("{name}/{file}") }))
+                .collect::<Vec<_>>();
+            items.push(serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "Secret",
+                "metadata": { "name": format!("narwhal-{name}") },
+                "stringData": data,
+            }));
+            let sources = serde_json::json!([
+                { "configMap": { "name": "narwhal-chain", "items": chain_items } },
+                { "secret": { "name": format!("narwhal-{name}"), "items": secret_items } },
+            ]);
+            items.push(self.stateful_set(&name, self.validator_args(validator), sources, true));
+        }
+
+        if self.config.follower {
+            let sources = serde_json::json!([
+                { "configMap": { "name": "narwhal-chain", "items": chain_items } },
+            ]);
+            items.push(self.stateful_set("rpc", self.follower_args(), sources, false));
+            items.push(serde_json::json!({
+                "apiVersion": "v1",
+                "kind": "Service",
+                "metadata": { "name": "narwhal-rpc" },
+                "spec": {
+                    "selector": { "app": "narwhal", "node": "rpc" },
+                    "ports": [{ "name": "http", "port": self.config.rpc_port }],
+                },
+            }));
+        }
+        Ok(serde_json::json!({ "apiVersion": "v1", "kind": "List", "items": items }))
+    }
+
+    /// Returns the stateful set of a node, with the projected chain files mounted at the chain
+    /// directory and a volume for the datadir.
+    fn stateful_set(
+        &self,
+        name: &str,
+        args: Vec<String>,
+        sources: serde_json::Value,
+        host_network: bool,
+    ) -> serde_json::Value {
+        let labels = serde_json::json!({ "app": "narwhal", "node": name });
+        serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "StatefulSet",
+            "metadata": { "name": format!("narwhal-{name}"), "labels": labels },
+            "spec": {
+                "serviceName": format!("narwhal-{name}"),
+                "replicas": 1,
+                "selector": { "matchLabels": labels },
+                "template": {
+                    "metadata": { "labels": labels },
+                    "spec": {
+                        "hostNetwork": host_network,
+                        "containers": [{
+                            "name": "reth",
+                            "image": self.config.image,
+                            "args": args,
+                            "volumeMounts": [
+                                { "name": "chain", "mountPath": CHAIN_MOUNT, "readOnly": true },
+                                { "name": "data", "mountPath": DATA_MOUNT },
+                            ],
+                        }],
+                        "volumes": [{ "name": "chain", "projected": { "sources": sources } }],
+                    },
+                },
+                "volumeClaimTemplates": [{
+                    "metadata": { "name": "data" },
+                    "spec": {
+                        "accessModes": ["ReadWriteOnce"],
+                        "resources": { "requests": { "storage": self.config.storage } },
+                    },
+                }],
+            },
+        })
+    }
+}
+
+/// Returns the name of the node of a validator.
+fn validator_name(validator: usize) -> String {
+    format!("validator-{validator}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scaffold::ChainManifest;
+
+    fn deployment(manifest: &ChainManifest) -> Deployment {
+        let scaffold = Scaffold::generate(manifest).unwrap();
+        let configs =
+            scaffold.validators.iter().map(|validator| validator.config.clone()).collect();
+        Deployment::new(scaffold.committee, configs, DeploymentConfig::default())
+    }
+
+    #[test]
+    fn local_compose() {
+        let manifest = ChainManifest { validators: 2, workers: 2, ..Default::default() };
+        let deployment = deployment(&manifest).with_p2p_id(1, "ab".to_string());
+        assert!(deployment.host_network());
+        assert_eq!(deployment.narwhal_ports(1), [30405, 30406, 30407, 30408, 30409]);
+        assert_eq!(deployment.p2p_addr(1), "127.0.0.1:30304".parse().unwrap());
+
+        let compose = deployment.compose(Path::new("/srv/chain"));
+        assert_eq!(compose.services.len(), 3);
+        assert_eq!(compose.volumes.len(), 3);
+        let validator = &compose.services["validator-1"];
+        assert_eq!(validator.network_mode.as_deref(), Some("host"));
+        assert!(validator.ports.is_empty());
+        assert_eq!(validator.volumes[0], "/srv/chain:/chain:ro");
+        assert!(validator.command.windows(2).any(|arg| arg == ["--instance", "2"]));
+        assert!(validator
+            .command
+            .windows(2)
+            .any(|arg| arg == ["--narwhal.keystore", "/chain/validator-1/keystore.json"]));
+
+        // the follower comes after the validators, and keeps the configured HTTP port
+        let rpc = &compose.services["rpc"];
+        assert!(rpc.command.windows(2).any(|arg| arg == ["--instance", "3"]));
+        assert!(rpc.command.windows(2).any(|arg| arg == ["--http.port", "8547"]));
+        assert!(rpc
+            .command
+            .windows(2)
+            .any(|arg| arg == ["--trusted-peers", "enode://ab@127.0.0.1:30304"]));
+    }
+
+    #[test]
+    fn remote_compose() {
+        let hosts = vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()];
+        let manifest = ChainManifest { validators: 2, hosts, ..Default::default() };
+        let deployment = deployment(&manifest);
+        assert!(!deployment.host_network());
+
+        let compose = deployment.compose(Path::new("/srv/chain"));
+        let validator = &compose.services["validator-0"];
+        assert_eq!(validator.network_mode, None);
+        assert_eq!(
+            validator.ports,
+            ["30303:30303/tcp", "30303:30303/udp", "30400:30400", "30401:30401", "30402:30402"]
+        );
+        assert!(!validator.command.iter().any(|arg| arg == "--instance"));
+        assert_eq!(compose.services["rpc"].ports, ["8545:8545"]);
+    }
+
+    #[test]
+    fn load_scaffold() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = ChainManifest { validators: 2, ..Default::default() };
+        let scaffold = Scaffold::generate(&manifest).unwrap().with_kdf_iterations(1);
+        scaffold.write(dir.path()).unwrap();
+        fs::write(dir.path().join(GENESIS_FILE), "{}").unwrap();
+        for validator in 0..2 {
+            let dir = Scaffold::validator_dir(dir.path(), validator);
+            fs::write(dir.join(P2P_SECRET_KEY_FILE), "00").unwrap();
+        }
+
+        let deployment =
+            Deployment::load(&dir.path().join(COMMITTEE_FILE), DeploymentConfig::default())
+                .unwrap();
+        assert_eq!(deployment.committee(), &scaffold.committee);
+        assert_eq!(deployment.narwhal_ports(0), [30400, 30401, 30402]);
+
+        let manifests = deployment.kubernetes(dir.path()).unwrap();
+        let items = manifests["items"].as_array().unwrap();
+        // the chain, a secret and a stateful set per validator, and the follower and its service
+        assert_eq!(items.len(), 1 + 2 * 2 + 2);
+        assert_eq!(items[1]["stringData"][PASSWORD_FILE], scaffold.validators[0].password);
+        assert_eq!(items[2]["spec"]["template"]["spec"]["hostNetwork"], true);
+    }
+}
@@ -0,0 +1,619 @@
+//! Deposits from a bridge contract on L1.
+//!
+//! Users deposit to a narwhal chain by calling the bridge contract on L1, which emits a
+//! `DepositInitiated` event with a sequential nonce. Once the L1 block of a deposit is finalized,
+//! the [`DepositIngestion`] task of every validator turns it into a system transaction and submits
+//! it to the workers, so deposits reach the chain through consensus output like any other
+//! transaction, and every validator derives the same blocks from them.
+//!
+//! A deposit transaction is sent by the deposit sender of the chain's [`DepositConfig`], with the
+//! nonce of the deposit as its transaction nonce. It calls `finalizeDeposit` on the
+//! [`BRIDGE`](crate::predeploys::BRIDGE) predeploy and carries the deposited amount as value, so
+//! the deposit sender must hold the bridged supply from the genesis on. Signatures are
+//! deterministic, so all validators submit the same transaction for a deposit and only its first
+//! inclusion is executed. The key of the deposit sender is shared by the validators: whoever holds
+//! it can mint deposits, like the signers of a multisig bridge.
+//!
+//! The executor moves the deposit transactions of a commit to the front of its first block, see
+//! [`deposits_first`], and blocks that include a deposit after another transaction are rejected by
+//! [`validate_deposit_order`](crate::validation::validate_deposit_order). The nonce of the deposit
+//! sender makes sure that every deposit is executed once, in the order of the L1 bridge.
+
+use alloy_primitives::{Address, BlockNumber};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+#[cfg(feature = "execution")]
+pub use ingestion::*;
+
+/// The deposit parameters of a chain, which every validator must agree on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositConfig {
+    /// The bridge contract on L1 that emits the deposits.
+    pub l1_bridge: Address,
+    /// The sender of the deposit transactions.
+    pub sender: Address,
+    /// The gas limit of every deposit transaction.
+    #[serde(default = "DepositConfig::default_gas_limit")]
+    pub gas_limit: u64,
+    /// The maximum fee per gas of every deposit transaction, which must cover the base fee of the
+    /// block that includes it.
+    #[serde(default = "DepositConfig::default_max_fee_per_gas")]
+    pub max_fee_per_gas: u128,
+}
+
+impl DepositConfig {
+    const fn default_gas_limit() -> u64 {
+        200_000
+    }
+
+    const fn default_max_fee_per_gas() -> u128 {
+        1_000_000_000_000
+    }
+}
+
+/// Node-local configuration of the [`DepositIngestion`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DepositIngestionConfig {
+    /// The HTTP endpoint of an L1 node, deposits are not ingested if `None`.
+    pub l1_rpc_url: Option<String>,
+    /// The file with the hex encoded key of the deposit sender.
+    pub key_file: Option<PathBuf>,
+    /// The first L1 block that is scanned for deposits, usually the block that deployed the
+    /// bridge.
+    pub start_block: BlockNumber,
+    /// How often the finalized block of L1 is polled.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+    /// The maximum number of L1 blocks whose deposits are requested at once.
+    pub max_block_range: u64,
+    /// How long a submitted deposit may stay unexecuted before it's submitted again.
+    #[serde(with = "humantime_serde")]
+    pub resubmit_after: Duration,
+}
+
+impl Default for DepositIngestionConfig {
+    fn default() -> Self {
+        Self {
+            l1_rpc_url: None,
+            key_file: None,
+            start_block: 0,
+            poll_interval: Duration::from_secs(12),
+            max_block_range: 1_000,
+            resubmit_after: Duration::from_secs(60),
+        }
+    }
+}
+
+#[cfg(feature = "execution")]
+mod ingestion {
+    use super::{DepositConfig, DepositIngestionConfig};
+    use crate::predeploys::BRIDGE;
+    use alloy_primitives::{Address, BlockNumber, Bytes, Log, B256, U256};
+    use alloy_sol_types::{sol, SolCall, SolEvent};
+    use reth_metrics::{
+        metrics::{Counter, Gauge},
+        Metrics,
+    };
+    use reth_primitives::{
+        sign_message, Transaction, TransactionSigned, TransactionSignedEcRecovered, TxEip1559,
+        TxKind,
+    };
+    use std::{
+        collections::BTreeMap, future::Future, io, ops::RangeInclusive, pin::Pin, time::Instant,
+    };
+    use tokio::sync::mpsc;
+    use tracing::{debug, info, warn};
+
+    sol! {
+        /// Emitted by the bridge contract on L1 for every deposit.
+        #[derive(Debug, PartialEq, Eq)]
+        event DepositInitiated(
+            uint64 indexed nonce,
+            address indexed from,
+            address indexed to,
+            uint256 amount,
+            bytes data
+        );
+
+        /// Called on the bridge predeploy by every deposit transaction, with the amount as value.
+        #[derive(Debug, PartialEq, Eq)]
+        function finalizeDeposit(uint64 nonce, address from, address to, bytes data);
+    }
+
+    /// Errors of the deposit sender's key.
+    #[derive(Debug, thiserror::Error)]
+    pub enum DepositKeyError {
+        /// The key is not a valid secp256k1 secret key.
+        #[error("invalid deposit key")]
+        InvalidKey,
+        /// The key is not the key of the chain's deposit sender.
+        #[error("deposit key of {got} is not the key of the deposit sender {expected}")]
+        WrongSender {
+            /// The address of the key.
+            got: Address,
+            /// The deposit sender of the chain.
+            expected: Address,
+        },
+    }
+
+    /// A deposit emitted by the L1 bridge.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Deposit {
+        /// The sequential nonce of the deposit, starting at 0.
+        pub nonce: u64,
+        /// The L1 block that emitted the deposit.
+        pub l1_block: BlockNumber,
+        /// The depositor on L1.
+        pub from: Address,
+        /// The recipient on the narwhal chain.
+        pub to: Address,
+        /// The deposited amount.
+        pub amount: U256,
+        /// The data passed on to the bridge predeploy.
+        pub data: Bytes,
+    }
+
+    impl Deposit {
+        /// Decodes the deposit of a log of the L1 bridge emitted in the given block.
+        ///
+        /// Returns `None` if the log is not a `DepositInitiated` event.
+        pub fn from_log(log: &Log, l1_block: BlockNumber) -> Option<Self> {
+            let event = DepositInitiated::decode_log(log, true).ok()?.data;
+            Some(Self {
+                nonce: event.nonce,
+                l1_block,
+                from: event.from,
+                to: event.to,
+                amount: event.amount,
+                data: event.data,
+            })
+        }
+
+        /// Returns the unsigned deposit transaction on the chain with the given id.
+        pub fn transaction(&self, config: &DepositConfig, chain_id: u64) -> Transaction {
+            let call = finalizeDepositCall {
+                nonce: self.nonce,
+                from: self.from,
+                to: self.to,
+                data: self.data.clone(),
+            };
+            Transaction::Eip1559(TxEip1559 {
+                chain_id,
+                nonce: self.nonce,
+                gas_limit: config.gas_limit,
+                max_fee_per_gas: config.max_fee_per_gas,
+                max_priority_fee_per_gas: 0,
+                to: TxKind::Call(BRIDGE),
+                value: self.amount,
+                input: call.abi_encode().into(),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Signs the deposit transactions of a chain with the key of its deposit sender.
+    #[derive(Clone)]
+    pub struct DepositSigner {
+        config: DepositConfig,
+        chain_id: u64,
+        key: B256,
+    }
+
+    impl std::fmt::Debug for DepositSigner {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("DepositSigner")
+                .field("config", &self.config)
+                .field("chain_id", &self.chain_id)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl DepositSigner {
+        /// Creates a signer with the given key, which must be the key of the chain's deposit
+        /// sender.
+        pub fn new(
+            config: DepositConfig,
+            chain_id: u64,
+            key: B256,
+        ) -> Result<Self, DepositKeyError> {
+            let signer = Self { config, chain_id, key };
+            let probe = Deposit {
+                nonce: 0,
+                l1_block: 0,
+                from: Address::ZERO,
+                to: Address::ZERO,
+                amount: U256::ZERO,
+                data: Bytes::new(),
+            };
+            let got = signer.sign(&probe)?.signer();
+            if got != config.sender {
+                return Err(DepositKeyError::WrongSender { got, expected: config.sender })
+            }
+            Ok(signer)
+        }
+
+        /// Returns the deposit parameters of the chain.
+        pub const fn config(&self) -> &DepositConfig {
+            &self.config
+        }
+
+        /// Returns the signed deposit transaction of a deposit.
+        pub fn sign(
+            &self,
+            deposit: &Deposit,
+        ) -> Result<TransactionSignedEcRecovered, DepositKeyError> {
+            let transaction = deposit.transaction(&self.config, self.chain_id);
+            let signature = sign_message(self.key, transaction.signature_hash())
+                .map_err(|_| DepositKeyError::InvalidKey)?;
+            TransactionSigned::from_transaction_and_signature(transaction, signature)
+                .into_ecrecovered()
+                .ok_or(DepositKeyError::InvalidKey)
+        }
+    }
+
+    /// Moves the transactions of the deposit sender to the front, and keeps the order of all
+    /// other transactions.
+    ///
+    /// The transactions must be sequenced already, so the deposits are in nonce order.
+    pub fn deposits_first(sender: Address, transactions: &mut [TransactionSignedEcRecovered]) {
+        // the sort is stable and only tells deposits apart from other transactions
+        transactions.sort_by_key(|transaction| transaction.signer() != sender);
+    }
+
+    /// The deposits of L1, e.g. read from an L1 node over JSON-RPC.
+    pub trait DepositSource: Send + Sync + 'static {
+        /// Returns the number of the last finalized L1 block.
+        fn finalized_block(
+            &self,
+        ) -> Pin<Box<dyn Future<Output = io::Result<BlockNumber>> + Send + '_>>;
+
+        /// Returns the deposits emitted in the given range of L1 blocks.
+        fn deposits(
+            &self,
+            blocks: RangeInclusive<BlockNumber>,
+        ) -> Pin<Box<dyn Future<Output = io::Result<Vec<Deposit>>> + Send + '_>>;
+    }
+
+    /// Metrics of the [`DepositIngestion`].
+    #[derive(Metrics)]
+    #[metrics(scope = "narwhal.deposits")]
+    struct DepositIngestionMetrics {
+        /// Number of deposit transactions submitted to the workers
+        submitted_deposits: Counter,
+        /// Number of deposit transactions submitted again because they were not executed in time
+        resubmitted_deposits: Counter,
+        /// Number of failed requests to the deposit source
+        failed_polls: Counter,
+        /// The last finalized L1 block that was scanned for deposits
+        scanned_block: Gauge,
+    }
+
+    /// A submitted deposit transaction that was not executed yet.
+    #[derive(Debug)]
+    struct PendingDeposit {
+        encoded: Bytes,
+        submitted: Instant,
+    }
+
+    /// Submits the finalized deposits of L1 as deposit transactions to the workers.
+    ///
+    /// `executed` returns the nonce of the deposit sender at the canonical head, i.e. the nonce of
+    /// the next deposit the chain executes. Deposits below it are skipped, and submitted deposits
+    /// that are not executed within [`DepositIngestionConfig::resubmit_after`] are submitted
+    /// again, e.g. because their batch was not certified.
+    pub struct DepositIngestion<S, F> {
+        source: S,
+        signer: DepositSigner,
+        config: DepositIngestionConfig,
+        executed: F,
+        submissions: mpsc::Sender<Bytes>,
+        /// The next L1 block to scan.
+        next_block: BlockNumber,
+        pending: BTreeMap<u64, PendingDeposit>,
+        metrics: DepositIngestionMetrics,
+    }
+
+    impl<S, F> std::fmt::Debug for DepositIngestion<S, F> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("DepositIngestion")
+                .field("signer", &self.signer)
+                .field("config", &self.config)
+                .field("next_block", &self.next_block)
+                .field("pending", &self.pending.len())
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<S, F, E> DepositIngestion<S, F>
+    where
+        S: DepositSource,
+        F: FnMut() -> Result<u64, E> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        /// Creates the task that submits the deposits of the source to the workers through the
+        /// given channel, usually the submissions of a
+        /// [`BatchMaker`](crate::worker::BatchMaker).
+        pub fn new(
+            source: S,
+            signer: DepositSigner,
+            config: DepositIngestionConfig,
+            executed: F,
+            submissions: mpsc::Sender<Bytes>,
+        ) -> Self {
+            Self {
+                source,
+                signer,
+                next_block: config.start_block,
+                config,
+                executed,
+                submissions,
+                pending: BTreeMap::new(),
+                metrics: DepositIngestionMetrics::default(),
+            }
+        }
+
+        /// Polls the source until the workers stop accepting submissions.
+        pub async fn run(mut self) {
+            info!(
+                target: "consensus::narwhal",
+                bridge = %self.signer.config().l1_bridge,
+                start_block = self.next_block,
+                "Ingesting deposits"
+            );
+            let mut interval = tokio::time::interval(self.config.poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                match self.poll().await {
+                    Ok(()) => {}
+                    Err(PollError::Closed) => return,
+                    Err(PollError::Source(err)) => {
+                        self.metrics.failed_polls.increment(1);
+                        warn!(target: "consensus::narwhal", %err, "Failed to poll deposits");
+                    }
+                }
+            }
+        }
+
+        /// Submits the deposits of the L1 blocks that were finalized since the last poll, and the
+        /// pending deposits that are due.
+        async fn poll(&mut self) -> Result<(), PollError> {
+            let executed = match (self.executed)() {
+                Ok(executed) => executed,
+                Err(err) => return Err(PollError::Source(io::Error::other(err.to_string()))),
+            };
+            self.pending = self.pending.split_off(&executed);
+
+            let finalized = self.source.finalized_block().await?;
+            while self.next_block <= finalized {
+                let last = finalized
+                    .min(self.next_block.saturating_add(self.config.max_block_range.max(1) - 1));
+                let mut deposits = self.source.deposits(self.next_block..=last).await?;
+                deposits.sort_unstable_by_key(|deposit| deposit.nonce);
+                for deposit in deposits {
+                    if deposit.nonce < executed || self.pending.contains_key(&deposit.nonce) {
+                        continue
+                    }
+                    let transaction = match self.signer.sign(&deposit) {
+                        Ok(transaction) => transaction,
+                        Err(err) => {
+                            warn!(target: "consensus::narwhal", %err, "Failed to sign deposit");
+                            continue
+                        }
+                    };
+                    let encoded = transaction.envelope_encoded();
+                    debug!(
+                        target: "consensus::narwhal",
+                        nonce = deposit.nonce,
+                        l1_block = deposit.l1_block,
+                        hash = %transaction.hash(),
+                        "Submitting deposit"
+                    );
+                    self.submit(encoded.clone()).await?;
+                    self.metrics.submitted_deposits.increment(1);
+                    self.pending.insert(
+                        deposit.nonce,
+                        PendingDeposit { encoded, submitted: Instant::now() },
+                    );
+                }
+                self.next_block = last + 1;
+                self.metrics.scanned_block.set(last as f64);
+            }
+
+            let due = self
+                .pending
+                .values_mut()
+                .filter(|pending| pending.submitted.elapsed() >= self.config.resubmit_after)
+                .map(|pending| {
+                    pending.submitted = Instant::now();
+                    pending.encoded.clone()
+                })
+                .collect::<Vec<_>>();
+            for encoded in due {
+                self.submit(encoded).await?;
+                self.metrics.resubmitted_deposits.increment(1);
+            }
+            Ok(())
+        }
+
+        async fn submit(&self, encoded: Bytes) -> Result<(), PollError> {
+            self.submissions.send(encoded).await.map_err(|_| PollError::Closed)
+        }
+    }
+
+    /// Why a poll of the [`DepositIngestion`] failed.
+    #[derive(Debug)]
+    enum PollError {
+        /// The workers stopped accepting submissions.
+        Closed,
+        /// The deposit source failed.
+        Source(io::Error),
+    }
+
+    impl From<io::Error> for PollError {
+        fn from(err: io::Error) -> Self {
+            Self::Source(err)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use parking_lot::Mutex;
+        use std::{convert::Infallible, sync::Arc, time::Duration};
+
+        const KEY: B256 = B256::with_last_byte(1);
+
+        fn signer() -> DepositSigner {
+            let config = DepositConfig {
+                l1_bridge: Address::with_last_byte(0xb),
+                sender: Address::ZERO,
+                gas_limit: 200_000,
+                max_fee_per_gas: 1_000_000_000_000,
+            };
+            let sender = match DepositSigner::new(config, 1, KEY) {
+                Err(DepositKeyError::WrongSender { got, .. }) => got,
+                other => panic!("unexpected {other:?}"),
+            };
+            DepositSigner::new(DepositConfig { sender, ..config }, 1, KEY).unwrap()
+        }
+
+        fn deposit(nonce: u64, l1_block: BlockNumber) -> Deposit {
+            Deposit {
+                nonce,
+                l1_block,
+                from: Address::with_last_byte(1),
+                to: Address::with_last_byte(2),
+                amount: U256::from(100),
+                data: Bytes::new(),
+            }
+        }
+
+        #[derive(Default)]
+        struct MockSource {
+            finalized: Mutex<BlockNumber>,
+            deposits: Vec<Deposit>,
+        }
+
+        impl DepositSource for Arc<MockSource> {
+            fn finalized_block(
+                &self,
+            ) -> Pin<Box<dyn Future<Output = io::Result<BlockNumber>> + Send + '_>> {
+                Box::pin(async move { Ok(*self.finalized.lock()) })
+            }
+
+            fn deposits(
+                &self,
+                blocks: RangeInclusive<BlockNumber>,
+            ) -> Pin<Box<dyn Future<Output = io::Result<Vec<Deposit>>> + Send + '_>> {
+                let deposits = self
+                    .deposits
+                    .iter()
+                    .filter(|deposit| blocks.contains(&deposit.l1_block))
+                    .cloned()
+                    .collect();
+                Box::pin(async move { Ok(deposits) })
+            }
+        }
+
+        #[test]
+        fn decode_deposit_log() {
+            let event = DepositInitiated {
+                nonce: 7,
+                from: Address::with_last_byte(1),
+                to: Address::with_last_byte(2),
+                amount: U256::from(100),
+                data: Bytes::from_static(b"data"),
+            };
+            let log = Log { address: Address::with_last_byte(0xb), data: event.encode_log_data() };
+            let deposit = Deposit::from_log(&log, 42).unwrap();
+            assert_eq!(deposit.nonce, 7);
+            assert_eq!(deposit.l1_block, 42);
+            assert_eq!(deposit.to, Address::with_last_byte(2));
+            assert_eq!(deposit.data, Bytes::from_static(b"data"));
+        }
+
+        #[test]
+        fn deposit_transactions_are_deterministic() {
+            let signer = signer();
+            let first = signer.sign(&deposit(0, 1)).unwrap();
+            let second = signer.sign(&deposit(0, 1)).unwrap();
+            assert_eq!(first.hash(), second.hash());
+            assert_eq!(first.signer(), signer.config().sender);
+            assert_eq!(first.nonce(), 0);
+            assert_eq!(first.to(), Some(BRIDGE));
+            assert_eq!(first.value(), U256::from(100));
+        }
+
+        #[test]
+        fn moves_deposits_to_the_front() {
+            let signer = signer();
+            let user = |nonce| {
+                let transaction = Transaction::Eip1559(TxEip1559 { nonce, ..Default::default() });
+                let signature =
+                    sign_message(B256::with_last_byte(2), transaction.signature_hash()).unwrap();
+                TransactionSigned::from_transaction_and_signature(transaction, signature)
+                    .into_ecrecovered()
+                    .unwrap()
+            };
+            let mut transactions = vec![
+                user(0),
+                signer.sign(&deposit(0, 1)).unwrap(),
+                user(1),
+                signer.sign(&deposit(1, 1)).unwrap(),
+            ];
+            deposits_first(signer.config().sender, &mut transactions);
+            let order = transactions
+                .iter()
+                .map(|transaction| {
+                    (transaction.signer() == signer.config().sender, transaction.nonce())
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(order, [(true, 0), (true, 1), (false, 0), (false, 1)]);
+        }
+
+        #[tokio::test]
+        async fn submits_finalized_deposits_once() {
+            let source = Arc::new(MockSource {
+                finalized: Mutex::new(1),
+                deposits: vec![deposit(1, 1), deposit(0, 1), deposit(2, 2)],
+            });
+            let executed = Arc::new(Mutex::new(0));
+            let (submissions, mut submitted) = mpsc::channel(10);
+            let config = DepositIngestionConfig {
+                start_block: 1,
+                resubmit_after: Duration::from_secs(3600),
+                ..Default::default()
+            };
+            let executed_nonce = executed.clone();
+            let mut ingestion = DepositIngestion::new(
+                source.clone(),
+                signer(),
+                config,
+                move || Ok::<_, Infallible>(*executed_nonce.lock()),
+                submissions,
+            );
+
+            // only the deposits of the finalized block, in nonce order
+            ingestion.poll().await.unwrap();
+            let decode = |encoded: Bytes| {
+                TransactionSigned::decode_enveloped(&mut &encoded[..]).unwrap().nonce()
+            };
+            assert_eq!(decode(submitted.try_recv().unwrap()), 0);
+            assert_eq!(decode(submitted.try_recv().unwrap()), 1);
+            assert!(submitted.try_recv().is_err());
+
+            // the executed deposits are no longer pending, the next block is scanned once
+            *executed.lock() = 2;
+            *source.finalized.lock() = 2;
+            ingestion.poll().await.unwrap();
+            assert_eq!(decode(submitted.try_recv().unwrap()), 2);
+            ingestion.poll().await.unwrap();
+            assert!(submitted.try_recv().is_err());
+            assert_eq!(ingestion.pending.keys().copied().collect::<Vec<_>>(), [2]);
+        }
+    }
+}
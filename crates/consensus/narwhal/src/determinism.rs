@@ -0,0 +1,200 @@
+//! Guard against ambient nondeterminism during block building.
+//!
+//! Every validator builds the same blocks from the same commits, so block building may only depend
+//! on the commit and the parent state. Inputs that differ between validators, such as the system
+//! time, randomness or environment variables, have to be routed through the [`Clock`] and [`Seed`]
+//! abstractions instead, which are derived from the commit while a block is built.
+//!
+//! Block building runs inside a [`DeterministicScope`]. In debug builds, the ambient sources of
+//! this module, [`SystemClock`], [`Seed::from_entropy`] and [`env_var`], panic when they are used
+//! inside the scope. This catches accidental nondeterminism in custom precompiles and hooks in
+//! tests, before validators disagree on a block.
+
+use alloy_primitives::{keccak256, B256};
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+thread_local! {
+    /// Number of [`DeterministicScope`]s entered on this thread.
+    static DETERMINISTIC_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Marks the current thread as building a block while it is alive.
+///
+/// Scopes may be nested, the thread leaves the deterministic section when the outermost scope is
+/// dropped. The scope can't be sent to another thread, since it only guards the thread that
+/// entered it.
+#[derive(Debug)]
+#[must_use = "the scope is left when it is dropped"]
+pub struct DeterministicScope {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl DeterministicScope {
+    /// Enters a deterministic section on the current thread.
+    pub fn enter() -> Self {
+        DETERMINISTIC_DEPTH.with(|depth| depth.set(depth.get() + 1));
+        Self { _not_send: PhantomData }
+    }
+
+    /// Returns `true` if the current thread is inside a deterministic section.
+    pub fn is_active() -> bool {
+        DETERMINISTIC_DEPTH.with(|depth| depth.get() > 0)
+    }
+}
+
+impl Drop for DeterministicScope {
+    fn drop(&mut self) {
+        DETERMINISTIC_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/// Panics in debug builds if the current thread is inside a [`DeterministicScope`].
+#[track_caller]
+fn assert_ambient_allowed(source: &str) {
+    debug_assert!(
+        !DeterministicScope::is_active(),
+        "{source} used during block building, use the block's Clock or Seed instead"
+    );
+}
+
+/// A source of the current time, in milliseconds since the unix epoch.
+pub trait Clock {
+    /// Returns the current time.
+    fn now_millis(&self) -> u64;
+}
+
+/// The system clock.
+///
+/// Must not be used during block building.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[track_caller]
+    fn now_millis(&self) -> u64 {
+        assert_ambient_allowed("system time");
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+    }
+}
+
+/// A clock that always returns the same time, e.g. the timestamp of the block being built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now_millis(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A deterministic source of pseudo-random values.
+///
+/// The values are `keccak256(seed ++ counter)`, so every validator that derives the seed from the
+/// same commit draws the same values in the same order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Seed {
+    seed: B256,
+    counter: u64,
+}
+
+impl Seed {
+    /// Creates a source seeded with the given value, e.g. the digest of the commit.
+    pub const fn new(seed: B256) -> Self {
+        Self { seed, counter: 0 }
+    }
+
+    /// Creates a source seeded from local entropy.
+    ///
+    /// Must not be used during block building.
+    #[track_caller]
+    pub fn from_entropy() -> Self {
+        assert_ambient_allowed("randomness");
+        let mut seed = [0u8; 32];
+        // every `RandomState` is keyed with fresh random keys
+        for (i, chunk) in seed.chunks_mut(8).enumerate() {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_usize(i);
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+        Self::new(B256::from(seed))
+    }
+
+    /// Returns the next pseudo-random value.
+    pub fn next_b256(&mut self) -> B256 {
+        let mut buf = [0u8; 40];
+        buf[..32].copy_from_slice(self.seed.as_slice());
+        buf[32..].copy_from_slice(&self.counter.to_be_bytes());
+        self.counter += 1;
+        keccak256(buf)
+    }
+
+    /// Returns the next pseudo-random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        u64::from_be_bytes(self.next_b256()[..8].try_into().unwrap())
+    }
+}
+
+/// Returns the value of an environment variable.
+///
+/// Must not be used during block building.
+#[track_caller]
+pub fn env_var(key: &str) -> Option<String> {
+    assert_ambient_allowed("environment variable");
+    std::env::var(key).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_is_deterministic() {
+        let mut a = Seed::new(B256::with_last_byte(1));
+        let mut b = a.clone();
+        let values = [a.next_u64(), a.next_u64()];
+        assert_eq!(values, [b.next_u64(), b.next_u64()]);
+        assert_ne!(values[0], values[1]);
+        assert_ne!(Seed::new(B256::ZERO).next_u64(), values[0]);
+    }
+
+    #[test]
+    fn ambient_sources_outside_scope() {
+        assert!(!DeterministicScope::is_active());
+        assert!(SystemClock.now_millis() > 0);
+        let _ = Seed::from_entropy();
+        let _ = env_var("PATH");
+    }
+
+    #[test]
+    fn nested_scopes() {
+        let outer = DeterministicScope::enter();
+        let inner = DeterministicScope::enter();
+        drop(inner);
+        assert!(DeterministicScope::is_active());
+        assert_eq!(FixedClock(5).now_millis(), 5);
+        drop(outer);
+        assert!(!DeterministicScope::is_active());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "system time used during block building")]
+    fn system_time_in_scope() {
+        let _scope = DeterministicScope::enter();
+        SystemClock.now_millis();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "randomness used during block building")]
+    fn randomness_in_scope() {
+        let _scope = DeterministicScope::enter();
+        Seed::from_entropy();
+    }
+}
@@ -0,0 +1,470 @@
+//! A committee that runs in a single process, for local development.
+//!
+//! A single validator never exercises the quorum logic of narwhal, so dev mode runs a whole
+//! [`DevCommittee`] instead: every authority has a primary, and the authorities exchange their
+//! batches and certificates over local channels rather than the network. The keys of the
+//! authorities are derived from fixed seeds, so they are the same on every run and must never be
+//! used outside of dev mode.
+
+use crate::{
+    committee::{Authority, Committee},
+    primary::{Primary, PrimaryConfig, PrimaryHandle},
+    signature::{AggregateScheme, Bls12381, BlsPublicKey, BlsSecretKey, VotesAggregator},
+    types::{Certificate, Header},
+    wire::{Handshake, NarwhalMessage, VersionRange, WireError, WireVersion},
+    worker::{BatchQuota, SealedBatch, WorkerMessage, WorkerTransport},
+};
+use alloy_primitives::{keccak256, Bytes};
+use reth_narwhal_verifier::{AuthorityIndex, SigningDomain, VerifierCommittee};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    pin::Pin,
+};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// The number of authorities of the dev committee, the smallest committee that tolerates a
+/// faulty authority.
+pub const DEFAULT_DEV_COMMITTEE_SIZE: usize = 4;
+
+/// The capacity of the channels between the authorities of a dev committee.
+const CHANNEL_CAPACITY: usize = 1_024;
+
+/// The first port of the addresses of the dev committee, which are only used to identify the
+/// authorities.
+const BASE_PORT: u16 = 30_400;
+
+/// A committee with equal stakes whose authorities all run in this process.
+#[derive(Debug, Clone)]
+pub struct DevCommittee {
+    committee: Committee,
+    secret_keys: Vec<BlsSecretKey>,
+}
+
+impl DevCommittee {
+    /// Creates the committee of `size` authorities for the first epoch.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is zero.
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "dev committee must have at least one authority");
+        let secret_keys = (0..size)
+            .map(|index| {
+                let seed = keccak256(format!("narwhal dev authority {index}"));
+                BlsSecretKey::from_seed(seed.as_slice()).expect("32 bytes of seed")
+            })
+            .collect::<Vec<_>>();
+        let authorities = secret_keys
+            .iter()
+            .enumerate()
+            .map(|(index, secret_key)| {
+                let port = BASE_PORT + 10 * index as u16;
+                let address = |port| SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+                Authority {
+                    public_key: Bytes::copy_from_slice(
+                        &Bls12381::public_key(secret_key).to_bytes(),
+                    ),
+                    proof_of_possession: Bytes::copy_from_slice(
+                        &secret_key.proof_of_possession().to_bytes(),
+                    ),
+                    stake: 1,
+                    primary_address: address(port),
+                    worker_addresses: vec![address(port + 1)],
+                }
+            })
+            .collect();
+        Self { committee: Committee { epoch: 0, authorities }, secret_keys }
+    }
+
+    /// Creates a committee whose authorities all run in this process from the keys of its
+    /// authorities, e.g. the committee of a chain with a single validator.
+    ///
+    /// Returns `None` if one of the authorities has none of the keys.
+    pub fn with_keys(committee: Committee, keys: &[BlsSecretKey]) -> Option<Self> {
+        let public_keys = keys
+            .iter()
+            .map(|secret_key| Bls12381::public_key(secret_key).to_bytes())
+            .collect::<Vec<_>>();
+        let secret_keys = committee
+            .authorities
+            .iter()
+            .map(|authority| {
+                let index =
+                    public_keys.iter().position(|key| key[..] == authority.public_key[..])?;
+                Some(keys[index].clone())
+            })
+            .collect::<Option<_>>()?;
+        Some(Self { committee, secret_keys })
+    }
+
+    /// Returns the committee.
+    pub const fn committee(&self) -> &Committee {
+        &self.committee
+    }
+
+    /// Returns the secret key of an authority.
+    pub fn secret_key(&self, authority: AuthorityIndex) -> Option<&BlsSecretKey> {
+        self.secret_keys.get(authority as usize)
+    }
+
+    /// Creates the primaries of all authorities, connected by a [`LocalCertifier`] that certifies
+    /// their headers on the network of the domain.
+    pub fn primaries(&self, domain: SigningDomain, config: PrimaryConfig) -> DevPrimaries {
+        let verifier = self
+            .committee
+            .verifier_committee::<BlsPublicKey>()
+            .expect("dev committee keys are valid");
+        let (headers, headers_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let mut primaries = Vec::with_capacity(self.secret_keys.len());
+        let mut handles = Vec::with_capacity(self.secret_keys.len());
+        let mut batches = Vec::with_capacity(self.secret_keys.len());
+        let mut certificates = Vec::with_capacity(self.secret_keys.len());
+        for author in 0..self.secret_keys.len() as AuthorityIndex {
+            let (batches_tx, batches_rx) = mpsc::channel(CHANNEL_CAPACITY);
+            let (certificates_tx, certificates_rx) = mpsc::channel(CHANNEL_CAPACITY);
+            let (primary, handle) = Primary::new(
+                &verifier,
+                author,
+                config,
+                batches_rx,
+                certificates_rx,
+                headers.clone(),
+            );
+            primaries.push(primary);
+            handles.push(handle);
+            batches.push(batches_tx);
+            certificates.push(certificates_tx);
+        }
+        let certifier = LocalCertifier {
+            committee: verifier,
+            secret_keys: self.secret_keys.clone(),
+            domain,
+            headers: headers_rx,
+            certificates,
+            batch_quota: None,
+        };
+        DevPrimaries { primaries, handles, batches, certifier }
+    }
+}
+
+/// The primaries of a [`DevCommittee`], which must all be run together with the certifier.
+#[derive(Debug)]
+pub struct DevPrimaries {
+    /// The primaries, by authority.
+    pub primaries: Vec<Primary>,
+    /// The handles of the primaries, by authority.
+    pub handles: Vec<PrimaryHandle>,
+    /// Feeds the batches sealed by the workers of each authority to its primary.
+    pub batches: Vec<mpsc::Sender<SealedBatch>>,
+    /// Certifies the headers of the primaries.
+    pub certifier: LocalCertifier,
+}
+
+/// Certifies the headers of the primaries of a [`DevCommittee`].
+///
+/// Every authority of the committee runs in this process, so instead of broadcasting a header
+/// and collecting the votes of the other authorities, the certifier signs the votes of all
+/// authorities itself and delivers the certificate to every primary.
+#[derive(Debug)]
+pub struct LocalCertifier {
+    committee: VerifierCommittee<BlsPublicKey>,
+    secret_keys: Vec<BlsSecretKey>,
+    domain: SigningDomain,
+    headers: mpsc::Receiver<Header>,
+    certificates: Vec<mpsc::Sender<Certificate>>,
+    batch_quota: Option<BatchQuota>,
+}
+
+impl LocalCertifier {
+    /// Refuses the votes of the other authorities for headers that reference more batches than
+    /// the quota of their author, see [`BatchQuota`].
+    ///
+    /// The primaries must be limited to the same quota with [`Primary::with_batch_quota`].
+    pub const fn with_batch_quota(mut self, quota: BatchQuota) -> Self {
+        self.batch_quota = Some(quota);
+        self
+    }
+
+    /// Returns a receiver of all certificates, e.g. for the
+    /// [`Committer`](crate::committer::Committer).
+    ///
+    /// The certifier waits for every receiver, so a receiver must be drained or dropped.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<Certificate> {
+        let (certificates, certificates_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        self.certificates.push(certificates);
+        certificates_rx
+    }
+
+    /// Certifies the headers of the primaries until all primaries stopped.
+    pub async fn run(mut self) {
+        while let Some(header) = self.headers.recv().await {
+            let Some(certificate) = self.certify(header) else { continue };
+            debug!(
+                target: "consensus::narwhal",
+                round = certificate.round(),
+                author = certificate.author(),
+                "Certified header of dev committee"
+            );
+            for primary in &self.certificates {
+                // a stopped primary doesn't stop the others
+                let _ = primary.send(certificate.clone()).await;
+            }
+        }
+    }
+
+    /// Returns the certificate of a header with the votes of all authorities that accept it.
+    fn certify(&self, header: Header) -> Option<Certificate> {
+        let author = header.author;
+        let over_quota = self.batch_quota.as_ref().and_then(|quota| {
+            let stake = self.committee.authority(author).map_or(0, |authority| authority.stake);
+            quota.check(stake, self.committee.total_stake(), header.payload.len() as u64).err()
+        });
+        if let Some(err) = over_quota {
+            warn!(
+                target: "consensus::narwhal",
+                %err,
+                round = header.round,
+                author,
+                "Refusing to vote for header above the batch quota"
+            );
+        }
+
+        let mut aggregator = VotesAggregator::<Bls12381>::new(header, &self.domain);
+        let message = aggregator.message();
+        for (voter, secret_key) in self.secret_keys.iter().enumerate() {
+            let voter = voter as AuthorityIndex;
+            // the author votes for its own header
+            if over_quota.is_some() && voter != author {
+                continue
+            }
+            let signature = Bls12381::sign(secret_key, message.as_slice());
+            match aggregator.add_vote(&self.committee, voter, signature) {
+                Ok(Some(certificate)) => return Some(certificate),
+                Ok(None) => {}
+                Err(err) => {
+                    warn!(target: "consensus::narwhal", %err, "Invalid vote of dev committee");
+                    return None
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The receiver of the messages a [`LocalTransport`] delivers to a worker, with the authority of
+/// the sender.
+pub type LocalInbound = mpsc::Receiver<(AuthorityIndex, WorkerMessage)>;
+
+/// A [`WorkerTransport`] that delivers the messages of a worker to the workers of the other
+/// authorities in this process.
+///
+/// Every pair of workers negotiates a [`WireVersion`] with a [`Handshake`] when the mesh is
+/// created, and batches cross the connection in the wire format of that version, see
+/// [`crate::wire`]. The other worker messages, which the wire format doesn't define, are delivered
+/// as they are.
+#[derive(Debug, Clone)]
+pub struct LocalTransport {
+    from: AuthorityIndex,
+    peers: HashMap<AuthorityIndex, LocalPeer>,
+}
+
+/// The connection of a [`LocalTransport`] to the worker of an authority.
+#[derive(Debug, Clone)]
+struct LocalPeer {
+    inbound: mpsc::Sender<(AuthorityIndex, WorkerMessage)>,
+    version: WireVersion,
+}
+
+impl LocalTransport {
+    /// Connects the workers of the given authorities with each other, all of which support the
+    /// wire versions of this release.
+    ///
+    /// Returns the transport of every authority, and the receiver of its inbound messages to
+    /// pass to its [`WorkerNetwork`](crate::worker::WorkerNetwork). Messages to other
+    /// authorities fail with [`io::ErrorKind::NotConnected`].
+    pub fn mesh(authorities: &[AuthorityIndex]) -> Vec<(Self, LocalInbound)> {
+        let authorities = authorities
+            .iter()
+            .map(|authority| (*authority, VersionRange::SUPPORTED))
+            .collect::<Vec<_>>();
+        Self::mesh_with_versions(&authorities).expect("same versions on all connections")
+    }
+
+    /// Connects the workers of the given authorities with each other, each of which offers the
+    /// given wire versions in its handshakes.
+    ///
+    /// Fails if two of the authorities support no common version.
+    pub fn mesh_with_versions(
+        authorities: &[(AuthorityIndex, VersionRange)],
+    ) -> Result<Vec<(Self, LocalInbound)>, WireError> {
+        let (senders, receivers): (HashMap<_, _>, Vec<_>) = authorities
+            .iter()
+            .map(|(authority, _)| {
+                let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+                ((*authority, tx), rx)
+            })
+            .unzip();
+        authorities
+            .iter()
+            .zip(receivers)
+            .map(|((authority, versions), inbound)| {
+                let handshake = Handshake::new(*versions);
+                let peers = authorities
+                    .iter()
+                    .map(|(peer, versions)| {
+                        // the handshake of the peer as it arrives on the connection
+                        let peer_handshake =
+                            Handshake::decode(&Handshake::new(*versions).encode())?;
+                        let version = handshake.negotiate(&peer_handshake)?;
+                        Ok((*peer, LocalPeer { inbound: senders[peer].clone(), version }))
+                    })
+                    .collect::<Result<_, WireError>>()?;
+                Ok((Self { from: *authority, peers }, inbound))
+            })
+            .collect()
+    }
+
+    /// Returns the negotiated wire version of the connection to the worker of an authority.
+    pub fn version(&self, to: AuthorityIndex) -> Option<WireVersion> {
+        self.peers.get(&to).map(|peer| peer.version)
+    }
+}
+
+impl WorkerTransport for LocalTransport {
+    fn send<'a>(
+        &'a mut self,
+        to: AuthorityIndex,
+        message: &'a WorkerMessage,
+    ) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let Some(peer) = self.peers.get(&to) else {
+                return Err(io::ErrorKind::NotConnected.into())
+            };
+            let message = match message {
+                WorkerMessage::Batch { batch } => {
+                    let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+                    let frame = NarwhalMessage::Batch(batch.clone())
+                        .encode(peer.version)
+                        .map_err(invalid)?;
+                    match NarwhalMessage::decode_negotiated(&frame, peer.version)
+                        .map_err(invalid)?
+                    {
+                        NarwhalMessage::Batch(batch) => WorkerMessage::Batch { batch },
+                        _ => return Err(io::ErrorKind::InvalidData.into()),
+                    }
+                }
+                message => message.clone(),
+            };
+            peer.inbound
+                .send((self.from, message))
+                .await
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        types::{Batch, BatchDigest, BatchRef},
+        wire::{MAX_WIRE_VERSION, MIN_WIRE_VERSION},
+        worker::BatchQuotaConfig,
+    };
+    use alloy_primitives::B256;
+    use std::time::Duration;
+
+    #[test]
+    fn deterministic_committee() {
+        let committee = DevCommittee::new(DEFAULT_DEV_COMMITTEE_SIZE);
+        assert_eq!(committee.committee().authorities.len(), 4);
+        assert!(committee.committee().validate().is_ok());
+        assert_eq!(committee.committee(), DevCommittee::new(4).committee());
+    }
+
+    #[test]
+    fn committee_with_keys() {
+        let dev = DevCommittee::new(2);
+        let keys = [dev.secret_key(1).unwrap().clone(), dev.secret_key(0).unwrap().clone()];
+        let local = DevCommittee::with_keys(dev.committee().clone(), &keys).unwrap();
+        for authority in 0..2 {
+            let public_key = |dev: &DevCommittee| {
+                Bls12381::public_key(dev.secret_key(authority).unwrap()).to_bytes()
+            };
+            assert_eq!(public_key(&local), public_key(&dev));
+        }
+
+        // every authority needs its key
+        assert!(DevCommittee::with_keys(dev.committee().clone(), &keys[..1]).is_none());
+    }
+
+    #[tokio::test]
+    async fn rounds_advance() {
+        let committee = DevCommittee::new(DEFAULT_DEV_COMMITTEE_SIZE);
+        let config =
+            PrimaryConfig { max_header_delay: Duration::from_millis(10), ..Default::default() };
+        let DevPrimaries { primaries, handles, batches: _batches, certifier } =
+            committee.primaries(SigningDomain::new(1337, B256::ZERO), config);
+        for primary in primaries {
+            tokio::spawn(primary.run());
+        }
+        tokio::spawn(certifier.run());
+
+        for handle in &handles {
+            let mut round = handle.subscribe();
+            tokio::time::timeout(Duration::from_secs(5), round.wait_for(|round| *round >= 5))
+                .await
+                .unwrap()
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn negotiate_wire_version() {
+        let held_back = VersionRange { min: MIN_WIRE_VERSION, max: MIN_WIRE_VERSION };
+        let mut mesh =
+            LocalTransport::mesh_with_versions(&[(0, VersionRange::SUPPORTED), (1, held_back)])
+                .unwrap();
+        let (_, mut inbound) = mesh.pop().unwrap();
+        let (mut transport, _) = mesh.pop().unwrap();
+        assert_eq!(transport.version(1), Some(MIN_WIRE_VERSION));
+        assert_eq!(transport.version(2), None);
+
+        let batch = Batch::new(vec![Bytes::from_static(&[0x02, 1])]);
+        transport.send(1, &WorkerMessage::Batch { batch: batch.clone() }).await.unwrap();
+        assert_eq!(inbound.recv().await, Some((0, WorkerMessage::Batch { batch })));
+
+        // authorities without a common version can't connect
+        let upgraded = VersionRange { min: MAX_WIRE_VERSION + 1, max: MAX_WIRE_VERSION + 1 };
+        assert!(matches!(
+            LocalTransport::mesh_with_versions(&[(0, held_back), (1, upgraded)]),
+            Err(WireError::NoCommonVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn refuse_header_above_batch_quota() {
+        let committee = DevCommittee::new(DEFAULT_DEV_COMMITTEE_SIZE);
+        let DevPrimaries { certifier, .. } =
+            committee.primaries(SigningDomain::new(1337, B256::ZERO), PrimaryConfig::default());
+        let certifier =
+            certifier.with_batch_quota(BatchQuota::new(BatchQuotaConfig { batches_per_round: 4 }));
+        let header = |batches: u8| Header {
+            round: 1,
+            author: 2,
+            payload: (0..batches)
+                .map(|byte| BatchRef { digest: BatchDigest(B256::with_last_byte(byte)), worker: 0 })
+                .collect(),
+            ..Default::default()
+        };
+
+        // every authority has a quarter of the stake and a quota of one batch
+        assert!(certifier.certify(header(1)).is_some());
+        assert!(certifier.certify(header(0)).is_some());
+        assert_eq!(certifier.certify(header(2)), None);
+    }
+}
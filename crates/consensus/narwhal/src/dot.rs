@@ -0,0 +1,211 @@
+//! Rendering of the stored DAG as a [Graphviz](https://graphviz.org) DOT graph.
+//!
+//! Every certificate is a node, laid out in one row per round, with an edge to each of its parents
+//! in the rendered rounds. Committed certificates are filled, and the certificates of the leaders
+//! are highlighted by the outcome of their commit decision, which makes the behavior of the commit
+//! rule on real data visible, e.g. with `dot -Tsvg dag.dot > dag.svg`.
+
+use crate::{
+    commit_log::{CommitAuditEntry, CommitOutcome},
+    dag_store::{DagStore, DagStoreError},
+    types::{Certificate, CertificateDigest, Round},
+};
+use reth_narwhal_verifier::AuthorityIndex;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    ops::RangeInclusive,
+};
+
+/// The number of audit entries read from the store at once.
+const AUDIT_PAGE: usize = 1_000;
+
+/// A range of rounds of a DAG, ready to be rendered with [`DagDot::render`].
+#[derive(Debug, Clone, Default)]
+pub struct DagDot {
+    certificates: Vec<Certificate>,
+    /// The round of the last committed certificate of each authority.
+    last_committed: BTreeMap<AuthorityIndex, Round>,
+    /// The commit decisions of the leader rounds, by the digest of the leader's certificate.
+    leaders: BTreeMap<CertificateDigest, CommitOutcome>,
+}
+
+impl DagDot {
+    /// Creates the graph of the certificates, of which every certificate of an authority up to its
+    /// last committed round is committed.
+    pub const fn new(
+        certificates: Vec<Certificate>,
+        last_committed: BTreeMap<AuthorityIndex, Round>,
+    ) -> Self {
+        Self { certificates, last_committed, leaders: BTreeMap::new() }
+    }
+
+    /// Reads the rounds of the stored DAG, with the commit decisions of their leaders.
+    ///
+    /// Rounds below the GC round of the store are not stored anymore and are missing from the
+    /// graph.
+    pub fn from_store(
+        store: &dyn DagStore,
+        rounds: RangeInclusive<Round>,
+    ) -> Result<Self, DagStoreError> {
+        let certificates = store
+            .certificates(*rounds.start())?
+            .into_iter()
+            .filter(|certificate| certificate.round() <= *rounds.end())
+            .collect();
+        let mut dot = Self::new(certificates, store.last_committed()?);
+
+        let mut from_index = 0;
+        loop {
+            let entries = store.audit_entries(from_index, AUDIT_PAGE)?;
+            let Some(last) = entries.last() else { break };
+            from_index = last.index + 1;
+            dot = dot.with_decisions(entries.iter().filter(|entry| rounds.contains(&entry.round)));
+        }
+        Ok(dot)
+    }
+
+    /// Highlights the leaders of the audit entries by the outcome of their commit decision.
+    ///
+    /// A later entry of the same leader, e.g. an indirect commit of a skipped leader, replaces the
+    /// earlier one.
+    pub fn with_decisions<'a>(
+        mut self,
+        entries: impl IntoIterator<Item = &'a CommitAuditEntry>,
+    ) -> Self {
+        for entry in entries {
+            if entry.leader_certificate != CertificateDigest::default() {
+                self.leaders.insert(entry.leader_certificate, entry.outcome);
+            }
+        }
+        self
+    }
+
+    /// Renders the graph in the DOT language.
+    pub fn render(&self) -> String {
+        let rendered = self.certificates.iter().map(Certificate::digest).collect::<HashSet<_>>();
+        let mut rounds = BTreeMap::<Round, Vec<&Certificate>>::new();
+        for certificate in &self.certificates {
+            rounds.entry(certificate.round()).or_default().push(certificate);
+        }
+
+        let mut dot = String::new();
+        // writing to a string can't fail
+        let _ = self.write(&mut dot, &rounds, &rendered);
+        dot
+    }
+
+    fn write(
+        &self,
+        dot: &mut String,
+        rounds: &BTreeMap<Round, Vec<&Certificate>>,
+        rendered: &HashSet<CertificateDigest>,
+    ) -> std::fmt::Result {
+        writeln!(dot, "digraph dag {{")?;
+        writeln!(dot, "  rankdir=BT;")?;
+        writeln!(dot, "  node [shape=box, style=\"rounded,filled\", fontname=monospace];")?;
+        for (round, certificates) in rounds {
+            writeln!(dot, "  subgraph round_{round} {{")?;
+            writeln!(dot, "    rank=same;")?;
+            for certificate in certificates {
+                let digest = certificate.digest();
+                let committed = self
+                    .last_committed
+                    .get(&certificate.author())
+                    .is_some_and(|&committed| certificate.round() <= committed);
+                let fill = if committed { "lightblue" } else { "white" };
+                let (leader, style) = match self.leaders.get(&digest) {
+                    Some(outcome) => {
+                        let color = match outcome {
+                            CommitOutcome::Committed { .. } => "gold",
+                            CommitOutcome::CommittedIndirectly { .. } => "orange",
+                            _ => "red",
+                        };
+                        ("\\nleader", format!(", color={color}, penwidth=3"))
+                    }
+                    None => ("", String::new()),
+                };
+                let label = format!(
+                    "r{} a{}\\n{}{leader}",
+                    certificate.round(),
+                    certificate.author(),
+                    &digest.0.to_string()[..10]
+                );
+                writeln!(
+                    dot,
+                    "    \"{}\" [label=\"{label}\", fillcolor={fill}{style}];",
+                    digest.0
+                )?;
+            }
+            writeln!(dot, "  }}")?;
+        }
+        for certificate in rounds.values().flatten() {
+            for parent in certificate.header.parents.iter().filter(|p| rendered.contains(p)) {
+                writeln!(dot, "  \"{}\" -> \"{}\";", certificate.digest().0, parent.0)?;
+            }
+        }
+        writeln!(dot, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dag_store::MemoryDagStore, types::Header};
+    use alloy_primitives::B256;
+
+    fn certificate(round: Round, author: AuthorityIndex, parents: &[&Certificate]) -> Certificate {
+        Certificate {
+            header: Header {
+                round,
+                author,
+                parents: parents.iter().map(|parent| parent.digest()).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn render_stored_dag() {
+        let store = MemoryDagStore::default();
+        let first = [certificate(1, 0, &[]), certificate(1, 1, &[])];
+        let leader = certificate(2, 0, &[&first[0], &first[1]]);
+        let other = certificate(2, 1, &[&first[0], &first[1]]);
+        let top = certificate(3, 0, &[&leader, &other]);
+        for certificate in first.iter().chain([&leader, &other, &top]) {
+            store.write_certificate(certificate).unwrap();
+        }
+        store.write_last_committed(0, 2).unwrap();
+        store.write_last_committed(1, 1).unwrap();
+        let entry = CommitAuditEntry {
+            index: 0,
+            parent: B256::ZERO,
+            epoch: 0,
+            round: 2,
+            leader: 0,
+            leader_certificate: leader.digest(),
+            outcome: CommitOutcome::Committed { support: 2 },
+            supporters: Vec::new(),
+        };
+        store.write_audit_entry(&entry).unwrap();
+
+        let dot = DagDot::from_store(&store, 2..=3).unwrap().render();
+        assert!(dot.starts_with("digraph dag {"));
+        // the rounds outside the range are not rendered
+        assert!(!dot.contains(&first[0].digest().0.to_string()));
+        assert_eq!(dot.matches("rank=same").count(), 2);
+
+        let node = |certificate: &Certificate| {
+            dot.lines()
+                .find(|line| line.contains(&format!("\"{}\" [", certificate.digest().0)))
+                .unwrap()
+                .to_string()
+        };
+        assert!(node(&leader).contains("fillcolor=lightblue, color=gold"));
+        assert!(node(&other).contains("fillcolor=white];"));
+        assert!(node(&top).contains("fillcolor=white];"));
+        assert!(dot.contains(&format!("\"{}\" -> \"{}\";", top.digest().0, leader.digest().0)));
+        assert_eq!(dot.matches(" -> ").count(), 2);
+    }
+}
@@ -0,0 +1,1004 @@
+//! Reconfiguration of the committee at epoch boundaries.
+//!
+//! The committee of the next epoch is scheduled with an [`EpochChange`], which a
+//! [`CommitteeSource`] reads from on-chain state or from a file the operator replaces. The change
+//! names a boundary round: the first committed leader at or above it is the last leader of the
+//! epoch, and everything the committee of the epoch commits after it is discarded, so that every
+//! validator ends the epoch with the same block.
+//!
+//! The [`EpochManager`] then halts the primary, workers and committer of the epoch, swaps the
+//! committee of its [`EpochCommitteeProvider`] and starts them again for the new epoch, whose DAG
+//! begins with the genesis certificates of the new committee. The node keeps running, and the
+//! sub-dags of all epochs reach the executor through the same channel. The batches of the node
+//! that the halted epoch didn't commit are proposed again in the next epoch, see
+//! [`UncommittedBatches`].
+//!
+//! With [`EpochSnapshots`], the manager snapshots the consensus state before it applies a change,
+//! so that a botched change can be rolled back, see [`crate::epoch_snapshot`].
+
+use crate::{
+    backpressure::ExecutionLag,
+    committee::{load_file, Committee, CommitteeError, CommitteeProvider},
+    dag_store::DagStore,
+    epoch_snapshot::{EpochSnapshot, EpochSnapshots},
+    events::{NarwhalEvent, NarwhalEvents},
+    gc::{gc_round, DEFAULT_GC_DEPTH},
+    recovery::CommittedSubDags,
+    rpc::ConsensusState,
+    shutdown::NarwhalShutdown,
+    types::{BatchDigest, Certificate, OrderedSubDag, Round},
+    worker::SealedBatch,
+};
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use reth_narwhal_verifier::Epoch;
+use reth_tasks::shutdown::{GracefulShutdown, GracefulShutdownGuard};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock},
+    time::Duration,
+};
+use tokio::sync::{mpsc, Notify};
+use tracing::{error, info, warn};
+
+/// The default interval in which the [`CommitteeSource`] is checked for the next committee.
+pub const DEFAULT_EPOCH_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A scheduled change to the committee of the next epoch.
+///
+/// Like the [`Committee`], it's loaded from a TOML or JSON file, e.g.
+///
+/// ```toml
+/// boundaryRound = 10000
+///
+/// [committee]
+/// epoch = 4
+///
+/// [[committee.authorities]]
+/// publicKey = "0x8f2d…"
+/// proofOfPossession = "0xa41c…"
+/// stake = 1
+/// primaryAddress = "10.0.0.1:30400"
+/// workerAddresses = ["10.0.0.1:30401"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochChange {
+    /// The first committed leader at or above this round is the last leader of the current epoch.
+    pub boundary_round: Round,
+    /// The committee of the next epoch.
+    pub committee: Committee,
+}
+
+impl EpochChange {
+    /// Loads and validates an epoch change from a file.
+    ///
+    /// Files with a `.toml` extension are parsed as TOML, all others as JSON.
+    pub fn load(path: &Path) -> Result<Self, CommitteeError> {
+        let change: Self = load_file(path)?;
+        change.committee.validate()?;
+        Ok(change)
+    }
+
+    /// Returns `true` if the commit of a leader of the given round ends the current epoch.
+    pub const fn ends_epoch(&self, leader_round: Round) -> bool {
+        leader_round >= self.boundary_round
+    }
+}
+
+/// Provides the committee of the next epoch, e.g. from a staking contract.
+///
+/// Validators must learn about a change well before its boundary round, a change whose boundary
+/// round was already committed is ignored.
+pub trait CommitteeSource: Debug + Send + Sync {
+    /// Returns the change from `epoch` to the next epoch, if it's scheduled.
+    fn epoch_change(&self, epoch: Epoch) -> Result<Option<EpochChange>, CommitteeError>;
+}
+
+/// A [`CommitteeSource`] that reads the [`EpochChange`] from a file.
+///
+/// The operator schedules the next epoch by replacing the file. A missing file, or a file with the
+/// committee of another epoch, doesn't schedule a change.
+#[derive(Debug, Clone)]
+pub struct FileCommitteeSource {
+    path: PathBuf,
+}
+
+impl FileCommitteeSource {
+    /// Creates a source that reads the given file.
+    pub const fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl CommitteeSource for FileCommitteeSource {
+    fn epoch_change(&self, epoch: Epoch) -> Result<Option<EpochChange>, CommitteeError> {
+        if !self.path.exists() {
+            return Ok(None)
+        }
+        let change = EpochChange::load(&self.path)?;
+        Ok((Some(change.committee.epoch) == epoch.checked_add(1)).then_some(change))
+    }
+}
+
+/// A [`CommitteeProvider`] whose committee is advanced by the [`EpochManager`].
+///
+/// It keeps the committees of all past epochs, so that certificates of earlier epochs can still be
+/// verified.
+#[derive(Debug, Clone)]
+pub struct EpochCommitteeProvider {
+    committees: Arc<RwLock<BTreeMap<Epoch, Arc<Committee>>>>,
+}
+
+impl EpochCommitteeProvider {
+    /// Creates a provider whose current committee is the given one.
+    pub fn new(committee: Committee) -> Self {
+        let committees = BTreeMap::from([(committee.epoch, Arc::new(committee))]);
+        Self { committees: Arc::new(RwLock::new(committees)) }
+    }
+
+    /// Returns the current epoch.
+    pub fn current_epoch(&self) -> Epoch {
+        self.current_committee().epoch
+    }
+
+    /// Makes the given committee the current one.
+    fn advance(&self, committee: Committee) -> Arc<Committee> {
+        let committee = Arc::new(committee);
+        self.committees
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(committee.epoch, Arc::clone(&committee));
+        committee
+    }
+}
+
+impl CommitteeProvider for EpochCommitteeProvider {
+    fn current_committee(&self) -> Arc<Committee> {
+        let committees = self.committees.read().unwrap_or_else(PoisonError::into_inner);
+        let (_, committee) = committees.last_key_value().expect("at least one committee");
+        Arc::clone(committee)
+    }
+
+    fn committee(&self, epoch: Epoch) -> Option<Arc<Committee>> {
+        self.committees.read().unwrap_or_else(PoisonError::into_inner).get(&epoch).cloned()
+    }
+}
+
+/// The start of an epoch, see [`EpochTasks::start`].
+#[derive(Debug)]
+pub struct EpochStart<'a> {
+    /// The committee of the epoch.
+    pub committee: Arc<Committee>,
+    /// The index of the first sub-dag the epoch commits.
+    pub first_sub_dag: u64,
+    /// Halts the tasks of the epoch, every task listens on the signal of its
+    /// [`ShutdownStage`](crate::shutdown::ShutdownStage).
+    pub halt: &'a NarwhalShutdown,
+}
+
+/// Starts the tasks that run for the duration of one epoch.
+pub trait EpochTasks: Send {
+    /// Starts the primary, workers and committer of an epoch.
+    ///
+    /// Returns the receiver of the sub-dags the committer commits, in commit order. The tasks must
+    /// stop once the signal of their stage is fired.
+    fn start(&mut self, epoch: EpochStart<'_>) -> mpsc::Receiver<OrderedSubDag>;
+}
+
+/// The batches the workers of the node sealed that no forwarded sub-dag committed yet.
+///
+/// The DAG of an epoch ends with the epoch, so a batch that was still pending in the primary, or
+/// whose certificate was committed after the last leader of the epoch, would never be executed.
+/// The [`EpochManager`] removes the batches of every sub-dag it forwards, and the [`EpochTasks`]
+/// of the next epoch hand the remaining batches to their primary again with
+/// [`UncommittedBatches::forward`], before the batches sealed in the new epoch.
+///
+/// Within an epoch, a certificate that no later round references is never committed. Once such a
+/// certificate is below the GC round of the last committed leader, its batches are handed to the
+/// primary again as well.
+///
+/// Cloning is cheap, all clones share the same batches.
+#[derive(Debug, Clone)]
+pub struct UncommittedBatches {
+    inner: Arc<Mutex<UncommittedBatchesInner>>,
+    orphaned: Arc<Notify>,
+    gc_depth: Round,
+    /// The store the batches are written to again before they are handed to a primary.
+    store: Option<Arc<dyn DagStore>>,
+}
+
+#[derive(Debug, Default)]
+struct UncommittedBatchesInner {
+    /// The batches by the sequence number of their sealing.
+    batches: BTreeMap<u64, UncommittedBatch>,
+    sequence: HashMap<BatchDigest, u64>,
+    next: u64,
+    /// The batches whose certificates were garbage collected without a commit.
+    orphaned: Vec<SealedBatch>,
+}
+
+#[derive(Debug)]
+struct UncommittedBatch {
+    batch: SealedBatch,
+    /// The round of the certificate of the current epoch that includes the batch, if any.
+    certified: Option<Round>,
+}
+
+impl UncommittedBatches {
+    /// Creates an empty set for a committer with the given GC depth.
+    pub fn new(gc_depth: Round) -> Self {
+        Self { inner: Default::default(), orphaned: Default::default(), gc_depth, store: None }
+    }
+
+    /// Writes every batch to the store again before it's handed to a primary, since the
+    /// [`DagPruner`](crate::gc::DagPruner) removes the batches of garbage collected certificates.
+    pub fn with_store(mut self, store: Arc<dyn DagStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Returns the number of uncommitted batches.
+    pub fn len(&self) -> usize {
+        self.lock().batches.len()
+    }
+
+    /// Returns `true` if all batches were committed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, UncommittedBatchesInner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Tracks a sealed batch until a sub-dag commits it.
+    pub fn insert(&self, batch: SealedBatch) {
+        let mut inner = self.lock();
+        if inner.sequence.contains_key(&batch.digest) {
+            return
+        }
+        let next = inner.next;
+        inner.next += 1;
+        inner.sequence.insert(batch.digest, next);
+        inner.batches.insert(next, UncommittedBatch { batch, certified: None });
+    }
+
+    /// Records the round of a certificate of the current epoch, e.g. from the
+    /// [`Committer`](crate::committer::Committer).
+    pub fn on_certified(&self, certificate: &Certificate) {
+        let mut inner = self.lock();
+        let inner = &mut *inner;
+        for batch in &certificate.header.payload {
+            if let Some(sequence) = inner.sequence.get(&batch.digest) {
+                if let Some(uncommitted) = inner.batches.get_mut(sequence) {
+                    uncommitted.certified = Some(certificate.round());
+                }
+            }
+        }
+    }
+
+    /// Removes the batches of a forwarded sub-dag, batches of other authorities are ignored.
+    ///
+    /// The batches of certificates below the GC round of its leader can no longer be committed,
+    /// they are handed to the running [`UncommittedBatches::forward`] again.
+    pub fn on_committed(&self, sub_dag: &OrderedSubDag) {
+        let mut inner = self.lock();
+        let inner = &mut *inner;
+        for batch in sub_dag.certificates.iter().flat_map(|certificate| &certificate.header.payload)
+        {
+            if let Some(sequence) = inner.sequence.remove(&batch.digest) {
+                inner.batches.remove(&sequence);
+            }
+        }
+
+        let gc_round = gc_round(sub_dag.leader_round(), self.gc_depth);
+        for uncommitted in inner.batches.values_mut() {
+            if uncommitted.certified.is_some_and(|round| round < gc_round) {
+                uncommitted.certified = None;
+                inner.orphaned.push(uncommitted.batch.clone());
+            }
+        }
+        if !inner.orphaned.is_empty() {
+            self.orphaned.notify_one();
+        }
+    }
+
+    /// Returns the uncommitted batches in the order they were sealed.
+    pub fn batches(&self) -> Vec<SealedBatch> {
+        self.lock().batches.values().map(|uncommitted| uncommitted.batch.clone()).collect()
+    }
+
+    /// Returns all uncommitted batches for the primary of a new epoch, whose DAG has none of
+    /// their certificates.
+    fn restart(&self) -> Vec<SealedBatch> {
+        let mut inner = self.lock();
+        inner.orphaned.clear();
+        inner
+            .batches
+            .values_mut()
+            .map(|uncommitted| {
+                uncommitted.certified = None;
+                uncommitted.batch.clone()
+            })
+            .collect()
+    }
+
+    /// Hands the uncommitted batches to the primary of a new epoch, and then tracks and forwards
+    /// the batches the workers seal, and the orphaned batches of the epoch, until the workers
+    /// stop.
+    ///
+    /// The future must not be dropped when the epoch is halted, it completes once the senders of
+    /// the sealed batches are dropped. Batches that were sealed after the primary stopped are
+    /// tracked for the next epoch.
+    pub async fn forward(
+        self,
+        mut sealed: mpsc::Receiver<SealedBatch>,
+        primary: mpsc::Sender<SealedBatch>,
+    ) {
+        let mut stopped = !self.resend(&primary, self.restart()).await;
+        loop {
+            tokio::select! {
+                batch = sealed.recv() => {
+                    let Some(batch) = batch else { return };
+                    self.insert(batch.clone());
+                    if !stopped && primary.send(batch).await.is_err() {
+                        stopped = true;
+                    }
+                }
+                () = self.orphaned.notified(), if !stopped => {
+                    let orphaned = std::mem::take(&mut self.lock().orphaned);
+                    stopped = !self.resend(&primary, orphaned).await;
+                }
+            }
+        }
+    }
+
+    /// Hands batches to the primary again, returns `false` if the primary stopped.
+    async fn resend(&self, primary: &mpsc::Sender<SealedBatch>, batches: Vec<SealedBatch>) -> bool {
+        for batch in batches {
+            if let Some(store) = &self.store {
+                if let Err(err) = store.write_batch(batch.digest, &batch.batch) {
+                    error!(
+                        target: "consensus::narwhal",
+                        %err,
+                        digest = %batch.digest,
+                        "Failed to store uncommitted batch"
+                    );
+                }
+            }
+            if primary.send(batch).await.is_err() {
+                return false
+            }
+        }
+        true
+    }
+}
+
+impl Default for UncommittedBatches {
+    fn default() -> Self {
+        Self::new(DEFAULT_GC_DEPTH)
+    }
+}
+
+/// Metrics of the [`EpochManager`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.epoch")]
+struct EpochManagerMetrics {
+    /// The current epoch
+    epoch: Gauge,
+    /// Number of completed epoch changes
+    epoch_changes: Counter,
+    /// Number of ignored epoch changes
+    ignored_changes: Counter,
+    /// Number of failed reads of the committee source
+    failed_polls: Counter,
+    /// Number of epoch changes applied without a snapshot because it couldn't be written
+    failed_snapshots: Counter,
+    /// Number of committed sub-dags that couldn't be recorded for recovery
+    failed_records: Counter,
+}
+
+/// How an epoch ended.
+#[derive(Debug)]
+enum EpochEnd {
+    /// The boundary of the change was committed by the leader of the given round.
+    Change(EpochChange, Round),
+    /// The node shuts down, the guard is held until the tasks of the epoch are halted.
+    Shutdown(GracefulShutdownGuard),
+    /// The tasks of the epoch or the executor stopped.
+    Stopped,
+}
+
+/// The task that runs the tasks of the current epoch and restarts them with the committee of the
+/// next epoch.
+#[derive(Debug)]
+pub struct EpochManager<T> {
+    provider: EpochCommitteeProvider,
+    source: Arc<dyn CommitteeSource>,
+    tasks: T,
+    /// Receives the sub-dags of all epochs.
+    output: mpsc::Sender<OrderedSubDag>,
+    /// The index of the next committed sub-dag.
+    next_sub_dag: u64,
+    poll_interval: Duration,
+    /// Records the commits for the RPC.
+    state: Option<ConsensusState>,
+    /// Snapshots the state before an epoch change, with the store of the DAG.
+    snapshots: Option<(EpochSnapshots, Arc<dyn DagStore>)>,
+    /// Records the commits for the back-pressure of the producers.
+    execution_lag: Option<ExecutionLag>,
+    /// Records the commits for the recovery of unexecuted sub-dags.
+    committed: Option<CommittedSubDags>,
+    /// Publishes the commits to in-process subscribers.
+    events: Option<NarwhalEvents>,
+    /// Releases the batches of the node once they're committed.
+    uncommitted: Option<UncommittedBatches>,
+    /// The store whose votes are cleared at every epoch change.
+    votes: Option<Arc<dyn DagStore>>,
+    metrics: EpochManagerMetrics,
+}
+
+impl<T: EpochTasks> EpochManager<T> {
+    /// Creates a manager that starts with the given committee and forwards the committed sub-dags
+    /// to `output`.
+    ///
+    /// Returns the manager and the provider of the committees it switches to, for the components
+    /// that outlive an epoch.
+    pub fn new(
+        committee: Committee,
+        source: Arc<dyn CommitteeSource>,
+        tasks: T,
+        output: mpsc::Sender<OrderedSubDag>,
+    ) -> (Self, EpochCommitteeProvider) {
+        let provider = EpochCommitteeProvider::new(committee);
+        let manager = Self::from_provider(provider.clone(), source, tasks, output);
+        (manager, provider)
+    }
+
+    /// Creates a manager that starts with the current committee of the provider, e.g. one that was
+    /// created before the manager to serve the committees to the RPC.
+    pub fn from_provider(
+        provider: EpochCommitteeProvider,
+        source: Arc<dyn CommitteeSource>,
+        tasks: T,
+        output: mpsc::Sender<OrderedSubDag>,
+    ) -> Self {
+        Self {
+            provider,
+            source,
+            tasks,
+            output,
+            next_sub_dag: 0,
+            poll_interval: DEFAULT_EPOCH_POLL_INTERVAL,
+            state: None,
+            snapshots: None,
+            execution_lag: None,
+            committed: None,
+            events: None,
+            uncommitted: None,
+            votes: None,
+            metrics: EpochManagerMetrics::default(),
+        }
+    }
+
+    /// Sets the index of the first sub-dag, the index after the last executed one.
+    pub const fn with_next_sub_dag(mut self, next_sub_dag: u64) -> Self {
+        self.next_sub_dag = next_sub_dag;
+        self
+    }
+
+    /// Sets the interval in which the source is checked for the next committee.
+    pub const fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Records every commit in the state served by the RPC.
+    pub fn with_consensus_state(mut self, state: ConsensusState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Snapshots the consensus state before every epoch change.
+    pub fn with_snapshots(mut self, snapshots: EpochSnapshots, store: Arc<dyn DagStore>) -> Self {
+        self.snapshots = Some((snapshots, store));
+        self
+    }
+
+    /// Reports every commit to the lag of execution, which must also be shared with the executor.
+    pub fn with_execution_lag(mut self, execution_lag: ExecutionLag) -> Self {
+        self.execution_lag = Some(execution_lag);
+        self
+    }
+
+    /// Records every commit before it's forwarded, so that the executor can replay the sub-dags
+    /// that were not executed before a restart.
+    pub fn with_committed_sub_dags(mut self, committed: CommittedSubDags) -> Self {
+        self.committed = Some(committed);
+        self
+    }
+
+    /// Publishes every commit of all epochs.
+    ///
+    /// The tasks of an epoch publish the events of its primary and workers themselves.
+    pub fn with_events(mut self, events: NarwhalEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Releases the batches of every forwarded sub-dag, which must be shared with the tasks of the
+    /// epochs.
+    pub fn with_uncommitted_batches(mut self, uncommitted: UncommittedBatches) -> Self {
+        self.uncommitted = Some(uncommitted);
+        self
+    }
+
+    /// Clears the votes in the store the primaries of the epochs write their votes to at every
+    /// epoch change.
+    ///
+    /// Votes are keyed by round and author, so a vote of the last epoch would otherwise keep the
+    /// primary from proposing or voting for the header of the same round in the next epoch.
+    pub fn with_votes(mut self, store: Arc<dyn DagStore>) -> Self {
+        self.votes = Some(store);
+        self
+    }
+
+    /// Runs the epochs until the node shuts down, or the tasks of an epoch or the receiver of the
+    /// sub-dags stop.
+    pub async fn run(mut self, mut shutdown: GracefulShutdown) {
+        loop {
+            let committee = self.provider.current_committee();
+            let epoch = committee.epoch;
+            self.metrics.epoch.set(epoch as f64);
+            info!(
+                target: "consensus::narwhal",
+                epoch,
+                first_sub_dag = self.next_sub_dag,
+                "Starting epoch"
+            );
+
+            let halt = NarwhalShutdown::default();
+            let mut sub_dags = self.tasks.start(EpochStart {
+                committee,
+                first_sub_dag: self.next_sub_dag,
+                halt: &halt,
+            });
+            let end = self.run_epoch(epoch, &mut sub_dags, &mut shutdown).await;
+
+            // later commits of the epoch are discarded, and the committer doesn't block on them
+            drop(sub_dags);
+            if !halt.shutdown().await {
+                warn!(target: "consensus::narwhal", epoch, "Tasks of epoch did not halt in time");
+            }
+
+            match end {
+                EpochEnd::Change(change, last_leader_round) => {
+                    self.snapshot(&change, last_leader_round);
+                    if let Some(Err(err)) = self.votes.as_ref().map(|store| store.clear_votes()) {
+                        error!(target: "consensus::narwhal", %err, epoch, "Failed to clear votes");
+                    }
+                    let committee = self.provider.advance(change.committee);
+                    self.metrics.epoch_changes.increment(1);
+                    info!(
+                        target: "consensus::narwhal",
+                        epoch = committee.epoch,
+                        authorities = committee.authorities.len(),
+                        "Changed committee"
+                    );
+                }
+                EpochEnd::Shutdown(guard) => {
+                    drop(guard);
+                    return
+                }
+                EpochEnd::Stopped => return,
+            }
+        }
+    }
+
+    /// Forwards the sub-dags of the epoch until the boundary of a scheduled change is committed.
+    async fn run_epoch(
+        &mut self,
+        epoch: Epoch,
+        sub_dags: &mut mpsc::Receiver<OrderedSubDag>,
+        shutdown: &mut GracefulShutdown,
+    ) -> EpochEnd {
+        let start = tokio::time::Instant::now() + self.poll_interval;
+        let mut poll = tokio::time::interval_at(start, self.poll_interval);
+        let mut committed_round = 0;
+        let mut change = self.poll_change(epoch, committed_round);
+        loop {
+            tokio::select! {
+                guard = &mut *shutdown => return EpochEnd::Shutdown(guard),
+                _ = poll.tick(), if change.is_none() => {
+                    change = self.poll_change(epoch, committed_round);
+                }
+                sub_dag = sub_dags.recv() => {
+                    let Some(sub_dag) = sub_dag else { return EpochEnd::Stopped };
+                    committed_round = sub_dag.leader_round();
+                    self.next_sub_dag = sub_dag.index + 1;
+                    if let Some(state) = &self.state {
+                        state.record_commit(&sub_dag);
+                    }
+                    if let Some(events) = &self.events {
+                        events.publish(NarwhalEvent::SubDagCommitted((&sub_dag).into()));
+                    }
+                    if let Some(execution_lag) = &self.execution_lag {
+                        execution_lag.on_committed(sub_dag.index);
+                    }
+                    if let Some(uncommitted) = &self.uncommitted {
+                        uncommitted.on_committed(&sub_dag);
+                    }
+                    self.record(&sub_dag);
+                    if self.output.send(sub_dag).await.is_err() {
+                        return EpochEnd::Stopped
+                    }
+                    change = match change {
+                        Some(change) if change.ends_epoch(committed_round) => {
+                            info!(
+                                target: "consensus::narwhal",
+                                epoch,
+                                round = committed_round,
+                                "Committed last leader of epoch"
+                            );
+                            return EpochEnd::Change(change, committed_round)
+                        }
+                        change => change,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Records a committed sub-dag for recovery.
+    ///
+    /// A sub-dag that can't be recorded is still executed, it's only lost if the node stops before
+    /// its execution.
+    fn record(&self, sub_dag: &OrderedSubDag) {
+        let Some(committed) = &self.committed else { return };
+        if let Err(err) = committed.record(sub_dag) {
+            self.metrics.failed_records.increment(1);
+            error!(
+                target: "consensus::narwhal",
+                %err,
+                sub_dag = sub_dag.index,
+                "Failed to record committed sub-dag"
+            );
+        }
+    }
+
+    /// Writes the snapshot of the current epoch before the change is applied.
+    ///
+    /// A snapshot that can't be written doesn't hold back the change, which every other validator
+    /// applies as well.
+    fn snapshot(&self, change: &EpochChange, last_leader_round: Round) {
+        let Some((snapshots, store)) = &self.snapshots else { return };
+        let committee = self.provider.current_committee();
+        let epoch = committee.epoch;
+        let result =
+            store.last_committed().map_err(|err| err.to_string()).and_then(|last_committed| {
+                let snapshot = EpochSnapshot {
+                    committee: Committee::clone(&committee),
+                    change: change.clone(),
+                    last_leader_round,
+                    next_sub_dag: self.next_sub_dag,
+                    last_committed,
+                };
+                snapshots.save(&snapshot).map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(()) => info!(target: "consensus::narwhal", epoch, "Saved epoch snapshot"),
+            Err(err) => {
+                self.metrics.failed_snapshots.increment(1);
+                error!(target: "consensus::narwhal", %err, epoch, "Failed to save epoch snapshot");
+            }
+        }
+    }
+
+    /// Reads the change from `epoch` to the next epoch from the source.
+    ///
+    /// Changes for another epoch, with an invalid committee or with a boundary round that was
+    /// already committed are ignored.
+    fn poll_change(&self, epoch: Epoch, committed_round: Round) -> Option<EpochChange> {
+        let change = match self.source.epoch_change(epoch) {
+            Ok(change) => change?,
+            Err(err) => {
+                self.metrics.failed_polls.increment(1);
+                warn!(
+                    target: "consensus::narwhal",
+                    %err,
+                    epoch,
+                    "Failed to read the next committee"
+                );
+                return None
+            }
+        };
+
+        let next_epoch = change.committee.epoch;
+        let reason = if Some(next_epoch) != epoch.checked_add(1) {
+            "the committee is not of the next epoch".to_string()
+        } else if let Err(err) = change.committee.validate() {
+            err.to_string()
+        } else if change.boundary_round <= committed_round {
+            format!("round {committed_round} was already committed")
+        } else {
+            info!(
+                target: "consensus::narwhal",
+                epoch = next_epoch,
+                boundary_round = change.boundary_round,
+                "Scheduled epoch change"
+            );
+            return Some(change)
+        };
+        self.metrics.ignored_changes.increment(1);
+        warn!(
+            target: "consensus::narwhal",
+            epoch = next_epoch,
+            boundary_round = change.boundary_round,
+            %reason,
+            "Ignoring epoch change"
+        );
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        committee::Authority,
+        dag_store::MemoryDagStore,
+        shutdown::ShutdownStage,
+        signature::{AggregateScheme, Bls12381, BlsSecretKey},
+        types::Header,
+        worker::{BatchBuilder, BatchConfig},
+    };
+    use alloy_primitives::{keccak256, Bytes};
+    use reth_tasks::TaskManager;
+    use std::sync::Mutex;
+
+    fn committee(epoch: Epoch) -> Committee {
+        let secret_key = BlsSecretKey::from_seed(&[epoch as u8 + 1; 32]).unwrap();
+        let authority = Authority {
+            public_key: Bytes::copy_from_slice(&Bls12381::public_key(&secret_key).to_bytes()),
+            proof_of_possession: Bytes::copy_from_slice(
+                &secret_key.proof_of_possession().to_bytes(),
+            ),
+            stake: 1,
+            primary_address: "127.0.0.1:30400".parse().unwrap(),
+            worker_addresses: Vec::new(),
+        };
+        Committee { epoch, authorities: vec![authority] }
+    }
+
+    fn sub_dag(epoch: Epoch, index: u64, round: Round) -> OrderedSubDag {
+        let header = Header { epoch, round, ..Default::default() };
+        OrderedSubDag {
+            index,
+            leader: Certificate { header, ..Default::default() },
+            certificates: Vec::new(),
+            batches: Vec::new(),
+            timestamp: 0,
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct ScheduledChanges(Mutex<Vec<EpochChange>>);
+
+    impl CommitteeSource for ScheduledChanges {
+        fn epoch_change(&self, epoch: Epoch) -> Result<Option<EpochChange>, CommitteeError> {
+            let changes = self.0.lock().unwrap();
+            Ok(changes.iter().find(|change| change.committee.epoch == epoch + 1).cloned())
+        }
+    }
+
+    /// Commits a leader in every even round, until it's halted.
+    #[derive(Debug, Default)]
+    struct Committer {
+        started: Arc<Mutex<Vec<(Epoch, u64)>>>,
+    }
+
+    impl EpochTasks for Committer {
+        fn start(&mut self, epoch: EpochStart<'_>) -> mpsc::Receiver<OrderedSubDag> {
+            let EpochStart { committee, first_sub_dag, halt } = epoch;
+            self.started.lock().unwrap().push((committee.epoch, first_sub_dag));
+            let mut halt = halt.on_shutdown(ShutdownStage::Committer);
+            let (sub_dags, sub_dags_rx) = mpsc::channel(1);
+            tokio::spawn(async move {
+                for (index, round) in (first_sub_dag..).zip((2..).step_by(2)) {
+                    tokio::select! {
+                        guard = &mut halt => {
+                            drop(guard);
+                            return
+                        }
+                        sent = sub_dags.send(sub_dag(committee.epoch, index, round)) => {
+                            if sent.is_err() {
+                                // wait for the halt signal once the epoch is over
+                                drop(halt.await);
+                                return
+                            }
+                        }
+                    }
+                }
+            });
+            sub_dags_rx
+        }
+    }
+
+    #[test]
+    fn change_committee_at_boundary() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let task_manager = TaskManager::new(runtime.handle().clone());
+        let source = Arc::new(ScheduledChanges::default());
+        source.0.lock().unwrap().push(EpochChange { boundary_round: 7, committee: committee(1) });
+        let tasks = Committer::default();
+        let started = Arc::clone(&tasks.started);
+        let (output, mut sub_dags) = mpsc::channel(1);
+        let (manager, provider) = EpochManager::new(committee(0), source, tasks, output);
+        let dir = tempfile::tempdir().unwrap();
+        let snapshots = EpochSnapshots::open(dir.path()).unwrap();
+        let store = Arc::new(MemoryDagStore::default());
+        store.write_last_committed(0, 8).unwrap();
+        let manager = manager
+            .with_next_sub_dag(5)
+            .with_snapshots(snapshots.clone(), store.clone())
+            .with_committed_sub_dags(CommittedSubDags::new(store.clone()));
+        let _guard = runtime.enter();
+        task_manager
+            .executor()
+            .spawn_critical_with_graceful_shutdown_signal("epochs", |shutdown| {
+                manager.run(shutdown)
+            });
+
+        runtime.block_on(async {
+            for round in [2, 4, 6, 8] {
+                let sub_dag = sub_dags.recv().await.unwrap();
+                assert_eq!((sub_dag.epoch(), sub_dag.leader_round()), (0, round));
+            }
+            // the next epoch starts at round 2 with the index after the last leader of epoch 0
+            let sub_dag = sub_dags.recv().await.unwrap();
+            assert_eq!((sub_dag.epoch(), sub_dag.index, sub_dag.leader_round()), (1, 9, 2));
+        });
+        assert_eq!(provider.current_epoch(), 1);
+        assert_eq!(provider.committee(0), Some(Arc::new(committee(0))));
+        assert_eq!(*started.lock().unwrap(), [(0, 5), (1, 9)]);
+
+        // the state at the end of epoch 0 was snapshotted before the change
+        let snapshot = snapshots.get(0).unwrap().unwrap();
+        assert_eq!(snapshot.committee, committee(0));
+        assert_eq!(snapshot.change.committee, committee(1));
+        assert_eq!((snapshot.last_leader_round, snapshot.next_sub_dag), (8, 9));
+        assert_eq!(snapshot.last_committed, BTreeMap::from([(0, 8)]));
+
+        // every forwarded sub-dag was recorded for recovery, the next one may be waiting already
+        let recorded = store.sub_dags(0).unwrap();
+        let indices = recorded.iter().map(|sub_dag| sub_dag.index).collect::<Vec<_>>();
+        assert_eq!(indices[..5], [5, 6, 7, 8, 9]);
+
+        drop(sub_dags);
+        assert!(task_manager.graceful_shutdown_with_timeout(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn ignore_invalid_changes() {
+        let source = Arc::new(ScheduledChanges::default());
+        let (output, _sub_dags) = mpsc::channel(1);
+        let (manager, _) =
+            EpochManager::new(committee(0), source.clone(), Committer::default(), output);
+
+        assert_eq!(manager.poll_change(0, 0), None);
+        let change = EpochChange { boundary_round: 10, committee: committee(1) };
+        source.0.lock().unwrap().push(change.clone());
+        assert_eq!(manager.poll_change(0, 9), Some(change));
+        assert_eq!(manager.poll_change(0, 10), None);
+        assert_eq!(manager.poll_change(1, 0), None);
+
+        let mut empty = committee(2);
+        empty.authorities.clear();
+        source.0.lock().unwrap().push(EpochChange { boundary_round: 10, committee: empty });
+        assert_eq!(manager.poll_change(1, 0), None);
+    }
+
+    fn sealed_batch(transaction: u8) -> SealedBatch {
+        let mut builder = BatchBuilder::new(BatchConfig::default(), 0);
+        builder.push(keccak256([transaction]), Bytes::from(vec![transaction]));
+        builder.seal().unwrap()
+    }
+
+    #[tokio::test]
+    async fn carry_over_uncommitted_batches() {
+        let uncommitted = UncommittedBatches::default();
+        let batches = (0..3).map(sealed_batch).collect::<Vec<_>>();
+        let (sealed, sealed_rx) = mpsc::channel(8);
+        let (primary, mut primary_rx) = mpsc::channel(8);
+        let forward = tokio::spawn(uncommitted.clone().forward(sealed_rx, primary));
+        for batch in &batches {
+            sealed.send(batch.clone()).await.unwrap();
+            assert_eq!(primary_rx.recv().await.unwrap().digest, batch.digest);
+        }
+        // a batch is only tracked once
+        sealed.send(batches[0].clone()).await.unwrap();
+        primary_rx.recv().await.unwrap();
+        assert_eq!(uncommitted.len(), 3);
+
+        let mut committed = sub_dag(0, 0, 2);
+        let header = Header { payload: vec![batches[0].batch_ref()], ..Default::default() };
+        committed.certificates.push(Certificate { header, ..Default::default() });
+        uncommitted.on_committed(&committed);
+        assert_eq!(uncommitted.len(), 2);
+
+        // a batch sealed after the primary stopped is kept for the next epoch
+        drop(primary_rx);
+        let late = sealed_batch(3);
+        sealed.send(late.clone()).await.unwrap();
+        drop(sealed);
+        forward.await.unwrap();
+
+        // the primary of the next epoch gets the uncommitted batches before the new ones
+        let (sealed, sealed_rx) = mpsc::channel(8);
+        let (primary, mut primary_rx) = mpsc::channel(8);
+        let next = sealed_batch(4);
+        sealed.send(next.clone()).await.unwrap();
+        drop(sealed);
+        uncommitted.clone().forward(sealed_rx, primary).await;
+        let mut forwarded = Vec::new();
+        while let Ok(batch) = primary_rx.try_recv() {
+            forwarded.push(batch.digest);
+        }
+        assert_eq!(forwarded, [batches[1].digest, batches[2].digest, late.digest, next.digest]);
+        assert_eq!(uncommitted.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn resend_orphaned_batches() {
+        let uncommitted = UncommittedBatches::new(10);
+        let (sealed, sealed_rx) = mpsc::channel(8);
+        let (primary, mut primary_rx) = mpsc::channel(8);
+        tokio::spawn(uncommitted.clone().forward(sealed_rx, primary));
+        let batch = sealed_batch(0);
+        sealed.send(batch.clone()).await.unwrap();
+        primary_rx.recv().await.unwrap();
+
+        let header = Header { round: 3, payload: vec![batch.batch_ref()], ..Default::default() };
+        uncommitted.on_certified(&Certificate { header, ..Default::default() });
+        // the certificate can still be committed above the GC round
+        uncommitted.on_committed(&sub_dag(0, 0, 12));
+        assert!(primary_rx.try_recv().is_err());
+
+        uncommitted.on_committed(&sub_dag(0, 1, 14));
+        assert_eq!(primary_rx.recv().await.unwrap().digest, batch.digest);
+        assert_eq!(uncommitted.len(), 1);
+    }
+
+    #[test]
+    fn load_epoch_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("next-committee.toml");
+        let source = FileCommitteeSource::new(path.clone());
+        assert_eq!(source.epoch_change(3).unwrap(), None);
+
+        let change = EpochChange { boundary_round: 10_000, committee: committee(4) };
+        std::fs::write(&path, toml::to_string(&change).unwrap()).unwrap();
+        let change = source.epoch_change(3).unwrap().unwrap();
+        assert_eq!(change.boundary_round, 10_000);
+        assert_eq!(change.committee.epoch, 4);
+        assert!(change.ends_epoch(10_000));
+        assert!(!change.ends_epoch(9_999));
+        // a stale file doesn't schedule another change
+        assert_eq!(source.epoch_change(4).unwrap(), None);
+
+        // a committee without proofs of possession is rejected
+        let mut change = change;
+        change.committee.authorities[0].proof_of_possession = Bytes::new();
+        std::fs::write(&path, toml::to_string(&change).unwrap()).unwrap();
+        assert!(matches!(
+            source.epoch_change(3),
+            Err(CommitteeError::MissingProofOfPossession { index: 0 })
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+//! Snapshots of the consensus state before an epoch change.
+//!
+//! An epoch change swaps the committee and the parameters every validator runs with, so a botched
+//! change can leave a validator unable to make progress. Before the [`EpochManager`] applies a
+//! change, it writes an [`EpochSnapshot`] with the state needed to undo it: the committee of the
+//! ending epoch, the applied change, the last commit and the cursors of the [`DagStore`].
+//!
+//! `reth narwhal rollback-epoch` restores the latest snapshot on a stopped validator, which then
+//! rejoins the previous epoch and resyncs the DAG from its peers.
+//!
+//! [`EpochManager`]: crate::epoch::EpochManager
+//! [`DagStore`]: crate::dag_store::DagStore
+
+use crate::{committee::Committee, epoch::EpochChange, types::Round};
+use reth_narwhal_verifier::{AuthorityIndex, Epoch};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// The state of a validator at the end of an epoch, before the change to the next epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochSnapshot {
+    /// The committee of the ending epoch.
+    pub committee: Committee,
+    /// The change to the next epoch.
+    pub change: EpochChange,
+    /// The round of the last committed leader of the epoch.
+    pub last_leader_round: Round,
+    /// The index of the first sub-dag of the next epoch.
+    pub next_sub_dag: u64,
+    /// The round of the last committed certificate of each authority.
+    pub last_committed: BTreeMap<AuthorityIndex, Round>,
+}
+
+impl EpochSnapshot {
+    /// Returns the ending epoch.
+    pub const fn epoch(&self) -> Epoch {
+        self.committee.epoch
+    }
+}
+
+/// The snapshots of past epoch changes, persisted as one JSON file per epoch.
+#[derive(Debug, Clone)]
+pub struct EpochSnapshots {
+    dir: PathBuf,
+}
+
+impl EpochSnapshots {
+    /// Opens the snapshots stored in the given directory, creating the directory if it doesn't
+    /// exist.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Persists the snapshot of an epoch, replacing an earlier snapshot of the same epoch.
+    pub fn save(&self, snapshot: &EpochSnapshot) -> io::Result<()> {
+        // write to a temporary file first, so a crash never leaves a partial snapshot behind
+        let path = self.path(snapshot.epoch());
+        let tmp = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp)?;
+        serde_json::to_writer(&mut file, snapshot).map_err(io::Error::from)?;
+        file.write_all(b"\n")?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)
+    }
+
+    /// Returns the snapshot taken at the end of the given epoch.
+    pub fn get(&self, epoch: Epoch) -> io::Result<Option<EpochSnapshot>> {
+        match fs::read(self.path(epoch)) {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents).map_err(io::Error::from)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the snapshot of the latest epoch.
+    pub fn latest(&self) -> io::Result<Option<EpochSnapshot>> {
+        match self.epochs()?.last() {
+            Some(epoch) => self.get(*epoch),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the snapshots of all epochs after the given epoch.
+    pub fn truncate(&self, epoch: Epoch) -> io::Result<()> {
+        for later in self.epochs()?.into_iter().filter(|later| *later > epoch) {
+            fs::remove_file(self.path(later))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the epochs with a snapshot, in ascending order.
+    fn epochs(&self) -> io::Result<Vec<Epoch>> {
+        let mut epochs = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if !path.extension().is_some_and(|extension| extension == "json") {
+                continue
+            }
+            let epoch = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.strip_prefix("epoch-"))
+                .and_then(|epoch| epoch.parse::<Epoch>().ok());
+            epochs.extend(epoch);
+        }
+        epochs.sort_unstable();
+        Ok(epochs)
+    }
+
+    fn path(&self, epoch: Epoch) -> PathBuf {
+        self.dir.join(format!("epoch-{epoch}.json"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev::DevCommittee;
+
+    fn snapshot(epoch: Epoch) -> EpochSnapshot {
+        let mut committee = DevCommittee::new(4).committee().clone();
+        committee.epoch = epoch;
+        let mut next = committee.clone();
+        next.epoch += 1;
+        EpochSnapshot {
+            committee,
+            change: EpochChange { boundary_round: 100, committee: next },
+            last_leader_round: 100,
+            next_sub_dag: 50,
+            last_committed: BTreeMap::from([(0, 100), (1, 99)]),
+        }
+    }
+
+    #[test]
+    fn save_and_truncate() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshots = EpochSnapshots::open(dir.path()).unwrap();
+        assert_eq!(snapshots.latest().unwrap(), None);
+
+        snapshots.save(&snapshot(0)).unwrap();
+        snapshots.save(&snapshot(1)).unwrap();
+        assert_eq!(snapshots.get(0).unwrap(), Some(snapshot(0)));
+        assert_eq!(snapshots.latest().unwrap(), Some(snapshot(1)));
+
+        snapshots.truncate(0).unwrap();
+        assert_eq!(snapshots.get(1).unwrap(), None);
+        assert_eq!(snapshots.latest().unwrap(), Some(snapshot(0)));
+    }
+}
@@ -0,0 +1,157 @@
+//! Events of the consensus service for in-process consumers.
+//!
+//! The [`ConsensusEvent`](crate::rpc::ConsensusEvent)s of the RPC namespace are limited to the DAG
+//! and its commits, and are serialized for remote subscribers. Execution extensions that index
+//! consensus data alongside the chain run in the node's process, so [`NarwhalEvents`] broadcasts
+//! the whole path of a transaction, from the batch that sequenced it to the block that executed
+//! it, without serializing anything.
+//!
+//! Every task publishes to the same [`NarwhalEvents`], e.g. `BatchMaker::with_events` and
+//! [`Primary::with_events`](crate::primary::Primary::with_events). Publishing never blocks the
+//! tasks: a subscriber that falls behind by more than [`NARWHAL_EVENT_CHANNEL_CAPACITY`] events
+//! misses the oldest ones.
+
+use crate::{
+    rpc::CommittedSubDag,
+    types::{BatchDigest, Certificate, CertificateDigest, Header, HeaderDigest, Round, WorkerId},
+};
+use alloy_primitives::{BlockNumber, B256};
+use reth_narwhal_verifier::AuthorityIndex;
+use tokio::sync::broadcast;
+
+/// The number of events a subscriber can fall behind before it misses events.
+pub const NARWHAL_EVENT_CHANNEL_CAPACITY: usize = 4_096;
+
+/// An event of the consensus service, published to the subscribers of [`NarwhalEvents`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NarwhalEvent {
+    /// A worker of this validator sealed a batch.
+    BatchCreated {
+        /// The worker that sealed the batch.
+        worker: WorkerId,
+        /// The digest of the batch.
+        digest: BatchDigest,
+        /// The number of transactions in the batch.
+        transactions: usize,
+    },
+    /// The primary of this validator proposed a header.
+    HeaderProposed {
+        /// The round of the header.
+        round: Round,
+        /// The digest of the header.
+        digest: HeaderDigest,
+        /// The number of batches the header references.
+        batches: usize,
+    },
+    /// A certificate of the committee was added to the DAG of this validator.
+    CertificateFormed {
+        /// The round of the certificate.
+        round: Round,
+        /// The author of the certificate.
+        author: AuthorityIndex,
+        /// The digest of the certificate.
+        digest: CertificateDigest,
+    },
+    /// A sub-dag was committed.
+    SubDagCommitted(CommittedSubDag),
+    /// A block of a committed sub-dag was executed and submitted to the engine.
+    BlockExecuted {
+        /// The position of the sub-dag in the sequence of all commits.
+        sub_dag: u64,
+        /// The position of the block among the blocks of the sub-dag, starting at 0.
+        part: usize,
+        /// The number of the block.
+        number: BlockNumber,
+        /// The hash of the block.
+        hash: B256,
+        /// The number of transactions in the block.
+        transactions: usize,
+        /// The gas used by the block.
+        gas_used: u64,
+    },
+}
+
+impl NarwhalEvent {
+    /// Returns the event of a proposed header.
+    pub fn header_proposed(header: &Header) -> Self {
+        Self::HeaderProposed {
+            round: header.round,
+            digest: header.digest(),
+            batches: header.payload.len(),
+        }
+    }
+
+    /// Returns the event of a certificate that was added to the DAG.
+    pub fn certificate_formed(certificate: &Certificate) -> Self {
+        Self::CertificateFormed {
+            round: certificate.round(),
+            author: certificate.author(),
+            digest: certificate.digest(),
+        }
+    }
+}
+
+/// The broadcast channel of the [`NarwhalEvent`]s.
+///
+/// Cloning is cheap, all clones publish to the same subscribers.
+#[derive(Debug, Clone)]
+pub struct NarwhalEvents {
+    sender: broadcast::Sender<NarwhalEvent>,
+}
+
+impl NarwhalEvents {
+    /// Creates a channel without subscribers.
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(NARWHAL_EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publishes an event to the current subscribers, a no-op without subscribers.
+    pub fn publish(&self, event: NarwhalEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    /// Returns a receiver of the events published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<NarwhalEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Returns the number of subscribers.
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for NarwhalEvents {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn broadcast_to_subscribers() {
+        let events = NarwhalEvents::new();
+        // publishing without subscribers doesn't fail
+        events.publish(NarwhalEvent::BatchCreated {
+            worker: 0,
+            digest: BatchDigest::default(),
+            transactions: 1,
+        });
+
+        let mut first = events.subscribe();
+        let mut second = events.clone().subscribe();
+        assert_eq!(events.subscribers(), 2);
+        let event = NarwhalEvent::CertificateFormed {
+            round: 3,
+            author: 1,
+            digest: CertificateDigest::default(),
+        };
+        events.publish(event.clone());
+        assert_eq!(first.recv().await.unwrap(), event);
+        assert_eq!(second.recv().await.unwrap(), event);
+    }
+}
@@ -0,0 +1,1648 @@
+//! Turns consensus output into blocks.
+//!
+//! Every [`OrderedSubDag`] is executed as one block on top of the block of the previous commit.
+//! The [`ConsensusOutputExecutor`] flattens the batches of the sub-dag into a transaction list,
+//! applies the chain's [`sequencing`](crate::sequencing) rules, executes the block, completes its
+//! header with the execution results, and submits it to the engine as a new payload followed by a
+//! forkchoice update. Commits are final, so the new block is also the safe and finalized block.
+//!
+//! Transactions that exceed the block gas limit are split across consecutive blocks in sequencing
+//! order, see [`split_by_gas_limit`], across at most [`MAX_BLOCKS_PER_SUB_DAG`] blocks. All blocks
+//! of a sub-dag commit to its digest, the round of its leader and their position among its blocks,
+//! see [`BlockCommitment`]. If the chain accepts deposits, the deposit transactions of a sub-dag
+//! are moved to the front of its first block, see [`deposits_first`].
+//!
+//! A transaction that fails the stateful checks of the EVM when the block is executed, e.g.
+//! because an earlier transaction spent the balance it needed, is left out of the block by the
+//! block executor in the same execution, see [`execute_sequenced`].
+//!
+//! Instead of executing the blocks itself, the executor can hand the sequenced transactions to the
+//! payload builder of the node through a [`PayloadBridge`].
+//!
+//! Blocks are executed and passed to the commit hooks inside a [`DeterministicScope`]. The local
+//! clock, which only the local clock [`TimestampPolicy`](crate::timestamp::TimestampPolicy) of
+//! single-validator chains reads, is read once when a sub-dag is sequenced, before its blocks are
+//! built.
+//!
+//! The accounts and storage slots of the executed blocks are counted in a [`HotStateTracker`], and
+//! the hottest ones of the previous run are read with [`ConsensusOutputExecutor::warm_start`]
+//! before the first block after a restart, see [`warm_start`](crate::warm_start).
+
+use crate::{
+    backpressure::ExecutionLag,
+    commit_hooks::{CommitHookInput, CommitHooks},
+    committee::{Committee, CommitteeProvider},
+    committee_history::CommitteeHistory,
+    deposits::deposits_first,
+    determinism::{Clock, DeterministicScope, FixedClock, SystemClock},
+    events::{NarwhalEvent, NarwhalEvents},
+    gas_report::{CommitGasReport, GasReporter, PhaseTimings},
+    messages::messages_root,
+    metrics::ConsensusMetrics,
+    payload::{BuiltSubDagPayload, NarwhalPayloadAttributes, PayloadBridge, PayloadBridgeError},
+    receipts::RecentReceipts,
+    recovery::{CommittedSubDags, RecoveryError},
+    sequencing::{
+        drop_expired, mark_replayed, sequence_by_nonce, split_by_gas_limit, AllowlistSyncError,
+        ChainSequencingFilter, SenderAllowlist, SequencingFilter, SkipReason, SkippedTransaction,
+    },
+    status::DroppedTransactions,
+    trace::TraceIds,
+    types::{BlockCommitment, OrderedSubDag, Round, TransactionExpiry, MAX_BLOCKS_PER_SUB_DAG},
+    warm_start::{HotStateSummary, HotStateTracker},
+    worker::TransactionSizeLimits,
+    NarwhalChainInfo,
+};
+use reth_beacon_consensus::{
+    BeaconConsensusEngineHandle, BeaconForkChoiceUpdateError, BeaconOnNewPayloadError,
+    ForkchoiceStatus,
+};
+use reth_chainspec::{ChainSpec, EthereumHardforks};
+use reth_db_api::models::StoredConsensusMetadata;
+use reth_engine_primitives::EngineTypes;
+use reth_evm::execute::{
+    BlockExecutionError, BlockExecutionInput, BlockExecutorProvider, ExecutionOutcome, Executor,
+};
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use reth_primitives::{
+    constants::EMPTY_OMMER_ROOT_HASH, proofs, Address, Block, BlockNumber, BlockWithSenders, Bloom,
+    Header, Requests, SealedBlockWithSenders, SealedHeader, TransactionSigned,
+    TransactionSignedEcRecovered, Withdrawals, B256, U256,
+};
+use reth_provider::{
+    AccountReader, ConsensusMetadataWriter, HeaderProvider, ProviderError, StateProvider,
+    StateProviderBox, StateProviderFactory, StateRootProvider, TransactionsProvider,
+};
+use reth_revm::database::StateProviderDatabase;
+use reth_rpc_types::engine::{
+    CancunPayloadFields, ForkchoiceState, PayloadAttributes as EthPayloadAttributes,
+    PayloadStatusEnum,
+};
+use reth_rpc_types_compat::engine::payload::block_to_payload;
+use reth_trie::HashedPostState;
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc::Receiver, watch};
+use tracing::{debug, error, info, warn};
+
+/// Errors of the [`ConsensusOutputExecutor`].
+#[derive(Debug, thiserror::Error)]
+pub enum ConsensusOutputError {
+    /// The state of the parent block could not be read.
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    /// The block could not be executed.
+    #[error(transparent)]
+    Execution(#[from] BlockExecutionError),
+    /// The engine was shut down.
+    #[error("consensus engine unavailable")]
+    EngineUnavailable,
+    /// The engine failed to process the new payload.
+    #[error(transparent)]
+    NewPayload(#[from] BeaconOnNewPayloadError),
+    /// The engine failed to process the forkchoice update.
+    #[error(transparent)]
+    ForkchoiceUpdate(#[from] BeaconForkChoiceUpdateError),
+    /// The engine did not accept the block as valid.
+    #[error("block {number} ({hash}) was not accepted by the engine: {status:?}")]
+    PayloadRejected {
+        /// The number of the block.
+        number: BlockNumber,
+        /// The hash of the block.
+        hash: B256,
+        /// The status returned by the engine.
+        status: PayloadStatusEnum,
+    },
+    /// The committed sub-dags that were not executed before the restart could not be recovered.
+    #[error(transparent)]
+    Recovery(#[from] RecoveryError),
+    /// The allowlist of the permissioned chain is behind the parent of the block.
+    #[error(transparent)]
+    Allowlist(#[from] AllowlistSyncError),
+    /// The payload builder failed to build the block.
+    #[error(transparent)]
+    PayloadBuilder(#[from] PayloadBridgeError),
+    /// The engine did not make the block the canonical head.
+    #[error("forkchoice update to block {number} ({hash}) returned {status:?}")]
+    ForkchoiceRejected {
+        /// The number of the block.
+        number: BlockNumber,
+        /// The hash of the block.
+        hash: B256,
+        /// The status returned by the engine.
+        status: ForkchoiceStatus,
+    },
+}
+
+/// A block executed from an [`OrderedSubDag`].
+#[derive(Debug, Clone)]
+pub struct SubDagBlock {
+    /// The sealed block with the senders of its transactions.
+    pub block: SealedBlockWithSenders,
+    /// The state changes and receipts of the block.
+    pub execution_outcome: ExecutionOutcome,
+    /// The transactions of the sub-dag that were not included in the block.
+    ///
+    /// The transactions the sequencing left out are reported with the first executed block of the
+    /// sub-dag.
+    pub skipped: Vec<SkippedTransaction>,
+    /// The number of transactions of the sub-dag that were dropped because they exceed the
+    /// chain's [`TransactionSizeLimits`], reported with the first executed block of the sub-dag.
+    pub oversized: usize,
+    /// The metadata of the commit, including the epoch of the committee that committed it.
+    pub metadata: StoredConsensusMetadata,
+    /// The position of the block among the blocks of the sub-dag, starting at 0.
+    pub part: usize,
+    /// The time spent building the block, and submitting it once it was executed.
+    pub timings: PhaseTimings,
+}
+
+/// Metrics of the [`ConsensusOutputExecutor`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.executor")]
+struct ConsensusOutputExecutorMetrics {
+    /// Number of blocks accepted by the engine
+    blocks: Counter,
+    /// Number of transactions included in blocks
+    included_transactions: Counter,
+    /// Number of sequenced transactions that were not included
+    skipped_transactions: Counter,
+    /// Number of skipped transactions that were already executed by an earlier block
+    replayed_transactions: Counter,
+    /// Number of transactions that were dropped because they exceed the size limits
+    oversized_transactions: Counter,
+    /// Number of batches that were executed once although a sub-dag contained them several times
+    duplicate_batches: Counter,
+    /// Number of sub-dags whose transactions were split across several blocks
+    split_sub_dags: Counter,
+    /// The index of the last executed sub-dag
+    last_sub_dag: Gauge,
+}
+
+/// Executes committed sub-dags as blocks and submits them to the engine.
+///
+/// Sub-dags must be executed in commit order, each on top of the block of the previous one.
+/// Transactions that exceed the chain's [`TransactionSizeLimits`] or can't be decoded or recovered
+/// are dropped, and transactions rejected by the sequencing filter or [`sequence_by_nonce`] are
+/// skipped, identically on every validator. Transactions whose gas limit exceeds the block gas
+/// limit are skipped as well. A transaction that fails the stateful checks at execution is excluded
+/// from its block with [`SkipReason::Invalid`], see [`execute_sequenced`]. Only errors that are not
+/// caused by a transaction fail the block with [`ConsensusOutputError::Execution`].
+///
+/// The metadata of the commit of every block is written with the provider's
+/// [`ConsensusMetadataWriter`] before the block is submitted, for the attribution RPC and the
+/// reports of the validators.
+#[derive(Debug)]
+pub struct ConsensusOutputExecutor<Provider, Executor, Engine: EngineTypes> {
+    chain_spec: Arc<ChainSpec>,
+    chain_info: NarwhalChainInfo,
+    sequencing_filter: Arc<dyn SequencingFilter>,
+    /// Allowed senders, if the chain is permissioned.
+    sender_allowlist: Option<SenderAllowlist>,
+    /// Receives the fees of every block.
+    beneficiary: Address,
+    provider: Provider,
+    executor: Executor,
+    engine: BeaconConsensusEngineHandle<Engine>,
+    /// The header of the last executed block, the parent of the next block.
+    parent: SealedHeader,
+    /// Publishes the header of the last executed block.
+    head: watch::Sender<SealedHeader>,
+    commit_hooks: CommitHooks,
+    consensus_metrics: Option<ConsensusMetrics>,
+    /// Records the executed sub-dags for the back-pressure of the producers.
+    execution_lag: Option<ExecutionLag>,
+    /// Removes the records of the executed sub-dags, and replays the unexecuted ones on startup.
+    committed: Option<CommittedSubDags>,
+    gas_reporter: Option<GasReporter>,
+    /// Builds the blocks with the payload builder of the node instead of executing them directly.
+    payload_bridge: Option<PayloadBridge>,
+    /// Publishes the executed blocks to in-process subscribers.
+    events: Option<NarwhalEvents>,
+    /// Records the transactions of the sub-dags that were left out of their blocks.
+    dropped: Option<DroppedTransactions>,
+    /// Records the receipts of the executed blocks before they are submitted to the engine.
+    receipts: Option<RecentReceipts>,
+    /// Records the committee of every epoch at its first block, with the committees of the
+    /// epochs.
+    committee_history:
+        Option<(Arc<RwLock<CommitteeHistory<Committee>>>, Arc<dyn CommitteeProvider>)>,
+    /// Counts the accounts and storage slots of the executed blocks.
+    hot_state: Option<Arc<Mutex<HotStateTracker>>>,
+    /// Logs the inclusion of the transactions with a trace id.
+    trace_ids: Option<TraceIds>,
+    metrics: ConsensusOutputExecutorMetrics,
+}
+
+impl<Provider, Executor, Engine> ConsensusOutputExecutor<Provider, Executor, Engine>
+where
+    Provider:
+        StateProviderFactory + TransactionsProvider + HeaderProvider + ConsensusMetadataWriter,
+    Executor: BlockExecutorProvider,
+    Engine: EngineTypes,
+{
+    /// Creates an executor that builds the next block on top of `parent`, usually the canonical
+    /// head.
+    ///
+    /// # Panics
+    ///
+    /// If the narwhal section of the chain spec's genesis config is malformed.
+    pub fn new(
+        chain_spec: Arc<ChainSpec>,
+        provider: Provider,
+        executor: Executor,
+        engine: BeaconConsensusEngineHandle<Engine>,
+        parent: SealedHeader,
+    ) -> Self {
+        let chain_info = NarwhalChainInfo::from_genesis(&chain_spec.genesis)
+            .expect("invalid narwhal chain info in genesis");
+        let sequencing_filter =
+            Arc::new(ChainSequencingFilter::new(chain_info.sequencing_filters.clone()));
+        let sender_allowlist = chain_info.permissioned.clone().map(SenderAllowlist::new);
+        Self {
+            chain_spec,
+            chain_info,
+            sequencing_filter,
+            sender_allowlist,
+            beneficiary: Address::ZERO,
+            provider,
+            executor,
+            engine,
+            head: watch::Sender::new(parent.clone()),
+            parent,
+            commit_hooks: CommitHooks::default(),
+            consensus_metrics: None,
+            execution_lag: None,
+            committed: None,
+            gas_reporter: None,
+            payload_bridge: None,
+            events: None,
+            dropped: None,
+            receipts: None,
+            committee_history: None,
+            hot_state: None,
+            trace_ids: None,
+            metrics: ConsensusOutputExecutorMetrics::default(),
+        }
+    }
+
+    /// Replaces the sequencing filter configured by the chain spec.
+    ///
+    /// Must be the filter of the node's [`NarwhalConsensus`](crate::NarwhalConsensus).
+    pub fn with_sequencing_filter(mut self, filter: Arc<dyn SequencingFilter>) -> Self {
+        self.sequencing_filter = filter;
+        self
+    }
+
+    /// Replaces the allowlist of senders of the permissioned chain, e.g. with one that catches
+    /// up from the database after a restart, see [`SenderAllowlist::with_source`].
+    ///
+    /// The executor applies every block it executes to the allowlist. Ignored if the chain is not
+    /// permissioned.
+    pub fn with_sender_allowlist(mut self, allowlist: SenderAllowlist) -> Self {
+        if self.chain_info.permissioned.is_some() {
+            self.sender_allowlist = Some(allowlist);
+        }
+        self
+    }
+
+    /// Sets the beneficiary of every block, the zero address by default.
+    ///
+    /// All validators must use the same beneficiary, otherwise their blocks diverge.
+    pub const fn with_beneficiary(mut self, beneficiary: Address) -> Self {
+        self.beneficiary = beneficiary;
+        self
+    }
+
+    /// Sets the hooks that are called with every block before it's submitted to the engine.
+    pub fn with_commit_hooks(mut self, hooks: CommitHooks) -> Self {
+        self.commit_hooks = hooks;
+        self
+    }
+
+    /// Records the commit latency of the executed batches.
+    ///
+    /// Must share the metrics of the node's batch makers, which track the sealed batches.
+    pub fn with_consensus_metrics(mut self, metrics: ConsensusMetrics) -> Self {
+        self.consensus_metrics = Some(metrics);
+        self
+    }
+
+    /// Reports every executed sub-dag to the lag of execution, which must also be shared with the
+    /// [`EpochManager`](crate::epoch::EpochManager).
+    pub fn with_execution_lag(mut self, execution_lag: ExecutionLag) -> Self {
+        self.execution_lag = Some(execution_lag);
+        self
+    }
+
+    /// Removes the records of the executed sub-dags, which the
+    /// [`EpochManager`](crate::epoch::EpochManager) records in the same store, see
+    /// [`ConsensusOutputExecutor::replay_committed`].
+    pub fn with_committed_sub_dags(mut self, committed: CommittedSubDags) -> Self {
+        self.committed = Some(committed);
+        self
+    }
+
+    /// Reports the gas throughput of every executed block.
+    pub fn with_gas_reporter(mut self, reporter: GasReporter) -> Self {
+        self.gas_reporter = Some(reporter);
+        self
+    }
+
+    /// Builds the blocks with the payload builder behind the bridge instead of executing them.
+    ///
+    /// Transactions the payload builder leaves out of a block are reported as
+    /// [`SkipReason::Excluded`]. All validators must build blocks the same way, otherwise their
+    /// blocks diverge.
+    pub fn with_payload_bridge(mut self, bridge: PayloadBridge) -> Self {
+        self.payload_bridge = Some(bridge);
+        self
+    }
+
+    /// Publishes every block once the engine accepted it.
+    pub fn with_events(mut self, events: NarwhalEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Records the transactions that were left out of every block once the engine accepted it,
+    /// for the RPC to report their status.
+    pub fn with_dropped_transactions(mut self, dropped: DroppedTransactions) -> Self {
+        self.dropped = Some(dropped);
+        self
+    }
+
+    /// Records the receipts of every block as soon as it's executed, for the receipt RPC to serve
+    /// them before the engine wrote the block to the database.
+    pub fn with_recent_receipts(mut self, receipts: RecentReceipts) -> Self {
+        self.receipts = Some(receipts);
+        self
+    }
+
+    /// Records the committee of every epoch in the history once the engine accepted the first
+    /// block built from a sub-dag of the epoch, for the RPC to serve the committees of old blocks.
+    ///
+    /// The committees are read from the given provider, which must know the committee of every
+    /// executed sub-dag.
+    pub fn with_committee_history(
+        mut self,
+        history: Arc<RwLock<CommitteeHistory<Committee>>>,
+        committees: Arc<dyn CommitteeProvider>,
+    ) -> Self {
+        self.committee_history = Some((history, committees));
+        self
+    }
+
+    /// Logs the trace ids of the transactions of every block once the engine accepted it, and of
+    /// the transactions that were left out of it.
+    pub fn with_trace_ids(mut self, trace_ids: TraceIds) -> Self {
+        self.trace_ids = Some(trace_ids);
+        self
+    }
+
+    /// Counts the accounts and storage slots every executed block changed in the tracker, whose
+    /// summary the node saves for the [warm start](Self::warm_start) after a restart.
+    pub fn with_hot_state_tracker(mut self, tracker: Arc<Mutex<HotStateTracker>>) -> Self {
+        self.hot_state = Some(tracker);
+        self
+    }
+
+    /// Reads the accounts and storage slots of the summary from the latest state, so that the
+    /// first blocks after a restart find them in the caches of the database.
+    ///
+    /// Must be called before the first sub-dag is executed. Returns the number of entries read.
+    pub fn warm_start(&self, summary: &HotStateSummary) -> Result<usize, ProviderError> {
+        let state = self.provider.latest()?;
+        for address in &summary.accounts {
+            state.basic_account(*address)?;
+        }
+        for slot in &summary.slots {
+            state.storage(slot.address, slot.slot)?;
+        }
+        Ok(summary.accounts.len() + summary.slots.len())
+    }
+
+    /// Returns the header of the last executed block.
+    pub const fn parent(&self) -> &SealedHeader {
+        &self.parent
+    }
+
+    /// Returns a receiver of the head sealed by the executor, the header of the last block the
+    /// engine made canonical.
+    ///
+    /// Services that only need the latest head can await it here instead of subscribing to the
+    /// canonical state notifications, which carry the executed blocks and their state changes. The
+    /// receiver keeps the last head after the executor stopped.
+    pub fn subscribe_head(&self) -> watch::Receiver<SealedHeader> {
+        self.head.subscribe()
+    }
+
+    /// Returns the payload attributes of the blocks of a sub-dag on top of the last executed block,
+    /// which carry the sequenced transactions of the sub-dag.
+    ///
+    /// Returns the attributes of several consecutive blocks if the transactions exceed the block
+    /// gas limit.
+    pub fn payload_attributes(
+        &self,
+        sub_dag: &OrderedSubDag,
+    ) -> Result<Vec<NarwhalPayloadAttributes>, ConsensusOutputError> {
+        let sequenced = self.sequence(sub_dag, &self.parent)?;
+        let mut parent_timestamp = self.parent.timestamp;
+        Ok(sequenced
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(part, transactions)| {
+                let timestamp = self.block_timestamp(sub_dag, parent_timestamp, &sequenced.clock);
+                parent_timestamp = timestamp;
+                self.sequenced_payload_attributes(sub_dag, part, timestamp, transactions)
+            })
+            .collect())
+    }
+
+    /// Executes the blocks of a sub-dag and submits them to the engine.
+    ///
+    /// The transactions of the sub-dag are split across consecutive blocks if they exceed the
+    /// block gas limit, see [`split_by_gas_limit`]. The blocks are built with the payload builder
+    /// of the [`PayloadBridge`], if set. Once the engine made a block the canonical head, it
+    /// becomes the parent of the next block.
+    pub async fn execute(
+        &mut self,
+        sub_dag: &OrderedSubDag,
+    ) -> Result<Vec<SubDagBlock>, ConsensusOutputError> {
+        let sequenced = self.sequence(sub_dag, &self.parent)?;
+        self.execute_blocks(sub_dag, sequenced, 0).await
+    }
+
+    /// Replays the recorded sub-dags that were committed but not executed before the node stopped,
+    /// on top of the parent, which must be the canonical head.
+    ///
+    /// If the node stopped between the blocks of a sub-dag, its remaining blocks are executed
+    /// first. Returns the index of the first sub-dag consensus commits next, `None` if no sub-dag
+    /// is recorded. Must be awaited before the executor accepts new consensus output.
+    pub async fn replay_committed(&mut self) -> Result<Option<u64>, ConsensusOutputError> {
+        let Some(committed) = self.committed.clone() else { return Ok(None) };
+        if let Some(interrupted) = committed.interrupted(self.parent.mix_hash)? {
+            self.resume(&interrupted).await?;
+        }
+        let Some(unexecuted) = committed.unexecuted(self.parent.number, self.parent.mix_hash)?
+        else {
+            return Ok(None)
+        };
+        if !unexecuted.sub_dags.is_empty() {
+            info!(
+                target: "consensus::narwhal",
+                sub_dags = unexecuted.sub_dags.len(),
+                next_sub_dag = unexecuted.next_sub_dag,
+                "Replaying committed sub-dags"
+            );
+        }
+        for sub_dag in &unexecuted.sub_dags {
+            self.execute(sub_dag).await?;
+        }
+        Ok(Some(unexecuted.next_sub_dag))
+    }
+
+    /// Executes the sub-dags in the order they are received, until the sender is dropped or a
+    /// sub-dag fails.
+    ///
+    /// A failed sub-dag stops the executor, since every later block would build on the missing
+    /// block. The sub-dags recorded before a restart must be replayed with
+    /// [`ConsensusOutputExecutor::replay_committed`] first.
+    pub async fn run(mut self, mut sub_dags: Receiver<OrderedSubDag>) {
+        while let Some(sub_dag) = sub_dags.recv().await {
+            if let Err(err) = self.execute(&sub_dag).await {
+                error!(
+                    target: "consensus::narwhal",
+                    %err,
+                    sub_dag = sub_dag.index,
+                    round = sub_dag.leader_round(),
+                    "Failed to execute sub-dag"
+                );
+                return
+            }
+        }
+    }
+
+    /// Executes the blocks of the sub-dag of the canonical head that were not executed before the
+    /// node stopped.
+    ///
+    /// The sub-dag is sequenced again on top of the parent of its first block, which yields the
+    /// blocks every other validator built, and the blocks after the head are executed.
+    async fn resume(
+        &mut self,
+        sub_dag: &OrderedSubDag,
+    ) -> Result<Vec<SubDagBlock>, ConsensusOutputError> {
+        let digest = sub_dag.digest().0;
+        let mut executed = 0;
+        let mut parent = self.parent.clone();
+        while parent.mix_hash == digest && parent.number > 0 {
+            executed += 1;
+            let number = parent.number - 1;
+            parent = self
+                .provider
+                .sealed_header(number)?
+                .ok_or(ProviderError::HeaderNotFound(number.into()))?;
+        }
+
+        let sequenced = self.sequence(sub_dag, &parent)?;
+        if sequenced.blocks.len() <= executed {
+            return Ok(Vec::new())
+        }
+        info!(
+            target: "consensus::narwhal",
+            sub_dag = sub_dag.index,
+            executed,
+            blocks = sequenced.blocks.len(),
+            "Resuming interrupted sub-dag"
+        );
+        self.execute_blocks(sub_dag, sequenced, executed).await
+    }
+
+    /// Executes the blocks of a sequenced sub-dag from the block at position `first` on, and
+    /// submits them to the engine.
+    ///
+    /// The sub-dag must be sequenced on top of the parent of its first block, and the blocks
+    /// before `first` must be executed already.
+    async fn execute_blocks(
+        &mut self,
+        sub_dag: &OrderedSubDag,
+        sequenced: SequencedSubDag,
+        first: usize,
+    ) -> Result<Vec<SubDagBlock>, ConsensusOutputError> {
+        let SequencedSubDag { state, blocks, mut skipped, oversized, io, sequencing, clock } =
+            sequenced;
+        let parts = blocks.len();
+        if parts > 1 {
+            self.metrics.split_sub_dags.increment(1);
+            debug!(
+                target: "consensus::narwhal",
+                sub_dag = sub_dag.index,
+                blocks = parts,
+                "Splitting sub-dag across blocks"
+            );
+        }
+        // the state of the sequencing is only the parent state of the first block
+        let mut state = (first == 0).then_some(state);
+
+        let mut executed_blocks = Vec::with_capacity(parts - first);
+        for (part, transactions) in blocks.into_iter().enumerate().skip(first) {
+            let state = match state.take() {
+                Some(state) => state,
+                None => self.provider.history_by_block_hash(self.parent.hash())?,
+            };
+            let (transactions, mut filtered) = self.filter_senders(transactions)?;
+            let mut executed = match &self.payload_bridge {
+                Some(bridge) => {
+                    // the payload builder reads the parent state itself
+                    drop(state);
+                    self.build_payload(sub_dag, part, transactions, bridge, &clock).await?
+                }
+                None => self.build_block(sub_dag, part, state, transactions, &clock)?,
+            };
+            filtered.append(&mut executed.skipped);
+            executed.skipped = filtered;
+            // the transactions the sequencing left out are reported with the first block
+            if part == first {
+                skipped.append(&mut executed.skipped);
+                executed.skipped = std::mem::take(&mut skipped);
+                executed.oversized = oversized;
+                executed.timings.io += io;
+                executed.timings.sequencing = sequencing;
+            }
+            if let Some(receipts) = &self.receipts {
+                receipts.insert_block(&executed.block, &executed.execution_outcome);
+            }
+            self.record_hot_state(&executed.execution_outcome);
+            // a block that is executed again after a restart overwrites the same entries
+            self.provider.insert_consensus_metadata(
+                executed.block.number,
+                executed.block.hash(),
+                executed.metadata.clone(),
+            )?;
+            let submitted = Instant::now();
+            self.submit(&executed).await?;
+            executed.timings.engine = submitted.elapsed();
+            if let Some(allowlist) = &self.sender_allowlist {
+                let number = executed.block.number;
+                let receipts = executed.execution_outcome.receipts_by_block(number);
+                allowlist
+                    .apply_block(number, receipts.iter().flatten())
+                    .map_err(AllowlistSyncError::from)?;
+            }
+
+            let header = executed.block.header.clone();
+            info!(
+                target: "consensus::narwhal",
+                sub_dag = sub_dag.index,
+                round = sub_dag.leader_round(),
+                part,
+                number = header.number,
+                hash = %header.hash(),
+                transactions = executed.block.body.len(),
+                skipped = executed.skipped.len(),
+                "Executed sub-dag"
+            );
+            self.metrics.blocks.increment(1);
+            self.metrics.included_transactions.increment(executed.block.body.len() as u64);
+            self.metrics.skipped_transactions.increment(executed.skipped.len() as u64);
+            self.metrics.replayed_transactions.increment(
+                executed
+                    .skipped
+                    .iter()
+                    .filter(|skipped| matches!(skipped.reason, SkipReason::AlreadyExecuted { .. }))
+                    .count() as u64,
+            );
+            self.metrics.oversized_transactions.increment(executed.oversized as u64);
+            if let Some(reporter) = &mut self.gas_reporter {
+                reporter.record(&CommitGasReport::new(
+                    sub_dag.index,
+                    header.number,
+                    executed.block.body.len() as u64,
+                    header.gas_used,
+                    header.gas_limit,
+                    &executed.timings,
+                ));
+            }
+            if let Some(events) = &self.events {
+                events.publish(NarwhalEvent::BlockExecuted {
+                    sub_dag: sub_dag.index,
+                    part,
+                    number: header.number,
+                    hash: header.hash(),
+                    transactions: executed.block.body.len(),
+                    gas_used: header.gas_used,
+                });
+            }
+            if let Some(dropped) = &self.dropped {
+                dropped.insert(header.number, executed.skipped.iter().cloned());
+            }
+            if let Some(trace_ids) = &self.trace_ids {
+                for transaction in &executed.block.body {
+                    trace_ids.on_included(&transaction.hash(), header.number);
+                }
+                for skipped in &executed.skipped {
+                    trace_ids.on_dropped(&skipped.hash, header.number, &skipped.reason);
+                }
+            }
+            self.record_committee(sub_dag, header.number);
+            self.head.send_replace(header.clone());
+            self.parent = header;
+            executed_blocks.push(executed);
+        }
+
+        self.metrics
+            .duplicate_batches
+            .increment((sub_dag.batches.len() - sub_dag.unique_batches().count()) as u64);
+        self.metrics.last_sub_dag.set(sub_dag.index as f64);
+        if let Some(metrics) = &self.consensus_metrics {
+            metrics.record_batches_executed(sub_dag.unique_batches().map(|batch| batch.digest()));
+        }
+        if let Some(execution_lag) = &self.execution_lag {
+            execution_lag.on_executed(sub_dag.index);
+        }
+        if let Some(committed) = &self.committed {
+            if let Err(err) = committed.executed(sub_dag.index) {
+                warn!(
+                    target: "consensus::narwhal",
+                    %err,
+                    sub_dag = sub_dag.index,
+                    "Failed to remove records of executed sub-dags"
+                );
+            }
+        }
+        Ok(executed_blocks)
+    }
+
+    /// Removes the transactions whose senders the allowlist of a permissioned chain rejects from
+    /// the block on top of the last executed block, and returns them as skipped.
+    ///
+    /// Unlike the [`SequencingFilter`] of [`Self::sequence`], the allowlist of a block depends on
+    /// the registry events of its parent, so it's applied to every block of a split sub-dag on
+    /// its own.
+    fn filter_senders(
+        &self,
+        transactions: Vec<TransactionSignedEcRecovered>,
+    ) -> Result<(Vec<TransactionSignedEcRecovered>, Vec<SkippedTransaction>), ConsensusOutputError>
+    {
+        let Some(allowlist) = &self.sender_allowlist else { return Ok((transactions, Vec::new())) };
+        // a parent the allowlist can't catch up to fails the block instead of emptying it
+        allowlist.sync_to(self.parent.number)?;
+        let number = self.parent.number + 1;
+        let mut skipped = Vec::new();
+        let transactions = transactions
+            .into_iter()
+            .filter(|transaction| {
+                match allowlist.check(number, transaction.signer(), transaction) {
+                    Ok(()) => true,
+                    Err(rejection) => {
+                        skipped.push(SkippedTransaction::new(
+                            transaction,
+                            SkipReason::Filtered(rejection),
+                        ));
+                        false
+                    }
+                }
+            })
+            .collect();
+        Ok((transactions, skipped))
+    }
+
+    /// Builds and executes a block of a sub-dag with the given sequenced transactions on top of the
+    /// last executed block, whose state is `state`.
+    ///
+    /// The transactions that fail at execution are excluded from the block and reported as
+    /// skipped, see [`execute_sequenced`].
+    fn build_block(
+        &self,
+        sub_dag: &OrderedSubDag,
+        part: usize,
+        state: StateProviderBox,
+        transactions: Vec<TransactionSignedEcRecovered>,
+        clock: &FixedClock,
+    ) -> Result<SubDagBlock, ConsensusOutputError> {
+        let timestamp = self.block_timestamp(sub_dag, self.parent.timestamp, clock);
+        let gas_limit = self.block_gas_limit(&self.parent);
+        let (BlockExecution { block, execution_outcome, evm, state_root }, skipped) =
+            execute_sequenced(
+                &self.chain_spec,
+                &self.executor,
+                state,
+                |body| {
+                    block_template(
+                        &self.chain_spec,
+                        &self.parent,
+                        self.beneficiary,
+                        timestamp,
+                        sub_dag.digest().0,
+                        gas_limit,
+                        body,
+                    )
+                },
+                sub_dag.leader_round(),
+                part as u32,
+                transactions,
+            )?;
+        let metadata = consensus_metadata(sub_dag);
+        let BlockWithSenders { block, senders } = block;
+        self.on_commit(
+            sub_dag,
+            &metadata,
+            &block.header,
+            &block.body,
+            &senders,
+            &execution_outcome,
+        );
+
+        let block = SealedBlockWithSenders::new(block.seal_slow(), senders)
+            .expect("one sender per transaction");
+        let timings = PhaseTimings {
+            io: Duration::ZERO,
+            sequencing: Duration::ZERO,
+            evm,
+            state_root,
+            engine: Duration::ZERO,
+        };
+        Ok(SubDagBlock { block, execution_outcome, skipped, oversized: 0, metadata, part, timings })
+    }
+
+    /// Builds a block of a sub-dag with the given sequenced transactions on top of the last
+    /// executed block with the payload builder of the [`PayloadBridge`].
+    ///
+    /// Transactions the payload builder left out of the block are reported as
+    /// [`SkipReason::Excluded`]. The time spent in the payload builder is reported as the time
+    /// spent executing the transactions.
+    async fn build_payload(
+        &self,
+        sub_dag: &OrderedSubDag,
+        part: usize,
+        transactions: Vec<TransactionSignedEcRecovered>,
+        bridge: &PayloadBridge,
+        clock: &FixedClock,
+    ) -> Result<SubDagBlock, ConsensusOutputError> {
+        let timestamp = self.block_timestamp(sub_dag, self.parent.timestamp, clock);
+        let attributes = self.sequenced_payload_attributes(sub_dag, part, timestamp, &transactions);
+
+        let started = Instant::now();
+        let BuiltSubDagPayload { block, execution_outcome } =
+            bridge.build(self.parent.hash(), attributes).await?;
+        let evm = started.elapsed();
+
+        let included =
+            block.body.iter().map(|transaction| transaction.hash()).collect::<HashSet<_>>();
+        let skipped = transactions
+            .iter()
+            .filter(|transaction| !included.contains(&transaction.hash()))
+            .map(|transaction| SkippedTransaction::new(transaction, SkipReason::Excluded))
+            .collect();
+
+        let metadata = consensus_metadata(sub_dag);
+        self.on_commit(
+            sub_dag,
+            &metadata,
+            &block.header,
+            &block.body,
+            &block.senders,
+            &execution_outcome,
+        );
+
+        let timings = PhaseTimings {
+            io: Duration::ZERO,
+            sequencing: Duration::ZERO,
+            evm,
+            state_root: Duration::ZERO,
+            engine: Duration::ZERO,
+        };
+        Ok(SubDagBlock { block, execution_outcome, skipped, oversized: 0, metadata, part, timings })
+    }
+
+    /// Counts the accounts and storage slots the block changed in the [`HotStateTracker`].
+    fn record_hot_state(&self, execution_outcome: &ExecutionOutcome) {
+        let Some(tracker) = &self.hot_state else { return };
+        let mut tracker = tracker.lock().unwrap_or_else(PoisonError::into_inner);
+        for (address, account) in &execution_outcome.state().state {
+            tracker.record_account(*address);
+            for slot in account.storage.keys() {
+                tracker.record_slot(*address, B256::from(*slot));
+            }
+        }
+    }
+
+    /// Returns the timestamp of a block of a sub-dag on top of a block with the given timestamp.
+    ///
+    /// The clock is the local time when the sub-dag was sequenced, see [`SequencedSubDag::clock`].
+    fn block_timestamp(
+        &self,
+        sub_dag: &OrderedSubDag,
+        parent_timestamp: u64,
+        clock: &FixedClock,
+    ) -> u64 {
+        self.chain_info.timestamp_policy.block_timestamp(sub_dag, parent_timestamp, clock)
+    }
+
+    /// Returns the gas limit of the blocks on top of the given parent.
+    fn block_gas_limit(&self, parent: &SealedHeader) -> u64 {
+        self.chain_info.block_gas_limit.unwrap_or(parent.gas_limit)
+    }
+
+    /// Decodes, filters and sequences the transactions of a sub-dag for the blocks on top of
+    /// `parent`, and splits them across blocks.
+    fn sequence(
+        &self,
+        sub_dag: &OrderedSubDag,
+        parent: &SealedHeader,
+    ) -> Result<SequencedSubDag, ConsensusOutputError> {
+        let number = parent.number + 1;
+        // the only read of the system clock for the blocks of the sub-dag, which are built inside
+        // a deterministic scope
+        let clock = FixedClock(SystemClock.now_millis());
+        let started = Instant::now();
+        let mut io = Duration::ZERO;
+        let state = self.provider.history_by_block_hash(parent.hash())?;
+        io += started.elapsed();
+
+        let duplicates = sub_dag.batches.len() - sub_dag.unique_batches().count();
+        if duplicates > 0 {
+            debug!(
+                target: "consensus::narwhal",
+                sub_dag = sub_dag.index,
+                duplicates,
+                "Skipping repeated batches of sub-dag"
+            );
+        }
+
+        let size_limits = self.chain_info.transaction_size_limits.unwrap_or_default();
+        let mut oversized = 0;
+        let mut skipped = Vec::new();
+        let mut transactions = Vec::with_capacity(sub_dag.num_transactions());
+        // every batch is executed once, at its first inclusion
+        for encoded in sub_dag.transactions() {
+            let (expiry, mut encoded) = TransactionExpiry::split_batched(encoded);
+            // workers don't batch oversized transactions, so they are dropped before decoding
+            if size_limits.check_size(encoded.len()).is_err() {
+                oversized += 1;
+                continue
+            }
+            // transactions without a valid signature can't be attributed to a sender and are
+            // dropped without a trace
+            let Some(transaction) = TransactionSigned::decode_enveloped(&mut encoded)
+                .ok()
+                .and_then(TransactionSigned::into_ecrecovered)
+            else {
+                continue
+            };
+            if size_limits.check_calldata(transaction.input().len()).is_err() {
+                oversized += 1;
+                continue
+            }
+            match self.sequencing_filter.check(number, transaction.signer(), &transaction) {
+                Ok(()) => transactions.push((transaction, expiry)),
+                Err(rejection) => skipped
+                    .push(SkippedTransaction::new(&transaction, SkipReason::Filtered(rejection))),
+            }
+        }
+        // expiries are checked against the round and the timestamp of the first block of the
+        // sub-dag, which all validators agree on
+        let timestamp = self.block_timestamp(sub_dag, parent.timestamp, &clock);
+        let (transactions, expired) = drop_expired(transactions, sub_dag.leader_round(), timestamp);
+        skipped.extend(expired);
+
+        if oversized > 0 {
+            debug!(
+                target: "consensus::narwhal",
+                sub_dag = sub_dag.index,
+                oversized,
+                "Dropped oversized transactions of sub-dag"
+            );
+        }
+
+        let mut sequenced =
+            sequence_by_nonce(self.chain_info.nonce_gap_policy, transactions, |sender| {
+                let read = Instant::now();
+                let nonce = state.account_nonce(sender)?.unwrap_or_default();
+                io += read.elapsed();
+                Ok::<_, ProviderError>(nonce)
+            })?;
+        // the transaction index of the database outlives the batches of the DAG, and only tells
+        // replays apart from transactions that lost their nonce
+        mark_replayed(number, &sequenced.included, &mut sequenced.skipped, |hash| {
+            let read = Instant::now();
+            let block = match self.provider.transaction_id(hash)? {
+                Some(id) => self.provider.transaction_block(id)?,
+                None => None,
+            };
+            io += read.elapsed();
+            Ok::<_, ProviderError>(block)
+        })?;
+        skipped.extend(sequenced.skipped);
+        if let Some(deposits) = &self.chain_info.deposits {
+            deposits_first(deposits.sender, &mut sequenced.included);
+        }
+        let (blocks, exceeding) = split_by_gas_limit(
+            sequenced.included,
+            self.block_gas_limit(parent),
+            MAX_BLOCKS_PER_SUB_DAG as usize,
+        );
+        skipped.extend(exceeding);
+
+        Ok(SequencedSubDag {
+            state,
+            blocks,
+            skipped,
+            oversized,
+            io,
+            sequencing: started.elapsed().saturating_sub(io),
+            clock,
+        })
+    }
+
+    /// Returns the payload attributes of a block of a sub-dag with the given sequenced
+    /// transactions, with the same header fields as the blocks built by
+    /// [`ConsensusOutputExecutor::build_block`].
+    fn sequenced_payload_attributes(
+        &self,
+        sub_dag: &OrderedSubDag,
+        part: usize,
+        timestamp: u64,
+        transactions: &[TransactionSignedEcRecovered],
+    ) -> NarwhalPayloadAttributes {
+        NarwhalPayloadAttributes {
+            payload_attributes: EthPayloadAttributes {
+                timestamp,
+                prev_randao: sub_dag.digest().0,
+                suggested_fee_recipient: self.beneficiary,
+                withdrawals: self
+                    .chain_spec
+                    .is_shanghai_active_at_timestamp(timestamp)
+                    .then(Vec::new),
+                parent_beacon_block_root: self
+                    .chain_spec
+                    .is_cancun_active_at_timestamp(timestamp)
+                    .then_some(B256::ZERO),
+            },
+            transactions: transactions
+                .iter()
+                .map(|transaction| transaction.envelope_encoded())
+                .collect(),
+            gas_limit: self.block_gas_limit(&self.parent),
+            round: sub_dag.leader_round(),
+            part: part as u32,
+        }
+    }
+
+    /// Calls the commit hooks with the block of a sub-dag, inside a deterministic scope.
+    fn on_commit(
+        &self,
+        sub_dag: &OrderedSubDag,
+        metadata: &StoredConsensusMetadata,
+        header: &Header,
+        transactions: &[TransactionSigned],
+        senders: &[Address],
+        execution_outcome: &ExecutionOutcome,
+    ) {
+        if self.commit_hooks.is_empty() {
+            return
+        }
+        let _scope = DeterministicScope::enter();
+        let receipts = execution_outcome
+            .receipts_by_block(header.number)
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        self.commit_hooks.on_commit(&CommitHookInput {
+            sub_dag,
+            metadata,
+            header,
+            transactions,
+            senders,
+            receipts: &receipts,
+        });
+    }
+
+    /// Records the committee of the sub-dag's epoch at the given block, if the history has no
+    /// record of the epoch yet.
+    ///
+    /// A committee that can't be recorded doesn't hold back execution, it's only missing from the
+    /// history.
+    fn record_committee(&self, sub_dag: &OrderedSubDag, number: BlockNumber) {
+        let Some((history, committees)) = &self.committee_history else { return };
+        let epoch = sub_dag.epoch();
+        let mut history = history.write().unwrap_or_else(PoisonError::into_inner);
+        if history.latest().is_some_and(|latest| latest.epoch >= epoch) {
+            return
+        }
+        let Some(committee) = committees.committee(epoch) else { return };
+        match history.insert(epoch, number, Committee::clone(&committee)) {
+            Ok(()) => info!(target: "consensus::narwhal", epoch, number, "Recorded committee"),
+            Err(err) => warn!(
+                target: "consensus::narwhal",
+                %err,
+                epoch,
+                number,
+                "Failed to record committee"
+            ),
+        }
+    }
+
+    /// Submits an executed block to the engine and makes it the canonical, safe and finalized
+    /// block.
+    async fn submit(&self, executed: &SubDagBlock) -> Result<(), ConsensusOutputError> {
+        let block = executed.block.block.clone();
+        let (number, hash) = (block.number, block.hash());
+        let cancun_fields =
+            block.parent_beacon_block_root.map(|parent_beacon_block_root| CancunPayloadFields {
+                parent_beacon_block_root,
+                versioned_hashes: block.blob_versioned_hashes_iter().copied().collect(),
+            });
+
+        let status = self
+            .engine
+            .new_payload(block_to_payload(block), cancun_fields)
+            .await
+            .map_err(|err| match err {
+                BeaconOnNewPayloadError::EngineUnavailable => {
+                    ConsensusOutputError::EngineUnavailable
+                }
+                err => err.into(),
+            })?;
+        if !status.status.is_valid() {
+            return Err(ConsensusOutputError::PayloadRejected {
+                number,
+                hash,
+                status: status.status,
+            })
+        }
+        debug!(target: "consensus::narwhal", number, %hash, "Block accepted by the engine");
+
+        let state = ForkchoiceState {
+            head_block_hash: hash,
+            safe_block_hash: hash,
+            finalized_block_hash: hash,
+        };
+        let response =
+            self.engine.fork_choice_updated(state, None).await.map_err(|err| match err {
+                BeaconForkChoiceUpdateError::EngineUnavailable => {
+                    ConsensusOutputError::EngineUnavailable
+                }
+                err => err.into(),
+            })?;
+        match ForkchoiceStatus::from(response.payload_status.status) {
+            ForkchoiceStatus::Valid => Ok(()),
+            status => Err(ConsensusOutputError::ForkchoiceRejected { number, hash, status }),
+        }
+    }
+}
+
+/// Returns the metadata of the commit of a sub-dag.
+fn consensus_metadata(sub_dag: &OrderedSubDag) -> StoredConsensusMetadata {
+    let batches = sub_dag.unique_batches().collect::<Vec<_>>();
+    StoredConsensusMetadata {
+        leader: sub_dag.leader.author().into(),
+        first_round: sub_dag
+            .certificates
+            .iter()
+            .map(|certificate| certificate.round())
+            .min()
+            .unwrap_or_else(|| sub_dag.leader_round()),
+        last_round: sub_dag.leader_round(),
+        certificates: sub_dag.certificates.len() as u64,
+        batches: batches.len() as u64,
+        batch_bytes: batches.iter().map(|batch| batch.size() as u64).sum(),
+        epoch: sub_dag.epoch(),
+    }
+}
+
+/// The transactions of a sub-dag in execution order, before they are executed.
+struct SequencedSubDag {
+    /// The state of the parent of the first block.
+    state: StateProviderBox,
+    /// The transactions of the consecutive blocks of the sub-dag.
+    blocks: Vec<Vec<TransactionSignedEcRecovered>>,
+    skipped: Vec<SkippedTransaction>,
+    oversized: usize,
+    /// The time spent reading the parent state.
+    io: Duration,
+    /// The time spent sequencing, without the reads of the state.
+    sequencing: Duration,
+    /// The local time when the sub-dag was sequenced.
+    ///
+    /// The system clock can't be read while a block is built, so all blocks of the sub-dag read
+    /// this time instead.
+    clock: FixedClock,
+}
+
+/// A block executed on top of the state of its parent.
+pub(crate) struct BlockExecution {
+    /// The block, with the header completed by the results of the execution.
+    pub(crate) block: BlockWithSenders,
+    pub(crate) execution_outcome: ExecutionOutcome,
+    /// The time spent executing the transactions.
+    pub(crate) evm: Duration,
+    /// The time spent computing the state root.
+    pub(crate) state_root: Duration,
+}
+
+/// Returns the block of a sub-dag before execution, with the header fields that are known
+/// upfront.
+pub(crate) fn block_template(
+    chain_spec: &ChainSpec,
+    parent: &SealedHeader,
+    beneficiary: Address,
+    timestamp: u64,
+    mix_hash: B256,
+    gas_limit: u64,
+    body: Vec<TransactionSigned>,
+) -> Block {
+    let withdrawals =
+        chain_spec.is_shanghai_active_at_timestamp(timestamp).then(Withdrawals::default);
+
+    let mut header = Header {
+        parent_hash: parent.hash(),
+        ommers_hash: EMPTY_OMMER_ROOT_HASH,
+        beneficiary,
+        transactions_root: proofs::calculate_transaction_root(&body),
+        withdrawals_root: withdrawals.as_ref().map(|w| proofs::calculate_withdrawals_root(w)),
+        number: parent.number + 1,
+        gas_limit,
+        timestamp,
+        // the digest of the sub-dag binds the block to the consensus output, it's unpredictable
+        // before the commit and the same on every validator
+        mix_hash,
+        base_fee_per_gas: parent
+            .next_block_base_fee(chain_spec.base_fee_params_at_timestamp(timestamp)),
+        ..Default::default()
+    };
+    if chain_spec.is_cancun_active_at_timestamp(timestamp) {
+        let blob_gas_used = body
+            .iter()
+            .filter_map(|transaction| transaction.transaction.as_eip4844())
+            .map(|transaction| transaction.blob_gas())
+            .sum();
+        header.blob_gas_used = Some(blob_gas_used);
+        header.excess_blob_gas = Some(parent.next_block_excess_blob_gas().unwrap_or_default());
+        header.parent_beacon_block_root = Some(B256::ZERO);
+    }
+
+    Block { header, body, ommers: Vec::new(), withdrawals, requests: None }
+}
+
+/// Executes the block of the sequenced transactions on top of the state of its parent, excludes
+/// the transactions that fail the validation of the EVM, and completes the header of the block with
+/// the results of the execution.
+///
+/// The block is built with `template`. The block is executed once, and the executor leaves out
+/// the transactions that fail the validation of the EVM, e.g. because an earlier transaction spent
+/// the balance they needed. Every validator excludes the same transactions, since they execute the
+/// same transactions on the same parent state. The block is then rebuilt from the remaining
+/// transactions. Returns the executed block and the excluded transactions in sequencing order.
+/// Any other error fails the block. The block is executed inside a [`DeterministicScope`].
+///
+/// The extra data of the header is the [`BlockCommitment`] of the block at position `part` of the
+/// sub-dag of the `mix_hash` of the template, committed with the leader of `round`.
+pub(crate) fn execute_sequenced<E: BlockExecutorProvider>(
+    chain_spec: &ChainSpec,
+    executor: &E,
+    state: StateProviderBox,
+    template: impl Fn(Vec<TransactionSigned>) -> Block,
+    round: Round,
+    part: u32,
+    transactions: Vec<TransactionSignedEcRecovered>,
+) -> Result<(BlockExecution, Vec<SkippedTransaction>), ConsensusOutputError> {
+    let _scope = DeterministicScope::enter();
+    let block = sequenced_block(&template, &transactions);
+    let (number, timestamp) = (block.number, block.timestamp);
+
+    let evm_started = Instant::now();
+    let mut db = StateProviderDatabase::new(state);
+    let mut output = executor.executor(&mut db).execute(
+        BlockExecutionInput::new(&block, U256::ZERO).with_invalid_transactions_skipped(),
+    )?;
+    let evm = evm_started.elapsed();
+
+    let (block, skipped) = if output.skipped.is_empty() {
+        (block, Vec::new())
+    } else {
+        let mut excluded = std::mem::take(&mut output.skipped).into_iter().peekable();
+        let mut included = Vec::with_capacity(transactions.len() - excluded.len());
+        let mut skipped = Vec::with_capacity(excluded.len());
+        for (index, transaction) in transactions.into_iter().enumerate() {
+            match excluded.next_if(|(excluded, _)| *excluded == index) {
+                Some((_, err)) => {
+                    debug!(
+                        target: "consensus::narwhal",
+                        hash = %transaction.hash(),
+                        sender = %transaction.signer(),
+                        %err,
+                        "Excluding transaction that failed at execution"
+                    );
+                    skipped.push(SkippedTransaction::new(&transaction, SkipReason::Invalid(err)));
+                }
+                None => included.push(transaction),
+            }
+        }
+        (sequenced_block(&template, &included), skipped)
+    };
+
+    let gas_used = output.gas_used;
+    let requests = chain_spec
+        .is_prague_active_at_timestamp(timestamp)
+        .then(|| Requests(output.requests.clone()));
+    let execution_outcome = ExecutionOutcome::from((output, number));
+
+    // complete the header with the results of the execution
+    let BlockWithSenders { block: mut block, senders } = block;
+    let state_root_started = Instant::now();
+    let hashed_state = HashedPostState::from_bundle_state(&execution_outcome.state().state);
+    block.header.state_root = db.state_root(hashed_state)?;
+    let state_root = state_root_started.elapsed();
+    block.header.gas_used = gas_used;
+    block.header.receipts_root =
+        execution_outcome.receipts_root_slow(number).expect("receipts of the block");
+    let receipts = execution_outcome.receipts_by_block(number).iter().flatten();
+    block.header.logs_bloom =
+        receipts.clone().fold(Bloom::ZERO, |bloom, receipt| bloom | receipt.bloom_slow());
+    block.header.extra_data =
+        BlockCommitment::new(block.header.mix_hash.into(), round, part, messages_root(receipts))
+            .encode();
+    block.header.requests_root =
+        requests.as_ref().map(|requests| proofs::calculate_requests_root(&requests.0));
+    block.requests = requests;
+
+    let execution = BlockExecution {
+        block: BlockWithSenders { block, senders },
+        execution_outcome,
+        evm,
+        state_root,
+    };
+    Ok((execution, skipped))
+}
+
+/// Returns the block of the transactions built with `template`.
+fn sequenced_block(
+    template: impl Fn(Vec<TransactionSigned>) -> Block,
+    transactions: &[TransactionSignedEcRecovered],
+) -> BlockWithSenders {
+    let (body, senders): (Vec<_>, Vec<_>) =
+        transactions.iter().cloned().map(|transaction| transaction.to_components()).unzip();
+    BlockWithSenders::new(template(body), senders).expect("one sender per transaction")
+}
+
+#[cfg(all(test, feature = "execution-test-utils"))]
+mod tests {
+    use super::*;
+    use crate::{
+        commit_hooks::{CommitHook, CommitHookConfig, CommitHookError},
+        committee::StaticCommitteeProvider,
+        committee_history::EpochCommittee,
+        status::TransactionStatus,
+        timestamp::TimestampPolicy,
+        types::{Batch, Certificate},
+    };
+    use reth_beacon_consensus::{BeaconEngineMessage, OnForkChoiceUpdated};
+    use reth_chainspec::ChainSpecBuilder;
+    use reth_ethereum_engine_primitives::EthEngineTypes;
+    use reth_evm_ethereum::execute::EthExecutorProvider;
+    use reth_primitives::{
+        revm_primitives::InvalidTransaction, sign_message, Transaction, TxEip1559, TxKind,
+    };
+    use reth_provider::{
+        test_utils::{ExtendedAccount, MockEthProvider},
+        ConsensusMetadataProvider,
+    };
+    use reth_rpc_types::engine::PayloadStatus;
+    use reth_tokio_util::EventSender;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::sync::mpsc;
+
+    fn transfer(secret: u8, nonce: u64) -> TransactionSigned {
+        let transaction = Transaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1,
+            to: TxKind::Call(Address::with_last_byte(0xff)),
+            value: U256::from(1),
+            ..Default::default()
+        });
+        let signature =
+            sign_message(B256::with_last_byte(secret), transaction.signature_hash()).unwrap();
+        TransactionSigned::from_transaction_and_signature(transaction, signature)
+    }
+
+    /// Accepts every payload and forkchoice update.
+    fn engine() -> BeaconConsensusEngineHandle<EthEngineTypes> {
+        let (to_engine, mut from_executor) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(message) = from_executor.recv().await {
+                let valid = PayloadStatus::from_status(PayloadStatusEnum::Valid);
+                match message {
+                    BeaconEngineMessage::NewPayload { tx, .. } => {
+                        let _ = tx.send(Ok(valid));
+                    }
+                    BeaconEngineMessage::ForkchoiceUpdated { tx, .. } => {
+                        let _ = tx.send(Ok(OnForkChoiceUpdated::valid(valid)));
+                    }
+                    _ => {}
+                }
+            }
+        });
+        BeaconConsensusEngineHandle::new(to_engine, EventSender::default())
+    }
+
+    #[tokio::test]
+    async fn invalid_transaction_is_excluded() {
+        let chain_spec = Arc::new(ChainSpecBuilder::mainnet().shanghai_activated().build());
+        let provider = MockEthProvider::default();
+        let funded = transfer(1, 0);
+        let unfunded = transfer(2, 0);
+        provider.add_account(
+            funded.recover_signer().unwrap(),
+            ExtendedAccount::new(0, U256::from(10u128.pow(18))),
+        );
+        let parent = Header {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            ..Default::default()
+        }
+        .seal_slow();
+        let dropped = DroppedTransactions::default();
+        let receipts = RecentReceipts::default();
+        let mut executor = ConsensusOutputExecutor::new(
+            chain_spec.clone(),
+            provider,
+            EthExecutorProvider::ethereum(chain_spec),
+            engine(),
+            parent,
+        )
+        .with_dropped_transactions(dropped.clone())
+        .with_recent_receipts(receipts.clone());
+
+        let batch = Batch::new(vec![unfunded.envelope_encoded(), funded.envelope_encoded()]);
+        let sub_dag = OrderedSubDag {
+            index: 0,
+            leader: Default::default(),
+            certificates: Vec::new(),
+            batches: vec![batch],
+            timestamp: 12,
+        };
+        let blocks = executor.execute(&sub_dag).await.unwrap();
+
+        // the sender of the unfunded transaction can't pay for its gas, the block keeps the rest
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].block.body, vec![funded]);
+        assert_eq!(blocks[0].skipped.len(), 1);
+        assert_eq!(blocks[0].skipped[0].hash, unfunded.hash());
+        assert!(matches!(
+            blocks[0].skipped[0].reason,
+            SkipReason::Invalid(InvalidTransaction::LackOfFundForMaxFee { .. })
+        ));
+        assert_eq!(executor.parent().number, 1);
+        assert!(matches!(
+            dropped.status(&unfunded.hash()),
+            Some(TransactionStatus::Dropped { block_number: 1, .. })
+        ));
+        let metadata = executor.provider.consensus_metadata(1).unwrap();
+        assert_eq!(metadata, Some(consensus_metadata(&sub_dag)));
+        let receipt = receipts.get(&blocks[0].block.body[0].hash()).unwrap();
+        assert_eq!((receipt.meta.block_number, receipt.meta.index), (1, 0));
+        assert!(receipt.receipt.success);
+        assert_eq!(receipts.get(&unfunded.hash()), None);
+    }
+
+    #[tokio::test]
+    async fn executed_state_is_tracked_for_warm_start() {
+        let chain_spec = Arc::new(ChainSpecBuilder::mainnet().shanghai_activated().build());
+        let provider = MockEthProvider::default();
+        let transaction = transfer(1, 0);
+        let sender = transaction.recover_signer().unwrap();
+        provider.add_account(sender, ExtendedAccount::new(0, U256::from(10u128.pow(18))));
+        let parent = Header {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            ..Default::default()
+        }
+        .seal_slow();
+        let tracker = Arc::new(Mutex::new(HotStateTracker::new(Default::default())));
+        let mut executor = ConsensusOutputExecutor::new(
+            chain_spec.clone(),
+            provider,
+            EthExecutorProvider::ethereum(chain_spec),
+            engine(),
+            parent,
+        )
+        .with_hot_state_tracker(tracker.clone());
+
+        let sub_dag = OrderedSubDag {
+            index: 0,
+            leader: Default::default(),
+            certificates: Vec::new(),
+            batches: vec![Batch::new(vec![transaction.envelope_encoded()])],
+            timestamp: 12,
+        };
+        executor.execute(&sub_dag).await.unwrap();
+
+        let summary = tracker.lock().unwrap().summary();
+        assert!(summary.accounts.contains(&sender));
+        assert!(summary.accounts.contains(&Address::with_last_byte(0xff)));
+        assert_eq!(executor.warm_start(&summary).unwrap(), summary.accounts.len());
+    }
+
+    #[tokio::test]
+    async fn expired_transaction_is_dropped() {
+        let chain_spec = Arc::new(ChainSpecBuilder::mainnet().shanghai_activated().build());
+        let provider = MockEthProvider::default();
+        let (expired, valid) = (transfer(1, 0), transfer(2, 0));
+        for transaction in [&expired, &valid] {
+            provider.add_account(
+                transaction.recover_signer().unwrap(),
+                ExtendedAccount::new(0, U256::from(10u128.pow(18))),
+            );
+        }
+        let parent = Header {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            ..Default::default()
+        }
+        .seal_slow();
+        let dropped = DroppedTransactions::default();
+        let mut executor = ConsensusOutputExecutor::new(
+            chain_spec.clone(),
+            provider,
+            EthExecutorProvider::ethereum(chain_spec),
+            engine(),
+            parent,
+        )
+        .with_dropped_transactions(dropped.clone());
+
+        // the block is built after the parent, so the expiry at its timestamp has passed
+        let expiry = TransactionExpiry::Timestamp(0);
+        let batch = Batch::new(vec![
+            expiry.encode_batched(&expired.envelope_encoded()),
+            TransactionExpiry::Round(u64::MAX).encode_batched(&valid.envelope_encoded()),
+        ]);
+        let sub_dag = OrderedSubDag {
+            index: 0,
+            leader: Default::default(),
+            certificates: Vec::new(),
+            batches: vec![batch],
+            timestamp: 12,
+        };
+        let blocks = executor.execute(&sub_dag).await.unwrap();
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].block.body, vec![valid]);
+        assert_eq!(blocks[0].skipped.len(), 1);
+        assert_eq!(blocks[0].skipped[0].reason, SkipReason::Expired(expiry));
+        assert_eq!(
+            dropped.status(&expired.hash()),
+            Some(TransactionStatus::Expired { block_number: 1, expiry })
+        );
+    }
+
+    #[tokio::test]
+    async fn committee_is_recorded_at_first_block_of_epoch() {
+        let chain_spec = Arc::new(ChainSpecBuilder::mainnet().shanghai_activated().build());
+        let parent = Header {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            ..Default::default()
+        }
+        .seal_slow();
+        let committee = Committee { epoch: 1, authorities: Vec::new() };
+        let dir = tempfile::tempdir().unwrap();
+        let history = Arc::new(RwLock::new(CommitteeHistory::open(dir.path()).unwrap()));
+        let mut executor = ConsensusOutputExecutor::new(
+            chain_spec.clone(),
+            MockEthProvider::default(),
+            EthExecutorProvider::ethereum(chain_spec),
+            engine(),
+            parent,
+        )
+        .with_committee_history(
+            history.clone(),
+            Arc::new(StaticCommitteeProvider::new(committee.clone())),
+        );
+
+        for index in 0..2 {
+            let mut leader = Certificate::default();
+            leader.header.epoch = 1;
+            let sub_dag = OrderedSubDag {
+                index,
+                leader,
+                certificates: Vec::new(),
+                batches: Vec::new(),
+                timestamp: 12 + index,
+            };
+            executor.execute(&sub_dag).await.unwrap();
+        }
+
+        // the committee is recorded once, at the first block of the epoch
+        assert_eq!(executor.parent().number, 2);
+        let history = history.read().unwrap();
+        assert_eq!(
+            history.epochs().collect::<Vec<_>>(),
+            [&EpochCommittee { epoch: 1, first_block: 1, committee }]
+        );
+    }
+
+    /// Records whether it was called inside a deterministic scope.
+    #[derive(Debug, Default)]
+    struct ScopeHook(AtomicBool);
+
+    impl CommitHook for ScopeHook {
+        fn name(&self) -> &str {
+            "scope"
+        }
+
+        fn on_commit(&self, _input: &CommitHookInput<'_>) -> Result<(), CommitHookError> {
+            self.0.store(DeterministicScope::is_active(), Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn block_is_built_in_deterministic_scope() {
+        let chain_spec = Arc::new(ChainSpecBuilder::mainnet().shanghai_activated().build());
+        let parent = Header {
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            ..Default::default()
+        }
+        .seal_slow();
+        let hook = Arc::new(ScopeHook::default());
+        let mut hooks = CommitHooks::new(CommitHookConfig::default());
+        hooks.register(hook.clone());
+        let mut executor = ConsensusOutputExecutor::new(
+            chain_spec.clone(),
+            MockEthProvider::default(),
+            EthExecutorProvider::ethereum(chain_spec),
+            engine(),
+            parent,
+        )
+        .with_commit_hooks(hooks);
+        executor.chain_info.timestamp_policy = TimestampPolicy::LocalClock;
+
+        let sub_dag = OrderedSubDag {
+            index: 0,
+            leader: Default::default(),
+            certificates: Vec::new(),
+            batches: Vec::new(),
+            timestamp: 12,
+        };
+        let started = SystemClock.now_millis() / 1000;
+        let blocks = executor.execute(&sub_dag).await.unwrap();
+
+        // the local clock is read before the scope is entered, the hooks run inside it
+        assert!(blocks[0].block.timestamp >= started);
+        assert!(hook.0.load(Ordering::Relaxed));
+        assert!(!DeterministicScope::is_active());
+    }
+}
@@ -0,0 +1,505 @@
+//! Hot standby failover between nodes that share one validator identity.
+//!
+//! An operator runs the validator on an active node and a standby node with the same keys. Only
+//! the holder of the [`Lease`] in a shared [`LeaseStore`] may sign, and the standby takes over the
+//! lease once the active node stopped renewing it before it expired.
+//!
+//! Expiry alone doesn't prevent double signing: the former holder may have signed a round just
+//! before its lease expired, and a stalled process may sign after it. Therefore a node authorizes
+//! every round it signs with [`SigningLease::authorize`], which records the round in the lease
+//! with a compare-and-swap on the store:
+//!
+//! - a node that lost the lease, or whose lease expires within the configured clock drift, can't
+//!   authorize any round, even if it didn't notice the takeover yet;
+//! - a node that took over the lease only signs rounds above the last round the former holder
+//!   authorized, so it never signs a round twice.
+//!
+//! The [`FileLeaseStore`] keeps the lease in a file on storage shared by both nodes. Other
+//! coordination services, e.g. etcd or a database, implement [`LeaseStore`] on top of their own
+//! compare-and-swap. A node with a [lease file](FailoverConfig::lease_file) authorizes the headers
+//! of its [`Primary`](crate::primary::Primary) with the lease.
+
+use crate::{
+    determinism::{Clock, SystemClock},
+    types::Round,
+};
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Configuration of the [`SigningLease`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FailoverConfig {
+    /// The file of the lease, on storage shared by the active and the standby node. Without one,
+    /// the node signs without a lease.
+    pub lease_file: Option<PathBuf>,
+    /// The id of this node in the lease, which must differ between the active and the standby
+    /// node.
+    pub node_id: String,
+    /// How long a lease is valid after it was acquired or renewed, and thus how long the standby
+    /// waits before it takes over from a failed node.
+    #[serde(with = "humantime_serde")]
+    pub lease_ttl: Duration,
+    /// How often the holder renews the lease, and the standby checks whether it expired.
+    #[serde(with = "humantime_serde")]
+    pub renew_interval: Duration,
+    /// The maximum difference between the clocks of the nodes. The holder stops signing this long
+    /// before its lease expires.
+    #[serde(with = "humantime_serde")]
+    pub max_clock_drift: Duration,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            lease_file: None,
+            node_id: String::new(),
+            lease_ttl: Duration::from_secs(10),
+            renew_interval: Duration::from_secs(2),
+            max_clock_drift: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The lease that entitles a node to sign for the validator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lease {
+    /// The id of the node that holds the lease.
+    pub holder: String,
+    /// Incremented whenever another node takes over the lease.
+    pub term: u64,
+    /// When the lease expires, in milliseconds since the unix epoch.
+    pub expires_at: u64,
+    /// The highest round any holder authorized for signing.
+    pub last_signed_round: Round,
+}
+
+/// Errors of the [`SigningLease`] and the [`LeaseStore`].
+#[derive(Debug, thiserror::Error)]
+pub enum FailoverError {
+    /// The lease couldn't be read or written.
+    #[error("failed to access lease at {path}: {err}")]
+    Io {
+        /// The path of the lease.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        err: io::Error,
+    },
+    /// The stored lease is malformed.
+    #[error("malformed lease at {path}: {message}")]
+    Malformed {
+        /// The path of the lease.
+        path: PathBuf,
+        /// The parser error.
+        message: String,
+    },
+    /// Another node holds the lease.
+    #[error("lease is held by {holder}")]
+    NotHolder {
+        /// The id of the node that holds the lease.
+        holder: String,
+    },
+    /// The lease expires within the maximum clock drift.
+    #[error("lease expired")]
+    Expired,
+    /// The round is at or below the last round the former holder authorized.
+    #[error(
+        "round {round} may have been signed by the former holder, which signed up to round \
+         {last_signed_round}"
+    )]
+    RoundSignedByFormerHolder {
+        /// The round to sign.
+        round: Round,
+        /// The last round the former holder authorized.
+        last_signed_round: Round,
+    },
+    /// The lease changed concurrently.
+    #[error("lease changed concurrently")]
+    Conflict,
+}
+
+/// Storage of the [`Lease`] that is shared by the nodes of a validator.
+pub trait LeaseStore: Debug + Send + Sync {
+    /// Returns the current lease, if any node ever acquired it.
+    fn load(&self) -> Result<Option<Lease>, FailoverError>;
+
+    /// Replaces the lease with `new` if it's still `current`, and returns whether it was
+    /// replaced.
+    fn compare_and_swap(&self, current: Option<&Lease>, new: &Lease)
+        -> Result<bool, FailoverError>;
+}
+
+/// A [`LeaseStore`] in memory, for nodes in the same process and tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLeaseStore {
+    lease: Arc<Mutex<Option<Lease>>>,
+}
+
+impl LeaseStore for MemoryLeaseStore {
+    fn load(&self) -> Result<Option<Lease>, FailoverError> {
+        Ok(self.lease.lock().unwrap_or_else(PoisonError::into_inner).clone())
+    }
+
+    fn compare_and_swap(
+        &self,
+        current: Option<&Lease>,
+        new: &Lease,
+    ) -> Result<bool, FailoverError> {
+        let mut lease = self.lease.lock().unwrap_or_else(PoisonError::into_inner);
+        if lease.as_ref() != current {
+            return Ok(false)
+        }
+        *lease = Some(new.clone());
+        Ok(true)
+    }
+}
+
+/// A [`LeaseStore`] in a JSON file, e.g. on a network file system mounted by both nodes.
+///
+/// A compare-and-swap holds a lock file next to the lease while it reads and replaces the lease.
+/// A lock file that is older than `stale_lock_timeout` was left behind by a crashed node and is
+/// removed.
+#[derive(Debug, Clone)]
+pub struct FileLeaseStore {
+    path: PathBuf,
+    lock_path: PathBuf,
+    stale_lock_timeout: Duration,
+}
+
+impl FileLeaseStore {
+    /// Creates a store of the lease in the file at the given path.
+    pub fn new(path: PathBuf) -> Self {
+        let lock_path = path.with_extension("lock");
+        Self { path, lock_path, stale_lock_timeout: Duration::from_secs(30) }
+    }
+
+    /// Sets the age after which a lock file is considered stale.
+    pub const fn with_stale_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.stale_lock_timeout = timeout;
+        self
+    }
+
+    fn io_error(&self, err: io::Error) -> FailoverError {
+        FailoverError::Io { path: self.path.clone(), err }
+    }
+
+    /// Creates the lock file, returns `false` if another node holds it.
+    fn lock(&self) -> Result<bool, FailoverError> {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&self.lock_path) {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                let stale = fs::metadata(&self.lock_path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|age| age > self.stale_lock_timeout);
+                if stale {
+                    warn!(
+                        target: "consensus::narwhal",
+                        path = ?self.lock_path,
+                        "Removing stale lease lock"
+                    );
+                    let _ = fs::remove_file(&self.lock_path);
+                }
+                Ok(false)
+            }
+            Err(err) => Err(self.io_error(err)),
+        }
+    }
+}
+
+impl LeaseStore for FileLeaseStore {
+    fn load(&self) -> Result<Option<Lease>, FailoverError> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(self.io_error(err)),
+        };
+        serde_json::from_slice(&contents).map(Some).map_err(|err| FailoverError::Malformed {
+            path: self.path.clone(),
+            message: err.to_string(),
+        })
+    }
+
+    fn compare_and_swap(
+        &self,
+        current: Option<&Lease>,
+        new: &Lease,
+    ) -> Result<bool, FailoverError> {
+        if !self.lock()? {
+            return Ok(false)
+        }
+        let result = (|| {
+            if self.load()?.as_ref() != current {
+                return Ok(false)
+            }
+            let tmp = self.path.with_extension("tmp");
+            let contents = serde_json::to_vec(new).expect("lease serializes");
+            fs::write(&tmp, contents).map_err(|err| self.io_error(err))?;
+            fs::rename(&tmp, &self.path).map_err(|err| self.io_error(err))?;
+            Ok(true)
+        })();
+        let _ = fs::remove_file(&self.lock_path);
+        result
+    }
+}
+
+/// Metrics of the [`SigningLease`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.failover")]
+struct FailoverMetrics {
+    /// Whether the node holds the lease and may sign
+    holder: Gauge,
+    /// Number of times the node took over the lease from another node
+    takeovers: Counter,
+    /// Number of rounds the node was denied to sign
+    denied_rounds: Counter,
+}
+
+/// The state of a [`SigningLease`] while its node holds the lease.
+#[derive(Debug, Clone, Copy)]
+struct HeldLease {
+    /// The term of the lease.
+    term: u64,
+    /// The first round the node may sign in its term.
+    first_round: Round,
+}
+
+/// The view of one node on the shared [`Lease`].
+///
+/// All methods take the current time in milliseconds since the unix epoch. Cloning is cheap, all
+/// clones share the same view, e.g. the task that renews the lease and the components that sign.
+#[derive(Debug, Clone)]
+pub struct SigningLease {
+    store: Arc<dyn LeaseStore>,
+    /// The id of this node.
+    node: Arc<str>,
+    config: FailoverConfig,
+    held: Arc<Mutex<Option<HeldLease>>>,
+    metrics: Arc<FailoverMetrics>,
+}
+
+impl SigningLease {
+    /// Creates the view of the node with the given id on the lease in the store.
+    pub fn new(store: Arc<dyn LeaseStore>, node: &str, config: FailoverConfig) -> Self {
+        Self {
+            store,
+            node: node.into(),
+            config,
+            held: Default::default(),
+            metrics: Default::default(),
+        }
+    }
+
+    /// Returns `true` if this node held the lease when it last acquired or renewed it.
+    pub fn is_holder(&self) -> bool {
+        self.held().is_some()
+    }
+
+    /// Acquires the lease if it's free or expired, or renews it if this node holds it.
+    ///
+    /// Returns whether this node holds the lease afterwards.
+    pub fn acquire(&self, now: u64) -> Result<bool, FailoverError> {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = self.store.load()?;
+        let expires_at = now + self.config.lease_ttl.as_millis() as u64;
+        let term = held.map(|held| held.term);
+        let new = match &current {
+            Some(lease) if self.is_held_by_us(lease, term) => Lease { expires_at, ..lease.clone() },
+            Some(lease) if now < lease.expires_at => {
+                self.lose(&mut held);
+                return Ok(false)
+            }
+            lease => Lease {
+                holder: self.node.to_string(),
+                term: lease.as_ref().map_or(0, |lease| lease.term) + 1,
+                expires_at,
+                last_signed_round: lease.as_ref().map_or(0, |lease| lease.last_signed_round),
+            },
+        };
+        if !self.store.compare_and_swap(current.as_ref(), &new)? {
+            self.lose(&mut held);
+            return Ok(false)
+        }
+        if term != Some(new.term) {
+            self.metrics.takeovers.increment(1);
+            self.metrics.holder.set(1.0);
+            info!(
+                target: "consensus::narwhal",
+                term = new.term,
+                last_signed_round = new.last_signed_round,
+                "Acquired signing lease"
+            );
+            *held = Some(HeldLease { term: new.term, first_round: new.last_signed_round + 1 });
+        }
+        Ok(true)
+    }
+
+    /// Authorizes this node to sign messages of the given round.
+    ///
+    /// Must be called, and succeed, before every signature. The round is recorded in the lease,
+    /// so that a node that takes over the lease doesn't sign it again.
+    pub fn authorize(&self, round: Round, now: u64) -> Result<(), FailoverError> {
+        let result = self.try_authorize(round, now);
+        if result.is_err() {
+            self.metrics.denied_rounds.increment(1);
+        }
+        result
+    }
+
+    fn try_authorize(&self, round: Round, now: u64) -> Result<(), FailoverError> {
+        let mut held = self.held.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = self.store.load()?;
+        let (lease, first_round) = match (&current, *held) {
+            (Some(lease), Some(ours)) if self.is_held_by_us(lease, Some(ours.term)) => {
+                (lease, ours.first_round)
+            }
+            (lease, _) => {
+                self.lose(&mut held);
+                let holder = lease.as_ref().map(|lease| lease.holder.clone()).unwrap_or_default();
+                return Err(FailoverError::NotHolder { holder })
+            }
+        };
+        if now + self.config.max_clock_drift.as_millis() as u64 >= lease.expires_at {
+            return Err(FailoverError::Expired)
+        }
+        if round < first_round {
+            return Err(FailoverError::RoundSignedByFormerHolder {
+                round,
+                last_signed_round: first_round - 1,
+            })
+        }
+        if round <= lease.last_signed_round {
+            return Ok(())
+        }
+        let new = Lease { last_signed_round: round, ..lease.clone() };
+        if !self.store.compare_and_swap(current.as_ref(), &new)? {
+            return Err(FailoverError::Conflict)
+        }
+        Ok(())
+    }
+
+    /// Acquires and renews the lease every `renew_interval`, until all receivers of `holder` are
+    /// dropped.
+    ///
+    /// The channel holds whether this node holds the lease. Nodes start their signing components
+    /// once they hold it, but must still authorize every round.
+    pub async fn run(self, holder: watch::Sender<bool>) {
+        let mut interval = tokio::time::interval(self.config.renew_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        while !holder.is_closed() {
+            interval.tick().await;
+            let held = self.acquire(SystemClock.now_millis()).unwrap_or_else(|err| {
+                warn!(target: "consensus::narwhal", %err, "Failed to renew signing lease");
+                self.lose(&mut self.held.lock().unwrap_or_else(PoisonError::into_inner));
+                false
+            });
+            holder.send_if_modified(|current| std::mem::replace(current, held) != held);
+        }
+    }
+
+    fn held(&self) -> Option<HeldLease> {
+        *self.held.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn is_held_by_us(&self, lease: &Lease, term: Option<u64>) -> bool {
+        *lease.holder == *self.node && Some(lease.term) == term
+    }
+
+    fn lose(&self, held: &mut Option<HeldLease>) {
+        if let Some(HeldLease { term, .. }) = held.take() {
+            self.metrics.holder.set(0.0);
+            warn!(target: "consensus::narwhal", term, "Lost signing lease");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lease(store: &Arc<dyn LeaseStore>, node: &str) -> SigningLease {
+        SigningLease::new(store.clone(), node, FailoverConfig::default())
+    }
+
+    #[test]
+    fn failover_without_double_signing() {
+        let store: Arc<dyn LeaseStore> = Arc::new(MemoryLeaseStore::default());
+        let (active, standby) = (lease(&store, "active"), lease(&store, "standby"));
+
+        assert!(active.acquire(1_000).unwrap());
+        assert!(!standby.acquire(1_000).unwrap());
+        active.authorize(5, 2_000).unwrap();
+        assert!(matches!(standby.authorize(5, 2_000), Err(FailoverError::NotHolder { .. })));
+
+        // the active node renews until it stalls, and doesn't sign close to the expiry
+        assert!(active.acquire(5_000).unwrap());
+        assert!(matches!(active.authorize(6, 14_500), Err(FailoverError::Expired)));
+        assert!(!standby.acquire(14_999).unwrap());
+
+        // the standby takes over, but doesn't sign the rounds the active node may have signed
+        assert!(standby.acquire(15_000).unwrap());
+        assert_eq!(store.load().unwrap().unwrap().term, 2);
+        assert!(matches!(
+            standby.authorize(5, 15_000),
+            Err(FailoverError::RoundSignedByFormerHolder { round: 5, last_signed_round: 5 })
+        ));
+        standby.authorize(6, 15_000).unwrap();
+        standby.authorize(6, 15_001).unwrap();
+
+        // the stalled node resumes, but can neither sign nor renew
+        assert!(active.is_holder());
+        assert!(matches!(active.authorize(7, 15_000), Err(FailoverError::NotHolder { .. })));
+        assert!(!active.is_holder());
+        assert!(!active.acquire(16_000).unwrap());
+        assert_eq!(store.load().unwrap().unwrap().last_signed_round, 6);
+    }
+
+    #[test]
+    fn concurrent_acquire() {
+        let store = MemoryLeaseStore::default();
+        let lease = |holder: &str| Lease {
+            holder: holder.to_string(),
+            term: 1,
+            expires_at: 10_000,
+            last_signed_round: 0,
+        };
+        assert!(store.compare_and_swap(None, &lease("a")).unwrap());
+        assert!(!store.compare_and_swap(None, &lease("b")).unwrap());
+        assert_eq!(store.load().unwrap(), Some(lease("a")));
+    }
+
+    #[test]
+    fn file_lease_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileLeaseStore::new(dir.path().join("lease.json"))
+            .with_stale_lock_timeout(Duration::from_secs(3600));
+        let first =
+            Lease { holder: "a".to_string(), term: 1, expires_at: 10, last_signed_round: 0 };
+        let second = Lease { term: 2, ..first.clone() };
+        assert_eq!(store.load().unwrap(), None);
+        assert!(store.compare_and_swap(None, &first).unwrap());
+        assert!(!store.compare_and_swap(None, &second).unwrap());
+        assert!(store.compare_and_swap(Some(&first), &second).unwrap());
+        assert_eq!(store.load().unwrap(), Some(second.clone()));
+
+        // a node holds the lock
+        fs::write(dir.path().join("lease.lock"), b"").unwrap();
+        assert!(!store.compare_and_swap(Some(&second), &first).unwrap());
+        assert_eq!(store.load().unwrap(), Some(second));
+    }
+}
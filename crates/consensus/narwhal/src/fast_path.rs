@@ -0,0 +1,335 @@
+//! The fast path of the commit rule for unanimous rounds.
+//!
+//! The commit rule decides the leader of an even round `r` from the certificates of round `r + 1`
+//! that reference it: the leader is committed once their stake reaches the validity threshold, see
+//! [`direct_decision`]. [`Bullshark`](crate::bullshark::Bullshark) takes the decision when the
+//! DAG reaches round `r + 2`, because a certificate of round `r + 1` that arrives late may still
+//! add the missing support.
+//!
+//! An authority certifies at most one header per round, so once the certificates of every
+//! authority of round `r + 1` arrived, no certificate can change the decision anymore. When they
+//! arrive within the [`FastPathConfig::window`], the [`FastPath`] commits the leader right away,
+//! one round earlier. It sees the same certificates of round `r + 1` as the commit rule at round
+//! `r + 2`, so the fast path changes when a leader is committed, but never which leaders are.
+//!
+//! Rounds that are not unanimous within the window are left to the commit rule, so that the fast
+//! path keeps no state for the rounds of an offline authority. The fast path is enabled for all
+//! validators of a chain with the `fastPathCommit` chain parameter, with which the
+//! [`Committer`](crate::committer::Committer) feeds it every certificate and lets the commit rule
+//! decide the leaders of the unanimous rounds, see
+//! [`Bullshark::try_commit_unanimous`](crate::bullshark::Bullshark::try_commit_unanimous).
+
+use crate::{
+    commit_log::{CommitDecision, CommitOutcome},
+    committee::Committee,
+    types::{Certificate, CertificateDigest, Round},
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_narwhal_verifier::{AuthorityIndex, Stake};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+/// Configuration of the [`FastPath`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FastPathConfig {
+    /// The time from the first to the last certificate of a unanimous round.
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+}
+
+impl Default for FastPathConfig {
+    fn default() -> Self {
+        Self { window: Duration::from_millis(200) }
+    }
+}
+
+/// Decides the leader of a round from the certificates of the following round.
+///
+/// `leader_digest` is the digest of the leader's certificate, `None` if the DAG has none. The
+/// certificates of other rounds are ignored.
+pub fn direct_decision<'a>(
+    committee: &Committee,
+    round: Round,
+    leader: AuthorityIndex,
+    leader_digest: Option<CertificateDigest>,
+    next_round: impl IntoIterator<Item = &'a Certificate>,
+) -> CommitDecision {
+    let Some(leader_digest) = leader_digest else {
+        return CommitDecision { round, leader, outcome: CommitOutcome::MissingCertificate }
+    };
+    // an authority has at most one certificate per round, repeated certificates count once
+    let supporters = next_round
+        .into_iter()
+        .filter(|certificate| {
+            certificate.round() == round + 1 && certificate.header.parents.contains(&leader_digest)
+        })
+        .map(Certificate::author)
+        .collect::<BTreeSet<_>>();
+    let support = supporters
+        .iter()
+        .filter_map(|author| committee.authority(*author))
+        .fold(0, |support: Stake, authority| support.saturating_add(authority.stake));
+    let threshold = committee.validity_threshold();
+    let outcome = if support >= threshold {
+        CommitOutcome::Committed { support }
+    } else {
+        CommitOutcome::InsufficientSupport { support, threshold }
+    };
+    CommitDecision { round, leader, outcome }
+}
+
+/// Metrics of the [`FastPath`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.fast_path")]
+struct FastPathMetrics {
+    /// Number of leaders committed by the fast path
+    commits: Counter,
+    /// Number of rounds that were not unanimous within the window
+    expired_rounds: Counter,
+}
+
+/// The certificates of a round that arrived so far.
+#[derive(Debug)]
+struct PendingRound {
+    /// The arrival time of the first certificate, in milliseconds.
+    first_arrival: u64,
+    certificates: BTreeMap<AuthorityIndex, Certificate>,
+}
+
+/// Commits the leaders of rounds whose following round is unanimous, see the [module
+/// docs](self).
+#[derive(Debug)]
+pub struct FastPath {
+    committee: Committee,
+    window: u64,
+    rounds: BTreeMap<Round, PendingRound>,
+    /// The rounds below are decided or expired, their certificates are ignored.
+    next_round: Round,
+    metrics: FastPathMetrics,
+}
+
+impl FastPath {
+    /// Creates the fast path of the committee's epoch.
+    pub fn new(committee: Committee, config: FastPathConfig) -> Self {
+        Self {
+            committee,
+            window: u64::try_from(config.window.as_millis()).unwrap_or(u64::MAX),
+            rounds: BTreeMap::new(),
+            next_round: 0,
+            metrics: Default::default(),
+        }
+    }
+
+    /// Adds a certificate of the DAG that arrived at `now`, in milliseconds, and returns the
+    /// commit of the leader it enables.
+    ///
+    /// `leader` returns the leader of an even round and the digest of its certificate, if the DAG
+    /// has it. It's only called once the round after the leader's is unanimous.
+    pub fn add_certificate<F>(
+        &mut self,
+        certificate: &Certificate,
+        now: u64,
+        leader: F,
+    ) -> Option<CommitDecision>
+    where
+        F: FnOnce(Round) -> Option<(AuthorityIndex, Option<CertificateDigest>)>,
+    {
+        self.expire(now);
+        let round = certificate.round();
+        // only the rounds that vote for a leader, the leaders of round 0 are genesis certificates
+        let unknown_author = self.committee.authority(certificate.author()).is_none();
+        if round % 2 == 0 || round < 3 || round < self.next_round || unknown_author {
+            return None
+        }
+
+        let pending = self
+            .rounds
+            .entry(round)
+            .or_insert_with(|| PendingRound { first_arrival: now, certificates: BTreeMap::new() });
+        pending.certificates.entry(certificate.author()).or_insert_with(|| certificate.clone());
+        if pending.certificates.len() < self.committee.authorities.len() {
+            return None
+        }
+
+        let pending = self.rounds.remove(&round)?;
+        self.prune(round + 1);
+        let (leader, leader_digest) = leader(round - 1)?;
+        let decision = direct_decision(
+            &self.committee,
+            round - 1,
+            leader,
+            leader_digest,
+            pending.certificates.values(),
+        );
+        if !decision.outcome.is_committed() {
+            return None
+        }
+        self.metrics.commits.increment(1);
+        Some(decision)
+    }
+
+    /// Drops the rounds whose window closed before `now`, together with the rounds below them.
+    fn expire(&mut self, now: u64) {
+        let window = self.window;
+        let expired = self
+            .rounds
+            .iter()
+            .filter(|(_, pending)| now.saturating_sub(pending.first_arrival) > window)
+            .map(|(round, _)| *round)
+            .max();
+        if let Some(round) = expired {
+            let before = self.rounds.len();
+            self.prune(round + 1);
+            self.metrics.expired_rounds.increment((before - self.rounds.len()) as u64);
+        }
+    }
+
+    /// Forgets the rounds below the given round, e.g. once the commit rule decided their leaders.
+    pub fn prune(&mut self, round: Round) {
+        self.next_round = self.next_round.max(round);
+        self.rounds = self.rounds.split_off(&self.next_round);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{committee::Authority, types::Header};
+    use alloy_primitives::{Bytes, B256};
+    use proptest::{collection::vec, prelude::*};
+
+    const LEADER: CertificateDigest = CertificateDigest(B256::repeat_byte(1));
+
+    fn committee(stakes: &[Stake]) -> Committee {
+        let authorities = stakes
+            .iter()
+            .enumerate()
+            .map(|(index, stake)| Authority {
+                public_key: Bytes::from(vec![index as u8]),
+                proof_of_possession: Bytes::new(),
+                stake: *stake,
+                primary_address: ([127, 0, 0, 1], 30400).into(),
+                worker_addresses: Vec::new(),
+            })
+            .collect();
+        Committee { epoch: 1, authorities }
+    }
+
+    /// A certificate of round 5 that references the leader of round 4 if `supports`.
+    fn certificate(author: usize, supports: bool) -> Certificate {
+        let parent = if supports { LEADER } else { CertificateDigest(B256::repeat_byte(2)) };
+        let header = Header {
+            round: 5,
+            author: author as AuthorityIndex,
+            parents: vec![parent],
+            ..Default::default()
+        };
+        Certificate { header, ..Default::default() }
+    }
+
+    fn leader(round: Round) -> Option<(AuthorityIndex, Option<CertificateDigest>)> {
+        (round == 4).then_some((0, Some(LEADER)))
+    }
+
+    #[test]
+    fn unanimous_round() {
+        let mut fast_path = FastPath::new(committee(&[1, 1, 1, 1]), FastPathConfig::default());
+        let supports = [true, false, true, false];
+        for (author, supports) in supports.into_iter().enumerate().take(3) {
+            assert_eq!(fast_path.add_certificate(&certificate(author, supports), 10, leader), None);
+        }
+        // a repeated certificate doesn't complete the round
+        assert_eq!(fast_path.add_certificate(&certificate(0, true), 10, leader), None);
+
+        let decision = fast_path.add_certificate(&certificate(3, false), 150, leader);
+        assert_eq!(
+            decision,
+            Some(CommitDecision {
+                round: 4,
+                leader: 0,
+                outcome: CommitOutcome::Committed { support: 2 }
+            })
+        );
+        // the round is decided once
+        assert_eq!(fast_path.add_certificate(&certificate(3, false), 150, leader), None);
+    }
+
+    #[test]
+    fn slow_or_unsupported_rounds() {
+        let config = FastPathConfig { window: Duration::from_millis(100) };
+        let mut fast_path = FastPath::new(committee(&[1, 1, 1, 1]), config);
+        for author in 0..3 {
+            fast_path.add_certificate(&certificate(author, true), 0, leader);
+        }
+        // the last certificate arrives after the window, and the round doesn't start over
+        assert_eq!(fast_path.add_certificate(&certificate(3, true), 101, leader), None);
+        assert!(fast_path.rounds.is_empty());
+        for author in 0..4 {
+            assert_eq!(fast_path.add_certificate(&certificate(author, true), 150, leader), None);
+        }
+
+        // a unanimous round without enough support is left to the commit rule
+        let mut fast_path = FastPath::new(committee(&[1, 1, 1, 1]), config);
+        for author in 0..4 {
+            let decision = fast_path.add_certificate(&certificate(author, author == 0), 0, leader);
+            assert_eq!(decision, None);
+        }
+
+        // pruned rounds are decided by the commit rule
+        let mut fast_path = FastPath::new(committee(&[1]), config);
+        fast_path.prune(6);
+        assert_eq!(fast_path.add_certificate(&certificate(0, true), 0, leader), None);
+    }
+
+    proptest! {
+        /// The fast path commits a leader only if all authorities certified the following round
+        /// within the window, and its decision is the one of the commit rule on that round.
+        #[test]
+        fn agrees_with_commit_rule(
+            authorities in vec((1u64..10, any::<bool>(), prop::option::of(0u64..200)), 1..8),
+            duplicates in vec(0usize..8, 0..4),
+        ) {
+            let stakes = authorities.iter().map(|(stake, ..)| *stake).collect::<Vec<_>>();
+            let committee = committee(&stakes);
+            let config = FastPathConfig { window: Duration::from_millis(100) };
+            let mut fast_path = FastPath::new(committee.clone(), config);
+
+            // the certificates of round 5 in order of arrival, some authorities never certify it
+            let mut arrivals = authorities
+                .iter()
+                .enumerate()
+                .filter_map(|(author, (_, supports, arrival))| {
+                    arrival.map(|arrival| (arrival, certificate(author, *supports)))
+                })
+                .collect::<Vec<_>>();
+            for duplicate in duplicates {
+                if let Some(arrival) = arrivals.get(duplicate).cloned() {
+                    arrivals.push(arrival);
+                }
+            }
+            arrivals.sort_by_key(|(arrival, _)| *arrival);
+
+            let decisions = arrivals
+                .iter()
+                .filter_map(|(arrival, certificate)| {
+                    fast_path.add_certificate(certificate, *arrival, leader)
+                })
+                .collect::<Vec<_>>();
+
+            let certificates = arrivals.iter().map(|(_, certificate)| certificate);
+            let expected = direct_decision(&committee, 4, 0, Some(LEADER), certificates);
+            let unanimous = authorities.iter().all(|(_, _, arrival)| arrival.is_some()) &&
+                arrivals.last().is_some_and(|(last, _)| last - arrivals[0].0 <= 100);
+
+            if unanimous && expected.outcome.is_committed() {
+                prop_assert_eq!(decisions, vec![expected]);
+            } else {
+                prop_assert!(decisions.is_empty());
+            }
+        }
+    }
+}
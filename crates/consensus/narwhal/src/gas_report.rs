@@ -0,0 +1,266 @@
+//! Gas throughput reports of the executed commits, for capacity planning.
+//!
+//! Whether the hardware of a validator sustains the gas throughput of a chain is best answered
+//! with the blocks it actually executed. The executor times the phases of the block of every commit
+//! and hands a [`CommitGasReport`] to the [`GasReporter`], which records it as metrics and appends
+//! it as a line of JSON to the [`GasReportConfig::path`], if set.
+//!
+//! The phases are reported separately, because they scale with different resources:
+//!
+//! - `io`: reading the parent state for sequencing, i.e. the nonces of the senders and the index of
+//!   executed transactions.
+//! - `sequencing`: decoding the transactions, recovering their senders and sequencing them.
+//! - `evm`: executing the transactions.
+//! - `stateRoot`: computing the state root of the block.
+//! - `engine`: submitting the block to the engine until it's the canonical head, which includes its
+//!   validation and persistence.
+
+use reth_metrics::{
+    metrics::{Counter, Histogram},
+    Metrics,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
+};
+use tracing::warn;
+
+/// Configuration of the [`GasReporter`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GasReportConfig {
+    /// The file the reports are appended to as lines of JSON, only metrics are recorded if
+    /// `None`.
+    pub path: Option<PathBuf>,
+}
+
+/// The time spent in the phases of building and importing the block of a commit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// Reading the parent state for sequencing.
+    pub io: Duration,
+    /// Decoding, recovering and sequencing the transactions, without the reads of the state.
+    pub sequencing: Duration,
+    /// Executing the transactions.
+    pub evm: Duration,
+    /// Computing the state root.
+    pub state_root: Duration,
+    /// Submitting the block to the engine until it's the canonical head.
+    pub engine: Duration,
+}
+
+impl PhaseTimings {
+    /// Returns the time spent in all phases.
+    pub fn total(&self) -> Duration {
+        self.io + self.sequencing + self.evm + self.state_root + self.engine
+    }
+}
+
+/// The gas throughput of the block of a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitGasReport {
+    /// The index of the committed sub-dag.
+    pub sub_dag: u64,
+    /// The number of the block.
+    pub block_number: u64,
+    /// The number of transactions of the block.
+    pub transactions: u64,
+    /// The gas used by the block.
+    pub gas_used: u64,
+    /// The gas limit of the block.
+    pub gas_limit: u64,
+    /// The gas used per second of the time spent in all phases.
+    pub gas_per_second: u64,
+    /// The gas used per second of the time spent in the EVM.
+    pub evm_gas_per_second: u64,
+    /// The time spent in all phases, in microseconds.
+    pub total_micros: u64,
+    /// The time spent reading the parent state for sequencing, in microseconds.
+    pub io_micros: u64,
+    /// The time spent sequencing the transactions, in microseconds.
+    pub sequencing_micros: u64,
+    /// The time spent executing the transactions, in microseconds.
+    pub evm_micros: u64,
+    /// The time spent computing the state root, in microseconds.
+    pub state_root_micros: u64,
+    /// The time spent submitting the block to the engine, in microseconds.
+    pub engine_micros: u64,
+}
+
+impl CommitGasReport {
+    /// Creates the report of the block of a sub-dag.
+    pub fn new(
+        sub_dag: u64,
+        block_number: u64,
+        transactions: u64,
+        gas_used: u64,
+        gas_limit: u64,
+        timings: &PhaseTimings,
+    ) -> Self {
+        Self {
+            sub_dag,
+            block_number,
+            transactions,
+            gas_used,
+            gas_limit,
+            gas_per_second: per_second(gas_used, timings.total()),
+            evm_gas_per_second: per_second(gas_used, timings.evm),
+            total_micros: micros(timings.total()),
+            io_micros: micros(timings.io),
+            sequencing_micros: micros(timings.sequencing),
+            evm_micros: micros(timings.evm),
+            state_root_micros: micros(timings.state_root),
+            engine_micros: micros(timings.engine),
+        }
+    }
+}
+
+/// Returns the amount per second of the elapsed time, 0 if no time elapsed.
+fn per_second(amount: u64, elapsed: Duration) -> u64 {
+    if elapsed.is_zero() {
+        return 0
+    }
+    (amount as f64 / elapsed.as_secs_f64()) as u64
+}
+
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
+
+/// Metrics of the [`GasReporter`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.gas")]
+struct GasReportMetrics {
+    /// Gas used by the block of a commit
+    gas_used: Histogram,
+    /// Gas used per second of the time spent in all phases of a block
+    gas_per_second: Histogram,
+    /// Gas used per second of the time spent in the EVM
+    evm_gas_per_second: Histogram,
+    /// Time spent reading the parent state for sequencing, in seconds
+    io_duration: Histogram,
+    /// Time spent sequencing the transactions, in seconds
+    sequencing_duration: Histogram,
+    /// Time spent executing the transactions, in seconds
+    evm_duration: Histogram,
+    /// Time spent computing the state root, in seconds
+    state_root_duration: Histogram,
+    /// Time spent submitting the block to the engine, in seconds
+    engine_duration: Histogram,
+    /// Number of reports that could not be written to the file
+    failed_writes: Counter,
+}
+
+/// Records the [`CommitGasReport`]s of the executed commits.
+#[derive(Default)]
+pub struct GasReporter {
+    writer: Option<Box<dyn Write + Send + Sync>>,
+    metrics: GasReportMetrics,
+}
+
+impl GasReporter {
+    /// Creates a reporter that records the reports as metrics, and appends them to the file of
+    /// the configuration, which is created if it doesn't exist.
+    pub fn new(config: &GasReportConfig) -> io::Result<Self> {
+        let Some(path) = &config.path else { return Ok(Self::default()) };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::with_writer(BufWriter::new(file)))
+    }
+
+    /// Creates a reporter that records the reports as metrics, and writes them to the writer.
+    pub fn with_writer(writer: impl Write + Send + Sync + 'static) -> Self {
+        Self { writer: Some(Box::new(writer)), metrics: Default::default() }
+    }
+
+    /// Records the report of a commit.
+    ///
+    /// A report that can't be written to the file is only logged, so that reporting never stops
+    /// the executor.
+    pub fn record(&mut self, report: &CommitGasReport) {
+        self.metrics.gas_used.record(report.gas_used as f64);
+        self.metrics.gas_per_second.record(report.gas_per_second as f64);
+        self.metrics.evm_gas_per_second.record(report.evm_gas_per_second as f64);
+        for (histogram, micros) in [
+            (&self.metrics.io_duration, report.io_micros),
+            (&self.metrics.sequencing_duration, report.sequencing_micros),
+            (&self.metrics.evm_duration, report.evm_micros),
+            (&self.metrics.state_root_duration, report.state_root_micros),
+            (&self.metrics.engine_duration, report.engine_micros),
+        ] {
+            histogram.record(micros as f64 / 1_000_000.0);
+        }
+
+        let Some(writer) = &mut self.writer else { return };
+        let written = serde_json::to_writer(&mut *writer, report)
+            .map_err(io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"))
+            .and_then(|()| writer.flush());
+        if let Err(err) = written {
+            self.metrics.failed_writes.increment(1);
+            warn!(
+                target: "consensus::narwhal",
+                %err,
+                sub_dag = report.sub_dag,
+                "Failed to write gas report"
+            );
+        }
+    }
+}
+
+impl fmt::Debug for GasReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GasReporter").field("writes", &self.writer.is_some()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_throughput() {
+        let timings = PhaseTimings {
+            io: Duration::from_millis(20),
+            sequencing: Duration::from_millis(30),
+            evm: Duration::from_millis(250),
+            state_root: Duration::from_millis(100),
+            engine: Duration::from_millis(100),
+        };
+        let report = CommitGasReport::new(7, 12, 100, 15_000_000, 30_000_000, &timings);
+        assert_eq!(report.total_micros, 500_000);
+        assert_eq!(report.gas_per_second, 30_000_000);
+        assert_eq!(report.evm_gas_per_second, 60_000_000);
+        assert_eq!(report.evm_micros, 250_000);
+
+        let empty = CommitGasReport::new(8, 13, 0, 0, 30_000_000, &PhaseTimings::default());
+        assert_eq!((empty.gas_per_second, empty.evm_gas_per_second), (0, 0));
+    }
+
+    #[test]
+    fn append_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = GasReportConfig { path: Some(dir.path().join("gas.jsonl")) };
+        let timings = PhaseTimings { evm: Duration::from_millis(10), ..Default::default() };
+        let reports = (0..2)
+            .map(|index| CommitGasReport::new(index, index + 1, 1, 21_000, 30_000_000, &timings))
+            .collect::<Vec<_>>();
+
+        // reports are appended across restarts
+        for report in &reports {
+            GasReporter::new(&config).unwrap().record(report);
+        }
+
+        let contents = std::fs::read_to_string(config.path.unwrap()).unwrap();
+        let lines = contents
+            .lines()
+            .map(|line| serde_json::from_str::<CommitGasReport>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, reports);
+        assert!(contents.starts_with(r#"{"subDag":0,"blockNumber":1,"transactions":1,"#));
+    }
+}
@@ -0,0 +1,142 @@
+//! Garbage collection of committed rounds.
+//!
+//! Once the sub-dag of a leader is committed and executed, the certificates of its rounds are only
+//! needed by authorities that are catching up. The [`DagPruner`] keeps the `gc_depth` rounds below
+//! the last committed round and prunes everything older from the [`DagStore`]. Components that
+//! keep rounds of the DAG in memory subscribe to the GC round and drop the rounds below it.
+
+use crate::{dag_store::DagStore, types::Round};
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{debug, error};
+
+/// The default number of rounds below the last committed round that are kept.
+pub const DEFAULT_GC_DEPTH: Round = 50;
+
+/// Returns the lowest round that is kept once `committed_round` is committed.
+pub const fn gc_round(committed_round: Round, gc_depth: Round) -> Round {
+    committed_round.saturating_sub(gc_depth)
+}
+
+/// Metrics of the [`DagPruner`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.gc")]
+struct DagPrunerMetrics {
+    /// The lowest round that is kept
+    gc_round: Gauge,
+    /// Number of pruned certificates
+    pruned_certificates: Counter,
+    /// Number of pruned votes
+    pruned_votes: Counter,
+    /// Number of pruned batches
+    pruned_batches: Counter,
+    /// Number of failed prune runs
+    failed_runs: Counter,
+}
+
+/// The task that prunes the rounds below the GC round from the [`DagStore`].
+///
+/// Pruning does blocking I/O, so the task should be spawned as a blocking task.
+#[derive(Debug)]
+pub struct DagPruner {
+    store: Arc<dyn DagStore>,
+    gc_depth: Round,
+    /// The round of the last committed leader.
+    committed: watch::Receiver<Round>,
+    gc_round: watch::Sender<Round>,
+    metrics: DagPrunerMetrics,
+}
+
+impl DagPruner {
+    /// Creates a pruner that follows the round of the last committed leader.
+    ///
+    /// Returns the pruner and a receiver of the GC round, the lowest round that is kept.
+    pub fn new(
+        store: Arc<dyn DagStore>,
+        gc_depth: Round,
+        committed: watch::Receiver<Round>,
+    ) -> (Self, watch::Receiver<Round>) {
+        let (gc_round, gc_round_rx) = watch::channel(0);
+        let pruner =
+            Self { store, gc_depth, committed, gc_round, metrics: DagPrunerMetrics::default() };
+        (pruner, gc_round_rx)
+    }
+
+    /// Runs the pruner until the sender of the committed rounds is dropped.
+    pub async fn run(mut self) {
+        loop {
+            let round = gc_round(*self.committed.borrow_and_update(), self.gc_depth);
+            if round > *self.gc_round.borrow() {
+                self.prune(round);
+            }
+            if self.committed.changed().await.is_err() {
+                return
+            }
+        }
+    }
+
+    /// Prunes the rounds below `round` and advances the GC round.
+    ///
+    /// If pruning fails, the GC round is not advanced and pruning is retried after the next
+    /// commit.
+    fn prune(&self, round: Round) {
+        match self.store.prune(round) {
+            Ok(pruned) => {
+                debug!(
+                    target: "consensus::narwhal",
+                    round,
+                    certificates = pruned.certificates,
+                    votes = pruned.votes,
+                    batches = pruned.batches,
+                    "Pruned DAG"
+                );
+                self.metrics.pruned_certificates.increment(pruned.certificates as u64);
+                self.metrics.pruned_votes.increment(pruned.votes as u64);
+                self.metrics.pruned_batches.increment(pruned.batches as u64);
+                self.metrics.gc_round.set(round as f64);
+                self.gc_round.send_replace(round);
+            }
+            Err(err) => {
+                self.metrics.failed_runs.increment(1);
+                error!(target: "consensus::narwhal", %err, round, "Failed to prune DAG");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dag_store::MemoryDagStore,
+        types::{Certificate, Header},
+    };
+
+    #[test]
+    fn prune_below_gc_round() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let store = Arc::new(MemoryDagStore::default());
+        for round in 1..=20 {
+            let header = Header { round, ..Default::default() };
+            store.write_certificate(&Certificate { header, ..Default::default() }).unwrap();
+        }
+        let (committed, committed_rx) = watch::channel(5);
+        let (pruner, mut gc_rounds) = DagPruner::new(store.clone(), 10, committed_rx);
+        let task = runtime.spawn(pruner.run());
+
+        runtime.block_on(async {
+            committed.send(16).unwrap();
+            gc_rounds.changed().await.unwrap();
+            assert_eq!(*gc_rounds.borrow(), 6);
+            assert_eq!(store.certificates(0).unwrap()[0].round(), 6);
+
+            drop(committed);
+            task.await.unwrap();
+        });
+        assert_eq!(gc_round(3, 10), 0);
+    }
+}
@@ -0,0 +1,380 @@
+//! Keys of the authority a node runs.
+//!
+//! An authority has three kinds of keys: the protocol key it signs headers and votes with, which
+//! is registered in the [`Committee`], the network key of its primary and a network key for each
+//! of its workers. Components get the keys from a [`KeyProvider`], so they don't depend on where
+//! the keys are stored.
+//!
+//! The [`Keystore`] keeps the keys of an [`AuthorityKeys`] in a file in the datadir, encrypted
+//! with AES-256-GCM under a key derived from a password with PBKDF2. Only the public key of the
+//! authority is readable without the password. `reth narwhal keygen` creates the keystore.
+
+use crate::{
+    committee::Committee,
+    signature::{AggregateScheme, Bls12381, BlsPublicKey, BlsSecretKey},
+    types::WorkerId,
+};
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, Key, KeyInit, Nonce,
+};
+use alloy_primitives::{Bytes, FixedBytes, B256};
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    fmt::{self, Debug},
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// The default number of PBKDF2 iterations of a new keystore.
+pub const DEFAULT_KDF_ITERATIONS: u32 = 600_000;
+
+/// The version of the keystore format.
+const KEYSTORE_VERSION: u32 = 1;
+
+/// Provides the keys of the authority this node runs.
+pub trait KeyProvider: Debug + Send + Sync {
+    /// Returns the key the authority signs headers and votes with.
+    fn authority_key(&self) -> &BlsSecretKey;
+
+    /// Returns the key that identifies the primary on the network.
+    fn network_key(&self) -> &NetworkKey;
+
+    /// Returns the key that identifies a worker on the network, `None` if the worker has no key.
+    fn worker_key(&self, worker: WorkerId) -> Option<&NetworkKey>;
+
+    /// Returns the public key of the authority.
+    fn authority_public_key(&self) -> BlsPublicKey {
+        Bls12381::public_key(self.authority_key())
+    }
+
+    /// Returns the index of the authority in the committee, `None` if it's not a member.
+    fn authority_index(&self, committee: &Committee) -> Option<AuthorityIndex> {
+        committee.index_of(&self.authority_public_key().to_bytes())
+    }
+}
+
+/// The secret key of a network identity.
+///
+/// The key is the secret key material the transport derives its identity from, e.g. an ed25519 or
+/// secp256k1 key pair.
+#[derive(Clone, PartialEq, Eq)]
+pub struct NetworkKey(B256);
+
+impl NetworkKey {
+    /// Returns the secret key material.
+    pub const fn secret(&self) -> &B256 {
+        &self.0
+    }
+}
+
+impl Debug for NetworkKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("NetworkKey(..)")
+    }
+}
+
+/// The secret key material of an [`AuthorityKeys`], as stored in the keystore.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeySeeds {
+    authority: B256,
+    network: B256,
+    workers: Vec<B256>,
+}
+
+/// The keys of an authority and its workers.
+#[derive(Clone)]
+pub struct AuthorityKeys {
+    seeds: KeySeeds,
+    authority: BlsSecretKey,
+    network: NetworkKey,
+    workers: Vec<NetworkKey>,
+}
+
+impl AuthorityKeys {
+    /// Generates random keys for an authority with the given number of workers.
+    pub fn generate(workers: usize) -> Self {
+        let seeds = KeySeeds {
+            authority: B256::from(rand::random::<[u8; 32]>()),
+            network: B256::from(rand::random::<[u8; 32]>()),
+            workers: (0..workers).map(|_| B256::from(rand::random::<[u8; 32]>())).collect(),
+        };
+        Self::from_seeds(seeds).expect("32 bytes of seed")
+    }
+
+    /// Returns the number of workers with a key.
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    fn from_seeds(seeds: KeySeeds) -> Result<Self, KeystoreError> {
+        let authority = BlsSecretKey::from_seed(seeds.authority.as_slice())
+            .map_err(|_| KeystoreError::InvalidKey)?;
+        let network = NetworkKey(seeds.network);
+        let workers = seeds.workers.iter().copied().map(NetworkKey).collect();
+        Ok(Self { seeds, authority, network, workers })
+    }
+}
+
+impl KeyProvider for AuthorityKeys {
+    fn authority_key(&self) -> &BlsSecretKey {
+        &self.authority
+    }
+
+    fn network_key(&self) -> &NetworkKey {
+        &self.network
+    }
+
+    fn worker_key(&self, worker: WorkerId) -> Option<&NetworkKey> {
+        self.workers.get(worker as usize)
+    }
+}
+
+impl Debug for AuthorityKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthorityKeys")
+            .field("public_key", &self.authority_public_key())
+            .field("workers", &self.workers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Errors of the [`Keystore`].
+#[derive(Debug, thiserror::Error)]
+pub enum KeystoreError {
+    /// The keystore couldn't be read.
+    #[error("failed to read keystore {path}: {err}")]
+    Read {
+        /// The path of the keystore.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        err: io::Error,
+    },
+    /// The keystore couldn't be written.
+    #[error("failed to write keystore {path}: {err}")]
+    Write {
+        /// The path of the keystore.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        err: io::Error,
+    },
+    /// The keystore is malformed.
+    #[error("failed to parse keystore {path}: {message}")]
+    Parse {
+        /// The path of the keystore.
+        path: PathBuf,
+        /// The parser error.
+        message: String,
+    },
+    /// The keystore was written by an unsupported version.
+    #[error("unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    /// The keystore can't be decrypted with the password.
+    #[error("wrong keystore password")]
+    WrongPassword,
+    /// The keystore holds key material that is not a valid authority key.
+    #[error("keystore holds an invalid authority key")]
+    InvalidKey,
+    /// A keystore already exists at the path.
+    #[error("keystore {0} already exists")]
+    AlreadyExists(PathBuf),
+}
+
+/// The parameters of the password based key derivation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KdfParams {
+    salt: B256,
+    iterations: u32,
+}
+
+/// The contents of a keystore file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeystoreFile {
+    version: u32,
+    /// The public key of the authority, which is readable without the password.
+    public_key: Bytes,
+    kdf: KdfParams,
+    nonce: FixedBytes<12>,
+    /// The encrypted [`KeySeeds`].
+    ciphertext: Bytes,
+}
+
+/// A file with the encrypted keys of an authority.
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    path: PathBuf,
+    iterations: u32,
+}
+
+impl Keystore {
+    /// Creates the keystore at the given path, which doesn't have to exist yet.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), iterations: DEFAULT_KDF_ITERATIONS }
+    }
+
+    /// Sets the number of PBKDF2 iterations used when the keystore is saved.
+    pub const fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Returns the path of the keystore.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` if the keystore file exists.
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Returns the public key of the authority, without decrypting the keystore.
+    pub fn public_key(&self) -> Result<Bytes, KeystoreError> {
+        Ok(self.read()?.public_key)
+    }
+
+    /// Decrypts the keys with the password.
+    pub fn load(&self, password: &[u8]) -> Result<AuthorityKeys, KeystoreError> {
+        let file = self.read()?;
+        if file.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(file.version))
+        }
+        let plaintext = cipher(password, &file.kdf)
+            .decrypt(
+                Nonce::from_slice(file.nonce.as_slice()),
+                Payload { msg: &file.ciphertext, aad: &file.public_key },
+            )
+            .map_err(|_| KeystoreError::WrongPassword)?;
+        let seeds = serde_json::from_slice(&plaintext).map_err(|err| self.parse_error(err))?;
+        let keys = AuthorityKeys::from_seeds(seeds)?;
+        if keys.authority_public_key().to_bytes().as_slice() != file.public_key.as_ref() {
+            return Err(KeystoreError::InvalidKey)
+        }
+        Ok(keys)
+    }
+
+    /// Encrypts the keys with the password and writes them to a new keystore file.
+    ///
+    /// Fails if the keystore already exists, keys are never overwritten.
+    pub fn create(&self, keys: &AuthorityKeys, password: &[u8]) -> Result<(), KeystoreError> {
+        if self.exists() {
+            return Err(KeystoreError::AlreadyExists(self.path.clone()))
+        }
+        let kdf =
+            KdfParams { salt: B256::from(rand::random::<[u8; 32]>()), iterations: self.iterations };
+        let public_key = Bytes::copy_from_slice(&keys.authority_public_key().to_bytes());
+        let nonce = FixedBytes::<12>::from(rand::random::<[u8; 12]>());
+        let plaintext = serde_json::to_vec(&keys.seeds).expect("seeds are serializable");
+        let ciphertext = cipher(password, &kdf)
+            .encrypt(
+                Nonce::from_slice(nonce.as_slice()),
+                Payload { msg: &plaintext, aad: &public_key },
+            )
+            .expect("keys are below the size limit of AES-GCM");
+        let file = KeystoreFile {
+            version: KEYSTORE_VERSION,
+            public_key,
+            kdf,
+            nonce,
+            ciphertext: ciphertext.into(),
+        };
+        self.write(&file).map_err(|err| KeystoreError::Write { path: self.path.clone(), err })
+    }
+
+    /// Loads the keys, or generates keys for the given number of workers and creates the keystore
+    /// if it doesn't exist.
+    pub fn load_or_create(
+        &self,
+        password: &[u8],
+        workers: usize,
+    ) -> Result<AuthorityKeys, KeystoreError> {
+        if self.exists() {
+            return self.load(password)
+        }
+        let keys = AuthorityKeys::generate(workers);
+        self.create(&keys, password)?;
+        Ok(keys)
+    }
+
+    fn read(&self) -> Result<KeystoreFile, KeystoreError> {
+        let contents = fs::read(&self.path)
+            .map_err(|err| KeystoreError::Read { path: self.path.clone(), err })?;
+        serde_json::from_slice(&contents).map_err(|err| self.parse_error(err))
+    }
+
+    fn write(&self, file: &KeystoreFile) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut out = options.open(&self.path)?;
+        serde_json::to_writer_pretty(&mut out, file).map_err(io::Error::from)?;
+        out.write_all(b"\n")?;
+        out.sync_all()
+    }
+
+    fn parse_error(&self, err: serde_json::Error) -> KeystoreError {
+        KeystoreError::Parse { path: self.path.clone(), message: err.to_string() }
+    }
+}
+
+/// Returns the cipher of the key derived from the password.
+fn cipher(password: &[u8], kdf: &KdfParams) -> Aes256Gcm {
+    let mut key = [0; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, kdf.salt.as_slice(), kdf.iterations, &mut key);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev::DevCommittee;
+
+    #[test]
+    fn create_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let keystore =
+            Keystore::new(dir.path().join("narwhal").join("keystore.json")).with_iterations(1_000);
+        let keys = keystore.load_or_create(b"password", 2).unwrap();
+        assert_eq!(keys.workers(), 2);
+        assert!(keys.worker_key(1).is_some());
+        assert_eq!(keys.worker_key(2), None);
+        assert_eq!(
+            keystore.public_key().unwrap().as_ref(),
+            keys.authority_public_key().to_bytes().as_slice()
+        );
+
+        let loaded = keystore.load_or_create(b"password", 4).unwrap();
+        assert_eq!(loaded.authority_public_key(), keys.authority_public_key());
+        assert_eq!(loaded.network_key(), keys.network_key());
+        assert_eq!(loaded.workers(), 2);
+
+        assert!(matches!(keystore.load(b"wrong"), Err(KeystoreError::WrongPassword)));
+        assert!(matches!(
+            keystore.create(&keys, b"password"),
+            Err(KeystoreError::AlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn authority_index() {
+        let keys = AuthorityKeys::generate(1);
+        let mut committee = DevCommittee::new(4).committee().clone();
+        assert_eq!(keys.authority_index(&committee), None);
+
+        committee.authorities[2].public_key =
+            Bytes::copy_from_slice(&keys.authority_public_key().to_bytes());
+        assert_eq!(keys.authority_index(&committee), Some(2));
+    }
+}
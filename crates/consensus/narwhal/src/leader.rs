@@ -0,0 +1,275 @@
+//! The election of the leaders of the commit rule.
+//!
+//! Every even round has a leader, whose certificate is committed once enough certificates of the
+//! following round support it. The [`LeaderSchedule`] configures how the leaders are elected, and
+//! the [`LeaderElector`] applies it to the committee of an epoch.
+//!
+//! The reputation schedule skips authorities whose leader rounds repeatedly fail to commit, e.g.
+//! because they are offline, so that their rounds don't stall the commits of the chain. The
+//! reputation is only updated from committed sub-dags, which every validator commits in the same
+//! order, so all validators elect the same leaders.
+
+use crate::{committee::Committee, types::Round};
+use alloy_primitives::keccak256;
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use reth_narwhal_verifier::{AuthorityIndex, Epoch, Stake};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// The default number of consecutive uncommitted leader rounds after which an authority is
+/// skipped by the [`LeaderSchedule::Reputation`] schedule.
+pub const DEFAULT_MAX_LEADER_FAILURES: u32 = 3;
+
+/// The default number of rounds an authority is skipped as leader by the
+/// [`LeaderSchedule::Reputation`] schedule.
+pub const DEFAULT_LEADER_EXCLUSION_ROUNDS: Round = 100;
+
+/// How the leaders of the commit rule are elected.
+///
+/// Unlike the other settings of the [`NarwhalConfig`](crate::NarwhalConfig), the schedule must be
+/// the same on every validator, otherwise they commit different leaders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum LeaderSchedule {
+    /// The authorities lead in turn, in the order of the committee.
+    #[default]
+    RoundRobin,
+    /// The leader of every round is drawn from the committee in proportion to its stake, with
+    /// the epoch and the round as the seed.
+    StakeWeighted,
+    /// The authorities lead in turn, but an authority whose leader rounds weren't committed
+    /// `max_failures` times in a row is skipped for the following `exclusion_rounds` rounds.
+    Reputation {
+        /// The number of consecutive uncommitted leader rounds after which an authority is
+        /// skipped.
+        max_failures: u32,
+        /// The number of rounds an authority is skipped.
+        exclusion_rounds: Round,
+    },
+}
+
+impl LeaderSchedule {
+    /// Returns the reputation schedule with the default parameters.
+    pub const fn reputation() -> Self {
+        Self::Reputation {
+            max_failures: DEFAULT_MAX_LEADER_FAILURES,
+            exclusion_rounds: DEFAULT_LEADER_EXCLUSION_ROUNDS,
+        }
+    }
+}
+
+/// Metrics of the [`LeaderElector`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.leader")]
+struct LeaderElectorMetrics {
+    /// Number of authorities that are currently skipped as leaders
+    excluded_leaders: Gauge,
+    /// Number of times an authority was excluded as leader
+    exclusions: Counter,
+}
+
+/// The reputation of an authority as leader.
+#[derive(Debug, Clone, Copy, Default)]
+struct Reputation {
+    /// The number of consecutive leader rounds of the authority that weren't committed.
+    failures: u32,
+    /// The first round in which the authority leads again.
+    excluded_until: Round,
+}
+
+/// Elects the leaders of the rounds of an epoch.
+#[derive(Debug)]
+pub struct LeaderElector {
+    schedule: LeaderSchedule,
+    epoch: Epoch,
+    stakes: Vec<Stake>,
+    total_stake: Stake,
+    reputations: Vec<Reputation>,
+    /// The round of the last committed leader.
+    last_committed: Round,
+    metrics: LeaderElectorMetrics,
+}
+
+impl LeaderElector {
+    /// Creates the elector of the leaders of the committee's epoch.
+    pub fn new(schedule: LeaderSchedule, committee: &Committee) -> Self {
+        let stakes = committee.authorities.iter().map(|authority| authority.stake).collect();
+        Self {
+            schedule,
+            epoch: committee.epoch,
+            stakes,
+            total_stake: committee.total_stake(),
+            reputations: vec![Reputation::default(); committee.authorities.len()],
+            last_committed: 0,
+            metrics: Default::default(),
+        }
+    }
+
+    /// Returns the leader of a round, or `None` if the round has no leader because it's odd or
+    /// the committee is empty.
+    pub fn leader(&self, round: Round) -> Option<AuthorityIndex> {
+        if round % 2 != 0 || self.stakes.is_empty() {
+            return None
+        }
+        let leader = match self.schedule {
+            LeaderSchedule::RoundRobin => self.round_robin(round, |_| true),
+            LeaderSchedule::StakeWeighted => self.stake_weighted(round),
+            LeaderSchedule::Reputation { .. } => self
+                .round_robin(round, |index| self.reputations[index].excluded_until <= round)
+                // if every authority is excluded, none of them is skipped
+                .or_else(|| self.round_robin(round, |_| true)),
+        };
+        leader.map(|index| index as AuthorityIndex)
+    }
+
+    /// Records the commit of the leader of a round.
+    ///
+    /// The leaders of the rounds since the last commit were not committed. Commits must be
+    /// recorded in commit order, commits of rounds at or below the last commit are ignored.
+    pub fn record_commit(&mut self, leader_round: Round) {
+        if leader_round <= self.last_committed {
+            return
+        }
+        let LeaderSchedule::Reputation { max_failures, exclusion_rounds } = self.schedule else {
+            self.last_committed = leader_round;
+            return
+        };
+
+        // the leaders are elected with the reputations as of the last commit
+        let skipped = (self.last_committed + 1..leader_round)
+            .filter_map(|round| self.leader(round))
+            .collect::<Vec<_>>();
+        let committed = self.leader(leader_round);
+        self.last_committed = leader_round;
+
+        for leader in skipped {
+            let reputation = &mut self.reputations[leader as usize];
+            reputation.failures += 1;
+            if reputation.failures >= max_failures {
+                reputation.failures = 0;
+                reputation.excluded_until = leader_round + exclusion_rounds + 1;
+                self.metrics.exclusions.increment(1);
+                info!(
+                    target: "consensus::narwhal",
+                    leader,
+                    until = reputation.excluded_until,
+                    "Skipping leader that failed to commit"
+                );
+            }
+        }
+        if let Some(leader) = committed {
+            self.reputations[leader as usize].failures = 0;
+        }
+        let excluded = self
+            .reputations
+            .iter()
+            .filter(|reputation| reputation.excluded_until > leader_round)
+            .count();
+        self.metrics.excluded_leaders.set(excluded as f64);
+    }
+
+    /// Returns the `round / 2`-th of the authorities that pass the filter, cycling through them.
+    fn round_robin(&self, round: Round, filter: impl Fn(usize) -> bool) -> Option<usize> {
+        let candidates = (0..self.stakes.len()).filter(|index| filter(*index)).collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return None
+        }
+        Some(candidates[((round / 2) % candidates.len() as Round) as usize])
+    }
+
+    /// Returns an authority drawn in proportion to its stake.
+    fn stake_weighted(&self, round: Round) -> Option<usize> {
+        if self.total_stake == 0 {
+            return None
+        }
+        let mut seed = [0; 16];
+        seed[..8].copy_from_slice(&self.epoch.to_be_bytes());
+        seed[8..].copy_from_slice(&round.to_be_bytes());
+        let hash = keccak256(seed);
+        let mut target =
+            u64::from_be_bytes(hash[..8].try_into().expect("8 bytes")) % self.total_stake;
+        self.stakes.iter().position(|stake| {
+            if target < *stake {
+                return true
+            }
+            target -= stake;
+            false
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::committee::Authority;
+    use alloy_primitives::Bytes;
+
+    fn committee(stakes: &[Stake]) -> Committee {
+        let authorities = stakes
+            .iter()
+            .enumerate()
+            .map(|(index, stake)| Authority {
+                public_key: Bytes::from(vec![index as u8]),
+                proof_of_possession: Bytes::new(),
+                stake: *stake,
+                primary_address: ([127, 0, 0, 1], 30400).into(),
+                worker_addresses: Vec::new(),
+            })
+            .collect();
+        Committee { epoch: 1, authorities }
+    }
+
+    #[test]
+    fn round_robin() {
+        let elector = LeaderElector::new(LeaderSchedule::RoundRobin, &committee(&[1, 1, 1]));
+        let leaders = (1..=8).map(|round| elector.leader(round)).collect::<Vec<_>>();
+        assert_eq!(leaders, vec![None, Some(1), None, Some(2), None, Some(0), None, Some(1)]);
+    }
+
+    #[test]
+    fn stake_weighted() {
+        let elector = LeaderElector::new(LeaderSchedule::StakeWeighted, &committee(&[1, 3]));
+        let mut counts = [0; 2];
+        for round in (2..=2_000).step_by(2) {
+            counts[elector.leader(round).unwrap() as usize] += 1;
+        }
+        // 1000 rounds drawn with a quarter and three quarters of the stake
+        assert!((200..300).contains(&counts[0]), "{counts:?}");
+        assert_eq!(counts[0] + counts[1], 1_000);
+
+        let same = LeaderElector::new(LeaderSchedule::StakeWeighted, &committee(&[1, 3]));
+        assert!((2..100).step_by(2).all(|round| same.leader(round) == elector.leader(round)));
+    }
+
+    #[test]
+    fn skip_failing_leaders() {
+        let schedule = LeaderSchedule::Reputation { max_failures: 2, exclusion_rounds: 10 };
+        let mut elector = LeaderElector::new(schedule, &committee(&[1, 1, 1]));
+        assert_eq!(elector.leader(4), Some(2));
+
+        // the leaders of rounds 4 and 10 were not committed, authority 2 fails twice
+        elector.record_commit(2);
+        elector.record_commit(6);
+        elector.record_commit(8);
+        assert_eq!(elector.leader(10), Some(2));
+        elector.record_commit(12);
+        assert_eq!(elector.reputations[2].excluded_until, 23);
+
+        // authorities 0 and 1 lead in turn until authority 2 is readmitted
+        let leaders = (14..=24).step_by(2).map(|round| elector.leader(round)).collect::<Vec<_>>();
+        assert_eq!(leaders, vec![Some(1), Some(0), Some(1), Some(0), Some(1), Some(0)]);
+
+        // a committed leader round resets the failures of the leader
+        for round in (14..=24).step_by(2) {
+            elector.record_commit(round);
+        }
+        elector.record_commit(28);
+        assert_eq!(elector.reputations[1].failures, 1);
+        elector.record_commit(30);
+        elector.record_commit(32);
+        assert_eq!(elector.reputations[1].failures, 0);
+    }
+}
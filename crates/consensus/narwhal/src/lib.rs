@@ -0,0 +1,122 @@
+//! Narwhal consensus integration for reth.
+//!
+//! Blocks of a narwhal chain are not proposed by a single block producer. Instead, transactions
+//! are sequenced by the narwhal mempool and ordered by the consensus protocol, and every validator
+//! deterministically turns each committed batch of transactions into a block.
+//!
+//! This crate contains the rules every validator needs to agree on when doing so, as well as the
+//! [`NarwhalConsensus`] used to validate the resulting blocks.
+//!
+//! ## Feature Flags
+//!
+//! - `execution` (default): The integration with reth, i.e. everything that turns consensus output
+//!   into blocks and validates them. Without it, the consensus core only depends on primitive types
+//!   and can be reused by simulators and external tooling.
+//! - `ed25519`: The [`Ed25519`](signature::Ed25519) signature scheme, for chains that can't use BLS
+//!   signatures.
+//! - `jsonrpsee-types`: Conversions between [`NarwhalRpcError`](rpc::NarwhalRpcError) and JSON-RPC
+//!   error objects.
+//! - `test-utils`: The [`SimulatedCommittee`](test_utils::SimulatedCommittee), which runs a
+//!   committee with faulty authorities in a single thread.
+//! - `sim`: The [`Simulation`](sim::Simulation), which runs a committee in virtual time on an
+//!   in-memory network, reproducibly from a seed.
+
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/paradigmxyz/reth/main/assets/reth-docs.png",
+    html_favicon_url = "https://avatars0.githubusercontent.com/u/97369466?s=256",
+    issue_tracker_base_url = "https://github.com/paradigmxyz/reth/issues/"
+)]
+#![cfg_attr(not(test), warn(unused_crate_dependencies))]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+pub mod backlog;
+pub mod backpressure;
+pub mod bullshark;
+#[cfg(feature = "execution")]
+mod chainspec;
+pub mod chaos;
+pub mod checkpoint;
+#[cfg(feature = "execution")]
+pub mod commit_hooks;
+pub mod commit_log;
+pub mod committer;
+pub mod committee;
+pub mod committee_history;
+pub mod config;
+#[cfg(feature = "execution")]
+mod consensus;
+pub mod crosscheck;
+pub mod dag;
+pub mod dag_store;
+pub mod deployment;
+pub mod deposits;
+pub mod determinism;
+pub mod dev;
+pub mod dot;
+pub mod epoch;
+pub mod epoch_snapshot;
+pub mod events;
+#[cfg(feature = "execution")]
+pub mod executor;
+pub mod failover;
+pub mod fast_path;
+pub mod gas_report;
+pub mod gc;
+pub mod keys;
+pub mod leader;
+pub mod memory;
+#[cfg(feature = "execution")]
+pub mod messages;
+pub mod metrics;
+#[cfg(feature = "execution")]
+pub mod payload;
+#[cfg(feature = "execution")]
+pub mod pool_maintenance;
+pub mod predeploys;
+pub mod primary;
+#[cfg(feature = "execution")]
+mod receipts;
+pub mod record;
+pub mod recovery;
+#[cfg(feature = "execution")]
+pub mod report;
+pub mod rpc;
+pub mod scaffold;
+pub mod self_check;
+#[cfg(feature = "execution")]
+pub mod sequencing;
+pub mod shadow;
+pub mod shutdown;
+pub mod signature;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod stall;
+pub mod state_snapshot;
+#[cfg(feature = "execution")]
+mod status;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+pub mod timestamp;
+pub mod trace;
+pub mod types;
+#[cfg(feature = "execution")]
+pub mod validation;
+pub mod warm_start;
+pub mod wire;
+pub mod worker;
+
+pub use reth_narwhal_verifier as verifier;
+
+pub use config::NarwhalConfig;
+pub use events::{NarwhalEvent, NarwhalEvents};
+
+#[cfg(feature = "execution")]
+pub use chainspec::{NarwhalChainInfo, NARWHAL_GENESIS_KEY};
+#[cfg(feature = "execution")]
+pub use consensus::NarwhalConsensus;
+#[cfg(feature = "execution")]
+pub use receipts::{RecentReceipt, RecentReceipts};
+#[cfg(feature = "execution")]
+pub use sequencing::NonceGapPolicy;
+#[cfg(feature = "execution")]
+pub use status::{DroppedTransactions, TransactionStatus};
@@ -0,0 +1,242 @@
+//! A memory budget shared by the in-memory caches of consensus.
+//!
+//! Caches that are bounded independently can together grow past the memory of the machine, e.g.
+//! when every cache fills up during a long partition. Instead, every cache registers with one
+//! [`MemoryBudget`] as a [`MemoryComponent`], charges the estimated size of its entries to its
+//! [`MemoryHandle`] and evicts entries while the handle reports an excess. Once the budget is
+//! exceeded, every component evicts a share of the excess proportional to its own usage, so the
+//! largest caches shrink the most.
+//!
+//! Components evict when they insert, so the budget can be exceeded by the entries of components
+//! that stopped growing until they insert again.
+//!
+//! Components whose entries consensus can't do without, like the DAG of the
+//! [`Committer`](crate::committer::Committer), are only accounted and never evict, so the other
+//! components evict more.
+
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// The default memory budget of all components, 2 GiB.
+pub const DEFAULT_MEMORY_BUDGET: usize = 2 << 30;
+
+/// A cache that is accounted against the [`MemoryBudget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryComponent {
+    /// The certificates and batches of the rounds above the GC round.
+    Dag,
+    /// Certificates whose parents haven't arrived yet.
+    PendingCertificates,
+    /// Digests of certificates and batches whose signatures or contents were already verified.
+    VerifiedDigests,
+    /// Results of executed sub-dags, e.g. the transactions that were dropped from their blocks.
+    ExecutionResults,
+}
+
+impl MemoryComponent {
+    /// All components.
+    pub const ALL: [Self; 4] =
+        [Self::Dag, Self::PendingCertificates, Self::VerifiedDigests, Self::ExecutionResults];
+
+    /// Returns the name of the component, which is used as the label of its metrics.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dag => "dag",
+            Self::PendingCertificates => "pending_certificates",
+            Self::VerifiedDigests => "verified_digests",
+            Self::ExecutionResults => "execution_results",
+        }
+    }
+}
+
+impl fmt::Display for MemoryComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Configuration of the [`MemoryBudget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MemoryBudgetConfig {
+    /// The estimated number of bytes all components may hold together.
+    pub max_bytes: usize,
+}
+
+impl Default for MemoryBudgetConfig {
+    fn default() -> Self {
+        Self { max_bytes: DEFAULT_MEMORY_BUDGET }
+    }
+}
+
+/// Metrics of a [`MemoryComponent`].
+#[derive(Clone, Metrics)]
+#[metrics(scope = "narwhal.memory")]
+struct MemoryComponentMetrics {
+    /// The estimated number of bytes held by the component
+    bytes: Gauge,
+    /// Number of bytes evicted from the component because the budget was exceeded
+    evicted_bytes: Counter,
+}
+
+/// The memory budget shared by all [`MemoryComponent`]s.
+///
+/// Cloning is cheap, all clones share the same budget.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    inner: Arc<BudgetInner>,
+}
+
+#[derive(Debug)]
+struct BudgetInner {
+    max_bytes: usize,
+    /// The bytes held by all components.
+    total: AtomicUsize,
+    /// The bytes held by each component, indexed by its position in [`MemoryComponent::ALL`].
+    usage: [AtomicUsize; MemoryComponent::ALL.len()],
+}
+
+impl MemoryBudget {
+    /// Creates a budget without any usage.
+    pub fn new(config: MemoryBudgetConfig) -> Self {
+        Self {
+            inner: Arc::new(BudgetInner {
+                max_bytes: config.max_bytes,
+                total: AtomicUsize::new(0),
+                usage: Default::default(),
+            }),
+        }
+    }
+
+    /// Returns the handle a component charges its entries to.
+    pub fn handle(&self, component: MemoryComponent) -> MemoryHandle {
+        MemoryHandle {
+            budget: self.clone(),
+            component,
+            metrics: MemoryComponentMetrics::new_with_labels(&[("component", component.as_str())]),
+        }
+    }
+
+    /// Returns the bytes held by all components.
+    pub fn total(&self) -> usize {
+        self.inner.total.load(Ordering::Relaxed)
+    }
+
+    /// Returns the bytes held by the given component.
+    pub fn usage(&self, component: MemoryComponent) -> usize {
+        self.inner.usage[component as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of bytes by which the budget is exceeded.
+    pub fn excess(&self) -> usize {
+        self.total().saturating_sub(self.inner.max_bytes)
+    }
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        Self::new(MemoryBudgetConfig::default())
+    }
+}
+
+/// The handle of a [`MemoryComponent`] to the [`MemoryBudget`].
+#[derive(Debug, Clone)]
+pub struct MemoryHandle {
+    budget: MemoryBudget,
+    component: MemoryComponent,
+    metrics: MemoryComponentMetrics,
+}
+
+impl MemoryHandle {
+    /// Returns the component of the handle.
+    pub const fn component(&self) -> MemoryComponent {
+        self.component
+    }
+
+    /// Returns the bytes held by the component.
+    pub fn usage(&self) -> usize {
+        self.budget.usage(self.component)
+    }
+
+    /// Charges the bytes of a new entry to the component.
+    pub fn charge(&self, bytes: usize) {
+        self.budget.inner.total.fetch_add(bytes, Ordering::Relaxed);
+        let usage = self.usage_counter().fetch_add(bytes, Ordering::Relaxed) + bytes;
+        self.metrics.bytes.set(usage as f64);
+    }
+
+    /// Releases the bytes of a removed entry.
+    pub fn release(&self, bytes: usize) {
+        let released = self
+            .usage_counter()
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |usage| {
+                Some(usage.saturating_sub(bytes))
+            })
+            .map_or(0, |usage| usage.min(bytes));
+        self.budget.inner.total.fetch_sub(released, Ordering::Relaxed);
+        self.metrics.bytes.set(self.usage() as f64);
+    }
+
+    /// Releases the bytes of an entry that was evicted because the budget was exceeded.
+    pub fn evict(&self, bytes: usize) {
+        self.release(bytes);
+        self.metrics.evicted_bytes.increment(bytes as u64);
+    }
+
+    /// Returns the number of bytes the component should evict, its share of the excess of the
+    /// budget proportional to its usage.
+    pub fn excess(&self) -> usize {
+        let excess = self.budget.excess();
+        let total = self.budget.total();
+        if excess == 0 || total == 0 {
+            return 0
+        }
+        let share = (self.usage() as u128 * excess as u128).div_ceil(total as u128);
+        share as usize
+    }
+
+    fn usage_counter(&self) -> &AtomicUsize {
+        &self.budget.inner.usage[self.component as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proportional_excess() {
+        let budget = MemoryBudget::new(MemoryBudgetConfig { max_bytes: 1_000 });
+        let dag = budget.handle(MemoryComponent::Dag);
+        let results = budget.handle(MemoryComponent::ExecutionResults);
+
+        dag.charge(600);
+        results.charge(300);
+        assert_eq!((budget.excess(), dag.excess(), results.excess()), (0, 0, 0));
+
+        // 200 bytes over budget, evicted in proportion to the usage of 900 and 300 bytes
+        dag.charge(300);
+        assert_eq!(budget.total(), 1_200);
+        assert_eq!((dag.excess(), results.excess()), (150, 50));
+
+        dag.evict(150);
+        results.evict(50);
+        assert_eq!((budget.total(), budget.excess()), (1_000, 0));
+        assert_eq!(budget.usage(MemoryComponent::Dag), 750);
+        assert_eq!(budget.usage(MemoryComponent::VerifiedDigests), 0);
+
+        // releasing more than the usage doesn't affect other components
+        results.release(1_000);
+        assert_eq!((results.usage(), budget.total()), (0, 750));
+    }
+}
@@ -0,0 +1,120 @@
+//! Outbound messages sent from a narwhal chain to other chains.
+//!
+//! Contracts send a message by calling the [`MESSAGE_QUEUE`] predeploy, which emits a
+//! [`MessageSent`] event with the hash of the message. The hashes of all messages sent in a block
+//! are the leaves of the block's message tree, see [`reth_narwhal_verifier::messages`], and the
+//! header commits to the root of that tree in its
+//! [`BlockCommitment`](crate::types::BlockCommitment). A message is proven on another chain with a
+//! [`MessageProof`] against the root of a verified checkpoint.
+
+use crate::predeploys::MESSAGE_QUEUE;
+use alloy_sol_types::{sol, SolEvent};
+use reth_narwhal_verifier::messages::{message_proof, message_root};
+use reth_primitives::{Receipt, B256};
+use serde::{Deserialize, Serialize};
+
+sol! {
+    /// Emitted by the message queue for every message sent to another chain.
+    #[derive(Debug)]
+    event MessageSent(bytes32 indexed messageHash);
+}
+
+/// Returns the hashes of all messages sent in a block, in the order they were sent.
+pub fn message_hashes<'a>(receipts: impl IntoIterator<Item = &'a Receipt>) -> Vec<B256> {
+    receipts
+        .into_iter()
+        .flat_map(|receipt| &receipt.logs)
+        .filter(|log| log.address == MESSAGE_QUEUE)
+        .filter_map(|log| match log.topics() {
+            [signature, message_hash] if *signature == MessageSent::SIGNATURE_HASH => {
+                Some(*message_hash)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the root of the message tree of a block with the given receipts.
+pub fn messages_root<'a>(receipts: impl IntoIterator<Item = &'a Receipt>) -> B256 {
+    message_root(&message_hashes(receipts))
+}
+
+/// Proof that a message was sent in a block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageProof {
+    /// The hash of the message.
+    pub message_hash: B256,
+    /// The index of the message in the block's message tree.
+    pub index: u64,
+    /// The sibling hashes on the path from the message to the root, starting with the sibling of
+    /// the message.
+    pub proof: Vec<B256>,
+    /// The message root of the block.
+    pub root: B256,
+}
+
+impl MessageProof {
+    /// Creates the proof of the first message with the given hash sent in a block with the given
+    /// receipts.
+    ///
+    /// Returns `None` if no such message was sent in the block.
+    pub fn new<'a>(
+        receipts: impl IntoIterator<Item = &'a Receipt>,
+        message_hash: B256,
+    ) -> Option<Self> {
+        let leaves = message_hashes(receipts);
+        let index = leaves.iter().position(|leaf| *leaf == message_hash)?;
+        Some(Self {
+            message_hash,
+            index: index as u64,
+            proof: message_proof(&leaves, index)?,
+            root: message_root(&leaves),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_narwhal_verifier::messages::verify_message_proof;
+    use reth_primitives::{Address, Log, TxType};
+
+    fn receipt(logs: Vec<Log>) -> Receipt {
+        Receipt { tx_type: TxType::Eip1559, success: true, logs, ..Default::default() }
+    }
+
+    fn message_sent(address: Address, message_hash: B256) -> Log {
+        Log::new_unchecked(
+            address,
+            vec![MessageSent::SIGNATURE_HASH, message_hash],
+            Default::default(),
+        )
+    }
+
+    #[test]
+    fn only_message_queue_events_are_messages() {
+        let (a, b, c) = (B256::with_last_byte(1), B256::with_last_byte(2), B256::with_last_byte(3));
+        let other = Address::with_last_byte(1);
+        let receipts = vec![
+            receipt(vec![message_sent(MESSAGE_QUEUE, a), message_sent(other, b)]),
+            receipt(vec![Log::new_unchecked(MESSAGE_QUEUE, vec![b], Default::default())]),
+            receipt(vec![message_sent(MESSAGE_QUEUE, c)]),
+        ];
+        assert_eq!(message_hashes(&receipts), vec![a, c]);
+    }
+
+    #[test]
+    fn prove_message() {
+        let hashes = (1..=3).map(B256::with_last_byte).collect::<Vec<_>>();
+        let receipts =
+            vec![receipt(hashes.iter().map(|hash| message_sent(MESSAGE_QUEUE, *hash)).collect())];
+
+        let proof = MessageProof::new(&receipts, hashes[2]).unwrap();
+        assert_eq!(proof.index, 2);
+        assert_eq!(proof.root, messages_root(&receipts));
+        assert!(verify_message_proof(proof.root, proof.message_hash, proof.index, &proof.proof));
+
+        assert_eq!(MessageProof::new(&receipts, B256::ZERO), None);
+    }
+}
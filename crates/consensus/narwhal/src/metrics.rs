@@ -0,0 +1,198 @@
+//! Metrics of the progress of consensus across its components.
+//!
+//! Every component publishes the metrics of its own work in its own scope. The metrics of this
+//! module describe how fast the DAG grows and how long a transaction takes from a batch to a
+//! block, which spans the batch makers, the primary and the executor. These components share one
+//! [`ConsensusMetrics`] handle to record them.
+
+use crate::types::BatchDigest;
+use reth_metrics::{
+    metrics::{Counter, Gauge, Histogram},
+    Metrics,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// The number of round advances the rate of rounds is averaged over.
+const ROUND_RATE_WINDOW: usize = 16;
+
+/// The maximum number of sealed batches whose commit latency is tracked at once.
+const MAX_TRACKED_BATCHES: usize = 16_384;
+
+/// Sealed batches that were not executed for this long are not tracked anymore, e.g. because they
+/// were never referenced by a committed header.
+const STALE_BATCH_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Metrics of the progress of the DAG and of the latency of batches.
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.progress")]
+struct ProgressMetrics {
+    /// The rate at which the primary advances rounds, averaged over the last rounds
+    rounds_per_second: Gauge,
+    /// Number of certificates that completed each round of the primary
+    certificates_per_round: Histogram,
+    /// Time from the first transaction of a batch until the batch was sealed, in seconds
+    batch_creation_latency: Histogram,
+    /// Time from sealing a batch until the block that includes it was executed, in seconds
+    commit_latency: Histogram,
+    /// Number of votes that were rejected
+    vote_rejections: Counter,
+    /// Number of certificates waiting for the sync of their missing parents
+    sync_queue_depth: Gauge,
+}
+
+/// The times of the last round advances.
+#[derive(Debug, Default)]
+struct RoundRate {
+    advances: VecDeque<Instant>,
+}
+
+impl RoundRate {
+    /// Records a round advance, and returns the average rate of the rounds in the window.
+    fn advance(&mut self, now: Instant) -> Option<f64> {
+        if self.advances.len() == ROUND_RATE_WINDOW {
+            self.advances.pop_front();
+        }
+        self.advances.push_back(now);
+        let elapsed = now.duration_since(*self.advances.front()?).as_secs_f64();
+        (elapsed > 0.0).then(|| (self.advances.len() - 1) as f64 / elapsed)
+    }
+}
+
+/// The times at which the tracked batches were sealed.
+#[derive(Debug, Default)]
+struct SealedBatches {
+    sealed: HashMap<BatchDigest, Instant>,
+}
+
+impl SealedBatches {
+    /// Tracks a sealed batch, unless too many batches are tracked already.
+    fn seal(&mut self, digest: BatchDigest, now: Instant) {
+        if self.sealed.len() >= MAX_TRACKED_BATCHES {
+            self.sealed.retain(|_, sealed| now.duration_since(*sealed) < STALE_BATCH_TIMEOUT);
+        }
+        if self.sealed.len() < MAX_TRACKED_BATCHES {
+            self.sealed.insert(digest, now);
+        }
+    }
+
+    /// Stops tracking an executed batch, and returns the time since it was sealed.
+    fn execute(&mut self, digest: &BatchDigest, now: Instant) -> Option<Duration> {
+        self.sealed.remove(digest).map(|sealed| now.duration_since(sealed))
+    }
+}
+
+#[derive(Debug, Default)]
+struct ConsensusMetricsInner {
+    metrics: ProgressMetrics,
+    rounds: Mutex<RoundRate>,
+    sealed: Mutex<SealedBatches>,
+}
+
+/// Records the metrics of the progress of consensus.
+///
+/// The commit latency is only known for the batches sealed by the node's own batch makers, so the
+/// batch makers and the executor must share the same handle.
+///
+/// Cloning is cheap, all clones record to the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct ConsensusMetrics {
+    inner: Arc<ConsensusMetricsInner>,
+}
+
+impl ConsensusMetrics {
+    /// Records that the primary advanced past a round that was completed by the given number of
+    /// certificates.
+    pub fn record_round(&self, certificates: usize) {
+        let metrics = &self.inner.metrics;
+        metrics.certificates_per_round.record(certificates as f64);
+        let rate = self
+            .inner
+            .rounds
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .advance(Instant::now());
+        if let Some(rate) = rate {
+            metrics.rounds_per_second.set(rate);
+        }
+    }
+
+    /// Records that a batch was sealed, whose first transaction arrived at `opened`.
+    pub fn record_batch_sealed(&self, digest: BatchDigest, opened: Instant) {
+        let now = Instant::now();
+        self.inner.metrics.batch_creation_latency.record(now.duration_since(opened).as_secs_f64());
+        self.inner.sealed.lock().unwrap_or_else(PoisonError::into_inner).seal(digest, now);
+    }
+
+    /// Records that the block including the given batches was executed.
+    ///
+    /// Batches that were not sealed by the node's batch makers are ignored.
+    pub fn record_batches_executed(&self, digests: impl IntoIterator<Item = BatchDigest>) {
+        let now = Instant::now();
+        let mut sealed = self.inner.sealed.lock().unwrap_or_else(PoisonError::into_inner);
+        for digest in digests {
+            if let Some(latency) = sealed.execute(&digest, now) {
+                self.inner.metrics.commit_latency.record(latency.as_secs_f64());
+            }
+        }
+    }
+
+    /// Records that a vote was rejected.
+    pub fn record_vote_rejection(&self) {
+        self.inner.metrics.vote_rejections.increment(1);
+    }
+
+    /// Sets the number of certificates that wait for the sync of their missing parents.
+    pub fn set_sync_queue_depth(&self, depth: usize) {
+        self.inner.metrics.sync_queue_depth.set(depth as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+
+    #[test]
+    fn round_rate() {
+        let mut rate = RoundRate::default();
+        let start = Instant::now();
+        assert_eq!(rate.advance(start), None);
+        assert_eq!(rate.advance(start + Duration::from_secs(10)), Some(0.1));
+
+        // the slow round is averaged in until it leaves the window
+        let mut now = start + Duration::from_secs(10);
+        for _ in 2..ROUND_RATE_WINDOW {
+            now += Duration::from_millis(500);
+            assert!(rate.advance(now).unwrap() < 2.0);
+        }
+        now += Duration::from_millis(500);
+        assert_eq!(rate.advance(now), Some(2.0));
+    }
+
+    #[test]
+    fn commit_latency_of_sealed_batches() {
+        let mut batches = SealedBatches::default();
+        let start = Instant::now();
+        let (own, other) =
+            (BatchDigest(B256::with_last_byte(1)), BatchDigest(B256::repeat_byte(2)));
+        batches.seal(own, start);
+
+        let executed = start + Duration::from_secs(3);
+        assert_eq!(batches.execute(&other, executed), None);
+        assert_eq!(batches.execute(&own, executed), Some(Duration::from_secs(3)));
+        assert_eq!(batches.execute(&own, executed), None);
+
+        // stale batches make room for new ones
+        for byte in 0..MAX_TRACKED_BATCHES as u64 {
+            batches.seal(BatchDigest(B256::left_padding_from(&byte.to_be_bytes())), start);
+        }
+        let later = start + STALE_BATCH_TIMEOUT;
+        batches.seal(own, later);
+        assert_eq!(batches.sealed.len(), 1);
+        assert_eq!(batches.execute(&own, later), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,465 @@
+//! Building the blocks of consensus output with the payload builder of the node.
+//!
+//! By default, the [`ConsensusOutputExecutor`](crate::executor::ConsensusOutputExecutor) executes
+//! the block of every sub-dag itself. With a [`PayloadBridge`], it instead hands the sequenced
+//! transactions of the sub-dag to the payload builder service of the node as
+//! [`NarwhalPayloadAttributes`], and awaits the payload built from them before it submits it to the
+//! engine. This reuses the gas accounting of the payload builder, and lets nodes replace the
+//! [`NarwhalPayloadBuilder`] with their own payload service.
+//!
+//! The payload of a sub-dag is fully determined by its attributes: unlike an Ethereum payload
+//! builder, the [`NarwhalPayloadBuilder`] doesn't pick transactions from the pool, it only leaves
+//! out the transactions of the attributes that exceed the remaining gas of the block or fail at
+//! execution. All
+//! validators of a chain must build blocks the same way, otherwise their blocks diverge.
+
+use crate::executor::{block_template, execute_sequenced, BlockExecution};
+use futures_util::future::BoxFuture;
+use reth_basic_payload_builder::{
+    BuildArguments, BuildOutcome, MissingPayloadBehaviour, PayloadBuilder, PayloadConfig,
+};
+use reth_chainspec::ChainSpec;
+use reth_evm::execute::{BlockExecutorProvider, ExecutionOutcome};
+use reth_payload_builder::{
+    error::PayloadBuilderError, EthBuiltPayload, EthPayloadBuilderAttributes, PayloadBuilderHandle,
+    PayloadId, PayloadStore,
+};
+use reth_payload_primitives::{
+    validate_version_specific_fields, BuiltPayload, EngineApiMessageVersion,
+    EngineObjectValidationError, PayloadAttributes, PayloadBuilderAttributes, PayloadTypes,
+};
+use reth_primitives::{
+    revm_primitives::{BlockEnv, CfgEnvWithHandlerCfg},
+    Address, Bytes, Header, SealedBlockWithSenders, TransactionSigned,
+    TransactionSignedEcRecovered, Withdrawals, B256, U256,
+};
+use reth_provider::StateProviderFactory;
+use reth_rpc_types::{engine::PayloadAttributes as EthPayloadAttributes, Withdrawal};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Arc};
+
+/// The payload attributes of the block of a sub-dag.
+///
+/// Narwhal blocks are not built from the transaction pool, so the Ethereum payload attributes are
+/// extended by the sequenced transactions of the sub-dag, the gas limit of the block, and the
+/// round and position the block commits to, see [`BlockCommitment`](crate::types::BlockCommitment).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NarwhalPayloadAttributes {
+    /// The Ethereum payload attributes, whose `prevRandao` is the digest of the sub-dag.
+    #[serde(flatten)]
+    pub payload_attributes: EthPayloadAttributes,
+    /// The EIP-2718 encoded transactions of the sub-dag, in execution order.
+    pub transactions: Vec<Bytes>,
+    /// The gas limit of the block.
+    #[serde(with = "alloy_serde::quantity")]
+    pub gas_limit: u64,
+    /// The round of the leader of the sub-dag.
+    #[serde(with = "alloy_serde::quantity")]
+    pub round: u64,
+    /// The position of the block among the blocks of the sub-dag.
+    #[serde(with = "alloy_serde::quantity")]
+    pub part: u32,
+}
+
+impl PayloadAttributes for NarwhalPayloadAttributes {
+    fn timestamp(&self) -> u64 {
+        self.payload_attributes.timestamp
+    }
+
+    fn withdrawals(&self) -> Option<&Vec<Withdrawal>> {
+        self.payload_attributes.withdrawals.as_ref()
+    }
+
+    fn parent_beacon_block_root(&self) -> Option<B256> {
+        self.payload_attributes.parent_beacon_block_root
+    }
+
+    fn ensure_well_formed_attributes(
+        &self,
+        chain_spec: &ChainSpec,
+        version: EngineApiMessageVersion,
+    ) -> Result<(), EngineObjectValidationError> {
+        validate_version_specific_fields(chain_spec, version, self.into())
+    }
+}
+
+/// Errors of decoding [`NarwhalPayloadAttributes`].
+#[derive(Debug, thiserror::Error)]
+pub enum NarwhalPayloadAttributesError {
+    /// A transaction could not be decoded.
+    #[error("transaction {index} of the payload attributes could not be decoded: {err}")]
+    Decode {
+        /// The index of the transaction.
+        index: usize,
+        /// The decoding error.
+        err: alloy_rlp::Error,
+    },
+    /// The sender of a transaction could not be recovered.
+    #[error("transaction {index} of the payload attributes has an invalid signature")]
+    InvalidSignature {
+        /// The index of the transaction.
+        index: usize,
+    },
+}
+
+/// The [`NarwhalPayloadAttributes`] of a payload job, with the senders of the transactions
+/// recovered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NarwhalPayloadBuilderAttributes {
+    /// The Ethereum payload builder attributes.
+    pub payload_attributes: EthPayloadBuilderAttributes,
+    /// The transactions of the sub-dag, in execution order.
+    pub transactions: Vec<TransactionSignedEcRecovered>,
+    /// The gas limit of the block.
+    pub gas_limit: u64,
+    /// The round of the leader of the sub-dag.
+    pub round: u64,
+    /// The position of the block among the blocks of the sub-dag.
+    pub part: u32,
+}
+
+impl PayloadBuilderAttributes for NarwhalPayloadBuilderAttributes {
+    type RpcPayloadAttributes = NarwhalPayloadAttributes;
+    type Error = NarwhalPayloadAttributesError;
+
+    /// Decodes the transactions of the attributes and recovers their senders.
+    ///
+    /// The [`PayloadId`] is derived from the Ethereum payload attributes only. It's still unique
+    /// per sub-dag, since the `prevRandao` is the digest of the sub-dag, which commits to its
+    /// transactions.
+    fn try_new(parent: B256, attributes: NarwhalPayloadAttributes) -> Result<Self, Self::Error> {
+        let transactions = attributes
+            .transactions
+            .iter()
+            .enumerate()
+            .map(|(index, encoded)| {
+                TransactionSigned::decode_enveloped(&mut &encoded[..])
+                    .map_err(|err| NarwhalPayloadAttributesError::Decode { index, err })?
+                    .into_ecrecovered()
+                    .ok_or(NarwhalPayloadAttributesError::InvalidSignature { index })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            payload_attributes: EthPayloadBuilderAttributes::new(
+                parent,
+                attributes.payload_attributes,
+            ),
+            transactions,
+            gas_limit: attributes.gas_limit,
+            round: attributes.round,
+            part: attributes.part,
+        })
+    }
+
+    fn payload_id(&self) -> PayloadId {
+        self.payload_attributes.id
+    }
+
+    fn parent(&self) -> B256 {
+        self.payload_attributes.parent
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.payload_attributes.timestamp
+    }
+
+    fn parent_beacon_block_root(&self) -> Option<B256> {
+        self.payload_attributes.parent_beacon_block_root
+    }
+
+    fn suggested_fee_recipient(&self) -> Address {
+        self.payload_attributes.suggested_fee_recipient
+    }
+
+    fn prev_randao(&self) -> B256 {
+        self.payload_attributes.prev_randao
+    }
+
+    fn withdrawals(&self) -> &Withdrawals {
+        &self.payload_attributes.withdrawals
+    }
+
+    fn cfg_and_block_env(
+        &self,
+        chain_spec: &ChainSpec,
+        parent: &Header,
+    ) -> (CfgEnvWithHandlerCfg, BlockEnv) {
+        let (cfg, mut block_env) = self.payload_attributes.cfg_and_block_env(chain_spec, parent);
+        block_env.gas_limit = U256::from(self.gas_limit);
+        (cfg, block_env)
+    }
+}
+
+/// Builds the payloads of sub-dags from their [`NarwhalPayloadBuilderAttributes`].
+///
+/// The transactions of the attributes are executed in order, like the
+/// [`ConsensusOutputExecutor`](crate::executor::ConsensusOutputExecutor) executes them, except
+/// that a transaction whose gas limit exceeds the remaining gas of the block is left out as well.
+/// Transactions that fail at execution are excluded the same way. Since the
+/// payload is fully determined by its attributes, it's built once per payload job.
+#[derive(Debug, Clone)]
+pub struct NarwhalPayloadBuilder<Executor> {
+    executor: Executor,
+}
+
+impl<Executor> NarwhalPayloadBuilder<Executor> {
+    /// Creates a builder that executes the payloads with the executor.
+    pub const fn new(executor: Executor) -> Self {
+        Self { executor }
+    }
+}
+
+impl<Executor: BlockExecutorProvider> NarwhalPayloadBuilder<Executor> {
+    /// Builds and executes the payload of the attributes on top of their parent block.
+    pub fn build<Client: StateProviderFactory>(
+        &self,
+        client: &Client,
+        config: PayloadConfig<NarwhalPayloadBuilderAttributes>,
+    ) -> Result<EthBuiltPayload, PayloadBuilderError> {
+        let PayloadConfig { parent_block, attributes, chain_spec, .. } = config;
+        let NarwhalPayloadBuilderAttributes {
+            payload_attributes,
+            transactions,
+            gas_limit,
+            round,
+            part,
+        } = attributes;
+
+        // the gas limit of a transaction bounds the gas it uses
+        let mut remaining_gas = gas_limit;
+        let transactions = transactions
+            .into_iter()
+            .filter(|transaction| {
+                let fits = transaction.gas_limit() <= remaining_gas;
+                if fits {
+                    remaining_gas -= transaction.gas_limit();
+                }
+                fits
+            })
+            .collect();
+
+        let state = client.state_by_block_hash(parent_block.hash())?;
+        let (BlockExecution { block, execution_outcome, .. }, _) = execute_sequenced(
+            &chain_spec,
+            &self.executor,
+            state,
+            |body| {
+                block_template(
+                    &chain_spec,
+                    &parent_block.header,
+                    payload_attributes.suggested_fee_recipient,
+                    payload_attributes.timestamp,
+                    payload_attributes.prev_randao,
+                    gas_limit,
+                    body,
+                )
+            },
+            round,
+            part,
+            transactions,
+        )
+        .map_err(PayloadBuilderError::other)?;
+
+        let receipts = execution_outcome
+            .receipts_by_block(block.number)
+            .iter()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut cumulative_gas_used = 0;
+        let fees =
+            block.body.iter().zip(&receipts).fold(U256::ZERO, |fees, (transaction, receipt)| {
+                let gas_used = receipt.cumulative_gas_used - cumulative_gas_used;
+                cumulative_gas_used = receipt.cumulative_gas_used;
+                let tip =
+                    transaction.effective_tip_per_gas(block.base_fee_per_gas).unwrap_or_default();
+                fees + U256::from(tip) * U256::from(gas_used)
+            });
+
+        Ok(EthBuiltPayload::new(payload_attributes.id, block.block.seal_slow(), fees, receipts))
+    }
+}
+
+impl<Pool, Client, Executor> PayloadBuilder<Pool, Client> for NarwhalPayloadBuilder<Executor>
+where
+    Client: StateProviderFactory,
+    Executor: BlockExecutorProvider,
+{
+    type Attributes = NarwhalPayloadBuilderAttributes;
+    type BuiltPayload = EthBuiltPayload;
+
+    fn try_build(
+        &self,
+        args: BuildArguments<Pool, Client, Self::Attributes, Self::BuiltPayload>,
+    ) -> Result<BuildOutcome<Self::BuiltPayload>, PayloadBuilderError> {
+        let BuildArguments { client, cached_reads, config, best_payload, .. } = args;
+        // a rebuild would produce the same payload
+        if best_payload.is_some() {
+            return Ok(BuildOutcome::Aborted { fees: U256::ZERO, cached_reads })
+        }
+        let payload = self.build(&client, config)?;
+        Ok(BuildOutcome::Better { payload, cached_reads })
+    }
+
+    /// Awaits the payload in progress, since an empty payload would drop the transactions of the
+    /// sub-dag.
+    fn on_missing_payload(
+        &self,
+        _args: BuildArguments<Pool, Client, Self::Attributes, Self::BuiltPayload>,
+    ) -> MissingPayloadBehaviour<Self::BuiltPayload> {
+        MissingPayloadBehaviour::AwaitInProgress
+    }
+
+    /// Builds the payload of the attributes, a sub-dag has no empty payload.
+    fn build_empty_payload(
+        &self,
+        client: &Client,
+        config: PayloadConfig<Self::Attributes>,
+    ) -> Result<Self::BuiltPayload, PayloadBuilderError> {
+        self.build(client, config)
+    }
+}
+
+/// Errors of the [`PayloadBridge`].
+#[derive(Debug, thiserror::Error)]
+pub enum PayloadBridgeError {
+    /// The payload builder could not derive its attributes from the payload attributes.
+    #[error("invalid payload attributes: {0}")]
+    Attributes(Box<dyn std::error::Error + Send + Sync>),
+    /// The payload builder failed to build the payload.
+    #[error(transparent)]
+    Builder(#[from] PayloadBuilderError),
+    /// The payload job ended without a payload.
+    #[error("payload {0} was not built")]
+    MissingPayload(PayloadId),
+    /// The sender of a transaction of the built payload could not be recovered.
+    #[error("built payload contains a transaction with an invalid signature")]
+    InvalidSignature,
+}
+
+/// The payload built for a sub-dag.
+#[derive(Debug, Clone)]
+pub struct BuiltSubDagPayload {
+    /// The sealed block with the senders of its transactions.
+    pub block: SealedBlockWithSenders,
+    /// The state changes and receipts of the block.
+    ///
+    /// Only holds the receipts and requests of the block if the payload builder doesn't provide
+    /// the execution of the block, which the [`NarwhalPayloadBuilder`] doesn't.
+    pub execution_outcome: ExecutionOutcome,
+}
+
+/// Builds the payload of the attributes on top of the parent block.
+type BuildPayload = dyn Fn(
+        B256,
+        NarwhalPayloadAttributes,
+    ) -> BoxFuture<'static, Result<BuiltSubDagPayload, PayloadBridgeError>>
+    + Send
+    + Sync;
+
+/// Builds the payloads of sub-dags with the payload builder service of the node.
+///
+/// Works with any payload service whose payload builder attributes are derived from
+/// [`NarwhalPayloadAttributes`].
+#[derive(Clone)]
+pub struct PayloadBridge {
+    build: Arc<BuildPayload>,
+}
+
+impl PayloadBridge {
+    /// Creates a bridge to the payload builder service of the handle.
+    pub fn new<T>(payload_builder: PayloadBuilderHandle<T>) -> Self
+    where
+        T: PayloadTypes + 'static,
+        T::PayloadBuilderAttributes:
+            PayloadBuilderAttributes<RpcPayloadAttributes = NarwhalPayloadAttributes>,
+        <T::PayloadBuilderAttributes as PayloadBuilderAttributes>::Error: Send + Sync + 'static,
+    {
+        let build = move |parent, attributes| {
+            let payload_builder = payload_builder.clone();
+            Box::pin(async move {
+                let attributes = T::PayloadBuilderAttributes::try_new(parent, attributes)
+                    .map_err(|err| PayloadBridgeError::Attributes(Box::new(err)))?;
+                let id = payload_builder.new_payload(attributes).await?;
+                let payload = PayloadStore::from(payload_builder)
+                    .resolve(id)
+                    .await
+                    .ok_or(PayloadBridgeError::MissingPayload(id))??;
+                built_sub_dag_payload(&payload)
+            }) as BoxFuture<'static, _>
+        };
+        Self { build: Arc::new(build) }
+    }
+
+    /// Builds the payload of the attributes on top of the parent block, and awaits it.
+    pub async fn build(
+        &self,
+        parent: B256,
+        attributes: NarwhalPayloadAttributes,
+    ) -> Result<BuiltSubDagPayload, PayloadBridgeError> {
+        (self.build)(parent, attributes).await
+    }
+}
+
+impl fmt::Debug for PayloadBridge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PayloadBridge").finish_non_exhaustive()
+    }
+}
+
+/// Returns the block and the execution of a built payload.
+fn built_sub_dag_payload(
+    payload: &impl BuiltPayload,
+) -> Result<BuiltSubDagPayload, PayloadBridgeError> {
+    if let Some(executed) = payload.executed_block() {
+        return Ok(BuiltSubDagPayload {
+            block: executed.sealed_block_with_senders(),
+            execution_outcome: Arc::unwrap_or_clone(executed.execution_output),
+        })
+    }
+
+    let block = payload.block().clone();
+    let number = block.number;
+    let requests = block.requests.clone().unwrap_or_default();
+    let block = block.seal_with_senders().ok_or(PayloadBridgeError::InvalidSignature)?;
+    let receipts = payload.receipts().iter().cloned().map(Some).collect::<Vec<_>>();
+    let execution_outcome =
+        ExecutionOutcome::new(Default::default(), vec![receipts].into(), number, vec![requests]);
+    Ok(BuiltSubDagPayload { block, execution_outcome })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_roundtrip() {
+        let attributes = NarwhalPayloadAttributes {
+            payload_attributes: EthPayloadAttributes {
+                timestamp: 1,
+                prev_randao: B256::repeat_byte(1),
+                suggested_fee_recipient: Address::ZERO,
+                withdrawals: Some(Vec::new()),
+                parent_beacon_block_root: None,
+            },
+            transactions: vec![Bytes::from_static(&[0x02])],
+            gas_limit: 30_000_000,
+            round: 4,
+            part: 1,
+        };
+        let json = serde_json::to_value(&attributes).unwrap();
+        assert_eq!(json["prevRandao"], serde_json::json!(B256::repeat_byte(1)));
+        assert_eq!(json["transactions"], serde_json::json!(["0x02"]));
+        assert_eq!(json["gasLimit"], "0x1c9c380");
+        assert_eq!(json["round"], "0x4");
+        assert_eq!(json["part"], "0x1");
+        assert_eq!(serde_json::from_value::<NarwhalPayloadAttributes>(json).unwrap(), attributes);
+
+        assert!(matches!(
+            NarwhalPayloadBuilderAttributes::try_new(B256::ZERO, attributes),
+            Err(NarwhalPayloadAttributesError::Decode { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+//! Maintenance of the transaction pool after every executed commit.
+//!
+//! Once the block of a sub-dag is canonical, the pool must remove the transactions it included and
+//! update the nonces and balances of the accounts it changed, which promotes the transactions that
+//! were waiting for them. Blocks of a narwhal chain are final, so the canonical chain only grows by
+//! the blocks of executed sub-dags. [`maintain_narwhal_pool`] relies on this: a commit that extends
+//! the last block seen by the pool only updates the accounts the commit changed, without the reorg
+//! handling of the Ethereum pool maintenance. A commit that doesn't extend it, e.g. because the
+//! pool missed notifications, additionally reloads the accounts of all senders of the pool.
+
+use futures_util::{Stream, StreamExt};
+use reth_chainspec::ChainSpec;
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_primitives::B256;
+use reth_provider::{
+    AccountReader, CanonStateNotification, Chain, ChangedAccount, ProviderError,
+    StateProviderFactory,
+};
+use reth_transaction_pool::{CanonicalStateUpdate, TransactionPool, TransactionPoolExt};
+use std::{collections::HashSet, sync::Arc};
+use tracing::{debug, warn};
+
+/// Metrics of [`maintain_narwhal_pool`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.pool_maintenance")]
+struct PoolMaintenanceMetrics {
+    /// Number of commits that extended the last block seen by the pool
+    linear_commits: Counter,
+    /// Number of commits after which the accounts of all senders of the pool were reloaded
+    reloads: Counter,
+    /// Number of reverted chain segments, which only an unwind of the chain causes
+    reverts: Counter,
+}
+
+/// Updates the pool with every commit of the canonical chain, until the stream ends.
+///
+/// Replaces the maintenance task of the Ethereum pool, which must not run next to it. Reverted
+/// blocks are not reinjected into the pool, since only an unwind of the chain reverts blocks of a
+/// narwhal chain.
+pub async fn maintain_narwhal_pool<Client, Pool, St>(
+    client: Client,
+    pool: Pool,
+    chain_spec: Arc<ChainSpec>,
+    mut events: St,
+) where
+    Client: StateProviderFactory,
+    Pool: TransactionPoolExt,
+    St: Stream<Item = CanonStateNotification> + Unpin,
+{
+    let metrics = PoolMaintenanceMetrics::default();
+    while let Some(event) = events.next().await {
+        let chain = match event {
+            CanonStateNotification::Commit { new } => new,
+            CanonStateNotification::Reorg { old, new } => {
+                metrics.reverts.increment(1);
+                warn!(
+                    target: "consensus::narwhal",
+                    reverted = old.len(),
+                    "Canonical blocks were reverted, the transactions of the pool may be stale"
+                );
+                // the next commit reloads the senders of the pool, since it doesn't extend the
+                // last block seen by the pool
+                if new.is_empty() {
+                    continue
+                }
+                new
+            }
+        };
+        on_commit(&client, &pool, &chain_spec, &chain, &metrics);
+    }
+}
+
+/// Updates the pool with the blocks of a commit.
+fn on_commit<Client, Pool>(
+    client: &Client,
+    pool: &Pool,
+    chain_spec: &ChainSpec,
+    chain: &Chain,
+    metrics: &PoolMaintenanceMetrics,
+) where
+    Client: StateProviderFactory,
+    Pool: TransactionPoolExt,
+{
+    let (blocks, execution_outcome) = chain.inner();
+    let tip = blocks.tip();
+    let mut changed_accounts = execution_outcome.changed_accounts().collect::<Vec<_>>();
+
+    if blocks.first().parent_hash == pool.block_info().last_seen_block_hash {
+        metrics.linear_commits.increment(1);
+    } else {
+        metrics.reloads.increment(1);
+        debug!(
+            target: "consensus::narwhal",
+            first = blocks.first().number,
+            tip = tip.number,
+            "Commit doesn't extend the block of the pool, reloading senders"
+        );
+        match reload_senders(client, pool, tip.hash(), &changed_accounts) {
+            Ok(reloaded) => changed_accounts.extend(reloaded),
+            Err(err) => warn!(target: "consensus::narwhal", %err, "Failed to reload pool senders"),
+        }
+    }
+
+    // the timestamp of the next block is only known once its sub-dag is committed
+    let pending_block_base_fee = tip
+        .next_block_base_fee(chain_spec.base_fee_params_at_timestamp(tip.timestamp))
+        .unwrap_or_default();
+    // commits are final, so the blobs of the included transactions are no longer needed
+    let mined_blobs = blocks
+        .transactions()
+        .filter(|transaction| transaction.is_eip4844())
+        .map(|transaction| transaction.hash)
+        .collect::<Vec<_>>();
+
+    pool.on_canonical_state_change(CanonicalStateUpdate {
+        new_tip: &tip.block,
+        pending_block_base_fee,
+        pending_block_blob_fee: tip.next_block_blob_fee(),
+        changed_accounts,
+        mined_transactions: blocks.transaction_hashes().collect(),
+    });
+    if !mined_blobs.is_empty() {
+        pool.delete_blobs(mined_blobs);
+    }
+}
+
+/// Returns the accounts of the senders of the pool at the block, except for the accounts that are
+/// already known to have changed.
+fn reload_senders<Client, Pool>(
+    client: &Client,
+    pool: &Pool,
+    block: B256,
+    changed_accounts: &[ChangedAccount],
+) -> Result<Vec<ChangedAccount>, ProviderError>
+where
+    Client: StateProviderFactory,
+    Pool: TransactionPool,
+{
+    let changed = changed_accounts.iter().map(|account| account.address).collect::<HashSet<_>>();
+    let state = client.state_by_block_hash(block)?;
+    pool.unique_senders()
+        .into_iter()
+        .filter(|sender| !changed.contains(sender))
+        .map(|address| {
+            let account = state.basic_account(address)?.unwrap_or_default();
+            Ok(ChangedAccount { address, nonce: account.nonce, balance: account.balance })
+        })
+        .collect()
+}
@@ -0,0 +1,104 @@
+//! Well-known addresses of the system contracts of narwhal chains.
+//!
+//! System contracts are predeployed in the genesis state of a chain. The addresses share the
+//! `0x4e61727768616c` (`"Narwhal"`) prefix so that they don't collide with precompiles or the
+//! predeploys of other chains. A chain only needs to predeploy the contracts it uses, the node
+//! refers to them by these addresses only.
+
+use alloy_primitives::{address, Address, Bytes, B256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "execution")]
+use reth_primitives::{Genesis, GenesisAccount};
+
+/// The message queue, which contracts call to send messages to other chains.
+pub const MESSAGE_QUEUE: Address = address!("4e61727768616c00000000000000000000000001");
+
+/// The bridge, which mints deposits from other chains.
+pub const BRIDGE: Address = address!("4e61727768616c00000000000000000000000002");
+
+/// The randomness beacon, which exposes the randomness of the consensus output to contracts.
+pub const RANDOMNESS: Address = address!("4e61727768616c00000000000000000000000003");
+
+/// The fee vault, which collects the transaction fees of a block.
+pub const FEE_VAULT: Address = address!("4e61727768616c00000000000000000000000004");
+
+/// The sender registry, which holds the allowlist of senders of permissioned chains.
+pub const SENDER_REGISTRY: Address = address!("4e61727768616c00000000000000000000000005");
+
+/// A system contract that is deployed in the genesis state of a chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Predeploy {
+    /// The address of the contract, usually one of the well-known addresses of this module.
+    pub address: Address,
+    /// The runtime code of the contract.
+    pub code: Bytes,
+    /// The initial storage of the contract.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<B256, B256>,
+}
+
+impl Predeploy {
+    /// Creates a new predeploy with the given runtime code and empty storage.
+    pub const fn new(address: Address, code: Bytes) -> Self {
+        Self { address, code, storage: BTreeMap::new() }
+    }
+
+    /// Sets the initial value of a storage slot.
+    pub fn with_storage(mut self, slot: B256, value: B256) -> Self {
+        self.storage.insert(slot, value);
+        self
+    }
+
+    /// Returns the genesis account of the predeploy.
+    ///
+    /// The nonce is set to 1, like for any contract created after EIP-161.
+    #[cfg(feature = "execution")]
+    pub fn genesis_account(&self) -> GenesisAccount {
+        GenesisAccount {
+            nonce: Some(1),
+            code: Some(self.code.clone()),
+            storage: (!self.storage.is_empty()).then(|| self.storage.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Adds the predeploys to the alloc of the genesis, replacing existing accounts at their addresses.
+#[cfg(feature = "execution")]
+pub fn with_predeploys(
+    genesis: Genesis,
+    predeploys: impl IntoIterator<Item = Predeploy>,
+) -> Genesis {
+    genesis.extend_accounts(
+        predeploys.into_iter().map(|predeploy| (predeploy.address, predeploy.genesis_account())),
+    )
+}
+
+#[cfg(all(test, feature = "execution"))]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+
+    #[test]
+    fn predeploys_in_genesis_alloc() {
+        let bridge = Predeploy::new(BRIDGE, Bytes::from_static(&[0x60, 0x00]))
+            .with_storage(B256::ZERO, B256::with_last_byte(1));
+        let vault = Predeploy::new(FEE_VAULT, Bytes::from_static(&[0x00]));
+        let genesis = Genesis::default()
+            .extend_accounts([(FEE_VAULT, GenesisAccount::default().with_balance(U256::MAX))]);
+
+        let genesis = with_predeploys(genesis, [bridge.clone(), vault]);
+        assert_eq!(genesis.alloc.len(), 2);
+
+        let account = &genesis.alloc[&BRIDGE];
+        assert_eq!(account.code, Some(bridge.code));
+        assert_eq!(account.storage, Some(bridge.storage));
+        assert_eq!(account.nonce, Some(1));
+
+        let account = &genesis.alloc[&FEE_VAULT];
+        assert_eq!(account.storage, None);
+        assert_eq!(account.balance, U256::ZERO);
+    }
+}
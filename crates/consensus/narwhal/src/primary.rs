@@ -0,0 +1,945 @@
+//! The primary of an authority.
+//!
+//! In every round, the primary proposes a [`Header`] that references the batches its workers
+//! sealed since the last proposal, and the certificates of the previous round as its parents. It
+//! advances to the next round once it received the certificates of a quorum of the committee for
+//! the current round, which become the parents of its next header.
+//!
+//! An authority can run several workers, which all send their sealed batches to the primary
+//! through the same channel. The workers take turns in the payload of a header, so a worker that
+//! seals batches faster than the others, e.g. the worker of a busy lane of the
+//! [`TransactionRouting`](crate::worker::TransactionRouting), can't starve them.
+//!
+//! The [`Proposer`] implements these rules without any I/O, the [`Primary`] task drives it from
+//! the channels of the workers and the network. With a [`DagStore`], the primary persists the
+//! certificates and its own headers, and resumes from the last round of the stored DAG after a
+//! restart. With [`Chaos`], the primary simulates such restarts while it runs.
+//!
+//! With a [`SigningLease`], the primary only proposes the headers of rounds the lease authorizes,
+//! so that the standby node of the validator never proposes a header of the same round, see
+//! [`crate::failover`].
+
+use crate::{
+    backpressure::Backpressure,
+    chaos::Chaos,
+    dag_store::{DagStore, DagStoreError},
+    determinism::{Clock, SystemClock},
+    events::{NarwhalEvent, NarwhalEvents},
+    failover::SigningLease,
+    keys::KeyProvider,
+    metrics::ConsensusMetrics,
+    signature::BlsPublicKey,
+    types::{BatchRef, Certificate, CertificateDigest, Header, Round, WorkerId},
+    worker::{BatchQuota, SealedBatch},
+};
+use reth_metrics::{
+    metrics::{Counter, Gauge, Histogram},
+    Metrics,
+};
+use reth_narwhal_verifier::{AuthorityIndex, Epoch, Stake, VerifierCommittee};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, watch},
+    time::Instant,
+};
+use tracing::{debug, error, info, warn};
+
+/// Configuration of the [`Primary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PrimaryConfig {
+    /// The number of batches at which a header is proposed without waiting for
+    /// `max_header_delay`, and the maximum number of batches a header references.
+    pub max_header_batches: usize,
+    /// The maximum time the primary waits for batches before it proposes a header of the current
+    /// round anyway.
+    #[serde(with = "humantime_serde")]
+    pub max_header_delay: Duration,
+}
+
+impl Default for PrimaryConfig {
+    fn default() -> Self {
+        Self { max_header_batches: 32, max_header_delay: Duration::from_millis(200) }
+    }
+}
+
+/// The certificates of a round, by author.
+#[derive(Debug, Default)]
+struct RoundCertificates {
+    digests: BTreeMap<AuthorityIndex, CertificateDigest>,
+    stake: Stake,
+}
+
+/// Decides when the primary proposes headers and advances rounds.
+#[derive(Debug)]
+pub struct Proposer {
+    epoch: Epoch,
+    author: AuthorityIndex,
+    max_header_batches: usize,
+    /// The stake of each authority, by index.
+    stakes: Vec<Stake>,
+    quorum_threshold: Stake,
+    round: Round,
+    /// The parents of the header of the current round, in ascending order.
+    parents: Vec<CertificateDigest>,
+    proposed: bool,
+    /// Batches of the workers that were not yet referenced by a header, by worker.
+    payload: BTreeMap<WorkerId, VecDeque<BatchRef>>,
+    /// The worker whose batches are included first by the next header.
+    next_worker: WorkerId,
+    /// Certificates of the current and later rounds.
+    certificates: BTreeMap<Round, RoundCertificates>,
+}
+
+impl Proposer {
+    /// Creates the proposer of `author` for the first round of the committee's epoch.
+    ///
+    /// The headers of round 1 reference the genesis certificates of all authorities.
+    pub fn new<K>(
+        committee: &VerifierCommittee<K>,
+        author: AuthorityIndex,
+        config: PrimaryConfig,
+    ) -> Self {
+        let epoch = committee.epoch();
+        let stakes = committee.authorities().iter().map(|authority| authority.stake).collect();
+        let genesis = Certificate::genesis(epoch, 0..committee.authorities().len() as u32);
+        let mut parents = genesis.iter().map(Certificate::digest).collect::<Vec<_>>();
+        parents.sort_unstable();
+        Self {
+            epoch,
+            author,
+            max_header_batches: config.max_header_batches.max(1),
+            stakes,
+            quorum_threshold: committee.quorum_threshold(),
+            round: 1,
+            parents,
+            proposed: false,
+            payload: BTreeMap::new(),
+            next_worker: 0,
+            certificates: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the proposer of the same author after a restart, which starts over from the first
+    /// round of the epoch without any batches or certificates.
+    pub fn restarted(&self) -> Self {
+        let genesis = Certificate::genesis(self.epoch, 0..self.stakes.len() as u32);
+        let mut parents = genesis.iter().map(Certificate::digest).collect::<Vec<_>>();
+        parents.sort_unstable();
+        Self {
+            round: 1,
+            parents,
+            proposed: false,
+            payload: BTreeMap::new(),
+            next_worker: 0,
+            certificates: BTreeMap::new(),
+            stakes: self.stakes.clone(),
+            ..*self
+        }
+    }
+
+    /// Limits the batches of a header to the quota of the author, see [`BatchQuota`].
+    pub fn limit_to_quota(&mut self, quota: &BatchQuota) {
+        let stake = self.stakes.get(self.author as usize).copied().unwrap_or_default();
+        let total_stake =
+            self.stakes.iter().fold(0, |total: Stake, stake| total.saturating_add(*stake));
+        let quota = usize::try_from(quota.quota(stake, total_stake)).unwrap_or(usize::MAX);
+        self.max_header_batches = self.max_header_batches.min(quota);
+    }
+
+    /// Returns the current round.
+    pub const fn round(&self) -> Round {
+        self.round
+    }
+
+    /// Returns `true` if the header of the current round was proposed.
+    pub const fn proposed(&self) -> bool {
+        self.proposed
+    }
+
+    /// Marks the header of the current round as proposed, e.g. because it was proposed before a
+    /// restart.
+    pub fn mark_proposed(&mut self) {
+        self.proposed = true;
+    }
+
+    /// Returns the number of batches waiting to be referenced by a header.
+    pub fn pending_batches(&self) -> usize {
+        self.payload.values().map(VecDeque::len).sum()
+    }
+
+    /// Returns the batches waiting to be referenced by a header, in the order they are included.
+    ///
+    /// The workers with pending batches take turns, starting with the worker after the last one
+    /// included by the previous header, so every worker gets an equal share of a header and the
+    /// share of a worker without batches goes to the others.
+    pub fn payload(&self) -> impl Iterator<Item = &BatchRef> + '_ {
+        let workers = self
+            .payload
+            .range(self.next_worker..)
+            .chain(self.payload.range(..self.next_worker))
+            .map(|(_, batches)| batches)
+            .collect::<Vec<_>>();
+        let depth = workers.iter().map(|batches| batches.len()).max().unwrap_or_default();
+        let mut payload = Vec::with_capacity(self.pending_batches());
+        for turn in 0..depth {
+            payload.extend(workers.iter().filter_map(|batches| batches.get(turn)));
+        }
+        payload.into_iter()
+    }
+
+    /// Queues a batch of one of the author's workers for the next header.
+    pub fn add_batch(&mut self, batch: BatchRef) {
+        self.payload.entry(batch.worker).or_default().push_back(batch);
+    }
+
+    /// Records a certificate of the committee.
+    ///
+    /// Returns `true` if the certificate completed a quorum for the current or a later round, in
+    /// which case the proposer advanced to the round after it. Certificates of other epochs, of
+    /// unknown authorities, of past rounds, and further certificates of the same author and round
+    /// are ignored.
+    pub fn add_certificate(&mut self, certificate: &Certificate) -> bool {
+        let round = certificate.round();
+        if certificate.header.epoch != self.epoch || round < self.round {
+            return false
+        }
+        let Some(&stake) = self.stakes.get(certificate.author() as usize) else { return false };
+
+        let certificates = self.certificates.entry(round).or_default();
+        if certificates.digests.contains_key(&certificate.author()) {
+            return false
+        }
+        certificates.digests.insert(certificate.author(), certificate.digest());
+        certificates.stake = certificates.stake.saturating_add(stake);
+        if certificates.stake < self.quorum_threshold {
+            return false
+        }
+
+        let mut parents = certificates.digests.values().copied().collect::<Vec<_>>();
+        parents.sort_unstable();
+        self.parents = parents;
+        self.round = round + 1;
+        self.proposed = false;
+        self.certificates = self.certificates.split_off(&self.round);
+        true
+    }
+
+    /// Returns the header of the current round if it's due.
+    ///
+    /// A header is due once enough batches are queued to fill it, or if `force` is set, e.g.
+    /// because `max_header_delay` passed. At most one header is proposed per round. `now` is the
+    /// creation time of the header in milliseconds since the unix epoch.
+    pub fn propose(&mut self, force: bool, now: u64) -> Option<Header> {
+        if self.proposed || (!force && self.pending_batches() < self.max_header_batches) {
+            return None
+        }
+        let payload =
+            self.payload().take(self.max_header_batches).copied().collect::<Vec<BatchRef>>();
+        for batch in &payload {
+            // the batches of a worker are included in the order they were sealed
+            if let Some(batches) = self.payload.get_mut(&batch.worker) {
+                batches.pop_front();
+            }
+        }
+        self.payload.retain(|_, batches| !batches.is_empty());
+        if let Some(last) = payload.last() {
+            self.next_worker = last.worker.wrapping_add(1);
+        }
+        self.proposed = true;
+        Some(Header {
+            epoch: self.epoch,
+            round: self.round,
+            author: self.author,
+            payload,
+            parents: self.parents.clone(),
+            created_at: now,
+        })
+    }
+
+    /// Takes back a header of the current round that wasn't sent, e.g. because the authority
+    /// wasn't authorized to sign it. Its batches are queued ahead of the others, and the header
+    /// of the round is proposed again.
+    pub fn withdraw(&mut self, header: Header) {
+        for batch in header.payload.into_iter().rev() {
+            self.payload.entry(batch.worker).or_default().push_front(batch);
+        }
+        self.proposed = false;
+    }
+}
+
+/// Metrics of the [`Primary`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.primary")]
+struct PrimaryMetrics {
+    /// The current round
+    round: Gauge,
+    /// Number of proposed headers
+    proposed_headers: Counter,
+    /// Number of batches referenced per proposed header
+    header_batches: Histogram,
+    /// Number of batches waiting to be referenced by a header
+    pending_batches: Gauge,
+    /// Whether header proposals are throttled because execution lags behind
+    throttled: Gauge,
+}
+
+/// A handle to query the round and the pending batches of the [`Primary`].
+///
+/// Cloning is cheap, all clones observe the same primary.
+#[derive(Debug, Clone)]
+pub struct PrimaryHandle {
+    author: AuthorityIndex,
+    round: watch::Receiver<Round>,
+    pending_batches: watch::Receiver<Vec<BatchRef>>,
+}
+
+impl PrimaryHandle {
+    /// Returns the authority of the primary.
+    pub const fn author(&self) -> AuthorityIndex {
+        self.author
+    }
+
+    /// Returns the current round of the primary.
+    pub fn current_round(&self) -> Round {
+        *self.round.borrow()
+    }
+
+    /// Returns the batches of the workers that are waiting to be referenced by a header.
+    pub fn pending_batches(&self) -> Vec<BatchRef> {
+        self.pending_batches.borrow().clone()
+    }
+
+    /// Returns a receiver that is notified whenever the primary advances its round.
+    pub fn subscribe(&self) -> watch::Receiver<Round> {
+        self.round.clone()
+    }
+}
+
+/// The task that proposes the headers of an authority.
+///
+/// Batches sealed by the authority's workers are received from a channel, as are the certificates
+/// of the committee, including those of the authority's own headers. Proposed headers are sent to
+/// the component that broadcasts them and collects the votes.
+#[derive(Debug)]
+pub struct Primary {
+    proposer: Proposer,
+    max_header_delay: Duration,
+    batches: mpsc::Receiver<SealedBatch>,
+    certificates: mpsc::Receiver<Certificate>,
+    headers: mpsc::Sender<Header>,
+    round: watch::Sender<Round>,
+    pending_batches: watch::Sender<Vec<BatchRef>>,
+    store: Option<Arc<dyn DagStore>>,
+    consensus_metrics: Option<ConsensusMetrics>,
+    backpressure: Option<Backpressure>,
+    events: Option<NarwhalEvents>,
+    chaos: Option<Chaos>,
+    lease: Option<SigningLease>,
+    metrics: PrimaryMetrics,
+}
+
+impl Primary {
+    /// Creates the primary of `author` for the first round of the committee's epoch.
+    pub fn new<K>(
+        committee: &VerifierCommittee<K>,
+        author: AuthorityIndex,
+        config: PrimaryConfig,
+        batches: mpsc::Receiver<SealedBatch>,
+        certificates: mpsc::Receiver<Certificate>,
+        headers: mpsc::Sender<Header>,
+    ) -> (Self, PrimaryHandle) {
+        let proposer = Proposer::new(committee, author, config);
+        let (round, round_rx) = watch::channel(proposer.round());
+        let (pending_batches, pending_batches_rx) = watch::channel(Vec::new());
+        let primary = Self {
+            proposer,
+            max_header_delay: config.max_header_delay,
+            batches,
+            certificates,
+            headers,
+            round,
+            pending_batches,
+            store: None,
+            consensus_metrics: None,
+            backpressure: None,
+            events: None,
+            chaos: None,
+            lease: None,
+            metrics: PrimaryMetrics::default(),
+        };
+        (primary, PrimaryHandle { author, round: round_rx, pending_batches: pending_batches_rx })
+    }
+
+    /// Creates the primary of the authority whose keys are provided, `None` if the authority is not
+    /// a member of the committee.
+    pub fn for_keys(
+        committee: &VerifierCommittee<BlsPublicKey>,
+        keys: &dyn KeyProvider,
+        config: PrimaryConfig,
+        batches: mpsc::Receiver<SealedBatch>,
+        certificates: mpsc::Receiver<Certificate>,
+        headers: mpsc::Sender<Header>,
+    ) -> Option<(Self, PrimaryHandle)> {
+        let public_key = keys.authority_public_key();
+        let author = committee
+            .authorities()
+            .iter()
+            .position(|authority| authority.public_key == public_key)?;
+        Some(Self::new(committee, author as AuthorityIndex, config, batches, certificates, headers))
+    }
+
+    /// Persists the DAG in the store, and resumes from the last round of the DAG that is already
+    /// stored in it.
+    pub fn with_store(mut self, store: Arc<dyn DagStore>) -> Result<Self, DagStoreError> {
+        self.recover(store.as_ref())?;
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// Resumes the proposer from the last round of the stored DAG.
+    fn recover(&mut self, store: &dyn DagStore) -> Result<(), DagStoreError> {
+        let recovered = store.recover()?;
+        for certificate in &recovered.certificates {
+            self.proposer.add_certificate(certificate);
+        }
+        // the author votes for its own headers, so a vote means the header of the round was
+        // proposed before the restart, and proposing another one would equivocate
+        if store.vote(self.proposer.round(), self.proposer.author)?.is_some() {
+            self.proposer.mark_proposed();
+        }
+        let round = self.proposer.round();
+        info!(
+            target: "consensus::narwhal",
+            round,
+            certificates = recovered.certificates.len(),
+            "Recovered DAG"
+        );
+        self.round.send_replace(round);
+        Ok(())
+    }
+
+    /// Proposes headers with at most the batch quota of the author of the chain, which the other
+    /// authorities refuse to vote for otherwise.
+    pub fn with_batch_quota(mut self, quota: BatchQuota) -> Self {
+        self.proposer.limit_to_quota(&quota);
+        self
+    }
+
+    /// Records the rate of rounds and the certificates per round.
+    pub fn with_consensus_metrics(mut self, metrics: ConsensusMetrics) -> Self {
+        self.consensus_metrics = Some(metrics);
+        self
+    }
+
+    /// Stops proposing headers while execution lags behind consensus.
+    pub fn with_backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = Some(backpressure);
+        self
+    }
+
+    /// Publishes the proposed headers and the received certificates.
+    pub fn with_events(mut self, events: NarwhalEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Simulates crashes of the primary, see [`crate::chaos`].
+    ///
+    /// Crashes are only simulated with a [`DagStore`], since the primary could equivocate after a
+    /// restart without one.
+    pub fn with_chaos(mut self, chaos: Chaos) -> Self {
+        self.chaos = Some(chaos);
+        self
+    }
+
+    /// Only proposes the headers of rounds the lease authorizes, see [`crate::failover`].
+    pub fn with_signing_lease(mut self, lease: SigningLease) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
+    /// Simulates a crash: the primary stays down for the restart delay, then restarts from the
+    /// store like after a real crash.
+    ///
+    /// The batches that were waiting for a header are added again, so that the crash doesn't lose
+    /// the transactions of the workers.
+    async fn crash(&mut self, store: Arc<dyn DagStore>, restart_delay: Duration) {
+        warn!(
+            target: "consensus::narwhal",
+            round = self.proposer.round(),
+            ?restart_delay,
+            "Chaos crashed the primary"
+        );
+        tokio::time::sleep(restart_delay).await;
+        let payload = self.proposer.payload().copied().collect::<Vec<_>>();
+        self.proposer = self.proposer.restarted();
+        if let Err(err) = self.recover(store.as_ref()) {
+            error!(target: "consensus::narwhal", %err, "Failed to recover DAG after chaos crash");
+        }
+        for batch in payload {
+            self.proposer.add_batch(batch);
+        }
+    }
+
+    /// Runs the primary until the certificate channel is closed or the receiver of the headers
+    /// is dropped.
+    ///
+    /// The primary keeps proposing headers without new batches once all workers stopped. While
+    /// the [`Backpressure`] throttles, it keeps collecting batches and certificates but proposes
+    /// no header.
+    pub async fn run(mut self) {
+        self.metrics.round.set(self.proposer.round() as f64);
+        let timer = tokio::time::sleep(self.max_header_delay);
+        tokio::pin!(timer);
+        let mut workers_stopped = false;
+        let mut throttled = false;
+
+        loop {
+            let force = tokio::select! {
+                batch = self.batches.recv(), if !workers_stopped => {
+                    match batch {
+                        Some(batch) => self.proposer.add_batch(batch.batch_ref()),
+                        None => workers_stopped = true,
+                    }
+                    false
+                }
+                certificate = self.certificates.recv() => {
+                    let Some(certificate) = certificate else { return };
+                    if let Some(store) = &self.store {
+                        if let Err(err) = store.write_certificate(&certificate) {
+                            let digest = certificate.digest();
+                            error!(
+                                target: "consensus::narwhal",
+                                %err,
+                                %digest,
+                                "Failed to store certificate"
+                            );
+                        }
+                    }
+                    if let Some(events) = &self.events {
+                        events.publish(NarwhalEvent::certificate_formed(&certificate));
+                    }
+                    if self.proposer.add_certificate(&certificate) {
+                        let round = self.proposer.round();
+                        debug!(target: "consensus::narwhal", round, "Advanced round");
+                        self.metrics.round.set(round as f64);
+                        if let Some(metrics) = &self.consensus_metrics {
+                            // the parents of the next header are the certificates of the round
+                            metrics.record_round(self.proposer.parents.len());
+                        }
+                        self.round.send_replace(round);
+                        timer.as_mut().reset(Instant::now() + self.max_header_delay);
+                    }
+                    false
+                }
+                () = &mut timer => {
+                    timer.as_mut().reset(Instant::now() + self.max_header_delay);
+                    true
+                }
+                () = released(&mut self.backpressure), if throttled => false,
+            };
+
+            let was_throttled = throttled;
+            throttled = self.backpressure.as_ref().is_some_and(Backpressure::is_throttled);
+            if throttled != was_throttled {
+                let lag = self.backpressure.as_ref().map(Backpressure::lag);
+                debug!(target: "consensus::narwhal", throttled, ?lag, "Changed header throttling");
+                self.metrics.throttled.set(if throttled { 1.0 } else { 0.0 });
+            }
+            let header = if throttled {
+                None
+            } else {
+                self.proposer.propose(force, SystemClock.now_millis())
+            };
+            if let Some(header) = header {
+                if let Some(lease) = &self.lease {
+                    if let Err(err) = lease.authorize(header.round, header.created_at) {
+                        debug!(
+                            target: "consensus::narwhal",
+                            %err,
+                            round = header.round,
+                            "Not authorized to propose header"
+                        );
+                        self.proposer.withdraw(header);
+                        continue
+                    }
+                }
+                if let Some(store) = &self.store {
+                    if let Err(err) = store.write_vote(&header) {
+                        // without the vote, another header could be proposed after a restart
+                        error!(target: "consensus::narwhal", %err, "Failed to store own header");
+                        continue
+                    }
+                }
+                debug!(
+                    target: "consensus::narwhal",
+                    round = header.round,
+                    batches = header.payload.len(),
+                    "Proposing header"
+                );
+                self.metrics.proposed_headers.increment(1);
+                self.metrics.header_batches.record(header.payload.len() as f64);
+                if let Some(events) = &self.events {
+                    events.publish(NarwhalEvent::header_proposed(&header));
+                }
+                if self.headers.send(header).await.is_err() {
+                    return
+                }
+            }
+            if let (Some(chaos), Some(store)) = (&mut self.chaos, &self.store) {
+                if chaos.crash() {
+                    let (store, restart_delay) = (store.clone(), chaos.restart_delay());
+                    self.crash(store, restart_delay).await;
+                    timer.as_mut().reset(Instant::now() + self.max_header_delay);
+                }
+            }
+            self.metrics.pending_batches.set(self.proposer.pending_batches() as f64);
+            self.pending_batches.send_if_modified(|pending| {
+                let modified = !pending.iter().eq(self.proposer.payload());
+                if modified {
+                    *pending = self.proposer.payload().copied().collect();
+                }
+                modified
+            });
+        }
+    }
+}
+
+/// Waits until the back-pressure is released, forever without back-pressure.
+async fn released(backpressure: &mut Option<Backpressure>) {
+    match backpressure {
+        Some(backpressure) => backpressure.released().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backpressure::{BackpressureConfig, ExecutionLag},
+        chaos::ChaosConfig,
+        dag_store::MemoryDagStore,
+        failover::MemoryLeaseStore,
+        types::{Batch, BatchDigest},
+        worker::BatchQuotaConfig,
+    };
+    use reth_narwhal_verifier::VerifierAuthority;
+
+    fn committee() -> VerifierCommittee<()> {
+        VerifierCommittee::new(
+            1,
+            (0..4).map(|_| VerifierAuthority { public_key: (), stake: 1 }).collect(),
+        )
+    }
+
+    fn certificate(round: Round, author: AuthorityIndex) -> Certificate {
+        Certificate {
+            header: Header { epoch: 1, round, author, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn batch(byte: u8) -> BatchRef {
+        BatchRef { digest: BatchDigest(alloy_primitives::B256::with_last_byte(byte)), worker: 0 }
+    }
+
+    #[test]
+    fn propose_headers() {
+        let config = PrimaryConfig { max_header_batches: 2, ..Default::default() };
+        let mut proposer = Proposer::new(&committee(), 3, config);
+        assert_eq!(proposer.round(), 1);
+
+        proposer.add_batch(batch(1));
+        assert_eq!(proposer.propose(false, 0), None);
+        for byte in 2..=3 {
+            proposer.add_batch(batch(byte));
+        }
+        let header = proposer.propose(false, 0).unwrap();
+        assert_eq!(header.round, 1);
+        assert_eq!(header.author, 3);
+        assert_eq!(header.payload, vec![batch(1), batch(2)]);
+        let mut genesis =
+            Certificate::genesis(1, 0..4).iter().map(Certificate::digest).collect::<Vec<_>>();
+        genesis.sort_unstable();
+        assert_eq!(header.parents, genesis);
+
+        // one header per round
+        assert_eq!(proposer.propose(true, 0), None);
+        assert_eq!(proposer.pending_batches(), 1);
+        assert_eq!(proposer.payload().collect::<Vec<_>>(), vec![&batch(3)]);
+    }
+
+    #[test]
+    fn propose_within_batch_quota() {
+        let config = PrimaryConfig { max_header_batches: 4, ..Default::default() };
+        let mut proposer = Proposer::new(&committee(), 0, config);
+        // a quarter of the stake of the committee
+        proposer.limit_to_quota(&BatchQuota::new(BatchQuotaConfig { batches_per_round: 8 }));
+        for byte in 1..=3 {
+            proposer.add_batch(batch(byte));
+        }
+        let header = proposer.propose(false, 0).unwrap();
+        assert_eq!(header.payload, vec![batch(1), batch(2)]);
+    }
+
+    #[test]
+    fn workers_take_turns() {
+        let batch = |worker, byte| BatchRef { worker, ..batch(byte) };
+        let config = PrimaryConfig { max_header_batches: 4, ..Default::default() };
+        let mut proposer = Proposer::new(&committee(), 0, config);
+
+        // worker 0 seals far more batches than workers 1 and 2
+        for byte in 0..6 {
+            proposer.add_batch(batch(0, byte));
+        }
+        proposer.add_batch(batch(1, 10));
+        proposer.add_batch(batch(2, 20));
+        proposer.add_batch(batch(2, 21));
+
+        let header = proposer.propose(false, 0).unwrap();
+        assert_eq!(header.payload, vec![batch(0, 0), batch(1, 10), batch(2, 20), batch(0, 1)]);
+        assert_eq!(proposer.pending_batches(), 5);
+
+        // the next header starts with the worker after the last one included
+        for author in 0..3 {
+            proposer.add_certificate(&certificate(1, author));
+        }
+        let header = proposer.propose(false, 0).unwrap();
+        assert_eq!(header.payload, vec![batch(2, 21), batch(0, 2), batch(0, 3), batch(0, 4)]);
+        assert_eq!(proposer.payload().collect::<Vec<_>>(), vec![&batch(0, 5)]);
+    }
+
+    #[test]
+    fn advance_on_quorum() {
+        let mut proposer = Proposer::new(&committee(), 0, PrimaryConfig::default());
+        proposer.propose(true, 0).unwrap();
+
+        assert!(!proposer.add_certificate(&certificate(1, 0)));
+        // duplicates, unknown authorities and other epochs don't count
+        assert!(!proposer.add_certificate(&certificate(1, 0)));
+        assert!(!proposer.add_certificate(&certificate(1, 7)));
+        let mut other_epoch = certificate(1, 1);
+        other_epoch.header.epoch = 2;
+        assert!(!proposer.add_certificate(&other_epoch));
+        // certificates of later rounds are kept
+        assert!(!proposer.add_certificate(&certificate(2, 1)));
+
+        assert!(!proposer.add_certificate(&certificate(1, 1)));
+        assert!(proposer.add_certificate(&certificate(1, 2)));
+        assert_eq!(proposer.round(), 2);
+        assert!(!proposer.proposed());
+
+        let header = proposer.propose(true, 0).unwrap();
+        let mut parents = (0..3).map(|author| certificate(1, author).digest()).collect::<Vec<_>>();
+        parents.sort_unstable();
+        assert_eq!(header.parents, parents);
+
+        // late certificates of past rounds are ignored
+        assert!(!proposer.add_certificate(&certificate(1, 3)));
+        assert!(!proposer.add_certificate(&certificate(2, 2)));
+        assert!(proposer.add_certificate(&certificate(2, 3)));
+        assert_eq!(proposer.round(), 3);
+    }
+
+    #[test]
+    fn primary_task() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (batches_tx, batches) = mpsc::channel(8);
+        let (certificates_tx, certificates) = mpsc::channel(8);
+        let (headers, mut headers_rx) = mpsc::channel(8);
+        let config =
+            PrimaryConfig { max_header_batches: 1, max_header_delay: Duration::from_millis(10) };
+        let (primary, handle) =
+            Primary::new(&committee(), 0, config, batches, certificates, headers);
+        let task = runtime.spawn(primary.run());
+
+        runtime.block_on(async {
+            let batch = Batch::new(vec![alloy_primitives::Bytes::from_static(b"tx")]);
+            batches_tx
+                .send(SealedBatch {
+                    worker: 0,
+                    digest: batch.digest(),
+                    batch,
+                    transaction_hashes: Vec::new(),
+                })
+                .await
+                .unwrap();
+            let header = headers_rx.recv().await.unwrap();
+            assert_eq!(header.round, 1);
+            assert_eq!(header.payload.len(), 1);
+
+            let mut rounds = handle.subscribe();
+            for author in 0..3 {
+                certificates_tx.send(certificate(1, author)).await.unwrap();
+            }
+            rounds.changed().await.unwrap();
+            assert_eq!(handle.current_round(), 2);
+
+            // without batches, the header is proposed after the delay
+            let header = headers_rx.recv().await.unwrap();
+            assert_eq!(header.round, 2);
+            assert!(header.payload.is_empty());
+
+            drop(certificates_tx);
+            task.await.unwrap();
+        });
+    }
+
+    #[tokio::test]
+    async fn throttle_proposals() {
+        let lag = ExecutionLag::new(0);
+        for index in 0..3 {
+            lag.on_committed(index);
+        }
+        let (batches_tx, batches) = mpsc::channel(8);
+        let (_certificates_tx, certificates) = mpsc::channel(8);
+        let (headers, mut headers_rx) = mpsc::channel(8);
+        let config =
+            PrimaryConfig { max_header_batches: 1, max_header_delay: Duration::from_millis(10) };
+        let (primary, _) = Primary::new(&committee(), 0, config, batches, certificates, headers);
+        let primary = primary
+            .with_backpressure(lag.backpressure(BackpressureConfig { max_execution_lag: 1 }));
+        let task = tokio::spawn(primary.run());
+
+        let batch = Batch::new(vec![alloy_primitives::Bytes::from_static(b"tx")]);
+        batches_tx
+            .send(SealedBatch {
+                worker: 0,
+                digest: batch.digest(),
+                batch,
+                transaction_hashes: vec![],
+            })
+            .await
+            .unwrap();
+        let throttled = tokio::time::timeout(Duration::from_millis(50), headers_rx.recv()).await;
+        assert!(throttled.is_err());
+
+        // the header is proposed once execution caught up
+        lag.on_executed(0);
+        lag.on_executed(1);
+        let header = headers_rx.recv().await.unwrap();
+        assert_eq!(header.round, 1);
+        assert_eq!(header.payload.len(), 1);
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn propose_with_signing_lease() {
+        let lease =
+            SigningLease::new(Arc::new(MemoryLeaseStore::default()), "active", Default::default());
+        let (batches_tx, batches) = mpsc::channel(8);
+        let (_certificates_tx, certificates) = mpsc::channel(8);
+        let (headers, mut headers_rx) = mpsc::channel(8);
+        let config =
+            PrimaryConfig { max_header_batches: 1, max_header_delay: Duration::from_millis(10) };
+        let (primary, _) = Primary::new(&committee(), 0, config, batches, certificates, headers);
+        let task = tokio::spawn(primary.with_signing_lease(lease.clone()).run());
+
+        let batch = Batch::new(vec![alloy_primitives::Bytes::from_static(b"tx")]);
+        batches_tx
+            .send(SealedBatch {
+                worker: 0,
+                digest: batch.digest(),
+                batch,
+                transaction_hashes: vec![],
+            })
+            .await
+            .unwrap();
+        let unauthorized = tokio::time::timeout(Duration::from_millis(50), headers_rx.recv()).await;
+        assert!(unauthorized.is_err());
+
+        // the header is proposed with its batch once the node holds the lease
+        assert!(lease.acquire(SystemClock.now_millis()).unwrap());
+        let header = headers_rx.recv().await.unwrap();
+        assert_eq!(header.round, 1);
+        assert_eq!(header.payload.len(), 1);
+        task.abort();
+    }
+
+    #[test]
+    fn recover_round() {
+        let store = Arc::new(MemoryDagStore::default());
+        for round in 1..=2 {
+            for author in 0..3 {
+                store.write_certificate(&certificate(round, author)).unwrap();
+            }
+        }
+        store.write_certificate(&certificate(3, 1)).unwrap();
+        let (primary, handle) = Primary::new(
+            &committee(),
+            0,
+            PrimaryConfig::default(),
+            mpsc::channel(1).1,
+            mpsc::channel(1).1,
+            mpsc::channel(1).0,
+        );
+        let mut primary = primary.with_store(store.clone()).unwrap();
+        assert_eq!(handle.current_round(), 3);
+        assert!(!primary.proposer.proposed());
+        let header = primary.proposer.propose(true, 0).unwrap();
+
+        // the header of the round was proposed before the restart
+        store.write_vote(&header).unwrap();
+        let (primary, _) = Primary::new(
+            &committee(),
+            0,
+            PrimaryConfig::default(),
+            mpsc::channel(1).1,
+            mpsc::channel(1).1,
+            mpsc::channel(1).0,
+        );
+        let mut primary = primary.with_store(store).unwrap();
+        assert_eq!(primary.proposer.round(), 3);
+        assert_eq!(primary.proposer.propose(true, 0), None);
+    }
+
+    #[tokio::test]
+    async fn chaos_crashes_recover_from_store() {
+        let store = Arc::new(MemoryDagStore::default());
+        let (batches_tx, batches) = mpsc::channel(8);
+        let (certificates_tx, certificates) = mpsc::channel(8);
+        let (headers, mut headers_rx) = mpsc::channel(8);
+        let config =
+            PrimaryConfig { max_header_batches: 1, max_header_delay: Duration::from_millis(10) };
+        let (primary, handle) =
+            Primary::new(&committee(), 0, config, batches, certificates, headers);
+        // the primary crashes after every event
+        let chaos = Chaos::new(ChaosConfig { crash_ppm: 1_000_000, ..Default::default() });
+        let primary = primary.with_store(store).unwrap().with_chaos(chaos);
+        let task = tokio::spawn(primary.run());
+
+        let batch = Batch::new(vec![alloy_primitives::Bytes::from_static(b"tx")]);
+        batches_tx
+            .send(SealedBatch {
+                worker: 0,
+                digest: batch.digest(),
+                batch,
+                transaction_hashes: Vec::new(),
+            })
+            .await
+            .unwrap();
+        let header = headers_rx.recv().await.unwrap();
+        assert_eq!((header.round, header.payload.len()), (1, 1));
+
+        // the restarted primary keeps the round of the stored certificates, and doesn't propose
+        // another header for a round it already proposed in
+        let mut rounds = handle.subscribe();
+        for author in 0..3 {
+            certificates_tx.send(certificate(1, author)).await.unwrap();
+        }
+        while *rounds.borrow_and_update() < 2 {
+            rounds.changed().await.unwrap();
+        }
+        let header = headers_rx.recv().await.unwrap();
+        assert_eq!(header.round, 2);
+        assert_eq!(handle.current_round(), 2);
+        task.abort();
+    }
+}
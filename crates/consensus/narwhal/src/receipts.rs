@@ -0,0 +1,187 @@
+//! Receipts of recently executed blocks.
+
+use crate::memory::MemoryHandle;
+use parking_lot::Mutex;
+use reth_evm::execute::ExecutionOutcome;
+use reth_primitives::{
+    Receipt, SealedBlockWithSenders, TransactionMeta, TransactionSigned, TxHash,
+};
+use schnellru::{ByLength, LruMap};
+use std::{mem, sync::Arc};
+
+/// The estimated size of an entry of [`RecentReceipts`], without the receipts of its block, which
+/// are shared by its transactions.
+const RECEIPT_ENTRY_SIZE: usize = mem::size_of::<(TxHash, RecentReceipt)>();
+
+/// The receipt of a transaction of a recently executed block, with the transaction and the block
+/// context the RPC needs to build its response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentReceipt {
+    /// The transaction.
+    pub transaction: TransactionSigned,
+    /// The position of the transaction in its block.
+    pub meta: TransactionMeta,
+    /// The receipt of the transaction.
+    pub receipt: Receipt,
+    /// The receipts of all transactions of the block, from which the gas used by the transaction
+    /// and the indices of its logs are derived.
+    pub block_receipts: Arc<Vec<Receipt>>,
+}
+
+/// A bounded record of the receipts of recently executed blocks.
+///
+/// The [`ConsensusOutputExecutor`](crate::executor::ConsensusOutputExecutor) records the receipts
+/// of a block as soon as it executed it, before the engine made the block canonical and wrote it
+/// to the database. The receipt RPC consults the record first, so that a receipt can be queried as
+/// soon as the block is announced to subscribers. Commits are final, so a recorded receipt never
+/// changes, it only becomes available from the database later.
+///
+/// With a [`MemoryHandle`], the record is charged to the
+/// [`ExecutionResults`](crate::memory::MemoryComponent::ExecutionResults) component of the memory
+/// budget and evicts its oldest receipts while the budget is exceeded.
+#[derive(Debug, Clone)]
+pub struct RecentReceipts {
+    inner: Arc<Mutex<LruMap<TxHash, RecentReceipt>>>,
+    memory: Option<MemoryHandle>,
+}
+
+impl RecentReceipts {
+    /// Creates a new record that remembers the receipts of at most `capacity` transactions.
+    pub fn new(capacity: u32) -> Self {
+        Self { inner: Arc::new(Mutex::new(LruMap::new(ByLength::new(capacity)))), memory: None }
+    }
+
+    /// Charges the record to the memory budget.
+    ///
+    /// The handle should belong to the
+    /// [`ExecutionResults`](crate::memory::MemoryComponent::ExecutionResults) component, and the
+    /// record should be empty.
+    pub fn with_memory_budget(mut self, memory: MemoryHandle) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Records the receipts of an executed block.
+    pub fn insert_block(
+        &self,
+        block: &SealedBlockWithSenders,
+        execution_outcome: &ExecutionOutcome,
+    ) {
+        let receipts = execution_outcome.receipts_by_block(block.number);
+        let block_receipts = Arc::new(receipts.iter().flatten().cloned().collect::<Vec<_>>());
+        let mut inner = self.inner.lock();
+        let len = inner.len();
+        for (index, (transaction, receipt)) in block.body.iter().zip(receipts).enumerate() {
+            let Some(receipt) = receipt else { continue };
+            let meta = TransactionMeta {
+                tx_hash: transaction.hash(),
+                index: index as u64,
+                block_hash: block.hash(),
+                block_number: block.number,
+                base_fee: block.base_fee_per_gas,
+                excess_blob_gas: block.excess_blob_gas,
+                timestamp: block.timestamp,
+            };
+            inner.insert(
+                transaction.hash(),
+                RecentReceipt {
+                    transaction: transaction.clone(),
+                    meta,
+                    receipt: receipt.clone(),
+                    block_receipts: Arc::clone(&block_receipts),
+                },
+            );
+        }
+        let Some(memory) = &self.memory else { return };
+
+        // receipts replaced within the capacity don't change the usage
+        memory.charge(inner.len().saturating_sub(len) * RECEIPT_ENTRY_SIZE);
+        let mut excess = memory.excess();
+        while excess > 0 && inner.pop_oldest().is_some() {
+            memory.evict(RECEIPT_ENTRY_SIZE);
+            excess = excess.saturating_sub(RECEIPT_ENTRY_SIZE);
+        }
+    }
+
+    /// Returns the receipt of the transaction, if it was executed recently.
+    pub fn get(&self, hash: &TxHash) -> Option<RecentReceipt> {
+        self.inner.lock().peek(hash).cloned()
+    }
+}
+
+impl Default for RecentReceipts {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{MemoryBudget, MemoryBudgetConfig, MemoryComponent};
+    use reth_primitives::{Address, Block, Header, Receipts, TxType};
+
+    /// Returns an executed block of three transactions, its receipts and its execution outcome.
+    fn executed_block() -> (SealedBlockWithSenders, Vec<Receipt>, ExecutionOutcome) {
+        let transactions = (0..3)
+            .map(|nonce| {
+                let mut transaction = TransactionSigned::default();
+                transaction.transaction.set_nonce(nonce);
+                transaction.hash = transaction.recalculate_hash();
+                transaction
+            })
+            .collect::<Vec<_>>();
+        let header =
+            Header { number: 5, timestamp: 9, base_fee_per_gas: Some(7), ..Default::default() };
+        let block = Block { header, body: transactions, ..Default::default() }.seal_slow();
+        let block = SealedBlockWithSenders::new(block, vec![Address::ZERO; 3]).unwrap();
+        let receipt = |cumulative_gas_used| Receipt {
+            tx_type: TxType::Legacy,
+            success: true,
+            cumulative_gas_used,
+            ..Default::default()
+        };
+        let receipts = vec![receipt(21_000), receipt(42_000), receipt(63_000)];
+        let outcome = ExecutionOutcome::new(
+            Default::default(),
+            Receipts { receipt_vec: vec![receipts.iter().cloned().map(Some).collect()] },
+            5,
+            Vec::new(),
+        );
+
+        (block, receipts, outcome)
+    }
+
+    #[test]
+    fn recent_receipts() {
+        let (block, receipts, outcome) = executed_block();
+        let recent = RecentReceipts::new(2);
+        recent.insert_block(&block, &outcome);
+        let second = recent.get(&block.body[1].hash()).unwrap();
+        assert_eq!(second.receipt, receipts[1]);
+        assert_eq!(*second.block_receipts, receipts);
+        assert_eq!((second.meta.index, second.meta.block_number), (1, 5));
+        assert_eq!((second.meta.block_hash, second.meta.base_fee), (block.hash(), Some(7)));
+
+        // the oldest receipts are evicted
+        assert_eq!(recent.get(&block.body[0].hash()), None);
+        assert!(recent.get(&block.body[2].hash()).is_some());
+    }
+
+    #[test]
+    fn evict_over_budget() {
+        let (block, _, outcome) = executed_block();
+        let budget = MemoryBudget::new(MemoryBudgetConfig { max_bytes: 2 * RECEIPT_ENTRY_SIZE });
+        let recent = RecentReceipts::new(10)
+            .with_memory_budget(budget.handle(MemoryComponent::ExecutionResults));
+
+        recent.insert_block(&block, &outcome);
+        assert_eq!(budget.total(), 2 * RECEIPT_ENTRY_SIZE);
+        assert_eq!(recent.get(&block.body[0].hash()), None);
+        assert!(recent.get(&block.body[1].hash()).is_some());
+
+        // replaying the block doesn't charge the receipts twice
+        recent.insert_block(&block, &outcome);
+        assert_eq!(budget.total(), 2 * RECEIPT_ENTRY_SIZE);
+    }
+}
@@ -0,0 +1,337 @@
+//! Recording and offline replay of the inbound consensus messages of a node.
+//!
+//! Ordering bugs usually depend on the order in which the messages of the committee reached a
+//! node, which is hard to reproduce on a live network. With a [`MessageRecordConfig`] path, the
+//! node's transports hand every inbound [`NarwhalMessage`] to a [`MessageRecorder`], which appends
+//! it to a file as a JSON line with the time it was received, the sender, and its wire encoding.
+//!
+//! An operator can then share the file, and a developer feeds it into the consensus stack of a
+//! single node with a [`Replayer`]: the certificates are inserted into a [`Dag`] in the recorded
+//! order, certificates that arrived before their parents wait for them like in the node, and the
+//! [`Bullshark`] commit rule runs after every message. Since the DAG and the commit rule are
+//! deterministic, the replay commits the leaders the node committed. Signatures are not verified,
+//! the recording holds the messages the node already accepted.
+
+use crate::{
+    bullshark::Bullshark,
+    committee::Committee,
+    dag::{Dag, DagError},
+    determinism::{Clock, SystemClock},
+    gc::{gc_round, DEFAULT_GC_DEPTH},
+    leader::{LeaderElector, LeaderSchedule},
+    types::{Certificate, CertificateDigest, DagVertex, Round},
+    wire::{NarwhalMessage, WireError, MAX_WIRE_VERSION},
+};
+use alloy_primitives::Bytes;
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tracing::warn;
+
+/// Where the node records its inbound consensus messages, disabled by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct MessageRecordConfig {
+    /// The file the messages are appended to, nothing is recorded if not set.
+    pub path: Option<PathBuf>,
+    /// Whether the batches of the workers are recorded too, which replays don't need but which
+    /// make the recording much larger.
+    pub batches: bool,
+}
+
+/// An inbound message of a recording.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedMessage {
+    /// The time the message was received, in milliseconds since the unix epoch.
+    pub received_at: u64,
+    /// The authority that sent the message.
+    pub from: AuthorityIndex,
+    /// The message in the wire format.
+    pub message: Bytes,
+}
+
+impl RecordedMessage {
+    /// Decodes the message.
+    pub fn decode(&self) -> Result<NarwhalMessage, WireError> {
+        NarwhalMessage::decode(&self.message).map(|(_, message)| message)
+    }
+}
+
+/// Appends the inbound messages of a node to a recording, see the [module docs](self).
+///
+/// Cloning is cheap, all clones append to the same file.
+#[derive(Debug, Clone)]
+pub struct MessageRecorder {
+    file: Arc<Mutex<BufWriter<File>>>,
+    batches: bool,
+}
+
+impl MessageRecorder {
+    /// Opens the recording of the configuration, `None` if recording is disabled.
+    pub fn from_config(config: &MessageRecordConfig) -> io::Result<Option<Self>> {
+        let Some(path) = &config.path else { return Ok(None) };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Some(Self { file: Arc::new(Mutex::new(BufWriter::new(file))), batches: config.batches }))
+    }
+
+    /// Records a message received now.
+    pub fn record(&self, from: AuthorityIndex, message: &NarwhalMessage) {
+        self.record_at(SystemClock.now_millis(), from, message)
+    }
+
+    /// Records a message received at the given time.
+    ///
+    /// The message is written through, so that the recording survives a crash of the node. A
+    /// recording that can't be written is logged, it never affects consensus.
+    pub fn record_at(&self, received_at: u64, from: AuthorityIndex, message: &NarwhalMessage) {
+        if matches!(message, NarwhalMessage::Batch(_)) && !self.batches {
+            return
+        }
+        let message = message.encode(MAX_WIRE_VERSION).expect("supported version");
+        let record = RecordedMessage { received_at, from, message: message.into() };
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let written = serde_json::to_writer(&mut *file, &record)
+            .map_err(io::Error::from)
+            .and_then(|()| writeln!(file))
+            .and_then(|()| file.flush());
+        if let Err(err) = written {
+            warn!(target: "consensus::narwhal", %err, "Failed to record message");
+        }
+    }
+}
+
+/// Reads the messages of a recording, in the order they were received.
+pub fn read_recording(path: &Path) -> io::Result<Vec<RecordedMessage>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// A leader committed during a replay.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedCommit {
+    /// The time the message that completed the commit was received.
+    pub received_at: u64,
+    /// The round of the leader.
+    pub round: Round,
+    /// The leader.
+    pub leader: AuthorityIndex,
+    /// The committed certificates in commit order, ending with the leader's.
+    pub certificates: Vec<CertificateDigest>,
+}
+
+/// What a [`Replayer`] did with the messages of a recording.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayReport {
+    /// The number of replayed messages.
+    pub messages: usize,
+    /// The number of certificates inserted into the DAG.
+    pub certificates: usize,
+    /// The number of headers, votes and batches, which don't change the DAG.
+    pub skipped: usize,
+    /// The messages that couldn't be decoded or were rejected by the DAG, with their index in the
+    /// recording and the reason.
+    pub rejected: Vec<(usize, String)>,
+    /// The committed leaders, in commit order.
+    pub commits: Vec<ReplayedCommit>,
+    /// The certificates still waiting for their parents at the end of the recording.
+    pub pending: Vec<CertificateDigest>,
+}
+
+/// Feeds a recording into the DAG and the commit rule of a single node, see the
+/// [module docs](self).
+#[derive(Debug)]
+pub struct Replayer {
+    committee: Committee,
+    dag: Dag,
+    bullshark: Bullshark,
+    gc_depth: Round,
+    /// The certificates that arrived before their parents.
+    pending: Vec<Certificate>,
+    report: ReplayReport,
+}
+
+impl Replayer {
+    /// Creates the replayer of a node of the committee's epoch that starts at the genesis round.
+    pub fn new(committee: Committee, schedule: LeaderSchedule) -> Self {
+        let mut dag = Dag::new(0);
+        for certificate in
+            Certificate::genesis(committee.epoch, 0..committee.authorities.len() as u32)
+        {
+            dag.insert(DagVertex::new(certificate)).expect("genesis has no parents");
+        }
+        let bullshark = Bullshark::new(LeaderElector::new(schedule, &committee));
+        Self {
+            committee,
+            dag,
+            bullshark,
+            gc_depth: DEFAULT_GC_DEPTH,
+            pending: Vec::new(),
+            report: ReplayReport::default(),
+        }
+    }
+
+    /// Sets the number of rounds below the last committed round the node kept.
+    pub const fn with_gc_depth(mut self, gc_depth: Round) -> Self {
+        self.gc_depth = gc_depth;
+        self
+    }
+
+    /// Replays the next message of the recording.
+    pub fn replay(&mut self, recorded: &RecordedMessage) {
+        let index = self.report.messages;
+        self.report.messages += 1;
+        let certificate = match recorded.decode() {
+            Ok(NarwhalMessage::Certificate(certificate)) => certificate,
+            Ok(_) => {
+                self.report.skipped += 1;
+                return
+            }
+            Err(err) => {
+                self.report.rejected.push((index, err.to_string()));
+                return
+            }
+        };
+
+        self.pending.push(certificate);
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for certificate in std::mem::take(&mut self.pending) {
+                match self.dag.insert(DagVertex::new(certificate.clone())) {
+                    Ok(inserted) => {
+                        progress |= inserted;
+                        self.report.certificates += usize::from(inserted);
+                    }
+                    Err(DagError::MissingParent { .. }) => self.pending.push(certificate),
+                    Err(err) => self.report.rejected.push((index, err.to_string())),
+                }
+            }
+        }
+
+        for leader in self.bullshark.try_commit(&self.committee, &mut self.dag) {
+            self.report.commits.push(ReplayedCommit {
+                received_at: recorded.received_at,
+                round: leader.round,
+                leader: leader.leader,
+                certificates: leader.certificates.iter().map(Certificate::digest).collect(),
+            });
+        }
+        let gc = gc_round(self.bullshark.last_committed(), self.gc_depth);
+        if gc > self.dag.gc_round() {
+            self.dag.prune(gc);
+            self.pending.retain(|certificate| certificate.round() >= gc);
+        }
+    }
+
+    /// Replays all messages of a recording, and returns what the node did with them.
+    pub fn replay_all<'a>(
+        mut self,
+        recording: impl IntoIterator<Item = &'a RecordedMessage>,
+    ) -> ReplayReport {
+        for recorded in recording {
+            self.replay(recorded);
+        }
+        self.finish()
+    }
+
+    /// Returns what the node did with the replayed messages.
+    pub fn finish(mut self) -> ReplayReport {
+        self.report.pending = self.pending.iter().map(Certificate::digest).collect();
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dev::DevCommittee, types::Header};
+
+    /// Returns the certificates of `rounds` rounds in which every authority references every
+    /// certificate of the previous round.
+    fn rounds(committee: &Committee, rounds: Round) -> Vec<Certificate> {
+        let mut previous = Certificate::genesis(committee.epoch, 0..4);
+        let mut certificates = Vec::new();
+        for round in 1..=rounds {
+            let mut parents = previous.iter().map(Certificate::digest).collect::<Vec<_>>();
+            parents.sort_unstable();
+            previous = (0..4)
+                .map(|author| {
+                    let header = Header {
+                        epoch: committee.epoch,
+                        round,
+                        author,
+                        parents: parents.clone(),
+                        ..Default::default()
+                    };
+                    Certificate { header, ..Default::default() }
+                })
+                .collect();
+            certificates.extend(previous.iter().cloned());
+        }
+        certificates
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let dev = DevCommittee::new(4);
+        let committee = dev.committee().clone();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("messages.jsonl");
+        let config = MessageRecordConfig { path: Some(path.clone()), batches: false };
+        let recorder = MessageRecorder::from_config(&config).unwrap().unwrap();
+
+        // the certificates arrive in reverse order, and a batch that isn't recorded
+        let certificates = rounds(&committee, 4);
+        for (at, certificate) in certificates.iter().rev().enumerate() {
+            recorder.record_at(
+                at as u64,
+                certificate.author(),
+                &NarwhalMessage::Certificate(certificate.clone()),
+            );
+        }
+        recorder.record_at(99, 0, &NarwhalMessage::Batch(Default::default()));
+        let recording = read_recording(&path).unwrap();
+        assert_eq!(recording.len(), certificates.len());
+
+        let report =
+            Replayer::new(committee.clone(), LeaderSchedule::RoundRobin).replay_all(&recording);
+        assert_eq!(report.messages, 16);
+        assert_eq!(report.certificates, 16);
+        assert!(report.rejected.is_empty() && report.pending.is_empty());
+        // the leader of round 2 commits once the last message completes the DAG
+        assert_eq!(report.commits.len(), 1);
+        assert_eq!(report.commits[0].round, 2);
+        assert_eq!(report.commits[0].received_at, 15);
+
+        // replaying the same recording commits the same leaders
+        let again = Replayer::new(committee, LeaderSchedule::RoundRobin).replay_all(&recording);
+        assert_eq!(again, report);
+    }
+
+    #[test]
+    fn replay_rejects_malformed_messages() {
+        let dev = DevCommittee::new(4);
+        let mut replayer = Replayer::new(dev.committee().clone(), LeaderSchedule::RoundRobin);
+        replayer.replay(&RecordedMessage {
+            received_at: 0,
+            from: 0,
+            message: Bytes::from_static(&[1]),
+        });
+        let vote = NarwhalMessage::Vote(Default::default()).encode(MAX_WIRE_VERSION).unwrap();
+        replayer.replay(&RecordedMessage { received_at: 1, from: 0, message: vote.into() });
+        let report = replayer.finish();
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.skipped, 1);
+    }
+}
@@ -0,0 +1,278 @@
+//! Recovery of the sub-dags that were committed but not executed before a restart.
+//!
+//! Consensus hands the committed sub-dags to the executor through a channel. If the node stops
+//! between the commit and the execution of a sub-dag, the sub-dag is lost with the channel: its
+//! certificates are marked as committed in the [`DagStore`], so the commit rule never outputs them
+//! again. [`CommittedSubDags`] therefore records every sub-dag in the store before it's handed to
+//! the executor, and the executor removes the records of the executed ones.
+//!
+//! On startup, [`CommittedSubDags::unexecuted`] finds the sub-dag of the canonical head, whose
+//! `mix_hash` is the [`OrderedSubDag::digest`], and assembles the sub-dags committed after it
+//! from the stored certificates and batches. The executor replays them before it accepts new
+//! consensus output, and since execution is deterministic, the replayed blocks are the blocks every
+//! other validator built.
+//!
+//! The transactions of a sub-dag that exceed the block gas limit are split across several blocks,
+//! which all carry the digest of the sub-dag. If the node stopped between them, the sub-dag of the
+//! head was executed partially, see [`CommittedSubDags::interrupted`].
+
+use crate::{
+    dag_store::{DagStore, DagStoreError, StoredSubDag},
+    types::{BatchDigest, CertificateDigest, OrderedSubDag},
+};
+use alloy_primitives::B256;
+use std::sync::Arc;
+
+/// Errors of the recovery of the committed sub-dags.
+#[derive(Debug, thiserror::Error)]
+pub enum RecoveryError {
+    /// The store failed.
+    #[error(transparent)]
+    Store(#[from] DagStoreError),
+    /// The canonical head was not built from a recorded sub-dag, so it's unknown which sub-dags
+    /// were executed.
+    #[error("canonical head {number} was not built from a recorded sub-dag")]
+    UnknownHead {
+        /// The number of the canonical head.
+        number: u64,
+    },
+    /// A certificate of a sub-dag is no longer stored.
+    #[error("certificate {digest} of sub-dag {index} is not stored")]
+    MissingCertificate {
+        /// The index of the sub-dag.
+        index: u64,
+        /// The digest of the certificate.
+        digest: CertificateDigest,
+    },
+    /// A batch of a sub-dag is no longer stored.
+    #[error("batch {digest} of sub-dag {index} is not stored")]
+    MissingBatch {
+        /// The index of the sub-dag.
+        index: u64,
+        /// The digest of the batch.
+        digest: BatchDigest,
+    },
+}
+
+/// The sub-dags that were committed after the sub-dag of the canonical head.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnexecutedSubDags {
+    /// The index of the first sub-dag consensus commits after the recorded ones.
+    pub next_sub_dag: u64,
+    /// The sub-dags to replay, in commit order.
+    pub sub_dags: Vec<OrderedSubDag>,
+}
+
+/// The sequence of committed sub-dags in a [`DagStore`].
+///
+/// Cloning is cheap, all clones record to the same store.
+#[derive(Debug, Clone)]
+pub struct CommittedSubDags {
+    store: Arc<dyn DagStore>,
+}
+
+impl CommittedSubDags {
+    /// Creates the sequence in the given store.
+    pub fn new(store: Arc<dyn DagStore>) -> Self {
+        Self { store }
+    }
+
+    /// Records a committed sub-dag, before it's handed to the executor.
+    pub fn record(&self, sub_dag: &OrderedSubDag) -> Result<(), DagStoreError> {
+        self.store.write_sub_dag(&StoredSubDag::from(sub_dag))
+    }
+
+    /// Records that the sub-dag with the given index was executed, and returns the number of
+    /// removed records.
+    ///
+    /// The records of the earlier sub-dags are removed. The record of the executed sub-dag is kept,
+    /// it identifies the canonical head on the next start.
+    pub fn executed(&self, index: u64) -> Result<usize, DagStoreError> {
+        self.store.prune_sub_dags(index)
+    }
+
+    /// Returns the recorded sub-dags that were committed after the sub-dag of the canonical head
+    /// with the given number and `mix_hash`, `None` if no sub-dag is recorded.
+    ///
+    /// A head that is the genesis block precedes all sub-dags, as long as the records start at the
+    /// first sub-dag.
+    pub fn unexecuted(
+        &self,
+        head_number: u64,
+        head_mix_hash: B256,
+    ) -> Result<Option<UnexecutedSubDags>, RecoveryError> {
+        let recorded = self.store.sub_dags(0)?;
+        let Some(last) = recorded.last() else { return Ok(None) };
+        let next_sub_dag = last.index + 1;
+
+        let executed = recorded.iter().position(|sub_dag| sub_dag.digest().0 == head_mix_hash);
+        let start = match executed {
+            Some(position) => position + 1,
+            None if head_number == 0 && recorded[0].index == 0 => 0,
+            None => return Err(RecoveryError::UnknownHead { number: head_number }),
+        };
+        let sub_dags = recorded[start..]
+            .iter()
+            .map(|sub_dag| self.assemble(sub_dag))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(UnexecutedSubDags { next_sub_dag, sub_dags }))
+    }
+
+    /// Returns the recorded sub-dag of the canonical head with the given `mix_hash` if its
+    /// execution may have been interrupted, `None` if it's known to be complete or not recorded.
+    ///
+    /// A sub-dag is only recorded as executed once all of its blocks were executed, which removes
+    /// the records of the earlier sub-dags. The sub-dag of the head is therefore complete if it's
+    /// the first recorded sub-dag, unless it's the first sub-dag of the chain.
+    pub fn interrupted(&self, head_mix_hash: B256) -> Result<Option<OrderedSubDag>, RecoveryError> {
+        let recorded = self.store.sub_dags(0)?;
+        let Some(position) =
+            recorded.iter().position(|sub_dag| sub_dag.digest().0 == head_mix_hash)
+        else {
+            return Ok(None)
+        };
+        if position == 0 && recorded[0].index != 0 {
+            return Ok(None)
+        }
+        self.assemble(&recorded[position]).map(Some)
+    }
+
+    /// Returns the recorded sub-dag with the given digest, the `mix_hash` of its blocks, `None` if
+    /// it's not recorded.
+    pub fn sub_dag(&self, digest: B256) -> Result<Option<OrderedSubDag>, RecoveryError> {
+        self.store.sub_dag(digest.into())?.map(|sub_dag| self.assemble(&sub_dag)).transpose()
+    }
+
+    /// Assembles a recorded sub-dag from the stored certificates and batches.
+    fn assemble(&self, sub_dag: &StoredSubDag) -> Result<OrderedSubDag, RecoveryError> {
+        let index = sub_dag.index;
+        let certificate = |digest| {
+            self.store
+                .certificate(digest)?
+                .ok_or(RecoveryError::MissingCertificate { index, digest })
+        };
+        let certificates =
+            sub_dag.certificates.iter().copied().map(certificate).collect::<Result<Vec<_>, _>>()?;
+        let leader = match certificates.last() {
+            Some(last) if last.digest() == sub_dag.leader => last.clone(),
+            _ => certificate(sub_dag.leader)?,
+        };
+        let batches = OrderedSubDag::batch_order(&certificates)
+            .into_iter()
+            .map(|batch| {
+                self.store
+                    .batch(batch.digest)?
+                    .ok_or(RecoveryError::MissingBatch { index, digest: batch.digest })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(OrderedSubDag { index, leader, certificates, batches, timestamp: sub_dag.timestamp })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dag_store::MemoryDagStore,
+        types::{Batch, BatchRef, Certificate, Header},
+    };
+    use alloy_primitives::Bytes;
+
+    /// Commits a sub-dag whose leader of round `2 * (index + 1)` references one batch.
+    fn commit(store: &MemoryDagStore, index: u64) -> OrderedSubDag {
+        let batch = Batch::new(vec![Bytes::from(vec![index as u8])]);
+        store.write_batch(batch.digest(), &batch).unwrap();
+        let header = Header {
+            round: 2 * (index + 1),
+            payload: vec![BatchRef { digest: batch.digest(), worker: 0 }],
+            ..Default::default()
+        };
+        let leader = Certificate { header, ..Default::default() };
+        store.write_certificate(&leader).unwrap();
+        OrderedSubDag {
+            index,
+            leader: leader.clone(),
+            certificates: vec![leader],
+            batches: vec![batch],
+            timestamp: 1_700_000_000 + index,
+        }
+    }
+
+    #[test]
+    fn replay_after_head() {
+        let store = Arc::new(MemoryDagStore::default());
+        let committed = CommittedSubDags::new(store.clone());
+        assert_eq!(committed.unexecuted(0, B256::ZERO).unwrap(), None);
+
+        let sub_dags = (0..4).map(|index| commit(&store, index)).collect::<Vec<_>>();
+        for sub_dag in &sub_dags {
+            committed.record(sub_dag).unwrap();
+        }
+
+        assert_eq!(StoredSubDag::from(&sub_dags[0]).digest(), sub_dags[0].digest());
+        assert_eq!(committed.sub_dag(sub_dags[2].digest().0).unwrap().as_ref(), Some(&sub_dags[2]));
+        assert_eq!(committed.sub_dag(B256::ZERO).unwrap(), None);
+
+        // nothing was executed yet
+        let unexecuted = committed.unexecuted(0, B256::ZERO).unwrap().unwrap();
+        assert_eq!(unexecuted, UnexecutedSubDags { next_sub_dag: 4, sub_dags: sub_dags.clone() });
+
+        // the node stopped after the block of sub-dag 1
+        assert_eq!(committed.executed(1).unwrap(), 1);
+        let head = sub_dags[1].digest().0;
+        let unexecuted = committed.unexecuted(2, head).unwrap().unwrap();
+        assert_eq!(unexecuted.sub_dags, sub_dags[2..]);
+        assert_eq!(unexecuted.next_sub_dag, 4);
+
+        // all sub-dags were executed
+        let head = sub_dags[3].digest().0;
+        assert!(committed.unexecuted(4, head).unwrap().unwrap().sub_dags.is_empty());
+
+        // the head was not built from a recorded sub-dag
+        assert!(matches!(
+            committed.unexecuted(0, B256::ZERO),
+            Err(RecoveryError::UnknownHead { number: 0 })
+        ));
+    }
+
+    #[test]
+    fn interrupted_head() {
+        let store = Arc::new(MemoryDagStore::default());
+        let committed = CommittedSubDags::new(store.clone());
+        let sub_dags = (0..3).map(|index| commit(&store, index)).collect::<Vec<_>>();
+        for sub_dag in &sub_dags {
+            committed.record(sub_dag).unwrap();
+        }
+        assert_eq!(committed.interrupted(B256::ZERO).unwrap(), None);
+        // the first sub-dag can't be told apart from an interrupted one
+        assert_eq!(
+            committed.interrupted(sub_dags[0].digest().0).unwrap().as_ref(),
+            Some(&sub_dags[0])
+        );
+
+        // sub-dag 0 was executed, the node stopped between the blocks of sub-dag 1
+        committed.executed(0).unwrap();
+        assert_eq!(
+            committed.interrupted(sub_dags[1].digest().0).unwrap().as_ref(),
+            Some(&sub_dags[1])
+        );
+
+        // all blocks of sub-dag 1 were executed
+        committed.executed(1).unwrap();
+        assert_eq!(committed.interrupted(sub_dags[1].digest().0).unwrap(), None);
+    }
+
+    #[test]
+    fn pruned_certificates() {
+        let store = Arc::new(MemoryDagStore::default());
+        let committed = CommittedSubDags::new(store.clone());
+        let sub_dag = commit(&store, 0);
+        committed.record(&sub_dag).unwrap();
+        store.prune(3).unwrap();
+
+        assert!(matches!(
+            committed.unexecuted(0, B256::ZERO),
+            Err(RecoveryError::MissingCertificate { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+//! Per-validator performance reports of an epoch.
+//!
+//! Reward committees and postmortems need to know how each validator of an epoch contributed to
+//! the chain. An [`EpochReport`] aggregates the consensus metadata indexed for every block of the
+//! epoch, see `BlockConsensusMetadata`, by the leader the block was built from. Leader rounds
+//! between two consecutive commits were not committed and are counted as missed leader rounds.
+
+use crate::{committee::Committee, types::Round};
+use alloy_primitives::{BlockNumber, Bytes};
+use reth_db_api::models::StoredConsensusMetadata;
+use reth_narwhal_verifier::{AuthorityIndex, Epoch, Stake};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+/// The contribution of a validator to the blocks of an epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidatorReport {
+    /// The index of the validator in the committee of the epoch.
+    pub authority: AuthorityIndex,
+    /// The public key of the validator.
+    pub public_key: Bytes,
+    /// The stake of the validator.
+    pub stake: Stake,
+    /// Number of blocks built from a commit of the validator as leader.
+    pub leader_commits: u64,
+    /// Number of certificates committed by the validator's leader commits.
+    pub committed_certificates: u64,
+    /// Number of batches committed by the validator's leader commits.
+    pub committed_batches: u64,
+    /// Total size of the batches committed by the validator's leader commits in bytes.
+    pub committed_batch_bytes: u64,
+}
+
+/// The performance of every validator of an epoch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochReport {
+    /// The epoch.
+    pub epoch: Epoch,
+    /// The first block of the epoch.
+    pub first_block: BlockNumber,
+    /// The last block of the epoch that was included in the report.
+    pub last_block: BlockNumber,
+    /// Number of leader rounds between the first and the last commit of the epoch that were not
+    /// committed.
+    pub missed_leader_rounds: u64,
+    /// Number of blocks without indexed metadata, committed by the committee of another epoch, or
+    /// whose leader is not in the committee.
+    pub unattributed_blocks: u64,
+    /// The validators in committee order.
+    pub validators: Vec<ValidatorReport>,
+}
+
+impl EpochReport {
+    /// Aggregates the metadata of the blocks of an epoch, in ascending block order.
+    ///
+    /// Blocks without metadata, e.g. because they were unwound or imported before metadata was
+    /// indexed, are counted as unattributed.
+    pub fn new(
+        committee: &Committee,
+        first_block: BlockNumber,
+        blocks: impl IntoIterator<Item = (BlockNumber, Option<StoredConsensusMetadata>)>,
+    ) -> Self {
+        let mut report = Self {
+            epoch: committee.epoch,
+            first_block,
+            last_block: first_block,
+            missed_leader_rounds: 0,
+            unattributed_blocks: 0,
+            validators: committee
+                .authorities
+                .iter()
+                .enumerate()
+                .map(|(index, authority)| ValidatorReport {
+                    authority: index as AuthorityIndex,
+                    public_key: authority.public_key.clone(),
+                    stake: authority.stake,
+                    leader_commits: 0,
+                    committed_certificates: 0,
+                    committed_batches: 0,
+                    committed_batch_bytes: 0,
+                })
+                .collect(),
+        };
+
+        let mut last_leader_round: Option<Round> = None;
+        for (number, metadata) in blocks {
+            report.last_block = number;
+            let Some(metadata) = metadata.filter(|metadata| metadata.epoch == report.epoch) else {
+                report.unattributed_blocks += 1;
+                continue
+            };
+
+            // leaders are elected for every even round
+            if let Some(previous) = last_leader_round {
+                let skipped = metadata.last_round.saturating_sub(previous) / 2;
+                report.missed_leader_rounds += skipped.saturating_sub(1);
+            }
+            last_leader_round = Some(metadata.last_round);
+
+            let validator = usize::try_from(metadata.leader)
+                .ok()
+                .and_then(|index| report.validators.get_mut(index));
+            let Some(validator) = validator else {
+                report.unattributed_blocks += 1;
+                continue
+            };
+            validator.leader_commits += 1;
+            validator.committed_certificates += metadata.certificates;
+            validator.committed_batches += metadata.batches;
+            validator.committed_batch_bytes += metadata.batch_bytes;
+        }
+        report
+    }
+
+    /// Writes the validator reports as CSV with a header row.
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "authority,public_key,stake,leader_commits,committed_certificates,committed_batches,\
+             committed_batch_bytes"
+        )?;
+        for validator in &self.validators {
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                validator.authority,
+                validator.public_key,
+                validator.stake,
+                validator.leader_commits,
+                validator.committed_certificates,
+                validator.committed_batches,
+                validator.committed_batch_bytes
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::committee::Authority;
+
+    fn committee(size: u8) -> Committee {
+        let authorities = (0..size)
+            .map(|index| Authority {
+                public_key: Bytes::from(vec![index]),
+                proof_of_possession: Bytes::new(),
+                stake: 1,
+                primary_address: ([127, 0, 0, 1], 30400).into(),
+                worker_addresses: Vec::new(),
+            })
+            .collect();
+        Committee { epoch: 3, authorities }
+    }
+
+    fn led(leader: u64, last_round: Round) -> Option<StoredConsensusMetadata> {
+        Some(StoredConsensusMetadata {
+            leader,
+            first_round: last_round.saturating_sub(1),
+            last_round,
+            certificates: 4,
+            batches: 2,
+            batch_bytes: 1_000,
+            epoch: 3,
+        })
+    }
+
+    #[test]
+    fn aggregate_epoch() {
+        let blocks = vec![
+            (100, led(0, 2)),
+            (101, led(1, 4)),
+            // the leaders of rounds 6 and 8 were not committed
+            (102, led(1, 10)),
+            (103, None),
+            (104, led(7, 12)),
+            (105, led(2, 14)),
+            // committed by the next committee
+            (106, led(0, 2).map(|metadata| StoredConsensusMetadata { epoch: 4, ..metadata })),
+        ];
+        let report = EpochReport::new(&committee(4), 100, blocks);
+
+        assert_eq!(report.epoch, 3);
+        assert_eq!((report.first_block, report.last_block), (100, 106));
+        assert_eq!(report.missed_leader_rounds, 2);
+        assert_eq!(report.unattributed_blocks, 3);
+        let commits: Vec<_> = report.validators.iter().map(|v| v.leader_commits).collect();
+        assert_eq!(commits, vec![1, 2, 1, 0]);
+        assert_eq!(report.validators[1].committed_certificates, 8);
+        assert_eq!(report.validators[1].committed_batch_bytes, 2_000);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[2], "1,0x01,1,2,8,4,2000");
+    }
+}
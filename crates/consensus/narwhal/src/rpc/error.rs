@@ -0,0 +1,176 @@
+use crate::types::Round;
+use reth_narwhal_verifier::Epoch;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+/// The JSON-RPC error codes reserved for narwhal methods.
+pub const NARWHAL_ERROR_CODES: RangeInclusive<i32> = -38099..=-38000;
+
+/// The JSON-RPC error code of a [`NarwhalRpcError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(i32)]
+pub enum NarwhalRpcErrorCode {
+    /// The node is not a member of the committee.
+    NotInCommittee = -38000,
+    /// The requested round is not known to the node.
+    RoundNotFound = -38001,
+    /// The requested data was pruned.
+    Pruned = -38002,
+    /// Consensus is paused.
+    Paused = -38003,
+    /// The caller is not allowed to call the method.
+    Unauthorized = -38004,
+    /// The connection exceeded its request rate.
+    RateLimited = -38005,
+    /// The log filter directives are invalid.
+    InvalidLogFilter = -38006,
+}
+
+impl NarwhalRpcErrorCode {
+    /// All codes, in ascending order of their absolute value.
+    pub const ALL: [Self; 7] = [
+        Self::NotInCommittee,
+        Self::RoundNotFound,
+        Self::Pruned,
+        Self::Paused,
+        Self::Unauthorized,
+        Self::RateLimited,
+        Self::InvalidLogFilter,
+    ];
+
+    /// Returns the JSON-RPC error code.
+    pub const fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Returns the error with the given JSON-RPC error code, if it's a narwhal error.
+    pub fn from_code(code: i32) -> Option<Self> {
+        Self::ALL.into_iter().find(|known| known.code() == code)
+    }
+}
+
+/// An error of a narwhal RPC method.
+///
+/// The error is serialized as the error data, tagged with its `kind`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum NarwhalRpcError {
+    /// The method is only available on members of the committee.
+    #[error("node is not a member of the committee of epoch {epoch}")]
+    NotInCommittee {
+        /// The current epoch.
+        epoch: Epoch,
+    },
+    /// The requested round is not known to the node.
+    #[error("round {round} not found, the latest round is {latest_round}")]
+    RoundNotFound {
+        /// The requested round.
+        round: Round,
+        /// The latest round known to the node.
+        latest_round: Round,
+    },
+    /// The requested data was garbage collected.
+    #[error("round {round} was pruned, the lowest available round is {lowest_available_round}")]
+    Pruned {
+        /// The requested round.
+        round: Round,
+        /// The lowest round the node still has data of.
+        lowest_available_round: Round,
+    },
+    /// Consensus is paused, e.g. for an upgrade.
+    #[error("consensus is paused")]
+    Paused,
+    /// The caller is not allowed to call the method.
+    #[error("unauthorized")]
+    Unauthorized,
+    /// The connection exceeded its request rate.
+    #[error("rate limited, retry after {retry_after_ms}ms")]
+    RateLimited {
+        /// The time after which the request can be retried, in milliseconds.
+        retry_after_ms: u64,
+    },
+    /// The log filter directives are invalid.
+    #[error("invalid log filter: {reason}")]
+    InvalidLogFilter {
+        /// Why the directives are invalid.
+        reason: String,
+    },
+}
+
+impl NarwhalRpcError {
+    /// Returns the JSON-RPC error code of the error.
+    pub const fn code(&self) -> NarwhalRpcErrorCode {
+        match self {
+            Self::NotInCommittee { .. } => NarwhalRpcErrorCode::NotInCommittee,
+            Self::RoundNotFound { .. } => NarwhalRpcErrorCode::RoundNotFound,
+            Self::Pruned { .. } => NarwhalRpcErrorCode::Pruned,
+            Self::Paused => NarwhalRpcErrorCode::Paused,
+            Self::Unauthorized => NarwhalRpcErrorCode::Unauthorized,
+            Self::RateLimited { .. } => NarwhalRpcErrorCode::RateLimited,
+            Self::InvalidLogFilter { .. } => NarwhalRpcErrorCode::InvalidLogFilter,
+        }
+    }
+}
+
+#[cfg(feature = "jsonrpsee-types")]
+impl From<NarwhalRpcError> for jsonrpsee_types::ErrorObject<'static> {
+    fn from(error: NarwhalRpcError) -> Self {
+        Self::owned(error.code().code(), error.to_string(), Some(&error))
+    }
+}
+
+#[cfg(feature = "jsonrpsee-types")]
+impl TryFrom<&jsonrpsee_types::ErrorObject<'_>> for NarwhalRpcError {
+    type Error = ();
+
+    /// Recovers the error from a JSON-RPC error object returned by a narwhal method.
+    fn try_from(error: &jsonrpsee_types::ErrorObject<'_>) -> Result<Self, Self::Error> {
+        let code = NarwhalRpcErrorCode::from_code(error.code()).ok_or(())?;
+        let error: Self = serde_json::from_str(error.data().ok_or(())?.get()).map_err(|_| ())?;
+        if error.code() != code {
+            return Err(())
+        }
+        Ok(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stable_codes() {
+        for (offset, code) in NarwhalRpcErrorCode::ALL.into_iter().enumerate() {
+            assert_eq!(code.code(), -38000 - offset as i32);
+            assert!(NARWHAL_ERROR_CODES.contains(&code.code()));
+            assert_eq!(NarwhalRpcErrorCode::from_code(code.code()), Some(code));
+        }
+        assert_eq!(NarwhalRpcErrorCode::from_code(-32000), None);
+    }
+
+    #[test]
+    fn error_data() {
+        let error = NarwhalRpcError::Pruned { round: 5, lowest_available_round: 100 };
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({ "kind": "pruned", "round": 5, "lowestAvailableRound": 100 })
+        );
+        assert_eq!(
+            serde_json::to_value(NarwhalRpcError::Paused).unwrap(),
+            serde_json::json!({ "kind": "paused" })
+        );
+    }
+
+    #[cfg(feature = "jsonrpsee-types")]
+    #[test]
+    fn error_object_roundtrip() {
+        let error = NarwhalRpcError::RoundNotFound { round: 12, latest_round: 10 };
+        let object = jsonrpsee_types::ErrorObject::from(error.clone());
+        assert_eq!(object.code(), -38001);
+        assert_eq!(object.message(), "round 12 not found, the latest round is 10");
+        assert_eq!(NarwhalRpcError::try_from(&object), Ok(error));
+
+        let other = jsonrpsee_types::ErrorObject::owned(-32000, "other", None::<()>);
+        assert_eq!(NarwhalRpcError::try_from(&other), Err(()));
+    }
+}
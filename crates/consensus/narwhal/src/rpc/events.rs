@@ -0,0 +1,240 @@
+use super::CommittedSubDag;
+use crate::{
+    commit_log::CommitDecision,
+    types::{Certificate, CertificateDigest, Round},
+};
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+/// The number of events a subscriber can fall behind before it misses events.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1_024;
+
+/// The kind of a [`ConsensusEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConsensusEventKind {
+    /// See [`ConsensusEvent::Certificate`].
+    Certificate,
+    /// See [`ConsensusEvent::LeaderDecision`].
+    LeaderDecision,
+    /// See [`ConsensusEvent::SubDagCommitted`].
+    SubDagCommitted,
+}
+
+/// An event of the consensus tasks, published by the [`ConsensusState`](super::ConsensusState).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ConsensusEvent {
+    /// A certificate was added to the DAG.
+    Certificate {
+        /// The round of the certificate.
+        round: Round,
+        /// The author of the certificate.
+        author: AuthorityIndex,
+        /// The digest of the certificate.
+        digest: CertificateDigest,
+    },
+    /// The commit rule decided whether the leader of a round is committed.
+    LeaderDecision(CommitDecision),
+    /// A sub-dag was committed.
+    SubDagCommitted(CommittedSubDag),
+}
+
+impl ConsensusEvent {
+    /// Returns the event of a certificate that was added to the DAG.
+    pub fn certificate(certificate: &Certificate) -> Self {
+        Self::Certificate {
+            round: certificate.round(),
+            author: certificate.author(),
+            digest: certificate.digest(),
+        }
+    }
+
+    /// Returns the kind of the event.
+    pub const fn kind(&self) -> ConsensusEventKind {
+        match self {
+            Self::Certificate { .. } => ConsensusEventKind::Certificate,
+            Self::LeaderDecision(_) => ConsensusEventKind::LeaderDecision,
+            Self::SubDagCommitted(_) => ConsensusEventKind::SubDagCommitted,
+        }
+    }
+
+    /// Returns the authority the event is about, the author of a certificate or the leader.
+    pub const fn authority(&self) -> AuthorityIndex {
+        match self {
+            Self::Certificate { author, .. } => *author,
+            Self::LeaderDecision(decision) => decision.leader,
+            Self::SubDagCommitted(sub_dag) => sub_dag.leader,
+        }
+    }
+
+    /// Returns the round the event is about, the round of a certificate or the leader round.
+    pub const fn round(&self) -> Round {
+        match self {
+            Self::Certificate { round, .. } => *round,
+            Self::LeaderDecision(decision) => decision.round,
+            Self::SubDagCommitted(sub_dag) => sub_dag.leader_round,
+        }
+    }
+
+    /// Returns `true` if the event reports the commit of a leader.
+    pub const fn is_leader_commit(&self) -> bool {
+        match self {
+            Self::Certificate { .. } => false,
+            Self::LeaderDecision(decision) => decision.outcome.is_committed(),
+            Self::SubDagCommitted(_) => true,
+        }
+    }
+}
+
+/// Selects the [`ConsensusEvent`]s a subscriber receives, the default selects all events.
+///
+/// Events are filtered before they are sent, so that subscribers of high-rate deployments only
+/// receive the events they need.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ConsensusEventFilter {
+    /// The kinds of events, all kinds if `None`.
+    pub kinds: Option<Vec<ConsensusEventKind>>,
+    /// The authority the events are about, see [`ConsensusEvent::authority`].
+    pub authority: Option<AuthorityIndex>,
+    /// The first round of the events, see [`ConsensusEvent::round`].
+    pub from_round: Option<Round>,
+    /// The last round of the events, inclusive.
+    pub to_round: Option<Round>,
+    /// Only selects the events that report the commit of a leader.
+    pub only_leader_commits: bool,
+}
+
+impl ConsensusEventFilter {
+    /// Returns `true` if the filter selects the event.
+    pub fn matches(&self, event: &ConsensusEvent) -> bool {
+        let round = event.round();
+        self.kinds.as_ref().map_or(true, |kinds| kinds.contains(&event.kind())) &&
+            self.authority.map_or(true, |authority| authority == event.authority()) &&
+            self.from_round.map_or(true, |from| round >= from) &&
+            self.to_round.map_or(true, |to| round <= to) &&
+            (!self.only_leader_commits || event.is_leader_commit())
+    }
+}
+
+/// A subscription to the [`ConsensusEvent`]s that match a [`ConsensusEventFilter`].
+#[derive(Debug)]
+pub struct ConsensusEvents {
+    events: broadcast::Receiver<ConsensusEvent>,
+    filter: ConsensusEventFilter,
+    missed: u64,
+}
+
+impl ConsensusEvents {
+    pub(super) const fn new(
+        events: broadcast::Receiver<ConsensusEvent>,
+        filter: ConsensusEventFilter,
+    ) -> Self {
+        Self { events, filter, missed: 0 }
+    }
+
+    /// Returns the next event that matches the filter, `None` once the
+    /// [`ConsensusState`](super::ConsensusState) was dropped.
+    ///
+    /// A subscriber that falls behind by more than [`EVENT_CHANNEL_CAPACITY`] events misses the
+    /// oldest ones, see [`ConsensusEvents::missed`].
+    pub async fn recv(&mut self) -> Option<ConsensusEvent> {
+        loop {
+            match self.events.recv().await {
+                Ok(event) if self.filter.matches(&event) => return Some(event),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => self.missed += missed,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Returns the number of events the subscriber missed because it fell behind, matching or
+    /// not.
+    pub const fn missed(&self) -> u64 {
+        self.missed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_log::CommitOutcome;
+
+    fn decision(round: Round, leader: AuthorityIndex, committed: bool) -> ConsensusEvent {
+        let outcome = if committed {
+            CommitOutcome::Committed { support: 3 }
+        } else {
+            CommitOutcome::MissingCertificate
+        };
+        ConsensusEvent::LeaderDecision(CommitDecision { round, leader, outcome })
+    }
+
+    #[test]
+    fn filter_events() {
+        let certificate = ConsensusEvent::Certificate {
+            round: 5,
+            author: 1,
+            digest: CertificateDigest::default(),
+        };
+        let (committed, skipped) = (decision(6, 2, true), decision(8, 1, false));
+
+        let all = ConsensusEventFilter::default();
+        assert!([&certificate, &committed, &skipped].into_iter().all(|event| all.matches(event)));
+
+        let kinds = ConsensusEventFilter {
+            kinds: Some(vec![ConsensusEventKind::LeaderDecision]),
+            ..Default::default()
+        };
+        assert!(!kinds.matches(&certificate) && kinds.matches(&skipped));
+
+        let authority = ConsensusEventFilter { authority: Some(1), ..Default::default() };
+        assert!(authority.matches(&certificate) && !authority.matches(&committed));
+
+        let rounds =
+            ConsensusEventFilter { from_round: Some(6), to_round: Some(7), ..Default::default() };
+        assert!(!rounds.matches(&certificate) && rounds.matches(&committed));
+        assert!(!rounds.matches(&skipped));
+
+        let commits = ConsensusEventFilter { only_leader_commits: true, ..Default::default() };
+        assert!(!commits.matches(&certificate) && commits.matches(&committed));
+        assert!(!commits.matches(&skipped));
+    }
+
+    #[test]
+    fn event_json() {
+        let json = serde_json::to_value(decision(6, 2, true)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "leaderDecision",
+                "round": 6,
+                "leader": 2,
+                "reason": "committed",
+                "support": 3
+            })
+        );
+        assert_eq!(serde_json::from_value::<ConsensusEvent>(json).unwrap(), decision(6, 2, true));
+
+        let filter: ConsensusEventFilter = serde_json::from_value(serde_json::json!({
+            "kinds": ["certificate", "subDagCommitted"],
+            "fromRound": 10,
+            "onlyLeaderCommits": true
+        }))
+        .unwrap();
+        assert_eq!(
+            filter,
+            ConsensusEventFilter {
+                kinds: Some(vec![
+                    ConsensusEventKind::Certificate,
+                    ConsensusEventKind::SubDagCommitted
+                ]),
+                from_round: Some(10),
+                only_leader_commits: true,
+                ..Default::default()
+            }
+        );
+    }
+}
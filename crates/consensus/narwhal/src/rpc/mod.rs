@@ -0,0 +1,69 @@
+//! Building blocks of the `narwhal_` RPC namespace.
+//!
+//! Every error of a narwhal method has a [`NarwhalRpcErrorCode`] from the reserved range
+//! [`NARWHAL_ERROR_CODES`], and its details as the error data, so clients can branch on the code
+//! and read the details without parsing the message. Codes are stable, a code is never reused for
+//! a different error.
+//!
+//! Queries of the DAG and the commit history return a [`Page`] of at most
+//! [`RpcLimitsConfig::max_page_size`] items with a cursor to the next page, and every connection is
+//! limited by an [`RpcRateLimiter`], so that a single client can't degrade the RPC latency of a
+//! validator.
+//!
+//! The introspection methods, e.g. `narwhal_currentRound`, serve the [`ConsensusState`] that the
+//! consensus tasks update as they run. The state also publishes a stream of [`ConsensusEvent`]s,
+//! which subscribers narrow down with a [`ConsensusEventFilter`] before the events are sent, and a
+//! stream of [`CertifiedCheckpoint`]s for the relayers that export them to other chains.
+//!
+//! [`openrpc_document`] describes the namespace for external tooling that generates clients.
+
+mod error;
+mod events;
+mod openrpc;
+mod page;
+mod rate_limit;
+mod state;
+
+pub use error::{NarwhalRpcError, NarwhalRpcErrorCode, NARWHAL_ERROR_CODES};
+pub use events::{
+    ConsensusEvent, ConsensusEventFilter, ConsensusEventKind, ConsensusEvents,
+    EVENT_CHANNEL_CAPACITY,
+};
+pub use openrpc::{openrpc_document, OPENRPC_API_VERSION};
+pub use page::{Page, PageCursor, PageRequest};
+pub use rate_limit::RpcRateLimiter;
+pub use reth_narwhal_client::rpc::CertifiedCheckpoint;
+pub use state::{CommittedSubDag, ConsensusState, RoundInfo, RECENT_DECISIONS};
+
+use serde::{Deserialize, Serialize};
+
+/// Server-side limits of the narwhal RPC namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RpcLimitsConfig {
+    /// The number of items of a page if the request doesn't specify a limit.
+    pub default_page_size: usize,
+    /// The maximum number of items of a page, larger limits are capped.
+    pub max_page_size: usize,
+    /// The number of requests per second a connection can make on average.
+    pub requests_per_second: u32,
+    /// Additional requests a connection that was idle can make at once.
+    pub burst: u32,
+}
+
+impl RpcLimitsConfig {
+    /// Returns the rate limit cost of a query for a page of the given size.
+    ///
+    /// A query costs one request per started page of the default size, so that clients can't
+    /// bypass the rate limit with large pages.
+    pub fn page_cost(&self, page_size: usize) -> u32 {
+        let pages = page_size.div_ceil(self.default_page_size.max(1)).max(1);
+        u32::try_from(pages).unwrap_or(u32::MAX)
+    }
+}
+
+impl Default for RpcLimitsConfig {
+    fn default() -> Self {
+        Self { default_page_size: 100, max_page_size: 1_000, requests_per_second: 20, burst: 40 }
+    }
+}
@@ -0,0 +1,781 @@
+//! The [OpenRPC](https://spec.open-rpc.org) document of the `narwhal_` namespace.
+//!
+//! [`openrpc_document`] describes every method of the namespace with the JSON schemas of its
+//! parameters and results, so that explorers and SDKs can generate clients from it. The document
+//! is checked in as `crates/consensus/narwhal/openrpc.json`, and kept in sync with the code by
+//! tests: the tests of this module check that the serialization of every type matches its schema
+//! and that the checked in document is up to date, and the tests of `reth-node-narwhal` check that
+//! every method of the server traits is documented. Running the tests with
+//! `NARWHAL_UPDATE_OPENRPC=1` regenerates the checked in document.
+//!
+//! Rust clients use the `client` feature of `reth-node-narwhal` instead, which generates client
+//! bindings from the server traits.
+
+use super::{NarwhalRpcErrorCode, RECENT_DECISIONS};
+use serde_json::{json, Map, Value};
+
+/// The version of the namespace in the document, bumped with every change of a method.
+pub const OPENRPC_API_VERSION: &str = "1.5.0";
+
+/// The properties of an object schema: the name, schema and whether the property is required.
+type Properties = Vec<(&'static str, Value, bool)>;
+
+/// Returns the [OpenRPC](https://spec.open-rpc.org) document of the `narwhal_` namespace.
+pub fn openrpc_document() -> Value {
+    json!({
+        "openrpc": "1.2.6",
+        "info": {
+            "title": "narwhal",
+            "description": "Introspection of the narwhal consensus of a node.",
+            "version": OPENRPC_API_VERSION,
+        },
+        "methods": methods(),
+        "components": {
+            "schemas": schemas(),
+            "errors": errors(),
+        },
+    })
+}
+
+/// Returns the methods of the namespace.
+fn methods() -> Vec<Value> {
+    vec![
+        method(
+            "narwhal_currentRound",
+            "Returns the current round of the node's primary.",
+            vec![],
+            ("round", schema_ref("Uint64")),
+        ),
+        method(
+            "narwhal_committee",
+            "Returns the committee of the current epoch.",
+            vec![],
+            ("committee", schema_ref("Committee")),
+        ),
+        method(
+            "narwhal_getCommittee",
+            "Returns the committee of the given epoch, `null` if the epoch is neither recorded in \
+             the committee history of the node nor one of the epochs since the node started.",
+            vec![param("epoch", schema_ref("Uint64"), true)],
+            ("committee", nullable(schema_ref("Committee"))),
+        ),
+        method(
+            "narwhal_lastCommittedSubDag",
+            "Returns the last sub-dag committed since the node started, without its batches.",
+            vec![],
+            ("subDag", nullable(schema_ref("CommittedSubDag"))),
+        ),
+        method(
+            "narwhal_certificate",
+            "Returns the certificate with the given digest, if it's stored and not pruned.",
+            vec![param("digest", schema_ref("Hash"), true)],
+            ("certificate", nullable(schema_ref("Certificate"))),
+        ),
+        method(
+            "narwhal_pendingBatches",
+            "Returns the batches of the node's workers that are waiting to be referenced by a \
+             header.",
+            vec![],
+            ("batches", array(schema_ref("BatchRef"))),
+        ),
+        method(
+            "narwhal_getRoundInfo",
+            "Returns which authorities produced certificates in the round, which votes certified \
+             the node's header of the round, the elected leader, and whether the leader was \
+             committed.",
+            vec![param("round", schema_ref("Uint64"), true)],
+            ("roundInfo", schema_ref("RoundInfo")),
+        ),
+        method(
+            "narwhal_getCommitDecision",
+            &format!(
+                "Returns why the leader of the round was or was not committed, `null` if the round \
+                 has no leader, its commit rule didn't run yet, or it's not one of the last \
+                 {RECENT_DECISIONS} leader rounds."
+            ),
+            vec![param("round", schema_ref("Uint64"), true)],
+            ("decision", nullable(schema_ref("CommitDecision"))),
+        ),
+        method(
+            "narwhal_commitLog",
+            "Returns the entries of the commit audit log with indices from `fromIndex` to \
+             `toIndex`, both inclusive, but at most a page of entries. The entries form a hash \
+             chain.",
+            vec![
+                param("fromIndex", schema_ref("Uint64"), true),
+                param("toIndex", schema_ref("Uint64"), true),
+            ],
+            ("entries", array(schema_ref("CommitAuditEntry"))),
+        ),
+        subscription(
+            "narwhal_subscribeEvents",
+            "Subscribes to the consensus events that match the filter, or to all events if no \
+             filter is given.",
+            vec![param("filter", schema_ref("ConsensusEventFilter"), false)],
+            ("narwhal_event", "narwhal_unsubscribeEvents", schema_ref("ConsensusEvent")),
+        ),
+        unsubscribe("narwhal_unsubscribeEvents"),
+        subscription(
+            "narwhal_subscribeCheckpoints",
+            "Subscribes to the checkpoint ranges signed by a quorum of the committee, with their \
+             ABI encoded proofs.",
+            vec![],
+            (
+                "narwhal_checkpoint",
+                "narwhal_unsubscribeCheckpoints",
+                schema_ref("CertifiedCheckpoint"),
+            ),
+        ),
+        unsubscribe("narwhal_unsubscribeCheckpoints"),
+        method(
+            "narwhal_getBlockAttribution",
+            "Returns the commit the block was built from, `null` if the block doesn't exist or \
+             was built before the node recorded the commits of its blocks.",
+            vec![param("block", schema_ref("BlockNumberOrTag"), true)],
+            ("attribution", nullable(schema_ref("BlockAttribution"))),
+        ),
+        method(
+            "narwhal_getMessageProof",
+            "Returns the proof that a message with the given hash was sent in the block, against \
+             the message root its header commits to, `null` if the block doesn't exist or sent no \
+             such message.",
+            vec![
+                param("block", schema_ref("BlockNumberOrTag"), true),
+                param("messageHash", schema_ref("Hash"), true),
+            ],
+            ("proof", nullable(schema_ref("MessageProof"))),
+        ),
+        method(
+            "narwhal_txStatus",
+            "Returns whether a sequenced transaction was included in a block, or dropped from the \
+             block of its commit or expired before it, `null` if the node doesn't know the \
+             transaction. Dropped and expired transactions are only remembered for a while and \
+             not across restarts.",
+            vec![param("hash", schema_ref("Hash"), true)],
+            ("status", nullable(schema_ref("TransactionStatus"))),
+        ),
+        method(
+            "narwhal_setLogFilter",
+            "Adds directives for consensus targets to the log filter of the node, replacing the \
+             directives of previous calls. The filter is reset after `duration` seconds, if \
+             given, and empty directives reset it immediately. Only served by the authenticated \
+             server of the engine API.",
+            vec![
+                param("directives", json!({ "type": "string" }), true),
+                param("duration", schema_ref("Uint64"), false),
+            ],
+            ("result", json!({ "type": "null" })),
+        ),
+    ]
+}
+
+/// Returns the schemas of the parameters and results, by name.
+fn schemas() -> Map<String, Value> {
+    let outcomes = commit_outcomes();
+    let decision =
+        vec![("round", schema_ref("Uint64"), true), ("leader", schema_ref("Uint32"), true)];
+    let audit_entry = vec![
+        ("index", schema_ref("Uint64"), true),
+        ("parent", schema_ref("Hash"), true),
+        ("epoch", schema_ref("Uint64"), true),
+        ("round", schema_ref("Uint64"), true),
+        ("leader", schema_ref("Uint32"), true),
+        ("leaderCertificate", schema_ref("Hash"), true),
+        ("supporters", array(schema_ref("CommitSupporter")), true),
+    ];
+    let sub_dag = vec![
+        ("index", schema_ref("Uint64"), true),
+        ("epoch", schema_ref("Uint64"), true),
+        ("leaderRound", schema_ref("Uint64"), true),
+        ("leader", schema_ref("Uint32"), true),
+        ("leaderDigest", schema_ref("Hash"), true),
+        ("certificates", array(schema_ref("Hash")), true),
+        ("batches", array(schema_ref("Hash")), true),
+        ("timestamp", schema_ref("Uint64"), true),
+    ];
+    let event_kind = |kind: &'static str| ("kind", json!({ "const": kind }), true);
+
+    let mut events = vec![object(
+        "A certificate was added to the DAG.",
+        vec![
+            event_kind("certificate"),
+            ("round", schema_ref("Uint64"), true),
+            ("author", schema_ref("Uint32"), true),
+            ("digest", schema_ref("Hash"), true),
+        ],
+    )];
+    events.extend(outcomes.iter().map(|(description, outcome)| {
+        let properties =
+            [vec![event_kind("leaderDecision")], decision.clone(), outcome.clone()].concat();
+        object(description, properties)
+    }));
+    events.push(object(
+        "A sub-dag was committed.",
+        [vec![event_kind("subDagCommitted")], sub_dag.clone()].concat(),
+    ));
+
+    let schemas = [
+        ("Uint32", json!({ "type": "integer", "minimum": 0, "maximum": u32::MAX })),
+        ("Uint64", json!({ "type": "integer", "minimum": 0 })),
+        (
+            "Hash",
+            json!({ "type": "string", "pattern": "^0x[0-9a-f]{64}$", "description": "32 bytes" }),
+        ),
+        ("Bytes", json!({ "type": "string", "pattern": "^0x([0-9a-f]{2})*$" })),
+        (
+            "BlockNumberOrTag",
+            json!({
+                "oneOf": [
+                    { "type": "string", "pattern": "^0x([1-9a-f][0-9a-f]*|0)$" },
+                    { "enum": ["earliest", "latest", "pending", "finalized", "safe"] },
+                ],
+            }),
+        ),
+        (
+            "Authority",
+            object(
+                "A member of the committee.",
+                vec![
+                    ("publicKey", schema_ref("Bytes"), true),
+                    ("proofOfPossession", schema_ref("Bytes"), true),
+                    ("stake", schema_ref("Uint64"), true),
+                    ("primaryAddress", json!({ "type": "string" }), true),
+                    ("workerAddresses", array(json!({ "type": "string" })), true),
+                ],
+            ),
+        ),
+        (
+            "Committee",
+            object(
+                "The authorities of an epoch, the position of an authority is its index.",
+                vec![
+                    ("epoch", schema_ref("Uint64"), true),
+                    ("authorities", array(schema_ref("Authority")), true),
+                ],
+            ),
+        ),
+        (
+            "BatchRef",
+            object(
+                "A batch of a worker.",
+                vec![("digest", schema_ref("Hash"), true), ("worker", schema_ref("Uint32"), true)],
+            ),
+        ),
+        (
+            "Header",
+            object(
+                "The header of an authority in a round.",
+                vec![
+                    ("epoch", schema_ref("Uint64"), true),
+                    ("round", schema_ref("Uint64"), true),
+                    ("author", schema_ref("Uint32"), true),
+                    ("payload", array(schema_ref("BatchRef")), true),
+                    ("parents", array(schema_ref("Hash")), true),
+                    ("createdAt", schema_ref("Uint64"), true),
+                ],
+            ),
+        ),
+        (
+            "Certificate",
+            object(
+                "A header with the aggregate signature of a quorum of votes.",
+                vec![
+                    ("header", schema_ref("Header"), true),
+                    ("signers", array(schema_ref("Uint32")), true),
+                    ("signature", schema_ref("Bytes"), true),
+                ],
+            ),
+        ),
+        ("CommittedSubDag", object("A committed sub-dag without its batches.", sub_dag)),
+        (
+            "CommitOutcome",
+            one_of(
+                outcomes.iter().map(|(description, outcome)| object(description, outcome.clone())),
+            ),
+        ),
+        (
+            "CommitDecision",
+            one_of(outcomes.iter().map(|(description, outcome)| {
+                object(description, [decision.clone(), outcome.clone()].concat())
+            })),
+        ),
+        (
+            "RoundInfo",
+            object(
+                &format!(
+                    "The participation of the committee in a round. The leader and outcome are \
+                     only known for the last {RECENT_DECISIONS} leader rounds."
+                ),
+                vec![
+                    ("round", schema_ref("Uint64"), true),
+                    ("certificates", array(schema_ref("Uint32")), true),
+                    ("missing", array(schema_ref("Uint32")), true),
+                    ("localVotes", nullable(array(schema_ref("Uint32"))), true),
+                    ("leader", nullable(schema_ref("Uint32")), true),
+                    ("committed", json!({ "type": "boolean" }), true),
+                    ("outcome", nullable(schema_ref("CommitOutcome")), true),
+                ],
+            ),
+        ),
+        (
+            "CommitSupporter",
+            object(
+                "A certificate of the round after a leader round that references the leader.",
+                vec![
+                    ("author", schema_ref("Uint32"), true),
+                    ("certificate", schema_ref("Hash"), true),
+                ],
+            ),
+        ),
+        (
+            "CommitAuditEntry",
+            one_of(outcomes.iter().map(|(description, outcome)| {
+                object(description, [audit_entry.clone(), outcome.clone()].concat())
+            })),
+        ),
+        (
+            "ConsensusEventKind",
+            json!({ "enum": ["certificate", "leaderDecision", "subDagCommitted"] }),
+        ),
+        (
+            "ConsensusEventFilter",
+            object(
+                "Selects the events of a subscription, an empty filter selects all events.",
+                vec![
+                    ("kinds", nullable(array(schema_ref("ConsensusEventKind"))), false),
+                    ("authority", nullable(schema_ref("Uint32")), false),
+                    ("fromRound", nullable(schema_ref("Uint64")), false),
+                    ("toRound", nullable(schema_ref("Uint64")), false),
+                    ("onlyLeaderCommits", json!({ "type": "boolean" }), false),
+                ],
+            ),
+        ),
+        ("ConsensusEvent", one_of(events)),
+        (
+            "CertifiedCheckpoint",
+            object(
+                "A range of blocks signed by a quorum of the committee.",
+                vec![
+                    ("epoch", schema_ref("Uint64"), true),
+                    ("firstBlock", schema_ref("Uint64"), true),
+                    ("lastBlock", schema_ref("Uint64"), true),
+                    ("blocksRoot", schema_ref("Hash"), true),
+                    ("stateRoot", schema_ref("Hash"), true),
+                    ("proof", schema_ref("Bytes"), true),
+                ],
+            ),
+        ),
+        (
+            "BlockAttribution",
+            object(
+                "The commit of the DAG a block was built from.",
+                vec![
+                    ("number", schema_ref("Uint64"), true),
+                    ("hash", schema_ref("Hash"), true),
+                    ("epoch", schema_ref("Uint64"), true),
+                    ("leader", schema_ref("Uint64"), true),
+                    ("firstRound", schema_ref("Uint64"), true),
+                    ("lastRound", schema_ref("Uint64"), true),
+                    ("certificates", schema_ref("Uint64"), true),
+                ],
+            ),
+        ),
+        (
+            "MessageProof",
+            object(
+                "The proof that a message was sent in a block.",
+                vec![
+                    ("messageHash", schema_ref("Hash"), true),
+                    ("index", schema_ref("Uint64"), true),
+                    ("proof", array(schema_ref("Hash")), true),
+                    ("root", schema_ref("Hash"), true),
+                ],
+            ),
+        ),
+        (
+            "TransactionStatus",
+            one_of([
+                object(
+                    "The transaction was executed and included in a block.",
+                    vec![
+                        ("status", json!({ "const": "included" }), true),
+                        ("blockNumber", schema_ref("Uint64"), true),
+                        ("blockHash", schema_ref("Hash"), true),
+                    ],
+                ),
+                object(
+                    "The transaction was sequenced, but excluded from the block derived from its \
+                     commit.",
+                    vec![
+                        ("status", json!({ "const": "dropped" }), true),
+                        ("blockNumber", schema_ref("Uint64"), true),
+                        ("reason", json!({ "type": "string" }), true),
+                    ],
+                ),
+                object(
+                    "The transaction was sequenced after its expiry, and excluded from the block \
+                     derived from its commit.",
+                    vec![
+                        ("status", json!({ "const": "expired" }), true),
+                        ("blockNumber", schema_ref("Uint64"), true),
+                        ("expiry", schema_ref("TransactionExpiry"), true),
+                    ],
+                ),
+            ]),
+        ),
+        (
+            "TransactionExpiry",
+            one_of([
+                object(
+                    "The last consensus round whose commit may include the transaction.",
+                    vec![("round", schema_ref("Uint64"), true)],
+                ),
+                object(
+                    "The last block timestamp that may include the transaction.",
+                    vec![("timestamp", schema_ref("Uint64"), true)],
+                ),
+            ]),
+        ),
+    ];
+    schemas.into_iter().map(|(name, schema)| (name.to_string(), schema)).collect()
+}
+
+/// Returns the variants of a commit outcome, which are flattened into the objects that contain
+/// them.
+fn commit_outcomes() -> Vec<(&'static str, Properties)> {
+    let reason = |reason: &'static str| ("reason", json!({ "const": reason }), true);
+    vec![
+        (
+            "The leader's certificate was committed.",
+            vec![reason("committed"), ("support", schema_ref("Uint64"), true)],
+        ),
+        (
+            "The leader's certificate was committed through the commit of a later leader.",
+            vec![reason("committedIndirectly"), ("byRound", schema_ref("Uint64"), true)],
+        ),
+        ("There is no certificate of the leader in the round.", vec![reason("missingCertificate")]),
+        (
+            "Not enough certificates of the next round reference the leader.",
+            vec![
+                reason("insufficientSupport"),
+                ("support", schema_ref("Uint64"), true),
+                ("threshold", schema_ref("Uint64"), true),
+            ],
+        ),
+        (
+            "The round timed out before the leader's certificate arrived.",
+            vec![reason("timeoutSkip")],
+        ),
+    ]
+}
+
+/// Returns the errors of the namespace, whose data is tagged with the `kind` of the error.
+fn errors() -> Map<String, Value> {
+    NarwhalRpcErrorCode::ALL
+        .into_iter()
+        .map(|code| {
+            let name = format!("{code:?}");
+            let error = json!({ "code": code.code(), "message": name });
+            (name, error)
+        })
+        .collect()
+}
+
+fn method(name: &str, summary: &str, params: Vec<Value>, (result, schema): (&str, Value)) -> Value {
+    json!({
+        "name": name,
+        "summary": summary,
+        "params": params,
+        "result": { "name": result, "schema": schema },
+    })
+}
+
+/// Returns a subscription method, whose result is the id of the subscription, with the
+/// notification method, the unsubscribe method and the schema of the items in `x-subscription`.
+fn subscription(
+    name: &str,
+    summary: &str,
+    params: Vec<Value>,
+    (notification, unsubscribe, item): (&str, &str, Value),
+) -> Value {
+    let mut method = method(name, summary, params, ("subscription", json!({ "type": "string" })));
+    method["x-subscription"] =
+        json!({ "notification": notification, "unsubscribe": unsubscribe, "item": item });
+    method
+}
+
+fn unsubscribe(name: &str) -> Value {
+    method(
+        name,
+        "Cancels a subscription, returns `false` if it doesn't exist.",
+        vec![param("subscription", json!({ "type": "string" }), true)],
+        ("cancelled", json!({ "type": "boolean" })),
+    )
+}
+
+fn param(name: &str, schema: Value, required: bool) -> Value {
+    json!({ "name": name, "required": required, "schema": schema })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "oneOf": [schema, { "type": "null" }] })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn one_of(schemas: impl IntoIterator<Item = Value>) -> Value {
+    json!({ "oneOf": schemas.into_iter().collect::<Vec<_>>() })
+}
+
+/// Returns the schema of an object without other properties than the given ones.
+fn object(description: &str, properties: Properties) -> Value {
+    let required = properties
+        .iter()
+        .filter(|(_, _, required)| *required)
+        .map(|(name, _, _)| *name)
+        .collect::<Vec<_>>();
+    let properties = properties
+        .into_iter()
+        .map(|(name, schema, _)| (name.to_string(), schema))
+        .collect::<Map<_, _>>();
+    json!({
+        "type": "object",
+        "description": description,
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        commit_log::{CommitAuditEntry, CommitDecision, CommitOutcome, CommitSupporter},
+        committee::{Authority, Committee},
+        rpc::{
+            CertifiedCheckpoint, CommittedSubDag, ConsensusEvent, ConsensusEventFilter, RoundInfo,
+        },
+        types::{BatchDigest, BatchRef, Certificate, CertificateDigest, Header, TransactionExpiry},
+    };
+    use alloy_primitives::{Bytes, B256};
+    use reth_narwhal_client::rpc::BlockAttribution;
+    use serde::Serialize;
+    use std::path::Path;
+
+    /// Returns `true` if the value matches the schema, resolving references in the document.
+    fn matches(document: &Value, schema: &Value, value: &Value) -> bool {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/components/schemas/");
+            return matches(document, &document["components"]["schemas"][name], value)
+        }
+        if let Some(schemas) = schema["oneOf"].as_array() {
+            return schemas.iter().filter(|schema| matches(document, schema, value)).count() == 1
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            return values.contains(value)
+        }
+        if !schema["const"].is_null() {
+            return &schema["const"] == value
+        }
+        match schema["type"].as_str().unwrap() {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "integer" => value.as_u64().is_some_and(|value| {
+                schema["maximum"].as_u64().map_or(true, |maximum| value <= maximum)
+            }),
+            "string" => value.as_str().is_some_and(|value| {
+                !schema["pattern"].as_str().is_some_and(|pattern| pattern.starts_with("^0x")) ||
+                    value.strip_prefix("0x").is_some_and(|hex| {
+                        hex.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+                    })
+            }),
+            "array" => value.as_array().is_some_and(|items| {
+                items.iter().all(|item| matches(document, &schema["items"], item))
+            }),
+            "object" => value.as_object().is_some_and(|object| {
+                let properties = schema["properties"].as_object().unwrap();
+                let required = schema["required"].as_array().unwrap();
+                required.iter().all(|name| object.contains_key(name.as_str().unwrap())) &&
+                    object.iter().all(|(name, value)| {
+                        properties.get(name).is_some_and(|schema| matches(document, schema, value))
+                    })
+            }),
+            other => panic!("unknown type {other}"),
+        }
+    }
+
+    fn assert_matches(document: &Value, name: &str, value: impl Serialize) {
+        let value = serde_json::to_value(value).unwrap();
+        assert!(matches(document, &schema_ref(name), &value), "{name} doesn't match {value}");
+    }
+
+    #[test]
+    fn schemas_match_serialization() {
+        let document = openrpc_document();
+        let digest = CertificateDigest(B256::with_last_byte(1));
+        let header = Header {
+            payload: vec![BatchRef { digest: BatchDigest(B256::ZERO), worker: 1 }],
+            parents: vec![digest],
+            ..Default::default()
+        };
+        assert_matches(
+            &document,
+            "Certificate",
+            Certificate { header, signers: vec![0, 2], signature: Bytes::from_static(&[1; 96]) },
+        );
+        assert_matches(
+            &document,
+            "Committee",
+            Committee {
+                epoch: 1,
+                authorities: vec![Authority {
+                    public_key: Bytes::from_static(&[1; 48]),
+                    proof_of_possession: Bytes::from_static(&[2; 96]),
+                    stake: 1,
+                    primary_address: ([127, 0, 0, 1], 30400).into(),
+                    worker_addresses: vec![([127, 0, 0, 1], 30401).into()],
+                }],
+            },
+        );
+
+        let outcomes = [
+            CommitOutcome::Committed { support: 3 },
+            CommitOutcome::CommittedIndirectly { by_round: 4 },
+            CommitOutcome::MissingCertificate,
+            CommitOutcome::InsufficientSupport { support: 1, threshold: 3 },
+            CommitOutcome::TimeoutSkip,
+        ];
+        for outcome in outcomes {
+            assert_matches(&document, "CommitOutcome", outcome);
+            let decision = CommitDecision { round: 2, leader: 1, outcome };
+            assert_matches(&document, "CommitDecision", decision);
+            assert_matches(&document, "ConsensusEvent", ConsensusEvent::LeaderDecision(decision));
+            let entry = CommitAuditEntry {
+                index: 0,
+                parent: B256::ZERO,
+                epoch: 0,
+                round: 2,
+                leader: 1,
+                leader_certificate: digest,
+                outcome,
+                supporters: vec![CommitSupporter { author: 0, certificate: digest }],
+            };
+            assert_matches(&document, "CommitAuditEntry", entry);
+        }
+
+        let sub_dag = CommittedSubDag {
+            index: 1,
+            epoch: 0,
+            leader_round: 2,
+            leader: 1,
+            leader_digest: digest,
+            certificates: vec![digest],
+            batches: vec![BatchDigest(B256::ZERO)],
+            timestamp: 1_700_000_000,
+        };
+        assert_matches(&document, "CommittedSubDag", &sub_dag);
+        assert_matches(&document, "ConsensusEvent", ConsensusEvent::SubDagCommitted(sub_dag));
+        assert_matches(
+            &document,
+            "ConsensusEvent",
+            ConsensusEvent::Certificate { round: 1, author: 0, digest },
+        );
+        assert_matches(&document, "ConsensusEventFilter", ConsensusEventFilter::default());
+        assert_matches(
+            &document,
+            "RoundInfo",
+            RoundInfo {
+                round: 2,
+                certificates: vec![0, 1, 2],
+                missing: vec![3],
+                local_votes: Some(vec![0, 1, 2]),
+                leader: None,
+                committed: false,
+                outcome: Some(CommitOutcome::TimeoutSkip),
+            },
+        );
+        assert_matches(
+            &document,
+            "CertifiedCheckpoint",
+            CertifiedCheckpoint {
+                epoch: 0,
+                first_block: 1,
+                last_block: 8,
+                blocks_root: B256::ZERO,
+                state_root: B256::ZERO,
+                proof: Bytes::from_static(&[0xab]),
+            },
+        );
+        assert_matches(
+            &document,
+            "BlockAttribution",
+            BlockAttribution {
+                number: 8,
+                hash: B256::ZERO,
+                epoch: 0,
+                leader: 1,
+                first_round: 1,
+                last_round: 2,
+                certificates: 4,
+            },
+        );
+        assert_matches(&document, "BlockNumberOrTag", "0x1f");
+        assert_matches(&document, "BlockNumberOrTag", "latest");
+
+        #[cfg(feature = "execution")]
+        {
+            use crate::{messages::MessageProof, TransactionStatus};
+
+            assert_matches(
+                &document,
+                "MessageProof",
+                MessageProof {
+                    message_hash: B256::with_last_byte(1),
+                    index: 1,
+                    proof: vec![B256::ZERO, B256::with_last_byte(2)],
+                    root: B256::with_last_byte(3),
+                },
+            );
+            assert_matches(
+                &document,
+                "TransactionStatus",
+                TransactionStatus::Included { block_number: 3, block_hash: B256::ZERO },
+            );
+            assert_matches(
+                &document,
+                "TransactionStatus",
+                TransactionStatus::Dropped { block_number: 3, reason: "invalid".to_string() },
+            );
+            assert_matches(
+                &document,
+                "TransactionStatus",
+                TransactionStatus::Expired { block_number: 3, expiry: TransactionExpiry::Round(2) },
+            );
+        }
+        assert_matches(&document, "TransactionExpiry", TransactionExpiry::Timestamp(1));
+    }
+
+    #[test]
+    fn checked_in_document_is_up_to_date() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("openrpc.json");
+        let document = openrpc_document();
+        if std::env::var_os("NARWHAL_UPDATE_OPENRPC").is_some() {
+            let json = serde_json::to_string_pretty(&document).unwrap();
+            std::fs::write(&path, json + "\n").unwrap();
+        }
+        let checked_in: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(
+            checked_in == document,
+            "{} is outdated, regenerate it with NARWHAL_UPDATE_OPENRPC=1",
+            path.display()
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use super::RpcLimitsConfig;
+use crate::types::Round;
+use serde::{Deserialize, Serialize};
+
+/// The position of an item in a query ordered by round, e.g. of a certificate by its round and
+/// author.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct PageCursor {
+    /// The round of the item.
+    pub round: Round,
+    /// The index of the item within its round.
+    pub index: u64,
+}
+
+impl PageCursor {
+    /// Returns the position of the first item of a round.
+    pub const fn first_of_round(round: Round) -> Self {
+        Self { round, index: 0 }
+    }
+}
+
+/// The page of a query a client requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PageRequest {
+    /// The position of the first item, the cursor returned with the previous page.
+    ///
+    /// Starts at the first item if not set.
+    pub cursor: Option<PageCursor>,
+    /// The maximum number of items.
+    pub limit: Option<usize>,
+}
+
+impl PageRequest {
+    /// Returns the number of items of the page, within the configured limits.
+    pub fn page_size(&self, limits: &RpcLimitsConfig) -> usize {
+        self.limit.unwrap_or(limits.default_page_size).clamp(1, limits.max_page_size.max(1))
+    }
+}
+
+/// A page of the results of a query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    /// The items of the page.
+    pub items: Vec<T>,
+    /// The position of the first item of the next page, `None` if this is the last page.
+    pub next_cursor: Option<PageCursor>,
+}
+
+impl<T> Page<T> {
+    /// Collects the requested page from the items of a query and their positions, in ascending
+    /// order.
+    ///
+    /// Items before the cursor of the request are skipped, and at most one item after the page is
+    /// consumed.
+    pub fn collect(
+        items: impl IntoIterator<Item = (PageCursor, T)>,
+        request: &PageRequest,
+        limits: &RpcLimitsConfig,
+    ) -> Self {
+        let page_size = request.page_size(limits);
+        let start = request.cursor.unwrap_or_default();
+        let mut items = items.into_iter().skip_while(|(position, _)| *position < start);
+
+        let page = items.by_ref().take(page_size).map(|(_, item)| item).collect();
+        let next_cursor = items.next().map(|(position, _)| position);
+        Self { items: page, next_cursor }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paginate() {
+        let limits =
+            RpcLimitsConfig { default_page_size: 3, max_page_size: 4, ..Default::default() };
+        let items = || {
+            (1..=3u64).flat_map(|round| {
+                (0..3).map(move |index| (PageCursor { round, index }, (round, index)))
+            })
+        };
+
+        let page = Page::collect(items(), &PageRequest::default(), &limits);
+        assert_eq!(page.items, vec![(1, 0), (1, 1), (1, 2)]);
+        assert_eq!(page.next_cursor, Some(PageCursor::first_of_round(2)));
+
+        // limits are capped
+        let request = PageRequest { cursor: page.next_cursor, limit: Some(100) };
+        let page = Page::collect(items(), &request, &limits);
+        assert_eq!(page.items, vec![(2, 0), (2, 1), (2, 2), (3, 0)]);
+        assert_eq!(page.next_cursor, Some(PageCursor { round: 3, index: 1 }));
+
+        let request = PageRequest { cursor: page.next_cursor, limit: Some(0) };
+        let page = Page::collect(items(), &request, &limits);
+        assert_eq!(page.items, vec![(3, 1)]);
+
+        let request = PageRequest { cursor: Some(PageCursor { round: 3, index: 1 }), limit: None };
+        let page = Page::collect(items(), &request, &limits);
+        assert_eq!(page.items, vec![(3, 1), (3, 2)]);
+        assert_eq!(page.next_cursor, None);
+    }
+}
@@ -0,0 +1,152 @@
+use super::{NarwhalRpcError, RpcLimitsConfig};
+use reth_metrics::{metrics::Counter, Metrics};
+use std::{collections::HashMap, hash::Hash, sync::Mutex, time::Instant};
+
+/// Metrics of the [`RpcRateLimiter`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.rpc")]
+struct RpcRateLimitMetrics {
+    /// Number of rejected requests
+    rate_limited_requests: Counter,
+}
+
+/// Token bucket of a single connection.
+#[derive(Debug, Clone, Copy)]
+struct ConnectionBudget {
+    /// Remaining requests in the bucket.
+    tokens: f64,
+    /// When the bucket was last refilled.
+    refilled_at: Instant,
+}
+
+#[derive(Debug)]
+struct RateLimiterInner<K> {
+    connections: HashMap<K, ConnectionBudget>,
+    /// The number of connections after idle connections were last forgotten.
+    retained: usize,
+}
+
+/// Limits the request rate of each connection with a token bucket that allows short bursts.
+///
+/// Connections are identified by a key of the server, e.g. the connection id or the address of
+/// the peer. The limiter can be shared by all handlers of the namespace.
+#[derive(Debug)]
+pub struct RpcRateLimiter<K> {
+    /// Requests per second.
+    rate: f64,
+    /// Maximum number of tokens of a bucket.
+    capacity: f64,
+    inner: Mutex<RateLimiterInner<K>>,
+    metrics: RpcRateLimitMetrics,
+}
+
+impl<K: Hash + Eq> RpcRateLimiter<K> {
+    /// Creates a rate limiter with the rate of the given limits.
+    pub fn new(limits: &RpcLimitsConfig) -> Self {
+        let rate = f64::from(limits.requests_per_second.max(1));
+        Self {
+            rate,
+            capacity: rate + f64::from(limits.burst),
+            inner: Mutex::new(RateLimiterInner { connections: HashMap::new(), retained: 0 }),
+            metrics: RpcRateLimitMetrics::default(),
+        }
+    }
+
+    /// Consumes `cost` requests of the connection's budget, see
+    /// [`RpcLimitsConfig::page_cost`].
+    ///
+    /// Returns [`NarwhalRpcError::RateLimited`] with the time until the budget suffices if the
+    /// connection exhausted its budget.
+    pub fn check(&self, connection: K, cost: u32, now: Instant) -> Result<(), NarwhalRpcError> {
+        let cost = f64::from(cost).min(self.capacity);
+        let mut inner = self.inner.lock().unwrap();
+        let budget = inner
+            .connections
+            .entry(connection)
+            .or_insert(ConnectionBudget { tokens: self.capacity, refilled_at: now });
+
+        let elapsed = now.saturating_duration_since(budget.refilled_at).as_secs_f64();
+        budget.tokens = elapsed.mul_add(self.rate, budget.tokens).min(self.capacity);
+        budget.refilled_at = now;
+
+        if budget.tokens < cost {
+            let retry_after_ms = ((cost - budget.tokens) * 1_000.0 / self.rate).ceil() as u64;
+            self.metrics.rate_limited_requests.increment(1);
+            return Err(NarwhalRpcError::RateLimited { retry_after_ms: retry_after_ms.max(1) })
+        }
+        budget.tokens -= cost;
+
+        // forget idle connections whenever the number of tracked connections doubled, which
+        // bounds the memory to the recently active connections
+        if inner.connections.len() > inner.retained.max(64) * 2 {
+            self.forget_idle(&mut inner, now);
+        }
+        Ok(())
+    }
+
+    /// Forgets the budget of a closed connection.
+    pub fn remove(&self, connection: &K) {
+        self.inner.lock().unwrap().connections.remove(connection);
+    }
+
+    /// Returns the number of tracked connections.
+    pub fn connections(&self) -> usize {
+        self.inner.lock().unwrap().connections.len()
+    }
+
+    /// Forgets all connections whose bucket has been refilled completely.
+    fn forget_idle(&self, inner: &mut RateLimiterInner<K>, now: Instant) {
+        let (rate, capacity) = (self.rate, self.capacity);
+        inner.connections.retain(|_, budget| {
+            let elapsed = now.saturating_duration_since(budget.refilled_at).as_secs_f64();
+            elapsed.mul_add(rate, budget.tokens) < capacity
+        });
+        inner.retained = inner.connections.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn limit_connections() {
+        let limits = RpcLimitsConfig { requests_per_second: 10, burst: 10, ..Default::default() };
+        let limiter = RpcRateLimiter::new(&limits);
+        let start = Instant::now();
+
+        assert_eq!(limiter.check(1, 15, start), Ok(()));
+        assert_eq!(limiter.check(1, 5, start), Ok(()));
+        assert_eq!(
+            limiter.check(1, 2, start),
+            Err(NarwhalRpcError::RateLimited { retry_after_ms: 200 })
+        );
+        // other connections have their own budget
+        assert_eq!(limiter.check(2, 20, start), Ok(()));
+
+        let later = start + Duration::from_millis(200);
+        assert_eq!(limiter.check(1, 2, later), Ok(()));
+        assert!(limiter.check(1, 1, later).is_err());
+
+        limiter.remove(&1);
+        assert_eq!(limiter.connections(), 1);
+        assert_eq!(limiter.check(1, 20, later), Ok(()));
+    }
+
+    #[test]
+    fn forget_idle_connections() {
+        let limiter = RpcRateLimiter::new(&RpcLimitsConfig::default());
+        let start = Instant::now();
+        for connection in 0..=128 {
+            limiter.check(connection, 1, start).unwrap();
+        }
+        assert_eq!(limiter.connections(), 129);
+
+        // the connections of the first second are idle once the tracked connections doubled
+        for connection in 129..=258 {
+            limiter.check(connection, 1, start + Duration::from_secs(1)).unwrap();
+        }
+        assert_eq!(limiter.connections(), 130);
+    }
+}
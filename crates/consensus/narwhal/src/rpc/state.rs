@@ -0,0 +1,478 @@
+use super::{
+    CertifiedCheckpoint, ConsensusEvent, ConsensusEventFilter, ConsensusEvents,
+    EVENT_CHANNEL_CAPACITY,
+};
+use crate::{
+    commit_log::{CommitAuditEntry, CommitDecision, CommitDecisionLog, CommitOutcome},
+    committee::{Committee, CommitteeProvider},
+    committee_history::CommitteeHistoryProvider,
+    dag_store::{DagStore, DagStoreError},
+    primary::PrimaryHandle,
+    types::{BatchDigest, BatchRef, Certificate, CertificateDigest, OrderedSubDag, Round},
+};
+use reth_narwhal_verifier::{abi::CheckpointRangeProof, AuthorityIndex, Epoch};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, PoisonError, RwLock};
+use tokio::sync::broadcast;
+
+/// A committed sub-dag without its batches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommittedSubDag {
+    /// The position of the sub-dag in the sequence of all commits.
+    pub index: u64,
+    /// The epoch of the committee that committed the sub-dag.
+    pub epoch: Epoch,
+    /// The round of the leader.
+    pub leader_round: Round,
+    /// The author of the leader.
+    pub leader: AuthorityIndex,
+    /// The digest of the leader's certificate.
+    pub leader_digest: CertificateDigest,
+    /// The digests of the committed certificates in commit order, ending with the leader.
+    pub certificates: Vec<CertificateDigest>,
+    /// The digests of the committed batches in execution order.
+    pub batches: Vec<BatchDigest>,
+    /// The commit timestamp in seconds.
+    pub timestamp: u64,
+}
+
+impl From<&OrderedSubDag> for CommittedSubDag {
+    fn from(sub_dag: &OrderedSubDag) -> Self {
+        Self {
+            index: sub_dag.index,
+            epoch: sub_dag.epoch(),
+            leader_round: sub_dag.leader_round(),
+            leader: sub_dag.leader.author(),
+            leader_digest: sub_dag.leader.digest(),
+            certificates: sub_dag.certificates.iter().map(Certificate::digest).collect(),
+            batches: sub_dag.unique_batches().map(|batch| batch.digest()).collect(),
+            timestamp: sub_dag.timestamp,
+        }
+    }
+}
+
+/// The participation of the committee in a round of the DAG, see
+/// [`ConsensusState::round_info`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundInfo {
+    /// The round.
+    pub round: Round,
+    /// The authorities whose certificates of the round are stored, in ascending order.
+    pub certificates: Vec<AuthorityIndex>,
+    /// The authorities of the committee without a stored certificate of the round.
+    pub missing: Vec<AuthorityIndex>,
+    /// The authorities whose votes certified the header of the local primary in the round,
+    /// `None` if the node has no certificate of its own in the round.
+    pub local_votes: Option<Vec<AuthorityIndex>>,
+    /// The elected leader, `None` if the round has no leader or its commit rule didn't run yet.
+    pub leader: Option<AuthorityIndex>,
+    /// Whether the leader was committed, directly or through a later leader.
+    pub committed: bool,
+    /// Why the leader was or was not committed, if the commit rule ran for the round.
+    pub outcome: Option<CommitOutcome>,
+}
+
+/// The number of leader rounds whose commit decisions the [`ConsensusState`] keeps.
+pub const RECENT_DECISIONS: usize = 1_000;
+
+#[derive(Debug)]
+struct StateInner {
+    primary: Option<PrimaryHandle>,
+    last_committed: Option<CommittedSubDag>,
+    /// The commit decisions of the last [`RECENT_DECISIONS`] leader rounds.
+    decisions: CommitDecisionLog,
+}
+
+impl Default for StateInner {
+    fn default() -> Self {
+        Self {
+            primary: None,
+            last_committed: None,
+            decisions: CommitDecisionLog::new(RECENT_DECISIONS),
+        }
+    }
+}
+
+/// The view of the consensus tasks that the introspection methods of the namespace serve.
+///
+/// The tasks update it as they run: the primary of every epoch is registered with
+/// [`ConsensusState::set_primary`], and every commit is recorded with
+/// [`ConsensusState::record_commit`]. Commits, commit decisions and certificates are published
+/// as [`ConsensusEvent`]s to the subscribers of [`ConsensusState::subscribe_events`], and the
+/// certified checkpoints to the subscribers of [`ConsensusState::subscribe_checkpoints`].
+///
+/// Cloning is cheap, all clones share the same state.
+#[derive(Debug, Clone)]
+pub struct ConsensusState {
+    committees: Arc<dyn CommitteeProvider>,
+    /// The committees of past epochs, including the epochs before the node started.
+    history: Option<Arc<RwLock<dyn CommitteeHistoryProvider<Committee = Committee>>>>,
+    store: Arc<dyn DagStore>,
+    inner: Arc<RwLock<StateInner>>,
+    events: broadcast::Sender<ConsensusEvent>,
+    checkpoints: broadcast::Sender<CertifiedCheckpoint>,
+}
+
+impl ConsensusState {
+    /// Creates the state of the consensus tasks that use the given committees and store.
+    pub fn new(committees: Arc<dyn CommitteeProvider>, store: Arc<dyn DagStore>) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        let (checkpoints, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { committees, history: None, store, inner: Default::default(), events, checkpoints }
+    }
+
+    /// Serves the committees of past epochs from the given history, which the executor records.
+    pub fn with_committee_history(
+        mut self,
+        history: Arc<RwLock<dyn CommitteeHistoryProvider<Committee = Committee>>>,
+    ) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Registers the primary of the current epoch.
+    pub fn set_primary(&self, primary: PrimaryHandle) {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner).primary = Some(primary);
+    }
+
+    /// Records a committed sub-dag.
+    pub fn record_commit(&self, sub_dag: &OrderedSubDag) {
+        let committed = CommittedSubDag::from(sub_dag);
+        self.inner.write().unwrap_or_else(PoisonError::into_inner).last_committed =
+            Some(committed.clone());
+        self.publish(ConsensusEvent::SubDagCommitted(committed));
+    }
+
+    /// Records the commit decision of a leader round, replacing an earlier decision of the round.
+    pub fn record_decision(&self, decision: &CommitDecision) {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner).decisions.record(*decision);
+        self.publish(ConsensusEvent::LeaderDecision(*decision));
+    }
+
+    /// Records a certificate that was added to the DAG.
+    pub fn record_certificate(&self, certificate: &Certificate) {
+        self.publish(ConsensusEvent::certificate(certificate));
+    }
+
+    /// Records a checkpoint range once a quorum of the committee signed it, see
+    /// [`CheckpointRangeAggregator`](crate::checkpoint::CheckpointRangeAggregator).
+    pub fn record_checkpoint(&self, proof: &CheckpointRangeProof) {
+        // there may be no subscribers
+        let _ = self.checkpoints.send(proof.into());
+    }
+
+    /// Subscribes to the certified checkpoints.
+    ///
+    /// A receiver that falls more than [`EVENT_CHANNEL_CAPACITY`] checkpoints behind misses the
+    /// oldest ones.
+    pub fn subscribe_checkpoints(&self) -> broadcast::Receiver<CertifiedCheckpoint> {
+        self.checkpoints.subscribe()
+    }
+
+    /// Subscribes to the events that match the filter.
+    pub fn subscribe_events(&self, filter: ConsensusEventFilter) -> ConsensusEvents {
+        ConsensusEvents::new(self.events.subscribe(), filter)
+    }
+
+    /// Returns the current round of the primary, or 0 if no primary is running.
+    pub fn current_round(&self) -> Round {
+        self.read(|inner| inner.primary.as_ref().map_or(0, PrimaryHandle::current_round))
+    }
+
+    /// Returns the committee of the current epoch.
+    pub fn committee(&self) -> Arc<Committee> {
+        self.committees.current_committee()
+    }
+
+    /// Returns the committee of the given epoch, `None` if the epoch is neither in the committee
+    /// history nor one of the epochs since the node started.
+    pub fn epoch_committee(&self, epoch: Epoch) -> Option<Committee> {
+        let recorded = self.history.as_ref().and_then(|history| {
+            history.read().unwrap_or_else(PoisonError::into_inner).committee(epoch).cloned()
+        });
+        recorded.or_else(|| {
+            self.committees.committee(epoch).map(|committee| Committee::clone(&committee))
+        })
+    }
+
+    /// Returns the last committed sub-dag, if any was committed since the node started.
+    pub fn last_committed(&self) -> Option<CommittedSubDag> {
+        self.read(|inner| inner.last_committed.clone())
+    }
+
+    /// Returns the stored certificate with the given digest, if it's not pruned yet.
+    pub fn certificate(
+        &self,
+        digest: CertificateDigest,
+    ) -> Result<Option<Certificate>, DagStoreError> {
+        self.store.certificate(digest)
+    }
+
+    /// Returns at most `limit` entries of the commit audit log, starting at index `from_index`.
+    pub fn commit_log(
+        &self,
+        from_index: u64,
+        limit: usize,
+    ) -> Result<Vec<CommitAuditEntry>, DagStoreError> {
+        self.store.audit_entries(from_index, limit)
+    }
+
+    /// Returns the batches of the workers that are waiting to be referenced by a header of the
+    /// primary.
+    pub fn pending_batches(&self) -> Vec<BatchRef> {
+        self.read(|inner| inner.primary.as_ref().map(PrimaryHandle::pending_batches))
+            .unwrap_or_default()
+    }
+
+    /// Returns the commit decision of the given leader round, if it's one of the last
+    /// [`RECENT_DECISIONS`] leader rounds.
+    pub fn commit_decision(&self, round: Round) -> Option<CommitDecision> {
+        self.read(|inner| inner.decisions.get(round).copied())
+    }
+
+    /// Returns which authorities took part in a round, and whether its leader was committed.
+    ///
+    /// The certificates are read from the store, so a round below the GC round has none. The
+    /// commit decisions are kept for the last [`RECENT_DECISIONS`] leader rounds.
+    pub fn round_info(&self, round: Round) -> Result<RoundInfo, DagStoreError> {
+        let certificates = self.store.certificates(round)?;
+        let certificates =
+            certificates.iter().take_while(|certificate| certificate.round() == round);
+        let (author, decision) = self.read(|inner| {
+            (inner.primary.as_ref().map(PrimaryHandle::author), inner.decisions.get(round).copied())
+        });
+
+        let mut authors = Vec::new();
+        let mut local_votes = None;
+        for certificate in certificates {
+            authors.push(certificate.author());
+            if Some(certificate.author()) == author {
+                local_votes = Some(certificate.signers.clone());
+            }
+        }
+        authors.sort_unstable();
+        authors.dedup();
+        let committee = self.committee();
+        let missing = (0..committee.authorities.len() as AuthorityIndex)
+            .filter(|index| authors.binary_search(index).is_err())
+            .collect();
+        Ok(RoundInfo {
+            round,
+            certificates: authors,
+            missing,
+            local_votes,
+            leader: decision.map(|decision| decision.leader),
+            committed: decision.is_some_and(|decision| decision.outcome.is_committed()),
+            outcome: decision.map(|decision| decision.outcome),
+        })
+    }
+
+    fn publish(&self, event: ConsensusEvent) {
+        // there may be no subscribers
+        let _ = self.events.send(event);
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&StateInner) -> T) -> T {
+        f(&self.inner.read().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        committee::StaticCommitteeProvider,
+        committee_history::CommitteeHistory,
+        dag_store::MemoryDagStore,
+        dev::DevCommittee,
+        primary::{Primary, PrimaryConfig},
+        signature::BlsPublicKey,
+        types::{Batch, Header},
+    };
+    use alloy_primitives::{Bytes, B256};
+    use reth_narwhal_verifier::abi::CheckpointRange;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn record_commit() {
+        let committee = Committee { epoch: 2, authorities: Vec::new() };
+        let store = Arc::new(MemoryDagStore::default());
+        let state =
+            ConsensusState::new(Arc::new(StaticCommitteeProvider::new(committee)), store.clone());
+        assert_eq!(state.committee().epoch, 2);
+        assert_eq!((state.current_round(), state.last_committed()), (0, None));
+        assert!(state.pending_batches().is_empty());
+
+        let leader = Certificate {
+            header: Header { epoch: 2, round: 4, author: 1, ..Default::default() },
+            ..Default::default()
+        };
+        let batch = Batch::new(vec![Bytes::from_static(b"tx")]);
+        let sub_dag = OrderedSubDag {
+            index: 7,
+            leader: leader.clone(),
+            certificates: vec![leader.clone()],
+            batches: vec![batch.clone(), batch.clone()],
+            timestamp: 100,
+        };
+        state.record_commit(&sub_dag);
+        assert_eq!(
+            state.last_committed(),
+            Some(CommittedSubDag {
+                index: 7,
+                epoch: 2,
+                leader_round: 4,
+                leader: 1,
+                leader_digest: leader.digest(),
+                certificates: vec![leader.digest()],
+                batches: vec![batch.digest()],
+                timestamp: 100,
+            })
+        );
+
+        assert_eq!(state.certificate(leader.digest()).unwrap(), None);
+        store.write_certificate(&leader).unwrap();
+        assert_eq!(state.certificate(leader.digest()).unwrap(), Some(leader));
+    }
+
+    #[test]
+    fn epoch_committee() {
+        let committee = |epoch| Committee { epoch, authorities: Vec::new() };
+        let state = ConsensusState::new(
+            Arc::new(StaticCommitteeProvider::new(committee(2))),
+            Arc::new(MemoryDagStore::default()),
+        );
+        assert_eq!(state.epoch_committee(2), Some(committee(2)));
+        assert_eq!(state.epoch_committee(1), None);
+
+        // the history has the epochs before the node started
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = CommitteeHistory::open(dir.path()).unwrap();
+        history.insert(1, 1, committee(1)).unwrap();
+        let state = state.with_committee_history(Arc::new(RwLock::new(history)));
+        assert_eq!(state.epoch_committee(1), Some(committee(1)));
+        assert_eq!(state.epoch_committee(2), Some(committee(2)));
+        assert_eq!(state.epoch_committee(3), None);
+    }
+
+    #[test]
+    fn round_info() {
+        let dev = DevCommittee::new(4);
+        let store = Arc::new(MemoryDagStore::default());
+        let state = ConsensusState::new(
+            Arc::new(StaticCommitteeProvider::new(dev.committee().clone())),
+            store.clone(),
+        );
+        let certificate = |round, author, signers: &[AuthorityIndex]| Certificate {
+            header: Header { round, author, ..Default::default() },
+            signers: signers.to_vec(),
+            ..Default::default()
+        };
+        for author in [0, 2, 3] {
+            store.write_certificate(&certificate(2, author, &[0, 2, 3])).unwrap();
+        }
+        store.write_certificate(&certificate(3, 1, &[0, 1, 2])).unwrap();
+
+        // without a primary there are no local votes, and the commit rule didn't run
+        let info = state.round_info(2).unwrap();
+        assert_eq!((info.certificates, info.missing), (vec![0, 2, 3], vec![1]));
+        assert_eq!((info.local_votes, info.leader, info.committed), (None, None, false));
+
+        let verifier = dev.committee().verifier_committee::<BlsPublicKey>().unwrap();
+        let (_primary, handle) = Primary::new(
+            &verifier,
+            2,
+            PrimaryConfig::default(),
+            mpsc::channel(1).1,
+            mpsc::channel(1).1,
+            mpsc::channel(1).0,
+        );
+        state.set_primary(handle);
+        let outcome = CommitOutcome::InsufficientSupport { support: 1, threshold: 3 };
+        state.record_decision(&CommitDecision { round: 2, leader: 1, outcome });
+        let info = state.round_info(2).unwrap();
+        assert_eq!(info.local_votes, Some(vec![0, 2, 3]));
+        assert_eq!((info.leader, info.committed, info.outcome), (Some(1), false, Some(outcome)));
+
+        // the leader is committed indirectly later
+        let outcome = CommitOutcome::CommittedIndirectly { by_round: 4 };
+        state.record_decision(&CommitDecision { round: 2, leader: 1, outcome });
+        assert!(state.round_info(2).unwrap().committed);
+        assert_eq!(state.commit_decision(2), Some(CommitDecision { round: 2, leader: 1, outcome }));
+        assert_eq!(state.commit_decision(4), None);
+
+        let info = state.round_info(3).unwrap();
+        assert_eq!((info.certificates, info.local_votes, info.leader), (vec![1], None, None));
+        assert_eq!(state.round_info(5).unwrap().missing, vec![0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn subscribe_events() {
+        let committee = Committee { epoch: 0, authorities: Vec::new() };
+        let state = ConsensusState::new(
+            Arc::new(StaticCommitteeProvider::new(committee)),
+            Arc::new(MemoryDagStore::default()),
+        );
+        let mut all = state.subscribe_events(ConsensusEventFilter::default());
+        let mut commits = state.subscribe_events(ConsensusEventFilter {
+            only_leader_commits: true,
+            ..Default::default()
+        });
+
+        let certificate = Certificate {
+            header: Header { round: 3, author: 2, ..Default::default() },
+            ..Default::default()
+        };
+        state.record_certificate(&certificate);
+        let decision = CommitDecision {
+            round: 2,
+            leader: 0,
+            outcome: crate::commit_log::CommitOutcome::MissingCertificate,
+        };
+        state.record_decision(&decision);
+        let sub_dag = OrderedSubDag {
+            index: 0,
+            leader: certificate.clone(),
+            certificates: vec![certificate.clone()],
+            batches: Vec::new(),
+            timestamp: 1,
+        };
+        state.record_commit(&sub_dag);
+
+        assert_eq!(all.recv().await, Some(ConsensusEvent::certificate(&certificate)));
+        assert_eq!(all.recv().await, Some(ConsensusEvent::LeaderDecision(decision)));
+        let committed = ConsensusEvent::SubDagCommitted(CommittedSubDag::from(&sub_dag));
+        assert_eq!(all.recv().await, Some(committed.clone()));
+        assert_eq!(commits.recv().await, Some(committed));
+
+        drop(state);
+        assert_eq!(commits.recv().await, None);
+        assert_eq!(commits.missed(), 0);
+    }
+
+    #[tokio::test]
+    async fn subscribe_checkpoints() {
+        let committee = Committee { epoch: 0, authorities: Vec::new() };
+        let state = ConsensusState::new(
+            Arc::new(StaticCommitteeProvider::new(committee)),
+            Arc::new(MemoryDagStore::default()),
+        );
+        let mut checkpoints = state.subscribe_checkpoints();
+
+        let range = CheckpointRange {
+            epoch: 1,
+            firstBlock: 10,
+            lastBlock: 19,
+            blocksRoot: B256::with_last_byte(1),
+            stateRoot: B256::with_last_byte(2),
+        };
+        let proof = CheckpointRangeProof::new(range, [0, 2], Bytes::from_static(&[7; 96])).unwrap();
+        state.record_checkpoint(&proof);
+
+        let checkpoint = checkpoints.recv().await.unwrap();
+        assert_eq!((checkpoint.epoch, checkpoint.first_block, checkpoint.last_block), (1, 10, 19));
+        assert_eq!(CheckpointRangeProof::decode(&checkpoint.proof).unwrap(), proof);
+    }
+}
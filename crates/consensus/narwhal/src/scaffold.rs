@@ -0,0 +1,499 @@
+//! Scaffolding of new narwhal chains.
+//!
+//! A [`ChainManifest`] describes an appchain: its chain id, the number of validators and workers,
+//! where the validators run, and the accounts and [`Predeploy`]s of its genesis. A [`Scaffold`]
+//! generated from it holds everything the validators need to start the chain, and
+//! [`Scaffold::write`] lays it out in a directory:
+//!
+//! ```text
+//! genesis.json
+//! committee.toml
+//! validator-0/
+//!     keystore.json   the keys of the validator, encrypted with the password
+//!     password.txt    the password of the keystore
+//!     narwhal.toml    the node-local narwhal configuration
+//!     reth.args       the command line arguments of the node, relative to the directory
+//! validator-1/
+//!     ...
+//! ```
+//!
+//! Validators on the same host get consecutive ports from [`ChainManifest::base_port`] on, and
+//! distinct reth ports with `--instance`. Validators on their own hosts all use the same ports.
+
+use crate::{
+    committee::{Authority, Committee, CommitteeError},
+    config::NarwhalConfig,
+    keys::{AuthorityKeys, KeyProvider, Keystore, KeystoreError, DEFAULT_KDF_ITERATIONS},
+    predeploys::Predeploy,
+    worker::SubmissionConfig,
+};
+use alloy_primitives::{hex, Address, Bytes, U256};
+use reth_narwhal_verifier::Stake;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "execution")]
+use reth_primitives::{ChainConfig, Genesis, GenesisAccount};
+
+/// The name of the genesis file of a scaffold.
+pub const GENESIS_FILE: &str = "genesis.json";
+
+/// The name of the committee file of a scaffold.
+pub const COMMITTEE_FILE: &str = "committee.toml";
+
+/// The name of the keystore file of a validator of a scaffold.
+pub const KEYSTORE_FILE: &str = "keystore.json";
+
+/// The name of the keystore password file of a validator of a scaffold.
+pub const PASSWORD_FILE: &str = "password.txt";
+
+/// The name of the narwhal configuration file of a validator of a scaffold.
+pub const CONFIG_FILE: &str = "narwhal.toml";
+
+/// The name of the file with the node arguments of a validator of a scaffold.
+pub const ARGS_FILE: &str = "reth.args";
+
+/// Errors when generating or writing a [`Scaffold`].
+#[derive(Debug, thiserror::Error)]
+pub enum ScaffoldError {
+    /// The manifest describes a chain that can't be started.
+    #[error("invalid chain manifest: {0}")]
+    InvalidManifest(String),
+    /// The generated committee is invalid.
+    #[error(transparent)]
+    Committee(#[from] CommitteeError),
+    /// A keystore couldn't be written.
+    #[error(transparent)]
+    Keystore(#[from] KeystoreError),
+    /// A file couldn't be written.
+    #[error("failed to write {path}: {err}")]
+    Write {
+        /// The path of the file.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        err: io::Error,
+    },
+    /// A file couldn't be serialized.
+    #[error("failed to serialize {path}: {message}")]
+    Serialize {
+        /// The path of the file.
+        path: PathBuf,
+        /// The serializer error.
+        message: String,
+    },
+}
+
+/// The description of a new chain, read from a TOML or JSON file, e.g.
+///
+/// ```toml
+/// chainId = 1337
+/// validators = 4
+/// workers = 1
+/// hosts = ["10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.4"]
+///
+/// [alloc]
+/// "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266" = "0xd3c21bcecceda1000000"
+///
+/// [narwhal]
+/// nonceGapPolicy = "defer"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ChainManifest {
+    /// The chain id.
+    pub chain_id: u64,
+    /// The number of validators.
+    pub validators: usize,
+    /// The number of workers of every validator.
+    pub workers: usize,
+    /// The stake of every validator.
+    pub stake: Stake,
+    /// The host of every validator, all validators run on the local host if empty.
+    pub hosts: Vec<IpAddr>,
+    /// The port of the first primary, followed by the ports of its workers and of their
+    /// transaction submission services.
+    pub base_port: u16,
+    /// The gas limit of the genesis block.
+    pub gas_limit: u64,
+    /// The balances of the genesis accounts.
+    pub alloc: BTreeMap<Address, U256>,
+    /// The system contracts of the genesis, see [`crate::predeploys`].
+    pub predeploys: Vec<Predeploy>,
+    /// The `narwhal` section of the genesis config, see
+    /// [`NarwhalChainInfo`](crate::NarwhalChainInfo).
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    pub narwhal: serde_json::Value,
+}
+
+impl Default for ChainManifest {
+    fn default() -> Self {
+        Self {
+            chain_id: 1337,
+            validators: 4,
+            workers: 1,
+            stake: 1,
+            hosts: Vec::new(),
+            base_port: 30400,
+            gas_limit: 30_000_000,
+            alloc: BTreeMap::new(),
+            predeploys: Vec::new(),
+            narwhal: serde_json::Value::Null,
+        }
+    }
+}
+
+impl ChainManifest {
+    /// Loads the manifest from a file.
+    ///
+    /// Files with a `.toml` extension are parsed as TOML, all others as JSON.
+    pub fn load(path: &Path) -> Result<Self, CommitteeError> {
+        crate::committee::load_file(path)
+    }
+
+    /// Returns the number of ports every validator uses on its host: one for the primary, and two
+    /// for every worker.
+    pub const fn ports_per_validator(&self) -> usize {
+        1 + 2 * self.workers
+    }
+
+    /// Returns the addresses of the primary, the workers and the first transaction submission
+    /// service of a validator.
+    fn addresses(
+        &self,
+        validator: usize,
+    ) -> Result<(SocketAddr, Vec<SocketAddr>, SocketAddr), ScaffoldError> {
+        let (host, first) = match self.hosts.get(validator) {
+            Some(host) => (*host, self.base_port as usize),
+            None => (
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                self.base_port as usize + validator * self.ports_per_validator(),
+            ),
+        };
+        let port = |offset: usize| {
+            u16::try_from(first + offset).map(|port| SocketAddr::new(host, port)).map_err(|_| {
+                ScaffoldError::InvalidManifest(format!(
+                    "the ports of validator {validator} exceed the port range"
+                ))
+            })
+        };
+        let workers = (1..=self.workers).map(port).collect::<Result<Vec<_>, _>>()?;
+        Ok((port(0)?, workers, port(self.workers + 1)?))
+    }
+
+    /// Returns the genesis of the chain.
+    ///
+    /// All hardforks up to Cancun are active from the genesis on, and the `narwhal` section is
+    /// checked to be valid.
+    #[cfg(feature = "execution")]
+    pub fn genesis(&self) -> Result<Genesis, ScaffoldError> {
+        let mut config = ChainConfig {
+            chain_id: self.chain_id,
+            homestead_block: Some(0),
+            eip150_block: Some(0),
+            eip155_block: Some(0),
+            eip158_block: Some(0),
+            byzantium_block: Some(0),
+            constantinople_block: Some(0),
+            petersburg_block: Some(0),
+            istanbul_block: Some(0),
+            berlin_block: Some(0),
+            london_block: Some(0),
+            terminal_total_difficulty: Some(U256::ZERO),
+            terminal_total_difficulty_passed: true,
+            shanghai_time: Some(0),
+            cancun_time: Some(0),
+            ..Default::default()
+        };
+        if !self.narwhal.is_null() {
+            serde_json::from_value::<crate::NarwhalChainInfo>(self.narwhal.clone()).map_err(
+                |err| ScaffoldError::InvalidManifest(format!("invalid narwhal section: {err}")),
+            )?;
+            config
+                .extra_fields
+                .insert(crate::NARWHAL_GENESIS_KEY.to_string(), self.narwhal.clone());
+        }
+
+        let genesis =
+            Genesis {
+                config,
+                gas_limit: self.gas_limit.into(),
+                base_fee_per_gas: Some(1_000_000_000),
+                ..Default::default()
+            }
+            .extend_accounts(self.alloc.iter().map(|(address, balance)| {
+                (*address, GenesisAccount::default().with_balance(*balance))
+            }));
+        Ok(crate::predeploys::with_predeploys(genesis, self.predeploys.iter().cloned()))
+    }
+}
+
+/// The keys and configuration of a validator of a [`Scaffold`].
+#[derive(Debug)]
+pub struct ValidatorBundle {
+    /// The keys of the validator.
+    pub keys: AuthorityKeys,
+    /// The password the keystore of the validator is encrypted with.
+    pub password: String,
+    /// The node-local narwhal configuration of the validator.
+    pub config: NarwhalConfig,
+    /// The reth instance of the validator, which offsets the reth ports of validators on the same
+    /// host, `None` if the validator runs on its own host.
+    pub instance: Option<u16>,
+}
+
+/// The files of a new chain, generated from a [`ChainManifest`].
+#[derive(Debug)]
+pub struct Scaffold {
+    /// The committee of the first epoch.
+    pub committee: Committee,
+    /// The validators, in the order of the committee.
+    pub validators: Vec<ValidatorBundle>,
+    kdf_iterations: u32,
+}
+
+impl Scaffold {
+    /// Generates new keys for every validator of the manifest, and the committee of the first
+    /// epoch.
+    pub fn generate(manifest: &ChainManifest) -> Result<Self, ScaffoldError> {
+        if manifest.validators == 0 || manifest.workers == 0 {
+            return Err(ScaffoldError::InvalidManifest(
+                "a chain needs at least one validator with one worker".to_string(),
+            ))
+        }
+        if !manifest.hosts.is_empty() && manifest.hosts.len() != manifest.validators {
+            return Err(ScaffoldError::InvalidManifest(format!(
+                "{} hosts for {} validators",
+                manifest.hosts.len(),
+                manifest.validators
+            )))
+        }
+
+        let mut authorities = Vec::with_capacity(manifest.validators);
+        let mut validators = Vec::with_capacity(manifest.validators);
+        for validator in 0..manifest.validators {
+            let (primary_address, worker_addresses, submission) = manifest.addresses(validator)?;
+            let keys = AuthorityKeys::generate(manifest.workers);
+            authorities.push(Authority {
+                public_key: Bytes::copy_from_slice(&keys.authority_public_key().to_bytes()),
+                proof_of_possession: Bytes::copy_from_slice(
+                    &keys.authority_key().proof_of_possession().to_bytes(),
+                ),
+                stake: manifest.stake,
+                primary_address,
+                worker_addresses,
+            });
+            let config = NarwhalConfig {
+                submission: SubmissionConfig { addr: Some(submission), ..Default::default() },
+                ..Default::default()
+            };
+            validators.push(ValidatorBundle {
+                keys,
+                password: hex::encode(rand::random::<[u8; 16]>()),
+                config,
+                // instances start at 1
+                instance: manifest.hosts.is_empty().then(|| validator as u16 + 1),
+            });
+        }
+        let committee = Committee { epoch: 0, authorities };
+        committee.validate()?;
+        Ok(Self { committee, validators, kdf_iterations: DEFAULT_KDF_ITERATIONS })
+    }
+
+    /// Sets the number of PBKDF2 iterations the keystores are encrypted with.
+    pub const fn with_kdf_iterations(mut self, iterations: u32) -> Self {
+        self.kdf_iterations = iterations;
+        self
+    }
+
+    /// Returns the directory of a validator in the output directory.
+    pub fn validator_dir(dir: &Path, validator: usize) -> PathBuf {
+        dir.join(format!("validator-{validator}"))
+    }
+
+    /// Returns the narwhal arguments of the node of a validator, with the paths of the files of a
+    /// scaffold written into the directory.
+    pub fn validator_args(dir: &Path, validator: usize, workers: usize) -> Vec<String> {
+        let validator_dir = Self::validator_dir(dir, validator);
+        let path = |path: PathBuf| path.display().to_string();
+        vec![
+            "--chain".to_string(),
+            path(dir.join(GENESIS_FILE)),
+            "--narwhal.committee-file".to_string(),
+            path(dir.join(COMMITTEE_FILE)),
+            "--narwhal.keystore".to_string(),
+            path(validator_dir.join(KEYSTORE_FILE)),
+            "--narwhal.keystore-password-file".to_string(),
+            path(validator_dir.join(PASSWORD_FILE)),
+            "--narwhal.config".to_string(),
+            path(validator_dir.join(CONFIG_FILE)),
+            "--narwhal.worker-count".to_string(),
+            workers.to_string(),
+        ]
+    }
+
+    /// Writes the committee and the files of every validator into the directory, see the
+    /// [module docs](self).
+    ///
+    /// The genesis is written separately, see [`ChainManifest::genesis`]. Fails if a keystore
+    /// already exists, keys are never overwritten.
+    pub fn write(&self, dir: &Path) -> Result<(), ScaffoldError> {
+        write_file(
+            &dir.join(COMMITTEE_FILE),
+            &to_toml(&dir.join(COMMITTEE_FILE), &self.committee)?,
+        )?;
+        for (index, validator) in self.validators.iter().enumerate() {
+            let validator_dir = Self::validator_dir(dir, index);
+            let relative_dir = Self::validator_dir(Path::new(""), index);
+            Keystore::new(validator_dir.join(KEYSTORE_FILE))
+                .with_iterations(self.kdf_iterations)
+                .create(&validator.keys, validator.password.as_bytes())?;
+            write_file(&validator_dir.join(PASSWORD_FILE), &validator.password)?;
+            let config_path = validator_dir.join(CONFIG_FILE);
+            write_file(&config_path, &to_toml(&config_path, &validator.config)?)?;
+
+            let mut args = Self::validator_args(Path::new(""), index, validator.keys.workers());
+            args.extend(["--datadir".to_string(), relative_dir.join("data").display().to_string()]);
+            if let Some(instance) = validator.instance {
+                args.extend(["--instance".to_string(), instance.to_string()]);
+            }
+            let args = args.chunks(2).map(|arg| arg.join(" ") + "\n").collect::<String>();
+            write_file(&validator_dir.join(ARGS_FILE), &args)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes a value of a file as TOML.
+fn to_toml<T: Serialize>(path: &Path, value: &T) -> Result<String, ScaffoldError> {
+    toml::to_string(value).map_err(|err| ScaffoldError::Serialize {
+        path: path.to_path_buf(),
+        message: err.to_string(),
+    })
+}
+
+/// Writes a new file that only its owner can read, creating its directory if needed.
+fn write_file(path: &Path, contents: &str) -> Result<(), ScaffoldError> {
+    let write = || {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        io::Write::write_all(&mut options.open(path)?, contents.as_bytes())
+    };
+    write().map_err(|err| ScaffoldError::Write { path: path.to_path_buf(), err })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_validators_get_distinct_ports() {
+        let manifest = ChainManifest { validators: 3, workers: 2, ..Default::default() };
+        let scaffold = Scaffold::generate(&manifest).unwrap();
+        let authorities = &scaffold.committee.authorities;
+        assert_eq!(authorities.len(), 3);
+        assert_eq!(authorities[0].primary_address.port(), 30400);
+        assert_eq!(authorities[0].worker_addresses.len(), 2);
+        assert_eq!(authorities[1].primary_address.port(), 30405);
+        assert_eq!(scaffold.validators[1].config.submission.addr.unwrap().port(), 30405 + 3);
+        assert_eq!(scaffold.validators[2].instance, Some(3));
+    }
+
+    #[test]
+    fn validators_on_their_own_hosts() {
+        let hosts = vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()];
+        let manifest = ChainManifest { validators: 2, hosts, ..Default::default() };
+        let scaffold = Scaffold::generate(&manifest).unwrap();
+        let second = &scaffold.committee.authorities[1];
+        assert_eq!(second.primary_address, "10.0.0.2:30400".parse().unwrap());
+        assert_eq!(scaffold.validators[1].instance, None);
+
+        let manifest = ChainManifest { validators: 3, ..manifest };
+        assert!(matches!(Scaffold::generate(&manifest), Err(ScaffoldError::InvalidManifest(_))));
+    }
+
+    #[test]
+    fn write_scaffold() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = ChainManifest { validators: 2, ..Default::default() };
+        let scaffold = Scaffold::generate(&manifest).unwrap().with_kdf_iterations(1);
+        scaffold.write(dir.path()).unwrap();
+
+        let committee = Committee::load(&dir.path().join(COMMITTEE_FILE)).unwrap();
+        assert_eq!(committee, scaffold.committee);
+        let validator_dir = Scaffold::validator_dir(dir.path(), 1);
+        let password = fs::read_to_string(validator_dir.join("password.txt")).unwrap();
+        let keys = Keystore::new(validator_dir.join("keystore.json")).load(password.as_bytes());
+        assert_eq!(
+            keys.unwrap().authority_public_key().to_bytes().as_slice(),
+            committee.authorities[1].public_key.as_ref()
+        );
+        let config: NarwhalConfig =
+            toml::from_str(&fs::read_to_string(validator_dir.join("narwhal.toml")).unwrap())
+                .unwrap();
+        assert_eq!(config, scaffold.validators[1].config);
+        let args = fs::read_to_string(validator_dir.join("reth.args")).unwrap();
+        assert!(args.contains("--narwhal.committee-file committee.toml\n"));
+        assert!(args.contains("--instance 2\n"));
+
+        // keys are never overwritten
+        assert!(scaffold.write(dir.path()).is_err());
+    }
+
+    #[test]
+    fn parse_manifest() {
+        let manifest: ChainManifest = toml::from_str(
+            r#"
+            chainId = 42
+            validators = 2
+            hosts = ["10.0.0.1", "10.0.0.2"]
+
+            [alloc]
+            "0x0000000000000000000000000000000000000001" = "0x10"
+
+            [narwhal]
+            nonceGapPolicy = "drop"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(manifest.chain_id, 42);
+        assert_eq!(manifest.workers, 1);
+        assert_eq!(manifest.alloc[&Address::with_last_byte(1)], U256::from(16));
+        assert_eq!(manifest.narwhal["nonceGapPolicy"], "drop");
+    }
+
+    #[cfg(feature = "execution")]
+    #[test]
+    fn manifest_genesis() {
+        let manifest = ChainManifest {
+            alloc: BTreeMap::from([(Address::with_last_byte(1), U256::from(16))]),
+            predeploys: vec![Predeploy::new(
+                crate::predeploys::BRIDGE,
+                Bytes::from_static(&[0x00]),
+            )],
+            narwhal: serde_json::json!({ "nonceGapPolicy": "drop" }),
+            ..Default::default()
+        };
+        let genesis = manifest.genesis().unwrap();
+        assert_eq!(genesis.config.chain_id, 1337);
+        assert_eq!(genesis.alloc.len(), 2);
+        let info = crate::NarwhalChainInfo::from_genesis(&genesis).unwrap();
+        assert_eq!(info.nonce_gap_policy, crate::NonceGapPolicy::Drop);
+
+        let manifest = ChainManifest {
+            narwhal: serde_json::json!({ "nonceGapPolicy": "never" }),
+            ..Default::default()
+        };
+        assert!(matches!(manifest.genesis(), Err(ScaffoldError::InvalidManifest(_))));
+    }
+}
@@ -0,0 +1,339 @@
+//! A startup self-check of the hardware of a validator.
+//!
+//! A validator that can't keep up with the committee slows down the rounds of every authority
+//! that waits for its certificates, and falls behind on execution. The self-check measures the
+//! operations that bound a validator on a short sample: the throughput of verifying the aggregate
+//! signatures of certificates, the latency of syncing writes of the DAG store to disk, and the
+//! time to compute the state root of a block. It warns about every measurement that falls short
+//! of what the configured committee and target throughput require.
+//!
+//! The requirements are estimates with twice the headroom of the expected load:
+//!
+//! - every round, an authority verifies the certificate and a vote of every other authority;
+//! - every round, an authority syncs its own header and the certificates of the round to disk;
+//! - every other round, a sub-dag is committed and executed as a block, whose state root covers the
+//!   accounts of its transactions.
+
+use crate::{
+    primary::PrimaryConfig,
+    signature::{AggregateScheme, Bls12381, BlsSecretKey},
+};
+use reth_metrics::{metrics::Gauge, Metrics};
+use reth_narwhal_verifier::SignatureScheme;
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// The default number of transactions per second the self-check expects the node to sustain.
+pub const DEFAULT_TARGET_TPS: u64 = 1_000;
+
+/// The factor by which every measurement must exceed the expected load.
+const HEADROOM: f64 = 2.0;
+
+/// The number of verifications of the signature sample.
+const SIGNATURE_SAMPLES: u32 = 32;
+
+/// The number of synced writes of the fsync sample.
+const FSYNC_SAMPLES: u32 = 16;
+
+/// The size of every synced write, about the size of an encoded certificate.
+const FSYNC_WRITE_BYTES: usize = 4096;
+
+/// The name of the file the fsync sample writes to, which is removed afterwards.
+const FSYNC_FILE: &str = ".narwhal-self-check";
+
+/// The maximum number of signers of the aggregate signature that is verified, bounding the time
+/// to generate the keys for large committees.
+const MAX_SAMPLE_SIGNERS: usize = 64;
+
+/// The load the self-check expects the node to sustain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfCheckTargets {
+    /// The number of authorities of the committee.
+    pub committee_size: usize,
+    /// The number of rounds the committee advances per second.
+    pub rounds_per_second: f64,
+    /// The number of transactions executed per second.
+    pub transactions_per_second: u64,
+}
+
+impl SelfCheckTargets {
+    /// Returns the targets of a committee whose primaries advance at most once per
+    /// `max_header_delay`.
+    pub fn new(
+        committee_size: usize,
+        primary: &PrimaryConfig,
+        transactions_per_second: u64,
+    ) -> Self {
+        let rounds_per_second = 1.0 / primary.max_header_delay.as_secs_f64().max(0.001);
+        Self { committee_size, rounds_per_second, transactions_per_second }
+    }
+
+    /// Returns the number of aggregate signatures the node must verify per second.
+    pub fn required_verifications_per_second(&self) -> f64 {
+        // a certificate and a vote of every authority per round
+        2.0 * self.committee_size as f64 * self.rounds_per_second * HEADROOM
+    }
+
+    /// Returns the maximum latency of a synced write.
+    pub fn max_fsync_latency(&self) -> Duration {
+        // the own header and the certificates of the round
+        let writes_per_second = (self.committee_size + 1) as f64 * self.rounds_per_second;
+        Duration::from_secs_f64(1.0 / (writes_per_second * HEADROOM))
+    }
+
+    /// Returns the maximum time to compute the state root of a block.
+    pub fn max_state_root_latency(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / (self.blocks_per_second() * HEADROOM))
+    }
+
+    /// Returns the number of accounts of the state root sample, the accounts changed by the
+    /// transactions of a block.
+    pub fn state_root_accounts(&self) -> usize {
+        // the sender and the recipient of every transaction
+        (2.0 * self.transactions_per_second as f64 / self.blocks_per_second()).ceil() as usize
+    }
+
+    /// Returns the number of blocks per second, a sub-dag is committed every other round.
+    fn blocks_per_second(&self) -> f64 {
+        self.rounds_per_second / 2.0
+    }
+}
+
+/// The measurements of the self-check.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfCheckReport {
+    /// The number of aggregate signatures of a quorum verified per second.
+    pub verifications_per_second: f64,
+    /// The mean latency of a synced write to the data directory.
+    pub fsync_latency: Duration,
+    /// The time to compute the state root of the accounts of a block, `None` without the
+    /// `execution` feature.
+    pub state_root_latency: Option<Duration>,
+}
+
+impl SelfCheckReport {
+    /// Returns the measurements that fall short of the targets.
+    pub fn warnings(&self, targets: &SelfCheckTargets) -> Vec<SelfCheckWarning> {
+        let mut warnings = Vec::new();
+        let required = targets.required_verifications_per_second();
+        if self.verifications_per_second < required {
+            warnings.push(SelfCheckWarning::SlowSignatureVerification {
+                measured: self.verifications_per_second,
+                required,
+            });
+        }
+        let max = targets.max_fsync_latency();
+        if self.fsync_latency > max {
+            warnings.push(SelfCheckWarning::SlowFsync { measured: self.fsync_latency, max });
+        }
+        let max = targets.max_state_root_latency();
+        if let Some(measured) = self.state_root_latency.filter(|latency| *latency > max) {
+            warnings.push(SelfCheckWarning::SlowStateRoot { measured, max });
+        }
+        warnings
+    }
+
+    fn record_metrics(&self) {
+        let metrics = SelfCheckMetrics::default();
+        metrics.verifications_per_second.set(self.verifications_per_second);
+        metrics.fsync_latency.set(self.fsync_latency.as_secs_f64());
+        if let Some(latency) = self.state_root_latency {
+            metrics.state_root_latency.set(latency.as_secs_f64());
+        }
+    }
+}
+
+/// A measurement of the self-check that falls short of the targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelfCheckWarning {
+    /// Signatures are verified too slowly.
+    SlowSignatureVerification {
+        /// The measured verifications per second.
+        measured: f64,
+        /// The required verifications per second.
+        required: f64,
+    },
+    /// Writes are synced to disk too slowly.
+    SlowFsync {
+        /// The measured latency.
+        measured: Duration,
+        /// The maximum latency.
+        max: Duration,
+    },
+    /// State roots are computed too slowly.
+    SlowStateRoot {
+        /// The measured latency.
+        measured: Duration,
+        /// The maximum latency.
+        max: Duration,
+    },
+}
+
+impl fmt::Display for SelfCheckWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlowSignatureVerification { measured, required } => write!(
+                f,
+                "verified {measured:.0} signatures per second, {required:.0} are recommended"
+            ),
+            Self::SlowFsync { measured, max } => {
+                write!(f, "synced writes took {measured:?}, at most {max:?} is recommended")
+            }
+            Self::SlowStateRoot { measured, max } => {
+                write!(f, "state root of a block took {measured:?}, at most {max:?} is recommended")
+            }
+        }
+    }
+}
+
+/// Metrics of the last self-check.
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.self_check")]
+struct SelfCheckMetrics {
+    /// Number of aggregate signatures of a quorum verified per second
+    verifications_per_second: Gauge,
+    /// Mean latency of a synced write to the data directory in seconds
+    fsync_latency: Gauge,
+    /// Time to compute the state root of the accounts of a block in seconds
+    state_root_latency: Gauge,
+}
+
+/// Runs the self-check, syncing writes to a temporary file in `data_dir`.
+///
+/// The measurements are recorded as metrics, and every warning is logged. The self-check takes a
+/// fraction of a second and blocks the thread, so it should run before the node starts its tasks.
+pub fn run_self_check(data_dir: &Path, targets: &SelfCheckTargets) -> io::Result<SelfCheckReport> {
+    let report = SelfCheckReport {
+        verifications_per_second: measure_verifications(targets.committee_size),
+        fsync_latency: measure_fsync(data_dir)?,
+        state_root_latency: measure_state_root(targets.state_root_accounts()),
+    };
+    report.record_metrics();
+    info!(
+        target: "consensus::narwhal",
+        verifications_per_second = report.verifications_per_second,
+        fsync_latency = ?report.fsync_latency,
+        state_root_latency = ?report.state_root_latency,
+        "Finished hardware self-check"
+    );
+    for warning in report.warnings(targets) {
+        warn!(
+            target: "consensus::narwhal",
+            committee_size = targets.committee_size,
+            transactions_per_second = targets.transactions_per_second,
+            "Machine is below the recommended hardware: {warning}"
+        );
+    }
+    Ok(report)
+}
+
+/// Measures the number of aggregate signatures of a quorum of the committee verified per second.
+fn measure_verifications(committee_size: usize) -> f64 {
+    let signers = (committee_size * 2 / 3 + 1).min(MAX_SAMPLE_SIGNERS);
+    let secret_keys = (0..signers)
+        .map(|index| {
+            let seed = [index as u8; 32];
+            BlsSecretKey::from_seed(&seed).expect("seed is long enough")
+        })
+        .collect::<Vec<_>>();
+    let public_keys = secret_keys.iter().map(Bls12381::public_key).collect::<Vec<_>>();
+    let signatures =
+        secret_keys.iter().map(|key| Bls12381::sign(key, b"self-check")).collect::<Vec<_>>();
+    let signature = Bls12381::aggregate(&signatures).expect("at least one signer");
+    let signers = public_keys.iter().collect::<Vec<_>>();
+
+    let start = Instant::now();
+    for _ in 0..SIGNATURE_SAMPLES {
+        assert!(Bls12381::verify_aggregate(&signers, b"self-check", &signature));
+    }
+    SIGNATURE_SAMPLES as f64 / start.elapsed().as_secs_f64()
+}
+
+/// Measures the mean latency of a synced write to a file in the directory.
+fn measure_fsync(dir: &Path) -> io::Result<Duration> {
+    let path = dir.join(FSYNC_FILE);
+    let mut file = OpenOptions::new().create(true).write(true).truncate(true).open(&path)?;
+    let data = [0xAB; FSYNC_WRITE_BYTES];
+    let start = Instant::now();
+    let result = (0..FSYNC_SAMPLES).try_for_each(|_| {
+        file.write_all(&data)?;
+        file.sync_data()
+    });
+    let elapsed = start.elapsed();
+    drop(file);
+    fs::remove_file(&path)?;
+    result.map(|()| elapsed / FSYNC_SAMPLES)
+}
+
+/// Measures the time to compute the state root of the given number of accounts.
+#[cfg(feature = "execution")]
+fn measure_state_root(accounts: usize) -> Option<Duration> {
+    use alloy_primitives::{keccak256, Address, U256};
+    use reth_primitives::Account;
+    use reth_trie::{root::state_root_unhashed, EMPTY_ROOT_HASH};
+
+    let accounts = (0..accounts as u64)
+        .map(|index| {
+            let address = Address::from_word(keccak256(index.to_be_bytes()));
+            let account = Account { nonce: index, balance: U256::from(index), bytecode_hash: None };
+            (address, (account, EMPTY_ROOT_HASH))
+        })
+        .collect::<Vec<_>>();
+    let start = Instant::now();
+    state_root_unhashed(accounts);
+    Some(start.elapsed())
+}
+
+/// Measures the time to compute the state root of the given number of accounts.
+#[cfg(not(feature = "execution"))]
+const fn measure_state_root(_accounts: usize) -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_of_committee() {
+        let primary =
+            PrimaryConfig { max_header_delay: Duration::from_millis(500), ..Default::default() };
+        let targets = SelfCheckTargets::new(4, &primary, 1_000);
+        assert_eq!(targets.rounds_per_second, 2.0);
+        assert_eq!(targets.required_verifications_per_second(), 32.0);
+        assert_eq!(targets.max_fsync_latency(), Duration::from_millis(50));
+        assert_eq!(targets.max_state_root_latency(), Duration::from_millis(500));
+        assert_eq!(targets.state_root_accounts(), 2_000);
+
+        let report = SelfCheckReport {
+            verifications_per_second: 20.0,
+            fsync_latency: Duration::from_millis(10),
+            state_root_latency: Some(Duration::from_secs(1)),
+        };
+        assert_eq!(
+            report.warnings(&targets),
+            vec![
+                SelfCheckWarning::SlowSignatureVerification { measured: 20.0, required: 32.0 },
+                SelfCheckWarning::SlowStateRoot {
+                    measured: Duration::from_secs(1),
+                    max: Duration::from_millis(500)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn measure() {
+        let dir = tempfile::tempdir().unwrap();
+        let targets = SelfCheckTargets::new(4, &PrimaryConfig::default(), 10);
+        let report = run_self_check(dir.path(), &targets).unwrap();
+        assert!(report.verifications_per_second > 0.0);
+        assert!(!dir.path().join(FSYNC_FILE).exists());
+    }
+}
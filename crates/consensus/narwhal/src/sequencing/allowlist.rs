@@ -0,0 +1,301 @@
+//! Allowlist of transaction senders of permissioned chains.
+//!
+//! Permissioned chains only sequence transactions of senders registered in the
+//! [`SENDER_REGISTRY`] predeploy. The registry emits [`SenderAllowed`] and [`SenderRevoked`]
+//! events, from which every node maintains a [`SenderAllowlist`] in memory. Changes made in a
+//! block take effect from the next block, so the allowlist a block is checked against only
+//! depends on its ancestors.
+//!
+//! The executor applies the blocks it executes, and an allowlist with an [`AllowlistSource`]
+//! catches up with the canonical chain from the receipts of the database, e.g. after a restart.
+
+use super::{FilterRejection, SequencingFilter};
+use crate::predeploys::SENDER_REGISTRY;
+use alloy_sol_types::{sol, SolEvent};
+use parking_lot::RwLock;
+use reth_primitives::{Address, BlockNumber, Receipt, TransactionSigned};
+use reth_provider::{ProviderError, ProviderResult, ReceiptProvider};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt, sync::Arc};
+
+sol! {
+    /// Emitted by the sender registry when a sender is added to the allowlist.
+    #[derive(Debug)]
+    event SenderAllowed(address indexed sender);
+
+    /// Emitted by the sender registry when a sender is removed from the allowlist.
+    #[derive(Debug)]
+    event SenderRevoked(address indexed sender);
+}
+
+/// Configuration of the permissioned mode, part of the chain specification.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PermissionedConfig {
+    /// The first block that only includes transactions of allowed senders.
+    pub from_block: BlockNumber,
+    /// The senders allowed in the genesis state of the registry.
+    pub genesis_senders: HashSet<Address>,
+}
+
+/// Error returned if blocks are applied to a [`SenderAllowlist`] out of order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("allowlist is synced to block {synced}, cannot apply block {block}")]
+pub struct AllowlistOutOfSync {
+    /// The last block applied to the allowlist.
+    pub synced: BlockNumber,
+    /// The block that was applied.
+    pub block: BlockNumber,
+}
+
+/// Error returned if a [`SenderAllowlist`] can't catch up with the canonical chain.
+#[derive(Debug, thiserror::Error)]
+pub enum AllowlistSyncError {
+    /// Blocks are missing and the allowlist has no source to read them from, or a block was
+    /// applied out of order.
+    #[error(transparent)]
+    OutOfSync(#[from] AllowlistOutOfSync),
+    /// The receipts of a canonical block are missing, e.g. because they were pruned.
+    #[error("missing receipts of block {0}")]
+    MissingReceipts(BlockNumber),
+    /// The receipts of a canonical block could not be read.
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+}
+
+/// Provides the receipts of the canonical blocks a [`SenderAllowlist`] catches up from.
+pub trait AllowlistSource: Send + Sync {
+    /// Returns the receipts of the canonical block with the given number.
+    fn receipts(&self, number: BlockNumber) -> ProviderResult<Option<Vec<Receipt>>>;
+}
+
+impl<P: ReceiptProvider> AllowlistSource for P {
+    fn receipts(&self, number: BlockNumber) -> ProviderResult<Option<Vec<Receipt>>> {
+        self.receipts_by_block(number.into())
+    }
+}
+
+/// The senders allowed by the [`SENDER_REGISTRY`], cached in memory.
+///
+/// The node applies the receipts of every canonical block with [`SenderAllowlist::apply_block`],
+/// starting with block 1. An allowlist with a [source](SenderAllowlist::with_source) reads the
+/// blocks it missed from the source, see [`SenderAllowlist::sync_to`]. As a [`SequencingFilter`]
+/// it only answers for the block following the last applied one, and rejects every transaction of
+/// blocks it can't catch up to.
+///
+/// Cloning is cheap, all clones share the same state.
+#[derive(Clone)]
+pub struct SenderAllowlist {
+    from_block: BlockNumber,
+    inner: Arc<RwLock<AllowlistInner>>,
+    source: Option<Arc<dyn AllowlistSource>>,
+}
+
+impl fmt::Debug for SenderAllowlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SenderAllowlist")
+            .field("from_block", &self.from_block)
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+struct AllowlistInner {
+    /// The last block applied.
+    synced: BlockNumber,
+    senders: HashSet<Address>,
+}
+
+impl SenderAllowlist {
+    /// Creates the allowlist of the genesis block.
+    pub fn new(config: PermissionedConfig) -> Self {
+        Self {
+            from_block: config.from_block,
+            inner: Arc::new(RwLock::new(AllowlistInner {
+                synced: 0,
+                senders: config.genesis_senders,
+            })),
+            source: None,
+        }
+    }
+
+    /// Catches up with the canonical chain from the receipts of the source.
+    ///
+    /// The receipts of the canonical blocks must not be pruned.
+    pub fn with_source(mut self, source: Arc<dyn AllowlistSource>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Returns the last block applied to the allowlist.
+    pub fn synced_block(&self) -> BlockNumber {
+        self.inner.read().synced
+    }
+
+    /// Returns `true` if the sender is currently allowed.
+    pub fn contains(&self, sender: &Address) -> bool {
+        self.inner.read().senders.contains(sender)
+    }
+
+    /// Applies the registry events of the block with the given receipts, which must directly
+    /// follow the last applied block. Blocks that were applied already are ignored.
+    pub fn apply_block<'a>(
+        &self,
+        number: BlockNumber,
+        receipts: impl IntoIterator<Item = &'a Receipt>,
+    ) -> Result<(), AllowlistOutOfSync> {
+        let mut inner = self.inner.write();
+        if number <= inner.synced {
+            return Ok(())
+        }
+        if inner.synced + 1 != number {
+            return Err(AllowlistOutOfSync { synced: inner.synced, block: number })
+        }
+
+        let logs = receipts
+            .into_iter()
+            .filter(|receipt| receipt.success)
+            .flat_map(|receipt| &receipt.logs)
+            .filter(|log| log.address == SENDER_REGISTRY);
+        for log in logs {
+            let [signature, sender] = log.topics() else { continue };
+            let sender = Address::from_word(*sender);
+            if *signature == SenderAllowed::SIGNATURE_HASH {
+                inner.senders.insert(sender);
+            } else if *signature == SenderRevoked::SIGNATURE_HASH {
+                inner.senders.remove(&sender);
+            }
+        }
+        inner.synced = number;
+        Ok(())
+    }
+
+    /// Applies the canonical blocks up to `number` that were not applied yet, with the receipts
+    /// of the source.
+    ///
+    /// Fails if blocks are missing and the allowlist has no source.
+    pub fn sync_to(&self, number: BlockNumber) -> Result<(), AllowlistSyncError> {
+        loop {
+            let next = self.synced_block() + 1;
+            if next > number {
+                return Ok(())
+            }
+            let Some(source) = &self.source else {
+                return Err(AllowlistOutOfSync { synced: next - 1, block: number }.into())
+            };
+            let receipts =
+                source.receipts(next)?.ok_or(AllowlistSyncError::MissingReceipts(next))?;
+            // a block applied concurrently is ignored
+            self.apply_block(next, &receipts)?;
+        }
+    }
+}
+
+impl SequencingFilter for SenderAllowlist {
+    fn check(
+        &self,
+        block: BlockNumber,
+        sender: Address,
+        _transaction: &TransactionSigned,
+    ) -> Result<(), FilterRejection> {
+        if block < self.from_block {
+            return Ok(())
+        }
+        if let Err(err) = self.sync_to(block.saturating_sub(1)) {
+            return Err(FilterRejection::Other(err.to_string()))
+        }
+        let inner = self.inner.read();
+        if inner.synced + 1 != block {
+            return Err(FilterRejection::Other(
+                AllowlistOutOfSync { synced: inner.synced, block }.to_string(),
+            ))
+        }
+        if !inner.senders.contains(&sender) {
+            return Err(FilterRejection::Sender(sender))
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Log, TxType};
+
+    fn receipt(success: bool, events: &[(bool, u8)]) -> Receipt {
+        let logs = events
+            .iter()
+            .map(|(allowed, sender)| {
+                let signature = if *allowed {
+                    SenderAllowed::SIGNATURE_HASH
+                } else {
+                    SenderRevoked::SIGNATURE_HASH
+                };
+                let sender = Address::with_last_byte(*sender).into_word();
+                Log::new_unchecked(SENDER_REGISTRY, vec![signature, sender], Default::default())
+            })
+            .collect();
+        Receipt { tx_type: TxType::Eip1559, success, logs, ..Default::default() }
+    }
+
+    #[test]
+    fn apply_registry_events() {
+        let allowlist = SenderAllowlist::new(PermissionedConfig {
+            from_block: 1,
+            genesis_senders: [Address::with_last_byte(1)].into(),
+        });
+        let check = |block, sender| {
+            allowlist.check(block, Address::with_last_byte(sender), &TransactionSigned::default())
+        };
+        assert_eq!(check(0, 2), Ok(()));
+        assert_eq!(check(1, 1), Ok(()));
+        assert_eq!(check(1, 2), Err(FilterRejection::Sender(Address::with_last_byte(2))));
+
+        allowlist
+            .apply_block(
+                1,
+                &[receipt(true, &[(true, 2), (false, 1)]), receipt(false, &[(true, 3)])],
+            )
+            .unwrap();
+        assert_eq!(check(2, 2), Ok(()));
+        assert!(check(2, 1).is_err());
+        assert!(check(2, 3).is_err());
+        // only the block after the last applied one is answered
+        assert!(matches!(check(3, 2), Err(FilterRejection::Other(_))));
+
+        assert_eq!(allowlist.apply_block(3, &[]), Err(AllowlistOutOfSync { synced: 1, block: 3 }));
+        // applied blocks are ignored
+        assert_eq!(allowlist.apply_block(1, &[receipt(true, &[(false, 2)])]), Ok(()));
+        assert_eq!(allowlist.synced_block(), 1);
+        assert!(allowlist.contains(&Address::with_last_byte(2)));
+    }
+
+    /// The receipts of consecutive blocks from block 1.
+    struct Receipts(Vec<Vec<Receipt>>);
+
+    impl AllowlistSource for Receipts {
+        fn receipts(&self, number: BlockNumber) -> ProviderResult<Option<Vec<Receipt>>> {
+            Ok(self.0.get(number as usize - 1).cloned())
+        }
+    }
+
+    #[test]
+    fn catch_up_from_source() {
+        let source = Receipts(vec![vec![receipt(true, &[(true, 2)])], Vec::new()]);
+        let allowlist =
+            SenderAllowlist::new(PermissionedConfig { from_block: 1, ..Default::default() })
+                .with_source(Arc::new(source));
+
+        // block 3 is checked against the registry events of blocks 1 and 2
+        let transaction = TransactionSigned::default();
+        assert_eq!(allowlist.check(3, Address::with_last_byte(2), &transaction), Ok(()));
+        assert_eq!(allowlist.synced_block(), 2);
+
+        assert!(matches!(allowlist.sync_to(3), Err(AllowlistSyncError::MissingReceipts(3))));
+        assert!(matches!(
+            allowlist.check(4, Address::with_last_byte(2), &transaction),
+            Err(FilterRejection::Other(_))
+        ));
+    }
+}
@@ -0,0 +1,64 @@
+//! Handling of sequenced transactions that expired before they were executed.
+//!
+//! Under heavy load a transaction can be executed long after it was submitted. Submitters can
+//! bound this with a [`TransactionExpiry`], which is carried in the batch next to the transaction,
+//! so that every validator drops the transaction once it expired.
+
+use super::{SkipReason, SkippedTransaction};
+use crate::types::TransactionExpiry;
+use reth_primitives::TransactionSignedEcRecovered;
+
+/// Splits the transactions of a commit into the ones that are still valid, in sequencing order,
+/// and the ones that expired.
+pub fn drop_expired<I>(
+    transactions: I,
+    round: u64,
+    timestamp: u64,
+) -> (Vec<TransactionSignedEcRecovered>, Vec<SkippedTransaction>)
+where
+    I: IntoIterator<Item = (TransactionSignedEcRecovered, Option<TransactionExpiry>)>,
+{
+    let mut valid = Vec::new();
+    let mut expired = Vec::new();
+    for (transaction, expiry) in transactions {
+        match expiry.filter(|expiry| expiry.is_expired(round, timestamp)) {
+            Some(expiry) => {
+                expired.push(SkippedTransaction::new(&transaction, SkipReason::Expired(expiry)))
+            }
+            None => valid.push(transaction),
+        }
+    }
+    (valid, expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Address, Signature, Transaction, TransactionSigned, TxLegacy};
+
+    fn tx(nonce: u64) -> TransactionSignedEcRecovered {
+        let transaction = Transaction::Legacy(TxLegacy { nonce, ..Default::default() });
+        let signed =
+            TransactionSigned::from_transaction_and_signature(transaction, Signature::default());
+        TransactionSignedEcRecovered::from_signed_transaction(signed, Address::with_last_byte(1))
+    }
+
+    #[test]
+    fn drop_expired_transactions() {
+        let transactions = vec![
+            (tx(0), None),
+            (tx(1), Some(TransactionExpiry::Round(4))),
+            (tx(2), Some(TransactionExpiry::Round(5))),
+            (tx(3), Some(TransactionExpiry::Timestamp(999))),
+        ];
+        let (valid, expired) = drop_expired(transactions, 5, 1000);
+        assert_eq!(valid.iter().map(|tx| tx.nonce()).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(
+            expired.into_iter().map(|tx| (tx.nonce, tx.reason)).collect::<Vec<_>>(),
+            vec![
+                (1, SkipReason::Expired(TransactionExpiry::Round(4))),
+                (3, SkipReason::Expired(TransactionExpiry::Timestamp(999)))
+            ]
+        );
+    }
+}
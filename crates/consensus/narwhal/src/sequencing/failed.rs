@@ -0,0 +1,57 @@
+//! Handling of sequenced transactions that fail stateful checks at execution time.
+//!
+//! A transaction that was valid when it was batched can become invalid once the transactions
+//! ordered before it have been executed, most commonly because an earlier transaction spent the
+//! balance the sender needed to cover the maximum fee.
+//!
+//! Such transactions are excluded from the block: they don't get a receipt and they don't change
+//! state, the same way an Ethereum block builder skips them. Including them with a failure receipt
+//! that charges intrinsic gas would produce blocks that a regular Ethereum executor rejects, which
+//! would break follower nodes and standard tooling. The exclusion is reported as
+//! [`SkipReason::Invalid`] so that it can be surfaced to users.
+
+use super::SkipReason;
+use reth_primitives::revm_primitives::EVMError;
+
+/// Returns the [`SkipReason`] for a transaction that failed to execute, or `None` if the error was
+/// not caused by the transaction itself.
+///
+/// Transactions are only excluded for errors that every validator observes identically when
+/// executing the same transactions on the same parent state. Any other error, e.g. a database
+/// error, must abort the derivation of the block instead of silently changing its contents.
+pub fn execution_skip_reason<DBError>(error: &EVMError<DBError>) -> Option<SkipReason> {
+    match error {
+        EVMError::Transaction(err) => Some(SkipReason::Invalid(err.clone())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{
+        revm_primitives::{InvalidHeader, InvalidTransaction},
+        U256,
+    };
+
+    #[test]
+    fn insufficient_funds_are_skipped() {
+        let err = InvalidTransaction::LackOfFundForMaxFee {
+            fee: Box::new(U256::from(2)),
+            balance: Box::new(U256::from(1)),
+        };
+        assert_eq!(
+            execution_skip_reason::<()>(&EVMError::Transaction(err.clone())),
+            Some(SkipReason::Invalid(err))
+        );
+    }
+
+    #[test]
+    fn other_errors_are_fatal() {
+        assert_eq!(execution_skip_reason(&EVMError::Database(())), None);
+        assert_eq!(
+            execution_skip_reason::<()>(&EVMError::Header(InvalidHeader::PrevrandaoNotSet)),
+            None
+        );
+    }
+}
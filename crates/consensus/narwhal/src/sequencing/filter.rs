@@ -0,0 +1,265 @@
+//! Filtering of transactions by sender, recipient and calldata.
+//!
+//! Permissioned chains restrict who may transact, or with which contracts. A [`SequencingFilter`]
+//! is applied by the workers before a transaction is batched, and again when a block is validated
+//! after execution, so a validator that batched a rejected transaction can't get it into a block.
+//!
+//! Filters have to be deterministic and may only change their rules at a block number, since
+//! every validator must reach the same verdict for the same block. The built-in
+//! [`ChainSequencingFilter`] is configured by the chain specification, see
+//! [`SequencingFilterRules`].
+
+use alloy_primitives::{Address, BlockNumber, Bytes, FixedBytes};
+use reth_primitives::TransactionSigned;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt, sync::Arc};
+
+/// Decides whether a transaction may be sequenced.
+pub trait SequencingFilter: fmt::Debug + Send + Sync {
+    /// Checks a transaction of `sender` for inclusion in the given block.
+    ///
+    /// Workers check against the number of the next block. Must return the same result for the
+    /// same block and transaction on every validator.
+    fn check(
+        &self,
+        block: BlockNumber,
+        sender: Address,
+        transaction: &TransactionSigned,
+    ) -> Result<(), FilterRejection>;
+}
+
+/// Why a transaction was rejected by a [`SequencingFilter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterRejection {
+    /// The sender is not allowed to transact.
+    Sender(Address),
+    /// Transactions to the recipient are not allowed, `None` for contract creations.
+    Recipient(Option<Address>),
+    /// Calls of the function with this selector are not allowed.
+    Selector(FixedBytes<4>),
+    /// Rejected by a custom filter.
+    Other(String),
+}
+
+impl fmt::Display for FilterRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sender(sender) => write!(f, "sender {sender} is not allowed"),
+            Self::Recipient(Some(recipient)) => write!(f, "recipient {recipient} is not allowed"),
+            Self::Recipient(None) => f.write_str("contract creation is not allowed"),
+            Self::Selector(selector) => write!(f, "function selector {selector} is not allowed"),
+            Self::Other(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// A filter that accepts every transaction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopSequencingFilter;
+
+impl SequencingFilter for NoopSequencingFilter {
+    fn check(
+        &self,
+        _block: BlockNumber,
+        _sender: Address,
+        _transaction: &TransactionSigned,
+    ) -> Result<(), FilterRejection> {
+        Ok(())
+    }
+}
+
+/// Rejects the transactions any of the filters rejects, with the rejection of the first one.
+impl SequencingFilter for Vec<Arc<dyn SequencingFilter>> {
+    fn check(
+        &self,
+        block: BlockNumber,
+        sender: Address,
+        transaction: &TransactionSigned,
+    ) -> Result<(), FilterRejection> {
+        self.iter().try_for_each(|filter| filter.check(block, sender, transaction))
+    }
+}
+
+/// Whether the lists of [`SequencingFilterRules`] are allowlists or denylists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterMode {
+    /// Only transactions matching every non-empty list are accepted.
+    Allow,
+    /// Transactions matching any list are rejected.
+    #[default]
+    Deny,
+}
+
+/// Sender, recipient and function selector lists that apply from a block onwards.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SequencingFilterRules {
+    /// The first block the rules apply to.
+    pub from_block: BlockNumber,
+    /// Whether the lists are allowlists or denylists.
+    pub mode: FilterMode,
+    /// Transaction senders.
+    pub senders: HashSet<Address>,
+    /// Transaction recipients.
+    pub recipients: HashSet<Address>,
+    /// Function selectors, i.e. the first four bytes of the calldata.
+    pub selectors: HashSet<FixedBytes<4>>,
+}
+
+impl SequencingFilterRules {
+    fn check(
+        &self,
+        sender: Address,
+        transaction: &TransactionSigned,
+    ) -> Result<(), FilterRejection> {
+        let recipient = transaction.to();
+        let selector = selector(transaction.input());
+
+        let (sender_listed, recipient_listed, selector_listed) = (
+            self.senders.contains(&sender),
+            recipient.is_some_and(|recipient| self.recipients.contains(&recipient)),
+            selector.is_some_and(|selector| self.selectors.contains(&selector)),
+        );
+        match self.mode {
+            FilterMode::Allow => {
+                if !self.senders.is_empty() && !sender_listed {
+                    return Err(FilterRejection::Sender(sender))
+                }
+                if !self.recipients.is_empty() && !recipient_listed {
+                    return Err(FilterRejection::Recipient(recipient))
+                }
+                if !self.selectors.is_empty() && !selector_listed {
+                    return Err(FilterRejection::Selector(selector.unwrap_or_default()))
+                }
+            }
+            FilterMode::Deny => {
+                if sender_listed {
+                    return Err(FilterRejection::Sender(sender))
+                }
+                if recipient_listed {
+                    return Err(FilterRejection::Recipient(recipient))
+                }
+                if let Some(selector) = selector.filter(|_| selector_listed) {
+                    return Err(FilterRejection::Selector(selector))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the function selector of the calldata, if it has one.
+fn selector(input: &Bytes) -> Option<FixedBytes<4>> {
+    input.get(..4).map(FixedBytes::from_slice)
+}
+
+/// The filter configured by the chain specification.
+///
+/// Each version of the rules applies from its `from_block` until the `from_block` of the next
+/// version. Transactions of blocks before the first version are not filtered.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainSequencingFilter {
+    /// The versions of the rules, ordered by `from_block`.
+    versions: Vec<SequencingFilterRules>,
+}
+
+impl ChainSequencingFilter {
+    /// Creates a filter from the versions of the rules, in any order.
+    pub fn new(mut versions: Vec<SequencingFilterRules>) -> Self {
+        versions.sort_by_key(|rules| rules.from_block);
+        Self { versions }
+    }
+
+    /// Returns the rules that apply to the given block.
+    pub fn rules_at(&self, block: BlockNumber) -> Option<&SequencingFilterRules> {
+        self.versions.iter().rev().find(|rules| rules.from_block <= block)
+    }
+}
+
+impl SequencingFilter for ChainSequencingFilter {
+    fn check(
+        &self,
+        block: BlockNumber,
+        sender: Address,
+        transaction: &TransactionSigned,
+    ) -> Result<(), FilterRejection> {
+        self.rules_at(block).map_or(Ok(()), |rules| rules.check(sender, transaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Signature, Transaction, TxKind, TxLegacy};
+
+    fn transaction(to: Option<u8>, input: &[u8]) -> TransactionSigned {
+        let transaction = Transaction::Legacy(TxLegacy {
+            to: to.map_or(TxKind::Create, |to| TxKind::Call(Address::with_last_byte(to))),
+            input: input.to_vec().into(),
+            ..Default::default()
+        });
+        TransactionSigned::from_transaction_and_signature(transaction, Signature::default())
+    }
+
+    fn check(
+        filter: &ChainSequencingFilter,
+        block: BlockNumber,
+        sender: u8,
+        to: Option<u8>,
+        input: &[u8],
+    ) -> Result<(), FilterRejection> {
+        filter.check(block, Address::with_last_byte(sender), &transaction(to, input))
+    }
+
+    #[test]
+    fn deny_list() {
+        let rules = SequencingFilterRules {
+            senders: [Address::with_last_byte(1)].into(),
+            selectors: [FixedBytes::new([0xa9, 0x05, 0x9c, 0xbb])].into(),
+            ..Default::default()
+        };
+        let filter = ChainSequencingFilter::new(vec![rules]);
+
+        assert_eq!(
+            check(&filter, 0, 1, Some(2), &[]),
+            Err(FilterRejection::Sender(Address::with_last_byte(1)))
+        );
+        assert!(check(&filter, 0, 2, Some(2), &[0xa9, 0x05, 0x9c, 0xbb, 0]).is_err());
+        assert_eq!(check(&filter, 0, 2, Some(2), &[0xa9, 0x05]), Ok(()));
+        assert_eq!(check(&filter, 0, 2, None, &[]), Ok(()));
+    }
+
+    #[test]
+    fn allow_list() {
+        let rules = SequencingFilterRules {
+            mode: FilterMode::Allow,
+            recipients: [Address::with_last_byte(9)].into(),
+            ..Default::default()
+        };
+        let filter = ChainSequencingFilter::new(vec![rules]);
+
+        assert_eq!(check(&filter, 0, 1, Some(9), &[]), Ok(()));
+        assert_eq!(
+            check(&filter, 0, 1, Some(8), &[]),
+            Err(FilterRejection::Recipient(Some(Address::with_last_byte(8))))
+        );
+        assert_eq!(check(&filter, 0, 1, None, &[]), Err(FilterRejection::Recipient(None)));
+    }
+
+    #[test]
+    fn versions_apply_from_their_block() {
+        let deny = |from_block, sender| SequencingFilterRules {
+            from_block,
+            senders: [Address::with_last_byte(sender)].into(),
+            ..Default::default()
+        };
+        let filter = ChainSequencingFilter::new(vec![deny(100, 2), deny(10, 1)]);
+
+        assert_eq!(check(&filter, 9, 1, Some(0), &[]), Ok(()));
+        assert!(check(&filter, 10, 1, Some(0), &[]).is_err());
+        assert_eq!(check(&filter, 99, 2, Some(0), &[]), Ok(()));
+        assert_eq!(check(&filter, 100, 1, Some(0), &[]), Ok(()));
+        assert!(check(&filter, 100, 2, Some(0), &[]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Splitting of the transactions of a commit across blocks.
+//!
+//! A commit can sequence more gas than a block can hold, e.g. after a burst of transactions or a
+//! round whose leader was skipped. Executing all of them in one block would fail the block once its
+//! gas runs out, so the transactions are split across consecutive blocks in sequencing order. The
+//! split only uses the gas limits of the transactions, which are known before execution, so every
+//! validator splits a commit identically, and a block never runs out of gas since no transaction
+//! uses more gas than its gas limit.
+
+use super::{SkipReason, SkippedTransaction};
+use reth_primitives::TransactionSignedEcRecovered;
+
+/// Splits the sequenced transactions of a commit into the transactions of at most `max_blocks`
+/// consecutive blocks with the given gas limit.
+///
+/// Every block takes the next transactions in sequencing order as long as the sum of their gas
+/// limits fits into the block gas limit. A transaction whose gas limit alone exceeds the block gas
+/// limit can't be included in any block and is skipped. Once the last of the `max_blocks` blocks is
+/// full, the remaining transactions are skipped. Returns at least one, possibly empty, block, and a
+/// single block if all transactions fit.
+pub fn split_by_gas_limit(
+    transactions: Vec<TransactionSignedEcRecovered>,
+    block_gas_limit: u64,
+    max_blocks: usize,
+) -> (Vec<Vec<TransactionSignedEcRecovered>>, Vec<SkippedTransaction>) {
+    let mut blocks = vec![Vec::new()];
+    let mut skipped = Vec::new();
+    let mut remaining_gas = block_gas_limit;
+    let mut full = false;
+    for transaction in transactions {
+        let gas_limit = transaction.gas_limit();
+        if gas_limit > block_gas_limit {
+            skipped.push(SkippedTransaction::new(
+                &transaction,
+                SkipReason::GasLimitExceeded { block_gas_limit },
+            ));
+            continue
+        }
+        // later transactions don't jump ahead of a transaction that didn't fit
+        full |= gas_limit > remaining_gas && blocks.len() >= max_blocks;
+        if full {
+            skipped.push(SkippedTransaction::new(
+                &transaction,
+                SkipReason::BlockLimitExceeded { max_blocks },
+            ));
+            continue
+        }
+        if gas_limit > remaining_gas {
+            blocks.push(Vec::new());
+            remaining_gas = block_gas_limit;
+        }
+        remaining_gas -= gas_limit;
+        blocks.last_mut().expect("at least one block").push(transaction);
+    }
+    (blocks, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Address, Signature, Transaction, TransactionSigned, TxLegacy};
+
+    fn tx(nonce: u64, gas_limit: u64) -> TransactionSignedEcRecovered {
+        let transaction = Transaction::Legacy(TxLegacy { nonce, gas_limit, ..Default::default() });
+        let signed =
+            TransactionSigned::from_transaction_and_signature(transaction, Signature::default());
+        TransactionSignedEcRecovered::from_signed_transaction(signed, Address::with_last_byte(1))
+    }
+
+    fn nonces(blocks: &[Vec<TransactionSignedEcRecovered>]) -> Vec<Vec<u64>> {
+        blocks.iter().map(|block| block.iter().map(|tx| tx.nonce()).collect()).collect()
+    }
+
+    #[test]
+    fn single_block_if_transactions_fit() {
+        let (blocks, skipped) = split_by_gas_limit(vec![tx(0, 40), tx(1, 60)], 100, 16);
+        assert_eq!(nonces(&blocks), vec![vec![0, 1]]);
+        assert!(skipped.is_empty());
+
+        let (blocks, _) = split_by_gas_limit(Vec::new(), 100, 16);
+        assert_eq!(nonces(&blocks), vec![Vec::<u64>::new()]);
+    }
+
+    #[test]
+    fn split_in_order() {
+        let transactions = vec![tx(0, 60), tx(1, 30), tx(2, 20), tx(3, 100), tx(4, 10)];
+        let (blocks, skipped) = split_by_gas_limit(transactions, 100, 16);
+        // a later transaction that would still fit doesn't jump ahead
+        assert_eq!(nonces(&blocks), vec![vec![0, 1], vec![2], vec![3], vec![4]]);
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn skip_transactions_exceeding_block_gas_limit() {
+        let (blocks, skipped) = split_by_gas_limit(vec![tx(0, 50), tx(1, 101), tx(2, 50)], 100, 16);
+        assert_eq!(nonces(&blocks), vec![vec![0, 2]]);
+        assert_eq!(
+            skipped.into_iter().map(|tx| (tx.nonce, tx.reason)).collect::<Vec<_>>(),
+            vec![(1, SkipReason::GasLimitExceeded { block_gas_limit: 100 })]
+        );
+    }
+
+    #[test]
+    fn skip_transactions_exceeding_block_limit() {
+        let transactions = vec![tx(0, 60), tx(1, 60), tx(2, 60), tx(3, 10)];
+        let (blocks, skipped) = split_by_gas_limit(transactions, 100, 2);
+        assert_eq!(nonces(&blocks), vec![vec![0], vec![1]]);
+        // the transaction that would still fit into the last block doesn't jump ahead either
+        assert_eq!(
+            skipped.into_iter().map(|tx| (tx.nonce, tx.reason)).collect::<Vec<_>>(),
+            vec![
+                (2, SkipReason::BlockLimitExceeded { max_blocks: 2 }),
+                (3, SkipReason::BlockLimitExceeded { max_blocks: 2 })
+            ]
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Deterministic rules applied to sequenced transactions when consensus output is turned into a
+//! block.
+//!
+//! Narwhal orders transactions before they are executed, so a committed batch can contain
+//! transactions that would never have been included by an Ethereum block builder. Every validator
+//! must resolve those cases identically, otherwise the blocks they derive from the same commit
+//! would diverge.
+
+use reth_primitives::{
+    revm_primitives::InvalidTransaction, Address, BlockNumber, TransactionSignedEcRecovered, TxHash,
+};
+use std::fmt;
+
+mod allowlist;
+mod expiry;
+mod failed;
+mod filter;
+mod gas;
+mod nonce;
+mod replay;
+
+pub use crate::types::TransactionExpiry;
+pub use allowlist::{
+    AllowlistOutOfSync, AllowlistSource, AllowlistSyncError, PermissionedConfig, SenderAllowed,
+    SenderAllowlist, SenderRevoked,
+};
+pub use expiry::drop_expired;
+pub use failed::execution_skip_reason;
+pub use filter::{
+    ChainSequencingFilter, FilterMode, FilterRejection, NoopSequencingFilter, SequencingFilter,
+    SequencingFilterRules,
+};
+pub use gas::split_by_gas_limit;
+pub use nonce::{sequence_by_nonce, NonceGapPolicy, SequencedTransactions};
+pub use replay::mark_replayed;
+
+/// Why a sequenced transaction was not included in the block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The nonce was already used, either by an executed transaction or by an earlier transaction
+    /// of the same commit.
+    NonceTooLow {
+        /// The next nonce of the sender.
+        expected: u64,
+    },
+    /// The transaction was already executed, and was committed again, e.g. in a batch that was
+    /// resubmitted after it was garbage collected.
+    AlreadyExecuted {
+        /// The number of the block that executed the transaction.
+        block_number: BlockNumber,
+    },
+    /// There is a gap between the sender's next nonce and the transaction's nonce.
+    NonceGap {
+        /// The next nonce of the sender.
+        expected: u64,
+    },
+    /// An earlier transaction of the commit with the same future nonce is waiting for the gap to
+    /// close, see [`NonceGapPolicy::Defer`].
+    DuplicateNonce,
+    /// The transaction failed a stateful check when it was executed on top of the preceding
+    /// transactions of the block, e.g. the sender could no longer cover its maximum fee.
+    Invalid(InvalidTransaction),
+    /// The transaction expired before the block was derived.
+    Expired(TransactionExpiry),
+    /// The transaction was rejected by the chain's [`SequencingFilter`].
+    Filtered(FilterRejection),
+    /// The payload builder left the transaction out of the block, e.g. because it exceeds the
+    /// remaining gas of the block.
+    Excluded,
+    /// The gas limit of the transaction exceeds the gas limit of every block.
+    GasLimitExceeded {
+        /// The gas limit of the blocks.
+        block_gas_limit: u64,
+    },
+    /// The transactions of the commit exceed the gas of the blocks a commit may be split across.
+    BlockLimitExceeded {
+        /// The maximum number of blocks of a commit.
+        max_blocks: usize,
+    },
+}
+
+/// A sequenced transaction that was not included in the block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedTransaction {
+    /// Hash of the skipped transaction.
+    pub hash: TxHash,
+    /// The sender of the skipped transaction.
+    pub sender: Address,
+    /// The nonce of the skipped transaction.
+    pub nonce: u64,
+    /// Why the transaction was skipped.
+    pub reason: SkipReason,
+}
+
+impl SkippedTransaction {
+    /// Creates a new [`SkippedTransaction`] for the given transaction.
+    pub fn new(transaction: &TransactionSignedEcRecovered, reason: SkipReason) -> Self {
+        Self {
+            hash: transaction.hash(),
+            sender: transaction.signer(),
+            nonce: transaction.nonce(),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonceTooLow { expected } => write!(f, "nonce too low, next nonce is {expected}"),
+            Self::AlreadyExecuted { block_number } => {
+                write!(f, "already executed in block {block_number}")
+            }
+            Self::NonceGap { expected } => write!(f, "nonce gap, next nonce is {expected}"),
+            Self::DuplicateNonce => f.write_str("nonce already used by a deferred transaction"),
+            Self::Invalid(err) => write!(f, "invalid transaction: {err}"),
+            Self::Expired(expiry) => write!(f, "expired at {expiry}"),
+            Self::Filtered(rejection) => write!(f, "filtered: {rejection}"),
+            Self::Excluded => f.write_str("excluded by the payload builder"),
+            Self::GasLimitExceeded { block_gas_limit } => {
+                write!(f, "gas limit exceeds the block gas limit of {block_gas_limit}")
+            }
+            Self::BlockLimitExceeded { max_blocks } => {
+                write!(f, "commit exceeds the gas of {max_blocks} blocks")
+            }
+        }
+    }
+}
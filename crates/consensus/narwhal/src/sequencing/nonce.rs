@@ -0,0 +1,216 @@
+//! Handling of sequenced transactions whose nonce does not match the sender's account nonce.
+
+use super::{SkipReason, SkippedTransaction};
+use reth_primitives::{Address, TransactionSignedEcRecovered};
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+
+/// What happens to a sequenced transaction that has a future nonce at execution time.
+///
+/// Transactions of one sender can be sequenced out of order, e.g. if they were submitted to
+/// different validators. A transaction with a nonce gap can't be executed, so it must either wait
+/// for the missing transactions or be dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NonceGapPolicy {
+    /// Skip the transaction and retry it once the missing nonces have been included.
+    ///
+    /// Deferral is bounded by the commit: transactions whose gap is still open after all
+    /// transactions of the commit have been processed are dropped, so that the block contents
+    /// only depend on the commit and the parent state.
+    #[default]
+    Defer,
+    /// Remove the transaction from the block, without any effect on state.
+    ///
+    /// The transactions of the block keep their sequencing order.
+    Drop,
+}
+
+/// The outcome of [`sequence_by_nonce`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SequencedTransactions {
+    /// Transactions to include in the block, in execution order.
+    pub included: Vec<TransactionSignedEcRecovered>,
+    /// Transactions that are dropped, in sequencing order.
+    pub skipped: Vec<SkippedTransaction>,
+}
+
+/// Orders the transactions of a commit so that every included transaction has the next nonce of
+/// its sender, applying the given [`NonceGapPolicy`] to transactions with a future nonce.
+///
+/// `account_nonce` returns the nonce of an account in the parent state of the block. It is called
+/// at most once per sender.
+///
+/// The result only depends on the order of `transactions` and the parent state, so every validator
+/// derives the same block from the same commit.
+pub fn sequence_by_nonce<I, F, E>(
+    policy: NonceGapPolicy,
+    transactions: I,
+    mut account_nonce: F,
+) -> Result<SequencedTransactions, E>
+where
+    I: IntoIterator<Item = TransactionSignedEcRecovered>,
+    F: FnMut(Address) -> Result<u64, E>,
+{
+    let mut sequenced = SequencedTransactions::default();
+    let mut next_nonces = HashMap::<Address, u64>::new();
+    // deferred transactions by sender and nonce, tagged with their position in the commit
+    let mut deferred =
+        HashMap::<Address, BTreeMap<u64, (usize, TransactionSignedEcRecovered)>>::new();
+
+    for (position, transaction) in transactions.into_iter().enumerate() {
+        let sender = transaction.signer();
+        let nonce = transaction.nonce();
+        let next_nonce = match next_nonces.entry(sender) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(account_nonce(sender)?),
+        };
+
+        if nonce < *next_nonce {
+            sequenced.skipped.push(SkippedTransaction::new(
+                &transaction,
+                SkipReason::NonceTooLow { expected: *next_nonce },
+            ));
+            continue
+        }
+
+        if nonce > *next_nonce {
+            match policy {
+                NonceGapPolicy::Defer => {
+                    let pending = deferred.entry(sender).or_default();
+                    if pending.contains_key(&nonce) {
+                        // the first transaction sequenced with this nonce takes precedence
+                        sequenced.skipped.push(SkippedTransaction::new(
+                            &transaction,
+                            SkipReason::DuplicateNonce,
+                        ));
+                    } else {
+                        pending.insert(nonce, (position, transaction));
+                    }
+                }
+                NonceGapPolicy::Drop => sequenced.skipped.push(SkippedTransaction::new(
+                    &transaction,
+                    SkipReason::NonceGap { expected: *next_nonce },
+                )),
+            }
+            continue
+        }
+
+        sequenced.included.push(transaction);
+        *next_nonce += 1;
+
+        // the gap may now be closed for deferred transactions of the same sender
+        if let Some(pending) = deferred.get_mut(&sender) {
+            while let Some((_, transaction)) = pending.remove(&*next_nonce) {
+                sequenced.included.push(transaction);
+                *next_nonce += 1;
+            }
+        }
+    }
+
+    // transactions whose gap was never closed are dropped in sequencing order
+    let mut unresolved = deferred
+        .into_iter()
+        .flat_map(|(sender, pending)| {
+            let expected = next_nonces[&sender];
+            pending.into_values().map(move |(position, transaction)| {
+                (position, SkippedTransaction::new(&transaction, SkipReason::NonceGap { expected }))
+            })
+        })
+        .collect::<Vec<_>>();
+    unresolved.sort_unstable_by_key(|(position, _)| *position);
+    sequenced.skipped.extend(unresolved.into_iter().map(|(_, skipped)| skipped));
+
+    Ok(sequenced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_primitives::{Signature, Transaction, TransactionSigned, TxLegacy};
+    use std::convert::Infallible;
+
+    fn tx(sender: Address, nonce: u64) -> TransactionSignedEcRecovered {
+        let transaction = Transaction::Legacy(TxLegacy { nonce, ..Default::default() });
+        let signed =
+            TransactionSigned::from_transaction_and_signature(transaction, Signature::default());
+        TransactionSignedEcRecovered::from_signed_transaction(signed, sender)
+    }
+
+    fn nonces(sequenced: &[TransactionSignedEcRecovered]) -> Vec<(Address, u64)> {
+        sequenced.iter().map(|tx| (tx.signer(), tx.nonce())).collect()
+    }
+
+    fn sequence(
+        policy: NonceGapPolicy,
+        transactions: Vec<TransactionSignedEcRecovered>,
+    ) -> SequencedTransactions {
+        sequence_by_nonce(policy, transactions, |_| Ok::<_, Infallible>(0)).unwrap()
+    }
+
+    #[test]
+    fn in_order_transactions_are_included() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        for policy in [NonceGapPolicy::Defer, NonceGapPolicy::Drop] {
+            let sequenced = sequence(policy, vec![tx(a, 0), tx(b, 0), tx(a, 1)]);
+            assert_eq!(nonces(&sequenced.included), vec![(a, 0), (b, 0), (a, 1)]);
+            assert!(sequenced.skipped.is_empty());
+        }
+    }
+
+    #[test]
+    fn deferred_transactions_follow_the_closing_nonce() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let sequenced =
+            sequence(NonceGapPolicy::Defer, vec![tx(a, 2), tx(a, 1), tx(b, 0), tx(a, 0)]);
+        assert_eq!(nonces(&sequenced.included), vec![(b, 0), (a, 0), (a, 1), (a, 2)]);
+        assert!(sequenced.skipped.is_empty());
+    }
+
+    #[test]
+    fn dropped_transactions_have_no_effect() {
+        let a = Address::with_last_byte(1);
+        let sequenced = sequence(NonceGapPolicy::Drop, vec![tx(a, 1), tx(a, 0), tx(a, 1)]);
+        assert_eq!(nonces(&sequenced.included), vec![(a, 0), (a, 1)]);
+        assert_eq!(sequenced.skipped.len(), 1);
+        assert_eq!(sequenced.skipped[0].reason, SkipReason::NonceGap { expected: 0 });
+    }
+
+    #[test]
+    fn unresolved_gaps_are_dropped_in_sequencing_order() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let sequenced = sequence(NonceGapPolicy::Defer, vec![tx(b, 3), tx(a, 2), tx(b, 2)]);
+        assert!(sequenced.included.is_empty());
+        let skipped = sequenced.skipped.iter().map(|tx| (tx.sender, tx.nonce)).collect::<Vec<_>>();
+        assert_eq!(skipped, vec![(b, 3), (a, 2), (b, 2)]);
+    }
+
+    #[test]
+    fn duplicate_deferred_nonces_are_skipped() {
+        let a = Address::with_last_byte(1);
+        let duplicate = tx(a, 1);
+        let sequenced =
+            sequence(NonceGapPolicy::Defer, vec![tx(a, 1), duplicate.clone(), tx(a, 0)]);
+        assert_eq!(nonces(&sequenced.included), vec![(a, 0), (a, 1)]);
+        assert_eq!(
+            sequenced.skipped,
+            vec![SkippedTransaction::new(&duplicate, SkipReason::DuplicateNonce)]
+        );
+    }
+
+    #[test]
+    fn stale_nonces_are_skipped() {
+        let a = Address::with_last_byte(1);
+        let sequenced =
+            sequence_by_nonce(NonceGapPolicy::Defer, vec![tx(a, 4), tx(a, 5), tx(a, 5)], |_| {
+                Ok::<_, Infallible>(5)
+            })
+            .unwrap();
+        assert_eq!(nonces(&sequenced.included), vec![(a, 5)]);
+        let reasons = sequenced.skipped.into_iter().map(|tx| tx.reason).collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![SkipReason::NonceTooLow { expected: 5 }, SkipReason::NonceTooLow { expected: 6 }]
+        );
+    }
+}
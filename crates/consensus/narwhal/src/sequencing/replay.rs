@@ -0,0 +1,128 @@
+//! Replay protection for transactions that are committed again.
+//!
+//! A batch can be committed more than once: by several headers of the same commit, or after it was
+//! garbage collected and a worker resubmitted it with identical contents. Transactions that were
+//! already executed are never executed again, and the nonce rule enforces this on its own: an
+//! executed transaction used the nonce of its sender, so once it's sequenced again its nonce is
+//! below the sender's next nonce and [`sequence_by_nonce`](super::sequence_by_nonce) skips it with
+//! [`SkipReason::NonceTooLow`]. This only depends on the parent state, so every validator derives
+//! the same block no matter how long it remembers batches.
+//!
+//! The nonce rule can't tell a replay from a transaction that lost its nonce to another
+//! transaction of the same sender. [`mark_replayed`] looks the transactions the nonce rule skipped
+//! up in an index of executed transactions and reports the replays as
+//! [`SkipReason::AlreadyExecuted`]. The index is never consulted for other transactions, so its
+//! contents can't change a block, and it may be pruned.
+
+use super::{SkipReason, SkippedTransaction};
+use reth_primitives::{BlockNumber, TransactionSignedEcRecovered, TxHash};
+use std::collections::HashSet;
+
+/// Reports the skipped transactions that were already executed as
+/// [`SkipReason::AlreadyExecuted`], and returns their number.
+///
+/// `included` are the transactions of the block with the given number, which also count as
+/// executed. `executed_in` returns the block of an executed transaction from the index of executed
+/// transactions, and is only called for transactions with [`SkipReason::NonceTooLow`].
+pub fn mark_replayed<F, E>(
+    block_number: BlockNumber,
+    included: &[TransactionSignedEcRecovered],
+    skipped: &mut [SkippedTransaction],
+    mut executed_in: F,
+) -> Result<usize, E>
+where
+    F: FnMut(TxHash) -> Result<Option<BlockNumber>, E>,
+{
+    let included = included.iter().map(|transaction| transaction.hash()).collect::<HashSet<_>>();
+    let mut replayed = 0;
+    for transaction in skipped {
+        if !matches!(transaction.reason, SkipReason::NonceTooLow { .. }) {
+            continue
+        }
+        let executed = if included.contains(&transaction.hash) {
+            Some(block_number)
+        } else {
+            executed_in(transaction.hash)?
+        };
+        if let Some(block_number) = executed {
+            transaction.reason = SkipReason::AlreadyExecuted { block_number };
+            replayed += 1;
+        }
+    }
+    Ok(replayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequencing::{sequence_by_nonce, NonceGapPolicy};
+    use reth_primitives::{Address, Signature, Transaction, TransactionSigned, TxLegacy, U256};
+    use std::{collections::HashMap, convert::Infallible};
+
+    fn tx(sender: Address, nonce: u64, value: u64) -> TransactionSignedEcRecovered {
+        let transaction =
+            Transaction::Legacy(TxLegacy { nonce, value: U256::from(value), ..Default::default() });
+        let signed =
+            TransactionSigned::from_transaction_and_signature(transaction, Signature::default());
+        TransactionSignedEcRecovered::from_signed_transaction(signed, sender)
+    }
+
+    #[test]
+    fn resubmitted_batch_is_not_executed_again() {
+        let a = Address::with_last_byte(1);
+        let batch = vec![tx(a, 0, 1), tx(a, 1, 1)];
+
+        // the batch is executed in block 1
+        let first =
+            sequence_by_nonce(NonceGapPolicy::Defer, batch.clone(), |_| Ok::<_, Infallible>(0))
+                .unwrap();
+        assert_eq!(first.included, batch);
+        let index = batch
+            .iter()
+            .map(|transaction| (transaction.hash(), 1))
+            .collect::<HashMap<TxHash, BlockNumber>>();
+
+        // after the batch was garbage collected, it's resubmitted and committed again together
+        // with a transaction that lost its nonce to the batch
+        let mut resubmitted = batch.clone();
+        resubmitted.push(tx(a, 1, 2));
+        resubmitted.push(tx(a, 2, 1));
+        let mut second =
+            sequence_by_nonce(NonceGapPolicy::Defer, resubmitted, |_| Ok::<_, Infallible>(2))
+                .unwrap();
+        assert_eq!(second.included, vec![tx(a, 2, 1)]);
+
+        let replayed = mark_replayed(5, &second.included, &mut second.skipped, |hash| {
+            Ok::<_, Infallible>(index.get(&hash).copied())
+        })
+        .unwrap();
+        assert_eq!(replayed, 2);
+        let reasons = second.skipped.into_iter().map(|tx| tx.reason).collect::<Vec<_>>();
+        assert_eq!(
+            reasons,
+            vec![
+                SkipReason::AlreadyExecuted { block_number: 1 },
+                SkipReason::AlreadyExecuted { block_number: 1 },
+                SkipReason::NonceTooLow { expected: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_transaction_of_the_same_block() {
+        let a = Address::with_last_byte(1);
+        let mut sequenced =
+            sequence_by_nonce(NonceGapPolicy::Defer, vec![tx(a, 0, 1), tx(a, 0, 1)], |_| {
+                Ok::<_, Infallible>(0)
+            })
+            .unwrap();
+
+        // the index of executed transactions doesn't contain the block being derived yet
+        let replayed = mark_replayed(3, &sequenced.included, &mut sequenced.skipped, |_| {
+            Ok::<_, Infallible>(None)
+        })
+        .unwrap();
+        assert_eq!(replayed, 1);
+        assert_eq!(sequenced.skipped[0].reason, SkipReason::AlreadyExecuted { block_number: 3 });
+    }
+}
@@ -0,0 +1,140 @@
+//! Shadow mode, in which a validator runs the full pipeline without participating.
+//!
+//! Operators validate a new machine or version against production traffic by starting it with the
+//! keys of a validator in [`ValidatorMode::Shadow`]. The node receives and processes all messages
+//! of the committee, but the messages it would have sent to participate, i.e. its batches, the
+//! acknowledgements of the batches of other workers, its headers, votes and certificates, are
+//! handed to the [`ShadowLog`] instead, which logs them. Requests for missing batches and the
+//! responses to the requests of others are still sent, since they don't affect the DAG.
+//!
+//! Before a shadow node is switched to [`ValidatorMode::Active`], the node that was active with the
+//! same keys must be stopped, otherwise the messages of the two nodes equivocate.
+
+use crate::types::{BatchDigest, Certificate, Header, WorkerId};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::info;
+
+/// Whether the validator participates in consensus.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidatorMode {
+    /// The validator sends its batches, headers, votes and certificates to the committee.
+    #[default]
+    Active,
+    /// The validator only logs the messages it would have sent to the committee.
+    Shadow,
+}
+
+impl ValidatorMode {
+    /// Returns `true` in shadow mode.
+    pub const fn is_shadow(&self) -> bool {
+        matches!(self, Self::Shadow)
+    }
+}
+
+/// Metrics of the messages a shadow node suppressed, by kind.
+#[derive(Clone, Metrics)]
+#[metrics(scope = "narwhal.shadow")]
+struct ShadowMetrics {
+    /// Number of messages that were logged instead of sent
+    suppressed: Counter,
+}
+
+/// The kinds of messages a shadow node suppresses.
+#[derive(Debug, Clone)]
+struct ShadowKindMetrics {
+    batches: ShadowMetrics,
+    acks: ShadowMetrics,
+    headers: ShadowMetrics,
+    votes: ShadowMetrics,
+    certificates: ShadowMetrics,
+}
+
+impl Default for ShadowKindMetrics {
+    fn default() -> Self {
+        let metrics = |kind| ShadowMetrics::new_with_labels(&[("kind", kind)]);
+        Self {
+            batches: metrics("batch"),
+            acks: metrics("ack"),
+            headers: metrics("header"),
+            votes: metrics("vote"),
+            certificates: metrics("certificate"),
+        }
+    }
+}
+
+/// Logs the messages a node in [`ValidatorMode::Shadow`] would have sent.
+///
+/// Cloning is cheap, all clones record to the same metrics.
+#[derive(Debug, Clone, Default)]
+pub struct ShadowLog {
+    metrics: ShadowKindMetrics,
+}
+
+impl ShadowLog {
+    /// Logs a batch a worker would have broadcast.
+    pub fn batch(&self, worker: WorkerId, digest: BatchDigest) {
+        self.metrics.batches.suppressed.increment(1);
+        info!(target: "consensus::narwhal", worker, %digest, "Would broadcast batch");
+    }
+
+    /// Logs the acknowledgement of a batch a worker would have sent to the authority of the batch.
+    pub fn ack(&self, worker: WorkerId, digest: BatchDigest, to: AuthorityIndex) {
+        self.metrics.acks.suppressed.increment(1);
+        info!(
+            target: "consensus::narwhal",
+            worker,
+            %digest,
+            to,
+            "Would acknowledge batch"
+        );
+    }
+
+    /// Logs a header the primary would have proposed.
+    pub fn header(&self, header: &Header) {
+        self.metrics.headers.suppressed.increment(1);
+        info!(
+            target: "consensus::narwhal",
+            round = header.round,
+            digest = %header.digest(),
+            batches = header.payload.len(),
+            parents = header.parents.len(),
+            "Would propose header"
+        );
+    }
+
+    /// Logs a vote the primary would have cast for a header of another authority.
+    pub fn vote(&self, header: &Header) {
+        self.metrics.votes.suppressed.increment(1);
+        info!(
+            target: "consensus::narwhal",
+            round = header.round,
+            author = header.author,
+            digest = %header.digest(),
+            "Would vote for header"
+        );
+    }
+
+    /// Logs a certificate the primary would have formed from the votes for its header.
+    pub fn certificate(&self, certificate: &Certificate) {
+        self.metrics.certificates.suppressed.increment(1);
+        info!(
+            target: "consensus::narwhal",
+            round = certificate.round(),
+            digest = %certificate.digest(),
+            signers = certificate.signers.len(),
+            "Would broadcast certificate"
+        );
+    }
+
+    /// Logs the headers proposed by the primary until the primary is dropped, in place of the
+    /// component that broadcasts them.
+    pub async fn run_headers(self, mut headers: mpsc::Receiver<Header>) {
+        while let Some(header) = headers.recv().await {
+            self.header(&header);
+        }
+    }
+}
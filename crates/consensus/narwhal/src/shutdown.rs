@@ -0,0 +1,120 @@
+//! Shutdown ordering of the narwhal tasks.
+//!
+//! The tasks of a validator form a pipeline from transaction ingestion to storage. Stopping them
+//! in arbitrary order can lose work that was already accepted, e.g. a commit that was never
+//! executed, so they are shut down front to back in [`ShutdownStage`] order.
+
+use reth_tasks::{
+    shutdown::{GracefulShutdown, StagedShutdown},
+    TaskExecutor,
+};
+use std::time::Duration;
+
+/// The default total time the shutdown of all stages may take.
+///
+/// This is below the graceful shutdown timeout of the node, so that the remaining stages still get
+/// a chance to shut down after a stage timed out.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// The stages the narwhal tasks are shut down in, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShutdownStage {
+    /// Workers stop accepting transactions and sealing batches.
+    Workers,
+    /// The primary stops proposing headers.
+    Primary,
+    /// The committer orders the certificates that were already received.
+    Committer,
+    /// The executor finishes the block it is executing.
+    Executor,
+    /// The stores flush their pending writes.
+    Stores,
+}
+
+impl ShutdownStage {
+    /// All stages, in shutdown order.
+    pub const ALL: [Self; 5] =
+        [Self::Workers, Self::Primary, Self::Committer, Self::Executor, Self::Stores];
+}
+
+/// Coordinates the shutdown of the narwhal tasks in [`ShutdownStage`] order.
+///
+/// Tasks listen on the signal of their stage with [`NarwhalShutdown::on_shutdown`] and hold the
+/// returned guard until they are done.
+#[derive(Debug)]
+pub struct NarwhalShutdown {
+    stages: StagedShutdown,
+    timeout: Duration,
+}
+
+impl NarwhalShutdown {
+    /// Creates a new instance that aborts the shutdown after the given total timeout.
+    pub fn new(timeout: Duration) -> Self {
+        Self { stages: StagedShutdown::new(ShutdownStage::ALL.len()), timeout }
+    }
+
+    /// Returns the shutdown signal of the given stage.
+    pub fn on_shutdown(&self, stage: ShutdownStage) -> GracefulShutdown {
+        self.stages.on_shutdown(stage as usize)
+    }
+
+    /// Shuts down all stages in order, e.g. to halt the tasks of an epoch.
+    ///
+    /// Returns `false` if the stages didn't complete within the timeout.
+    pub async fn shutdown(self) -> bool {
+        self.stages.shutdown(self.timeout).await
+    }
+
+    /// Spawns the task that runs the shutdown once the node shuts down.
+    ///
+    /// If the stages don't complete within the timeout, all remaining stages are signaled at once,
+    /// and tasks that still haven't completed when the node's graceful shutdown times out are
+    /// aborted with the runtime.
+    pub fn spawn(self, executor: &TaskExecutor) {
+        executor.spawn_critical_with_graceful_shutdown_signal(
+            "narwhal shutdown",
+            |shutdown| async move {
+                let guard = shutdown.await;
+                self.shutdown().await;
+                drop(guard);
+            },
+        );
+    }
+}
+
+impl Default for NarwhalShutdown {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHUTDOWN_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_tasks::TaskManager;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn stages_shut_down_in_order() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let manager = TaskManager::new(runtime.handle().clone());
+        let executor = manager.executor();
+
+        let shutdown = NarwhalShutdown::default();
+        let stopped = Arc::new(Mutex::new(Vec::new()));
+        for stage in ShutdownStage::ALL.into_iter().rev() {
+            let on_shutdown = shutdown.on_shutdown(stage);
+            let stopped = Arc::clone(&stopped);
+            runtime.spawn(async move {
+                let guard = on_shutdown.await;
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                stopped.lock().unwrap().push(stage);
+                drop(guard);
+            });
+        }
+        shutdown.spawn(&executor);
+
+        assert!(manager.graceful_shutdown_with_timeout(Duration::from_secs(10)));
+        assert_eq!(*stopped.lock().unwrap(), ShutdownStage::ALL);
+    }
+}
@@ -0,0 +1,171 @@
+use super::AggregateScheme;
+use crate::{
+    metrics::ConsensusMetrics,
+    types::{Certificate, Header},
+};
+use alloy_primitives::B256;
+use reth_narwhal_verifier::{AuthorityIndex, Intent, SigningDomain, Stake, VerifierCommittee};
+use std::{collections::BTreeMap, fmt};
+
+/// Errors when adding a vote to a [`VotesAggregator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum VoteError {
+    /// The voter is not a member of the committee.
+    #[error("authority {voter} is not a member of the committee")]
+    UnknownVoter {
+        /// The index of the voter.
+        voter: AuthorityIndex,
+    },
+    /// The voter already voted for the header.
+    #[error("authority {voter} already voted")]
+    DuplicateVote {
+        /// The index of the voter.
+        voter: AuthorityIndex,
+    },
+    /// The signature of the vote is invalid.
+    #[error("invalid vote signature of authority {voter}")]
+    InvalidSignature {
+        /// The index of the voter.
+        voter: AuthorityIndex,
+    },
+}
+
+/// Collects the votes for a header until they form a quorum, and aggregates them into a
+/// [`Certificate`].
+pub struct VotesAggregator<S: AggregateScheme> {
+    header: Header,
+    /// The message the voters sign.
+    message: B256,
+    votes: BTreeMap<AuthorityIndex, S::Signature>,
+    stake: Stake,
+    certified: bool,
+    metrics: Option<ConsensusMetrics>,
+}
+
+impl<S: AggregateScheme> VotesAggregator<S> {
+    /// Creates an aggregator for the votes for the given header on the network of the domain.
+    pub fn new(header: Header, domain: &SigningDomain) -> Self {
+        let message = domain.signing_message(Intent::Vote, header.digest().0);
+        Self { header, message, votes: BTreeMap::new(), stake: 0, certified: false, metrics: None }
+    }
+
+    /// Records the rejected votes.
+    pub fn with_metrics(mut self, metrics: ConsensusMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Returns the message the voters sign.
+    pub const fn message(&self) -> B256 {
+        self.message
+    }
+
+    /// Verifies and records the vote of an authority.
+    ///
+    /// Returns the certificate once the votes form a quorum of the committee. Votes that arrive
+    /// after that are ignored.
+    pub fn add_vote(
+        &mut self,
+        committee: &VerifierCommittee<S::PublicKey>,
+        voter: AuthorityIndex,
+        signature: S::Signature,
+    ) -> Result<Option<Certificate>, VoteError> {
+        self.aggregate(committee, voter, signature).inspect_err(|_| {
+            if let Some(metrics) = &self.metrics {
+                metrics.record_vote_rejection();
+            }
+        })
+    }
+
+    fn aggregate(
+        &mut self,
+        committee: &VerifierCommittee<S::PublicKey>,
+        voter: AuthorityIndex,
+        signature: S::Signature,
+    ) -> Result<Option<Certificate>, VoteError> {
+        if self.certified {
+            return Ok(None)
+        }
+        let authority = committee.authority(voter).ok_or(VoteError::UnknownVoter { voter })?;
+        if self.votes.contains_key(&voter) {
+            return Err(VoteError::DuplicateVote { voter })
+        }
+        if !S::verify_aggregate(&[&authority.public_key], self.message.as_slice(), &signature) {
+            return Err(VoteError::InvalidSignature { voter })
+        }
+
+        self.votes.insert(voter, signature);
+        self.stake = self.stake.saturating_add(authority.stake);
+        if self.stake < committee.quorum_threshold() {
+            return Ok(None)
+        }
+
+        self.certified = true;
+        let signatures = std::mem::take(&mut self.votes);
+        let signers = signatures.keys().copied().collect();
+        let signatures = signatures.into_values().collect::<Vec<_>>();
+        let Some(signature) = S::aggregate(&signatures) else { return Ok(None) };
+        Ok(Some(Certificate {
+            header: self.header.clone(),
+            signers,
+            signature: S::encode_signature(&signature).into(),
+        }))
+    }
+}
+
+impl<S: AggregateScheme> fmt::Debug for VotesAggregator<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VotesAggregator")
+            .field("header", &self.header)
+            .field("voters", &self.votes.keys().collect::<Vec<_>>())
+            .field("stake", &self.stake)
+            .field("certified", &self.certified)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{tests::secret_keys, Bls12381};
+    use reth_narwhal_verifier::VerifierAuthority;
+
+    #[test]
+    fn form_certificate() {
+        let secret_keys = secret_keys();
+        let committee = VerifierCommittee::new(
+            0,
+            secret_keys
+                .iter()
+                .map(|key| VerifierAuthority { public_key: Bls12381::public_key(key), stake: 1 })
+                .collect(),
+        );
+        let domain = SigningDomain::new(1, B256::ZERO);
+        let header = Header { round: 1, author: 2, ..Default::default() };
+        let mut aggregator = VotesAggregator::<Bls12381>::new(header.clone(), &domain);
+        let vote =
+            |voter: usize| Bls12381::sign(&secret_keys[voter], aggregator.message().as_slice());
+        let (vote_0, vote_1, vote_3) = (vote(0), vote(1), vote(3));
+
+        assert_eq!(aggregator.add_vote(&committee, 3, vote_3), Ok(None));
+        assert_eq!(
+            aggregator.add_vote(&committee, 3, vote_3),
+            Err(VoteError::DuplicateVote { voter: 3 })
+        );
+        assert_eq!(
+            aggregator.add_vote(&committee, 1, vote_0),
+            Err(VoteError::InvalidSignature { voter: 1 })
+        );
+        assert_eq!(
+            aggregator.add_vote(&committee, 4, vote_0),
+            Err(VoteError::UnknownVoter { voter: 4 })
+        );
+        assert_eq!(aggregator.add_vote(&committee, 0, vote_0), Ok(None));
+
+        let certificate = aggregator.add_vote(&committee, 1, vote_1).unwrap().unwrap();
+        assert_eq!(certificate.header, header);
+        assert_eq!(certificate.signers, vec![0, 1, 3]);
+        assert_eq!(certificate.verify::<Bls12381>(&committee, &domain), Ok(()));
+        assert_eq!(aggregator.add_vote(&committee, 2, vote_1), Ok(None));
+    }
+}
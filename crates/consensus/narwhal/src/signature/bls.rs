@@ -0,0 +1,207 @@
+use super::{AggregateItem, AggregateScheme};
+use blst::{
+    blst_scalar,
+    min_pk::{AggregatePublicKey, AggregateSignature, PublicKey, SecretKey, Signature},
+    BLST_ERROR,
+};
+use rand::RngCore;
+use reth_narwhal_verifier::SignatureScheme;
+use std::fmt;
+
+/// The domain separation tag of votes, for the proof of possession scheme with public keys in G1.
+pub const BLS_SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// The domain separation tag of proofs of possession.
+pub const BLS_POP_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// BLS signatures on the BLS12-381 curve, with 48 byte public keys and 96 byte signatures.
+///
+/// Aggregate signatures over the same message are only secure if every public key of the
+/// committee was checked with [`BlsPublicKey::verify_proof_of_possession`] when it was registered,
+/// otherwise an authority could forge votes with a rogue key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bls12381;
+
+/// A BLS public key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlsPublicKey(PublicKey);
+
+impl BlsPublicKey {
+    /// Returns the compressed encoding of the key.
+    pub fn to_bytes(&self) -> [u8; 48] {
+        self.0.compress()
+    }
+
+    /// Returns `true` if the proof shows that the owner of the key knows its secret key.
+    pub fn verify_proof_of_possession(&self, proof: &BlsSignature) -> bool {
+        proof.0.verify(false, &self.to_bytes(), BLS_POP_DST, &[], &self.0, false) ==
+            BLST_ERROR::BLST_SUCCESS
+    }
+}
+
+impl TryFrom<&[u8]> for BlsPublicKey {
+    type Error = BLST_ERROR;
+
+    /// Decodes a key and checks that it's a valid point of the group.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        PublicKey::key_validate(bytes).map(Self)
+    }
+}
+
+/// A BLS signature, possibly aggregated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlsSignature(Signature);
+
+impl BlsSignature {
+    /// Returns the compressed encoding of the signature.
+    pub fn to_bytes(&self) -> [u8; 96] {
+        self.0.compress()
+    }
+}
+
+impl TryFrom<&[u8]> for BlsSignature {
+    type Error = BLST_ERROR;
+
+    /// Decodes a signature and checks that it's a valid point of the group.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Signature::sig_validate(bytes, true).map(Self)
+    }
+}
+
+/// A BLS secret key.
+#[derive(Clone)]
+pub struct BlsSecretKey(SecretKey);
+
+impl BlsSecretKey {
+    /// Derives a secret key from at least 32 bytes of secret key material.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, BLST_ERROR> {
+        SecretKey::key_gen(seed, &[]).map(Self)
+    }
+
+    /// Returns the proof of possession of the key, that is registered with its public key.
+    pub fn proof_of_possession(&self) -> BlsSignature {
+        BlsSignature(self.0.sign(&self.0.sk_to_pk().compress(), BLS_POP_DST, &[]))
+    }
+}
+
+impl fmt::Debug for BlsSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BlsSecretKey(..)")
+    }
+}
+
+impl SignatureScheme for Bls12381 {
+    type PublicKey = BlsPublicKey;
+    type Signature = BlsSignature;
+
+    fn verify_aggregate(
+        signers: &[&Self::PublicKey],
+        message: &[u8],
+        signature: &Self::Signature,
+    ) -> bool {
+        let public_keys = signers.iter().map(|key| &key.0).collect::<Vec<_>>();
+        // keys and signatures are group checked when they are decoded
+        signature.0.fast_aggregate_verify(false, message, BLS_SIGNATURE_DST, &public_keys) ==
+            BLST_ERROR::BLST_SUCCESS
+    }
+}
+
+impl AggregateScheme for Bls12381 {
+    type SecretKey = BlsSecretKey;
+
+    fn public_key(secret_key: &Self::SecretKey) -> Self::PublicKey {
+        BlsPublicKey(secret_key.0.sk_to_pk())
+    }
+
+    fn sign(secret_key: &Self::SecretKey, message: &[u8]) -> Self::Signature {
+        BlsSignature(secret_key.0.sign(message, BLS_SIGNATURE_DST, &[]))
+    }
+
+    fn aggregate(signatures: &[Self::Signature]) -> Option<Self::Signature> {
+        let signatures = signatures.iter().map(|signature| &signature.0).collect::<Vec<_>>();
+        AggregateSignature::aggregate(&signatures, false)
+            .ok()
+            .map(|aggregate| BlsSignature(aggregate.to_signature()))
+    }
+
+    fn verify_batch(items: &[AggregateItem<'_, Self>]) -> bool {
+        if items.is_empty() {
+            return true
+        }
+
+        let mut public_keys = Vec::with_capacity(items.len());
+        for item in items {
+            let signers = item.signers.iter().map(|key| &key.0).collect::<Vec<_>>();
+            let Ok(aggregate) = AggregatePublicKey::aggregate(&signers, false) else {
+                return false
+            };
+            public_keys.push(aggregate.to_public_key());
+        }
+        let public_keys = public_keys.iter().collect::<Vec<_>>();
+        let messages = items.iter().map(|item| item.message.as_slice()).collect::<Vec<_>>();
+        let signatures = items.iter().map(|item| &item.signature.0).collect::<Vec<_>>();
+
+        // random weights, so that invalid signatures can't cancel each other out
+        let mut rng = rand::thread_rng();
+        let weights = items
+            .iter()
+            .map(|_| {
+                let mut scalar = blst_scalar::default();
+                rng.fill_bytes(&mut scalar.b[..8]);
+                scalar
+            })
+            .collect::<Vec<_>>();
+
+        Signature::verify_multiple_aggregate_signatures(
+            &messages,
+            BLS_SIGNATURE_DST,
+            &public_keys,
+            false,
+            &signatures,
+            false,
+            &weights,
+            64,
+        ) == BLST_ERROR::BLST_SUCCESS
+    }
+
+    fn encode_signature(signature: &Self::Signature) -> Vec<u8> {
+        signature.0.compress().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::tests::secret_keys;
+
+    #[test]
+    fn aggregate_signatures() {
+        let secret_keys = secret_keys();
+        let public_keys = secret_keys.iter().map(Bls12381::public_key).collect::<Vec<_>>();
+        let signatures =
+            secret_keys.iter().map(|key| Bls12381::sign(key, b"header")).collect::<Vec<_>>();
+        let aggregate = Bls12381::aggregate(&signatures[..3]).unwrap();
+        let signers = public_keys[..3].iter().collect::<Vec<_>>();
+
+        assert!(Bls12381::verify_aggregate(&signers, b"header", &aggregate));
+        assert!(!Bls12381::verify_aggregate(&signers, b"other", &aggregate));
+        assert!(!Bls12381::verify_aggregate(&signers[..2], b"header", &aggregate));
+        assert_eq!(Bls12381::aggregate(&[]), None);
+
+        let encoded = Bls12381::encode_signature(&aggregate);
+        assert_eq!(encoded.len(), 96);
+        assert_eq!(BlsSignature::try_from(encoded.as_slice()), Ok(aggregate));
+        let key = public_keys[0].to_bytes();
+        assert_eq!(BlsPublicKey::try_from(key.as_slice()), Ok(public_keys[0]));
+        assert!(BlsPublicKey::try_from(&[0u8; 48][..]).is_err());
+    }
+
+    #[test]
+    fn proof_of_possession() {
+        let secret_keys = secret_keys();
+        let proof = secret_keys[0].proof_of_possession();
+        assert!(Bls12381::public_key(&secret_keys[0]).verify_proof_of_possession(&proof));
+        assert_eq!(BlsSignature::try_from(&proof.to_bytes()[..]), Ok(proof));
+        assert!(!Bls12381::public_key(&secret_keys[1]).verify_proof_of_possession(&proof));
+    }
+}
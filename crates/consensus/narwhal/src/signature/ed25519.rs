@@ -0,0 +1,124 @@
+use super::{AggregateItem, AggregateScheme};
+use ed25519_dalek::{Signature, SignatureError, Signer, SigningKey, VerifyingKey};
+use reth_narwhal_verifier::SignatureScheme;
+
+/// Ed25519 signatures.
+///
+/// Ed25519 signatures can't be aggregated, the aggregate is the list of the signatures of all
+/// signers in signer order, see [`Ed25519Signatures`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ed25519;
+
+/// The signatures of multiple signers over the same message, in signer order.
+///
+/// Encoded as the concatenation of the 64 byte signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ed25519Signatures(pub Vec<Signature>);
+
+impl TryFrom<&[u8]> for Ed25519Signatures {
+    type Error = SignatureError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() % Signature::BYTE_SIZE != 0 {
+            return Err(SignatureError::new())
+        }
+        bytes
+            .chunks_exact(Signature::BYTE_SIZE)
+            .map(Signature::from_slice)
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl SignatureScheme for Ed25519 {
+    type PublicKey = VerifyingKey;
+    type Signature = Ed25519Signatures;
+
+    fn verify_aggregate(
+        signers: &[&Self::PublicKey],
+        message: &[u8],
+        signature: &Self::Signature,
+    ) -> bool {
+        signers.len() == signature.0.len() &&
+            signers
+                .iter()
+                .zip(&signature.0)
+                .all(|(key, signature)| key.verify_strict(message, signature).is_ok())
+    }
+}
+
+impl AggregateScheme for Ed25519 {
+    type SecretKey = SigningKey;
+
+    fn public_key(secret_key: &Self::SecretKey) -> Self::PublicKey {
+        secret_key.verifying_key()
+    }
+
+    fn sign(secret_key: &Self::SecretKey, message: &[u8]) -> Self::Signature {
+        Ed25519Signatures(vec![secret_key.sign(message)])
+    }
+
+    fn aggregate(signatures: &[Self::Signature]) -> Option<Self::Signature> {
+        if signatures.is_empty() {
+            return None
+        }
+        Some(Ed25519Signatures(
+            signatures.iter().flat_map(|signatures| signatures.0.iter().copied()).collect(),
+        ))
+    }
+
+    fn verify_batch(items: &[AggregateItem<'_, Self>]) -> bool {
+        let mut messages = Vec::new();
+        let mut signatures = Vec::new();
+        let mut public_keys = Vec::new();
+        for item in items {
+            if item.signers.len() != item.signature.0.len() {
+                return false
+            }
+            for (key, signature) in item.signers.iter().zip(&item.signature.0) {
+                messages.push(item.message.as_slice());
+                signatures.push(*signature);
+                public_keys.push(**key);
+            }
+        }
+        messages.is_empty() ||
+            ed25519_dalek::verify_batch(&messages, &signatures, &public_keys).is_ok()
+    }
+
+    fn encode_signature(signature: &Self::Signature) -> Vec<u8> {
+        signature.0.iter().flat_map(Signature::to_bytes).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+
+    #[test]
+    fn multi_signatures() {
+        let secret_keys =
+            (0..3u8).map(|seed| SigningKey::from_bytes(&[seed; 32])).collect::<Vec<_>>();
+        let public_keys = secret_keys.iter().map(Ed25519::public_key).collect::<Vec<_>>();
+        let signers = public_keys.iter().collect::<Vec<_>>();
+        let message = B256::with_last_byte(1);
+        let signatures = secret_keys
+            .iter()
+            .map(|key| Ed25519::sign(key, message.as_slice()))
+            .collect::<Vec<_>>();
+        let aggregate = Ed25519::aggregate(&signatures).unwrap();
+
+        assert!(Ed25519::verify_aggregate(&signers, message.as_slice(), &aggregate));
+        assert!(!Ed25519::verify_aggregate(&signers[..2], message.as_slice(), &aggregate));
+
+        let encoded = Ed25519::encode_signature(&aggregate);
+        assert_eq!(Ed25519Signatures::try_from(encoded.as_slice()).unwrap(), aggregate);
+        assert!(Ed25519Signatures::try_from(&encoded[1..]).is_err());
+
+        let item = |signature| AggregateItem { signers: signers.clone(), message, signature };
+        assert!(Ed25519::verify_batch(&[item(aggregate.clone()), item(aggregate.clone())]));
+        let mut swapped = aggregate.clone();
+        swapped.0.swap(0, 1);
+        assert!(!Ed25519::verify_batch(&[item(aggregate), item(swapped)]));
+    }
+}
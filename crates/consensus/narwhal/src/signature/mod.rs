@@ -0,0 +1,174 @@
+//! Signatures of the committee.
+//!
+//! Authorities vote for a header by signing its digest, and a quorum of votes is aggregated into
+//! the signature of the [`Certificate`]. The default scheme is [`Bls12381`], whose signatures
+//! aggregate into a single signature of constant size. Chains that can't use BLS can enable the
+//! `ed25519` feature and use [`Ed25519`], whose aggregate is the list of the individual
+//! signatures.
+//!
+//! Every authority verifies every certificate of the DAG, so certificates are verified in batches
+//! with [`verify_certificates`] instead of one by one.
+
+mod aggregator;
+mod bls;
+#[cfg(feature = "ed25519")]
+mod ed25519;
+
+pub use aggregator::{VoteError, VotesAggregator};
+pub use bls::{Bls12381, BlsPublicKey, BlsSecretKey, BlsSignature, BLS_POP_DST, BLS_SIGNATURE_DST};
+#[cfg(feature = "ed25519")]
+pub use ed25519::{Ed25519, Ed25519Signatures};
+
+use crate::types::Certificate;
+use alloy_primitives::B256;
+use reth_narwhal_verifier::{
+    quorum_signers, Intent, SignatureScheme, SigningDomain, VerificationError, VerifierCommittee,
+};
+
+/// A [`SignatureScheme`] that can create signatures and verify many of them at once.
+pub trait AggregateScheme: SignatureScheme {
+    /// The secret key of a signer.
+    type SecretKey;
+
+    /// Returns the public key of a secret key.
+    fn public_key(secret_key: &Self::SecretKey) -> Self::PublicKey;
+
+    /// Signs a message.
+    fn sign(secret_key: &Self::SecretKey, message: &[u8]) -> Self::Signature;
+
+    /// Aggregates the signatures of distinct signers over the same message, given in the order of
+    /// the signers.
+    ///
+    /// Returns `None` if there are no signatures.
+    fn aggregate(signatures: &[Self::Signature]) -> Option<Self::Signature>;
+
+    /// Returns `true` if every item is a valid aggregate signature.
+    ///
+    /// This is equivalent to calling [`SignatureScheme::verify_aggregate`] for every item, but
+    /// faster for many items.
+    fn verify_batch(items: &[AggregateItem<'_, Self>]) -> bool;
+
+    /// Encodes a signature, the inverse of its `TryFrom<&[u8]>` implementation.
+    fn encode_signature(signature: &Self::Signature) -> Vec<u8>;
+}
+
+/// An aggregate signature to verify with [`AggregateScheme::verify_batch`].
+#[derive(Debug)]
+pub struct AggregateItem<'a, S: SignatureScheme + ?Sized> {
+    /// The public keys of the signers.
+    pub signers: Vec<&'a S::PublicKey>,
+    /// The signed message.
+    pub message: B256,
+    /// The aggregate signature.
+    pub signature: S::Signature,
+}
+
+/// Verifies that each certificate carries the votes of a quorum of the committee, cast on the
+/// network of the given domain.
+///
+/// Returns the outcome of each certificate, in order. The signatures of all certificates are
+/// verified in one batch. Only if the batch fails, they are verified one by one to find the
+/// invalid ones.
+pub fn verify_certificates<S>(
+    committee: &VerifierCommittee<S::PublicKey>,
+    domain: &SigningDomain,
+    certificates: &[Certificate],
+) -> Vec<Result<(), VerificationError>>
+where
+    S: AggregateScheme,
+    for<'a> S::Signature: TryFrom<&'a [u8]>,
+{
+    let mut results = Vec::with_capacity(certificates.len());
+    let mut items = Vec::with_capacity(certificates.len());
+    // the index in `results` of each item
+    let mut positions = Vec::with_capacity(certificates.len());
+
+    for certificate in certificates {
+        let item = S::Signature::try_from(certificate.signature.as_ref())
+            .map_err(|_| VerificationError::MalformedProof)
+            .and_then(|signature| {
+                let signers =
+                    quorum_signers(committee, certificate.header.epoch, &certificate.signers)?;
+                let message = domain.signing_message(Intent::Vote, certificate.header.digest().0);
+                Ok(AggregateItem { signers, message, signature })
+            });
+        match item {
+            Ok(item) => {
+                positions.push(results.len());
+                items.push(item);
+                results.push(Ok(()));
+            }
+            Err(err) => results.push(Err(err)),
+        }
+    }
+
+    if !S::verify_batch(&items) {
+        for (item, position) in items.iter().zip(positions) {
+            if !S::verify_aggregate(&item.signers, item.message.as_slice(), &item.signature) {
+                results[position] = Err(VerificationError::InvalidSignature);
+            }
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Header;
+    use reth_narwhal_verifier::VerifierAuthority;
+
+    pub(super) fn secret_keys() -> Vec<BlsSecretKey> {
+        (0..4u8).map(|seed| BlsSecretKey::from_seed(&[seed; 32]).unwrap()).collect()
+    }
+
+    #[test]
+    fn verify_certificate_batch() {
+        let secret_keys = secret_keys();
+        let committee = VerifierCommittee::new(
+            1,
+            secret_keys
+                .iter()
+                .map(|key| VerifierAuthority { public_key: Bls12381::public_key(key), stake: 1 })
+                .collect(),
+        );
+        let domain = SigningDomain::new(1337, B256::ZERO);
+
+        let certificate = |round, signers: &[u32]| {
+            let header = Header { epoch: 1, round, author: 0, ..Default::default() };
+            let message = domain.signing_message(Intent::Vote, header.digest().0);
+            let signatures = signers
+                .iter()
+                .map(|&signer| Bls12381::sign(&secret_keys[signer as usize], message.as_slice()))
+                .collect::<Vec<_>>();
+            let signature = Bls12381::aggregate(&signatures).unwrap();
+            Certificate {
+                header,
+                signers: signers.to_vec(),
+                signature: Bls12381::encode_signature(&signature).into(),
+            }
+        };
+
+        let valid = (1..=3).map(|round| certificate(round, &[0, 1, 2])).collect::<Vec<_>>();
+        assert!(verify_certificates::<Bls12381>(&committee, &domain, &valid)
+            .iter()
+            .all(Result::is_ok));
+
+        let mut forged = certificate(4, &[0, 1, 3]);
+        forged.header.round = 5;
+        let mut malformed = certificate(6, &[0, 1, 2]);
+        malformed.signature = vec![1; 96].into();
+        let certificates =
+            vec![valid[0].clone(), forged, certificate(7, &[0, 1]), malformed, valid[1].clone()];
+        assert_eq!(
+            verify_certificates::<Bls12381>(&committee, &domain, &certificates),
+            vec![
+                Ok(()),
+                Err(VerificationError::InvalidSignature),
+                Err(VerificationError::InsufficientStake { stake: 2, threshold: 3 }),
+                Err(VerificationError::MalformedProof),
+                Ok(()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Deterministic simulation of a committee in virtual time.
+//!
+//! A [`Simulation`] runs the workers and primaries of every authority of a [`DevCommittee`] in a
+//! single thread, on an in-memory network. Instead of tasks and timers, it keeps a queue of events
+//! ordered by their virtual time: the workers seal batches, the primaries propose headers with the
+//! [`Proposer`] of the real primary, vote, aggregate the votes into certificates and commit them
+//! with the [`Bullshark`](crate::bullshark::Bullshark) commit rule of the validators, and every
+//! message is delivered after a latency drawn from the [`Seed`]. The [`SimClock`] only moves when
+//! the next event is processed.
+//!
+//! The seed is therefore the only input of a run: two runs of the same [`SimConfig`] process the
+//! same events at the same virtual times and commit the same leaders, so the ordering of commits
+//! and the garbage collection of the DAG can be tested reproducibly. The simulation logs its seed
+//! when it starts, and a failing run is replayed by setting the seed, e.g. with the
+//! [`SIM_SEED_ENV`] variable read by [`SimConfig::from_env`].
+
+use crate::{
+    determinism::{env_var, Clock, Seed},
+    dev::{DevCommittee, DEFAULT_DEV_COMMITTEE_SIZE},
+    gc::{gc_round, DEFAULT_GC_DEPTH},
+    primary::{PrimaryConfig, Proposer},
+    signature::{AggregateScheme, Bls12381, BlsPublicKey, BlsSignature, VotesAggregator},
+    test_utils::{SimulatedCommit, SimulatedDag},
+    types::{Batch, BatchDigest, BatchRef, Certificate, Header, HeaderDigest, Round, Vote},
+    wire::NarwhalMessage,
+};
+use alloy_primitives::{keccak256, Bytes, B256};
+use reth_narwhal_verifier::{AuthorityIndex, Intent, SigningDomain, VerifierCommittee};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering as AtomicOrdering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::{debug, info};
+
+/// The environment variable with the seed of [`SimConfig::from_env`].
+pub const SIM_SEED_ENV: &str = "NARWHAL_SIM_SEED";
+
+/// The virtual time at which every simulation starts, in milliseconds since the unix epoch.
+const SIM_START_MILLIS: u64 = 1_700_000_000_000;
+
+/// Configuration of a [`Simulation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimConfig {
+    /// The seed of all pseudo-random choices of the simulation.
+    pub seed: u64,
+    /// The number of authorities.
+    pub committee_size: usize,
+    /// The configuration of the primaries.
+    pub primary: PrimaryConfig,
+    /// The interval in which every worker seals a batch.
+    pub batch_interval: Duration,
+    /// The lowest latency of a message.
+    pub min_latency: Duration,
+    /// The highest latency of a message.
+    pub max_latency: Duration,
+    /// The number of rounds below the last committed round that every authority keeps.
+    pub gc_depth: Round,
+}
+
+impl SimConfig {
+    /// Returns the default configuration with the seed of the [`SIM_SEED_ENV`] variable, or with
+    /// a random seed if it's not set.
+    ///
+    /// # Panics
+    ///
+    /// If the variable is not a number.
+    pub fn from_env() -> Self {
+        let seed = env_var(SIM_SEED_ENV).map_or_else(
+            || Seed::from_entropy().next_u64(),
+            |seed| seed.parse().expect("simulation seed must be a number"),
+        );
+        Self { seed, ..Default::default() }
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            committee_size: DEFAULT_DEV_COMMITTEE_SIZE,
+            primary: PrimaryConfig {
+                max_header_batches: 4,
+                max_header_delay: Duration::from_millis(100),
+            },
+            batch_interval: Duration::from_millis(50),
+            min_latency: Duration::from_millis(10),
+            max_latency: Duration::from_millis(100),
+            gc_depth: DEFAULT_GC_DEPTH,
+        }
+    }
+}
+
+/// The virtual clock of a [`Simulation`].
+///
+/// Cloning is cheap, all clones read the same time.
+#[derive(Debug, Clone)]
+pub struct SimClock {
+    millis: Arc<AtomicU64>,
+}
+
+impl SimClock {
+    fn new(millis: u64) -> Self {
+        Self { millis: Arc::new(AtomicU64::new(millis)) }
+    }
+
+    /// Returns the time that passed since the simulation started.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.now_millis() - SIM_START_MILLIS)
+    }
+
+    /// Moves the clock forward to the given time, it never goes back.
+    fn advance_to(&self, millis: u64) {
+        self.millis.fetch_max(millis, AtomicOrdering::Relaxed);
+    }
+}
+
+impl Clock for SimClock {
+    fn now_millis(&self) -> u64 {
+        self.millis.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// An event of a [`Simulation`].
+#[derive(Debug)]
+enum Event {
+    /// The worker of the authority seals a batch.
+    SealBatch(AuthorityIndex),
+    /// The `max_header_delay` of the round of the primary of the authority passed.
+    HeaderTimeout(AuthorityIndex, Round),
+    /// A message reaches an authority.
+    Deliver(AuthorityIndex, NarwhalMessage),
+}
+
+/// An event, ordered by the time it's due and then by the order in which it was scheduled.
+#[derive(Debug)]
+struct Scheduled {
+    at: u64,
+    sequence: u64,
+    event: Event,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.at, self.sequence).cmp(&(other.at, other.sequence))
+    }
+}
+
+/// A committee that runs in virtual time, see the [module docs](self).
+#[derive(Debug)]
+pub struct Simulation {
+    config: SimConfig,
+    dev: DevCommittee,
+    verifier: VerifierCommittee<BlsPublicKey>,
+    domain: SigningDomain,
+    clock: SimClock,
+    rng: Seed,
+    queue: BinaryHeap<Reverse<Scheduled>>,
+    /// The number of events scheduled so far.
+    scheduled: u64,
+    /// The number of events processed so far.
+    processed: u64,
+    authorities: Vec<SimAuthority>,
+}
+
+impl Simulation {
+    /// Creates the committee of the configuration at the genesis round.
+    ///
+    /// # Panics
+    ///
+    /// If the committee is empty or the latencies are reversed.
+    pub fn new(config: SimConfig) -> Self {
+        assert!(config.min_latency <= config.max_latency, "min latency above max latency");
+        info!(target: "consensus::narwhal", seed = config.seed, "Starting simulation");
+        let dev = DevCommittee::new(config.committee_size);
+        let verifier = dev.committee().verifier_committee().expect("dev committee keys are valid");
+        let authorities = (0..config.committee_size as AuthorityIndex)
+            .map(|index| SimAuthority {
+                proposer: Proposer::new(&verifier, index, config.primary),
+                dag: SimulatedDag::new(dev.committee()),
+                batches: HashSet::new(),
+                votes: HashMap::new(),
+                pending: Vec::new(),
+                aggregator: None,
+            })
+            .collect();
+        let mut simulation = Self {
+            config,
+            dev,
+            verifier,
+            domain: SigningDomain::new(1337, B256::ZERO),
+            clock: SimClock::new(SIM_START_MILLIS),
+            rng: Seed::new(keccak256(config.seed.to_be_bytes())),
+            queue: BinaryHeap::new(),
+            scheduled: 0,
+            processed: 0,
+            authorities,
+        };
+        for authority in 0..config.committee_size as AuthorityIndex {
+            // the workers don't seal their batches in lockstep
+            let offset = simulation.draw(Duration::ZERO, config.batch_interval);
+            simulation.schedule(offset, Event::SealBatch(authority));
+            simulation
+                .schedule(config.primary.max_header_delay, Event::HeaderTimeout(authority, 1));
+        }
+        simulation
+    }
+
+    /// Returns the configuration.
+    pub const fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// Returns the virtual clock.
+    pub const fn clock(&self) -> &SimClock {
+        &self.clock
+    }
+
+    /// Returns the number of events processed so far.
+    pub const fn processed_events(&self) -> u64 {
+        self.processed
+    }
+
+    /// Returns the current round of the primary of an authority.
+    pub fn round(&self, authority: AuthorityIndex) -> Round {
+        self.authorities[authority as usize].proposer.round()
+    }
+
+    /// Returns the DAG of an authority.
+    pub fn dag(&self, authority: AuthorityIndex) -> &SimulatedDag {
+        &self.authorities[authority as usize].dag
+    }
+
+    /// Returns the leaders an authority committed, in commit order.
+    pub fn commits(&self, authority: AuthorityIndex) -> &[SimulatedCommit] {
+        self.authorities[authority as usize].dag.commits()
+    }
+
+    /// Processes the events of the given virtual time.
+    pub fn run_for(&mut self, duration: Duration) {
+        let end = self.clock.now_millis() + duration.as_millis() as u64;
+        while self.queue.peek().is_some_and(|Reverse(next)| next.at <= end) {
+            self.step();
+        }
+        self.clock.advance_to(end);
+    }
+
+    /// Processes events until `done` returns `true`, for at most the given virtual time.
+    ///
+    /// Returns `false` if the time passed first.
+    pub fn run_until(&mut self, timeout: Duration, mut done: impl FnMut(&Self) -> bool) -> bool {
+        let end = self.clock.now_millis() + timeout.as_millis() as u64;
+        while !done(self) {
+            if !self.queue.peek().is_some_and(|Reverse(next)| next.at <= end) {
+                self.clock.advance_to(end);
+                return false
+            }
+            self.step();
+        }
+        true
+    }
+
+    /// Processes the next event, and returns `false` if there is none.
+    pub fn step(&mut self) -> bool {
+        let Some(Reverse(Scheduled { at, event, .. })) = self.queue.pop() else { return false };
+        self.clock.advance_to(at);
+        self.processed += 1;
+        match event {
+            Event::SealBatch(authority) => {
+                self.seal_batch(authority);
+                self.schedule(self.config.batch_interval, Event::SealBatch(authority));
+            }
+            Event::HeaderTimeout(authority, round) => {
+                if self.round(authority) == round {
+                    self.propose(authority, true);
+                }
+            }
+            Event::Deliver(to, message) => match message {
+                NarwhalMessage::Batch(batch) => {
+                    self.authorities[to as usize].batches.insert(batch.digest());
+                    self.retry_headers(to);
+                }
+                NarwhalMessage::Header(header) => self.receive_header(to, header),
+                NarwhalMessage::Vote(vote) => self.receive_vote(to, vote),
+                NarwhalMessage::Certificate(certificate) => {
+                    self.receive_certificate(to, certificate)
+                }
+            },
+        }
+        true
+    }
+
+    /// Schedules an event after the given delay.
+    fn schedule(&mut self, delay: Duration, event: Event) {
+        let at = self.clock.now_millis() + delay.as_millis() as u64;
+        self.queue.push(Reverse(Scheduled { at, sequence: self.scheduled, event }));
+        self.scheduled += 1;
+    }
+
+    /// Draws a duration between the bounds, both inclusive, at millisecond precision.
+    fn draw(&mut self, min: Duration, max: Duration) -> Duration {
+        let (min, max) = (min.as_millis() as u64, max.as_millis() as u64);
+        Duration::from_millis(min + self.rng.next_u64() % (max - min + 1))
+    }
+
+    /// Sends a message to another authority, which receives it after a random latency.
+    fn send(&mut self, to: AuthorityIndex, message: NarwhalMessage) {
+        let latency = self.draw(self.config.min_latency, self.config.max_latency);
+        self.schedule(latency, Event::Deliver(to, message));
+    }
+
+    /// Sends a message to all other authorities.
+    fn broadcast(&mut self, from: AuthorityIndex, message: NarwhalMessage) {
+        for to in 0..self.authorities.len() as AuthorityIndex {
+            if to != from {
+                self.send(to, message.clone());
+            }
+        }
+    }
+
+    /// Seals a batch of the worker of an authority, and broadcasts it.
+    fn seal_batch(&mut self, authority: AuthorityIndex) {
+        let transaction = Bytes::copy_from_slice(self.rng.next_b256().as_slice());
+        let batch = Batch::new(vec![transaction]);
+        let digest = batch.digest();
+        let state = &mut self.authorities[authority as usize];
+        state.batches.insert(digest);
+        state.proposer.add_batch(BatchRef { digest, worker: 0 });
+        self.broadcast(authority, NarwhalMessage::Batch(batch));
+        self.propose(authority, false);
+    }
+
+    /// Proposes the header of the current round of an authority if it's due, and broadcasts it.
+    fn propose(&mut self, authority: AuthorityIndex, force: bool) {
+        let now = self.clock.now_millis();
+        let Some(header) = self.authorities[authority as usize].proposer.propose(force, now) else {
+            return
+        };
+        debug!(target: "consensus::narwhal", authority, round = header.round, "Simulated header");
+        let aggregator = VotesAggregator::new(header.clone(), &self.domain);
+        self.authorities[authority as usize].aggregator = Some(aggregator);
+        let vote = self.vote(authority, &header);
+        self.receive_vote(authority, vote);
+        self.broadcast(authority, NarwhalMessage::Header(header));
+    }
+
+    /// Returns the signed vote of an authority for a header.
+    fn vote(&self, voter: AuthorityIndex, header: &Header) -> Vote {
+        let digest = header.digest();
+        let message = self.domain.signing_message(Intent::Vote, digest.0);
+        let secret_key = self.dev.secret_key(voter).expect("voter of the committee");
+        let signature = Bls12381::sign(secret_key, message.as_slice());
+        Vote {
+            header: digest,
+            epoch: header.epoch,
+            round: header.round,
+            origin: header.author,
+            voter,
+            signature: Bls12381::encode_signature(&signature).into(),
+        }
+    }
+
+    /// Votes for a header of another authority, or keeps it until its batches and parents arrive.
+    fn receive_header(&mut self, to: AuthorityIndex, header: Header) {
+        let state = &mut self.authorities[to as usize];
+        if header.round < state.dag.gc_round() {
+            return
+        }
+        let digest = header.digest();
+        if let Some(voted) = state.votes.get(&(header.round, header.author)) {
+            // an authority votes for at most one header per round and author
+            debug_assert_eq!(*voted, digest, "honest authorities don't equivocate");
+            return
+        }
+        let parents = state.dag.round(header.round - 1);
+        let available = header.payload.iter().all(|batch| state.batches.contains(&batch.digest)) &&
+            header
+                .parents
+                .iter()
+                .all(|parent| parents.iter().any(|certificate| certificate.digest() == *parent));
+        if !available {
+            state.pending.push(header);
+            return
+        }
+        state.votes.insert((header.round, header.author), digest);
+        let vote = self.vote(to, &header);
+        self.send(header.author, NarwhalMessage::Vote(vote));
+    }
+
+    /// Adds a vote to the aggregator of the own header of an authority, and broadcasts the
+    /// certificate once the votes form a quorum.
+    fn receive_vote(&mut self, to: AuthorityIndex, vote: Vote) {
+        let state = &mut self.authorities[to as usize];
+        let Some(aggregator) = &mut state.aggregator else { return };
+        let Ok(signature) = BlsSignature::try_from(vote.signature.as_ref()) else { return };
+        // votes for the headers of earlier rounds are rejected by the signature check
+        let Ok(Some(certificate)) = aggregator.add_vote(&self.verifier, vote.voter, signature)
+        else {
+            return
+        };
+        state.aggregator = None;
+        self.broadcast(to, NarwhalMessage::Certificate(certificate.clone()));
+        self.receive_certificate(to, certificate);
+    }
+
+    /// Adds a certificate to the DAG of an authority, advances its primary, commits and collects
+    /// the garbage.
+    fn receive_certificate(&mut self, to: AuthorityIndex, certificate: Certificate) {
+        let state = &mut self.authorities[to as usize];
+        let mut advanced = false;
+        for certificate in state.dag.insert(certificate) {
+            advanced |= state.proposer.add_certificate(&certificate);
+        }
+        let committee = self.dev.committee();
+        for commit in state.dag.commit(committee) {
+            debug!(
+                target: "consensus::narwhal",
+                authority = to,
+                round = commit.round,
+                leader = commit.leader,
+                certificates = commit.certificates.len(),
+                "Simulated commit"
+            );
+        }
+        let gc = gc_round(state.dag.last_committed(), self.config.gc_depth);
+        if gc > state.dag.gc_round() {
+            state.dag.prune(gc);
+            state.votes.retain(|(round, _), _| *round >= gc);
+            state.pending.retain(|header| header.round >= gc);
+        }
+
+        if advanced {
+            let round = state.proposer.round();
+            self.schedule(self.config.primary.max_header_delay, Event::HeaderTimeout(to, round));
+            self.propose(to, false);
+        }
+        self.retry_headers(to);
+    }
+
+    /// Handles the headers an authority kept, e.g. after their batches or parents arrived.
+    fn retry_headers(&mut self, authority: AuthorityIndex) {
+        let pending = std::mem::take(&mut self.authorities[authority as usize].pending);
+        for header in pending {
+            self.receive_header(authority, header);
+        }
+    }
+}
+
+/// An authority of a [`Simulation`].
+#[derive(Debug)]
+struct SimAuthority {
+    proposer: Proposer,
+    dag: SimulatedDag,
+    /// The batches the worker of the authority stored.
+    batches: HashSet<BatchDigest>,
+    /// The headers the authority voted for, by round and author.
+    votes: HashMap<(Round, AuthorityIndex), HeaderDigest>,
+    /// The headers of other authorities whose batches or parents are missing.
+    pending: Vec<Header>,
+    /// Collects the votes for the last header of the authority until it's certified.
+    aggregator: Option<VotesAggregator<Bls12381>>,
+}
@@ -0,0 +1,375 @@
+//! Detection of stalled consensus.
+//!
+//! A validator that neither advances rounds nor commits is idle without any visible error. The
+//! [`StallDetector`] notices this and reports a [`StallEvent`] with the likely causes, derived from
+//! a [`NodeHealth`] snapshot of the node. A node runs it with [`StallDetector::run`], which follows
+//! the progress of consensus in the [`NarwhalEvents`](crate::NarwhalEvents).
+
+use crate::events::NarwhalEvent;
+use reth_narwhal_verifier::Stake;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::error;
+
+/// Configuration of the [`StallDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StallDetectorConfig {
+    /// How long the round may not advance before consensus is considered stalled.
+    #[serde(with = "humantime_serde")]
+    pub round_timeout: Duration,
+    /// How long no sub-dag may be committed before consensus is considered stalled.
+    #[serde(with = "humantime_serde")]
+    pub commit_timeout: Duration,
+    /// How often [`StallDetector::run`] checks for a stall.
+    #[serde(with = "humantime_serde")]
+    pub check_interval: Duration,
+    /// Clock skew to other authorities above which it is reported as a possible cause.
+    #[serde(with = "humantime_serde")]
+    pub max_clock_skew: Duration,
+    /// Number of commits waiting for execution above which the backlog is reported as a possible
+    /// cause.
+    pub max_execution_backlog: usize,
+}
+
+impl Default for StallDetectorConfig {
+    fn default() -> Self {
+        Self {
+            round_timeout: Duration::from_secs(30),
+            commit_timeout: Duration::from_secs(60),
+            check_interval: Duration::from_secs(5),
+            max_clock_skew: Duration::from_millis(500),
+            max_execution_backlog: 16,
+        }
+    }
+}
+
+/// A snapshot of the node's state that is used to diagnose a stall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeHealth {
+    /// Combined stake of the authorities the node is connected to, including its own.
+    pub connected_stake: Stake,
+    /// The stake required for a quorum.
+    pub quorum_threshold: Stake,
+    /// Largest offset between the local clock and the timestamps of other authorities' headers.
+    pub clock_skew: Duration,
+    /// Time since the consensus store last completed a write, if it has a write in flight.
+    pub pending_store_write: Option<Duration>,
+    /// Number of commits waiting for execution.
+    pub execution_backlog: usize,
+}
+
+/// What stopped making progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallKind {
+    /// The round did not advance.
+    Round,
+    /// No sub-dag was committed.
+    Commit,
+}
+
+/// A likely cause of a stall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StallCause {
+    /// The node is not connected to a quorum of the committee.
+    LostQuorum {
+        /// Combined stake of the connected authorities.
+        connected_stake: Stake,
+        /// The stake required for a quorum.
+        quorum_threshold: Stake,
+    },
+    /// The local clock deviates from the clocks of other authorities.
+    ClockSkew {
+        /// The observed skew.
+        skew: Duration,
+    },
+    /// A write to the consensus store did not complete.
+    StorageStall {
+        /// How long the write has been pending.
+        pending_for: Duration,
+    },
+    /// Execution does not keep up with consensus.
+    ExecutionBacklog {
+        /// Number of commits waiting for execution.
+        pending: usize,
+    },
+}
+
+impl fmt::Display for StallCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LostQuorum { connected_stake, quorum_threshold } => write!(
+                f,
+                "lost quorum: connected to {connected_stake} stake, {quorum_threshold} required, \
+                 check peer connectivity and firewall rules"
+            ),
+            Self::ClockSkew { skew } => {
+                write!(f, "clock skew of {skew:?} to other authorities, check NTP synchronization")
+            }
+            Self::StorageStall { pending_for } => write!(
+                f,
+                "storage write pending for {pending_for:?}, check disk health and free space"
+            ),
+            Self::ExecutionBacklog { pending } => write!(
+                f,
+                "{pending} commits waiting for execution, check whether execution is paused or slow"
+            ),
+        }
+    }
+}
+
+/// Reported when consensus did not make progress for the configured timeout.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StallEvent {
+    /// What stopped making progress.
+    pub kind: StallKind,
+    /// The last round the node was in.
+    pub round: u64,
+    /// How long no progress was made.
+    pub stalled_for: Duration,
+    /// The likely causes, empty if none of the known causes applies.
+    pub causes: Vec<StallCause>,
+}
+
+impl StallEvent {
+    /// Logs the event as an error.
+    pub fn log(&self) {
+        let causes = self.causes.iter().map(ToString::to_string).collect::<Vec<_>>();
+        error!(
+            target: "consensus::narwhal",
+            kind = ?self.kind,
+            round = self.round,
+            stalled_for = ?self.stalled_for,
+            ?causes,
+            "Consensus stalled"
+        );
+    }
+}
+
+/// Detects when rounds stop advancing or commits stop happening.
+///
+/// The detector is driven by the caller: progress is reported with [`StallDetector::on_round`]
+/// and [`StallDetector::on_commit`], and [`StallDetector::check`] is called periodically. A stall
+/// is reported once per timeout for as long as it lasts.
+#[derive(Debug)]
+pub struct StallDetector {
+    config: StallDetectorConfig,
+    round: u64,
+    last_round_advance: Instant,
+    last_commit: Instant,
+    last_report: Option<Instant>,
+}
+
+impl StallDetector {
+    /// Creates a new detector, treating `now` as the time of the last progress.
+    pub const fn new(config: StallDetectorConfig, now: Instant) -> Self {
+        Self { config, round: 0, last_round_advance: now, last_commit: now, last_report: None }
+    }
+
+    /// Records that the node advanced to the given round.
+    pub fn on_round(&mut self, round: u64, now: Instant) {
+        if round > self.round {
+            self.round = round;
+            self.last_round_advance = now;
+            self.last_report = None;
+        }
+    }
+
+    /// Records that a sub-dag was committed.
+    pub fn on_commit(&mut self, now: Instant) {
+        self.last_commit = now;
+        self.last_report = None;
+    }
+
+    /// Returns a [`StallEvent`] if a stall is due to be reported.
+    pub fn check(&mut self, now: Instant, health: &NodeHealth) -> Option<StallEvent> {
+        let since_round = now.saturating_duration_since(self.last_round_advance);
+        let since_commit = now.saturating_duration_since(self.last_commit);
+        let (kind, stalled_for, timeout) = if since_round >= self.config.round_timeout {
+            (StallKind::Round, since_round, self.config.round_timeout)
+        } else if since_commit >= self.config.commit_timeout {
+            (StallKind::Commit, since_commit, self.config.commit_timeout)
+        } else {
+            return None
+        };
+
+        if self.last_report.is_some_and(|last| now.saturating_duration_since(last) < timeout) {
+            return None
+        }
+        self.last_report = Some(now);
+
+        Some(StallEvent { kind, round: self.round, stalled_for, causes: self.causes(health) })
+    }
+
+    /// Follows the progress of consensus in the events and reports a stall whenever one is due,
+    /// e.g. with [`StallEvent::log`], until the events are closed.
+    ///
+    /// A certificate formed or a header proposed in a new round advances the round, a committed
+    /// sub-dag counts as a commit. The health of the node is taken at every check.
+    pub async fn run(
+        mut self,
+        mut events: broadcast::Receiver<NarwhalEvent>,
+        health: impl Fn() -> NodeHealth,
+        mut report: impl FnMut(StallEvent),
+    ) {
+        let mut interval = tokio::time::interval(self.config.check_interval);
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(NarwhalEvent::CertificateFormed { round, .. } |
+                        NarwhalEvent::HeaderProposed { round, .. }) => {
+                        self.on_round(round, tokio::time::Instant::now().into_std());
+                    }
+                    Ok(NarwhalEvent::SubDagCommitted(_)) => {
+                        self.on_commit(tokio::time::Instant::now().into_std());
+                    }
+                    // missed events only delay the progress to the next event
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                },
+                now = interval.tick() => {
+                    if let Some(event) = self.check(now.into_std(), &health()) {
+                        report(event);
+                    }
+                }
+            }
+        }
+    }
+
+    fn causes(&self, health: &NodeHealth) -> Vec<StallCause> {
+        let mut causes = Vec::new();
+        if health.connected_stake < health.quorum_threshold {
+            causes.push(StallCause::LostQuorum {
+                connected_stake: health.connected_stake,
+                quorum_threshold: health.quorum_threshold,
+            });
+        }
+        if health.clock_skew > self.config.max_clock_skew {
+            causes.push(StallCause::ClockSkew { skew: health.clock_skew });
+        }
+        if let Some(pending_for) =
+            health.pending_store_write.filter(|pending| *pending >= self.config.round_timeout)
+        {
+            causes.push(StallCause::StorageStall { pending_for });
+        }
+        if health.execution_backlog > self.config.max_execution_backlog {
+            causes.push(StallCause::ExecutionBacklog { pending: health.execution_backlog });
+        }
+        causes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NarwhalEvents;
+
+    const HEALTHY: NodeHealth = NodeHealth {
+        connected_stake: 4,
+        quorum_threshold: 3,
+        clock_skew: Duration::ZERO,
+        pending_store_write: None,
+        execution_backlog: 0,
+    };
+
+    #[test]
+    fn report_round_stall_once_per_timeout() {
+        let start = Instant::now();
+        let config = StallDetectorConfig::default();
+        let mut detector = StallDetector::new(config, start);
+
+        let at = |secs| start + Duration::from_secs(secs);
+        detector.on_round(1, at(10));
+        detector.on_commit(at(10));
+        assert_eq!(detector.check(at(39), &HEALTHY), None);
+
+        let event = detector.check(at(40), &HEALTHY).unwrap();
+        assert_eq!(event.kind, StallKind::Round);
+        assert_eq!(event.round, 1);
+        assert_eq!(event.stalled_for, config.round_timeout);
+        assert!(event.causes.is_empty());
+
+        assert_eq!(detector.check(at(41), &HEALTHY), None);
+        assert!(detector.check(at(70), &HEALTHY).is_some());
+
+        // progress resets the stall
+        detector.on_round(2, at(71));
+        detector.on_commit(at(71));
+        assert_eq!(detector.check(at(72), &HEALTHY), None);
+    }
+
+    #[test]
+    fn report_commit_stall() {
+        let start = Instant::now();
+        let mut detector = StallDetector::new(StallDetectorConfig::default(), start);
+        for round in 1..=7 {
+            detector.on_round(round, start + Duration::from_secs(round * 10));
+        }
+        let event = detector.check(start + Duration::from_secs(75), &HEALTHY).unwrap();
+        assert_eq!(event.kind, StallKind::Commit);
+
+        detector.on_commit(start + Duration::from_secs(76));
+        assert_eq!(detector.check(start + Duration::from_secs(77), &HEALTHY), None);
+    }
+
+    #[tokio::test]
+    async fn run_on_events() {
+        let config = StallDetectorConfig {
+            round_timeout: Duration::from_millis(100),
+            commit_timeout: Duration::from_secs(60),
+            check_interval: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let events = NarwhalEvents::new();
+        let detector = StallDetector::new(config, Instant::now());
+        let (stalls, mut stalled) = tokio::sync::mpsc::unbounded_channel();
+        let run = tokio::spawn(detector.run(
+            events.subscribe(),
+            || HEALTHY,
+            move |event| {
+                let _ = stalls.send(event);
+            },
+        ));
+
+        events.publish(NarwhalEvent::CertificateFormed {
+            round: 3,
+            author: 0,
+            digest: Default::default(),
+        });
+        let event = tokio::time::timeout(Duration::from_secs(5), stalled.recv()).await.unwrap();
+        let event = event.unwrap();
+        assert_eq!((event.kind, event.round), (StallKind::Round, 3));
+        assert!(event.stalled_for >= config.round_timeout);
+
+        // the detector stops with the events
+        drop(events);
+        tokio::time::timeout(Duration::from_secs(5), run).await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn diagnose_causes() {
+        let start = Instant::now();
+        let mut detector = StallDetector::new(StallDetectorConfig::default(), start);
+        let health = NodeHealth {
+            connected_stake: 2,
+            quorum_threshold: 3,
+            clock_skew: Duration::from_secs(2),
+            pending_store_write: Some(Duration::from_secs(45)),
+            execution_backlog: 100,
+        };
+        let event = detector.check(start + Duration::from_secs(60), &health).unwrap();
+        assert_eq!(
+            event.causes,
+            vec![
+                StallCause::LostQuorum { connected_stake: 2, quorum_threshold: 3 },
+                StallCause::ClockSkew { skew: Duration::from_secs(2) },
+                StallCause::StorageStall { pending_for: Duration::from_secs(45) },
+                StallCause::ExecutionBacklog { pending: 100 },
+            ]
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Snapshots of the consensus state, to bootstrap new validators.
+//!
+//! A validator that joins a running chain would otherwise have to fetch the certificate history
+//! from its peers before it can take part in consensus. Instead, `reth narwhal snapshot export`
+//! writes a [`ConsensusSnapshot`] of a synced validator into a single file: the certificates and
+//! batches of its [`DagStore`], the last committed round of every authority, the records of the
+//! committed sub-dags, which identify the last committed index, and the committees of all epochs.
+//! `reth narwhal snapshot import` writes the snapshot into the stores of the new validator, which
+//! then resumes from the rounds of the snapshot.
+//!
+//! A snapshot is trusted like the data directory it was exported from: its certificates are not
+//! verified again on import. Votes are not exported, they are specific to the validator that cast
+//! them.
+//!
+//! [`DagStore`]: crate::dag_store::DagStore
+
+use crate::{
+    committee::Committee,
+    committee_history::{CommitteeHistory, CommitteeHistoryError, EpochCommittee},
+    dag_store::{DagStore, DagStoreError, StoredSubDag},
+    types::{Batch, BatchDigest, Certificate, CertificateDigest, Round},
+};
+use reth_narwhal_verifier::{AuthorityIndex, Epoch};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// The version of the snapshot format written by [`ConsensusSnapshot::write`].
+pub const SNAPSHOT_VERSION: u64 = 1;
+
+/// Errors of exporting and importing a [`ConsensusSnapshot`].
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    /// The snapshot was written in a format this version can't read.
+    #[error("unsupported snapshot version {0}, expected {SNAPSHOT_VERSION}")]
+    UnsupportedVersion(u64),
+    /// The snapshot has no committee.
+    #[error("snapshot has no committee")]
+    NoCommittee,
+    /// The committees of the snapshot are not of consecutive epochs.
+    #[error("committee of epoch {epoch} does not follow the committee of epoch {previous}")]
+    NonConsecutiveEpoch {
+        /// The epoch of the committee.
+        epoch: Epoch,
+        /// The epoch of the previous committee.
+        previous: Epoch,
+    },
+    /// A certificate is of an epoch without a committee in the snapshot.
+    #[error("certificate {digest} is of epoch {epoch}, which has no committee in the snapshot")]
+    UnknownEpoch {
+        /// The digest of the certificate.
+        digest: CertificateDigest,
+        /// The epoch of the certificate.
+        epoch: Epoch,
+    },
+    /// The committee history already has a different committee for an epoch of the snapshot.
+    #[error("committee history has a different committee for epoch {0}")]
+    CommitteeMismatch(Epoch),
+    /// The DAG store of the importing validator already has certificates or sub-dags.
+    #[error("DAG store is not empty")]
+    StoreNotEmpty,
+    /// The DAG store failed.
+    #[error(transparent)]
+    Store(#[from] DagStoreError),
+    /// The committee history failed.
+    #[error(transparent)]
+    History(#[from] CommitteeHistoryError),
+    /// Reading or writing the snapshot failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// The consensus state of a validator, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsensusSnapshot {
+    /// The version of the snapshot format.
+    pub version: u64,
+    /// The committees of all epochs, in ascending order.
+    pub committees: Vec<EpochCommittee<Committee>>,
+    /// The round of the last committed certificate of each authority.
+    pub last_committed: BTreeMap<AuthorityIndex, Round>,
+    /// The records of the committed sub-dags, in commit order.
+    pub sub_dags: Vec<StoredSubDag>,
+    /// The stored certificates, ordered by round and author.
+    pub certificates: Vec<Certificate>,
+    /// The stored batches referenced by the certificates.
+    pub batches: Vec<Batch>,
+}
+
+impl ConsensusSnapshot {
+    /// Exports the state of a DAG store and the committees of all epochs.
+    pub fn export<'a>(
+        store: &dyn DagStore,
+        committees: impl IntoIterator<Item = &'a EpochCommittee<Committee>>,
+    ) -> Result<Self, SnapshotError> {
+        let certificates = store.certificates(0)?;
+        // batches of executed certificates may already be pruned
+        let digests = certificates
+            .iter()
+            .flat_map(|certificate| &certificate.header.payload)
+            .map(|batch| batch.digest)
+            .collect::<BTreeSet<BatchDigest>>();
+        let mut batches = Vec::with_capacity(digests.len());
+        for digest in digests {
+            batches.extend(store.batch(digest)?);
+        }
+
+        Ok(Self {
+            version: SNAPSHOT_VERSION,
+            committees: committees.into_iter().cloned().collect(),
+            last_committed: store.last_committed()?,
+            sub_dags: store.sub_dags(0)?,
+            certificates,
+            batches,
+        })
+    }
+
+    /// Returns the latest epoch of the snapshot.
+    pub fn epoch(&self) -> Option<Epoch> {
+        self.committees.last().map(|record| record.epoch)
+    }
+
+    /// Returns the index of the first sub-dag committed after the snapshot.
+    pub fn next_sub_dag(&self) -> u64 {
+        self.sub_dags.last().map_or(0, |sub_dag| sub_dag.index + 1)
+    }
+
+    /// Returns the highest round of the certificates of the snapshot.
+    pub fn highest_round(&self) -> Option<Round> {
+        self.certificates.iter().map(Certificate::round).max()
+    }
+
+    /// Checks that the snapshot is complete and consistent.
+    pub fn validate(&self) -> Result<(), SnapshotError> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(self.version))
+        }
+        if self.committees.is_empty() {
+            return Err(SnapshotError::NoCommittee)
+        }
+        for pair in self.committees.windows(2) {
+            let (previous, epoch) = (pair[0].epoch, pair[1].epoch);
+            if previous.checked_add(1) != Some(epoch) {
+                return Err(SnapshotError::NonConsecutiveEpoch { epoch, previous })
+            }
+        }
+        let epochs = self.committees.iter().map(|record| record.epoch).collect::<BTreeSet<_>>();
+        if let Some(certificate) =
+            self.certificates.iter().find(|certificate| !epochs.contains(&certificate.header.epoch))
+        {
+            return Err(SnapshotError::UnknownEpoch {
+                digest: certificate.digest(),
+                epoch: certificate.header.epoch,
+            })
+        }
+        Ok(())
+    }
+
+    /// Imports the snapshot into the empty DAG store and the committee history of a new
+    /// validator.
+    ///
+    /// The committees that the history already has must match the ones of the snapshot.
+    pub fn import(
+        &self,
+        store: &dyn DagStore,
+        history: &mut CommitteeHistory<Committee>,
+    ) -> Result<(), SnapshotError> {
+        self.validate()?;
+        if !store.certificates(0)?.is_empty() || !store.sub_dags(0)?.is_empty() {
+            return Err(SnapshotError::StoreNotEmpty)
+        }
+        for record in &self.committees {
+            match history.epoch(record.epoch) {
+                Some(existing) if existing == record => {}
+                Some(_) => return Err(SnapshotError::CommitteeMismatch(record.epoch)),
+                None => {
+                    history.insert(record.epoch, record.first_block, record.committee.clone())?
+                }
+            }
+        }
+
+        store.reset(&self.last_committed)?;
+        for batch in &self.batches {
+            store.write_batch(batch.digest(), batch)?;
+        }
+        for certificate in &self.certificates {
+            store.write_certificate(certificate)?;
+        }
+        for sub_dag in &self.sub_dags {
+            store.write_sub_dag(sub_dag)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the snapshot to a file as JSON, replacing an existing file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        // write to a temporary file first, so a crash never leaves a partial snapshot behind
+        let tmp = path.with_extension("tmp");
+        let mut file = BufWriter::new(fs::File::create(&tmp)?);
+        serde_json::to_writer(&mut file, self).map_err(io::Error::from)?;
+        file.write_all(b"\n")?;
+        file.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Reads a snapshot written by [`ConsensusSnapshot::write`].
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let file = BufReader::new(fs::File::open(path)?);
+        serde_json::from_reader(file).map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dag_store::MemoryDagStore,
+        dev::DevCommittee,
+        types::{BatchRef, Header},
+    };
+    use alloy_primitives::Bytes;
+
+    fn committee(epoch: Epoch, first_block: u64) -> EpochCommittee<Committee> {
+        let mut committee = DevCommittee::new(4).committee().clone();
+        committee.epoch = epoch;
+        EpochCommittee { epoch, first_block, committee }
+    }
+
+    /// A store with two rounds of epoch 1, whose first round is committed.
+    fn store() -> MemoryDagStore {
+        let store = MemoryDagStore::default();
+        for round in 1..=2 {
+            for author in 0..4 {
+                let batch = Batch::new(vec![Bytes::from(vec![round as u8, author as u8])]);
+                store.write_batch(batch.digest(), &batch).unwrap();
+                let header = Header {
+                    epoch: 1,
+                    round,
+                    author,
+                    payload: vec![BatchRef { digest: batch.digest(), worker: 0 }],
+                    ..Default::default()
+                };
+                let certificate = Certificate { header, ..Default::default() };
+                store.write_vote(&certificate.header).unwrap();
+                store.write_certificate(&certificate).unwrap();
+            }
+        }
+        for author in 0..4 {
+            store.write_last_committed(author, 1).unwrap();
+        }
+        let leader = store.certificates(1).unwrap()[0].digest();
+        let sub_dag = StoredSubDag {
+            index: 6,
+            round: 1,
+            leader,
+            certificates: vec![leader],
+            timestamp: 1_700_000_000,
+        };
+        store.write_sub_dag(&sub_dag).unwrap();
+        store
+    }
+
+    #[test]
+    fn export_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let committees = [committee(0, 0), committee(1, 100)];
+        let source = store();
+        let snapshot = ConsensusSnapshot::export(&source, &committees).unwrap();
+        assert_eq!((snapshot.epoch(), snapshot.next_sub_dag()), (Some(1), 7));
+        assert_eq!((snapshot.certificates.len(), snapshot.batches.len()), (8, 8));
+        assert_eq!(snapshot.highest_round(), Some(2));
+
+        let path = dir.path().join("snapshot.json");
+        snapshot.write(&path).unwrap();
+        let read = ConsensusSnapshot::read(&path).unwrap();
+        assert_eq!(read, snapshot);
+
+        // the new validator already knows the genesis committee
+        let target = MemoryDagStore::default();
+        let mut history = CommitteeHistory::open(dir.path().join("committees")).unwrap();
+        history.insert(0, 0, committee(0, 0).committee).unwrap();
+        read.import(&target, &mut history).unwrap();
+
+        assert_eq!(target.recover().unwrap(), source.recover().unwrap());
+        assert_eq!(target.sub_dags(0).unwrap(), source.sub_dags(0).unwrap());
+        let batch = &snapshot.batches[3];
+        assert_eq!(target.batch(batch.digest()).unwrap().as_ref(), Some(batch));
+        assert_eq!(target.vote(2, 0).unwrap(), None);
+        assert_eq!(history.latest(), Some(&committees[1]));
+
+        // a snapshot is only imported into an empty store
+        let mut history = CommitteeHistory::open(dir.path().join("committees")).unwrap();
+        assert!(matches!(read.import(&target, &mut history), Err(SnapshotError::StoreNotEmpty)));
+    }
+
+    #[test]
+    fn reject_inconsistent_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot = ConsensusSnapshot::export(&store(), &[committee(1, 100)]).unwrap();
+        assert!(snapshot.validate().is_ok());
+
+        let future = ConsensusSnapshot { version: SNAPSHOT_VERSION + 1, ..snapshot.clone() };
+        assert!(matches!(future.validate(), Err(SnapshotError::UnsupportedVersion(2))));
+
+        let gap = ConsensusSnapshot {
+            committees: vec![committee(1, 100), committee(3, 300)],
+            ..snapshot.clone()
+        };
+        assert!(matches!(
+            gap.validate(),
+            Err(SnapshotError::NonConsecutiveEpoch { epoch: 3, previous: 1 })
+        ));
+
+        let unknown = ConsensusSnapshot { committees: vec![committee(0, 0)], ..snapshot.clone() };
+        assert!(matches!(unknown.validate(), Err(SnapshotError::UnknownEpoch { epoch: 1, .. })));
+
+        // the history of the new validator disagrees about epoch 1
+        let mut history = CommitteeHistory::open(dir.path()).unwrap();
+        history.insert(1, 50, committee(1, 50).committee).unwrap();
+        let result = snapshot.import(&MemoryDagStore::default(), &mut history);
+        assert!(matches!(result, Err(SnapshotError::CommitteeMismatch(1))));
+    }
+}
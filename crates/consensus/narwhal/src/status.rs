@@ -0,0 +1,202 @@
+//! Inclusion status of sequenced transactions.
+
+use crate::{
+    memory::MemoryHandle,
+    sequencing::{SkipReason, SkippedTransaction},
+    types::TransactionExpiry,
+};
+use parking_lot::Mutex;
+use reth_primitives::{BlockNumber, TxHash, B256};
+use schnellru::{ByLength, LruMap};
+use serde::{Deserialize, Serialize};
+use std::{mem, sync::Arc};
+
+/// The estimated size of an entry of [`DroppedTransactions`].
+const DROPPED_ENTRY_SIZE: usize = mem::size_of::<(TxHash, (BlockNumber, SkipReason))>();
+
+/// The status of a transaction that was sequenced by narwhal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum TransactionStatus {
+    /// The transaction was executed and included in a block.
+    #[serde(rename_all = "camelCase")]
+    Included {
+        /// Number of the block that includes the transaction.
+        block_number: BlockNumber,
+        /// Hash of the block that includes the transaction.
+        block_hash: B256,
+    },
+    /// The transaction was sequenced, but excluded from the block derived from its commit.
+    #[serde(rename_all = "camelCase")]
+    Dropped {
+        /// Number of the block derived from the commit that sequenced the transaction.
+        block_number: BlockNumber,
+        /// Why the transaction was excluded.
+        reason: String,
+    },
+    /// The transaction was sequenced after its expiry, and excluded from the block derived from
+    /// its commit.
+    #[serde(rename_all = "camelCase")]
+    Expired {
+        /// Number of the block derived from the commit that sequenced the transaction.
+        block_number: BlockNumber,
+        /// The expiry the transaction was submitted with.
+        expiry: TransactionExpiry,
+    },
+}
+
+/// A bounded record of recently dropped transactions.
+///
+/// Dropped transactions leave no trace in the chain, so the component deriving blocks records them
+/// here and the RPC layer consults it for transactions it can't find in the database. A dropped
+/// transaction can be resubmitted and included later, which is why the database must be checked
+/// first.
+///
+/// With a [`MemoryHandle`], the record is charged to the
+/// [`ExecutionResults`](crate::memory::MemoryComponent::ExecutionResults) component of the memory
+/// budget and evicts its oldest entries while the budget is exceeded.
+#[derive(Debug, Clone)]
+pub struct DroppedTransactions {
+    inner: Arc<Mutex<LruMap<TxHash, (BlockNumber, SkipReason)>>>,
+    memory: Option<MemoryHandle>,
+}
+
+impl DroppedTransactions {
+    /// Creates a new record that remembers at most `capacity` dropped transactions.
+    pub fn new(capacity: u32) -> Self {
+        Self { inner: Arc::new(Mutex::new(LruMap::new(ByLength::new(capacity)))), memory: None }
+    }
+
+    /// Charges the record to the memory budget.
+    ///
+    /// The handle should belong to the
+    /// [`ExecutionResults`](crate::memory::MemoryComponent::ExecutionResults) component, and the
+    /// record should be empty.
+    pub fn with_memory_budget(mut self, memory: MemoryHandle) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Records the transactions that were dropped when deriving the block with the given number.
+    ///
+    /// Replays of transactions that were already executed are not recorded, their status is the
+    /// status of the block that executed them.
+    pub fn insert(
+        &self,
+        block_number: BlockNumber,
+        skipped: impl IntoIterator<Item = SkippedTransaction>,
+    ) {
+        let mut inner = self.inner.lock();
+        let len = inner.len();
+        for transaction in skipped {
+            if matches!(transaction.reason, SkipReason::AlreadyExecuted { .. }) {
+                continue
+            }
+            inner.insert(transaction.hash, (block_number, transaction.reason));
+        }
+        let Some(memory) = &self.memory else { return };
+
+        // entries replaced within the capacity don't change the usage
+        memory.charge(inner.len().saturating_sub(len) * DROPPED_ENTRY_SIZE);
+        let mut excess = memory.excess();
+        while excess > 0 && inner.pop_oldest().is_some() {
+            memory.evict(DROPPED_ENTRY_SIZE);
+            excess = excess.saturating_sub(DROPPED_ENTRY_SIZE);
+        }
+    }
+
+    /// Returns the [`TransactionStatus::Dropped`] or [`TransactionStatus::Expired`] status of the
+    /// transaction, if it was recently dropped.
+    pub fn status(&self, hash: &TxHash) -> Option<TransactionStatus> {
+        self.inner.lock().peek(hash).map(|(block_number, reason)| match reason {
+            SkipReason::Expired(expiry) => {
+                TransactionStatus::Expired { block_number: *block_number, expiry: *expiry }
+            }
+            reason => TransactionStatus::Dropped {
+                block_number: *block_number,
+                reason: reason.to_string(),
+            },
+        })
+    }
+}
+
+impl Default for DroppedTransactions {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{MemoryBudget, MemoryBudgetConfig, MemoryComponent};
+    use reth_primitives::Address;
+
+    #[test]
+    fn dropped_transaction_status() {
+        let dropped = DroppedTransactions::new(1);
+        let skipped = |hash| SkippedTransaction {
+            hash,
+            sender: Address::ZERO,
+            nonce: 1,
+            reason: SkipReason::NonceGap { expected: 0 },
+        };
+
+        dropped.insert(7, [skipped(TxHash::with_last_byte(1))]);
+        assert_eq!(
+            dropped.status(&TxHash::with_last_byte(1)),
+            Some(TransactionStatus::Dropped {
+                block_number: 7,
+                reason: "nonce gap, next nonce is 0".to_string()
+            })
+        );
+
+        // oldest entries are evicted
+        dropped.insert(8, [skipped(TxHash::with_last_byte(2))]);
+        assert_eq!(dropped.status(&TxHash::with_last_byte(1)), None);
+
+        let expiry = TransactionExpiry::Round(4);
+        let expired =
+            SkippedTransaction { reason: SkipReason::Expired(expiry), ..skipped(B256::ZERO) };
+        dropped.insert(9, [expired]);
+        assert_eq!(
+            dropped.status(&B256::ZERO),
+            Some(TransactionStatus::Expired { block_number: 9, expiry })
+        );
+    }
+
+    #[test]
+    fn evict_over_budget() {
+        let budget = MemoryBudget::new(MemoryBudgetConfig { max_bytes: 3 * DROPPED_ENTRY_SIZE });
+        let dropped = DroppedTransactions::new(10)
+            .with_memory_budget(budget.handle(MemoryComponent::ExecutionResults));
+        let skipped = |hash: u8| SkippedTransaction {
+            hash: TxHash::with_last_byte(hash),
+            sender: Address::ZERO,
+            nonce: 1,
+            reason: SkipReason::NonceGap { expected: 0 },
+        };
+
+        dropped.insert(7, (1..=3).map(skipped));
+        assert_eq!(budget.total(), 3 * DROPPED_ENTRY_SIZE);
+        dropped.insert(8, (4..=5).map(skipped));
+        assert_eq!(budget.total(), 3 * DROPPED_ENTRY_SIZE);
+        assert_eq!(dropped.status(&TxHash::with_last_byte(2)), None);
+        assert!(dropped.status(&TxHash::with_last_byte(3)).is_some());
+    }
+
+    #[test]
+    fn serialize_status() {
+        let status = TransactionStatus::Dropped { block_number: 1, reason: "reason".to_string() };
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"status":"dropped","blockNumber":1,"reason":"reason"}"#
+        );
+        let status =
+            TransactionStatus::Expired { block_number: 1, expiry: TransactionExpiry::Timestamp(2) };
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"status":"expired","blockNumber":1,"expiry":{"timestamp":2}}"#
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! Utilities to test the consensus core against faulty authorities.
+//!
+//! A [`SimulatedCommittee`] runs the protocol of a whole committee in lockstep rounds, in a single
+//! thread and without timers: in every round, each authority broadcasts a batch, proposes a header
+//! on the certificates of the previous round, votes for the headers of the others, and commits the
+//! leaders its DAG supports. Any authority can be made faulty with a [`ByzantineBehavior`], so that
+//! tests can check that the honest authorities keep committing, and commit the same leaders, as
+//! long as at most `f` of the `3f + 1` authorities misbehave.
+//!
+//! The authorities sign with the keys of the [`DevCommittee`], and every certificate is verified
+//! before it is added to the [`SimulatedDag`] of an authority, which runs the [`Bullshark`] commit
+//! rule of the validators.
+
+use crate::{
+    bullshark::Bullshark,
+    committee::Committee,
+    dag::{Dag, DagError},
+    dev::DevCommittee,
+    leader::{LeaderElector, LeaderSchedule},
+    signature::{AggregateScheme, Bls12381, BlsPublicKey, VotesAggregator},
+    types::{
+        Batch, BatchDigest, BatchRef, Certificate, CertificateDigest, DagVertex, Header,
+        HeaderDigest, Round,
+    },
+    wire::{NarwhalMessage, MAX_WIRE_VERSION},
+};
+use alloy_primitives::{Bytes, B256};
+use reth_narwhal_verifier::{AuthorityIndex, SigningDomain, Stake, VerifierCommittee};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A way in which a faulty authority of a [`SimulatedCommittee`] deviates from the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByzantineBehavior {
+    /// Proposes two different headers in every round, each to one half of the committee, and
+    /// votes for both.
+    Equivocate,
+    /// Never votes for the headers of the other authorities.
+    WithholdVotes,
+    /// Delivers the certificates of its headers to the other authorities late.
+    DelayCertificates {
+        /// The number of rounds the certificates are late.
+        rounds: Round,
+    },
+    /// Broadcasts batches that fail to decode, and proposes headers that reference them.
+    MalformedBatches,
+}
+
+/// A leader committed by a [`SimulatedDag`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedCommit {
+    /// The round of the leader.
+    pub round: Round,
+    /// The leader.
+    pub leader: AuthorityIndex,
+    /// The newly committed certificates in commit order, ending with the leader's.
+    pub certificates: Vec<CertificateDigest>,
+}
+
+/// A committee whose authorities run the protocol in lockstep rounds, see the
+/// [module docs](self).
+#[derive(Debug)]
+pub struct SimulatedCommittee {
+    dev: DevCommittee,
+    verifier: VerifierCommittee<BlsPublicKey>,
+    domain: SigningDomain,
+    authorities: Vec<SimulatedAuthority>,
+    /// The certificates on their way, with the round they are delivered in and their recipient.
+    in_flight: Vec<(Round, AuthorityIndex, Certificate)>,
+    /// Every certificate formed so far.
+    certificates: Vec<Certificate>,
+    round: Round,
+}
+
+impl SimulatedCommittee {
+    /// Creates a committee of `size` honest authorities with equal stakes, at the genesis round.
+    ///
+    /// # Panics
+    ///
+    /// If `size` is zero.
+    pub fn new(size: usize) -> Self {
+        let dev = DevCommittee::new(size);
+        let verifier = dev.committee().verifier_committee().expect("dev committee keys are valid");
+        let authorities = (0..size as AuthorityIndex)
+            .map(|index| SimulatedAuthority::new(index, dev.committee()))
+            .collect();
+        Self {
+            dev,
+            verifier,
+            domain: SigningDomain::new(1337, B256::ZERO),
+            authorities,
+            in_flight: Vec::new(),
+            certificates: Vec::new(),
+            round: 0,
+        }
+    }
+
+    /// Makes an authority faulty. Behaviors of the same authority add up.
+    ///
+    /// # Panics
+    ///
+    /// If the authority is not part of the committee.
+    pub fn with_behavior(mut self, authority: AuthorityIndex, behavior: ByzantineBehavior) -> Self {
+        self.authorities[authority as usize].behaviors.push(behavior);
+        self
+    }
+
+    /// Returns the committee.
+    pub const fn committee(&self) -> &Committee {
+        self.dev.committee()
+    }
+
+    /// Returns the last round that was run.
+    pub const fn round(&self) -> Round {
+        self.round
+    }
+
+    /// Returns `true` if the authority follows the protocol.
+    pub fn is_honest(&self, authority: AuthorityIndex) -> bool {
+        self.authorities.get(authority as usize).is_some_and(|state| state.behaviors.is_empty())
+    }
+
+    /// Returns the authorities that follow the protocol.
+    pub fn honest(&self) -> impl Iterator<Item = AuthorityIndex> + '_ {
+        self.authorities.iter().filter(|state| state.behaviors.is_empty()).map(|state| state.index)
+    }
+
+    /// Returns the leaders an authority committed, in commit order.
+    pub fn commits(&self, authority: AuthorityIndex) -> &[SimulatedCommit] {
+        self.authorities[authority as usize].dag.commits()
+    }
+
+    /// Returns the highest round of the DAG of an authority.
+    pub fn highest_round(&self, authority: AuthorityIndex) -> Round {
+        self.authorities[authority as usize].dag.highest_round()
+    }
+
+    /// Returns every certificate formed so far, in the order they were formed.
+    pub fn certificates(&self) -> &[Certificate] {
+        &self.certificates
+    }
+
+    /// Runs the given number of rounds.
+    pub fn run(&mut self, rounds: Round) {
+        for _ in 0..rounds {
+            self.advance();
+        }
+    }
+
+    /// Runs the next round.
+    pub fn advance(&mut self) {
+        self.round += 1;
+        let round = self.round;
+        let size = self.authorities.len() as AuthorityIndex;
+
+        let mut proposals = Vec::new();
+        for author in 0..size {
+            let digest = self.broadcast_batch(author);
+            let Some(parents) = self.authorities[author as usize].parents(&self.verifier, round)
+            else {
+                // the authority is missing certificates of the previous round
+                continue
+            };
+            let header = Header {
+                epoch: 0,
+                round,
+                author,
+                payload: vec![BatchRef { digest, worker: 0 }],
+                parents,
+                created_at: round,
+            };
+            if self.behaves(author, |behavior| behavior == ByzantineBehavior::Equivocate) {
+                let twin = Header { created_at: round + 1, ..header.clone() };
+                let half = size / 2;
+                let recipients = |range: std::ops::Range<AuthorityIndex>| {
+                    range.filter(|recipient| *recipient != author).chain([author]).collect()
+                };
+                proposals.push((header, recipients(0..half)));
+                proposals.push((twin, recipients(half..size)));
+            } else {
+                proposals.push((header, (0..size).collect()));
+            }
+        }
+
+        for (header, recipients) in proposals {
+            let Some(certificate) = self.certify(header, recipients) else { continue };
+            let delay = self.authorities[certificate.author() as usize].certificate_delay();
+            for recipient in 0..size {
+                let at = if recipient == certificate.author() { round } else { round + delay };
+                self.in_flight.push((at, recipient, certificate.clone()));
+            }
+            self.certificates.push(certificate);
+        }
+
+        let (due, later) = std::mem::take(&mut self.in_flight)
+            .into_iter()
+            .partition::<Vec<_>, _>(|(at, ..)| *at <= round);
+        self.in_flight = later;
+        for (_, recipient, certificate) in due {
+            self.authorities[recipient as usize].receive(certificate, &self.verifier, &self.domain);
+        }
+        let committee = self.dev.committee();
+        for authority in &mut self.authorities {
+            authority.dag.commit(committee);
+        }
+    }
+
+    /// Returns `true` if the authority has a behavior that matches the predicate.
+    fn behaves(&self, authority: AuthorityIndex, f: impl Fn(ByzantineBehavior) -> bool) -> bool {
+        self.authorities[authority as usize].behaviors.iter().any(|behavior| f(*behavior))
+    }
+
+    /// Broadcasts the batch of an authority for the current round, and returns its digest.
+    ///
+    /// The other authorities only store the batch if its message decodes.
+    fn broadcast_batch(&mut self, author: AuthorityIndex) -> BatchDigest {
+        let transaction = format!("round {} author {author}", self.round);
+        let batch = Batch::new(vec![Bytes::from(transaction.into_bytes())]);
+        let digest = batch.digest();
+        let mut message =
+            NarwhalMessage::Batch(batch).encode(MAX_WIRE_VERSION).expect("supported version");
+        if self.behaves(author, |behavior| behavior == ByzantineBehavior::MalformedBatches) {
+            message.pop();
+        }
+        for authority in &mut self.authorities {
+            if authority.index == author {
+                authority.batches.insert(digest);
+            } else if let Ok((_, NarwhalMessage::Batch(batch))) = NarwhalMessage::decode(&message) {
+                authority.batches.insert(batch.digest());
+            }
+        }
+        digest
+    }
+
+    /// Collects the votes of the recipients of a header, and returns its certificate if they form
+    /// a quorum.
+    fn certify(&mut self, header: Header, recipients: Vec<AuthorityIndex>) -> Option<Certificate> {
+        let mut aggregator = VotesAggregator::<Bls12381>::new(header.clone(), &self.domain);
+        let message = aggregator.message();
+        for voter in recipients {
+            if !self.authorities[voter as usize].vote(&header, &self.verifier) {
+                continue
+            }
+            let secret_key = self.dev.secret_key(voter).expect("voter of the committee");
+            let signature = Bls12381::sign(secret_key, message.as_slice());
+            let certificate = aggregator
+                .add_vote(&self.verifier, voter, signature)
+                .expect("votes of the committee are valid");
+            if certificate.is_some() {
+                return certificate
+            }
+        }
+        None
+    }
+}
+
+/// An authority of a [`SimulatedCommittee`].
+#[derive(Debug)]
+struct SimulatedAuthority {
+    index: AuthorityIndex,
+    behaviors: Vec<ByzantineBehavior>,
+    dag: SimulatedDag,
+    /// The batches the workers of the authority stored.
+    batches: HashSet<BatchDigest>,
+    /// The headers the authority voted for, by round and author.
+    votes: HashMap<(Round, AuthorityIndex), HeaderDigest>,
+}
+
+impl SimulatedAuthority {
+    fn new(index: AuthorityIndex, committee: &Committee) -> Self {
+        Self {
+            index,
+            behaviors: Vec::new(),
+            dag: SimulatedDag::new(committee),
+            batches: HashSet::new(),
+            votes: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of rounds the certificates of the authority reach the others late.
+    fn certificate_delay(&self) -> Round {
+        self.behaviors
+            .iter()
+            .find_map(|behavior| match behavior {
+                ByzantineBehavior::DelayCertificates { rounds } => Some(*rounds),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the parents of a header of the round, or `None` if the certificates of the previous
+    /// round in the DAG are not a quorum.
+    fn parents(
+        &self,
+        committee: &VerifierCommittee<BlsPublicKey>,
+        round: Round,
+    ) -> Option<Vec<CertificateDigest>> {
+        let previous = self.dag.round(round - 1);
+        let stake = previous
+            .iter()
+            .filter_map(|certificate| committee.authority(certificate.author()))
+            .fold(0, |stake: Stake, authority| stake.saturating_add(authority.stake));
+        (stake >= committee.quorum_threshold())
+            .then(|| previous.iter().map(Certificate::digest).collect())
+    }
+
+    /// Returns `true` if the authority votes for the header.
+    ///
+    /// An honest authority votes for at most one header per round and author, and only once it
+    /// stored the batches of the header and has its parents.
+    fn vote(&mut self, header: &Header, committee: &VerifierCommittee<BlsPublicKey>) -> bool {
+        if header.author == self.index {
+            return true
+        }
+        if self.behaviors.contains(&ByzantineBehavior::WithholdVotes) {
+            return false
+        }
+        let digest = header.digest();
+        if self.votes.get(&(header.round, header.author)).is_some_and(|voted| *voted != digest) {
+            return false
+        }
+        if !header.payload.iter().all(|batch| self.batches.contains(&batch.digest)) {
+            return false
+        }
+        let Some(parents) = self.parents(committee, header.round) else { return false };
+        if !header.parents.iter().all(|parent| parents.contains(parent)) {
+            return false
+        }
+        self.votes.insert((header.round, header.author), digest);
+        true
+    }
+
+    /// Verifies a certificate and adds it to the DAG.
+    fn receive(
+        &mut self,
+        certificate: Certificate,
+        committee: &VerifierCommittee<BlsPublicKey>,
+        domain: &SigningDomain,
+    ) {
+        if certificate.verify::<Bls12381>(committee, domain).is_ok() {
+            self.dag.insert(certificate);
+        }
+    }
+}
+
+/// The DAG of a simulated authority, and the [`Bullshark`] commit rule that runs over it.
+///
+/// Leaders are elected round robin.
+#[derive(Debug)]
+pub struct SimulatedDag {
+    dag: Dag,
+    /// The certificates in the DAG, by round, in the order they were inserted.
+    certificates: BTreeMap<Round, Vec<Certificate>>,
+    /// The certificates that were inserted before their parents.
+    pending: Vec<Certificate>,
+    bullshark: Bullshark,
+    commits: Vec<SimulatedCommit>,
+}
+
+impl SimulatedDag {
+    /// Creates the DAG of the committee's epoch, with the genesis certificates.
+    pub fn new(committee: &Committee) -> Self {
+        let genesis = Certificate::genesis(committee.epoch, 0..committee.authorities.len() as u32);
+        let mut dag = Dag::new(0);
+        for certificate in &genesis {
+            dag.insert(DagVertex::new(certificate.clone())).expect("genesis has no parents");
+        }
+        Self {
+            dag,
+            certificates: BTreeMap::from([(0, genesis)]),
+            pending: Vec::new(),
+            bullshark: Bullshark::new(LeaderElector::new(LeaderSchedule::RoundRobin, committee)),
+            commits: Vec::new(),
+        }
+    }
+
+    /// Returns the number of certificates in the DAG, without the dropped committed ones.
+    pub fn len(&self) -> usize {
+        self.dag.len()
+    }
+
+    /// Returns `true` if the DAG has no certificates.
+    pub fn is_empty(&self) -> bool {
+        self.dag.is_empty()
+    }
+
+    /// Returns the highest round with a certificate.
+    pub fn highest_round(&self) -> Round {
+        self.dag.highest_round().unwrap_or_default()
+    }
+
+    /// Returns the round below which certificates were pruned.
+    pub const fn gc_round(&self) -> Round {
+        self.dag.gc_round()
+    }
+
+    /// Returns the round of the last committed leader.
+    pub const fn last_committed(&self) -> Round {
+        self.bullshark.last_committed()
+    }
+
+    /// Returns the committed leaders, in commit order.
+    pub fn commits(&self) -> &[SimulatedCommit] {
+        &self.commits
+    }
+
+    /// Returns the certificates of a round, in the order they were inserted.
+    pub fn round(&self, round: Round) -> &[Certificate] {
+        self.certificates.get(&round).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Inserts a certificate, together with the pending certificates it is the missing parent of.
+    ///
+    /// Returns the inserted certificates, in the order they were inserted. A certificate whose
+    /// parents are missing is kept until they are inserted.
+    pub fn insert(&mut self, certificate: Certificate) -> Vec<Certificate> {
+        let mut inserted = Vec::new();
+        self.pending.push(certificate);
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for certificate in std::mem::take(&mut self.pending) {
+                match self.dag.insert(DagVertex::new(certificate.clone())) {
+                    Ok(true) => {
+                        progress = true;
+                        self.certificates
+                            .entry(certificate.round())
+                            .or_default()
+                            .push(certificate.clone());
+                        inserted.push(certificate);
+                    }
+                    Err(DagError::MissingParent { .. }) => self.pending.push(certificate),
+                    Ok(false) | Err(_) => {}
+                }
+            }
+        }
+        inserted
+    }
+
+    /// Decides the leaders whose following round is complete, and commits the supported ones
+    /// together with the earlier leaders they link to, see [`Bullshark::try_commit`].
+    ///
+    /// Returns the new commits.
+    pub fn commit(&mut self, committee: &Committee) -> &[SimulatedCommit] {
+        let committed = self.commits.len();
+        for leader in self.bullshark.try_commit(committee, &mut self.dag) {
+            self.commits.push(SimulatedCommit {
+                round: leader.round,
+                leader: leader.leader,
+                certificates: leader.certificates.iter().map(Certificate::digest).collect(),
+            });
+        }
+        &self.commits[committed..]
+    }
+
+    /// Drops the certificates below the given round, committed or not.
+    pub fn prune(&mut self, gc_round: Round) {
+        self.dag.prune(gc_round);
+        self.certificates = self.certificates.split_off(&gc_round);
+        self.pending.retain(|certificate| certificate.round() >= gc_round);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn honest_committee_commits_every_leader() {
+        let mut committee = SimulatedCommittee::new(4);
+        committee.run(10);
+
+        let commits = committee.commits(0);
+        assert_eq!(commits.iter().map(|commit| commit.round).collect::<Vec<_>>(), [2, 4, 6, 8]);
+        for authority in committee.honest() {
+            assert_eq!(committee.commits(authority), commits);
+            assert_eq!(committee.highest_round(authority), 10);
+        }
+        // the first commit orders the genesis round and the first two rounds of the leader
+        assert_eq!(commits[0].certificates.len(), 4 + 4 + 1);
+        assert_eq!(committee.certificates().len(), 40);
+    }
+}
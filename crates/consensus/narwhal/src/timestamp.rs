@@ -0,0 +1,106 @@
+//! The timestamps of the blocks built from consensus output.
+//!
+//! The timestamp of a block is visible to contracts through the `TIMESTAMP` opcode and selects the
+//! active hardforks, so every validator must assign the same timestamp to the block of a commit.
+//! The [`TimestampPolicy`] of the chain derives it from the creation times the authors put into
+//! their headers, which are part of the committed certificates, and every block's timestamp is
+//! greater than its parent's.
+
+use crate::{determinism::Clock, types::OrderedSubDag};
+use serde::{Deserialize, Serialize};
+
+/// How the timestamp of the block of a commit is derived.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimestampPolicy {
+    /// The median of the creation times of the committed certificates.
+    ///
+    /// Authorities with faulty clocks can't move the median beyond the clocks of the other
+    /// authorities as long as their certificates are a minority of the commit.
+    #[default]
+    MedianCertificates,
+    /// The creation time of the leader's header.
+    ///
+    /// The leader alone decides the timestamp, so a faulty leader can push it into the future.
+    Leader,
+    /// The local clock of the node that builds the block.
+    ///
+    /// Validators build different blocks with this policy, so it's only suitable for chains with
+    /// a single validator, e.g. for development.
+    LocalClock,
+}
+
+impl TimestampPolicy {
+    /// Returns the timestamp of the block of a commit in seconds, which is greater than the
+    /// timestamp of the parent even if the policy yields an earlier time.
+    pub fn block_timestamp(
+        &self,
+        sub_dag: &OrderedSubDag,
+        parent_timestamp: u64,
+        clock: &impl Clock,
+    ) -> u64 {
+        let millis = match self {
+            Self::MedianCertificates => {
+                let mut created = sub_dag
+                    .certificates
+                    .iter()
+                    .map(|certificate| certificate.header.created_at)
+                    .collect::<Vec<_>>();
+                if created.is_empty() {
+                    sub_dag.leader.header.created_at
+                } else {
+                    // the lower median, so that no averaging is needed for an even count
+                    let median = (created.len() - 1) / 2;
+                    *created.select_nth_unstable(median).1
+                }
+            }
+            Self::Leader => sub_dag.leader.header.created_at,
+            Self::LocalClock => clock.now_millis(),
+        };
+        (millis / 1000).max(parent_timestamp + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        determinism::FixedClock,
+        types::{Certificate, Header},
+    };
+
+    fn certificate(author: u32, created_at: u64) -> Certificate {
+        Certificate {
+            header: Header { round: 2, author, created_at, ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let leader = certificate(0, 90_000);
+        let sub_dag = OrderedSubDag {
+            index: 0,
+            leader: leader.clone(),
+            certificates: vec![
+                certificate(1, 10_500),
+                certificate(2, 12_000),
+                certificate(3, 999_000),
+                leader,
+            ],
+            batches: Vec::new(),
+            timestamp: 0,
+        };
+        let clock = FixedClock(50_000);
+
+        let timestamp =
+            |policy: TimestampPolicy, parent| policy.block_timestamp(&sub_dag, parent, &clock);
+        assert_eq!(timestamp(TimestampPolicy::MedianCertificates, 0), 12);
+        assert_eq!(timestamp(TimestampPolicy::Leader, 0), 90);
+        assert_eq!(timestamp(TimestampPolicy::LocalClock, 0), 50);
+
+        // timestamps increase even if the policy yields an earlier time
+        assert_eq!(timestamp(TimestampPolicy::MedianCertificates, 12), 13);
+        assert_eq!(timestamp(TimestampPolicy::Leader, 100), 101);
+    }
+}
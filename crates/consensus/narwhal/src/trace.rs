@@ -0,0 +1,187 @@
+//! Client-supplied trace ids of submitted transactions.
+//!
+//! Applications can attach a [`TraceId`] when they submit a transaction, to correlate their own
+//! request logs with the inclusion of the transaction. Trace ids are node-local: they are never
+//! part of a batch or a block, so validators don't have to agree on them. The node that received
+//! the transaction keeps the id in its [`TraceIds`] and logs it with every status change of the
+//! transaction under the `narwhal::trace` target.
+//!
+//! Clients set the trace id in the `x-narwhal-trace-id` header of a gRPC submission, and the batch
+//! maker and the executor of the node log it when the transaction is batched, and when it's
+//! included in or dropped from a block.
+
+use alloy_primitives::{BlockNumber, TxHash};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+};
+use tracing::info;
+
+/// The maximum length of a [`TraceId`].
+pub const MAX_TRACE_ID_LENGTH: usize = 64;
+
+/// An opaque id chosen by the submitter of a transaction.
+///
+/// Trace ids are at most [`MAX_TRACE_ID_LENGTH`] printable ASCII characters, so they can be logged
+/// and passed in HTTP headers as is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TraceId(String);
+
+impl TraceId {
+    /// Returns the id as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Error returned for a malformed [`TraceId`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("trace ids must be 1 to {MAX_TRACE_ID_LENGTH} printable ASCII characters")]
+pub struct InvalidTraceId;
+
+impl TryFrom<String> for TraceId {
+    type Error = InvalidTraceId;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        let valid = !id.is_empty() &&
+            id.len() <= MAX_TRACE_ID_LENGTH &&
+            id.bytes().all(|byte| byte.is_ascii_graphic());
+        if !valid {
+            return Err(InvalidTraceId)
+        }
+        Ok(Self(id))
+    }
+}
+
+impl From<TraceId> for String {
+    fn from(id: TraceId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The trace ids of recently submitted transactions.
+///
+/// At most `capacity` ids are kept, the oldest are forgotten first. Cloning is cheap, all clones
+/// share the same ids.
+#[derive(Debug, Clone)]
+pub struct TraceIds {
+    inner: Arc<Mutex<TraceIdsInner>>,
+}
+
+#[derive(Debug)]
+struct TraceIdsInner {
+    capacity: usize,
+    ids: HashMap<TxHash, TraceId>,
+    /// Transactions in insertion order.
+    order: VecDeque<TxHash>,
+}
+
+impl TraceIds {
+    /// Creates a new record of at most `capacity` trace ids.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TraceIdsInner {
+                capacity,
+                ids: HashMap::new(),
+                order: VecDeque::new(),
+            })),
+        }
+    }
+
+    /// Records the trace id of a submitted transaction.
+    pub fn insert(&self, hash: TxHash, id: TraceId) {
+        info!(target: "narwhal::trace", trace_id = %id, tx_hash = %hash, "Transaction submitted");
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.ids.insert(hash, id).is_none() {
+            inner.order.push_back(hash);
+        }
+        while inner.order.len() > inner.capacity {
+            if let Some(oldest) = inner.order.pop_front() {
+                inner.ids.remove(&oldest);
+            }
+        }
+    }
+
+    /// Returns the trace id of the transaction, if it has one.
+    pub fn get(&self, hash: &TxHash) -> Option<TraceId> {
+        self.inner.lock().unwrap().ids.get(hash).cloned()
+    }
+
+    /// Logs that the worker sealed the transaction into a batch.
+    pub fn on_batched(&self, hash: &TxHash) {
+        if let Some(id) = self.get(hash) {
+            info!(target: "narwhal::trace", trace_id = %id, tx_hash = %hash, "Transaction batched");
+        }
+    }
+
+    /// Logs that the transaction was included in a block.
+    pub fn on_included(&self, hash: &TxHash, block_number: BlockNumber) {
+        if let Some(id) = self.get(hash) {
+            info!(
+                target: "narwhal::trace",
+                trace_id = %id,
+                tx_hash = %hash,
+                block_number,
+                "Transaction included"
+            );
+        }
+    }
+
+    /// Logs that the transaction was sequenced but dropped from the block.
+    pub fn on_dropped(&self, hash: &TxHash, block_number: BlockNumber, reason: &dyn fmt::Display) {
+        if let Some(id) = self.get(hash) {
+            info!(
+                target: "narwhal::trace",
+                trace_id = %id,
+                tx_hash = %hash,
+                block_number,
+                %reason,
+                "Transaction dropped"
+            );
+        }
+    }
+}
+
+impl Default for TraceIds {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(id: &str) -> TraceId {
+        TraceId::try_from(id.to_string()).unwrap()
+    }
+
+    #[test]
+    fn validate_trace_ids() {
+        assert_eq!(id("req-42/a").as_str(), "req-42/a");
+        assert_eq!(TraceId::try_from(String::new()), Err(InvalidTraceId));
+        assert_eq!(TraceId::try_from("a b".to_string()), Err(InvalidTraceId));
+        assert_eq!(TraceId::try_from("a".repeat(65)), Err(InvalidTraceId));
+        assert!(serde_json::from_str::<TraceId>(r#""a\nb""#).is_err());
+    }
+
+    #[test]
+    fn oldest_ids_are_forgotten() {
+        let ids = TraceIds::new(2);
+        for i in 1..=3 {
+            ids.insert(TxHash::with_last_byte(i), id(&i.to_string()));
+        }
+        assert_eq!(ids.get(&TxHash::with_last_byte(1)), None);
+        assert_eq!(ids.get(&TxHash::with_last_byte(3)), Some(id("3")));
+    }
+}
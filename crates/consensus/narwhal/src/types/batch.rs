@@ -0,0 +1,74 @@
+use super::BatchDigest;
+use alloy_primitives::{keccak256, Bytes};
+use alloy_rlp::{Decodable, Encodable};
+use serde::{Deserialize, Serialize};
+
+/// The transactions sealed by a worker, in the order they are executed.
+///
+/// Batches are encoded as the list of their transactions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Batch {
+    /// The EIP-2718 encoded transactions, in the batched encoding of
+    /// [`TransactionExpiry::encode_batched`](super::TransactionExpiry::encode_batched) if they
+    /// expire.
+    pub transactions: Vec<Bytes>,
+}
+
+impl Batch {
+    /// Creates a batch of the given encoded transactions.
+    pub const fn new(transactions: Vec<Bytes>) -> Self {
+        Self { transactions }
+    }
+
+    /// Computes the digest of the batch, see [`BatchDigest::of`].
+    pub fn digest(&self) -> BatchDigest {
+        BatchDigest(keccak256(alloy_rlp::encode(self)))
+    }
+
+    /// Returns the number of transactions in the batch.
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    /// Returns `true` if the batch has no transactions.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Returns the total size of the encoded transactions in bytes.
+    pub fn size(&self) -> usize {
+        self.transactions.iter().map(|transaction| transaction.len()).sum()
+    }
+}
+
+impl Encodable for Batch {
+    fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
+        self.transactions.encode(out)
+    }
+
+    fn length(&self) -> usize {
+        self.transactions.length()
+    }
+}
+
+impl Decodable for Batch {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        Vec::decode(buf).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_digest() {
+        let batch = Batch::new(vec![Bytes::from_static(b"a"), Bytes::from_static(b"bc")]);
+        assert_eq!(batch.digest(), BatchDigest::of(&batch.transactions));
+        assert_eq!(batch.size(), 3);
+
+        let encoded = alloy_rlp::encode(&batch);
+        assert_eq!(Batch::decode(&mut encoded.as_slice()).unwrap(), batch);
+    }
+}
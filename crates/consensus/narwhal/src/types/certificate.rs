@@ -0,0 +1,252 @@
+use super::{BatchDigest, CertificateDigest, HeaderDigest, Round, WorkerId};
+use alloy_primitives::{keccak256, Bytes};
+use alloy_rlp::{RlpDecodable, RlpEncodable};
+use reth_narwhal_verifier::{
+    verify_commit_proof, AuthorityIndex, CommitProof, Epoch, Intent, SignatureScheme,
+    SigningDomain, VerificationError, VerifierCommittee,
+};
+use serde::{Deserialize, Serialize};
+
+/// A batch referenced by a header.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, RlpEncodable, RlpDecodable,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchRef {
+    /// The digest of the batch.
+    pub digest: BatchDigest,
+    /// The worker of the header's author that sealed the batch.
+    pub worker: WorkerId,
+}
+
+/// The proposal of an authority for a round.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, RlpEncodable, RlpDecodable,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct Header {
+    /// The epoch of the committee of the author.
+    pub epoch: Epoch,
+    /// The round of the header.
+    pub round: Round,
+    /// The author of the header.
+    pub author: AuthorityIndex,
+    /// The batches of the author's workers included by the header.
+    pub payload: Vec<BatchRef>,
+    /// The certificates of the previous round the header references, in ascending order.
+    pub parents: Vec<CertificateDigest>,
+    /// The time the author created the header, in milliseconds since the unix epoch.
+    ///
+    /// The timestamps of the blocks are derived from the creation times of the committed headers,
+    /// see [`TimestampPolicy`](crate::timestamp::TimestampPolicy).
+    pub created_at: u64,
+}
+
+impl Header {
+    /// Computes the digest of the header, the `keccak256` of its RLP encoding.
+    ///
+    /// Votes for the header sign the digest with the [`Intent::Vote`] intent.
+    pub fn digest(&self) -> HeaderDigest {
+        HeaderDigest(keccak256(alloy_rlp::encode(self)))
+    }
+}
+
+/// The vote of an authority for the header of another authority, or its own.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, RlpEncodable, RlpDecodable,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct Vote {
+    /// The digest of the header.
+    pub header: HeaderDigest,
+    /// The epoch of the header.
+    pub epoch: Epoch,
+    /// The round of the header.
+    pub round: Round,
+    /// The author of the header.
+    pub origin: AuthorityIndex,
+    /// The voting authority.
+    pub voter: AuthorityIndex,
+    /// The signature of the voter over the header digest with the [`Intent::Vote`] intent.
+    pub signature: Bytes,
+}
+
+/// A header with the aggregated votes of a quorum of the committee.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, RlpEncodable, RlpDecodable,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct Certificate {
+    /// The certified header.
+    pub header: Header,
+    /// The indices of the authorities that voted for the header, in strictly ascending order.
+    pub signers: Vec<AuthorityIndex>,
+    /// The aggregate signature of the votes.
+    pub signature: Bytes,
+}
+
+/// The contents of a certificate its digest is computed over.
+#[derive(RlpEncodable)]
+struct CertificateDigestInput<'a> {
+    header: HeaderDigest,
+    signers: &'a Vec<AuthorityIndex>,
+}
+
+impl Certificate {
+    /// Returns the certificates of round 0 of an epoch, one per authority.
+    ///
+    /// Genesis certificates are known to every authority and have no votes, they are the parents of
+    /// the headers of round 1.
+    pub fn genesis(
+        epoch: Epoch,
+        authorities: impl IntoIterator<Item = AuthorityIndex>,
+    ) -> Vec<Self> {
+        authorities
+            .into_iter()
+            .map(|author| Self {
+                header: Header { epoch, author, ..Default::default() },
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Returns the round of the certified header.
+    pub const fn round(&self) -> Round {
+        self.header.round
+    }
+
+    /// Returns the author of the certified header.
+    pub const fn author(&self) -> AuthorityIndex {
+        self.header.author
+    }
+
+    /// Computes the digest of the certificate.
+    ///
+    /// The digest covers the header and the signers, but not the aggregate signature, so that it
+    /// doesn't depend on the encoding of the signature.
+    pub fn digest(&self) -> CertificateDigest {
+        let input = CertificateDigestInput { header: self.header.digest(), signers: &self.signers };
+        CertificateDigest(keccak256(alloy_rlp::encode(input)))
+    }
+
+    /// Verifies that the certificate carries the votes of a quorum of the given committee, cast on
+    /// the network of the given domain.
+    pub fn verify<S>(
+        &self,
+        committee: &VerifierCommittee<S::PublicKey>,
+        domain: &SigningDomain,
+    ) -> Result<(), VerificationError>
+    where
+        S: SignatureScheme,
+        for<'a> S::Signature: TryFrom<&'a [u8]>,
+    {
+        let signature = S::Signature::try_from(self.signature.as_ref())
+            .map_err(|_| VerificationError::MalformedProof)?;
+        let proof = CommitProof {
+            epoch: self.header.epoch,
+            digest: self.header.digest().0,
+            signers: self.signers.clone(),
+            signature,
+        };
+        verify_commit_proof::<S>(committee, domain, Intent::Vote, &proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+    use alloy_rlp::Decodable;
+    use reth_narwhal_verifier::VerifierAuthority;
+
+    /// A scheme whose "signature" is the concatenation of the signers' keys and the message.
+    struct ConcatScheme;
+
+    impl SignatureScheme for ConcatScheme {
+        type PublicKey = u8;
+        type Signature = Vec<u8>;
+
+        fn verify_aggregate(
+            signers: &[&Self::PublicKey],
+            message: &[u8],
+            signature: &Self::Signature,
+        ) -> bool {
+            let expected = signers.iter().map(|key| **key).chain(message.iter().copied());
+            expected.eq(signature.iter().copied())
+        }
+    }
+
+    fn certificate() -> Certificate {
+        Certificate {
+            header: Header {
+                epoch: 1,
+                round: 2,
+                author: 3,
+                payload: vec![BatchRef {
+                    digest: BatchDigest::of(&[Bytes::from_static(b"tx")]),
+                    worker: 0,
+                }],
+                parents: vec![CertificateDigest(B256::with_last_byte(1))],
+                created_at: 1_700_000_000_000,
+            },
+            signers: vec![0, 1, 3],
+            signature: Bytes::new(),
+        }
+    }
+
+    #[test]
+    fn encoding_roundtrip() {
+        let certificate = certificate();
+
+        let encoded = alloy_rlp::encode(&certificate);
+        assert_eq!(Certificate::decode(&mut encoded.as_slice()).unwrap(), certificate);
+
+        let json = serde_json::to_string(&certificate).unwrap();
+        assert_eq!(serde_json::from_str::<Certificate>(&json).unwrap(), certificate);
+    }
+
+    #[test]
+    fn digests() {
+        let certificate = certificate();
+
+        let mut resigned = certificate.clone();
+        resigned.signature = Bytes::from_static(&[1]);
+        assert_eq!(resigned.digest(), certificate.digest());
+
+        let mut other_signers = certificate.clone();
+        other_signers.signers.push(2);
+        assert_ne!(other_signers.digest(), certificate.digest());
+
+        let mut other_payload = certificate.clone();
+        other_payload.header.payload.clear();
+        assert_ne!(other_payload.header.digest(), certificate.header.digest());
+        assert_ne!(other_payload.digest(), certificate.digest());
+
+        let genesis = Certificate::genesis(0, 0..4);
+        assert_eq!(genesis.len(), 4);
+        assert_ne!(genesis[0].digest(), genesis[1].digest());
+    }
+
+    #[test]
+    fn verify_votes() {
+        let committee = VerifierCommittee::new(
+            1,
+            (0..4).map(|key| VerifierAuthority { public_key: key, stake: 1 }).collect(),
+        );
+        let domain = SigningDomain::new(1337, B256::ZERO);
+
+        let mut certificate = certificate();
+        let mut signature = vec![0, 1, 3];
+        signature.extend_from_slice(
+            domain.signing_message(Intent::Vote, certificate.header.digest().0).as_slice(),
+        );
+        certificate.signature = signature.into();
+        assert_eq!(certificate.verify::<ConcatScheme>(&committee, &domain), Ok(()));
+
+        certificate.header.round += 1;
+        assert_eq!(
+            certificate.verify::<ConcatScheme>(&committee, &domain),
+            Err(VerificationError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use super::{Round, SubDagDigest};
+use alloy_primitives::{keccak256, Bytes, FixedBytes, B256};
+
+/// The largest number of blocks the transactions of a sub-dag are split across.
+///
+/// Validators reject a block beyond it, so a commit can't stall the chain with an unbounded run of
+/// blocks.
+pub const MAX_BLOCKS_PER_SUB_DAG: u32 = 16;
+
+/// The length of the truncated hash of a [`BlockCommitment`].
+const HASH_LEN: usize = 20;
+
+/// The commitment of a block to the sub-dag it was built from, the extra data of its header.
+///
+/// The `mix_hash` of the block is the [`SubDagDigest`]. The extra data holds the round of the
+/// committed leader, the position of the block among the blocks of the sub-dag, and the hash of
+/// the digest and the root of the messages sent in the block. The engine API limits the extra data
+/// to 32 bytes, so the hash is truncated to its first 20 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCommitment {
+    /// The round of the committed leader.
+    pub round: Round,
+    /// The position of the block among the blocks of the sub-dag, starting at zero.
+    pub part: u32,
+    /// The first bytes of the `keccak256` of the digest of the sub-dag and the message root.
+    pub hash: FixedBytes<HASH_LEN>,
+}
+
+impl BlockCommitment {
+    /// Creates the commitment of the block at position `part` of the sub-dag with the given digest
+    /// and leader round, in which the messages with the given root were sent.
+    pub fn new(sub_dag: SubDagDigest, round: Round, part: u32, messages_root: B256) -> Self {
+        let hash = keccak256([sub_dag.0, messages_root].concat());
+        Self { round, part, hash: FixedBytes::from_slice(&hash[..HASH_LEN]) }
+    }
+
+    /// Returns the extra data of the header: the big-endian round and part, and the hash.
+    pub fn encode(&self) -> Bytes {
+        let mut encoded = Vec::with_capacity(B256::len_bytes());
+        encoded.extend(self.round.to_be_bytes());
+        encoded.extend(self.part.to_be_bytes());
+        encoded.extend_from_slice(self.hash.as_slice());
+        encoded.into()
+    }
+
+    /// Decodes the extra data of a header, `None` if it's not a commitment.
+    pub fn decode(extra_data: &[u8]) -> Option<Self> {
+        let extra_data: &[u8; 32] = extra_data.try_into().ok()?;
+        let (round, rest) = extra_data.split_first_chunk::<8>()?;
+        let (part, hash) = rest.split_first_chunk::<4>()?;
+        Some(Self {
+            round: Round::from_be_bytes(*round),
+            part: u32::from_be_bytes(*part),
+            hash: FixedBytes::from_slice(hash),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commitment_roundtrip() {
+        let digest = SubDagDigest::new(B256::repeat_byte(1));
+        let commitment = BlockCommitment::new(digest, 7, 2, B256::repeat_byte(2));
+        let encoded = commitment.encode();
+        assert_eq!(encoded.len(), 32);
+        assert_eq!(&encoded[..12], &[0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 2]);
+        assert_eq!(BlockCommitment::decode(&encoded), Some(commitment));
+
+        // the hash binds the digest and the message root
+        assert_ne!(BlockCommitment::new(digest, 7, 2, B256::ZERO), commitment);
+        assert_ne!(
+            BlockCommitment::new(SubDagDigest::default(), 7, 2, B256::repeat_byte(2)),
+            commitment
+        );
+        assert_eq!(BlockCommitment::decode(&encoded[1..]), None);
+        assert_eq!(BlockCommitment::decode(&[]), None);
+    }
+}
@@ -0,0 +1,99 @@
+use super::Round;
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_rlp::{RlpDecodableWrapper, RlpEncodable, RlpEncodableWrapper};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+macro_rules! digest_type {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(
+            Debug,
+            Clone,
+            Copy,
+            Default,
+            PartialEq,
+            Eq,
+            Hash,
+            PartialOrd,
+            Ord,
+            Serialize,
+            Deserialize,
+            RlpEncodableWrapper,
+            RlpDecodableWrapper,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub B256);
+
+        impl $name {
+            /// Creates a digest from its hash.
+            pub const fn new(hash: B256) -> Self {
+                Self(hash)
+            }
+        }
+
+        impl From<B256> for $name {
+            fn from(hash: B256) -> Self {
+                Self(hash)
+            }
+        }
+
+        impl From<$name> for B256 {
+            fn from(digest: $name) -> Self {
+                digest.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+digest_type!(
+    /// The digest of a batch, the `keccak256` of the RLP list of its transactions.
+    BatchDigest
+);
+
+digest_type!(
+    /// The digest of a [`Header`](super::Header).
+    HeaderDigest
+);
+
+digest_type!(
+    /// The digest of a [`Certificate`](super::Certificate).
+    CertificateDigest
+);
+
+digest_type!(
+    /// The digest of an [`OrderedSubDag`](super::OrderedSubDag), which the blocks of the sub-dag
+    /// commit to.
+    SubDagDigest
+);
+
+impl BatchDigest {
+    /// Computes the digest of a batch with the given encoded transactions.
+    pub fn of(transactions: &[Bytes]) -> Self {
+        let mut encoded = Vec::new();
+        alloy_rlp::encode_list::<_, Bytes>(transactions, &mut encoded);
+        Self(keccak256(encoded))
+    }
+}
+
+/// The contents of a sub-dag its digest is computed over.
+#[derive(RlpEncodable)]
+struct SubDagDigestInput {
+    round: Round,
+    certificates: Vec<CertificateDigest>,
+}
+
+impl SubDagDigest {
+    /// Computes the digest of a sub-dag committed by the leader of the given round, with the
+    /// digests of its certificates in commit order.
+    pub fn of(round: Round, certificates: impl IntoIterator<Item = CertificateDigest>) -> Self {
+        let certificates = certificates.into_iter().collect();
+        Self(keccak256(alloy_rlp::encode(SubDagDigestInput { round, certificates })))
+    }
+}
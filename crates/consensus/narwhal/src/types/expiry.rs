@@ -0,0 +1,115 @@
+use alloy_primitives::Bytes;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The first byte of a transaction of a [`Batch`](super::Batch) that carries a
+/// [`TransactionExpiry`].
+///
+/// No EIP-2718 encoded transaction starts with it, typed transactions start with their type below
+/// `0x80` and legacy transactions with the header of an RLP list.
+pub const EXPIRING_TRANSACTION_PREFIX: u8 = 0xbf;
+
+/// The length of the expiry of a batched transaction, after the prefix.
+const EXPIRY_LEN: usize = 9;
+
+/// The point after which a sequenced transaction must no longer be executed.
+///
+/// Submitters attach the expiry to a transaction they submit to a worker, which carries it in the
+/// batch next to the transaction, see [`TransactionExpiry::encode_batched`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionExpiry {
+    /// The last consensus round whose commit may include the transaction.
+    Round(u64),
+    /// The last block timestamp that may include the transaction.
+    Timestamp(u64),
+}
+
+impl TransactionExpiry {
+    /// Returns `true` if the transaction expired in a block derived from the commit of the given
+    /// leader round with the given timestamp.
+    ///
+    /// Only inputs that all validators agree on are used, so the decision is deterministic.
+    pub const fn is_expired(&self, round: u64, timestamp: u64) -> bool {
+        match *self {
+            Self::Round(last) => round > last,
+            Self::Timestamp(last) => timestamp > last,
+        }
+    }
+
+    /// Returns the batched encoding of the EIP-2718 encoded transaction with this expiry: the
+    /// [`EXPIRING_TRANSACTION_PREFIX`], the kind of the expiry, `0` for a round and `1` for a
+    /// timestamp, its big-endian value and the transaction.
+    pub fn encode_batched(&self, transaction: &[u8]) -> Bytes {
+        let (kind, value) = match *self {
+            Self::Round(round) => (0, round),
+            Self::Timestamp(timestamp) => (1, timestamp),
+        };
+        let mut encoded = Vec::with_capacity(1 + EXPIRY_LEN + transaction.len());
+        encoded.extend([EXPIRING_TRANSACTION_PREFIX, kind]);
+        encoded.extend(value.to_be_bytes());
+        encoded.extend_from_slice(transaction);
+        encoded.into()
+    }
+
+    /// Splits a transaction of a batch into its expiry, if it has one, and its EIP-2718 encoding.
+    ///
+    /// A malformed expiry is returned as part of the transaction, which then fails to decode.
+    pub fn split_batched(encoded: &[u8]) -> (Option<Self>, &[u8]) {
+        let Some((&EXPIRING_TRANSACTION_PREFIX, rest)) = encoded.split_first() else {
+            return (None, encoded)
+        };
+        let Some((expiry, transaction)) = rest.split_first_chunk::<EXPIRY_LEN>() else {
+            return (None, encoded)
+        };
+        let value = u64::from_be_bytes(expiry[1..].try_into().expect("8 bytes"));
+        match expiry[0] {
+            0 => (Some(Self::Round(value)), transaction),
+            1 => (Some(Self::Timestamp(value)), transaction),
+            _ => (None, encoded),
+        }
+    }
+}
+
+impl fmt::Display for TransactionExpiry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Round(round) => write!(f, "round {round}"),
+            Self::Timestamp(timestamp) => write!(f, "timestamp {timestamp}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry_is_inclusive() {
+        assert!(!TransactionExpiry::Round(10).is_expired(10, 0));
+        assert!(TransactionExpiry::Round(10).is_expired(11, 0));
+        assert!(!TransactionExpiry::Timestamp(100).is_expired(0, 100));
+        assert!(TransactionExpiry::Timestamp(100).is_expired(0, 101));
+    }
+
+    #[test]
+    fn batched_encoding() {
+        let transaction = [0x02, 0xc0];
+        for expiry in [TransactionExpiry::Round(7), TransactionExpiry::Timestamp(u64::MAX)] {
+            let encoded = expiry.encode_batched(&transaction);
+            assert_eq!(encoded.len(), 1 + EXPIRY_LEN + transaction.len());
+            assert_eq!(
+                TransactionExpiry::split_batched(&encoded),
+                (Some(expiry), &transaction[..])
+            );
+        }
+
+        // transactions without an expiry and malformed expiries are left as they are
+        assert_eq!(TransactionExpiry::split_batched(&transaction), (None, &transaction[..]));
+        let truncated = [EXPIRING_TRANSACTION_PREFIX, 0, 1];
+        assert_eq!(TransactionExpiry::split_batched(&truncated), (None, &truncated[..]));
+        let mut unknown = TransactionExpiry::Round(1).encode_batched(&transaction).to_vec();
+        unknown[1] = 2;
+        assert_eq!(TransactionExpiry::split_batched(&unknown), (None, &unknown[..]));
+    }
+}
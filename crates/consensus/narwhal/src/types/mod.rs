@@ -0,0 +1,34 @@
+//! The data model of the narwhal mempool.
+//!
+//! Workers seal transactions into [`Batch`]es, identified by their [`BatchDigest`]. In every round,
+//! the primary of each authority proposes a [`Header`] that references the batches of its workers
+//! and the certificates of the previous round. Once a quorum of the committee voted for a header,
+//! the votes are aggregated into a [`Certificate`]. The certificates and their references to the
+//! certificates of the previous round form the DAG, whose vertices are [`DagVertex`]es. Every
+//! commit outputs an [`OrderedSubDag`], which is executed as one or more blocks that carry a
+//! [`BlockCommitment`] to it.
+//!
+//! All types are RLP encoded in storage, and their digests are the `keccak256` of the RLP encoding
+//! of their contents. On the wire, they are encoded in the versioned format of [`crate::wire`].
+
+mod batch;
+mod certificate;
+mod commitment;
+mod digest;
+mod expiry;
+mod sub_dag;
+mod vertex;
+
+pub use batch::Batch;
+pub use certificate::{BatchRef, Certificate, Header, Vote};
+pub use commitment::{BlockCommitment, MAX_BLOCKS_PER_SUB_DAG};
+pub use digest::{BatchDigest, CertificateDigest, HeaderDigest, SubDagDigest};
+pub use expiry::{TransactionExpiry, EXPIRING_TRANSACTION_PREFIX};
+pub use sub_dag::OrderedSubDag;
+pub use vertex::DagVertex;
+
+/// A round of the DAG.
+pub type Round = u64;
+
+/// The index of a worker of an authority.
+pub type WorkerId = u32;
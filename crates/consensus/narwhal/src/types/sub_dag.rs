@@ -0,0 +1,206 @@
+use super::{Batch, BatchDigest, BatchRef, Certificate, Round, SubDagDigest};
+use alloy_primitives::Bytes;
+use reth_narwhal_verifier::Epoch;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The output of a commit: the certificates of a committed leader's causal history that were not
+/// committed before, together with the batches they reference.
+///
+/// Every sub-dag is turned into one block, or into consecutive blocks if its transactions exceed
+/// the block gas limit, so the order of the certificates and batches is the order in which their
+/// transactions are executed.
+///
+/// The same batch can be referenced by several headers of a commit, e.g. by different authorities
+/// or by headers of different rounds. Its transactions are executed once, at the position of its
+/// first inclusion, see [`OrderedSubDag::batch_order`]. Batches that are committed again by a later
+/// commit, e.g. after they were garbage collected and resubmitted, are executed again, and their
+/// transactions are skipped by the nonce rules of sequencing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderedSubDag {
+    /// The position of the sub-dag in the sequence of all commits, starting at 0.
+    pub index: u64,
+    /// The certificate of the committed leader.
+    pub leader: Certificate,
+    /// The newly committed certificates in commit order, ending with the leader.
+    pub certificates: Vec<Certificate>,
+    /// The batches referenced by the certificates, in the order of
+    /// [`OrderedSubDag::batch_order`].
+    pub batches: Vec<Batch>,
+    /// The commit timestamp in seconds, which every validator derives identically from the
+    /// committed certificates.
+    pub timestamp: u64,
+}
+
+impl OrderedSubDag {
+    /// Returns the round of the committed leader.
+    pub const fn leader_round(&self) -> Round {
+        self.leader.round()
+    }
+
+    /// Returns the digest of the sub-dag, over the round of the leader and the digests of the
+    /// certificates in commit order.
+    ///
+    /// The blocks of the sub-dag commit to the digest in their `mix_hash`.
+    pub fn digest(&self) -> SubDagDigest {
+        SubDagDigest::of(self.leader_round(), self.certificates.iter().map(Certificate::digest))
+    }
+
+    /// Returns the epoch of the committee that committed the sub-dag.
+    pub const fn epoch(&self) -> Epoch {
+        self.leader.header.epoch
+    }
+
+    /// Returns the batches referenced by the certificates of a commit, in the order they are
+    /// executed.
+    ///
+    /// This is the canonical first-inclusion rule: batches are ordered by the position of the
+    /// certificates in the commit and by their position in the payload of the certified header,
+    /// and every batch is only included at its first reference.
+    pub fn batch_order<'a>(
+        certificates: impl IntoIterator<Item = &'a Certificate>,
+    ) -> Vec<BatchRef> {
+        let mut included = HashSet::new();
+        certificates
+            .into_iter()
+            .flat_map(|certificate| &certificate.header.payload)
+            .filter(|batch| included.insert(batch.digest))
+            .copied()
+            .collect()
+    }
+
+    /// Returns the batches of the sub-dag without repeated batches, in execution order.
+    ///
+    /// Sub-dags assembled by [`OrderedSubDag::batch_order`] have no repeated batches. The
+    /// executor still skips every batch whose digest was already included, so that a sub-dag
+    /// assembled by a faulty implementation can't execute a batch twice.
+    pub fn unique_batches(&self) -> impl Iterator<Item = &Batch> + '_ {
+        let mut included = HashSet::<BatchDigest>::new();
+        self.batches.iter().filter(move |batch| included.insert(batch.digest()))
+    }
+
+    /// Returns the encoded transactions of the unique batches in execution order.
+    pub fn transactions(&self) -> impl Iterator<Item = &Bytes> + '_ {
+        self.unique_batches().flat_map(|batch| &batch.transactions)
+    }
+
+    /// Returns the number of transactions of all batches, including repeated ones.
+    pub fn num_transactions(&self) -> usize {
+        self.batches.iter().map(Batch::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Header;
+    use alloy_primitives::B256;
+    use proptest::{collection::vec, prelude::*};
+
+    fn batch(transaction: u8) -> Batch {
+        Batch::new(vec![Bytes::from(vec![transaction])])
+    }
+
+    fn certificate(author: usize, batches: &[u8]) -> Certificate {
+        let payload = batches
+            .iter()
+            .map(|batch| BatchRef {
+                digest: BatchDigest(B256::with_last_byte(*batch)),
+                worker: author as u32,
+            })
+            .collect();
+        let header = Header { round: 1, author: author as u32, payload, ..Default::default() };
+        Certificate { header, ..Default::default() }
+    }
+
+    #[test]
+    fn flatten_batches() {
+        let leader =
+            Certificate { header: Header { round: 4, ..Default::default() }, ..Default::default() };
+        let sub_dag = OrderedSubDag {
+            index: 1,
+            leader: leader.clone(),
+            certificates: vec![leader],
+            batches: vec![
+                Batch::new(vec![Bytes::from_static(&[1]), Bytes::from_static(&[2])]),
+                Batch::default(),
+                Batch::new(vec![Bytes::from_static(&[3])]),
+                // repeated batches are executed once
+                Batch::new(vec![Bytes::from_static(&[1]), Bytes::from_static(&[2])]),
+            ],
+            timestamp: 1_700_000_000,
+        };
+        assert_eq!(sub_dag.leader_round(), 4);
+        assert_eq!(sub_dag.num_transactions(), 5);
+        assert_eq!(sub_dag.unique_batches().count(), 3);
+        let transactions: Vec<_> = sub_dag.transactions().map(|tx| tx[0]).collect();
+        assert_eq!(transactions, vec![1, 2, 3]);
+
+        let json = serde_json::to_string(&sub_dag).unwrap();
+        assert_eq!(serde_json::from_str::<OrderedSubDag>(&json).unwrap(), sub_dag);
+    }
+
+    proptest! {
+        /// Every batch is ordered once, at its first reference in commit and payload order.
+        #[test]
+        fn first_inclusion(payloads in vec(vec(0u8..16, 0..8), 0..8)) {
+            let certificates: Vec<_> = payloads
+                .iter()
+                .enumerate()
+                .map(|(author, batches)| certificate(author, batches))
+                .collect();
+
+            let mut expected: Vec<BatchRef> = Vec::new();
+            for certificate in &certificates {
+                for batch in &certificate.header.payload {
+                    if expected.iter().all(|included| included.digest != batch.digest) {
+                        expected.push(*batch);
+                    }
+                }
+            }
+            prop_assert_eq!(OrderedSubDag::batch_order(&certificates), expected);
+        }
+
+        /// Certificates that only reference already included batches don't change the order.
+        #[test]
+        fn repeated_references_are_ignored(
+            payloads in vec(vec(0u8..16, 1..8), 1..8),
+            repeated in vec(any::<prop::sample::Index>(), 0..8),
+        ) {
+            let mut certificates: Vec<_> = payloads
+                .iter()
+                .enumerate()
+                .map(|(author, batches)| certificate(author, batches))
+                .collect();
+            let order = OrderedSubDag::batch_order(&certificates);
+
+            let included: Vec<_> = payloads.iter().flatten().copied().collect();
+            let batches: Vec<_> =
+                repeated.iter().map(|index| *index.get(&included)).collect();
+            certificates.push(certificate(payloads.len(), &batches));
+            prop_assert_eq!(OrderedSubDag::batch_order(&certificates), order);
+        }
+
+        /// The executor's dedup pass executes the transactions of every batch once.
+        #[test]
+        fn execute_batches_once(batches in vec(0u8..8, 0..16)) {
+            let sub_dag = OrderedSubDag {
+                index: 0,
+                leader: Certificate::default(),
+                certificates: Vec::new(),
+                batches: batches.iter().copied().map(batch).collect(),
+                timestamp: 0,
+            };
+
+            let mut expected = Vec::new();
+            for batch in batches {
+                if !expected.contains(&batch) {
+                    expected.push(batch);
+                }
+            }
+            let transactions: Vec<_> = sub_dag.transactions().map(|tx| tx[0]).collect();
+            prop_assert_eq!(transactions, expected);
+        }
+    }
+}
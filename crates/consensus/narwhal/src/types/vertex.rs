@@ -0,0 +1,107 @@
+use super::{Certificate, CertificateDigest, Round};
+use alloy_rlp::{Decodable, Encodable};
+use reth_narwhal_verifier::AuthorityIndex;
+use serde::{Deserialize, Serialize};
+
+/// A vertex of the DAG, i.e. a certificate together with its digest.
+///
+/// The edges of the DAG point from a vertex to its parents, the certificates of the previous round
+/// referenced by its header. Vertices are encoded like their certificate, the digest is recomputed
+/// when decoding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Certificate", into = "Certificate")]
+pub struct DagVertex {
+    certificate: Certificate,
+    digest: CertificateDigest,
+}
+
+impl DagVertex {
+    /// Creates the vertex of a certificate.
+    pub fn new(certificate: Certificate) -> Self {
+        let digest = certificate.digest();
+        Self { certificate, digest }
+    }
+
+    /// Returns the digest of the certificate.
+    pub const fn digest(&self) -> CertificateDigest {
+        self.digest
+    }
+
+    /// Returns the round of the vertex.
+    pub const fn round(&self) -> Round {
+        self.certificate.round()
+    }
+
+    /// Returns the author of the vertex.
+    pub const fn author(&self) -> AuthorityIndex {
+        self.certificate.author()
+    }
+
+    /// Returns the digests of the parents of the vertex.
+    pub fn parents(&self) -> &[CertificateDigest] {
+        &self.certificate.header.parents
+    }
+
+    /// Returns the certificate of the vertex.
+    pub const fn certificate(&self) -> &Certificate {
+        &self.certificate
+    }
+
+    /// Consumes the vertex and returns its certificate.
+    pub fn into_certificate(self) -> Certificate {
+        self.certificate
+    }
+}
+
+impl From<Certificate> for DagVertex {
+    fn from(certificate: Certificate) -> Self {
+        Self::new(certificate)
+    }
+}
+
+impl From<DagVertex> for Certificate {
+    fn from(vertex: DagVertex) -> Self {
+        vertex.certificate
+    }
+}
+
+impl Encodable for DagVertex {
+    fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
+        self.certificate.encode(out)
+    }
+
+    fn length(&self) -> usize {
+        self.certificate.length()
+    }
+}
+
+impl Decodable for DagVertex {
+    fn decode(buf: &mut &[u8]) -> alloy_rlp::Result<Self> {
+        Certificate::decode(buf).map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Header;
+
+    #[test]
+    fn vertex_roundtrip() {
+        let parents = Certificate::genesis(0, 0..4).iter().map(Certificate::digest).collect();
+        let vertex = DagVertex::new(Certificate {
+            header: Header { round: 1, author: 2, parents, ..Default::default() },
+            ..Default::default()
+        });
+        assert_eq!(vertex.round(), 1);
+        assert_eq!(vertex.author(), 2);
+        assert_eq!(vertex.parents().len(), 4);
+
+        let encoded = alloy_rlp::encode(&vertex);
+        assert_eq!(DagVertex::decode(&mut encoded.as_slice()).unwrap(), vertex);
+
+        let json = serde_json::to_string(&vertex).unwrap();
+        let decoded: DagVertex = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.digest(), vertex.digest());
+    }
+}
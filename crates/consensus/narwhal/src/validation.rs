@@ -0,0 +1,547 @@
+//! Collection of methods for narwhal block validation.
+
+use crate::{
+    dag_store::DagStore,
+    messages::messages_root,
+    sequencing::SequencingFilter,
+    types::{BlockCommitment, MAX_BLOCKS_PER_SUB_DAG},
+    worker::TransactionSizeLimits,
+    NonceGapPolicy,
+};
+use reth_consensus::ConsensusError;
+use reth_primitives::{
+    Address, BlockWithSenders, GotExpected, Header, InvalidTransactionError, Receipt, SealedBlock,
+    TxHash, B256,
+};
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Validates that the transactions of every sender in the block have consecutive nonces, and
+/// that the block orders the transactions of its commit as the chain's [`NonceGapPolicy`] does.
+///
+/// Blocks are derived from consensus output with
+/// [`sequence_by_nonce`](crate::sequencing::sequence_by_nonce), which never includes a transaction
+/// with a nonce gap. With [`NonceGapPolicy::Drop`], the block keeps the sequencing order of the
+/// commit. With [`NonceGapPolicy::Defer`], a transaction may only be moved behind later
+/// transactions of the commit to directly follow the transaction of its sender that closed its
+/// gap.
+///
+/// `sequenced` are the hashes of the transactions of the block's commit in sequencing order, if
+/// the commit is known. Without it, only the nonces are checked. The nonce of the first transaction
+/// of a sender is checked against the state during execution.
+pub fn validate_sender_nonces(
+    block: &BlockWithSenders,
+    policy: NonceGapPolicy,
+    sequenced: Option<&[TxHash]>,
+) -> Result<(), ConsensusError> {
+    let mut next_nonces = HashMap::<Address, u64>::new();
+    for (sender, transaction) in block.transactions_with_sender() {
+        let nonce = transaction.nonce();
+        if let Some(next_nonce) = next_nonces.insert(*sender, nonce + 1) {
+            if nonce != next_nonce {
+                return Err(InvalidTransactionError::NonceNotConsistent.into())
+            }
+        }
+    }
+
+    let Some(sequenced) = sequenced else { return Ok(()) };
+    let mut positions = HashMap::with_capacity(sequenced.len());
+    for (position, hash) in sequenced.iter().enumerate() {
+        // a transaction sequenced twice is executed at its first position
+        positions.entry(*hash).or_insert(position);
+    }
+    let mut latest = None;
+    let mut previous: Option<(Address, u64)> = None;
+    for (sender, transaction) in block.transactions_with_sender() {
+        let hash = transaction.hash();
+        let nonce = transaction.nonce();
+        // transactions that are not part of the commit are not ordered by the policy
+        if let Some(&position) = positions.get(&hash) {
+            if latest.is_some_and(|latest| position < latest) {
+                let follows_gap = previous == Some((*sender, nonce.wrapping_sub(1)));
+                if policy == NonceGapPolicy::Drop || !follows_gap {
+                    return Err(ConsensusError::TransactionOutOfOrder { hash })
+                }
+            }
+            latest = latest.max(Some(position));
+        }
+        previous = Some((*sender, nonce));
+    }
+    Ok(())
+}
+
+/// Validates that every transaction of the block passes the chain's sequencing filter.
+///
+/// Workers apply the same filter before batching, so this only fails if a validator batched a
+/// transaction it should have rejected.
+pub fn validate_sequencing_filter(
+    block: &BlockWithSenders,
+    filter: &dyn SequencingFilter,
+) -> Result<(), ConsensusError> {
+    for (sender, transaction) in block.transactions_with_sender() {
+        if filter.check(block.number, *sender, transaction).is_err() {
+            return Err(ConsensusError::TransactionFiltered { hash: transaction.hash() })
+        }
+    }
+    Ok(())
+}
+
+/// Validates that no transaction of the block exceeds the chain's size limits.
+///
+/// Workers don't batch oversized transactions and executors drop them from committed batches, so
+/// this only fails for blocks that were not derived from consensus output.
+pub fn validate_transaction_sizes(
+    block: &SealedBlock,
+    limits: &TransactionSizeLimits,
+) -> Result<(), ConsensusError> {
+    for transaction in &block.body {
+        if limits.check(transaction).is_err() {
+            return Err(ConsensusError::TransactionTooLarge { hash: transaction.hash() })
+        }
+    }
+    Ok(())
+}
+
+/// Validates that the deposit transactions of the block, sent by the chain's deposit sender, are
+/// included before all other transactions.
+///
+/// The executor moves the deposits of a commit to the front of its first block, see
+/// [`deposits_first`](crate::deposits::deposits_first). That the deposits are in the order of the
+/// L1 bridge follows from the consecutive nonces of the deposit sender, see
+/// [`validate_sender_nonces`].
+pub fn validate_deposit_order(
+    block: &BlockWithSenders,
+    deposit_sender: Address,
+) -> Result<(), ConsensusError> {
+    let mut deposits = true;
+    for (sender, transaction) in block.transactions_with_sender() {
+        if *sender != deposit_sender {
+            deposits = false;
+        } else if !deposits {
+            return Err(ConsensusError::DepositOutOfOrder { hash: transaction.hash() })
+        }
+    }
+    Ok(())
+}
+
+/// Validates that the extra data of the header is a [`BlockCommitment`] and returns it.
+pub fn validate_block_commitment_present(
+    header: &Header,
+) -> Result<BlockCommitment, ConsensusError> {
+    BlockCommitment::decode(&header.extra_data).ok_or(ConsensusError::BlockCommitmentMissing)
+}
+
+/// Validates that the timestamp of the header is greater than the timestamp of its parent.
+///
+/// The executor derives timestamps that always increase, see
+/// [`TimestampPolicy::block_timestamp`](crate::timestamp::TimestampPolicy::block_timestamp).
+pub fn validate_timestamp(header: &Header, parent: &Header) -> Result<(), ConsensusError> {
+    if header.timestamp <= parent.timestamp {
+        return Err(ConsensusError::TimestampIsInPast {
+            parent_timestamp: parent.timestamp,
+            timestamp: header.timestamp,
+        })
+    }
+    Ok(())
+}
+
+/// Validates the position of the header among the blocks of its sub-dag against its parent.
+///
+/// The `mix_hash` of a block is the [`OrderedSubDag::digest`](crate::types::OrderedSubDag::digest)
+/// of its sub-dag. The transactions of a sub-dag that exceed the block gas limit are split across
+/// at most [`MAX_BLOCKS_PER_SUB_DAG`] consecutive blocks with the same `mix_hash` and leader round,
+/// numbered from zero. A block of another sub-dag than its parent's is the first of its sub-dag.
+/// The genesis block doesn't commit to a sub-dag.
+pub fn validate_block_commitment_against_parent(
+    header: &Header,
+    parent: &Header,
+) -> Result<(), ConsensusError> {
+    let commitment = validate_block_commitment_present(header)?;
+    if commitment.part >= MAX_BLOCKS_PER_SUB_DAG {
+        return Err(ConsensusError::SubDagBlocksExceeded { max: MAX_BLOCKS_PER_SUB_DAG.into() })
+    }
+    let expected_part = if parent.number != 0 && header.mix_hash == parent.mix_hash {
+        let parent = validate_block_commitment_present(parent)?;
+        if commitment.round != parent.round {
+            return Err(ConsensusError::SubDagRoundDiff(GotExpected {
+                got: commitment.round,
+                expected: parent.round,
+            }))
+        }
+        parent.part + 1
+    } else {
+        0
+    };
+    if commitment.part != expected_part {
+        return Err(ConsensusError::SubDagPartDiff(GotExpected {
+            got: commitment.part.into(),
+            expected: expected_part.into(),
+        }))
+    }
+    Ok(())
+}
+
+/// Validates that the header commits to the sub-dag that was committed after the sub-dag of its
+/// parent, and to the round of its leader, according to the sub-dags recorded in the local
+/// [`DagStore`].
+///
+/// A node with a DAG store derives its blocks from the recorded sub-dags, so the check fails
+/// closed: a block whose sub-dag, or whose parent's sub-dag, is not recorded is rejected, and so is
+/// a block when the store can't be read.
+pub fn validate_sub_dag_commitment(
+    header: &Header,
+    parent: &Header,
+    store: &dyn DagStore,
+) -> Result<(), ConsensusError> {
+    let recorded = |digest: B256| {
+        store
+            .sub_dag(digest.into())
+            .map_err(|err| {
+                warn!(target: "consensus::narwhal", %err, "Failed to read recorded sub-dags");
+                ConsensusError::SubDagStoreUnavailable
+            })?
+            .ok_or(ConsensusError::SubDagUnknown(digest))
+    };
+    let sub_dag = recorded(header.mix_hash)?;
+    let commitment = validate_block_commitment_present(header)?;
+    if commitment.round != sub_dag.round {
+        return Err(ConsensusError::SubDagRoundDiff(GotExpected {
+            got: commitment.round,
+            expected: sub_dag.round,
+        }))
+    }
+
+    // a later block of the sub-dag of the parent
+    if parent.number != 0 && header.mix_hash == parent.mix_hash {
+        return Ok(())
+    }
+    // the first block built from consensus output commits to the first sub-dag
+    let expected = if parent.number == 0 { 0 } else { recorded(parent.mix_hash)?.index + 1 };
+    if sub_dag.index != expected {
+        return Err(ConsensusError::SubDagIndexDiff(GotExpected { got: sub_dag.index, expected }))
+    }
+    Ok(())
+}
+
+/// Validates that the header commits to its sub-dag and to the root of the messages sent in the
+/// block.
+///
+/// See [`messages`](crate::messages) for how the root is derived from the receipts.
+pub fn validate_block_commitment(
+    header: &Header,
+    receipts: &[Receipt],
+) -> Result<(), ConsensusError> {
+    let got = validate_block_commitment_present(header)?;
+    let expected =
+        BlockCommitment::new(header.mix_hash.into(), got.round, got.part, messages_root(receipts));
+    if got != expected {
+        return Err(ConsensusError::BlockCommitmentDiff(
+            GotExpected {
+                got: B256::from_slice(&got.encode()),
+                expected: B256::from_slice(&expected.encode()),
+            }
+            .into(),
+        ))
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dag_store::{MemoryDagStore, StoredSubDag},
+        messages::MessageSent,
+        predeploys::MESSAGE_QUEUE,
+        types::CertificateDigest,
+    };
+    use alloy_sol_types::SolEvent;
+    use reth_primitives::{
+        Block, Bytes, Log, Signature, Transaction, TransactionSigned, TxKind, TxLegacy, B256,
+    };
+
+    fn block(transactions: &[(Address, u64)]) -> BlockWithSenders {
+        let (senders, body) = transactions
+            .iter()
+            .map(|(sender, nonce)| {
+                let transaction = Transaction::Legacy(TxLegacy {
+                    nonce: *nonce,
+                    to: TxKind::Call(*sender),
+                    ..Default::default()
+                });
+                (
+                    *sender,
+                    TransactionSigned::from_transaction_and_signature(
+                        transaction,
+                        Signature::default(),
+                    ),
+                )
+            })
+            .unzip();
+        BlockWithSenders { block: Block { body, ..Default::default() }, senders }
+    }
+
+    #[test]
+    fn consecutive_nonces() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        for policy in [NonceGapPolicy::Defer, NonceGapPolicy::Drop] {
+            let block = block(&[(a, 3), (b, 0), (a, 4), (b, 1)]);
+            assert!(validate_sender_nonces(&block, policy, None).is_ok());
+        }
+    }
+
+    #[test]
+    fn nonce_gap() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        for policy in [NonceGapPolicy::Defer, NonceGapPolicy::Drop] {
+            assert_eq!(
+                validate_sender_nonces(&block(&[(a, 3), (b, 0), (a, 5)]), policy, None),
+                Err(ConsensusError::InvalidTransaction(
+                    InvalidTransactionError::NonceNotConsistent
+                ))
+            );
+            assert!(validate_sender_nonces(&block(&[(a, 3), (a, 3)]), policy, None).is_err());
+        }
+    }
+
+    /// Returns the hashes of the transactions of the block in the given sequencing order.
+    fn sequencing_order(block: &BlockWithSenders, order: &[usize]) -> Vec<TxHash> {
+        order.iter().map(|index| block.body[*index].hash()).collect()
+    }
+
+    #[test]
+    fn defer_policy_order() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        // the commit sequenced a:1 before a:0, so a:1 was deferred until a:0 closed the gap
+        let block = block(&[(b, 0), (a, 0), (a, 1), (b, 1)]);
+        let deferred = sequencing_order(&block, &[2, 0, 1, 3]);
+        assert_eq!(validate_sender_nonces(&block, NonceGapPolicy::Defer, Some(&deferred)), Ok(()));
+        let in_order = sequencing_order(&block, &[0, 1, 2, 3]);
+        assert_eq!(validate_sender_nonces(&block, NonceGapPolicy::Defer, Some(&in_order)), Ok(()));
+
+        // b:1 was sequenced first, but doesn't follow the transaction that closed its gap
+        let misplaced = sequencing_order(&block, &[3, 0, 1, 2]);
+        assert_eq!(
+            validate_sender_nonces(&block, NonceGapPolicy::Defer, Some(&misplaced)),
+            Err(ConsensusError::TransactionOutOfOrder { hash: block.body[3].hash() })
+        );
+    }
+
+    #[test]
+    fn drop_policy_order() {
+        let (a, b) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let block = block(&[(b, 0), (a, 0), (a, 1)]);
+        // transactions of the commit that are not in the block were dropped
+        let mut in_order = sequencing_order(&block, &[0, 1, 2]);
+        in_order.insert(1, TxHash::with_last_byte(1));
+        assert_eq!(validate_sender_nonces(&block, NonceGapPolicy::Drop, Some(&in_order)), Ok(()));
+
+        // a deferred transaction is only allowed by the defer policy
+        let deferred = sequencing_order(&block, &[2, 0, 1]);
+        assert_eq!(
+            validate_sender_nonces(&block, NonceGapPolicy::Drop, Some(&deferred)),
+            Err(ConsensusError::TransactionOutOfOrder { hash: block.body[2].hash() })
+        );
+        assert_eq!(validate_sender_nonces(&block, NonceGapPolicy::Drop, None), Ok(()));
+    }
+
+    #[test]
+    fn deposit_order() {
+        let (deposits, user) = (Address::with_last_byte(0xd1), Address::with_last_byte(1));
+        assert_eq!(
+            validate_deposit_order(&block(&[(deposits, 0), (deposits, 1), (user, 0)]), deposits),
+            Ok(())
+        );
+        assert_eq!(validate_deposit_order(&block(&[(user, 0)]), deposits), Ok(()));
+
+        let block = block(&[(deposits, 0), (user, 0), (deposits, 1)]);
+        assert_eq!(
+            validate_deposit_order(&block, deposits),
+            Err(ConsensusError::DepositOutOfOrder { hash: block.body[2].hash() })
+        );
+    }
+
+    #[test]
+    fn transaction_sizes() {
+        let transaction = TransactionSigned::from_transaction_and_signature(
+            Transaction::Legacy(TxLegacy { input: vec![0; 100].into(), ..Default::default() }),
+            Signature::default(),
+        );
+        let hash = transaction.hash();
+        let block = SealedBlock { body: vec![transaction], ..Default::default() };
+
+        let limits = TransactionSizeLimits { max_calldata_size: Some(100), ..Default::default() };
+        assert_eq!(validate_transaction_sizes(&block, &limits), Ok(()));
+        let limits = TransactionSizeLimits { max_calldata_size: Some(99), ..Default::default() };
+        assert_eq!(
+            validate_transaction_sizes(&block, &limits),
+            Err(ConsensusError::TransactionTooLarge { hash })
+        );
+        let limits =
+            TransactionSizeLimits { max_transaction_size: Some(100), ..Default::default() };
+        assert!(validate_transaction_sizes(&block, &limits).is_err());
+    }
+
+    #[test]
+    fn block_commitment() {
+        let message_hash = B256::with_last_byte(1);
+        let receipts = vec![Receipt {
+            logs: vec![Log::new_unchecked(
+                MESSAGE_QUEUE,
+                vec![MessageSent::SIGNATURE_HASH, message_hash],
+                Bytes::new(),
+            )],
+            ..Default::default()
+        }];
+        let digest = B256::repeat_byte(1);
+        let header =
+            |extra_data: Bytes| Header { mix_hash: digest, extra_data, ..Default::default() };
+
+        let commitment = BlockCommitment::new(digest.into(), 3, 1, message_hash);
+        assert_eq!(validate_block_commitment(&header(commitment.encode()), &receipts), Ok(()));
+        let empty = BlockCommitment::new(digest.into(), 3, 1, B256::ZERO);
+        assert_eq!(validate_block_commitment(&header(empty.encode()), &[]), Ok(()));
+        assert_eq!(
+            validate_block_commitment(&header(empty.encode()), &receipts),
+            Err(ConsensusError::BlockCommitmentDiff(
+                GotExpected {
+                    got: B256::from_slice(&empty.encode()),
+                    expected: B256::from_slice(&commitment.encode()),
+                }
+                .into()
+            ))
+        );
+        // a commitment to another sub-dag
+        let other = BlockCommitment::new(B256::ZERO.into(), 3, 1, message_hash);
+        assert!(validate_block_commitment(&header(other.encode()), &receipts).is_err());
+        assert_eq!(
+            validate_block_commitment(&header(Bytes::new()), &[]),
+            Err(ConsensusError::BlockCommitmentMissing)
+        );
+    }
+
+    /// Returns a header at the given height that commits to the sub-dag with the given digest.
+    fn committed_header(number: u64, mix_hash: B256, round: u64, part: u32) -> Header {
+        let extra_data = BlockCommitment::new(mix_hash.into(), round, part, B256::ZERO).encode();
+        Header { number, mix_hash, extra_data, ..Default::default() }
+    }
+
+    #[test]
+    fn block_commitment_against_parent() {
+        let (first, second) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        let genesis = Header::default();
+        assert_eq!(
+            validate_block_commitment_against_parent(&committed_header(1, first, 2, 0), &genesis),
+            Ok(())
+        );
+        // the sub-dag of the parent was split across several blocks
+        let parent = committed_header(1, first, 2, 0);
+        assert_eq!(
+            validate_block_commitment_against_parent(&committed_header(2, first, 2, 1), &parent),
+            Ok(())
+        );
+        assert_eq!(
+            validate_block_commitment_against_parent(&committed_header(2, first, 4, 1), &parent),
+            Err(ConsensusError::SubDagRoundDiff(GotExpected { got: 4, expected: 2 }))
+        );
+        assert_eq!(
+            validate_block_commitment_against_parent(&committed_header(2, first, 2, 2), &parent),
+            Err(ConsensusError::SubDagPartDiff(GotExpected { got: 2, expected: 1 }))
+        );
+        // the first block of the next sub-dag
+        assert_eq!(
+            validate_block_commitment_against_parent(&committed_header(2, second, 4, 0), &parent),
+            Ok(())
+        );
+        assert_eq!(
+            validate_block_commitment_against_parent(&committed_header(2, second, 4, 1), &parent),
+            Err(ConsensusError::SubDagPartDiff(GotExpected { got: 1, expected: 0 }))
+        );
+
+        // a sub-dag can't be split across more blocks than allowed
+        let last = committed_header(1, first, 2, MAX_BLOCKS_PER_SUB_DAG - 1);
+        assert_eq!(
+            validate_block_commitment_against_parent(
+                &committed_header(2, first, 2, MAX_BLOCKS_PER_SUB_DAG),
+                &last
+            ),
+            Err(ConsensusError::SubDagBlocksExceeded { max: MAX_BLOCKS_PER_SUB_DAG.into() })
+        );
+    }
+
+    #[test]
+    fn sub_dag_commitment() {
+        let sub_dag = |index: u64| StoredSubDag {
+            index,
+            round: 2 * (index + 1),
+            certificates: vec![CertificateDigest::new(B256::with_last_byte(index as u8))],
+            ..Default::default()
+        };
+        let store = MemoryDagStore::default();
+        for index in 0..2 {
+            store.write_sub_dag(&sub_dag(index)).unwrap();
+        }
+        let (first, second) = (sub_dag(0).digest().0, sub_dag(1).digest().0);
+        let genesis = Header::default();
+
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(1, first, 2, 0), &genesis, &store),
+            Ok(())
+        );
+        let parent = committed_header(1, first, 2, 0);
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(2, second, 4, 0), &parent, &store),
+            Ok(())
+        );
+        // the sub-dag of the parent was split across several blocks
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(2, first, 2, 1), &parent, &store),
+            Ok(())
+        );
+        // the header must commit to the round of the leader of its sub-dag
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(2, second, 6, 0), &parent, &store),
+            Err(ConsensusError::SubDagRoundDiff(GotExpected { got: 6, expected: 4 }))
+        );
+        // the first block must commit to the first sub-dag
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(1, second, 4, 0), &genesis, &store),
+            Err(ConsensusError::SubDagIndexDiff(GotExpected { got: 1, expected: 0 }))
+        );
+        let parent = committed_header(2, second, 4, 0);
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(3, first, 2, 0), &parent, &store),
+            Err(ConsensusError::SubDagIndexDiff(GotExpected { got: 0, expected: 2 }))
+        );
+
+        // sub-dags that are not recorded fail closed
+        let unrelated = B256::repeat_byte(1);
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(3, unrelated, 6, 0), &parent, &store),
+            Err(ConsensusError::SubDagUnknown(unrelated))
+        );
+        let parent = committed_header(2, unrelated, 4, 0);
+        assert_eq!(
+            validate_sub_dag_commitment(&committed_header(3, second, 4, 0), &parent, &store),
+            Err(ConsensusError::SubDagUnknown(unrelated))
+        );
+        assert_eq!(
+            validate_sub_dag_commitment(
+                &committed_header(1, first, 2, 0),
+                &genesis,
+                &MemoryDagStore::default()
+            ),
+            Err(ConsensusError::SubDagUnknown(first))
+        );
+    }
+
+    #[test]
+    fn timestamp() {
+        let header = |timestamp| Header { timestamp, ..Default::default() };
+        assert_eq!(validate_timestamp(&header(11), &header(10)), Ok(()));
+        assert_eq!(
+            validate_timestamp(&header(10), &header(10)),
+            Err(ConsensusError::TimestampIsInPast { parent_timestamp: 10, timestamp: 10 })
+        );
+    }
+}
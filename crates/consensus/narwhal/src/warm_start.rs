@@ -0,0 +1,188 @@
+//! Warm start of the execution caches after a restart.
+//!
+//! After a restart, the first blocks are executed against cold caches and every state access hits
+//! the database, which shows up as a spike of the lag between commit and sealed block. The executor
+//! records the accounts and storage slots it accesses in a [`HotStateTracker`]. At shutdown, a
+//! [`HotStateSummary`] of the most accessed ones is written to disk, and on startup they are
+//! prefetched into the caches before the first commit is executed. The executor counts the
+//! accounts and storage slots every block changes, and reads the summary ahead with
+//! `ConsensusOutputExecutor::warm_start`.
+//!
+//! The summary only affects performance, a missing or stale summary is harmless.
+
+use alloy_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    hash::Hash,
+    io::{self, Write},
+    path::Path,
+};
+
+/// Configuration of the [`HotStateTracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WarmStartConfig {
+    /// The maximum number of accounts in the summary.
+    pub max_accounts: usize,
+    /// The maximum number of storage slots in the summary.
+    pub max_slots: usize,
+}
+
+impl Default for WarmStartConfig {
+    fn default() -> Self {
+        Self { max_accounts: 4_096, max_slots: 16_384 }
+    }
+}
+
+/// A storage slot of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct HotSlot {
+    /// The address of the account.
+    pub address: Address,
+    /// The storage slot.
+    pub slot: B256,
+}
+
+/// The most accessed accounts and storage slots, hottest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HotStateSummary {
+    /// The accounts to prefetch.
+    pub accounts: Vec<Address>,
+    /// The storage slots to prefetch.
+    pub slots: Vec<HotSlot>,
+}
+
+impl HotStateSummary {
+    /// Writes the summary to the given file, replacing it atomically.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        serde_json::to_writer(&mut file, self)?;
+        file.write_all(b"\n")?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Reads the summary from the given file.
+    ///
+    /// Returns `None` if the file doesn't exist, e.g. on the first start.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read(path) {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Counts the accesses of accounts and storage slots during execution.
+///
+/// Memory is bounded: once more than four times the configured number of entries are tracked,
+/// only the hottest ones are kept.
+#[derive(Debug, Default)]
+pub struct HotStateTracker {
+    config: WarmStartConfig,
+    accounts: HashMap<Address, u64>,
+    slots: HashMap<HotSlot, u64>,
+}
+
+impl HotStateTracker {
+    /// Creates a new tracker.
+    pub fn new(config: WarmStartConfig) -> Self {
+        Self { config, ..Default::default() }
+    }
+
+    /// Records an access of an account.
+    pub fn record_account(&mut self, address: Address) {
+        *self.accounts.entry(address).or_default() += 1;
+        if self.accounts.len() > self.config.max_accounts.saturating_mul(4) {
+            self.accounts = hottest(&self.accounts, self.config.max_accounts)
+                .map(|address| (address, self.accounts[&address]))
+                .collect();
+        }
+    }
+
+    /// Records an access of a storage slot.
+    pub fn record_slot(&mut self, address: Address, slot: B256) {
+        *self.slots.entry(HotSlot { address, slot }).or_default() += 1;
+        if self.slots.len() > self.config.max_slots.saturating_mul(4) {
+            self.slots = hottest(&self.slots, self.config.max_slots)
+                .map(|slot| (slot, self.slots[&slot]))
+                .collect();
+        }
+    }
+
+    /// Returns the summary of the hottest accounts and storage slots.
+    pub fn summary(&self) -> HotStateSummary {
+        HotStateSummary {
+            accounts: hottest(&self.accounts, self.config.max_accounts).collect(),
+            slots: hottest(&self.slots, self.config.max_slots).collect(),
+        }
+    }
+}
+
+/// Returns the `limit` keys with the highest counts, hottest first.
+fn hottest<K: Copy + Ord + Hash>(
+    counts: &HashMap<K, u64>,
+    limit: usize,
+) -> impl Iterator<Item = K> {
+    let mut entries = counts.iter().map(|(key, count)| (*count, *key)).collect::<Vec<_>>();
+    // ties are broken by key, so the summary doesn't depend on the iteration order of the map
+    entries.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    entries.into_iter().take(limit).map(|(_, key)| key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hottest_entries() {
+        let mut tracker = HotStateTracker::new(WarmStartConfig { max_accounts: 2, max_slots: 1 });
+        for (address, accesses) in [(1, 1), (2, 3), (3, 2), (4, 1)] {
+            for _ in 0..accesses {
+                tracker.record_account(Address::with_last_byte(address));
+            }
+        }
+        tracker.record_slot(Address::with_last_byte(1), B256::ZERO);
+        tracker.record_slot(Address::with_last_byte(1), B256::with_last_byte(1));
+        tracker.record_slot(Address::with_last_byte(1), B256::with_last_byte(1));
+
+        let summary = tracker.summary();
+        assert_eq!(summary.accounts, vec![Address::with_last_byte(2), Address::with_last_byte(3)]);
+        assert_eq!(
+            summary.slots,
+            vec![HotSlot { address: Address::with_last_byte(1), slot: B256::with_last_byte(1) }]
+        );
+    }
+
+    #[test]
+    fn bounded_tracking() {
+        let mut tracker = HotStateTracker::new(WarmStartConfig { max_accounts: 2, max_slots: 1 });
+        tracker.record_account(Address::ZERO);
+        for address in 0..=8 {
+            tracker.record_account(Address::with_last_byte(address));
+        }
+        assert_eq!(tracker.accounts.len(), 2);
+        assert_eq!(tracker.summary().accounts[0], Address::ZERO);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warm-start.json");
+        assert_eq!(HotStateSummary::load(&path).unwrap(), None);
+
+        let summary = HotStateSummary {
+            accounts: vec![Address::with_last_byte(1)],
+            slots: vec![HotSlot { address: Address::with_last_byte(1), slot: B256::ZERO }],
+        };
+        summary.save(&path).unwrap();
+        assert_eq!(HotStateSummary::load(&path).unwrap(), Some(summary));
+    }
+}
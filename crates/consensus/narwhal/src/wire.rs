@@ -0,0 +1,345 @@
+//! Versioned wire format of the messages between validators.
+//!
+//! Every [`NarwhalMessage`] is prefixed with the version of its encoding and its kind, followed by
+//! the encoded payload. When two validators connect, both send a [`Handshake`] with the range of
+//! versions they support, and all messages of the connection are encoded with the highest version
+//! both support, see [`Handshake::negotiate`].
+//!
+//! A release that changes the encoding adds a version and keeps supporting the previous ones. The
+//! validators of a committee can then upgrade one by one: upgraded validators keep talking to the
+//! others with the previous version, and switch to the new version once both sides of a connection
+//! support it. Operators can hold back the new version until the whole committee upgraded with
+//! [`WireConfig::max_version`].
+//!
+//! The [`LocalTransport`](crate::dev::LocalTransport) of a dev committee negotiates the version
+//! of every pair of workers from the versions of
+//! [`NarwhalConfig::wire`](crate::NarwhalConfig::wire), and sends batches in the wire format of
+//! that version.
+//!
+//! Version 1 encodes the payload as the RLP encoding of its type, see [`crate::types`].
+
+use crate::types::{Batch, Certificate, Header, Vote};
+use alloy_rlp::{Decodable, Encodable};
+use serde::{Deserialize, Serialize};
+
+/// The version of the encoding of a message.
+pub type WireVersion = u8;
+
+/// The oldest version this release can encode and decode.
+pub const MIN_WIRE_VERSION: WireVersion = 1;
+
+/// The newest version this release can encode and decode.
+pub const MAX_WIRE_VERSION: WireVersion = 1;
+
+/// The first byte of a [`Handshake`], which no message starts with.
+///
+/// The format of the handshake never changes, so that peers of any version can read it.
+const HANDSHAKE_MARKER: u8 = 0;
+
+/// Errors of the wire format.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WireError {
+    /// The message is shorter than its prefix.
+    #[error("message too short")]
+    TooShort,
+    /// The message is encoded with a version this release doesn't support.
+    #[error("unsupported wire version {0}")]
+    UnsupportedVersion(WireVersion),
+    /// The message is encoded with another version than the one negotiated for the connection.
+    #[error("message of wire version {actual} on a connection of version {expected}")]
+    UnexpectedVersion {
+        /// The negotiated version of the connection.
+        expected: WireVersion,
+        /// The version of the message.
+        actual: WireVersion,
+    },
+    /// The kind of the message is unknown in its version.
+    #[error("unknown message kind {kind} in wire version {version}")]
+    UnknownKind {
+        /// The version of the message.
+        version: WireVersion,
+        /// The kind of the message.
+        kind: u8,
+    },
+    /// The payload of the message is malformed.
+    #[error("malformed payload: {0}")]
+    Payload(#[from] alloy_rlp::Error),
+    /// The bytes are not a handshake.
+    #[error("invalid handshake")]
+    InvalidHandshake,
+    /// The peer supports none of the versions of this release.
+    #[error("no common wire version, supported {local}, peer supports {remote}")]
+    NoCommonVersion {
+        /// The versions of this node.
+        local: VersionRange,
+        /// The versions of the peer.
+        remote: VersionRange,
+    },
+}
+
+/// Configuration of the wire format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WireConfig {
+    /// The newest version the node offers in its handshakes.
+    ///
+    /// Values outside the versions of the release are clamped to them.
+    pub max_version: WireVersion,
+}
+
+impl WireConfig {
+    /// Returns the versions the node offers in its handshakes.
+    pub fn versions(&self) -> VersionRange {
+        VersionRange {
+            min: MIN_WIRE_VERSION,
+            max: self.max_version.clamp(MIN_WIRE_VERSION, MAX_WIRE_VERSION),
+        }
+    }
+}
+
+impl Default for WireConfig {
+    fn default() -> Self {
+        Self { max_version: MAX_WIRE_VERSION }
+    }
+}
+
+/// An inclusive range of wire versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionRange {
+    /// The oldest version.
+    pub min: WireVersion,
+    /// The newest version.
+    pub max: WireVersion,
+}
+
+impl VersionRange {
+    /// All versions of this release.
+    pub const SUPPORTED: Self = Self { min: MIN_WIRE_VERSION, max: MAX_WIRE_VERSION };
+
+    /// Returns `true` if the range contains the version.
+    pub const fn contains(&self, version: WireVersion) -> bool {
+        self.min <= version && version <= self.max
+    }
+}
+
+impl std::fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..={}", self.min, self.max)
+    }
+}
+
+/// The first message on a connection between two validators, with the versions the sender
+/// supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handshake {
+    /// The versions the sender supports.
+    pub versions: VersionRange,
+}
+
+impl Handshake {
+    /// Creates a handshake that offers the given versions.
+    pub const fn new(versions: VersionRange) -> Self {
+        Self { versions }
+    }
+
+    /// Encodes the handshake.
+    pub fn encode(&self) -> Vec<u8> {
+        vec![HANDSHAKE_MARKER, self.versions.min, self.versions.max]
+    }
+
+    /// Decodes a handshake.
+    ///
+    /// Trailing bytes are ignored, so that later releases can extend the handshake.
+    pub fn decode(bytes: &[u8]) -> Result<Self, WireError> {
+        match bytes {
+            [HANDSHAKE_MARKER, min, max, ..] if min <= max => {
+                Ok(Self::new(VersionRange { min: *min, max: *max }))
+            }
+            _ => Err(WireError::InvalidHandshake),
+        }
+    }
+
+    /// Returns the version of the connection with the peer that sent the handshake, the highest
+    /// version both sides support.
+    ///
+    /// Both sides of a connection compute the same version from the two handshakes.
+    pub fn negotiate(&self, peer: &Self) -> Result<WireVersion, WireError> {
+        let (local, remote) = (self.versions, peer.versions);
+        let version = local.max.min(remote.max);
+        if local.contains(version) && remote.contains(version) {
+            Ok(version)
+        } else {
+            Err(WireError::NoCommonVersion { local, remote })
+        }
+    }
+}
+
+/// A message between the primaries or the workers of two validators.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NarwhalMessage {
+    /// A header proposed by the sender.
+    Header(Header),
+    /// A vote of the sender for a header.
+    Vote(Vote),
+    /// A certificate of the DAG.
+    Certificate(Certificate),
+    /// A batch of a worker.
+    Batch(Batch),
+}
+
+impl NarwhalMessage {
+    /// Returns the kind of the message in its prefix.
+    const fn kind(&self) -> u8 {
+        match self {
+            Self::Header(_) => 0,
+            Self::Vote(_) => 1,
+            Self::Certificate(_) => 2,
+            Self::Batch(_) => 3,
+        }
+    }
+
+    /// Encodes the message with the given version, usually the negotiated version of the
+    /// connection.
+    pub fn encode(&self, version: WireVersion) -> Result<Vec<u8>, WireError> {
+        if !VersionRange::SUPPORTED.contains(version) {
+            return Err(WireError::UnsupportedVersion(version))
+        }
+        let mut out = vec![version, self.kind()];
+        match self {
+            Self::Header(header) => header.encode(&mut out),
+            Self::Vote(vote) => vote.encode(&mut out),
+            Self::Certificate(certificate) => certificate.encode(&mut out),
+            Self::Batch(batch) => batch.encode(&mut out),
+        }
+        Ok(out)
+    }
+
+    /// Decodes a message, and returns it with the version it was encoded with.
+    ///
+    /// A peer must only send messages of the negotiated version, the caller should drop the
+    /// connection if the version differs.
+    pub fn decode(bytes: &[u8]) -> Result<(WireVersion, Self), WireError> {
+        let [version, kind, payload @ ..] = bytes else { return Err(WireError::TooShort) };
+        let (version, kind, mut payload) = (*version, *kind, payload);
+        if !VersionRange::SUPPORTED.contains(version) {
+            return Err(WireError::UnsupportedVersion(version))
+        }
+        let message = match kind {
+            0 => Self::Header(Header::decode(&mut payload)?),
+            1 => Self::Vote(Vote::decode(&mut payload)?),
+            2 => Self::Certificate(Certificate::decode(&mut payload)?),
+            3 => Self::Batch(Batch::decode(&mut payload)?),
+            kind => return Err(WireError::UnknownKind { version, kind }),
+        };
+        if !payload.is_empty() {
+            return Err(alloy_rlp::Error::UnexpectedLength.into())
+        }
+        Ok((version, message))
+    }
+
+    /// Decodes a message received on a connection of the given negotiated version, and rejects
+    /// messages of any other version.
+    pub fn decode_negotiated(bytes: &[u8], version: WireVersion) -> Result<Self, WireError> {
+        match Self::decode(bytes)? {
+            (actual, message) if actual == version => Ok(message),
+            (actual, _) => Err(WireError::UnexpectedVersion { expected: version, actual }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BatchDigest, BatchRef, HeaderDigest};
+    use alloy_primitives::{Bytes, B256};
+
+    fn header() -> Header {
+        Header {
+            epoch: 1,
+            round: 2,
+            author: 3,
+            payload: vec![BatchRef { digest: BatchDigest::of(&[]), worker: 0 }],
+            parents: Vec::new(),
+            created_at: 1_700_000_000_000,
+        }
+    }
+
+    #[test]
+    fn message_roundtrip() {
+        let messages = [
+            NarwhalMessage::Header(header()),
+            NarwhalMessage::Vote(Vote {
+                header: HeaderDigest(B256::with_last_byte(1)),
+                epoch: 1,
+                round: 2,
+                origin: 3,
+                voter: 0,
+                signature: Bytes::from_static(&[4; 96]),
+            }),
+            NarwhalMessage::Certificate(Certificate {
+                header: header(),
+                signers: vec![0, 1, 3],
+                signature: Bytes::from_static(&[5; 96]),
+            }),
+            NarwhalMessage::Batch(Batch::new(vec![Bytes::from_static(&[0x02, 1])])),
+        ];
+        for message in messages {
+            let encoded = message.encode(1).unwrap();
+            assert_eq!(encoded[0], 1);
+            assert_eq!(NarwhalMessage::decode(&encoded).unwrap(), (1, message));
+        }
+    }
+
+    #[test]
+    fn reject_unknown_messages() {
+        let message = NarwhalMessage::Batch(Batch::new(Vec::new()));
+        assert_eq!(message.encode(2), Err(WireError::UnsupportedVersion(2)));
+
+        let mut encoded = message.encode(1).unwrap();
+        encoded[0] = 2;
+        assert_eq!(NarwhalMessage::decode(&encoded), Err(WireError::UnsupportedVersion(2)));
+        encoded[0] = 1;
+        encoded[1] = 9;
+        assert_eq!(
+            NarwhalMessage::decode(&encoded),
+            Err(WireError::UnknownKind { version: 1, kind: 9 })
+        );
+        encoded[1] = 3;
+        encoded.push(0);
+        assert!(matches!(NarwhalMessage::decode(&encoded), Err(WireError::Payload(_))));
+        assert_eq!(NarwhalMessage::decode(&[1]), Err(WireError::TooShort));
+        assert_eq!(
+            NarwhalMessage::decode_negotiated(&message.encode(1).unwrap(), 2),
+            Err(WireError::UnexpectedVersion { expected: 2, actual: 1 })
+        );
+        // a handshake is not a message
+        let handshake = Handshake::new(VersionRange::SUPPORTED).encode();
+        assert_eq!(NarwhalMessage::decode(&handshake), Err(WireError::UnsupportedVersion(0)));
+    }
+
+    #[test]
+    fn negotiate_highest_common_version() {
+        let handshake = |min, max| Handshake::new(VersionRange { min, max });
+        let local = handshake(1, 3);
+        assert_eq!(local.negotiate(&handshake(2, 5)), Ok(3));
+        assert_eq!(handshake(2, 5).negotiate(&local), Ok(3));
+        assert_eq!(local.negotiate(&handshake(1, 2)), Ok(2));
+        assert_eq!(
+            local.negotiate(&handshake(4, 5)),
+            Err(WireError::NoCommonVersion {
+                local: local.versions,
+                remote: VersionRange { min: 4, max: 5 }
+            })
+        );
+
+        // later releases may append fields to the handshake
+        let mut encoded = local.encode();
+        encoded.push(7);
+        assert_eq!(Handshake::decode(&encoded), Ok(local));
+        assert_eq!(Handshake::decode(&[0, 2, 1]), Err(WireError::InvalidHandshake));
+        assert_eq!(Handshake::decode(&[1, 1, 1]), Err(WireError::InvalidHandshake));
+
+        let config = WireConfig { max_version: 0 };
+        assert_eq!(config.versions(), VersionRange { min: 1, max: 1 });
+    }
+}
@@ -0,0 +1,849 @@
+//! Sealing of pool transactions into batches.
+//!
+//! The [`BatchMaker`] of a worker listens for transactions that become pending in the transaction
+//! pool and accumulates them in a [`BatchBuilder`]. A batch is sealed once it reaches the
+//! configured size or number of transactions, or once `max_batch_delay` passed without that
+//! happening, so transactions are never held back for long on a quiet network. Sealed batches are
+//! handed to the primary, which references them in its next header.
+
+use crate::types::{Batch, BatchDigest, BatchRef, WorkerId};
+use alloy_primitives::{Bytes, TxHash};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Configuration of the [`BatchBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BatchConfig {
+    /// The size of the encoded transactions at which a batch is sealed, in bytes.
+    ///
+    /// A transaction that doesn't fit into the current batch starts a new one, so only a batch
+    /// with a single transaction can be larger.
+    pub max_batch_bytes: usize,
+    /// The maximum time a batch stays open before it's sealed.
+    #[serde(with = "humantime_serde")]
+    pub max_batch_delay: Duration,
+    /// The number of transactions at which a batch is sealed.
+    pub max_transactions: usize,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_bytes: 500_000,
+            max_batch_delay: Duration::from_millis(100),
+            max_transactions: 1_000,
+        }
+    }
+}
+
+/// A batch sealed by a worker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedBatch {
+    /// The worker that sealed the batch.
+    pub worker: WorkerId,
+    /// The digest of the batch.
+    pub digest: BatchDigest,
+    /// The batch.
+    pub batch: Batch,
+    /// The hashes of the transactions of the batch, in batch order.
+    pub transaction_hashes: Vec<TxHash>,
+}
+
+impl SealedBatch {
+    /// Returns the reference to the batch that is included in a header.
+    pub const fn batch_ref(&self) -> BatchRef {
+        BatchRef { digest: self.digest, worker: self.worker }
+    }
+}
+
+/// Accumulates transactions into size bounded batches.
+#[derive(Debug)]
+pub struct BatchBuilder {
+    config: BatchConfig,
+    worker: WorkerId,
+    transactions: Vec<Bytes>,
+    hashes: Vec<TxHash>,
+    size: usize,
+}
+
+impl BatchBuilder {
+    /// Creates a builder for the batches of the given worker.
+    pub const fn new(config: BatchConfig, worker: WorkerId) -> Self {
+        Self { config, worker, transactions: Vec::new(), hashes: Vec::new(), size: 0 }
+    }
+
+    /// Returns the worker of the batches.
+    pub const fn worker(&self) -> WorkerId {
+        self.worker
+    }
+
+    /// Returns the configuration of the builder.
+    pub const fn config(&self) -> &BatchConfig {
+        &self.config
+    }
+
+    /// Returns `true` if no transactions are pending.
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    /// Returns `true` if the pending batch reached the configured size or number of transactions.
+    pub fn is_full(&self) -> bool {
+        self.transactions.len() >= self.config.max_transactions ||
+            self.size >= self.config.max_batch_bytes
+    }
+
+    /// Adds an encoded transaction to the pending batch.
+    ///
+    /// If the transaction doesn't fit into the pending batch anymore, that batch is sealed first
+    /// and returned. The caller should check [`Self::is_full`] afterwards.
+    pub fn push(&mut self, hash: TxHash, transaction: Bytes) -> Option<SealedBatch> {
+        let sealed = if self.size + transaction.len() > self.config.max_batch_bytes {
+            self.seal()
+        } else {
+            None
+        };
+        self.size += transaction.len();
+        self.transactions.push(transaction);
+        self.hashes.push(hash);
+        sealed
+    }
+
+    /// Seals the pending batch, if it has any transactions.
+    pub fn seal(&mut self) -> Option<SealedBatch> {
+        if self.is_empty() {
+            return None
+        }
+        self.size = 0;
+        let batch = Batch::new(std::mem::take(&mut self.transactions));
+        Some(SealedBatch {
+            worker: self.worker,
+            digest: batch.digest(),
+            batch,
+            transaction_hashes: std::mem::take(&mut self.hashes),
+        })
+    }
+}
+
+#[cfg(feature = "execution")]
+pub use maker::BatchMaker;
+
+#[cfg(feature = "execution")]
+mod maker {
+    use super::{BatchBuilder, BatchConfig, SealedBatch};
+    use crate::{
+        backpressure::Backpressure,
+        dag_store::DagStore,
+        events::{NarwhalEvent, NarwhalEvents},
+        metrics::ConsensusMetrics,
+        sequencing::SequencingFilter,
+        trace::TraceIds,
+        types::{Round, TransactionExpiry, WorkerId},
+        worker::{
+            BatchDeduplicator, BatchOrigin, BatchRecord, FirehoseSink, RateLimitOutcome,
+            SenderRateLimiter, SequencedTransactions, TransactionClass, TransactionRouting,
+            TransactionSizeLimits, WorkerHandle, WorkerNetworkError,
+        },
+    };
+    use alloy_primitives::{Bytes, TxHash};
+    use futures_util::StreamExt;
+    use reth_metrics::{
+        metrics::{Counter, Histogram},
+        Metrics,
+    };
+    use reth_primitives::{
+        IntoRecoveredTransaction, SealedHeader, TransactionSigned, TransactionSignedEcRecovered,
+    };
+    use reth_transaction_pool::{
+        NewSubpoolTransactionStream, PoolTransaction, SubPool, TransactionListenerKind,
+        TransactionPool,
+    };
+    use std::{collections::VecDeque, sync::Arc};
+    use tokio::{
+        sync::{mpsc, watch},
+        time::Instant,
+    };
+    use tracing::{debug, error, trace};
+
+    /// Metrics of the [`BatchMaker`].
+    #[derive(Metrics)]
+    #[metrics(scope = "narwhal.worker.batch_maker")]
+    struct BatchMakerMetrics {
+        /// Number of sealed batches
+        sealed_batches: Counter,
+        /// Number of batches sealed because `max_batch_delay` passed
+        timed_out_batches: Counter,
+        /// Number of transactions per sealed batch
+        batch_transactions: Histogram,
+        /// Size of the sealed batches in bytes
+        batch_bytes: Histogram,
+        /// Number of transactions that were not batched because they exceed the size limits
+        oversized_transactions: Counter,
+        /// Number of transactions that were left to the worker of their class
+        routed_away_transactions: Counter,
+        /// Number of transactions that were not batched because the sequencing filter rejected
+        /// them
+        filtered_transactions: Counter,
+        /// Number of sealed batches that waited for execution to catch up
+        throttled_batches: Counter,
+        /// Number of transactions submitted over gRPC
+        submitted_transactions: Counter,
+        /// Number of submitted transactions that were not valid signed transactions
+        invalid_submitted_transactions: Counter,
+        /// Number of rate limited transactions that were left to the pool because too many
+        /// transactions were held back already
+        dropped_rate_limited_transactions: Counter,
+    }
+
+    /// A transaction that passed the routing, with its hash and expiry.
+    type PendingTransaction = (TxHash, TransactionSignedEcRecovered, Option<TransactionExpiry>);
+
+    /// Seals the pending transactions of a [`TransactionPool`] into batches and hands them to the
+    /// primary.
+    #[derive(Debug)]
+    pub struct BatchMaker<Pool> {
+        pool: Pool,
+        builder: BatchBuilder,
+        to_primary: mpsc::Sender<SealedBatch>,
+        deduplicator: Option<BatchDeduplicator>,
+        sequenced: Option<SequencedTransactions>,
+        routing: TransactionRouting,
+        size_limits: TransactionSizeLimits,
+        /// The filter of the chain and the head whose next block the transactions are checked for.
+        sequencing_filter: Option<(Arc<dyn SequencingFilter>, watch::Receiver<SealedHeader>)>,
+        firehose: Option<FirehoseSink>,
+        network: Option<WorkerHandle>,
+        trace_ids: Option<TraceIds>,
+        store: Option<Arc<dyn DagStore>>,
+        consensus_metrics: Option<ConsensusMetrics>,
+        backpressure: Option<Backpressure>,
+        events: Option<NarwhalEvents>,
+        /// Receives the transactions submitted over gRPC.
+        submissions: Option<mpsc::Receiver<Bytes>>,
+        rate_limiter: Option<SenderRateLimiter>,
+        /// The round of the primary the rate limiter budgets transactions for.
+        rounds: Option<watch::Receiver<Round>>,
+        /// The transactions the rate limiter held back, until the next batch or round.
+        held: VecDeque<PendingTransaction>,
+        /// The held back transactions that are checked again before new ones.
+        retry: VecDeque<PendingTransaction>,
+        /// When the first transaction of the pending batch arrived.
+        opened: Option<Instant>,
+        metrics: BatchMakerMetrics,
+    }
+
+    impl<Pool> BatchMaker<Pool>
+    where
+        Pool: TransactionPool + 'static,
+        Pool::Transaction: PoolTransaction<Consensus = TransactionSignedEcRecovered>,
+    {
+        /// Creates a batch maker for the given worker that sends its batches to the primary.
+        pub fn new(
+            pool: Pool,
+            config: BatchConfig,
+            worker: WorkerId,
+            to_primary: mpsc::Sender<SealedBatch>,
+        ) -> Self {
+            Self {
+                pool,
+                builder: BatchBuilder::new(config, worker),
+                to_primary,
+                deduplicator: None,
+                sequenced: None,
+                routing: TransactionRouting::default(),
+                size_limits: TransactionSizeLimits::default(),
+                sequencing_filter: None,
+                firehose: None,
+                network: None,
+                trace_ids: None,
+                store: None,
+                consensus_metrics: None,
+                backpressure: None,
+                events: None,
+                submissions: None,
+                rate_limiter: None,
+                rounds: None,
+                held: VecDeque::new(),
+                retry: VecDeque::new(),
+                opened: None,
+                metrics: BatchMakerMetrics::default(),
+            }
+        }
+
+        /// Skips transactions that another worker of this validator already batched.
+        pub fn with_deduplicator(mut self, deduplicator: BatchDeduplicator) -> Self {
+            self.deduplicator = Some(deduplicator);
+            self
+        }
+
+        /// Indexes the transactions of the sealed batches as sequenced.
+        pub fn with_sequenced_index(mut self, sequenced: SequencedTransactions) -> Self {
+            self.sequenced = Some(sequenced);
+            self
+        }
+
+        /// Only batches the transactions that the routing assigns to the worker.
+        ///
+        /// Every worker of the node must use the same routing, otherwise transactions are batched
+        /// twice or not at all. The routing should be validated with
+        /// [`TransactionRouting::validate`] beforehand.
+        pub fn with_routing(mut self, routing: TransactionRouting) -> Self {
+            self.routing = routing;
+            self
+        }
+
+        /// Skips transactions that exceed the size limits of the chain.
+        ///
+        /// Must be the limits of the chain spec, executors drop oversized transactions from
+        /// committed batches.
+        pub const fn with_size_limits(mut self, size_limits: TransactionSizeLimits) -> Self {
+            self.size_limits = size_limits;
+            self
+        }
+
+        /// Skips transactions the sequencing filter rejects for the block after the head.
+        ///
+        /// Must be the filter of the node's [`NarwhalConsensus`](crate::NarwhalConsensus), blocks
+        /// with rejected transactions are invalid. The head is usually the one the
+        /// [`ConsensusOutputExecutor`](crate::executor::ConsensusOutputExecutor) publishes.
+        pub fn with_sequencing_filter(
+            mut self,
+            filter: Arc<dyn SequencingFilter>,
+            head: watch::Receiver<SealedHeader>,
+        ) -> Self {
+            self.sequencing_filter = Some((filter, head));
+            self
+        }
+
+        /// Forwards the sealed batches to the firehose.
+        pub fn with_firehose(mut self, firehose: FirehoseSink) -> Self {
+            self.firehose = Some(firehose);
+            self
+        }
+
+        /// Replicates the sealed batches to the workers of the other authorities, and only hands a
+        /// batch to the primary once a quorum of the committee stored it.
+        pub fn with_network(mut self, network: WorkerHandle) -> Self {
+            self.network = Some(network);
+            self
+        }
+
+        /// Logs the trace ids of the batched transactions.
+        pub fn with_trace_ids(mut self, trace_ids: TraceIds) -> Self {
+            self.trace_ids = Some(trace_ids);
+            self
+        }
+
+        /// Stores the sealed batches, so that they can be served after a restart.
+        pub fn with_store(mut self, store: Arc<dyn DagStore>) -> Self {
+            self.store = Some(store);
+            self
+        }
+
+        /// Records the creation latency of the sealed batches, and tracks them for the commit
+        /// latency.
+        pub fn with_consensus_metrics(mut self, metrics: ConsensusMetrics) -> Self {
+            self.consensus_metrics = Some(metrics);
+            self
+        }
+
+        /// Holds back sealed batches while execution lags behind consensus.
+        ///
+        /// The batch maker stops sealing batches while it waits, so new transactions stay in the
+        /// pool.
+        pub fn with_backpressure(mut self, backpressure: Backpressure) -> Self {
+            self.backpressure = Some(backpressure);
+            self
+        }
+
+        /// Publishes the sealed batches.
+        pub fn with_events(mut self, events: NarwhalEvents) -> Self {
+            self.events = Some(events);
+            self
+        }
+
+        /// Batches the transactions submitted over gRPC next to the transactions of the pool, see
+        /// [`TransactionSubmissionServer`](crate::worker::TransactionSubmissionServer).
+        ///
+        /// Submitted transactions never enter the pool, so they are neither propagated to peers
+        /// nor validated against the state. Transactions that fail at execution are skipped.
+        ///
+        /// A submitted transaction may be in the batched encoding of a [`TransactionExpiry`], which
+        /// the batch keeps so executors drop the transaction once it expired.
+        pub fn with_submissions(mut self, submissions: mpsc::Receiver<Bytes>) -> Self {
+            self.submissions = Some(submissions);
+            self
+        }
+
+        /// Limits the transactions of a single sender per batch and per round of the primary, see
+        /// [`SenderRateLimiter`].
+        ///
+        /// Transactions above the limits are held back and checked again once the next batch is
+        /// sealed or the primary advances its round. At most `max_transactions` of the batch
+        /// config are held back, further limited transactions are left to the pool.
+        pub fn with_rate_limiter(
+            mut self,
+            rate_limiter: SenderRateLimiter,
+            rounds: watch::Receiver<Round>,
+        ) -> Self {
+            self.rate_limiter = Some(rate_limiter);
+            self.rounds = Some(rounds);
+            self
+        }
+
+        /// Runs the batch maker until the pool or the primary shuts down.
+        ///
+        /// Transactions of a batch that is still open at that point are left in the pool.
+        pub async fn run(mut self) {
+            // local transactions that are not propagated are sequenced as well
+            let mut pending = NewSubpoolTransactionStream::new(
+                self.pool.new_transactions_listener_for(TransactionListenerKind::All),
+                SubPool::Pending,
+            );
+            let max_batch_delay = self.builder.config().max_batch_delay;
+            let timer = tokio::time::sleep(max_batch_delay);
+            tokio::pin!(timer);
+
+            debug!(target: "consensus::narwhal", "Batch maker started");
+            loop {
+                let (hash, transaction, expiry) = if let Some(held) = self.retry.pop_front() {
+                    held
+                } else {
+                    tokio::select! {
+                        event = pending.next() => {
+                            let Some(event) = event else { return };
+                            let hash = *event.transaction.hash();
+                            let pooled = &event.transaction.transaction;
+                            let class = self.routing.classify(
+                                pooled.is_eip4844(),
+                                pooled.input().len(),
+                                pooled.max_priority_fee_per_gas(),
+                            );
+                            if !self.accepts(class, &hash) {
+                                continue
+                            }
+                            (hash, event.transaction.to_recovered_transaction(), None)
+                        }
+                        submitted = submitted(&mut self.submissions) => {
+                            let Some(encoded) = submitted else {
+                                // the submission service stopped, the pool is still batched
+                                self.submissions = None;
+                                continue
+                            };
+                            let Some((transaction, expiry)) = self.decode_submitted(&encoded) else {
+                                continue
+                            };
+                            let class = self.routing.classify(
+                                transaction.is_eip4844(),
+                                transaction.input().len(),
+                                transaction.max_priority_fee_per_gas(),
+                            );
+                            if !self.accepts(class, &transaction.hash()) {
+                                continue
+                            }
+                            (transaction.hash(), transaction, expiry)
+                        }
+                        () = &mut timer => {
+                            if let Some(batch) = self.builder.seal() {
+                                self.metrics.timed_out_batches.increment(1);
+                                let opened = self.opened.take();
+                                if !self.send(batch, opened).await {
+                                    return
+                                }
+                            }
+                            timer.as_mut().reset(Instant::now() + max_batch_delay);
+                            continue
+                        }
+                        round = round_changed(&mut self.rounds) => {
+                            match round {
+                                Some(round) => self.on_new_round(round),
+                                // the primary stopped, the round budgets are no longer refilled
+                                None => self.rounds = None,
+                            }
+                            continue
+                        }
+                    }
+                };
+
+                if let Err(err) = self.size_limits.check(&transaction) {
+                    self.metrics.oversized_transactions.increment(1);
+                    debug!(
+                        target: "consensus::narwhal",
+                        %hash,
+                        %err,
+                        "Skipping oversized transaction"
+                    );
+                    continue
+                }
+                if let Some((filter, head)) = &self.sequencing_filter {
+                    let block = head.borrow().number + 1;
+                    if let Err(rejection) = filter.check(block, transaction.signer(), &transaction)
+                    {
+                        self.metrics.filtered_transactions.increment(1);
+                        debug!(
+                            target: "consensus::narwhal",
+                            %hash,
+                            %rejection,
+                            "Skipping filtered transaction"
+                        );
+                        continue
+                    }
+                }
+                if let Some(rate_limiter) = &mut self.rate_limiter {
+                    if rate_limiter.check(transaction.signer()) != RateLimitOutcome::Accepted {
+                        self.hold((hash, transaction, expiry));
+                        continue
+                    }
+                }
+                let encoded = transaction.envelope_encoded();
+                let encoded = match expiry {
+                    Some(expiry) => expiry.encode_batched(&encoded),
+                    None => encoded,
+                };
+
+                // the transaction may seal the previous batch and fill the next one
+                let previous = self.builder.push(hash, encoded);
+                let previous = previous.map(|batch| (batch, self.opened.take()));
+                // the transaction is the first of the pending batch unless it joined it
+                self.opened.get_or_insert_with(Instant::now);
+                let full = self
+                    .builder
+                    .is_full()
+                    .then(|| self.builder.seal())
+                    .flatten()
+                    .map(|batch| (batch, self.opened.take()));
+                if previous.is_some() || full.is_some() {
+                    for (batch, opened) in previous.into_iter().chain(full) {
+                        if !self.send(batch, opened).await {
+                            return
+                        }
+                    }
+                    timer.as_mut().reset(Instant::now() + max_batch_delay);
+                }
+            }
+        }
+
+        /// Returns `true` if the transaction is routed to the worker and no other worker of this
+        /// validator batched it.
+        fn accepts(&mut self, class: TransactionClass, hash: &TxHash) -> bool {
+            if !self.routing.accepts(self.builder.worker(), class, hash) {
+                self.metrics.routed_away_transactions.increment(1);
+                return false
+            }
+            self.deduplicator.as_ref().map_or(true, |dedup| dedup.insert(hash))
+        }
+
+        /// Decodes a transaction submitted over gRPC with its expiry and recovers its sender,
+        /// `None` if it's not a valid signed transaction.
+        ///
+        /// Submitted transactions skip the validation of the pool, so at least their signature is
+        /// checked before they take space in a batch.
+        fn decode_submitted(
+            &self,
+            encoded: &Bytes,
+        ) -> Option<(TransactionSignedEcRecovered, Option<TransactionExpiry>)> {
+            self.metrics.submitted_transactions.increment(1);
+            let (expiry, mut encoded) = TransactionExpiry::split_batched(encoded);
+            let transaction = TransactionSigned::decode_enveloped(&mut encoded)
+                .ok()
+                .and_then(TransactionSigned::into_ecrecovered);
+            if transaction.is_none() {
+                self.metrics.invalid_submitted_transactions.increment(1);
+                debug!(target: "consensus::narwhal", "Skipping invalid submitted transaction");
+            }
+            transaction.map(|transaction| (transaction, expiry))
+        }
+
+        /// Holds back a transaction the rate limiter didn't accept, or leaves it to the pool if too
+        /// many transactions are held back already.
+        fn hold(&mut self, transaction: PendingTransaction) {
+            if self.held.len() >= self.builder.config().max_transactions {
+                self.metrics.dropped_rate_limited_transactions.increment(1);
+                debug!(
+                    target: "consensus::narwhal",
+                    hash = %transaction.0,
+                    sender = %transaction.1.signer(),
+                    "Leaving rate limited transaction to the pool"
+                );
+                return
+            }
+            self.held.push_back(transaction);
+        }
+
+        /// Refills the budgets of the senders for the new round and checks the held back
+        /// transactions again.
+        fn on_new_round(&mut self, round: Round) {
+            if let Some(rate_limiter) = &mut self.rate_limiter {
+                rate_limiter.on_new_round(round);
+                self.retry.extend(self.held.drain(..));
+            }
+        }
+
+        /// Hands a sealed batch to the primary, returns `false` if the primary or the worker
+        /// network shut down.
+        async fn send(&mut self, batch: SealedBatch, opened: Option<Instant>) -> bool {
+            if let Some(rate_limiter) = &mut self.rate_limiter {
+                rate_limiter.on_batch_sealed();
+                self.retry.extend(self.held.drain(..));
+            }
+            if let Some(backpressure) = &mut self.backpressure {
+                if backpressure.is_throttled() {
+                    self.metrics.throttled_batches.increment(1);
+                    debug!(
+                        target: "consensus::narwhal",
+                        lag = backpressure.lag(),
+                        "Waiting for execution to catch up"
+                    );
+                    backpressure.released().await;
+                }
+            }
+            self.metrics.sealed_batches.increment(1);
+            if let Some((metrics, opened)) = self.consensus_metrics.as_ref().zip(opened) {
+                metrics.record_batch_sealed(batch.digest, opened.into_std());
+            }
+            self.metrics.batch_transactions.record(batch.batch.len() as f64);
+            self.metrics.batch_bytes.record(batch.batch.size() as f64);
+            trace!(
+                target: "consensus::narwhal",
+                digest = %batch.digest,
+                transactions = batch.batch.len(),
+                "Sealed batch"
+            );
+
+            if let Some(store) = &self.store {
+                if let Err(err) = store.write_batch(batch.digest, &batch.batch) {
+                    error!(
+                        target: "consensus::narwhal",
+                        %err,
+                        digest = %batch.digest,
+                        "Failed to store batch"
+                    );
+                }
+            }
+            if let Some(sequenced) = &self.sequenced {
+                for hash in &batch.transaction_hashes {
+                    sequenced.insert(hash);
+                }
+            }
+            if let Some(trace_ids) = &self.trace_ids {
+                for hash in &batch.transaction_hashes {
+                    trace_ids.on_batched(hash);
+                }
+            }
+            if let Some(firehose) = &self.firehose {
+                firehose.forward(BatchRecord {
+                    digest: batch.digest,
+                    worker: batch.worker,
+                    origin: BatchOrigin::Created,
+                    transactions: batch.batch.transactions.clone(),
+                });
+            }
+            if let Some(events) = &self.events {
+                events.publish(NarwhalEvent::BatchCreated {
+                    worker: batch.worker,
+                    digest: batch.digest,
+                    transactions: batch.batch.len(),
+                });
+            }
+            if let Some(network) = &self.network {
+                match network.broadcast(batch.batch.clone()).await {
+                    Ok(_) => {}
+                    Err(WorkerNetworkError::Shutdown) => return false,
+                    Err(err) => {
+                        error!(
+                            target: "consensus::narwhal",
+                            %err,
+                            digest = %batch.digest,
+                            "Failed to replicate batch, dropping it"
+                        );
+                        return true
+                    }
+                }
+            }
+            self.to_primary.send(batch).await.is_ok()
+        }
+    }
+
+    /// Waits for the next submitted transaction, forever without the submission service.
+    async fn submitted(submissions: &mut Option<mpsc::Receiver<Bytes>>) -> Option<Bytes> {
+        match submissions {
+            Some(submissions) => submissions.recv().await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Waits for the next round of the primary, forever without a rate limiter. Returns `None`
+    /// once the primary stopped.
+    async fn round_changed(rounds: &mut Option<watch::Receiver<Round>>) -> Option<Round> {
+        match rounds {
+            Some(rounds) => rounds.changed().await.ok().map(|()| *rounds.borrow_and_update()),
+            None => std::future::pending().await,
+        }
+    }
+
+    #[cfg(all(test, feature = "execution-test-utils"))]
+    mod tests {
+        use super::*;
+        use crate::{
+            sequencing::{ChainSequencingFilter, FilterMode, SequencingFilterRules},
+            worker::SenderRateLimitConfig,
+        };
+        use reth_primitives::{
+            sign_message, Address, Header, Transaction, TxEip1559, TxKind, B256, U256,
+        };
+        use reth_transaction_pool::test_utils::testing_pool;
+
+        fn transfer(secret: u8) -> TransactionSigned {
+            transfer_with_nonce(secret, 0)
+        }
+
+        fn transfer_with_nonce(secret: u8, nonce: u64) -> TransactionSigned {
+            let transaction = Transaction::Eip1559(TxEip1559 {
+                chain_id: 1,
+                nonce,
+                gas_limit: 21_000,
+                max_fee_per_gas: 2_000_000_000,
+                to: TxKind::Call(Address::with_last_byte(0xff)),
+                value: U256::from(1),
+                ..Default::default()
+            });
+            let signature =
+                sign_message(B256::with_last_byte(secret), transaction.signature_hash()).unwrap();
+            TransactionSigned::from_transaction_and_signature(transaction, signature)
+        }
+
+        #[tokio::test]
+        async fn filtered_transaction_is_not_batched() {
+            let (allowed, denied) = (transfer(1), transfer(2));
+            let rules = SequencingFilterRules {
+                from_block: 1,
+                mode: FilterMode::Deny,
+                senders: [denied.recover_signer().unwrap()].into(),
+                ..Default::default()
+            };
+            let filter = Arc::new(ChainSequencingFilter::new(vec![rules]));
+            let (_head, head) = watch::channel(Header::default().seal_slow());
+            let (to_primary, mut batches) = mpsc::channel(1);
+            let (submit, submissions) = mpsc::channel(2);
+            let batch_maker =
+                BatchMaker::new(testing_pool(), BatchConfig::default(), 0, to_primary)
+                    .with_sequencing_filter(filter, head)
+                    .with_submissions(submissions);
+            tokio::spawn(batch_maker.run());
+
+            // the filter applies to the block after the genesis head
+            submit.send(denied.envelope_encoded()).await.unwrap();
+            submit.send(allowed.envelope_encoded()).await.unwrap();
+            let batch = batches.recv().await.unwrap();
+            assert_eq!(batch.transaction_hashes, vec![allowed.hash()]);
+        }
+
+        #[tokio::test]
+        async fn submitted_expiry_is_batched() {
+            let transaction = transfer(1);
+            let expiry = TransactionExpiry::Round(5);
+            let (to_primary, mut batches) = mpsc::channel(1);
+            let (submit, submissions) = mpsc::channel(1);
+            let config = BatchConfig { max_transactions: 1, ..Default::default() };
+            let batch_maker = BatchMaker::new(testing_pool(), config, 0, to_primary)
+                .with_submissions(submissions);
+            tokio::spawn(batch_maker.run());
+
+            submit.send(expiry.encode_batched(&transaction.envelope_encoded())).await.unwrap();
+            let batch = batches.recv().await.unwrap();
+            assert_eq!(batch.transaction_hashes, vec![transaction.hash()]);
+            assert_eq!(
+                TransactionExpiry::split_batched(&batch.batch.transactions[0]),
+                (Some(expiry), &transaction.envelope_encoded()[..])
+            );
+        }
+
+        #[tokio::test]
+        async fn rate_limited_transaction_is_held_back() {
+            let (first, second, other) =
+                (transfer_with_nonce(1, 0), transfer_with_nonce(1, 1), transfer(2));
+            let (round, rounds) = watch::channel(0);
+            let (to_primary, mut batches) = mpsc::channel(1);
+            let (submit, submissions) = mpsc::channel(3);
+            let config = BatchConfig { max_transactions: 2, ..Default::default() };
+            let rate_limiter = SenderRateLimiter::new(SenderRateLimitConfig {
+                max_transactions_per_batch: 1,
+                max_transactions_per_round: 1,
+                burst: 0,
+            });
+            let batch_maker = BatchMaker::new(testing_pool(), config, 0, to_primary)
+                .with_submissions(submissions)
+                .with_rate_limiter(rate_limiter, rounds);
+            tokio::spawn(batch_maker.run());
+
+            // the second transaction of the sender exceeds its budget of the round
+            for transaction in [&first, &second, &other] {
+                submit.send(transaction.envelope_encoded()).await.unwrap();
+            }
+            let batch = batches.recv().await.unwrap();
+            assert_eq!(batch.transaction_hashes, vec![first.hash(), other.hash()]);
+
+            round.send(1).unwrap();
+            let batch = batches.recv().await.unwrap();
+            assert_eq!(batch.transaction_hashes, vec![second.hash()]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(byte: u8, len: usize) -> (TxHash, Bytes) {
+        (TxHash::with_last_byte(byte), vec![byte; len].into())
+    }
+
+    #[test]
+    fn seal_at_limits() {
+        let config = BatchConfig { max_batch_bytes: 10, max_transactions: 3, ..Default::default() };
+        let mut builder = BatchBuilder::new(config, 1);
+        assert_eq!(builder.seal(), None);
+
+        let (hash, tx) = transaction(1, 4);
+        assert_eq!(builder.push(hash, tx), None);
+        let (hash, tx) = transaction(2, 4);
+        assert_eq!(builder.push(hash, tx), None);
+        assert!(!builder.is_full());
+
+        // doesn't fit anymore, the first two are sealed
+        let (hash, tx) = transaction(3, 4);
+        let sealed = builder.push(hash, tx).unwrap();
+        assert_eq!(
+            sealed.transaction_hashes,
+            vec![TxHash::with_last_byte(1), TxHash::with_last_byte(2)]
+        );
+        assert_eq!(sealed.digest, BatchDigest::of(&sealed.batch.transactions));
+        assert_eq!(sealed.batch_ref(), BatchRef { digest: sealed.digest, worker: 1 });
+
+        for byte in 4..6 {
+            let (hash, tx) = transaction(byte, 1);
+            assert_eq!(builder.push(hash, tx), None);
+        }
+        assert!(builder.is_full());
+        assert_eq!(builder.seal().unwrap().batch.len(), 3);
+        assert!(builder.is_empty());
+    }
+
+    #[test]
+    fn oversized_transaction() {
+        let config = BatchConfig { max_batch_bytes: 10, ..Default::default() };
+        let mut builder = BatchBuilder::new(config, 0);
+        let (hash, tx) = transaction(1, 20);
+        assert_eq!(builder.push(hash, tx), None);
+        assert!(builder.is_full());
+        assert_eq!(builder.seal().unwrap().batch.size(), 20);
+    }
+
+    #[test]
+    fn config_serde() {
+        let config: BatchConfig = serde_json::from_str(r#"{"maxBatchDelay":"250ms"}"#).unwrap();
+        assert_eq!(config.max_batch_delay, Duration::from_millis(250));
+        assert_eq!(config.max_transactions, BatchConfig::default().max_transactions);
+    }
+}
@@ -0,0 +1,263 @@
+//! Deduplication of transactions across the workers of one validator.
+//!
+//! A transaction that reaches a validator through multiple paths, e.g. from several peers, would
+//! otherwise be sealed by more than one of its workers. The duplicates are removed when the DAG is
+//! committed, but until then they waste bandwidth. All workers of a validator share a
+//! [`BatchDeduplicator`], a bloom filter of recently batched transaction hashes.
+//!
+//! The filter is best effort: false positives skip a transaction for the current batch, which is
+//! retried from the pool later, and recently forgotten hashes are caught by the commit-time
+//! deduplication.
+
+use alloy_primitives::B256;
+use reth_metrics::{metrics::Counter, Metrics};
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+/// Configuration of the [`BatchDeduplicator`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BatchDedupConfig {
+    /// Number of transactions after which the filter starts to forget the oldest hashes.
+    ///
+    /// Hashes are remembered for at least this many and at most twice this many insertions.
+    pub capacity: usize,
+    /// The targeted false-positive rate while the filter holds `capacity` hashes.
+    pub false_positive_rate: f64,
+}
+
+impl Default for BatchDedupConfig {
+    fn default() -> Self {
+        Self { capacity: 100_000, false_positive_rate: 0.001 }
+    }
+}
+
+/// Metrics of the [`BatchDeduplicator`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.worker.dedup")]
+struct BatchDedupMetrics {
+    /// Number of transactions that were filtered as already batched
+    duplicate_transactions: Counter,
+    /// Number of times the oldest generation of hashes was forgotten
+    rotations: Counter,
+}
+
+/// A bloom filter of the transaction hashes recently batched by any worker of this validator.
+///
+/// Cloning is cheap, all clones share the same filter.
+#[derive(Debug, Clone)]
+pub struct BatchDeduplicator {
+    inner: Arc<DedupInner>,
+}
+
+#[derive(Debug)]
+struct DedupInner {
+    hashes: RecentHashes,
+    metrics: BatchDedupMetrics,
+}
+
+impl BatchDeduplicator {
+    /// Creates a new, empty filter.
+    pub fn new(config: BatchDedupConfig) -> Self {
+        Self {
+            inner: Arc::new(DedupInner {
+                hashes: RecentHashes::new(config.capacity, config.false_positive_rate),
+                metrics: Default::default(),
+            }),
+        }
+    }
+
+    /// Records the hash of a transaction that is about to be batched.
+    ///
+    /// Returns `false` if the transaction was probably batched before and should be skipped.
+    pub fn insert(&self, hash: &B256) -> bool {
+        let inner = &*self.inner;
+        if inner.hashes.contains(hash) {
+            inner.metrics.duplicate_transactions.increment(1);
+            return false
+        }
+        if inner.hashes.insert(hash) {
+            inner.metrics.rotations.increment(1);
+        }
+        true
+    }
+}
+
+/// A bloom filter of recently inserted hashes.
+///
+/// The filter consists of two generations. Hashes are inserted into the current generation, and
+/// once it holds `capacity` hashes the older generation is cleared and becomes the current one.
+#[derive(Debug)]
+pub(super) struct RecentHashes {
+    generations: [BloomFilter; 2],
+    /// Index of the current generation.
+    current: AtomicUsize,
+    /// Hashes inserted into the current generation.
+    inserted: AtomicUsize,
+    capacity: usize,
+    /// Serializes rotations.
+    rotation: Mutex<()>,
+}
+
+impl RecentHashes {
+    /// Creates a new, empty filter.
+    pub(super) fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1);
+        let (bits, hashes) = BloomFilter::dimensions(capacity, false_positive_rate);
+        Self {
+            generations: [BloomFilter::new(bits, hashes), BloomFilter::new(bits, hashes)],
+            current: AtomicUsize::new(0),
+            inserted: AtomicUsize::new(0),
+            capacity,
+            rotation: Mutex::new(()),
+        }
+    }
+
+    /// Returns `true` if the hash was probably inserted into either generation.
+    pub(super) fn contains(&self, hash: &B256) -> bool {
+        self.generations.iter().any(|generation| generation.contains(hash))
+    }
+
+    /// Inserts the hash into the current generation.
+    ///
+    /// Returns `true` if the insertion forgot the oldest generation.
+    pub(super) fn insert(&self, hash: &B256) -> bool {
+        let current = self.current.load(Ordering::Acquire);
+        self.generations[current].insert(hash);
+        self.inserted.fetch_add(1, Ordering::AcqRel) + 1 >= self.capacity && self.rotate(current)
+    }
+
+    /// Clears the older generation and makes it the current one, unless another thread already
+    /// rotated away from `current`.
+    fn rotate(&self, current: usize) -> bool {
+        let _guard = self.rotation.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if self.current.load(Ordering::Acquire) != current {
+            return false
+        }
+
+        let next = 1 - current;
+        self.generations[next].clear();
+        self.inserted.store(0, Ordering::Release);
+        self.current.store(next, Ordering::Release);
+        true
+    }
+}
+
+/// A bloom filter with atomic bits.
+#[derive(Debug)]
+struct BloomFilter {
+    bits: Box<[AtomicU64]>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// Returns the number of bits and hash functions for the given capacity and false-positive
+    /// rate.
+    fn dimensions(capacity: usize, false_positive_rate: f64) -> (usize, u32) {
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-(capacity as f64) * rate.ln() / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let hashes = ((bits as f64 / capacity as f64) * ln2).round().clamp(1.0, 32.0) as u32;
+        (bits, hashes)
+    }
+
+    fn new(bits: usize, hashes: u32) -> Self {
+        let words = bits.div_ceil(64);
+        Self { bits: (0..words).map(|_| AtomicU64::new(0)).collect(), hashes }
+    }
+
+    /// Returns the bit positions of the hash.
+    ///
+    /// Transaction hashes are uniformly distributed, so the positions are derived from the hash
+    /// itself with double hashing.
+    fn positions(&self, hash: &B256) -> impl Iterator<Item = usize> {
+        let len = (self.bits.len() * 64) as u64;
+        let h1 = u64::from_le_bytes(hash[..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(hash[8..16].try_into().unwrap()) | 1;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+
+    fn contains(&self, hash: &B256) -> bool {
+        self.positions(hash)
+            .all(|bit| self.bits[bit / 64].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0)
+    }
+
+    fn insert(&self, hash: &B256) {
+        for bit in self.positions(hash) {
+            self.bits[bit / 64].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+        }
+    }
+
+    fn clear(&self) {
+        for word in &self.bits {
+            word.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    fn hash(i: u64) -> B256 {
+        keccak256(i.to_be_bytes())
+    }
+
+    #[test]
+    fn filter_duplicates() {
+        let dedup = BatchDeduplicator::new(BatchDedupConfig::default());
+        let other_worker = dedup.clone();
+        assert!(dedup.insert(&hash(1)));
+        assert!(other_worker.insert(&hash(2)));
+        assert!(!dedup.insert(&hash(2)));
+        assert!(!other_worker.insert(&hash(1)));
+    }
+
+    #[test]
+    fn false_positive_rate() {
+        let config = BatchDedupConfig { capacity: 10_000, false_positive_rate: 0.01 };
+        let dedup = BatchDeduplicator::new(config);
+        for i in 0..config.capacity as u64 - 1 {
+            dedup.insert(&hash(i));
+        }
+        let false_positives = (1_000_000..1_010_000).filter(|i| !dedup.insert(&hash(*i))).count();
+        // at most twice the configured rate, filling the filter with 10k more hashes
+        assert!(false_positives < 200, "{false_positives}");
+    }
+
+    #[test]
+    fn forget_after_two_generations() {
+        let dedup = BatchDeduplicator::new(BatchDedupConfig { capacity: 10, ..Default::default() });
+        assert!(dedup.insert(&hash(0)));
+        for i in 1..10 {
+            assert!(dedup.insert(&hash(i)));
+        }
+        // first rotation, the hash is still in the older generation
+        assert!(!dedup.insert(&hash(0)));
+        for i in 10..20 {
+            assert!(dedup.insert(&hash(i)));
+        }
+        // second rotation cleared the generation holding the hash
+        assert!(dedup.insert(&hash(0)));
+    }
+
+    #[test]
+    fn concurrent_workers() {
+        let dedup = BatchDeduplicator::new(BatchDedupConfig::default());
+        let inserted = std::thread::scope(|scope| {
+            let workers = (0..4)
+                .map(|_| {
+                    let dedup = dedup.clone();
+                    scope.spawn(move || (0..1000).filter(|i| dedup.insert(&hash(*i))).count())
+                })
+                .collect::<Vec<_>>();
+            workers.into_iter().map(|worker| worker.join().unwrap()).sum::<usize>()
+        });
+        // every hash was batched by one worker, racing inserts may let a few through twice
+        assert!((1000..1010).contains(&inserted), "{inserted}");
+    }
+}
@@ -0,0 +1,247 @@
+//! End-to-end encryption of batches between the workers of the committee.
+//!
+//! The transport between workers is expected to be encrypted, but it may be terminated by
+//! intermediaries such as proxies or load balancers that can read the transactions of a batch
+//! before they are committed. With a [`BatchCipher`], the
+//! [`WorkerNetwork`](crate::worker::WorkerNetwork) encrypts the batches it sends, so only the
+//! validators of the committee can read them.
+//!
+//! The key of an epoch is derived from a secret shared by the validators, salted with the
+//! committee of the epoch, so every epoch has its own key and a new committee stops accepting the
+//! batches of the previous one. Batches are encrypted with AES-256-GCM under a random nonce, and
+//! the ciphertext is bound to the epoch, the worker and the sending authority.
+
+use crate::{
+    committee::Committee,
+    types::{Batch, WorkerId},
+};
+use aes_gcm::{
+    aead::{Aead, Payload},
+    Aes256Gcm, Key, KeyInit, Nonce,
+};
+use alloy_primitives::{hex, keccak256, Bytes, FixedBytes, B256};
+use alloy_rlp::Decodable;
+use hkdf::Hkdf;
+use reth_narwhal_verifier::{AuthorityIndex, Epoch};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{fmt, fs, io, path::PathBuf};
+
+/// The length of the secret shared by the validators in bytes.
+pub const BATCH_ENCRYPTION_SECRET_LEN: usize = 32;
+
+/// Info of the key derivation, which separates the keys of batches from other uses of the secret.
+const KEY_INFO: &[u8] = b"narwhal batch encryption";
+
+/// Configuration of the encryption of batches.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BatchEncryptionConfig {
+    /// Path to the file with the hex encoded secret shared by the validators of the committee,
+    /// batches are sent in plaintext if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret_file: Option<PathBuf>,
+}
+
+impl BatchEncryptionConfig {
+    /// Returns the cipher of the committee, `None` if encryption is disabled.
+    pub fn cipher(&self, committee: &Committee) -> Result<Option<BatchCipher>, EncryptionError> {
+        let Some(path) = &self.secret_file else { return Ok(None) };
+        let contents = fs::read_to_string(path)
+            .map_err(|err| EncryptionError::Read { path: path.clone(), err })?;
+        let secret = hex::decode(contents.trim())
+            .ok()
+            .and_then(|secret| <[u8; BATCH_ENCRYPTION_SECRET_LEN]>::try_from(secret).ok())
+            .ok_or(EncryptionError::InvalidSecret)?;
+        Ok(Some(BatchCipher::new(&secret, committee)))
+    }
+}
+
+/// Errors of the encryption of batches.
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    /// The secret file couldn't be read.
+    #[error("failed to read batch encryption secret {path}: {err}")]
+    Read {
+        /// The path of the file.
+        path: PathBuf,
+        /// The I/O error.
+        #[source]
+        err: io::Error,
+    },
+    /// The secret file doesn't hold a hex encoded secret of [`BATCH_ENCRYPTION_SECRET_LEN`] bytes.
+    #[error("batch encryption secret must be {BATCH_ENCRYPTION_SECRET_LEN} hex encoded bytes")]
+    InvalidSecret,
+    /// The batch was encrypted for another epoch.
+    #[error("batch is encrypted for epoch {epoch}, expected epoch {expected}")]
+    EpochMismatch {
+        /// The epoch of the batch.
+        epoch: Epoch,
+        /// The epoch of the cipher.
+        expected: Epoch,
+    },
+    /// The ciphertext was not produced by the committee's key for the worker and authority.
+    #[error("failed to decrypt batch")]
+    Decrypt,
+    /// The plaintext is not an encoded batch.
+    #[error("failed to decode decrypted batch: {0}")]
+    Decode(#[from] alloy_rlp::Error),
+}
+
+/// A batch encrypted by a [`BatchCipher`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptedBatch {
+    /// The epoch of the key.
+    pub epoch: Epoch,
+    /// The random nonce of the encryption.
+    pub nonce: FixedBytes<12>,
+    /// The encrypted RLP encoding of the batch, followed by the authentication tag.
+    pub ciphertext: Bytes,
+}
+
+/// Encrypts and decrypts the batches of a committee.
+#[derive(Clone)]
+pub struct BatchCipher {
+    epoch: Epoch,
+    cipher: Aes256Gcm,
+}
+
+impl BatchCipher {
+    /// Derives the cipher of a committee from the secret shared by its validators.
+    pub fn new(secret: &[u8; BATCH_ENCRYPTION_SECRET_LEN], committee: &Committee) -> Self {
+        let salt = committee_salt(committee);
+        let mut key = [0; 32];
+        Hkdf::<Sha256>::new(Some(salt.as_slice()), secret)
+            .expand(KEY_INFO, &mut key)
+            .expect("32 bytes is a valid output length");
+        Self { epoch: committee.epoch, cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)) }
+    }
+
+    /// Returns the epoch of the committee.
+    pub const fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// Encrypts a batch sent by the worker of an authority.
+    pub fn encrypt(&self, worker: WorkerId, from: AuthorityIndex, batch: &Batch) -> EncryptedBatch {
+        let nonce = FixedBytes::<12>::from(rand::random::<[u8; 12]>());
+        let aad = associated_data(self.epoch, worker, from);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(nonce.as_slice()),
+                Payload { msg: &alloy_rlp::encode(batch), aad: &aad },
+            )
+            .expect("batches are below the size limit of AES-GCM");
+        EncryptedBatch { epoch: self.epoch, nonce, ciphertext: ciphertext.into() }
+    }
+
+    /// Decrypts a batch received from the worker of an authority.
+    pub fn decrypt(
+        &self,
+        worker: WorkerId,
+        from: AuthorityIndex,
+        batch: &EncryptedBatch,
+    ) -> Result<Batch, EncryptionError> {
+        if batch.epoch != self.epoch {
+            return Err(EncryptionError::EpochMismatch { epoch: batch.epoch, expected: self.epoch })
+        }
+        let aad = associated_data(self.epoch, worker, from);
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(batch.nonce.as_slice()),
+                Payload { msg: &batch.ciphertext, aad: &aad },
+            )
+            .map_err(|_| EncryptionError::Decrypt)?;
+        Ok(Batch::decode(&mut plaintext.as_slice())?)
+    }
+}
+
+impl fmt::Debug for BatchCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchCipher").field("epoch", &self.epoch).finish_non_exhaustive()
+    }
+}
+
+/// Returns the salt of the key derivation, which commits to the epoch and the authorities of the
+/// committee.
+fn committee_salt(committee: &Committee) -> B256 {
+    let mut preimage = committee.epoch.to_be_bytes().to_vec();
+    for authority in &committee.authorities {
+        preimage.extend_from_slice(&(authority.public_key.len() as u64).to_be_bytes());
+        preimage.extend_from_slice(&authority.public_key);
+        preimage.extend_from_slice(&authority.stake.to_be_bytes());
+    }
+    keccak256(preimage)
+}
+
+/// Returns the data authenticated together with a batch.
+fn associated_data(epoch: Epoch, worker: WorkerId, from: AuthorityIndex) -> [u8; 16] {
+    let mut aad = [0; 16];
+    aad[..8].copy_from_slice(&epoch.to_be_bytes());
+    aad[8..12].copy_from_slice(&worker.to_be_bytes());
+    aad[12..].copy_from_slice(&from.to_be_bytes());
+    aad
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev::DevCommittee;
+    use std::io::Write;
+
+    const SECRET: [u8; BATCH_ENCRYPTION_SECRET_LEN] = [7; BATCH_ENCRYPTION_SECRET_LEN];
+
+    fn batch() -> Batch {
+        Batch::new(vec![Bytes::from_static(b"a"), Bytes::from_static(b"bc")])
+    }
+
+    #[test]
+    fn encrypt_batch() {
+        let committee = DevCommittee::new(4).committee().clone();
+        let cipher = BatchCipher::new(&SECRET, &committee);
+        let encrypted = cipher.encrypt(0, 1, &batch());
+        assert!(!encrypted.ciphertext.windows(2).any(|window| window == b"bc"));
+        assert_eq!(cipher.decrypt(0, 1, &encrypted).unwrap(), batch());
+
+        // the ciphertext is bound to the worker and the sender
+        assert!(matches!(cipher.decrypt(1, 1, &encrypted), Err(EncryptionError::Decrypt)));
+        assert!(matches!(cipher.decrypt(0, 2, &encrypted), Err(EncryptionError::Decrypt)));
+
+        // another secret or another committee derive another key
+        let other = BatchCipher::new(&[8; BATCH_ENCRYPTION_SECRET_LEN], &committee);
+        assert!(matches!(other.decrypt(0, 1, &encrypted), Err(EncryptionError::Decrypt)));
+        let mut next = committee.clone();
+        next.authorities.pop();
+        let next = BatchCipher::new(&SECRET, &next);
+        assert!(matches!(next.decrypt(0, 1, &encrypted), Err(EncryptionError::Decrypt)));
+
+        let mut next_epoch = committee;
+        next_epoch.epoch = 1;
+        let next_epoch = BatchCipher::new(&SECRET, &next_epoch);
+        assert!(matches!(
+            next_epoch.decrypt(0, 1, &encrypted),
+            Err(EncryptionError::EpochMismatch { epoch: 0, expected: 1 })
+        ));
+    }
+
+    #[test]
+    fn load_secret() {
+        let committee = DevCommittee::new(4).committee().clone();
+        assert!(BatchEncryptionConfig::default().cipher(&committee).unwrap().is_none());
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "{}", hex::encode(SECRET)).unwrap();
+        let config = BatchEncryptionConfig { secret_file: Some(file.path().to_path_buf()) };
+        let cipher = config.cipher(&committee).unwrap().unwrap();
+        let encrypted = BatchCipher::new(&SECRET, &committee).encrypt(0, 1, &batch());
+        assert_eq!(cipher.decrypt(0, 1, &encrypted).unwrap(), batch());
+
+        let mut short = tempfile::NamedTempFile::new().unwrap();
+        write!(short, "0x0102").unwrap();
+        let config = BatchEncryptionConfig { secret_file: Some(short.path().to_path_buf()) };
+        assert!(matches!(config.cipher(&committee), Err(EncryptionError::InvalidSecret)));
+    }
+}
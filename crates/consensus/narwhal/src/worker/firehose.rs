@@ -0,0 +1,205 @@
+//! Streaming of batches to an external data pipeline.
+//!
+//! Operators that capture the pre-consensus transaction flow, e.g. for analytics or compliance,
+//! can enable a firehose: every batch a worker seals or receives from another authority is
+//! forwarded to a [`FirehoseTransport`]. The transport of a broker like NATS or Kafka is provided
+//! by the node, the crate only ships [`JsonLinesTransport`].
+//!
+//! The firehose never slows down the worker. Records are buffered in a bounded queue and dropped
+//! when the transport can't keep up.
+
+use crate::types::{BatchDigest, WorkerId};
+use alloy_primitives::Bytes;
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_narwhal_verifier::AuthorityIndex;
+use reth_tasks::TaskExecutor;
+use serde::{Deserialize, Serialize};
+use std::{future::Future, io, pin::Pin, sync::Arc};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+use tracing::warn;
+
+/// Configuration of the [`FirehoseSink`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct FirehoseConfig {
+    /// The number of records buffered while the transport is busy.
+    pub buffer: usize,
+}
+
+impl Default for FirehoseConfig {
+    fn default() -> Self {
+        Self { buffer: 1_024 }
+    }
+}
+
+/// How a worker got a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BatchOrigin {
+    /// The worker sealed the batch.
+    Created,
+    /// The batch was received from a worker of another authority.
+    Received {
+        /// The authority of the sending worker.
+        from: AuthorityIndex,
+    },
+}
+
+/// A batch forwarded to the firehose.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchRecord {
+    /// The digest of the batch.
+    pub digest: BatchDigest,
+    /// The worker that created or received the batch.
+    pub worker: WorkerId,
+    /// How the worker got the batch.
+    pub origin: BatchOrigin,
+    /// The encoded transactions of the batch.
+    pub transactions: Vec<Bytes>,
+}
+
+/// Delivers [`BatchRecord`]s to an external endpoint.
+pub trait FirehoseTransport: Send + 'static {
+    /// Sends a record to the endpoint.
+    fn send<'a>(
+        &'a mut self,
+        record: &'a BatchRecord,
+    ) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>>;
+}
+
+/// A transport that writes every record as a line of JSON, e.g. to a file or a TCP socket.
+#[derive(Debug)]
+pub struct JsonLinesTransport<W> {
+    writer: W,
+}
+
+impl<W> JsonLinesTransport<W> {
+    /// Creates a transport writing to the given writer.
+    pub const fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: AsyncWrite + Unpin + Send + 'static> FirehoseTransport for JsonLinesTransport<W> {
+    fn send<'a>(
+        &'a mut self,
+        record: &'a BatchRecord,
+    ) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut line = serde_json::to_vec(record)?;
+            line.push(b'\n');
+            self.writer.write_all(&line).await?;
+            self.writer.flush().await
+        })
+    }
+}
+
+/// Metrics of the [`FirehoseSink`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.firehose")]
+struct FirehoseMetrics {
+    /// Number of records delivered to the transport
+    sent_records: Counter,
+    /// Number of records dropped because the buffer was full
+    dropped_records: Counter,
+    /// Number of records the transport failed to deliver
+    failed_records: Counter,
+}
+
+/// Forwards the batches of the workers to a [`FirehoseTransport`].
+///
+/// Cloning is cheap, all clones share the same queue.
+#[derive(Debug, Clone)]
+pub struct FirehoseSink {
+    records: mpsc::Sender<BatchRecord>,
+    metrics: Arc<FirehoseMetrics>,
+}
+
+impl FirehoseSink {
+    /// Creates a sink and the task that delivers its records to the transport.
+    ///
+    /// The task ends once all clones of the sink are dropped.
+    pub fn new<T: FirehoseTransport>(
+        config: FirehoseConfig,
+        mut transport: T,
+    ) -> (Self, impl Future<Output = ()> + Send) {
+        let (records, mut rx) = mpsc::channel::<BatchRecord>(config.buffer.max(1));
+        let metrics = Arc::new(FirehoseMetrics::default());
+        let sink = Self { records, metrics: Arc::clone(&metrics) };
+
+        let task = async move {
+            while let Some(record) = rx.recv().await {
+                match transport.send(&record).await {
+                    Ok(()) => metrics.sent_records.increment(1),
+                    Err(err) => {
+                        metrics.failed_records.increment(1);
+                        warn!(
+                            target: "consensus::narwhal",
+                            %err,
+                            digest = %record.digest,
+                            "Failed to send batch to firehose"
+                        );
+                    }
+                }
+            }
+        };
+        (sink, task)
+    }
+
+    /// Creates a sink and spawns the task that delivers its records.
+    pub fn spawn<T: FirehoseTransport>(
+        config: FirehoseConfig,
+        transport: T,
+        executor: &TaskExecutor,
+    ) -> Self {
+        let (sink, task) = Self::new(config, transport);
+        executor.spawn(Box::pin(task));
+        sink
+    }
+
+    /// Queues a record for delivery, dropping it if the buffer is full.
+    pub fn forward(&self, record: BatchRecord) {
+        if self.records.try_send(record).is_err() {
+            self.metrics.dropped_records.increment(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forward_as_json_lines() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (writer, mut reader) = tokio::io::duplex(4_096);
+        let (sink, task) =
+            FirehoseSink::new(FirehoseConfig::default(), JsonLinesTransport::new(writer));
+
+        let record = |origin| BatchRecord {
+            digest: BatchDigest::of(&[]),
+            worker: 1,
+            origin,
+            transactions: Vec::new(),
+        };
+        sink.forward(record(BatchOrigin::Created));
+        sink.forward(record(BatchOrigin::Received { from: 2 }));
+        drop(sink);
+        runtime.block_on(task);
+
+        let mut output = String::new();
+        runtime
+            .block_on(tokio::io::AsyncReadExt::read_to_string(&mut reader, &mut output))
+            .unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            serde_json::from_str::<BatchRecord>(lines[1]).unwrap(),
+            record(BatchOrigin::Received { from: 2 })
+        );
+    }
+}
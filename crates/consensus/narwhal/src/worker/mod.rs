@@ -0,0 +1,46 @@
+//! Components of a narwhal worker.
+//!
+//! Workers pull transactions from the transaction pool, seal them into batches and replicate the
+//! batches to the workers of the other authorities. The batches are then referenced by the headers
+//! proposed by their primary.
+
+mod batch_maker;
+mod dedup;
+mod encryption;
+mod firehose;
+mod network;
+mod quota;
+mod rate_limit;
+mod routing;
+mod sequenced;
+mod size_limit;
+mod submission;
+
+#[cfg(feature = "execution")]
+pub use batch_maker::BatchMaker;
+pub use batch_maker::{BatchBuilder, BatchConfig, SealedBatch};
+pub use dedup::{BatchDedupConfig, BatchDeduplicator};
+pub use encryption::{
+    BatchCipher, BatchEncryptionConfig, EncryptedBatch, EncryptionError,
+    BATCH_ENCRYPTION_SECRET_LEN,
+};
+pub use firehose::{
+    BatchOrigin, BatchRecord, FirehoseConfig, FirehoseSink, FirehoseTransport, JsonLinesTransport,
+};
+pub use network::{
+    BatchAcknowledgements, WorkerHandle, WorkerMessage, WorkerNetwork, WorkerNetworkConfig,
+    WorkerNetworkError, WorkerTransport,
+};
+pub use quota::{BatchQuota, BatchQuotaConfig, BatchQuotaExceeded};
+pub use rate_limit::{RateLimitOutcome, SenderRateLimitConfig, SenderRateLimiter};
+pub use routing::{
+    RoutingError, TransactionClass, TransactionRouting, WorkerLane,
+    DEFAULT_LARGE_CALLDATA_THRESHOLD,
+};
+#[cfg(feature = "execution")]
+pub use sequenced::{AlreadySequenced, SequencedTransactionValidator};
+pub use sequenced::{SequencedIndexConfig, SequencedTransactions};
+pub use size_limit::{TransactionSizeLimits, TransactionTooLarge};
+pub use submission::SubmissionConfig;
+#[cfg(feature = "grpc")]
+pub use submission::TransactionSubmissionServer;
@@ -0,0 +1,810 @@
+//! Replication of batches between the workers of the committee.
+//!
+//! A primary may only reference a batch in its header once the batch is stored by a quorum of the
+//! committee, otherwise a committed header could reference a batch no honest validator can
+//! execute. The [`WorkerNetwork`] of a worker broadcasts every sealed batch to the workers with the
+//! same id of all other authorities, which store the batch and acknowledge it.
+//! [`WorkerHandle::broadcast`] resolves once the acknowledging authorities, including the worker's
+//! own, hold a quorum of stake. Workers that miss a batch referenced by a certificate fetch it with
+//! [`WorkerHandle::request_batches`].
+//!
+//! At most `max_pending_batches` broadcasts wait for their quorum at the same time, further
+//! broadcasts wait for a slot, which slows down the batch maker while the committee falls behind.
+//!
+//! Messages are exchanged over a [`WorkerTransport`] provided by the node. In shadow mode, the
+//! network neither broadcasts the worker's batches nor acknowledges the batches of others, and
+//! hands them to the [`ShadowLog`] instead.
+//!
+//! With a [`BatchCipher`], batches are encrypted end-to-end: the network only sends
+//! [`WorkerMessage::EncryptedBatch`] and [`WorkerMessage::EncryptedBatchResponse`], and drops
+//! batches that are sent in plaintext or can't be decrypted.
+
+use crate::{
+    dag_store::DagStore,
+    shadow::ShadowLog,
+    types::{Batch, BatchDigest, WorkerId},
+    worker::{
+        BatchCipher, BatchOrigin, BatchRecord, EncryptedBatch, FirehoseSink, SequencedTransactions,
+    },
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_narwhal_verifier::{AuthorityIndex, Stake, VerifierCommittee};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{mpsc, oneshot, Semaphore};
+use tracing::{debug, error, trace, warn};
+
+/// Configuration of the [`WorkerNetwork`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WorkerNetworkConfig {
+    /// The number of broadcast batches that may wait for a quorum of acknowledgements at the same
+    /// time.
+    pub max_pending_batches: usize,
+    /// The time a worker waits for the response to a batch request.
+    #[serde(with = "humantime_serde")]
+    pub request_timeout: Duration,
+}
+
+impl Default for WorkerNetworkConfig {
+    fn default() -> Self {
+        Self { max_pending_batches: 16, request_timeout: Duration::from_secs(5) }
+    }
+}
+
+/// A message exchanged between the workers of the committee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WorkerMessage {
+    /// A batch sealed by the sending worker, to be stored and acknowledged.
+    Batch {
+        /// The batch.
+        batch: Batch,
+    },
+    /// The sending worker stored the batch.
+    Ack {
+        /// The digest of the batch.
+        digest: BatchDigest,
+    },
+    /// Requests batches the sending worker is missing.
+    BatchRequest {
+        /// Identifies the response.
+        id: u64,
+        /// The digests of the requested batches.
+        digests: Vec<BatchDigest>,
+    },
+    /// The requested batches the sending worker stores.
+    BatchResponse {
+        /// The id of the request.
+        id: u64,
+        /// The batches, batches the worker doesn't store are omitted.
+        batches: Vec<Batch>,
+    },
+    /// A [`WorkerMessage::Batch`] encrypted by a [`BatchCipher`].
+    EncryptedBatch {
+        /// The encrypted batch.
+        batch: EncryptedBatch,
+    },
+    /// A [`WorkerMessage::BatchResponse`] encrypted by a [`BatchCipher`].
+    EncryptedBatchResponse {
+        /// The id of the request.
+        id: u64,
+        /// The encrypted batches.
+        batches: Vec<EncryptedBatch>,
+    },
+}
+
+/// Delivers [`WorkerMessage`]s to the workers of other authorities.
+///
+/// The transport must only deliver messages to the workers with the id of the sending worker, and
+/// must hand the messages it receives to the [`WorkerNetwork`] together with the authority of the
+/// sender. It identifies the worker to its peers with the key of
+/// [`KeyProvider::worker_key`](crate::keys::KeyProvider::worker_key).
+pub trait WorkerTransport: Send + 'static {
+    /// Sends a message to the worker of an authority.
+    ///
+    /// The network waits for the message to be sent, so the transport should queue messages rather
+    /// than wait for their delivery.
+    fn send<'a>(
+        &'a mut self,
+        to: AuthorityIndex,
+        message: &'a WorkerMessage,
+    ) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>>;
+}
+
+/// Errors of the [`WorkerHandle`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WorkerNetworkError {
+    /// The worker network shut down.
+    #[error("worker network shut down")]
+    Shutdown,
+    /// The batch could not be stored, so it was not broadcast.
+    #[error("failed to store batch {digest}")]
+    Store {
+        /// The digest of the batch.
+        digest: BatchDigest,
+    },
+    /// The authority didn't respond to a batch request in time.
+    #[error("batch request to authority {authority} timed out")]
+    RequestTimeout {
+        /// The requested authority.
+        authority: AuthorityIndex,
+    },
+    /// The authority responded with a batch that was not requested.
+    #[error("authority {authority} responded with batch {digest} that was not requested")]
+    UnexpectedBatch {
+        /// The requested authority.
+        authority: AuthorityIndex,
+        /// The digest of the unexpected batch.
+        digest: BatchDigest,
+    },
+}
+
+/// Tracks the acknowledgements of broadcast batches until they reach a quorum.
+#[derive(Debug)]
+pub struct BatchAcknowledgements {
+    /// The stake of each authority, by index.
+    stakes: Vec<Stake>,
+    quorum_threshold: Stake,
+    pending: HashMap<BatchDigest, PendingBatch>,
+}
+
+#[derive(Debug, Default)]
+struct PendingBatch {
+    acknowledged: HashSet<AuthorityIndex>,
+    stake: Stake,
+}
+
+impl BatchAcknowledgements {
+    /// Creates the tracker for the committee.
+    pub fn new<K>(committee: &VerifierCommittee<K>) -> Self {
+        Self {
+            stakes: committee.authorities().iter().map(|authority| authority.stake).collect(),
+            quorum_threshold: committee.quorum_threshold(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of batches waiting for a quorum.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if no batch waits for a quorum.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Starts tracking the acknowledgements of a batch.
+    pub fn track(&mut self, digest: BatchDigest) {
+        self.pending.entry(digest).or_default();
+    }
+
+    /// Records the acknowledgement of a batch by an authority.
+    ///
+    /// Returns `true` if the acknowledgement completed the quorum, after which the batch is no
+    /// longer tracked. Acknowledgements of untracked batches, of unknown authorities and repeated
+    /// acknowledgements are ignored.
+    pub fn acknowledge(&mut self, digest: BatchDigest, authority: AuthorityIndex) -> bool {
+        let Some(&stake) = self.stakes.get(authority as usize) else { return false };
+        let Some(pending) = self.pending.get_mut(&digest) else { return false };
+        if !pending.acknowledged.insert(authority) {
+            return false
+        }
+        pending.stake = pending.stake.saturating_add(stake);
+        if pending.stake < self.quorum_threshold {
+            return false
+        }
+        self.pending.remove(&digest);
+        true
+    }
+}
+
+/// Metrics of the [`WorkerNetwork`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.worker.network")]
+struct WorkerNetworkMetrics {
+    /// Number of batches broadcast by this worker
+    broadcast_batches: Counter,
+    /// Number of broadcast batches that were acknowledged by a quorum
+    acknowledged_batches: Counter,
+    /// Number of batches received from other workers
+    received_batches: Counter,
+    /// Number of batches requested from other workers
+    requested_batches: Counter,
+    /// Number of batches sent in response to requests of other workers
+    served_batches: Counter,
+    /// Number of messages the transport failed to send
+    failed_messages: Counter,
+    /// Number of batch messages dropped because they were not encrypted as configured or
+    /// couldn't be decrypted
+    rejected_messages: Counter,
+}
+
+/// A command of a [`WorkerHandle`].
+#[derive(Debug)]
+enum WorkerCommand {
+    Broadcast {
+        batch: Batch,
+        acknowledged: oneshot::Sender<Result<BatchDigest, WorkerNetworkError>>,
+    },
+    Request {
+        from: AuthorityIndex,
+        digests: Vec<BatchDigest>,
+        response: oneshot::Sender<Result<Vec<Batch>, WorkerNetworkError>>,
+    },
+}
+
+/// A batch request waiting for its response.
+#[derive(Debug)]
+struct PendingRequest {
+    from: AuthorityIndex,
+    digests: HashSet<BatchDigest>,
+    response: oneshot::Sender<Result<Vec<Batch>, WorkerNetworkError>>,
+}
+
+/// A handle to broadcast and request batches through the [`WorkerNetwork`].
+///
+/// Cloning is cheap, all clones share the same network and the same limit of pending batches.
+#[derive(Debug, Clone)]
+pub struct WorkerHandle {
+    commands: mpsc::Sender<WorkerCommand>,
+    pending: Arc<Semaphore>,
+    request_timeout: Duration,
+}
+
+impl WorkerHandle {
+    /// Broadcasts a batch to the workers of all other authorities.
+    ///
+    /// Resolves with the digest of the batch once a quorum of the committee stored it, at which
+    /// point the primary may reference it. Waits while `max_pending_batches` other broadcasts
+    /// wait for their quorum.
+    pub async fn broadcast(&self, batch: Batch) -> Result<BatchDigest, WorkerNetworkError> {
+        let _permit = self.pending.acquire().await.map_err(|_| WorkerNetworkError::Shutdown)?;
+        let (acknowledged, rx) = oneshot::channel();
+        self.commands
+            .send(WorkerCommand::Broadcast { batch, acknowledged })
+            .await
+            .map_err(|_| WorkerNetworkError::Shutdown)?;
+        rx.await.map_err(|_| WorkerNetworkError::Shutdown)?
+    }
+
+    /// Requests batches from the worker of an authority.
+    ///
+    /// Returns the requested batches the authority stores, batches it doesn't store are omitted.
+    pub async fn request_batches(
+        &self,
+        from: AuthorityIndex,
+        digests: Vec<BatchDigest>,
+    ) -> Result<Vec<Batch>, WorkerNetworkError> {
+        let (response, rx) = oneshot::channel();
+        self.commands
+            .send(WorkerCommand::Request { from, digests, response })
+            .await
+            .map_err(|_| WorkerNetworkError::Shutdown)?;
+        match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(response) => response.map_err(|_| WorkerNetworkError::Shutdown)?,
+            Err(_) => Err(WorkerNetworkError::RequestTimeout { authority: from }),
+        }
+    }
+}
+
+/// The task that replicates the batches of a worker to the workers of the other authorities.
+///
+/// Batches are stored in the [`DagStore`], which serves the batch requests of other workers.
+#[derive(Debug)]
+pub struct WorkerNetwork<T> {
+    authority: AuthorityIndex,
+    worker: WorkerId,
+    /// The other authorities of the committee.
+    peers: Vec<AuthorityIndex>,
+    transport: T,
+    store: Arc<dyn DagStore>,
+    acknowledgements: BatchAcknowledgements,
+    /// The broadcasts waiting for their quorum.
+    broadcasts: HashMap<BatchDigest, oneshot::Sender<Result<BatchDigest, WorkerNetworkError>>>,
+    requests: HashMap<u64, PendingRequest>,
+    next_request: u64,
+    commands: mpsc::Receiver<WorkerCommand>,
+    inbound: mpsc::Receiver<(AuthorityIndex, WorkerMessage)>,
+    firehose: Option<FirehoseSink>,
+    shadow: Option<ShadowLog>,
+    encryption: Option<BatchCipher>,
+    sequenced: Option<SequencedTransactions>,
+    metrics: WorkerNetworkMetrics,
+}
+
+impl<T: WorkerTransport> WorkerNetwork<T> {
+    /// Creates the network of a worker of `authority`, and the handle to use it.
+    ///
+    /// The messages of other workers are received from `inbound`.
+    pub fn new<K>(
+        committee: &VerifierCommittee<K>,
+        authority: AuthorityIndex,
+        worker: WorkerId,
+        config: WorkerNetworkConfig,
+        transport: T,
+        store: Arc<dyn DagStore>,
+        inbound: mpsc::Receiver<(AuthorityIndex, WorkerMessage)>,
+    ) -> (Self, WorkerHandle) {
+        let max_pending_batches = config.max_pending_batches.max(1);
+        let (commands, commands_rx) = mpsc::channel(max_pending_batches);
+        let peers = (0..committee.authorities().len() as AuthorityIndex)
+            .filter(|peer| *peer != authority)
+            .collect();
+        let network = Self {
+            authority,
+            worker,
+            peers,
+            transport,
+            store,
+            acknowledgements: BatchAcknowledgements::new(committee),
+            broadcasts: HashMap::new(),
+            requests: HashMap::new(),
+            next_request: 0,
+            commands: commands_rx,
+            inbound,
+            firehose: None,
+            shadow: None,
+            encryption: None,
+            sequenced: None,
+            metrics: WorkerNetworkMetrics::default(),
+        };
+        let handle = WorkerHandle {
+            commands,
+            pending: Arc::new(Semaphore::new(max_pending_batches)),
+            request_timeout: config.request_timeout,
+        };
+        (network, handle)
+    }
+
+    /// Forwards the batches received from other workers to the firehose.
+    pub fn with_firehose(mut self, firehose: FirehoseSink) -> Self {
+        self.firehose = Some(firehose);
+        self
+    }
+
+    /// Runs the network in shadow mode.
+    ///
+    /// Broadcasts resolve as soon as the batch is stored, without sending it, and received batches
+    /// are stored without acknowledging them.
+    pub fn with_shadow_log(mut self, shadow: ShadowLog) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// Encrypts the batches sent to other workers, and only accepts encrypted batches.
+    ///
+    /// All validators of the committee must enable encryption together.
+    pub fn with_encryption(mut self, cipher: BatchCipher) -> Self {
+        self.encryption = Some(cipher);
+        self
+    }
+
+    /// Indexes the transactions of the batches received from other workers as sequenced.
+    pub fn with_sequenced_index(mut self, sequenced: SequencedTransactions) -> Self {
+        self.sequenced = Some(sequenced);
+        self
+    }
+
+    /// Runs the network until all handles are dropped or the inbound channel is closed.
+    pub async fn run(mut self) {
+        debug!(target: "consensus::narwhal", worker = self.worker, "Worker network started");
+        loop {
+            tokio::select! {
+                command = self.commands.recv() => {
+                    let Some(command) = command else { return };
+                    self.on_command(command).await;
+                }
+                message = self.inbound.recv() => {
+                    let Some((from, message)) = message else { return };
+                    self.on_message(from, message).await;
+                }
+            }
+        }
+    }
+
+    async fn on_command(&mut self, command: WorkerCommand) {
+        match command {
+            WorkerCommand::Broadcast { batch, acknowledged } => {
+                let digest = batch.digest();
+                if let Err(err) = self.store.write_batch(digest, &batch) {
+                    // the batch couldn't be served to workers that miss it
+                    error!(target: "consensus::narwhal", %err, %digest, "Failed to store batch");
+                    let _ = acknowledged.send(Err(WorkerNetworkError::Store { digest }));
+                    return
+                }
+                if let Some(shadow) = &self.shadow {
+                    shadow.batch(self.worker, digest);
+                    let _ = acknowledged.send(Ok(digest));
+                    return
+                }
+                self.metrics.broadcast_batches.increment(1);
+                self.acknowledgements.track(digest);
+                if self.acknowledgements.acknowledge(digest, self.authority) {
+                    self.metrics.acknowledged_batches.increment(1);
+                    let _ = acknowledged.send(Ok(digest));
+                } else {
+                    self.broadcasts.insert(digest, acknowledged);
+                }
+
+                let message = match &self.encryption {
+                    Some(cipher) => WorkerMessage::EncryptedBatch {
+                        batch: cipher.encrypt(self.worker, self.authority, &batch),
+                    },
+                    None => WorkerMessage::Batch { batch },
+                };
+                for peer in self.peers.clone() {
+                    self.send(peer, &message).await;
+                }
+            }
+            WorkerCommand::Request { from, digests, response } => {
+                // forget the requests whose requester gave up
+                self.requests.retain(|_, request| !request.response.is_closed());
+                let id = self.next_request;
+                self.next_request += 1;
+                self.metrics.requested_batches.increment(digests.len() as u64);
+
+                let message = WorkerMessage::BatchRequest { id, digests: digests.clone() };
+                let digests = digests.into_iter().collect();
+                self.requests.insert(id, PendingRequest { from, digests, response });
+                self.send(from, &message).await;
+            }
+        }
+    }
+
+    async fn on_message(&mut self, from: AuthorityIndex, message: WorkerMessage) {
+        match message {
+            WorkerMessage::Batch { batch } => {
+                if self.encryption.is_some() {
+                    self.reject(from, "plaintext batch");
+                    return
+                }
+                self.on_batch(from, batch).await;
+            }
+            WorkerMessage::EncryptedBatch { batch } => {
+                if let Some(mut batches) = self.decrypt(from, &[batch]) {
+                    self.on_batch(from, batches.remove(0)).await;
+                }
+            }
+            WorkerMessage::Ack { digest } => {
+                if self.acknowledgements.acknowledge(digest, from) {
+                    self.metrics.acknowledged_batches.increment(1);
+                    if let Some(acknowledged) = self.broadcasts.remove(&digest) {
+                        let _ = acknowledged.send(Ok(digest));
+                    }
+                }
+            }
+            WorkerMessage::BatchRequest { id, digests } => {
+                let mut batches = Vec::new();
+                for digest in digests {
+                    match self.store.batch(digest) {
+                        Ok(Some(batch)) => batches.push(batch),
+                        Ok(None) => {}
+                        Err(err) => error!(
+                            target: "consensus::narwhal",
+                            %err,
+                            %digest,
+                            "Failed to read batch"
+                        ),
+                    }
+                }
+                self.metrics.served_batches.increment(batches.len() as u64);
+                let message = match &self.encryption {
+                    Some(cipher) => WorkerMessage::EncryptedBatchResponse {
+                        id,
+                        batches: batches
+                            .iter()
+                            .map(|batch| cipher.encrypt(self.worker, self.authority, batch))
+                            .collect(),
+                    },
+                    None => WorkerMessage::BatchResponse { id, batches },
+                };
+                self.send(from, &message).await;
+            }
+            WorkerMessage::BatchResponse { id, batches } => {
+                if self.encryption.is_some() {
+                    self.reject(from, "plaintext batch response");
+                    return
+                }
+                self.on_batch_response(from, id, batches);
+            }
+            WorkerMessage::EncryptedBatchResponse { id, batches } => {
+                if let Some(batches) = self.decrypt(from, &batches) {
+                    self.on_batch_response(from, id, batches);
+                }
+            }
+        }
+    }
+
+    /// Stores and acknowledges a batch of another worker.
+    async fn on_batch(&mut self, from: AuthorityIndex, batch: Batch) {
+        let digest = batch.digest();
+        trace!(target: "consensus::narwhal", %digest, from, "Received batch");
+        if let Err(err) = self.store.write_batch(digest, &batch) {
+            // unacknowledged, so the batch doesn't count towards the quorum
+            error!(target: "consensus::narwhal", %err, %digest, "Failed to store batch");
+            return
+        }
+        self.metrics.received_batches.increment(1);
+        if let Some(sequenced) = &self.sequenced {
+            sequenced.insert_batch(&batch);
+        }
+        if let Some(firehose) = &self.firehose {
+            firehose.forward(BatchRecord {
+                digest,
+                worker: self.worker,
+                origin: BatchOrigin::Received { from },
+                transactions: batch.transactions,
+            });
+        }
+        match &self.shadow {
+            Some(shadow) => shadow.ack(self.worker, digest, from),
+            None => self.send(from, &WorkerMessage::Ack { digest }).await,
+        }
+    }
+
+    /// Resolves the request of a response.
+    fn on_batch_response(&mut self, from: AuthorityIndex, id: u64, batches: Vec<Batch>) {
+        let Some(request) = self.requests.remove(&id) else {
+            debug!(target: "consensus::narwhal", id, from, "Ignoring unexpected response");
+            return
+        };
+        if request.from != from {
+            debug!(target: "consensus::narwhal", id, from, "Ignoring unexpected response");
+            self.requests.insert(id, request);
+            return
+        }
+        let result = self.on_response(from, &request.digests, batches);
+        let _ = request.response.send(result);
+    }
+
+    /// Decrypts the batches of a message, `None` if encryption is disabled or any batch can't be
+    /// decrypted.
+    fn decrypt(&self, from: AuthorityIndex, batches: &[EncryptedBatch]) -> Option<Vec<Batch>> {
+        let Some(cipher) = &self.encryption else {
+            self.reject(from, "encrypted batch");
+            return None
+        };
+        let batches = batches
+            .iter()
+            .map(|batch| cipher.decrypt(self.worker, from, batch))
+            .collect::<Result<Vec<_>, _>>();
+        match batches {
+            Ok(batches) => Some(batches),
+            Err(err) => {
+                self.metrics.rejected_messages.increment(1);
+                warn!(target: "consensus::narwhal", %err, from, "Failed to decrypt batch");
+                None
+            }
+        }
+    }
+
+    /// Drops a batch message that doesn't match the encryption of the network.
+    fn reject(&self, from: AuthorityIndex, kind: &str) {
+        self.metrics.rejected_messages.increment(1);
+        warn!(target: "consensus::narwhal", from, kind, "Dropping unexpected batch message");
+    }
+
+    /// Checks and stores the batches of a response.
+    fn on_response(
+        &self,
+        from: AuthorityIndex,
+        requested: &HashSet<BatchDigest>,
+        batches: Vec<Batch>,
+    ) -> Result<Vec<Batch>, WorkerNetworkError> {
+        for batch in &batches {
+            let digest = batch.digest();
+            if !requested.contains(&digest) {
+                return Err(WorkerNetworkError::UnexpectedBatch { authority: from, digest })
+            }
+            if let Err(err) = self.store.write_batch(digest, batch) {
+                error!(target: "consensus::narwhal", %err, %digest, "Failed to store batch");
+            }
+            if let Some(sequenced) = &self.sequenced {
+                sequenced.insert_batch(batch);
+            }
+        }
+        Ok(batches)
+    }
+
+    async fn send(&mut self, to: AuthorityIndex, message: &WorkerMessage) {
+        if let Err(err) = self.transport.send(to, message).await {
+            self.metrics.failed_messages.increment(1);
+            warn!(target: "consensus::narwhal", %err, to, "Failed to send worker message");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dag_store::MemoryDagStore,
+        dev::{DevCommittee, LocalTransport},
+    };
+    use alloy_primitives::Bytes;
+    use reth_narwhal_verifier::VerifierAuthority;
+
+    fn committee(stakes: &[Stake]) -> VerifierCommittee<()> {
+        VerifierCommittee::new(
+            1,
+            stakes
+                .iter()
+                .map(|stake| VerifierAuthority { public_key: (), stake: *stake })
+                .collect(),
+        )
+    }
+
+    fn batch(byte: u8) -> Batch {
+        Batch::new(vec![Bytes::from(vec![byte])])
+    }
+
+    /// Spawns the networks of the given authorities of the committee.
+    fn spawn_networks(
+        committee: &VerifierCommittee<()>,
+        online: &[AuthorityIndex],
+    ) -> Vec<(WorkerHandle, Arc<MemoryDagStore>)> {
+        online
+            .iter()
+            .zip(LocalTransport::mesh(online))
+            .map(|(authority, (transport, inbound))| {
+                let store = Arc::new(MemoryDagStore::default());
+                let (network, handle) = WorkerNetwork::new(
+                    committee,
+                    *authority,
+                    0,
+                    WorkerNetworkConfig {
+                        request_timeout: Duration::from_millis(100),
+                        ..Default::default()
+                    },
+                    transport,
+                    store.clone(),
+                    inbound,
+                );
+                tokio::spawn(network.run());
+                (handle, store)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn acknowledge_quorum() {
+        let mut acknowledgements = BatchAcknowledgements::new(&committee(&[1, 1, 1, 1]));
+        let digest = batch(1).digest();
+        assert!(!acknowledgements.acknowledge(digest, 0));
+
+        acknowledgements.track(digest);
+        assert!(!acknowledgements.acknowledge(digest, 0));
+        // repeated and unknown authorities don't count
+        assert!(!acknowledgements.acknowledge(digest, 0));
+        assert!(!acknowledgements.acknowledge(digest, 7));
+        assert!(!acknowledgements.acknowledge(digest, 2));
+        assert_eq!(acknowledgements.len(), 1);
+        assert!(acknowledgements.acknowledge(digest, 3));
+        assert!(acknowledgements.is_empty());
+        assert!(!acknowledgements.acknowledge(digest, 1));
+    }
+
+    #[test]
+    fn acknowledge_by_stake() {
+        let mut acknowledgements = BatchAcknowledgements::new(&committee(&[5, 1, 1, 1]));
+        let digest = batch(1).digest();
+        acknowledgements.track(digest);
+        assert!(!acknowledgements.acknowledge(digest, 1));
+        assert!(acknowledgements.acknowledge(digest, 0));
+    }
+
+    #[tokio::test]
+    async fn replicate_batches() {
+        let committee = committee(&[1, 1, 1, 1]);
+        // one authority is offline, the other three form a quorum
+        let networks = spawn_networks(&committee, &[0, 1, 2]);
+        let (handle, _) = &networks[0];
+
+        let digest = handle.broadcast(batch(1)).await.unwrap();
+        assert_eq!(digest, batch(1).digest());
+        for (_, store) in &networks {
+            assert_eq!(store.batch(digest).unwrap(), Some(batch(1)));
+        }
+
+        let (requester, _) = &networks[2];
+        let missing = batch(2).digest();
+        assert_eq!(requester.request_batches(0, vec![digest, missing]).await, Ok(vec![batch(1)]));
+        assert_eq!(
+            requester.request_batches(3, vec![digest]).await,
+            Err(WorkerNetworkError::RequestTimeout { authority: 3 })
+        );
+    }
+
+    #[tokio::test]
+    async fn shadow_mode() {
+        let committee = committee(&[1, 1, 1, 1]);
+        let mut mesh = LocalTransport::mesh(&[0, 1]);
+        let (mut peer, mut peer_inbound) = mesh.pop().unwrap();
+        let (transport, inbound) = mesh.pop().unwrap();
+        let store = Arc::new(MemoryDagStore::default());
+        let (network, handle) = WorkerNetwork::new(
+            &committee,
+            0,
+            0,
+            WorkerNetworkConfig {
+                request_timeout: Duration::from_millis(100),
+                ..Default::default()
+            },
+            transport,
+            store.clone(),
+            inbound,
+        );
+        tokio::spawn(network.with_shadow_log(ShadowLog::default()).run());
+
+        // the own batch is stored but not sent
+        assert_eq!(handle.broadcast(batch(1)).await, Ok(batch(1).digest()));
+        assert_eq!(store.batch(batch(1).digest()).unwrap(), Some(batch(1)));
+
+        // the batch of a peer is stored but not acknowledged
+        peer.send(0, &WorkerMessage::Batch { batch: batch(2) }).await.unwrap();
+        let digest = batch(2).digest();
+        assert_eq!(
+            handle.request_batches(1, vec![digest]).await,
+            Err(WorkerNetworkError::RequestTimeout { authority: 1 })
+        );
+        assert_eq!(store.batch(digest).unwrap(), Some(batch(2)));
+        assert!(matches!(peer_inbound.try_recv(), Ok((0, WorkerMessage::BatchRequest { .. }))));
+        assert!(peer_inbound.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn encrypted_batches() {
+        let committee = committee(&[2, 2, 1]);
+        let cipher = BatchCipher::new(&[7; 32], DevCommittee::new(3).committee());
+        let mut mesh = LocalTransport::mesh(&[0, 1, 2]);
+        let (mut plaintext_peer, _) = mesh.pop().unwrap();
+        let mut networks = Vec::new();
+        for (authority, (transport, inbound)) in mesh.into_iter().enumerate() {
+            let store = Arc::new(MemoryDagStore::default());
+            let (network, handle) = WorkerNetwork::new(
+                &committee,
+                authority as AuthorityIndex,
+                0,
+                WorkerNetworkConfig::default(),
+                transport,
+                store.clone(),
+                inbound,
+            );
+            tokio::spawn(network.with_encryption(cipher.clone()).run());
+            networks.push((handle, store));
+        }
+
+        // the two encrypting authorities form a quorum
+        let (handle, _) = &networks[0];
+        let broadcast = tokio::time::timeout(Duration::from_secs(1), handle.broadcast(batch(1)));
+        let digest = broadcast.await.unwrap().unwrap();
+        let (requester, store) = &networks[1];
+        assert_eq!(store.batch(digest).unwrap(), Some(batch(1)));
+        assert_eq!(requester.request_batches(0, vec![digest]).await, Ok(vec![batch(1)]));
+
+        // plaintext batches are dropped
+        plaintext_peer.send(1, &WorkerMessage::Batch { batch: batch(2) }).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(store.batch(batch(2).digest()).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn wait_for_quorum() {
+        let committee = committee(&[1, 1, 1, 1]);
+        let networks = spawn_networks(&committee, &[0, 1]);
+        let (handle, _) = &networks[0];
+
+        let broadcast =
+            tokio::time::timeout(Duration::from_millis(100), handle.broadcast(batch(1)));
+        assert!(broadcast.await.is_err());
+    }
+}
@@ -0,0 +1,87 @@
+//! Batch quotas proportional to stake.
+//!
+//! Without quotas, a validator with little stake can fill the DAG with its batches by running
+//! more or faster workers, taking bandwidth and block space from the rest of the committee. A
+//! chain can optionally cap the number of batches a header may reference per round at the
+//! validator's share of a committee-wide budget. Primaries propose at most their quota, and refuse
+//! to vote for headers of other authorities that exceed theirs.
+
+use reth_narwhal_verifier::Stake;
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`BatchQuota`], part of the chain specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchQuotaConfig {
+    /// The number of batches the whole committee may reference per round.
+    pub batches_per_round: u64,
+}
+
+/// Error returned if a header references more batches than the quota of its author.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("header references {batches} batches, the quota of its author is {quota}")]
+pub struct BatchQuotaExceeded {
+    /// The number of batches referenced by the header.
+    pub batches: u64,
+    /// The quota of the author.
+    pub quota: u64,
+}
+
+/// Computes the number of batches an authority may reference per round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchQuota {
+    config: BatchQuotaConfig,
+}
+
+impl BatchQuota {
+    /// Creates the quota for the given configuration.
+    pub const fn new(config: BatchQuotaConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns the number of batches an authority with `stake` may reference per round.
+    ///
+    /// The quota is the authority's share of the budget, rounded up, and at least one batch, so
+    /// that every authority can make progress.
+    pub fn quota(&self, stake: Stake, total_stake: Stake) -> u64 {
+        if total_stake == 0 {
+            return 1
+        }
+        let share =
+            (self.config.batches_per_round as u128 * stake as u128).div_ceil(total_stake as u128);
+        share.clamp(1, u64::MAX as u128) as u64
+    }
+
+    /// Checks that a header of an authority with `stake` referencing `batches` batches is within
+    /// the authority's quota.
+    pub fn check(
+        &self,
+        stake: Stake,
+        total_stake: Stake,
+        batches: u64,
+    ) -> Result<(), BatchQuotaExceeded> {
+        let quota = self.quota(stake, total_stake);
+        if batches > quota {
+            return Err(BatchQuotaExceeded { batches, quota })
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proportional_quotas() {
+        let quota = BatchQuota::new(BatchQuotaConfig { batches_per_round: 100 });
+        assert_eq!(quota.quota(10, 100), 10);
+        assert_eq!(quota.quota(1, 3), 34);
+        assert_eq!(quota.quota(0, 100), 1);
+        assert_eq!(quota.quota(1, 1_000), 1);
+        assert_eq!(quota.quota(100, 100), 100);
+
+        assert_eq!(quota.check(10, 100, 10), Ok(()));
+        assert_eq!(quota.check(10, 100, 11), Err(BatchQuotaExceeded { batches: 11, quota: 10 }));
+    }
+}
@@ -0,0 +1,211 @@
+//! Per-sender rate limiting of the transactions a worker seals into batches.
+//!
+//! Sequencing bandwidth is not priced on a fee-insensitive chain, so without a cap a single sender
+//! could fill every batch of a worker. The [`SenderRateLimiter`] bounds the number of transactions
+//! of a sender per batch and per round with a token bucket that allows short bursts.
+
+use alloy_primitives::Address;
+use reth_metrics::{metrics::Counter, Metrics};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Configuration of the [`SenderRateLimiter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SenderRateLimitConfig {
+    /// Maximum number of transactions of a single sender in one batch.
+    pub max_transactions_per_batch: u64,
+    /// Number of transactions of a single sender that can be sealed per round on average.
+    pub max_transactions_per_round: u64,
+    /// Additional transactions a sender that was idle can send in a single round.
+    pub burst: u64,
+}
+
+impl Default for SenderRateLimitConfig {
+    fn default() -> Self {
+        Self { max_transactions_per_batch: 16, max_transactions_per_round: 64, burst: 64 }
+    }
+}
+
+/// The outcome of [`SenderRateLimiter::check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitOutcome {
+    /// The transaction can be sealed into the current batch.
+    Accepted,
+    /// The sender reached the per batch cap, the transaction should be retried for the next
+    /// batch.
+    Deferred,
+    /// The sender exhausted its budget for the current round, the transaction should be left in
+    /// the pool until a later round.
+    Rejected,
+}
+
+/// Metrics of the [`SenderRateLimiter`].
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.worker.rate_limit")]
+struct SenderRateLimitMetrics {
+    /// Number of transactions deferred to a later batch
+    deferred_transactions: Counter,
+    /// Number of transactions rejected for the current round
+    rejected_transactions: Counter,
+}
+
+/// Token bucket of a single sender.
+#[derive(Debug, Clone, Copy)]
+struct SenderBudget {
+    /// Remaining transactions in the bucket.
+    tokens: u64,
+    /// The round the bucket was last refilled in.
+    refilled_at: u64,
+    /// Transactions sealed into the current batch.
+    in_batch: u64,
+}
+
+/// Limits the number of transactions of a single sender a worker seals into batches.
+#[derive(Debug)]
+pub struct SenderRateLimiter {
+    config: SenderRateLimitConfig,
+    /// The current round of the primary.
+    round: u64,
+    /// Budgets of all senders that were seen since their bucket was last full.
+    senders: HashMap<Address, SenderBudget>,
+    metrics: SenderRateLimitMetrics,
+}
+
+impl SenderRateLimiter {
+    /// Creates a new rate limiter with the given configuration.
+    pub fn new(config: SenderRateLimitConfig) -> Self {
+        Self { config, round: 0, senders: HashMap::new(), metrics: Default::default() }
+    }
+
+    /// Returns the configuration of the rate limiter.
+    pub const fn config(&self) -> &SenderRateLimitConfig {
+        &self.config
+    }
+
+    /// Maximum number of tokens of a sender's bucket.
+    const fn capacity(&self) -> u64 {
+        self.config.max_transactions_per_round.saturating_add(self.config.burst)
+    }
+
+    /// Checks whether the next transaction of `sender` can be sealed into the current batch and
+    /// consumes budget if it can.
+    pub fn check(&mut self, sender: Address) -> RateLimitOutcome {
+        let capacity = self.capacity();
+        let per_round = self.config.max_transactions_per_round;
+        let round = self.round;
+        let budget = self.senders.entry(sender).or_insert(SenderBudget {
+            tokens: capacity,
+            refilled_at: round,
+            in_batch: 0,
+        });
+
+        // lazily refill the bucket for the rounds that passed since it was last refilled
+        let elapsed = round.saturating_sub(budget.refilled_at);
+        budget.tokens =
+            budget.tokens.saturating_add(elapsed.saturating_mul(per_round)).min(capacity);
+        budget.refilled_at = round;
+
+        if budget.in_batch >= self.config.max_transactions_per_batch {
+            self.metrics.deferred_transactions.increment(1);
+            return RateLimitOutcome::Deferred
+        }
+        if budget.tokens == 0 {
+            self.metrics.rejected_transactions.increment(1);
+            return RateLimitOutcome::Rejected
+        }
+
+        budget.tokens -= 1;
+        budget.in_batch += 1;
+        RateLimitOutcome::Accepted
+    }
+
+    /// Resets the per batch counters, must be called whenever the worker sealed a batch.
+    pub fn on_batch_sealed(&mut self) {
+        for budget in self.senders.values_mut() {
+            budget.in_batch = 0;
+        }
+    }
+
+    /// Advances the rate limiter to the given round of the primary.
+    ///
+    /// This also forgets all senders whose bucket has been refilled completely, bounding the memory
+    /// used by the rate limiter to the senders that were recently active.
+    pub fn on_new_round(&mut self, round: u64) {
+        if round <= self.round {
+            return
+        }
+        self.round = round;
+
+        let capacity = self.capacity();
+        let per_round = self.config.max_transactions_per_round;
+        self.senders.retain(|_, budget| {
+            let elapsed = round - budget.refilled_at;
+            budget.in_batch > 0 ||
+                budget.tokens.saturating_add(elapsed.saturating_mul(per_round)) < capacity
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(per_batch: u64, per_round: u64, burst: u64) -> SenderRateLimiter {
+        SenderRateLimiter::new(SenderRateLimitConfig {
+            max_transactions_per_batch: per_batch,
+            max_transactions_per_round: per_round,
+            burst,
+        })
+    }
+
+    #[test]
+    fn defers_above_batch_cap() {
+        let mut limiter = limiter(2, 10, 0);
+        let sender = Address::with_last_byte(1);
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Accepted);
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Accepted);
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Deferred);
+
+        // other senders are not affected
+        assert_eq!(limiter.check(Address::with_last_byte(2)), RateLimitOutcome::Accepted);
+
+        limiter.on_batch_sealed();
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Accepted);
+    }
+
+    #[test]
+    fn rejects_above_round_budget() {
+        let mut limiter = limiter(10, 2, 1);
+        let sender = Address::with_last_byte(1);
+
+        // idle senders can burst
+        for _ in 0..3 {
+            assert_eq!(limiter.check(sender), RateLimitOutcome::Accepted);
+        }
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Rejected);
+
+        // the bucket is refilled per round
+        limiter.on_batch_sealed();
+        limiter.on_new_round(1);
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Accepted);
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Accepted);
+        assert_eq!(limiter.check(sender), RateLimitOutcome::Rejected);
+    }
+
+    #[test]
+    fn forgets_idle_senders() {
+        let mut limiter = limiter(10, 2, 2);
+        let sender = Address::with_last_byte(1);
+        for _ in 0..4 {
+            assert_eq!(limiter.check(sender), RateLimitOutcome::Accepted);
+        }
+        limiter.on_batch_sealed();
+
+        limiter.on_new_round(1);
+        assert_eq!(limiter.senders.len(), 1);
+
+        limiter.on_new_round(2);
+        assert!(limiter.senders.is_empty());
+    }
+}
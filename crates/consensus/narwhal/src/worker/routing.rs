@@ -0,0 +1,283 @@
+//! Routing of transaction classes to dedicated workers.
+//!
+//! By default every worker of a validator batches every pending transaction, and the
+//! [`BatchDeduplicator`](crate::worker::BatchDeduplicator) keeps them from batching one twice.
+//! Transactions with different costs are better batched separately, e.g. blob transactions in
+//! smaller batches that don't hold back regular transfers. A [`TransactionRouting`] assigns
+//! [`TransactionClass`]es to lanes of dedicated workers with their own [`BatchConfig`].
+//!
+//! Routing only depends on the transaction, so a transaction is always routed to the same worker:
+//! the worker of its class' lane that is selected by its hash. Classes without a lane are batched
+//! by the workers that aren't assigned to any lane.
+
+use crate::{types::WorkerId, worker::BatchConfig};
+use alloy_primitives::TxHash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// The default calldata size from which a transaction is [`TransactionClass::LargeCalldata`].
+pub const DEFAULT_LARGE_CALLDATA_THRESHOLD: usize = 32 * 1024;
+
+/// A class of transactions that can be routed to dedicated workers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionClass {
+    /// Transactions of no other class.
+    Regular,
+    /// EIP-4844 transactions with blobs.
+    Blob,
+    /// Transactions with at least `large_calldata_threshold` bytes of calldata.
+    LargeCalldata,
+    /// Transactions that pay at least `priority_fee_threshold` as priority fee.
+    Priority,
+}
+
+/// Workers dedicated to a class of transactions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerLane {
+    /// The class of the transactions of the lane.
+    pub class: TransactionClass,
+    /// The workers of the lane.
+    pub workers: Vec<WorkerId>,
+    /// How the workers of the lane seal batches, the node's batch configuration if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchConfig>,
+}
+
+/// Errors of an invalid [`TransactionRouting`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RoutingError {
+    /// A class has more than one lane.
+    #[error("transaction class {class:?} has more than one lane")]
+    DuplicateLane {
+        /// The class.
+        class: TransactionClass,
+    },
+    /// A lane has no workers.
+    #[error("lane of transaction class {class:?} has no workers")]
+    EmptyLane {
+        /// The class of the lane.
+        class: TransactionClass,
+    },
+    /// A worker is assigned to more than one lane.
+    #[error("worker {worker} is assigned to more than one lane")]
+    DuplicateWorker {
+        /// The worker.
+        worker: WorkerId,
+    },
+    /// A lane references a worker the node doesn't run.
+    #[error("worker {worker} is not one of the {workers} workers of the node")]
+    UnknownWorker {
+        /// The worker.
+        worker: WorkerId,
+        /// The number of workers of the node.
+        workers: usize,
+    },
+    /// A class has no lane, but every worker is assigned to one, so its transactions would never
+    /// be batched.
+    #[error("transaction class {class:?} has no lane and no unassigned worker")]
+    UnroutedClass {
+        /// The class.
+        class: TransactionClass,
+    },
+}
+
+/// Assigns classes of transactions to lanes of dedicated workers.
+///
+/// The default routing has no lanes, every worker batches every transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransactionRouting {
+    /// The calldata size in bytes from which a transaction is
+    /// [`TransactionClass::LargeCalldata`].
+    pub large_calldata_threshold: usize,
+    /// The priority fee per gas from which a transaction is [`TransactionClass::Priority`],
+    /// disabled if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority_fee_threshold: Option<u128>,
+    /// The lanes, at most one per class.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lanes: Vec<WorkerLane>,
+}
+
+impl Default for TransactionRouting {
+    fn default() -> Self {
+        Self {
+            large_calldata_threshold: DEFAULT_LARGE_CALLDATA_THRESHOLD,
+            priority_fee_threshold: None,
+            lanes: Vec::new(),
+        }
+    }
+}
+
+impl TransactionRouting {
+    /// Checks that every transaction is routed to exactly one of the node's `workers`.
+    pub fn validate(&self, workers: usize) -> Result<(), RoutingError> {
+        let mut classes = HashSet::new();
+        let mut assigned = HashSet::new();
+        for lane in &self.lanes {
+            if !classes.insert(lane.class) {
+                return Err(RoutingError::DuplicateLane { class: lane.class })
+            }
+            if lane.workers.is_empty() {
+                return Err(RoutingError::EmptyLane { class: lane.class })
+            }
+            for worker in &lane.workers {
+                if *worker as usize >= workers {
+                    return Err(RoutingError::UnknownWorker { worker: *worker, workers })
+                }
+                if !assigned.insert(*worker) {
+                    return Err(RoutingError::DuplicateWorker { worker: *worker })
+                }
+            }
+        }
+        if assigned.len() == workers {
+            let classes = [
+                TransactionClass::Regular,
+                TransactionClass::Blob,
+                TransactionClass::LargeCalldata,
+                TransactionClass::Priority,
+            ];
+            // the priority class is only used if it has a threshold
+            let unrouted = classes.into_iter().find(|class| {
+                !self.lanes.iter().any(|lane| lane.class == *class) &&
+                    (*class != TransactionClass::Priority ||
+                        self.priority_fee_threshold.is_some())
+            });
+            if let Some(class) = unrouted {
+                return Err(RoutingError::UnroutedClass { class })
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the class of a transaction.
+    ///
+    /// Blob transactions are always [`TransactionClass::Blob`], the other classes are checked in
+    /// the order large calldata, priority.
+    pub fn classify(
+        &self,
+        is_blob: bool,
+        calldata_size: usize,
+        priority_fee: Option<u128>,
+    ) -> TransactionClass {
+        if is_blob {
+            TransactionClass::Blob
+        } else if calldata_size >= self.large_calldata_threshold {
+            TransactionClass::LargeCalldata
+        } else if self
+            .priority_fee_threshold
+            .zip(priority_fee)
+            .is_some_and(|(threshold, fee)| fee >= threshold)
+        {
+            TransactionClass::Priority
+        } else {
+            TransactionClass::Regular
+        }
+    }
+
+    /// Returns `true` if the transaction with the given hash and class is batched by `worker`.
+    pub fn accepts(&self, worker: WorkerId, class: TransactionClass, hash: &TxHash) -> bool {
+        match self.lanes.iter().find(|lane| lane.class == class) {
+            Some(lane) => {
+                let selector = u64::from_be_bytes(hash[..8].try_into().expect("8 bytes"));
+                lane.workers[(selector % lane.workers.len() as u64) as usize] == worker
+            }
+            None => !self.lanes.iter().any(|lane| lane.workers.contains(&worker)),
+        }
+    }
+
+    /// Returns the batch configuration of a worker, `default` unless the worker's lane has its
+    /// own.
+    pub fn batch_config(&self, worker: WorkerId, default: BatchConfig) -> BatchConfig {
+        self.lanes
+            .iter()
+            .find(|lane| lane.workers.contains(&worker))
+            .and_then(|lane| lane.batch)
+            .unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn routing() -> TransactionRouting {
+        TransactionRouting {
+            priority_fee_threshold: Some(1_000),
+            lanes: vec![
+                WorkerLane {
+                    class: TransactionClass::Blob,
+                    workers: vec![1],
+                    batch: Some(BatchConfig {
+                        max_transactions: 6,
+                        max_batch_delay: Duration::from_millis(500),
+                        ..Default::default()
+                    }),
+                },
+                WorkerLane { class: TransactionClass::Priority, workers: vec![2, 3], batch: None },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let routing = routing();
+        assert_eq!(routing.classify(true, 100_000, Some(5_000)), TransactionClass::Blob);
+        assert_eq!(routing.classify(false, 100_000, Some(5_000)), TransactionClass::LargeCalldata);
+        assert_eq!(routing.classify(false, 100, Some(5_000)), TransactionClass::Priority);
+        assert_eq!(routing.classify(false, 100, Some(999)), TransactionClass::Regular);
+        assert_eq!(routing.classify(false, 100, None), TransactionClass::Regular);
+    }
+
+    #[test]
+    fn route_to_lanes() {
+        let routing = routing();
+        assert_eq!(routing.validate(4), Ok(()));
+
+        // every transaction is accepted by exactly one worker
+        for byte in 0..=255 {
+            let hash = TxHash::repeat_byte(byte);
+            for class in [
+                TransactionClass::Regular,
+                TransactionClass::Blob,
+                TransactionClass::LargeCalldata,
+                TransactionClass::Priority,
+            ] {
+                let workers = (0..4)
+                    .filter(|worker| routing.accepts(*worker, class, &hash))
+                    .collect::<Vec<_>>();
+                match class {
+                    TransactionClass::Blob => assert_eq!(workers, vec![1]),
+                    TransactionClass::Priority => assert_eq!(workers.len(), 1),
+                    _ => assert_eq!(workers, vec![0]),
+                }
+            }
+        }
+
+        assert_eq!(routing.batch_config(1, BatchConfig::default()).max_transactions, 6);
+        assert_eq!(routing.batch_config(2, BatchConfig::default()), BatchConfig::default());
+        assert_eq!(routing.batch_config(0, BatchConfig::default()), BatchConfig::default());
+    }
+
+    #[test]
+    fn invalid_routing() {
+        let routing = routing();
+        assert_eq!(routing.validate(3), Err(RoutingError::UnknownWorker { worker: 3, workers: 3 }));
+
+        // all workers are assigned, but regular transactions have no lane
+        let mut all_assigned = routing.clone();
+        all_assigned.lanes[1].workers.push(0);
+        assert_eq!(
+            all_assigned.validate(4),
+            Err(RoutingError::UnroutedClass { class: TransactionClass::Regular })
+        );
+
+        let mut duplicate = routing;
+        duplicate.lanes[1].workers = vec![1];
+        assert_eq!(duplicate.validate(4), Err(RoutingError::DuplicateWorker { worker: 1 }));
+    }
+}
@@ -0,0 +1,220 @@
+//! Index of the transactions that are sequenced in the DAG but not committed yet.
+//!
+//! A transaction that was batched by any validator keeps circulating on the p2p network until its
+//! batch is committed. Without the index, every validator that receives it inserts it into its
+//! pool and batches it again, which wastes bandwidth and bloats the batches with duplicates that
+//! the executor drops. [`SequencedTransactions`] is a bloom filter of the transactions of all
+//! batches a validator stored, and [`SequencedTransactionValidator`] rejects the transactions it
+//! contains before they enter the pool.
+//!
+//! A bloom filter can't forget individual hashes, so the index forgets the oldest hashes in
+//! generations, like the [`BatchDeduplicator`](super::BatchDeduplicator). Once a batch is
+//! committed the pool rejects its transactions by their nonce, so the capacity only needs to cover
+//! the transactions sequenced between two commits.
+
+use super::dedup::RecentHashes;
+use crate::types::{Batch, TransactionExpiry};
+use alloy_primitives::{keccak256, B256};
+use reth_metrics::{metrics::Counter, Metrics};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Configuration of the [`SequencedTransactions`] index.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SequencedIndexConfig {
+    /// Number of transactions after which the index starts to forget the oldest hashes.
+    ///
+    /// Hashes are remembered for at least this many and at most twice this many insertions.
+    pub capacity: usize,
+    /// The targeted false-positive rate while the index holds `capacity` hashes.
+    pub false_positive_rate: f64,
+}
+
+impl Default for SequencedIndexConfig {
+    fn default() -> Self {
+        Self { capacity: 200_000, false_positive_rate: 0.0001 }
+    }
+}
+
+/// Metrics of the [`SequencedTransactions`] index.
+#[derive(Metrics)]
+#[metrics(scope = "narwhal.worker.sequenced")]
+struct SequencedIndexMetrics {
+    /// Number of transactions inserted into the index
+    indexed_transactions: Counter,
+    /// Number of times the oldest generation of hashes was forgotten
+    rotations: Counter,
+}
+
+/// A bloom filter of the transaction hashes in the batches stored by the workers of this
+/// validator, including the batches of other validators.
+///
+/// Cloning is cheap, all clones share the same index.
+#[derive(Debug, Clone)]
+pub struct SequencedTransactions {
+    inner: Arc<SequencedInner>,
+}
+
+#[derive(Debug)]
+struct SequencedInner {
+    hashes: RecentHashes,
+    metrics: SequencedIndexMetrics,
+}
+
+impl SequencedTransactions {
+    /// Creates a new, empty index.
+    pub fn new(config: SequencedIndexConfig) -> Self {
+        Self {
+            inner: Arc::new(SequencedInner {
+                hashes: RecentHashes::new(config.capacity, config.false_positive_rate),
+                metrics: Default::default(),
+            }),
+        }
+    }
+
+    /// Records the hash of a sequenced transaction.
+    pub fn insert(&self, hash: &B256) {
+        let inner = &*self.inner;
+        inner.metrics.indexed_transactions.increment(1);
+        if inner.hashes.insert(hash) {
+            inner.metrics.rotations.increment(1);
+        }
+    }
+
+    /// Records the transactions of a batch.
+    pub fn insert_batch(&self, batch: &Batch) {
+        for transaction in &batch.transactions {
+            // the hash of a transaction is the hash of its EIP-2718 encoding, without its expiry
+            self.insert(&keccak256(TransactionExpiry::split_batched(transaction).1));
+        }
+    }
+
+    /// Returns `true` if the transaction was probably sequenced.
+    pub fn contains(&self, hash: &B256) -> bool {
+        self.inner.hashes.contains(hash)
+    }
+}
+
+impl Default for SequencedTransactions {
+    fn default() -> Self {
+        Self::new(SequencedIndexConfig::default())
+    }
+}
+
+#[cfg(feature = "execution")]
+pub use validator::{AlreadySequenced, SequencedTransactionValidator};
+
+#[cfg(feature = "execution")]
+mod validator {
+    use super::SequencedTransactions;
+    use reth_metrics::{metrics::Counter, Metrics};
+    use reth_primitives::SealedBlock;
+    use reth_transaction_pool::{
+        error::{InvalidPoolTransactionError, PoolTransactionError},
+        PoolTransaction, TransactionOrigin, TransactionValidationOutcome, TransactionValidator,
+    };
+    use std::sync::Arc;
+
+    /// The transaction is already sequenced in the DAG.
+    #[derive(Debug, Clone, Copy, thiserror::Error)]
+    #[error("transaction is already sequenced")]
+    pub struct AlreadySequenced;
+
+    impl PoolTransactionError for AlreadySequenced {
+        fn is_bad_transaction(&self) -> bool {
+            // peers keep relaying transactions until they are committed
+            false
+        }
+    }
+
+    /// Metrics of the [`SequencedTransactionValidator`].
+    #[derive(Metrics)]
+    #[metrics(scope = "narwhal.worker.sequenced")]
+    struct SequencedValidatorMetrics {
+        /// Number of transactions rejected by the pool as already sequenced
+        rejected_transactions: Counter,
+    }
+
+    /// A [`TransactionValidator`] that rejects external transactions which are already sequenced
+    /// in the DAG, and validates all other transactions with the wrapped validator.
+    ///
+    /// Local and private transactions are never rejected by the index, since a false positive of
+    /// the bloom filter would reject a transaction submitted by a user.
+    #[derive(Debug, Clone)]
+    pub struct SequencedTransactionValidator<V> {
+        inner: V,
+        sequenced: SequencedTransactions,
+        metrics: Arc<SequencedValidatorMetrics>,
+    }
+
+    impl<V> SequencedTransactionValidator<V> {
+        /// Wraps the validator with the index of sequenced transactions.
+        pub fn new(inner: V, sequenced: SequencedTransactions) -> Self {
+            Self { inner, sequenced, metrics: Default::default() }
+        }
+
+        /// Returns the wrapped validator.
+        pub const fn inner(&self) -> &V {
+            &self.inner
+        }
+    }
+
+    impl<V: TransactionValidator> TransactionValidator for SequencedTransactionValidator<V> {
+        type Transaction = V::Transaction;
+
+        async fn validate_transaction(
+            &self,
+            origin: TransactionOrigin,
+            transaction: Self::Transaction,
+        ) -> TransactionValidationOutcome<Self::Transaction> {
+            if origin.is_external() && self.sequenced.contains(transaction.hash()) {
+                self.metrics.rejected_transactions.increment(1);
+                return TransactionValidationOutcome::Invalid(
+                    transaction,
+                    InvalidPoolTransactionError::Other(Box::new(AlreadySequenced)),
+                )
+            }
+            self.inner.validate_transaction(origin, transaction).await
+        }
+
+        fn on_new_head_block(&self, new_tip_block: &SealedBlock) {
+            self.inner.on_new_head_block(new_tip_block)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Bytes;
+
+    #[test]
+    fn index_batch_transactions() {
+        let sequenced = SequencedTransactions::new(SequencedIndexConfig::default());
+        let transactions = vec![Bytes::from_static(&[0x02, 1, 2]), Bytes::from_static(&[0xf8, 3])];
+        let unsequenced = keccak256([0x02, 4]);
+        assert!(!sequenced.contains(&keccak256(&transactions[0])));
+
+        sequenced.insert_batch(&Batch::new(transactions.clone()));
+        assert!(transactions.iter().all(|transaction| sequenced.contains(&keccak256(transaction))));
+        assert!(!sequenced.contains(&unsequenced));
+
+        // transactions with an expiry are indexed by their hash
+        let expiring = TransactionExpiry::Round(1).encode_batched(&[0x02, 5]);
+        sequenced.insert_batch(&Batch::new(vec![expiring]));
+        assert!(sequenced.contains(&keccak256([0x02, 5])));
+    }
+
+    #[test]
+    fn forget_oldest_generation() {
+        let config = SequencedIndexConfig { capacity: 4, ..Default::default() };
+        let sequenced = SequencedTransactions::new(config);
+        let hash = |i: u64| keccak256(i.to_be_bytes());
+        for i in 0..8 {
+            sequenced.insert(&hash(i));
+        }
+        assert!(!sequenced.contains(&hash(0)));
+        assert!((4..8).all(|i| sequenced.contains(&hash(i))));
+    }
+}
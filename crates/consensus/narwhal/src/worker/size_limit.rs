@@ -0,0 +1,123 @@
+//! Caps on the size of sequenced transactions.
+//!
+//! Every batch is broadcast to all workers of the committee, so a single transaction with
+//! megabytes of calldata consumes the bandwidth of a whole round. A chain can optionally cap the
+//! encoded size and the calldata of every transaction. Workers don't batch transactions above the
+//! limits, executors drop them from committed batches before decoding them, and blocks that
+//! contain them are invalid.
+
+use serde::{Deserialize, Serialize};
+
+/// Caps on the size of a transaction, part of the chain specification.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TransactionSizeLimits {
+    /// The maximum size of the EIP-2718 encoded transaction in bytes, unlimited if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_transaction_size: Option<usize>,
+    /// The maximum size of the calldata, or of the init code of a contract creation, in bytes,
+    /// unlimited if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_calldata_size: Option<usize>,
+}
+
+/// Error returned if a transaction exceeds the [`TransactionSizeLimits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TransactionTooLarge {
+    /// The encoded transaction is too large.
+    #[error("transaction size {size} exceeds the limit of {max} bytes")]
+    Size {
+        /// The size of the encoded transaction.
+        size: usize,
+        /// The maximum size.
+        max: usize,
+    },
+    /// The calldata of the transaction is too large.
+    #[error("calldata size {size} exceeds the limit of {max} bytes")]
+    Calldata {
+        /// The size of the calldata.
+        size: usize,
+        /// The maximum size.
+        max: usize,
+    },
+}
+
+impl TransactionSizeLimits {
+    /// Checks the size of an encoded transaction.
+    ///
+    /// This is checked before the transaction is decoded.
+    pub const fn check_size(&self, size: usize) -> Result<(), TransactionTooLarge> {
+        match self.max_transaction_size {
+            Some(max) if size > max => Err(TransactionTooLarge::Size { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the size of the calldata of a transaction.
+    pub const fn check_calldata(&self, size: usize) -> Result<(), TransactionTooLarge> {
+        match self.max_calldata_size {
+            Some(max) if size > max => Err(TransactionTooLarge::Calldata { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the encoded size and the calldata of a transaction.
+    #[cfg(feature = "execution")]
+    pub fn check(
+        &self,
+        transaction: &reth_primitives::TransactionSigned,
+    ) -> Result<(), TransactionTooLarge> {
+        self.check_size(transaction.length_without_header())?;
+        self.check_calldata(transaction.input().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_limits() {
+        let limits =
+            TransactionSizeLimits { max_transaction_size: Some(100), max_calldata_size: Some(64) };
+        assert_eq!(limits.check_size(100), Ok(()));
+        assert_eq!(limits.check_size(101), Err(TransactionTooLarge::Size { size: 101, max: 100 }));
+        assert_eq!(limits.check_calldata(64), Ok(()));
+        assert_eq!(
+            limits.check_calldata(65),
+            Err(TransactionTooLarge::Calldata { size: 65, max: 64 })
+        );
+
+        let unlimited = TransactionSizeLimits::default();
+        assert_eq!(unlimited.check_size(usize::MAX), Ok(()));
+        assert_eq!(unlimited.check_calldata(usize::MAX), Ok(()));
+    }
+
+    #[cfg(feature = "execution")]
+    #[test]
+    fn check_transaction() {
+        use reth_primitives::{Signature, Transaction, TransactionSigned, TxLegacy};
+
+        let transaction = TransactionSigned::from_transaction_and_signature(
+            Transaction::Legacy(TxLegacy { input: vec![0; 1_000].into(), ..Default::default() }),
+            Signature::default(),
+        );
+        let size = transaction.envelope_encoded().len();
+        let limits = TransactionSizeLimits {
+            max_transaction_size: Some(size),
+            max_calldata_size: Some(1_000),
+        };
+        assert_eq!(limits.check(&transaction), Ok(()));
+
+        let limits = TransactionSizeLimits { max_calldata_size: Some(999), ..limits };
+        assert_eq!(
+            limits.check(&transaction),
+            Err(TransactionTooLarge::Calldata { size: 1_000, max: 999 })
+        );
+        let limits = TransactionSizeLimits { max_transaction_size: Some(size - 1), ..limits };
+        assert_eq!(
+            limits.check(&transaction),
+            Err(TransactionTooLarge::Size { size, max: size - 1 })
+        );
+    }
+}
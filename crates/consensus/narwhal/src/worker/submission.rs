@@ -0,0 +1,642 @@
+//! Direct submission of transactions to the workers over gRPC.
+//!
+//! Workers batch the transactions of the pool, which only receives them through the p2p network
+//! and the RPC. Sequencer clients that produce transactions themselves can skip the pool and its
+//! gossip with the `Transactions` service of the Narwhal worker protocol, which every worker serves
+//! on its own port if [`SubmissionConfig::addr`] is set:
+//!
+//! ```protobuf
+//! package narwhal;
+//!
+//! service Transactions {
+//!     rpc SubmitTransaction(Transaction) returns (Empty);
+//!     rpc SubmitTransactionStream(stream Transaction) returns (Empty);
+//! }
+//!
+//! message Transaction {
+//!     bytes transaction = 1;
+//!     oneof expiry {
+//!         uint64 expiry_round = 2;
+//!         uint64 expiry_timestamp = 3;
+//!     }
+//! }
+//! message Empty {}
+//! ```
+//!
+//! A transaction is the EIP-2718 encoding of a signed transaction. The service acknowledges it once
+//! the batch maker of the worker queued it, see `BatchMaker::with_submissions`. The batch maker
+//! only checks that the transaction decodes and fits the size limits of the chain; nonces, balances
+//! and fees are only checked at execution, which skips invalid transactions.
+//!
+//! A transaction with an expiry is only executed in the commit of a leader round up to
+//! `expiry_round`, or in a block with a timestamp up to `expiry_timestamp`. The batch carries the
+//! expiry next to the transaction, see [`TransactionExpiry::encode_batched`], and executors drop
+//! the transaction once it expired, which `narwhal_txStatus` reports.
+//!
+//! A request may carry a [`TraceId`] in the `x-narwhal-trace-id` header, which the node records
+//! for every transaction of the request in its [`TraceIds`], see
+//! `TransactionSubmissionServer::with_trace_ids`.
+//!
+//! Clients encode the messages and decode the status of a submission with the `submission` module
+//! of `reth-narwhal-client`, which the server shares its constants with.
+
+use crate::types::WorkerId;
+#[cfg(doc)]
+use crate::{
+    trace::{TraceId, TraceIds},
+    types::TransactionExpiry,
+};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Configuration of the gRPC transaction submission service of the workers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SubmissionConfig {
+    /// The address of the service of the first worker, the service is disabled if `None`.
+    ///
+    /// Worker `i` listens on the port of the address plus `i`.
+    pub addr: Option<SocketAddr>,
+    /// The maximum size of a submitted message in bytes.
+    pub max_message_bytes: usize,
+    /// The number of submitted transactions queued while the batch maker is busy.
+    ///
+    /// Submissions wait for the queue once it's full, which slows down the clients.
+    pub buffer: usize,
+}
+
+impl SubmissionConfig {
+    /// Returns the address of the service of the worker, `None` if the service is disabled or the
+    /// port of the worker is out of range.
+    pub fn worker_addr(&self, worker: WorkerId) -> Option<SocketAddr> {
+        let mut addr = self.addr?;
+        let port = u16::try_from(worker).ok().and_then(|worker| addr.port().checked_add(worker))?;
+        addr.set_port(port);
+        Some(addr)
+    }
+}
+
+impl Default for SubmissionConfig {
+    fn default() -> Self {
+        Self { addr: None, max_message_bytes: 4 * 1024 * 1024, buffer: 10_000 }
+    }
+}
+
+#[cfg(feature = "grpc")]
+pub use server::TransactionSubmissionServer;
+
+#[cfg(feature = "grpc")]
+mod server {
+    use super::SubmissionConfig;
+    use crate::{
+        trace::{InvalidTraceId, TraceId, TraceIds},
+        types::TransactionExpiry,
+    };
+    use alloy_primitives::{keccak256, Bytes};
+    use hyper::{
+        body::{Body, Frame, Incoming, SizeHint},
+        header, HeaderMap, Method, Request, Response, StatusCode,
+    };
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use reth_metrics::{metrics::Counter, Metrics};
+    use reth_narwhal_client::submission::{
+        CONTENT_TYPE, EXPIRY_ROUND_KEY, EXPIRY_TIMESTAMP_KEY, MESSAGE_PREFIX_LEN,
+        SUBMIT_TRANSACTION_PATH, SUBMIT_TRANSACTION_STREAM_PATH, TRACE_ID_HEADER,
+    };
+    use reth_tasks::TaskExecutor;
+    use std::{
+        convert::Infallible,
+        future::poll_fn,
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+    use tokio::{net::TcpListener, sync::mpsc};
+    use tracing::{debug, warn};
+
+    /// The error of a failed submission, returned to the client as a gRPC status.
+    #[derive(Debug, thiserror::Error)]
+    enum SubmissionError {
+        /// The method is not a method of the `Transactions` service.
+        #[error("unknown method")]
+        UnknownMethod,
+        /// The client compressed a message.
+        #[error("compressed messages are not supported")]
+        Compressed,
+        /// A message exceeds the configured maximum size.
+        #[error("message of {size} bytes exceeds the limit of {max} bytes")]
+        TooLarge { size: usize, max: usize },
+        /// A message is not a valid `Transaction` message.
+        #[error("malformed message: {0}")]
+        Malformed(&'static str),
+        /// The unary method received no or several messages.
+        #[error("expected a single message")]
+        NotUnary,
+        /// The trace id header is malformed.
+        #[error(transparent)]
+        TraceId(#[from] InvalidTraceId),
+        /// The request body failed.
+        #[error("failed to read request")]
+        Body,
+        /// The batch maker of the worker stopped.
+        #[error("worker is shutting down")]
+        Shutdown,
+    }
+
+    impl SubmissionError {
+        /// Returns the gRPC status code of the error.
+        const fn code(&self) -> u16 {
+            match self {
+                // INVALID_ARGUMENT
+                Self::Malformed(_) | Self::NotUnary | Self::TraceId(_) => 3,
+                // RESOURCE_EXHAUSTED
+                Self::TooLarge { .. } => 8,
+                // UNIMPLEMENTED
+                Self::UnknownMethod | Self::Compressed => 12,
+                // UNAVAILABLE
+                Self::Body | Self::Shutdown => 14,
+            }
+        }
+    }
+
+    /// Metrics of the [`TransactionSubmissionServer`].
+    #[derive(Metrics)]
+    #[metrics(scope = "narwhal.worker.submission")]
+    struct SubmissionMetrics {
+        /// Number of transactions queued for the batch maker
+        submitted_transactions: Counter,
+        /// Number of requests that failed with a gRPC error status
+        failed_requests: Counter,
+    }
+
+    /// The gRPC server of the `Transactions` service of a worker.
+    ///
+    /// Cloning is cheap, all clones submit to the same batch maker.
+    #[derive(Debug, Clone)]
+    pub struct TransactionSubmissionServer {
+        submissions: mpsc::Sender<Bytes>,
+        max_message_bytes: usize,
+        trace_ids: Option<TraceIds>,
+        metrics: Arc<SubmissionMetrics>,
+    }
+
+    impl TransactionSubmissionServer {
+        /// Creates a server and the receiver of the submitted transactions, which must be handed
+        /// to the batch maker of the worker.
+        pub fn new(config: &SubmissionConfig) -> (Self, mpsc::Receiver<Bytes>) {
+            let (submissions, rx) = mpsc::channel(config.buffer.max(1));
+            let server = Self {
+                submissions,
+                max_message_bytes: config.max_message_bytes,
+                trace_ids: None,
+                metrics: Default::default(),
+            };
+            (server, rx)
+        }
+
+        /// Records the trace id of a request for every transaction it submits, before the
+        /// transaction is queued for the batch maker.
+        pub fn with_trace_ids(mut self, trace_ids: TraceIds) -> Self {
+            self.trace_ids = Some(trace_ids);
+            self
+        }
+
+        /// Serves the HTTP/2 connections of the listener until the batch maker stops.
+        ///
+        /// Every connection is served by a task of the executor.
+        pub async fn serve(self, listener: TcpListener, executor: TaskExecutor) {
+            loop {
+                let stream = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(err) => {
+                            warn!(
+                                target: "consensus::narwhal",
+                                %err,
+                                "Failed to accept submission connection"
+                            );
+                            continue
+                        }
+                    },
+                    () = self.submissions.closed() => return,
+                };
+                let server = self.clone();
+                let service = hyper::service::service_fn(move |request: Request<Incoming>| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.handle(request).await) }
+                });
+                executor.spawn(Box::pin(async move {
+                    let connection = hyper::server::conn::http2::Builder::new(TokioExecutor::new())
+                        .serve_connection(TokioIo::new(stream), service);
+                    if let Err(err) = connection.await {
+                        debug!(target: "consensus::narwhal", %err, "Submission connection failed");
+                    }
+                }));
+            }
+        }
+
+        /// Handles a gRPC request.
+        async fn handle<B>(&self, request: Request<B>) -> Response<GrpcBody>
+        where
+            B: Body<Data = hyper::body::Bytes> + Unpin,
+        {
+            let is_grpc = request
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .is_some_and(|content_type| content_type.starts_with(CONTENT_TYPE));
+            if request.method() != Method::POST || !is_grpc {
+                let mut response = Response::new(GrpcBody::empty());
+                *response.status_mut() = StatusCode::UNSUPPORTED_MEDIA_TYPE;
+                return response
+            }
+            let unary = match request.uri().path() {
+                SUBMIT_TRANSACTION_PATH => true,
+                SUBMIT_TRANSACTION_STREAM_PATH => false,
+                _ => return self.failed(SubmissionError::UnknownMethod),
+            };
+            let trace_id = match request.headers().get(TRACE_ID_HEADER).map(trace_id).transpose() {
+                Ok(trace_id) => trace_id,
+                Err(err) => return self.failed(err),
+            };
+            match self.submit(request.into_body(), unary, trace_id.as_ref()).await {
+                // the response is an `Empty` message
+                Ok(()) => grpc_response(GrpcBody::message(&[]), 0, None),
+                Err(err) => self.failed(err),
+            }
+        }
+
+        /// Queues the transactions of the request body for the batch maker.
+        ///
+        /// The transactions of a stream are queued as they arrive, so a failed stream still
+        /// submitted the transactions before the failure. The transaction of a unary request is
+        /// only queued once the request is complete.
+        async fn submit<B>(
+            &self,
+            mut body: B,
+            unary: bool,
+            trace_id: Option<&TraceId>,
+        ) -> Result<(), SubmissionError>
+        where
+            B: Body<Data = hyper::body::Bytes> + Unpin,
+        {
+            let mut decoder = MessageDecoder::new(self.max_message_bytes);
+            let mut single = None;
+            while let Some(frame) = poll_fn(|cx| Pin::new(&mut body).poll_frame(cx)).await {
+                let Ok(data) = frame.map_err(|_| SubmissionError::Body)?.into_data() else {
+                    // trailers of the request
+                    continue
+                };
+                decoder.extend(&data);
+                while let Some(transaction) = decoder.next_message()? {
+                    if unary {
+                        if single.replace(transaction).is_some() {
+                            return Err(SubmissionError::NotUnary)
+                        }
+                    } else {
+                        self.queue(transaction, trace_id).await?;
+                    }
+                }
+            }
+            if !decoder.is_empty() {
+                return Err(SubmissionError::Malformed("truncated message"))
+            }
+            if unary {
+                self.queue(single.ok_or(SubmissionError::NotUnary)?, trace_id).await?;
+            }
+            Ok(())
+        }
+
+        /// Queues a transaction for the batch maker, waiting while the queue is full.
+        async fn queue(
+            &self,
+            transaction: Bytes,
+            trace_id: Option<&TraceId>,
+        ) -> Result<(), SubmissionError> {
+            if let Some((trace_ids, trace_id)) = self.trace_ids.as_ref().zip(trace_id) {
+                let (_, encoded) = TransactionExpiry::split_batched(&transaction);
+                trace_ids.insert(keccak256(encoded), trace_id.clone());
+            }
+            self.submissions.send(transaction).await.map_err(|_| SubmissionError::Shutdown)?;
+            self.metrics.submitted_transactions.increment(1);
+            Ok(())
+        }
+
+        /// Returns the response of a failed request.
+        fn failed(&self, err: SubmissionError) -> Response<GrpcBody> {
+            self.metrics.failed_requests.increment(1);
+            debug!(target: "consensus::narwhal", %err, "Rejected transaction submission");
+            // a trailers-only response carries the status in its headers
+            grpc_response(GrpcBody::empty(), err.code(), Some(&err.to_string()))
+        }
+    }
+
+    /// Parses the value of the trace id header.
+    fn trace_id(value: &header::HeaderValue) -> Result<TraceId, SubmissionError> {
+        let value = value.to_str().map_err(|_| InvalidTraceId)?;
+        Ok(TraceId::try_from(value.to_owned())?)
+    }
+
+    /// Returns a gRPC response with the status in the trailers, or in the headers if the body is
+    /// empty.
+    fn grpc_response(mut body: GrpcBody, code: u16, message: Option<&str>) -> Response<GrpcBody> {
+        let mut status = HeaderMap::new();
+        status.insert("grpc-status", code.into());
+        // the messages of the errors only contain printable ASCII characters other than `%`, so
+        // they don't need to be percent-encoded
+        if let Some(message) = message.and_then(|message| message.parse().ok()) {
+            status.insert("grpc-message", message);
+        }
+        let mut response = if body.data.is_some() {
+            body.trailers = Some(status);
+            Response::new(body)
+        } else {
+            let mut response = Response::new(body);
+            response.headers_mut().extend(status);
+            response
+        };
+        response
+            .headers_mut()
+            .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/grpc"));
+        response
+    }
+
+    /// Splits the length-prefixed gRPC messages of a request body and decodes them as
+    /// `Transaction` messages.
+    #[derive(Debug)]
+    struct MessageDecoder {
+        buffer: Vec<u8>,
+        max_message_bytes: usize,
+    }
+
+    impl MessageDecoder {
+        const fn new(max_message_bytes: usize) -> Self {
+            Self { buffer: Vec::new(), max_message_bytes }
+        }
+
+        /// Appends the next chunk of the body.
+        fn extend(&mut self, data: &[u8]) {
+            self.buffer.extend_from_slice(data);
+        }
+
+        /// Returns `true` if no partial message is buffered.
+        fn is_empty(&self) -> bool {
+            self.buffer.is_empty()
+        }
+
+        /// Returns the transaction of the next complete message, `None` if the message is not
+        /// complete yet.
+        fn next_message(&mut self) -> Result<Option<Bytes>, SubmissionError> {
+            let Some(prefix) = self.buffer.get(..MESSAGE_PREFIX_LEN) else { return Ok(None) };
+            if prefix[0] != 0 {
+                return Err(SubmissionError::Compressed)
+            }
+            let size = u32::from_be_bytes(prefix[1..].try_into().expect("4 bytes")) as usize;
+            if size > self.max_message_bytes {
+                return Err(SubmissionError::TooLarge { size, max: self.max_message_bytes })
+            }
+            if self.buffer.len() < MESSAGE_PREFIX_LEN + size {
+                return Ok(None)
+            }
+            let transaction =
+                decode_transaction(&self.buffer[MESSAGE_PREFIX_LEN..MESSAGE_PREFIX_LEN + size])?;
+            self.buffer.drain(..MESSAGE_PREFIX_LEN + size);
+            Ok(Some(transaction))
+        }
+    }
+
+    /// Decodes the `transaction` and `expiry` fields of a protobuf `Transaction` message, skipping
+    /// unknown fields.
+    ///
+    /// Returns the transaction in the batched encoding of its expiry, if it has one.
+    fn decode_transaction(mut message: &[u8]) -> Result<Bytes, SubmissionError> {
+        let mut transaction = None;
+        let mut expiry = None;
+        while !message.is_empty() {
+            let key = read_varint(&mut message)?;
+            let length = match key & 0x7 {
+                // varint
+                0 => {
+                    let value = read_varint(&mut message)?;
+                    // the last member of a oneof wins
+                    if key == u64::from(EXPIRY_ROUND_KEY) {
+                        expiry = Some(TransactionExpiry::Round(value));
+                    } else if key == u64::from(EXPIRY_TIMESTAMP_KEY) {
+                        expiry = Some(TransactionExpiry::Timestamp(value));
+                    }
+                    0
+                }
+                // 64-bit
+                1 => 8,
+                // length-delimited
+                2 => usize::try_from(read_varint(&mut message)?)
+                    .map_err(|_| SubmissionError::Malformed("field too long"))?,
+                // 32-bit
+                5 => 4,
+                _ => return Err(SubmissionError::Malformed("unsupported wire type")),
+            };
+            if length > message.len() {
+                return Err(SubmissionError::Malformed("truncated field"))
+            }
+            let (value, rest) = message.split_at(length);
+            // the last value of a repeated scalar field wins
+            if key == (1 << 3) | 2 {
+                transaction = Some(value);
+            }
+            message = rest;
+        }
+        match transaction {
+            Some(transaction) if !transaction.is_empty() => Ok(expiry.map_or_else(
+                || Bytes::copy_from_slice(transaction),
+                |expiry| expiry.encode_batched(transaction),
+            )),
+            _ => Err(SubmissionError::Malformed("empty transaction")),
+        }
+    }
+
+    /// Reads a protobuf varint.
+    fn read_varint(buf: &mut &[u8]) -> Result<u64, SubmissionError> {
+        let mut value = 0u64;
+        for (i, byte) in buf.iter().take(10).enumerate() {
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                *buf = &buf[i + 1..];
+                return Ok(value)
+            }
+        }
+        Err(SubmissionError::Malformed("invalid varint"))
+    }
+
+    /// A body of a single message, followed by the trailers.
+    #[derive(Debug, Default)]
+    struct GrpcBody {
+        data: Option<hyper::body::Bytes>,
+        trailers: Option<HeaderMap>,
+    }
+
+    impl GrpcBody {
+        /// Returns an empty body.
+        fn empty() -> Self {
+            Self::default()
+        }
+
+        /// Returns the body of a single length-prefixed message.
+        fn message(message: &[u8]) -> Self {
+            let mut data = Vec::with_capacity(MESSAGE_PREFIX_LEN + message.len());
+            data.push(0);
+            data.extend_from_slice(&(message.len() as u32).to_be_bytes());
+            data.extend_from_slice(message);
+            Self { data: Some(data.into()), trailers: None }
+        }
+    }
+
+    impl Body for GrpcBody {
+        type Data = hyper::body::Bytes;
+        type Error = Infallible;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            let frame = match self.data.take() {
+                Some(data) => Some(Frame::data(data)),
+                None => self.trailers.take().map(Frame::trailers),
+            };
+            Poll::Ready(frame.map(Ok))
+        }
+
+        fn is_end_stream(&self) -> bool {
+            self.data.is_none() && self.trailers.is_none()
+        }
+
+        fn size_hint(&self) -> SizeHint {
+            SizeHint::with_exact(self.data.as_ref().map_or(0, |data| data.len() as u64))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use reth_narwhal_client::submission::{
+            encode_expiring_transaction, encode_transaction, encode_transactions, SubmissionExpiry,
+        };
+
+        fn request(path: &str, body: Vec<u8>) -> Request<GrpcBody> {
+            Request::post(path)
+                .header(header::CONTENT_TYPE, CONTENT_TYPE)
+                .body(GrpcBody { data: Some(body.into()), trailers: None })
+                .unwrap()
+        }
+
+        fn status(response: &Response<GrpcBody>) -> Option<&str> {
+            let status = response.body().trailers.as_ref().unwrap_or_else(|| response.headers());
+            status.get("grpc-status").map(|status| status.to_str().unwrap())
+        }
+
+        #[test]
+        fn decode_messages() {
+            let mut decoder = MessageDecoder::new(64);
+            let mut body = encode_transaction(&[0x02, 1, 2]);
+            // an unknown varint field before the transaction
+            let fields = [4 << 3, 150, 1, (1 << 3) | 2, 1, 0xf8];
+            body.extend([0, 0, 0, 0, fields.len() as u8]);
+            body.extend(fields);
+
+            decoder.extend(&body[..4]);
+            assert_eq!(decoder.next_message().unwrap(), None);
+            decoder.extend(&body[4..]);
+            assert_eq!(decoder.next_message().unwrap(), Some(Bytes::from_static(&[0x02, 1, 2])));
+            assert_eq!(decoder.next_message().unwrap(), Some(Bytes::from_static(&[0xf8])));
+            assert!(decoder.is_empty());
+
+            decoder.extend(&[0, 0, 0, 0, 65]);
+            assert!(matches!(
+                decoder.next_message(),
+                Err(SubmissionError::TooLarge { size: 65, max: 64 })
+            ));
+            assert!(matches!(decode_transaction(&[]), Err(SubmissionError::Malformed(_))));
+            assert!(decode_transaction(&[(1 << 3) | 2, 5, 1]).is_err());
+        }
+
+        #[test]
+        fn decode_expiring_transaction() {
+            let message = encode_expiring_transaction(&[0x02, 1], SubmissionExpiry::Round(300));
+            assert_eq!(
+                decode_transaction(&message[MESSAGE_PREFIX_LEN..]).unwrap(),
+                TransactionExpiry::Round(300).encode_batched(&[0x02, 1])
+            );
+            let message = encode_expiring_transaction(&[0x02], SubmissionExpiry::Timestamp(1));
+            assert_eq!(
+                TransactionExpiry::split_batched(
+                    &decode_transaction(&message[MESSAGE_PREFIX_LEN..]).unwrap()
+                ),
+                (Some(TransactionExpiry::Timestamp(1)), &[0x02][..])
+            );
+            // an expiry without a transaction
+            assert!(decode_transaction(&[EXPIRY_ROUND_KEY, 1]).is_err());
+        }
+
+        #[tokio::test]
+        async fn submit_transactions() {
+            let config = SubmissionConfig { buffer: 8, ..Default::default() };
+            let (server, mut submissions) = TransactionSubmissionServer::new(&config);
+
+            let response =
+                server.handle(request(SUBMIT_TRANSACTION_PATH, encode_transaction(&[1]))).await;
+            assert_eq!(status(&response), Some("0"));
+            assert_eq!(response.body().data.as_deref(), Some(&[0, 0, 0, 0, 0][..]));
+            assert_eq!(submissions.recv().await.unwrap(), Bytes::from_static(&[1]));
+
+            let stream = encode_transactions([&[2u8][..], &[3][..]]);
+            let response =
+                server.handle(request(SUBMIT_TRANSACTION_STREAM_PATH, stream.clone())).await;
+            assert_eq!(status(&response), Some("0"));
+            assert_eq!(submissions.recv().await.unwrap(), Bytes::from_static(&[2]));
+            assert_eq!(submissions.recv().await.unwrap(), Bytes::from_static(&[3]));
+
+            // the unary method only takes a single transaction
+            let response = server.handle(request(SUBMIT_TRANSACTION_PATH, stream)).await;
+            assert_eq!(status(&response), Some("3"));
+            assert!(submissions.try_recv().is_err());
+            let response = server.handle(request("/narwhal.Transactions/Other", Vec::new())).await;
+            assert_eq!(status(&response), Some("12"));
+
+            drop(submissions);
+            let response =
+                server.handle(request(SUBMIT_TRANSACTION_PATH, encode_transaction(&[4]))).await;
+            assert_eq!(status(&response), Some("14"));
+        }
+
+        #[tokio::test]
+        async fn record_trace_ids() {
+            let config = SubmissionConfig { buffer: 8, ..Default::default() };
+            let (server, mut submissions) = TransactionSubmissionServer::new(&config);
+            let trace_ids = TraceIds::new(8);
+            let server = server.with_trace_ids(trace_ids.clone());
+
+            let expiring = encode_expiring_transaction(&[2], SubmissionExpiry::Round(1));
+            let stream = [encode_transaction(&[1]), expiring].concat();
+            let mut traced = request(SUBMIT_TRANSACTION_STREAM_PATH, stream);
+            traced
+                .headers_mut()
+                .insert(TRACE_ID_HEADER, header::HeaderValue::from_static("order-42"));
+            assert_eq!(status(&server.handle(traced).await), Some("0"));
+            submissions.recv().await.unwrap();
+            submissions.recv().await.unwrap();
+            // the transactions are traced by the hash of their encoding without the expiry
+            for transaction in [[1], [2]] {
+                assert_eq!(
+                    trace_ids.get(&keccak256(transaction)).as_ref().map(TraceId::as_str),
+                    Some("order-42")
+                );
+            }
+
+            let mut malformed = request(SUBMIT_TRANSACTION_PATH, encode_transaction(&[3]));
+            malformed
+                .headers_mut()
+                .insert(TRACE_ID_HEADER, header::HeaderValue::from_static("a b"));
+            assert_eq!(status(&server.handle(malformed).await), Some("3"));
+            assert!(submissions.try_recv().is_err());
+        }
+    }
+}
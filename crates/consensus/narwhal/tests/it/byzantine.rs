@@ -0,0 +1,99 @@
+//! Commits of committees with faulty authorities.
+
+use reth_narwhal_consensus::{
+    leader::{LeaderElector, LeaderSchedule},
+    test_utils::{ByzantineBehavior, SimulatedCommittee},
+};
+use std::collections::HashSet;
+
+const ROUNDS: u64 = 30;
+
+/// Asserts that the honest authorities reached the last round, committed the same leaders, and
+/// committed the leader of every round that an honest authority leads.
+fn assert_honest_progress(committee: &SimulatedCommittee) {
+    let honest = committee.honest().collect::<Vec<_>>();
+    let commits = committee.commits(honest[0]);
+    for authority in &honest {
+        assert_eq!(committee.highest_round(*authority), committee.round());
+        assert_eq!(committee.commits(*authority), commits, "authority {authority} diverged");
+    }
+
+    let committed = commits.iter().map(|commit| commit.round).collect::<HashSet<_>>();
+    let elector = LeaderElector::new(LeaderSchedule::RoundRobin, committee.committee());
+    for round in (2..=committee.round() - 2).step_by(2) {
+        let leader = elector.leader(round).unwrap();
+        if committee.is_honest(leader) {
+            assert!(committed.contains(&round), "leader {leader} of round {round} not committed");
+        }
+    }
+
+    let mut certified = HashSet::new();
+    for certificate in committee.certificates() {
+        assert!(
+            certified.insert((certificate.round(), certificate.author())),
+            "two certificates of authority {} in round {}",
+            certificate.author(),
+            certificate.round()
+        );
+    }
+}
+
+#[test]
+fn withheld_votes() {
+    let mut committee =
+        SimulatedCommittee::new(4).with_behavior(3, ByzantineBehavior::WithholdVotes);
+    committee.run(ROUNDS);
+    assert_honest_progress(&committee);
+    // the headers of the faulty authority are still certified by the honest ones
+    let faulty = committee.certificates().iter().filter(|certificate| certificate.author() == 3);
+    assert_eq!(faulty.count() as u64, ROUNDS);
+}
+
+#[test]
+fn equivocation() {
+    let mut committee = SimulatedCommittee::new(4).with_behavior(1, ByzantineBehavior::Equivocate);
+    committee.run(ROUNDS);
+    assert_honest_progress(&committee);
+}
+
+#[test]
+fn delayed_certificates() {
+    let mut committee = SimulatedCommittee::new(4)
+        .with_behavior(3, ByzantineBehavior::DelayCertificates { rounds: 3 });
+    committee.run(ROUNDS);
+    assert_honest_progress(&committee);
+}
+
+#[test]
+fn malformed_batches() {
+    let mut committee =
+        SimulatedCommittee::new(4).with_behavior(2, ByzantineBehavior::MalformedBatches);
+    committee.run(ROUNDS);
+    assert_honest_progress(&committee);
+    // no honest authority stores the batches, so none of the headers is certified
+    assert!(committee.certificates().iter().all(|certificate| certificate.author() != 2));
+}
+
+#[test]
+fn combined_faults() {
+    let mut committee = SimulatedCommittee::new(7)
+        .with_behavior(1, ByzantineBehavior::Equivocate)
+        .with_behavior(1, ByzantineBehavior::WithholdVotes)
+        .with_behavior(4, ByzantineBehavior::DelayCertificates { rounds: 2 })
+        .with_behavior(4, ByzantineBehavior::MalformedBatches);
+    committee.run(ROUNDS);
+    assert_honest_progress(&committee);
+}
+
+#[test]
+fn more_than_f_faults_stall() {
+    let mut committee = SimulatedCommittee::new(4)
+        .with_behavior(2, ByzantineBehavior::WithholdVotes)
+        .with_behavior(3, ByzantineBehavior::WithholdVotes);
+    committee.run(ROUNDS);
+    // the honest headers miss a quorum of votes, so no round completes after the first
+    for authority in 0..4 {
+        assert_eq!(committee.highest_round(authority), 1);
+        assert!(committee.commits(authority).is_empty());
+    }
+}
@@ -0,0 +1,301 @@
+//! Continuous load across changes of the committee.
+
+use alloy_primitives::{keccak256, Bytes, B256};
+use reth_narwhal_consensus::{
+    committee::{Authority, Committee, CommitteeError},
+    committer::Committer,
+    dag_store::{DagStore, MemoryDagStore},
+    dev::{DevCommittee, DevPrimaries},
+    epoch::{
+        CommitteeSource, EpochChange, EpochManager, EpochStart, EpochTasks, UncommittedBatches,
+    },
+    leader::LeaderSchedule,
+    primary::PrimaryConfig,
+    shutdown::ShutdownStage,
+    signature::BlsSecretKey,
+    types::{OrderedSubDag, Round},
+    verifier::{Epoch, SigningDomain},
+    worker::{BatchBuilder, BatchConfig, SealedBatch},
+};
+use reth_tasks::{shutdown::GracefulShutdown, TaskManager};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+/// The boundary round of every epoch change.
+const BOUNDARY_ROUND: Round = 12;
+
+/// The interval in which the local worker seals a batch.
+const BATCH_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The number of transactions of a batch.
+const BATCH_TRANSACTIONS: u64 = 4;
+
+/// The number of sub-dags the last epoch commits before the load stops.
+const LAST_EPOCH_SUB_DAGS: usize = 5;
+
+/// The GC depth of the committers, which bounds the latency of batches whose certificates are
+/// never committed and have to be proposed again.
+const GC_DEPTH: Round = 10;
+
+/// The maximum time between the sealing of a transaction and its commit.
+const MAX_COMMIT_LATENCY: Duration = Duration::from_secs(10);
+
+/// The maximum time between the last commit of an epoch and the first commit of the next one.
+const MAX_EPOCH_GAP: Duration = Duration::from_secs(5);
+
+/// The committees of the epochs, drawn from the authorities of a dev committee of five.
+fn committees() -> (Vec<Committee>, Vec<BlsSecretKey>) {
+    let dev = DevCommittee::new(5);
+    let keys = (0..5).map(|authority| dev.secret_key(authority).unwrap().clone()).collect();
+    let committee = |epoch, members: &[(usize, u64)]| Committee {
+        epoch,
+        authorities: members
+            .iter()
+            .map(|&(index, stake)| Authority {
+                stake,
+                ..dev.committee().authorities[index].clone()
+            })
+            .collect(),
+    };
+    let committees = vec![
+        committee(0, &[(0, 1), (1, 1), (2, 1), (3, 1)]),
+        // an authority joins
+        committee(1, &[(0, 1), (1, 1), (2, 1), (3, 1), (4, 1)]),
+        // the stakes change
+        committee(2, &[(0, 2), (1, 1), (2, 1), (3, 1), (4, 3)]),
+        // an authority leaves
+        committee(3, &[(0, 2), (1, 1), (3, 1), (4, 3)]),
+    ];
+    (committees, keys)
+}
+
+/// Schedules the change to every committee at [`BOUNDARY_ROUND`] of the previous epoch.
+#[derive(Debug)]
+struct ScheduledCommittees(Vec<Committee>);
+
+impl CommitteeSource for ScheduledCommittees {
+    fn epoch_change(&self, epoch: Epoch) -> Result<Option<EpochChange>, CommitteeError> {
+        Ok(self.0.get(epoch as usize + 1).map(|committee| EpochChange {
+            boundary_round: BOUNDARY_ROUND,
+            committee: committee.clone(),
+        }))
+    }
+}
+
+/// The transactions of the load, numbered in the order they were sealed.
+#[derive(Debug, Default)]
+struct Load {
+    next: AtomicU64,
+    /// The time every transaction was sealed at.
+    sealed: Mutex<HashMap<u64, Instant>>,
+    stopped: AtomicBool,
+}
+
+/// Runs the authorities of the committee like a node that runs all of them, with a worker of the
+/// first authority that seals a batch of new transactions every [`BATCH_INTERVAL`].
+#[derive(Debug)]
+struct LoadedEpochTasks {
+    keys: Vec<BlsSecretKey>,
+    store: Arc<dyn DagStore>,
+    uncommitted: UncommittedBatches,
+    load: Arc<Load>,
+}
+
+impl EpochTasks for LoadedEpochTasks {
+    fn start(&mut self, epoch: EpochStart<'_>) -> mpsc::Receiver<OrderedSubDag> {
+        let EpochStart { committee, first_sub_dag, halt } = epoch;
+        let dev = DevCommittee::with_keys(Committee::clone(&committee), &self.keys).unwrap();
+        let config =
+            PrimaryConfig { max_header_delay: Duration::from_millis(20), ..Default::default() };
+        let DevPrimaries { primaries, batches, mut certifier, .. } =
+            dev.primaries(SigningDomain::new(1337, B256::ZERO), config);
+        let (output, sub_dags) = mpsc::channel(16);
+        let committer = Committer::new(
+            committee,
+            LeaderSchedule::default(),
+            Arc::clone(&self.store),
+            GC_DEPTH,
+            certifier.subscribe(),
+            output,
+            first_sub_dag,
+        )
+        .unwrap()
+        .with_uncommitted_batches(self.uncommitted.clone());
+        spawn_until(halt.on_shutdown(ShutdownStage::Committer), committer.run());
+        tokio::spawn(certifier.run());
+        for primary in primaries {
+            let primary = primary.with_store(Arc::clone(&self.store)).unwrap();
+            spawn_until(halt.on_shutdown(ShutdownStage::Primary), primary.run());
+        }
+
+        // the first key is the local authority, which is a member of every committee
+        let to_primary = batches.into_iter().next().unwrap();
+        let (sealed, sealed_rx) = mpsc::channel(16);
+        let forward = self.uncommitted.clone().forward(sealed_rx, to_primary);
+        let forward_halt = halt.on_shutdown(ShutdownStage::Workers);
+        tokio::spawn(async move {
+            forward.await;
+            drop(forward_halt.await);
+        });
+        tokio::spawn(seal_batches(
+            Arc::clone(&self.load),
+            Arc::clone(&self.store),
+            sealed,
+            halt.on_shutdown(ShutdownStage::Workers),
+        ));
+        sub_dags
+    }
+}
+
+/// Runs a task until it completes or the epoch is halted.
+fn spawn_until(mut halt: GracefulShutdown, task: impl Future<Output = ()> + Send + 'static) {
+    tokio::spawn(async move {
+        tokio::select! {
+            guard = &mut halt => drop(guard),
+            () = task => drop(halt.await),
+        }
+    });
+}
+
+/// Seals a batch of new transactions every [`BATCH_INTERVAL`] until the epoch is halted.
+///
+/// A sealed batch is always handed over to the forwarder, which keeps it for the next epoch if it
+/// isn't committed in this one.
+async fn seal_batches(
+    load: Arc<Load>,
+    store: Arc<dyn DagStore>,
+    sealed: mpsc::Sender<SealedBatch>,
+    mut halt: GracefulShutdown,
+) {
+    let mut builder = BatchBuilder::new(BatchConfig::default(), 0);
+    let mut interval = tokio::time::interval(BATCH_INTERVAL);
+    loop {
+        tokio::select! {
+            guard = &mut halt => {
+                drop(guard);
+                return
+            }
+            _ = interval.tick() => {}
+        }
+        if load.stopped.load(Ordering::Relaxed) {
+            continue
+        }
+        let first = load.next.fetch_add(BATCH_TRANSACTIONS, Ordering::Relaxed);
+        for id in first..first + BATCH_TRANSACTIONS {
+            let transaction = Bytes::copy_from_slice(&id.to_be_bytes());
+            builder.push(keccak256(&transaction), transaction);
+        }
+        let batch = builder.seal().unwrap();
+        store.write_batch(batch.digest, &batch.batch).unwrap();
+        let now = Instant::now();
+        load.sealed.lock().unwrap().extend((first..first + BATCH_TRANSACTIONS).map(|id| (id, now)));
+        sealed.send(batch).await.unwrap();
+    }
+}
+
+/// The commits of an epoch.
+#[derive(Debug)]
+struct EpochCommits {
+    first: Instant,
+    last: Instant,
+    sub_dags: usize,
+    authorities: BTreeSet<u32>,
+}
+
+#[test]
+fn no_lost_transactions_across_epochs() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let task_manager = TaskManager::new(runtime.handle().clone());
+    let (committees, keys) = committees();
+    let epochs = committees.len();
+    let load = Arc::new(Load::default());
+    let store: Arc<dyn DagStore> = Arc::new(MemoryDagStore::default());
+    let uncommitted = UncommittedBatches::new(GC_DEPTH).with_store(Arc::clone(&store));
+    let tasks = LoadedEpochTasks {
+        keys,
+        store: Arc::clone(&store),
+        uncommitted: uncommitted.clone(),
+        load: Arc::clone(&load),
+    };
+    let (output, mut sub_dags) = mpsc::channel(16);
+    let first_committee = committees[0].clone();
+    let source = Arc::new(ScheduledCommittees(committees));
+    let (manager, provider) = EpochManager::new(first_committee, source, tasks, output);
+    let manager = manager.with_uncommitted_batches(uncommitted.clone()).with_votes(store);
+    let _guard = runtime.enter();
+    task_manager
+        .executor()
+        .spawn_critical_with_graceful_shutdown_signal("epochs", |shutdown| manager.run(shutdown));
+
+    let mut committed = HashMap::<u64, Epoch>::new();
+    let mut commits = BTreeMap::<Epoch, EpochCommits>::new();
+    runtime.block_on(async {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(120);
+        loop {
+            let sub_dag = tokio::time::timeout_at(deadline, sub_dags.recv())
+                .await
+                .expect("load committed in time")
+                .unwrap();
+            let now = Instant::now();
+            let epoch = sub_dag.epoch();
+            let entry = commits.entry(epoch).or_insert_with(|| EpochCommits {
+                first: now,
+                last: now,
+                sub_dags: 0,
+                authorities: BTreeSet::new(),
+            });
+            entry.last = now;
+            entry.sub_dags += 1;
+            entry
+                .authorities
+                .extend(sub_dag.certificates.iter().map(|certificate| certificate.author()));
+
+            let sealed = load.sealed.lock().unwrap().clone();
+            for transaction in sub_dag.batches.iter().flat_map(|batch| &batch.transactions) {
+                let id = u64::from_be_bytes(transaction[..].try_into().unwrap());
+                if let Some(first) = committed.insert(id, epoch) {
+                    panic!("transaction {id} committed in epoch {first} and {epoch}");
+                }
+                let latency = now - sealed[&id];
+                assert!(
+                    latency < MAX_COMMIT_LATENCY,
+                    "transaction {id} committed after {latency:?}"
+                );
+            }
+
+            if epoch as usize == epochs - 1 && entry.sub_dags >= LAST_EPOCH_SUB_DAGS {
+                load.stopped.store(true, Ordering::Relaxed);
+            }
+            if load.stopped.load(Ordering::Relaxed) && committed.len() == sealed.len() {
+                break
+            }
+        }
+    });
+
+    // every sealed transaction was committed exactly once, including those sealed at a boundary
+    let sealed = load.sealed.lock().unwrap();
+    assert!(sealed.keys().all(|id| committed.contains_key(id)));
+    assert_eq!(committed.len(), sealed.len());
+    assert_eq!(provider.current_epoch(), epochs as Epoch - 1);
+
+    // every epoch committed the certificates of its whole committee, and the commits resumed
+    // shortly after each change
+    let sizes = commits.values().map(|commits| commits.authorities.len()).collect::<Vec<_>>();
+    assert_eq!(sizes, [4, 5, 5, 4]);
+    for (previous, next) in commits.values().zip(commits.values().skip(1)) {
+        let gap = next.first - previous.last;
+        assert!(gap < MAX_EPOCH_GAP, "no commit for {gap:?} after an epoch change");
+    }
+    assert!(uncommitted.is_empty());
+
+    drop(sub_dags);
+    assert!(task_manager.graceful_shutdown_with_timeout(Duration::from_secs(10)));
+}
@@ -0,0 +1,9 @@
+//! narwhal consensus integration tests
+
+#[cfg(feature = "test-utils")]
+mod byzantine;
+mod epochs;
+#[cfg(feature = "sim")]
+mod sim;
+
+const fn main() {}
@@ -0,0 +1,103 @@
+//! Reproducible runs of committees in virtual time.
+
+use reth_narwhal_consensus::{
+    determinism::Clock,
+    gc::gc_round,
+    sim::{SimConfig, Simulation},
+    test_utils::SimulatedCommit,
+};
+use std::time::Duration;
+
+/// Runs the committee until every authority committed the leader of the given round.
+fn run_until_committed(config: SimConfig, round: u64) -> Simulation {
+    let mut simulation = Simulation::new(config);
+    let committed = simulation.run_until(Duration::from_secs(600), |simulation| {
+        (0..config.committee_size as u32)
+            .all(|authority| simulation.dag(authority).last_committed() >= round)
+    });
+    assert!(committed, "seed {}: round {round} not committed", config.seed);
+    simulation
+}
+
+/// Asserts that the commits of every authority are a prefix of the longest commit sequence.
+fn assert_same_commit_order(simulation: &Simulation) {
+    let size = simulation.config().committee_size as u32;
+    let longest = (0..size)
+        .map(|authority| simulation.commits(authority))
+        .max_by_key(|commits| commits.len())
+        .unwrap();
+    for authority in 0..size {
+        let commits = simulation.commits(authority);
+        assert_eq!(
+            commits,
+            &longest[..commits.len()],
+            "seed {}: authority {authority} diverged",
+            simulation.config().seed
+        );
+    }
+}
+
+#[test]
+fn replay_from_seed() {
+    let config = SimConfig { seed: 7, ..Default::default() };
+    let mut first = Simulation::new(config);
+    let mut second = Simulation::new(config);
+    first.run_for(Duration::from_secs(5));
+    second.run_for(Duration::from_secs(5));
+
+    assert_eq!(first.processed_events(), second.processed_events());
+    assert_eq!(first.clock().now_millis(), second.clock().now_millis());
+    for authority in 0..4 {
+        assert!(!first.commits(authority).is_empty());
+        assert_eq!(first.commits(authority), second.commits(authority));
+        assert_eq!(first.round(authority), second.round(authority));
+    }
+
+    let mut other = Simulation::new(SimConfig { seed: 8, ..config });
+    other.run_for(Duration::from_secs(5));
+    assert_ne!(first.processed_events(), other.processed_events());
+}
+
+#[test]
+fn same_commit_order() {
+    for seed in 0..4 {
+        let simulation = run_until_committed(SimConfig { seed, ..Default::default() }, 20);
+        assert_same_commit_order(&simulation);
+    }
+    // reproduce a failure with `NARWHAL_SIM_SEED`
+    let config = SimConfig { committee_size: 7, ..SimConfig::from_env() };
+    let simulation = run_until_committed(config, 10);
+    assert_same_commit_order(&simulation);
+}
+
+#[test]
+fn garbage_collection() {
+    let config = SimConfig { seed: 3, gc_depth: 4, ..Default::default() };
+    let simulation = run_until_committed(config, 40);
+    for authority in 0..4 {
+        let dag = simulation.dag(authority);
+        assert_eq!(dag.gc_round(), gc_round(dag.last_committed(), 4));
+        // the DAG holds the uncommitted rounds and the rounds above the GC round
+        let rounds = dag.highest_round() - dag.gc_round() + 1;
+        assert!(
+            dag.len() as u64 <= 4 * rounds,
+            "authority {authority} kept {} vertices",
+            dag.len()
+        );
+        assert!(dag.round(dag.gc_round() - 1).is_empty());
+    }
+
+    // garbage collection doesn't change which leaders are committed
+    let unpruned = run_until_committed(SimConfig { gc_depth: 1_000, ..config }, 40);
+    let leaders = |commits: &[SimulatedCommit]| {
+        commits
+            .iter()
+            .map(|commit| commit.round)
+            .take_while(|round| *round <= 40)
+            .collect::<Vec<_>>()
+    };
+    for authority in 0..4 {
+        assert_eq!(leaders(simulation.commits(authority)), leaders(unpruned.commits(authority)));
+        assert_eq!(unpruned.dag(authority).gc_round(), 0);
+    }
+}
@@ -29,8 +29,10 @@ use reth_revm::{
 };
 use revm_primitives::{
     db::{DatabaseCommit, DatabaseRef},
-    BlockEnv, CfgEnvWithHandlerCfg, EVMError, EnvWithHandlerCfg, ResultAndState,
+    BlockEnv, CfgEnvWithHandlerCfg, EVMError, EnvWithHandlerCfg, InvalidTransaction,
+    ResultAndState,
 };
+use tracing::warn;
 
 #[cfg(not(feature = "std"))]
 use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
@@ -111,6 +113,8 @@ struct EthExecuteOutput {
     receipts: Vec<Receipt>,
     requests: Vec<Request>,
     gas_used: u64,
+    /// The transactions that were left out of the block, by their index in the block.
+    skipped: Vec<(usize, InvalidTransaction)>,
 }
 
 /// Helper container type for EVM with chain spec.
@@ -136,10 +140,14 @@ where
     ///
     /// It does __not__ apply post-execution changes that do not require an [EVM](Evm), for that see
     /// [`EthBlockExecutor::post_execution`].
+    ///
+    /// If `skip_invalid` is set, transactions that fail the validation of the EVM are left out of
+    /// the block instead of failing it.
     fn execute_state_transitions<Ext, DB>(
         &self,
         block: &BlockWithSenders,
         mut evm: Evm<'_, Ext, &mut State<DB>>,
+        skip_invalid: bool,
     ) -> Result<(EthExecuteOutput, Vec<ResultAndState>), BlockExecutionError>
     where
         DB: DatabaseRef<Error: Into<ProviderError> + Display> + Send + Sync,
@@ -166,7 +174,8 @@ where
         let mut cumulative_gas_used = 0;
         let mut receipts = Vec::with_capacity(block.body.len());
         let mut results = Vec::with_capacity(block.body.len());
-        for (sender, transaction) in block.transactions_with_sender() {
+        let mut skipped = Vec::new();
+        for (index, (sender, transaction)) in block.transactions_with_sender().enumerate() {
             // The sum of the transaction’s gas limit, Tg, and the gas utilized in this block prior,
             // must be no greater than the block’s gasLimit.
             let block_available_gas = block.header.gas_limit - cumulative_gas_used;
@@ -181,7 +190,14 @@ where
             self.evm_config.fill_tx_env(evm.tx_mut(), transaction, *sender);
 
             // Execute transaction.
-            let result_and_state = evm.transact().map_err(move |err| {
+            let result_and_state = match evm.transact() {
+                Err(EVMError::Transaction(err)) if skip_invalid => {
+                    skipped.push((index, err));
+                    continue
+                }
+                result => result,
+            };
+            let result_and_state = result_and_state.map_err(move |err| {
                 let new_err = match err {
                     EVMError::Transaction(e) => EVMError::Transaction(e),
                     EVMError::Header(e) => EVMError::Header(e),
@@ -235,7 +251,10 @@ where
             vec![]
         };
 
-        Ok((EthExecuteOutput { receipts, requests, gas_used: cumulative_gas_used }, results))
+        Ok((
+            EthExecuteOutput { receipts, requests, gas_used: cumulative_gas_used, skipped },
+            results,
+        ))
     }
 }
 
@@ -304,6 +323,17 @@ where
         &mut self,
         block: &BlockWithSenders,
         total_difficulty: U256,
+    ) -> Result<EthExecuteOutput, BlockExecutionError> {
+        self.execute_transactions(block, total_difficulty, false)
+    }
+
+    /// Executes the block like [`Self::execute_without_verification`], and leaves out the
+    /// transactions that fail the validation of the EVM if `skip_invalid` is set.
+    fn execute_transactions(
+        &mut self,
+        block: &BlockWithSenders,
+        total_difficulty: U256,
+        skip_invalid: bool,
     ) -> Result<EthExecuteOutput, BlockExecutionError> {
         // 1. prepare state on new block
         self.on_new_block(&block.header);
@@ -348,20 +378,36 @@ where
                 tx_envs,
                 core::num::NonZeroUsize::new(8).unwrap(),
             )
-        }
-        .unwrap_or_else(|err| panic!("{:?}", err));
+        };
         let env = self.evm_env_for_block(&block.header, total_difficulty);
         let (output_sequential, results_sequential) = {
             let evm = self.executor.evm_config.evm_with_env(&mut self.state, env);
-            self.executor.execute_state_transitions(block, evm)
+            self.executor.execute_state_transitions(block, evm, skip_invalid)
         }?;
-        for (tx_idx, (seq, par)) in
-            results_sequential.iter().zip(results_parallel.iter()).enumerate()
-        {
-            if seq != par {
-                dbg!(&seq);
-                dbg!(&par);
-                panic!("Block {} mismatched at tx {tx_idx}", block.number);
+        // an invalid transaction fails both executions, the error of the sequential execution
+        // identifies the transaction. The parallel execution only cross-checks the sequential one,
+        // so a block it fails to execute keeps the sequential results. The results of a block
+        // without its skipped transactions can't be compared.
+        match results_parallel {
+            Ok(_) if !output_sequential.skipped.is_empty() => {}
+            Ok(results_parallel) => {
+                for (tx_idx, (seq, par)) in
+                    results_sequential.iter().zip(results_parallel.iter()).enumerate()
+                {
+                    if seq != par {
+                        dbg!(&seq);
+                        dbg!(&par);
+                        panic!("Block {} mismatched at tx {tx_idx}", block.number);
+                    }
+                }
+            }
+            Err(err) => {
+                warn!(
+                    target: "evm",
+                    block = block.number,
+                    ?err,
+                    "Parallel execution failed, keeping the sequential results"
+                );
             }
         }
 
@@ -425,14 +471,20 @@ where
     ///
     /// Returns an error if the block could not be executed or failed verification.
     fn execute(mut self, input: Self::Input<'_>) -> Result<Self::Output, Self::Error> {
-        let BlockExecutionInput { block, total_difficulty } = input;
-        let EthExecuteOutput { receipts, requests, gas_used } =
-            self.execute_without_verification(block, total_difficulty)?;
+        let BlockExecutionInput { block, total_difficulty, skip_invalid_transactions } = input;
+        let EthExecuteOutput { receipts, requests, gas_used, skipped } =
+            self.execute_transactions(block, total_difficulty, skip_invalid_transactions)?;
 
         // NOTE: we need to merge keep the reverts for the bundle retention
         self.state.merge_transitions(BundleRetention::Reverts);
 
-        Ok(BlockExecutionOutput { state: self.state.take_bundle(), receipts, requests, gas_used })
+        Ok(BlockExecutionOutput {
+            state: self.state.take_bundle(),
+            receipts,
+            requests,
+            gas_used,
+            skipped,
+        })
     }
 }
 
@@ -467,13 +519,13 @@ where
     type Error = BlockExecutionError;
 
     fn execute_and_verify_one(&mut self, input: Self::Input<'_>) -> Result<(), Self::Error> {
-        let BlockExecutionInput { block, total_difficulty } = input;
+        let BlockExecutionInput { block, total_difficulty, .. } = input;
 
         if self.batch_record.first_block().is_none() {
             self.batch_record.set_first_block(block.number);
         }
 
-        let EthExecuteOutput { receipts, requests, gas_used: _ } =
+        let EthExecuteOutput { receipts, requests, .. } =
             self.executor.execute_without_verification(block, total_difficulty)?;
 
         validate_block_post_execution(block, self.executor.chain_spec(), &receipts, &requests)?;
@@ -524,7 +576,8 @@ mod tests {
     use reth_chainspec::{ChainSpecBuilder, ForkCondition};
     use reth_primitives::{
         constants::{EMPTY_ROOT_HASH, ETH_TO_WEI},
-        keccak256, public_key_to_address, Account, Block, Transaction, TxKind, TxLegacy, B256,
+        keccak256, public_key_to_address, Account, Address, Block, Transaction, TxKind, TxLegacy,
+        B256,
     };
     use reth_revm::{
         database::StateProviderDatabase, test_utils::StateProviderTest, TransitionState,
@@ -1474,4 +1527,54 @@ mod tests {
             ),
         }
     }
+
+    #[test]
+    fn invalid_transaction_is_skipped() {
+        let chain_spec = Arc::new(ChainSpecBuilder::from(&*MAINNET).shanghai_activated().build());
+
+        let mut db = StateProviderTest::default();
+        let secp = Secp256k1::new();
+        let sender_key_pair = Keypair::new(&secp, &mut generators::rng());
+        let sender_address = public_key_to_address(sender_key_pair.public_key());
+        db.insert_account(
+            sender_address,
+            Account { nonce: 1, balance: U256::from(ETH_TO_WEI), bytecode_hash: None },
+            None,
+            HashMap::new(),
+        );
+
+        let mut header = chain_spec.genesis_header();
+        header.gas_limit = 1_500_000;
+        let transfer = |nonce| {
+            sign_tx_with_key_pair(
+                sender_key_pair,
+                Transaction::Legacy(TxLegacy {
+                    chain_id: Some(chain_spec.chain.id()),
+                    nonce,
+                    gas_price: header.base_fee_per_gas.unwrap().into(),
+                    gas_limit: 21_000,
+                    to: TxKind::Call(Address::with_last_byte(0xff)),
+                    value: U256::from(1),
+                    input: Bytes::new(),
+                }),
+            )
+        };
+        // the second transaction skips a nonce
+        let body = vec![transfer(1), transfer(5), transfer(2)];
+
+        let executor =
+            executor_provider(chain_spec.clone()).executor(StateProviderDatabase::new(&db));
+        let block = Block { header, body, ommers: vec![], withdrawals: None, requests: None }
+            .with_recovered_senders()
+            .unwrap();
+        let output = executor
+            .execute(
+                BlockExecutionInput::new(&block, U256::ZERO).with_invalid_transactions_skipped(),
+            )
+            .unwrap();
+
+        assert_eq!(output.skipped, vec![(1, InvalidTransaction::NonceTooHigh { tx: 5, state: 2 })]);
+        assert_eq!(output.receipts.len(), 2);
+        assert_eq!(output.gas_used, 42_000);
+    }
 }
@@ -1,5 +1,5 @@
 use reth_primitives::{Request, U256};
-use revm::db::BundleState;
+use revm::{db::BundleState, primitives::InvalidTransaction};
 
 /// A helper type for ethereum block inputs that consists of a block and the total difficulty.
 #[derive(Debug)]
@@ -8,12 +8,24 @@ pub struct BlockExecutionInput<'a, Block> {
     pub block: &'a Block,
     /// The total difficulty of the block.
     pub total_difficulty: U256,
+    /// Whether the transactions that fail the validation of the EVM are left out of the block
+    /// instead of failing its execution, see [`BlockExecutionOutput::skipped`].
+    pub skip_invalid_transactions: bool,
 }
 
 impl<'a, Block> BlockExecutionInput<'a, Block> {
     /// Creates a new input.
     pub const fn new(block: &'a Block, total_difficulty: U256) -> Self {
-        Self { block, total_difficulty }
+        Self { block, total_difficulty, skip_invalid_transactions: false }
+    }
+
+    /// Leaves the transactions that fail the validation of the EVM out of the block, instead of
+    /// failing its execution.
+    ///
+    /// Executors that don't support it ignore it and fail the block.
+    pub const fn with_invalid_transactions_skipped(mut self) -> Self {
+        self.skip_invalid_transactions = true;
+        self
     }
 }
 
@@ -36,4 +48,7 @@ pub struct BlockExecutionOutput<T> {
     pub requests: Vec<Request>,
     /// The total gas used by the block.
     pub gas_used: u64,
+    /// The transactions that were left out of the block because they failed the validation of
+    /// the EVM, by their index in the block, if the input allowed it. They have no receipts.
+    pub skipped: Vec<(usize, InvalidTransaction)>,
 }
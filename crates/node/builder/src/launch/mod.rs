@@ -2,11 +2,13 @@
 
 pub mod common;
 mod exex;
+mod stages;
 
 pub(crate) mod engine;
 
 pub use common::LaunchContext;
 pub use exex::ExExLauncher;
+pub use stages::{ConsensusDriver, EngineSpawnContext, LaunchStage, LaunchStageHook};
 
 use std::{future::Future, sync::Arc};
 
@@ -41,6 +43,7 @@ use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use crate::{
     builder::{NodeAdapter, NodeTypesAdapter},
+    common::{Attached, LaunchContextWith, WithComponents, WithConfigs},
     components::{NodeComponents, NodeComponentsBuilder},
     hooks::NodeHooks,
     node::FullNode,
@@ -87,20 +90,33 @@ where
 }
 
 /// The default launcher for a node.
+///
+/// The launch flow is split into [`LaunchStage`]s. A [`LaunchStageHook`] installed with
+/// [`DefaultNodeLauncher::with_stage_hook`] is notified about every stage and can replace the
+/// consensus driver, while all other stages run as usual.
 #[derive(Debug)]
-pub struct DefaultNodeLauncher {
+pub struct DefaultNodeLauncher<H = ()> {
     /// The task executor for the node.
     pub ctx: LaunchContext,
+    /// The hook into the launch stages.
+    pub stage_hook: H,
 }
 
 impl DefaultNodeLauncher {
     /// Create a new instance of the default node launcher.
     pub const fn new(task_executor: TaskExecutor, data_dir: ChainPath<DataDirPath>) -> Self {
-        Self { ctx: LaunchContext::new(task_executor, data_dir) }
+        Self { ctx: LaunchContext::new(task_executor, data_dir), stage_hook: () }
     }
 }
 
-impl<T, CB, AO> LaunchNode<NodeBuilderWithComponents<T, CB, AO>> for DefaultNodeLauncher
+impl<H> DefaultNodeLauncher<H> {
+    /// Installs a hook into the launch stages.
+    pub fn with_stage_hook<H2>(self, stage_hook: H2) -> DefaultNodeLauncher<H2> {
+        DefaultNodeLauncher { ctx: self.ctx, stage_hook }
+    }
+}
+
+impl<T, CB, AO, H> LaunchNode<NodeBuilderWithComponents<T, CB, AO>> for DefaultNodeLauncher<H>
 where
     T: FullNodeTypes<
         Provider = BlockchainProvider<<T as FullNodeTypes>::DB>,
@@ -116,6 +132,7 @@ where
             >,
         > + AddDevSigners,
     >,
+    H: LaunchStageHook<NodeAdapter<T, CB::Components>>,
 {
     type Node = NodeHandle<NodeAdapter<T, CB::Components>, AO>;
 
@@ -123,7 +140,7 @@ where
         self,
         target: NodeBuilderWithComponents<T, CB, AO>,
     ) -> eyre::Result<Self::Node> {
-        let Self { ctx } = self;
+        let Self { ctx, mut stage_hook } = self;
         let NodeBuilderWithComponents {
             adapter: NodeTypesAdapter { database },
             components_builder,
@@ -145,6 +162,7 @@ where
             canon_state_notification_sender.clone(),
         ));
 
+        stage_hook.on_stage_started(LaunchStage::DatabaseInit);
         // setup the launch context
         let ctx = ctx
             .with_configured_globals()
@@ -168,14 +186,23 @@ where
             .with_genesis()?
             .inspect(|this| {
                 info!(target: "reth::cli", "\n{}", this.chain_spec().display_hardforks());
-            })
+            });
+        stage_hook.on_stage_finished(LaunchStage::DatabaseInit);
+
+        stage_hook.on_stage_started(LaunchStage::ProviderInit);
+        let ctx = ctx
             .with_metrics_task()
             // passing FullNodeTypes as type parameter here so that we can build
             // later the components.
-            .with_blockchain_db::<T, _>(move |provider_factory| {
-                Ok(BlockchainProvider::new(provider_factory, tree)?)
-            }, tree_config, canon_state_notification_sender)?
-            .with_components(components_builder, on_component_initialized).await?;
+            .with_blockchain_db::<T, _>(
+                move |provider_factory| Ok(BlockchainProvider::new(provider_factory, tree)?),
+                tree_config,
+                canon_state_notification_sender,
+            )?;
+        stage_hook.on_stage_finished(LaunchStage::ProviderInit);
+
+        stage_hook.on_stage_started(LaunchStage::ComponentBuild);
+        let ctx = ctx.with_components(components_builder, on_component_initialized).await?;
 
         // spawn exexs
         let exex_manager_handle = ExExLauncher::new(
@@ -186,152 +213,38 @@ where
         )
         .launch()
         .await;
-
-        // create pipeline
-        let network_client = ctx.components().network().fetch_client().await?;
-        let (consensus_engine_tx, consensus_engine_rx) = unbounded_channel();
-
-        let node_config = ctx.node_config();
-        let consensus_engine_stream = UnboundedReceiverStream::from(consensus_engine_rx)
-            .maybe_skip_fcu(node_config.debug.skip_fcu)
-            .maybe_skip_new_payload(node_config.debug.skip_new_payload)
-            .maybe_reorg(
-                ctx.blockchain_db().clone(),
-                ctx.components().evm_config().clone(),
-                reth_payload_validator::ExecutionPayloadValidator::new(ctx.chain_spec()),
-                node_config.debug.reorg_frequency,
-                node_config.debug.reorg_depth,
-            )
-            // Store messages _after_ skipping so that `replay-engine` command
-            // would replay only the messages that were observed by the engine
-            // during this run.
-            .maybe_store_messages(node_config.debug.engine_api_store.clone());
-
-        let max_block = ctx.max_block(network_client.clone()).await?;
-        let mut hooks = EngineHooks::new();
-
-        if let Some(ref hook_type) = ctx.node_config().debug.invalid_block_hook {
-            warn!(target: "reth::cli", ?hook_type, "Bad block hooks are not implemented yet! The `debug.bad-block-hook` flag will do nothing for now.");
-        }
-
-        let static_file_producer = ctx.static_file_producer();
-        let static_file_producer_events = static_file_producer.lock().events();
-        hooks.add(StaticFileHook::new(
-            static_file_producer.clone(),
-            Box::new(ctx.task_executor().clone()),
-        ));
-        info!(target: "reth::cli", "StaticFileProducer initialized");
-
-        // Configure the pipeline
-        let pipeline_exex_handle =
-            exex_manager_handle.clone().unwrap_or_else(ExExManagerHandle::empty);
-        let (pipeline, client) = if ctx.is_dev() {
-            info!(target: "reth::cli", "Starting Reth in dev mode");
-
-            for (idx, (address, alloc)) in ctx.chain_spec().genesis.alloc.iter().enumerate() {
-                info!(target: "reth::cli", "Allocated Genesis Account: {:02}. {} ({} ETH)", idx, address.to_string(), format_ether(alloc.balance));
+        stage_hook.on_stage_finished(LaunchStage::ComponentBuild);
+
+        stage_hook.on_stage_started(LaunchStage::EngineSpawn);
+        let driver = stage_hook.spawn_consensus_driver(EngineSpawnContext {
+            node: ctx.node_adapter(),
+            config: ctx.node_config(),
+            head: ctx.head(),
+            exex_manager_handle: exex_manager_handle.as_ref(),
+        })?;
+        let ConsensusDriver { engine_handle, events, driver } = match driver {
+            Some(driver) => {
+                info!(target: "reth::cli", "Custom consensus driver initialized");
+                driver
+            }
+            None => {
+                // a hook that seals the blocks itself replaces the auto-seal miner of dev mode
+                let auto_seal = ctx.is_dev() && !stage_hook.seals_blocks();
+                beacon_consensus_driver(&ctx, exex_manager_handle.as_ref(), auto_seal).await?
             }
-
-            // install auto-seal
-            let mining_mode =
-                ctx.dev_mining_mode(ctx.components().pool().pending_transactions_listener());
-            info!(target: "reth::cli", mode=%mining_mode, "configuring dev mining mode");
-
-            let (_, client, mut task) = reth_auto_seal_consensus::AutoSealBuilder::new(
-                ctx.chain_spec(),
-                ctx.blockchain_db().clone(),
-                ctx.components().pool().clone(),
-                consensus_engine_tx.clone(),
-                mining_mode,
-                ctx.components().block_executor().clone(),
-            )
-            .build();
-
-            let pipeline = crate::setup::build_networked_pipeline(
-                &ctx.toml_config().stages,
-                client.clone(),
-                ctx.consensus(),
-                ctx.provider_factory().clone(),
-                ctx.task_executor(),
-                ctx.sync_metrics_tx(),
-                ctx.prune_config(),
-                max_block,
-                static_file_producer,
-                ctx.components().block_executor().clone(),
-                pipeline_exex_handle,
-            )?;
-
-            let pipeline_events = pipeline.events();
-            task.set_pipeline_events(pipeline_events);
-            debug!(target: "reth::cli", "Spawning auto mine task");
-            ctx.task_executor().spawn(Box::pin(task));
-
-            (pipeline, Either::Left(client))
-        } else {
-            let pipeline = crate::setup::build_networked_pipeline(
-                &ctx.toml_config().stages,
-                network_client.clone(),
-                ctx.consensus(),
-                ctx.provider_factory().clone(),
-                ctx.task_executor(),
-                ctx.sync_metrics_tx(),
-                ctx.prune_config(),
-                max_block,
-                static_file_producer,
-                ctx.components().block_executor().clone(),
-                pipeline_exex_handle,
-            )?;
-
-            (pipeline, Either::Right(network_client.clone()))
         };
-
-        let pipeline_events = pipeline.events();
-
-        let initial_target = ctx.node_config().debug.tip;
-
-        let mut pruner_builder = ctx.pruner_builder();
-        if let Some(exex_manager_handle) = &exex_manager_handle {
-            pruner_builder =
-                pruner_builder.finished_exex_height(exex_manager_handle.finished_height());
-        }
-        let pruner = pruner_builder.build_with_provider_factory(ctx.provider_factory().clone());
-
-        let pruner_events = pruner.events();
-        info!(target: "reth::cli", prune_config=?ctx.prune_config().unwrap_or_default(), "Pruner initialized");
-        hooks.add(PruneHook::new(pruner, Box::new(ctx.task_executor().clone())));
-
-        // Configure the consensus engine
-        let (beacon_consensus_engine, beacon_engine_handle) = BeaconConsensusEngine::with_channel(
-            client,
-            pipeline,
-            ctx.blockchain_db().clone(),
-            Box::new(ctx.task_executor().clone()),
-            Box::new(ctx.components().network().clone()),
-            max_block,
-            ctx.components().payload_builder().clone(),
-            initial_target,
-            reth_beacon_consensus::MIN_BLOCKS_FOR_PIPELINE_RUN,
-            consensus_engine_tx,
-            Box::pin(consensus_engine_stream),
-            hooks,
-        )?;
-        info!(target: "reth::cli", "Consensus engine initialized");
-
-        let events = stream_select!(
-            ctx.components().network().event_listener().map(Into::into),
-            beacon_engine_handle.event_listener().map(Into::into),
-            pipeline_events.map(Into::into),
-            if ctx.node_config().debug.tip.is_none() && !ctx.is_dev() {
-                Either::Left(
-                    ConsensusLayerHealthEvents::new(Box::new(ctx.blockchain_db().clone()))
-                        .map(Into::into),
-                )
-            } else {
-                Either::Right(stream::empty())
+        stage_hook.on_engine_spawned(
+            EngineSpawnContext {
+                node: ctx.node_adapter(),
+                config: ctx.node_config(),
+                head: ctx.head(),
+                exex_manager_handle: exex_manager_handle.as_ref(),
             },
-            pruner_events.map(Into::into),
-            static_file_producer_events.map(Into::into),
-        );
+            &engine_handle,
+        )?;
+
+        let events =
+            stream_select!(ctx.components().network().event_listener().map(Into::into), events);
         ctx.task_executor().spawn_critical(
             "events task",
             node::handle_events(
@@ -340,7 +253,9 @@ where
                 events,
             ),
         );
+        stage_hook.on_stage_finished(LaunchStage::EngineSpawn);
 
+        stage_hook.on_stage_started(LaunchStage::RpcStart);
         let client = ClientVersionV1 {
             code: CLIENT_CODE,
             name: NAME_CLIENT.to_string(),
@@ -350,7 +265,7 @@ where
         let engine_api = EngineApi::new(
             ctx.blockchain_db().clone(),
             ctx.chain_spec(),
-            beacon_engine_handle,
+            engine_handle,
             ctx.components().payload_builder().clone().into(),
             Box::new(ctx.task_executor().clone()),
             client,
@@ -380,7 +295,7 @@ where
         let (tx, rx) = oneshot::channel();
         info!(target: "reth::cli", "Starting consensus engine");
         ctx.task_executor().spawn_critical_blocking("consensus engine", async move {
-            let res = beacon_consensus_engine.await;
+            let res = driver.await;
             let _ = tx.send(res);
         });
 
@@ -426,6 +341,8 @@ where
             });
         }
 
+        stage_hook.on_stage_finished(LaunchStage::RpcStart);
+
         let full_node = FullNode {
             evm_config: ctx.components().evm_config().clone(),
             block_executor: ctx.components().block_executor().clone(),
@@ -444,7 +361,7 @@ where
 
         let handle = NodeHandle {
             node_exit_future: NodeExitFuture::new(
-                async { Ok(rx.await??) },
+                async { rx.await? },
                 full_node.config.debug.terminate,
             ),
             node: full_node,
@@ -453,3 +370,168 @@ where
         Ok(handle)
     }
 }
+
+/// Creates the default consensus driver: the beacon consensus engine, which syncs with the
+/// pipeline, or with the auto-seal miner if `auto_seal` is set.
+async fn beacon_consensus_driver<T, CB>(
+    ctx: &LaunchContextWith<Attached<WithConfigs, WithComponents<T::DB, T, CB>>>,
+    exex_manager_handle: Option<&ExExManagerHandle>,
+    auto_seal: bool,
+) -> eyre::Result<ConsensusDriver<T::Engine>>
+where
+    T: FullNodeTypes<
+        Provider = BlockchainProvider<<T as FullNodeTypes>::DB>,
+        ChainSpec = ChainSpec,
+    >,
+    CB: NodeComponentsBuilder<T>,
+{
+    // create pipeline
+    let network_client = ctx.components().network().fetch_client().await?;
+    let (consensus_engine_tx, consensus_engine_rx) = unbounded_channel();
+
+    let node_config = ctx.node_config();
+    let consensus_engine_stream = UnboundedReceiverStream::from(consensus_engine_rx)
+        .maybe_skip_fcu(node_config.debug.skip_fcu)
+        .maybe_skip_new_payload(node_config.debug.skip_new_payload)
+        .maybe_reorg(
+            ctx.blockchain_db().clone(),
+            ctx.components().evm_config().clone(),
+            reth_payload_validator::ExecutionPayloadValidator::new(ctx.chain_spec()),
+            node_config.debug.reorg_frequency,
+            node_config.debug.reorg_depth,
+        )
+        // Store messages _after_ skipping so that `replay-engine` command
+        // would replay only the messages that were observed by the engine
+        // during this run.
+        .maybe_store_messages(node_config.debug.engine_api_store.clone());
+
+    let max_block = ctx.max_block(network_client.clone()).await?;
+    let mut hooks = EngineHooks::new();
+
+    if let Some(ref hook_type) = ctx.node_config().debug.invalid_block_hook {
+        warn!(target: "reth::cli", ?hook_type, "Bad block hooks are not implemented yet! The `debug.bad-block-hook` flag will do nothing for now.");
+    }
+
+    let static_file_producer = ctx.static_file_producer();
+    let static_file_producer_events = static_file_producer.lock().events();
+    hooks.add(StaticFileHook::new(
+        static_file_producer.clone(),
+        Box::new(ctx.task_executor().clone()),
+    ));
+    info!(target: "reth::cli", "StaticFileProducer initialized");
+
+    // Configure the pipeline
+    let pipeline_exex_handle =
+        exex_manager_handle.cloned().unwrap_or_else(ExExManagerHandle::empty);
+    let (pipeline, client) = if auto_seal {
+        info!(target: "reth::cli", "Starting Reth in dev mode");
+
+        for (idx, (address, alloc)) in ctx.chain_spec().genesis.alloc.iter().enumerate() {
+            info!(target: "reth::cli", "Allocated Genesis Account: {:02}. {} ({} ETH)", idx, address.to_string(), format_ether(alloc.balance));
+        }
+
+        // install auto-seal
+        let mining_mode =
+            ctx.dev_mining_mode(ctx.components().pool().pending_transactions_listener());
+        info!(target: "reth::cli", mode=%mining_mode, "configuring dev mining mode");
+
+        let (_, client, mut task) = reth_auto_seal_consensus::AutoSealBuilder::new(
+            ctx.chain_spec(),
+            ctx.blockchain_db().clone(),
+            ctx.components().pool().clone(),
+            consensus_engine_tx.clone(),
+            mining_mode,
+            ctx.components().block_executor().clone(),
+        )
+        .build();
+
+        let pipeline = crate::setup::build_networked_pipeline(
+            &ctx.toml_config().stages,
+            client.clone(),
+            ctx.consensus(),
+            ctx.provider_factory().clone(),
+            ctx.task_executor(),
+            ctx.sync_metrics_tx(),
+            ctx.prune_config(),
+            max_block,
+            static_file_producer,
+            ctx.components().block_executor().clone(),
+            pipeline_exex_handle,
+        )?;
+
+        let pipeline_events = pipeline.events();
+        task.set_pipeline_events(pipeline_events);
+        debug!(target: "reth::cli", "Spawning auto mine task");
+        ctx.task_executor().spawn(Box::pin(task));
+
+        (pipeline, Either::Left(client))
+    } else {
+        let pipeline = crate::setup::build_networked_pipeline(
+            &ctx.toml_config().stages,
+            network_client.clone(),
+            ctx.consensus(),
+            ctx.provider_factory().clone(),
+            ctx.task_executor(),
+            ctx.sync_metrics_tx(),
+            ctx.prune_config(),
+            max_block,
+            static_file_producer,
+            ctx.components().block_executor().clone(),
+            pipeline_exex_handle,
+        )?;
+
+        (pipeline, Either::Right(network_client.clone()))
+    };
+
+    let pipeline_events = pipeline.events();
+
+    let initial_target = ctx.node_config().debug.tip;
+
+    let mut pruner_builder = ctx.pruner_builder();
+    if let Some(exex_manager_handle) = exex_manager_handle {
+        pruner_builder = pruner_builder.finished_exex_height(exex_manager_handle.finished_height());
+    }
+    let pruner = pruner_builder.build_with_provider_factory(ctx.provider_factory().clone());
+
+    let pruner_events = pruner.events();
+    info!(target: "reth::cli", prune_config=?ctx.prune_config().unwrap_or_default(), "Pruner initialized");
+    hooks.add(PruneHook::new(pruner, Box::new(ctx.task_executor().clone())));
+
+    // Configure the consensus engine
+    let (beacon_consensus_engine, beacon_engine_handle) = BeaconConsensusEngine::with_channel(
+        client,
+        pipeline,
+        ctx.blockchain_db().clone(),
+        Box::new(ctx.task_executor().clone()),
+        Box::new(ctx.components().network().clone()),
+        max_block,
+        ctx.components().payload_builder().clone(),
+        initial_target,
+        reth_beacon_consensus::MIN_BLOCKS_FOR_PIPELINE_RUN,
+        consensus_engine_tx,
+        Box::pin(consensus_engine_stream),
+        hooks,
+    )?;
+    info!(target: "reth::cli", "Consensus engine initialized");
+
+    let events = stream_select!(
+        beacon_engine_handle.event_listener().map(Into::into),
+        pipeline_events.map(Into::into),
+        if ctx.node_config().debug.tip.is_none() && !ctx.is_dev() {
+            Either::Left(
+                ConsensusLayerHealthEvents::new(Box::new(ctx.blockchain_db().clone()))
+                    .map(Into::into),
+            )
+        } else {
+            Either::Right(stream::empty())
+        },
+        pruner_events.map(Into::into),
+        static_file_producer_events.map(Into::into),
+    );
+
+    ConsensusDriver {
+        engine_handle: beacon_engine_handle,
+        events: events.boxed(),
+        driver: Box::pin(async move { Ok::<_, eyre::Report>(beacon_consensus_engine.await?) }),
+    }
+}
@@ -0,0 +1,146 @@
+//! Named stages of the [`DefaultNodeLauncher`](super::DefaultNodeLauncher) launch flow.
+
+use std::{fmt, future::Future, pin::Pin};
+
+use futures::stream::BoxStream;
+use reth_beacon_consensus::BeaconConsensusEngineHandle;
+use reth_exex::ExExManagerHandle;
+use reth_node_api::{EngineTypes, FullNodeComponents};
+use reth_node_core::node_config::NodeConfig;
+use reth_node_events::node::NodeEvent;
+use reth_primitives::Head;
+
+/// A stage of the [`DefaultNodeLauncher`](super::DefaultNodeLauncher) launch flow.
+///
+/// Stages run in the order they are declared in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LaunchStage {
+    /// Loads the configs, opens the database and initializes the genesis.
+    DatabaseInit,
+    /// Creates the blockchain provider on top of the database.
+    ProviderInit,
+    /// Builds the node components and launches the installed `ExEx`es.
+    ComponentBuild,
+    /// Spawns the consensus driver, by default the beacon consensus engine and its pipeline.
+    EngineSpawn,
+    /// Starts the RPC servers and the debug consensus clients.
+    RpcStart,
+}
+
+impl LaunchStage {
+    /// All stages in the order they run.
+    pub const ALL: [Self; 5] = [
+        Self::DatabaseInit,
+        Self::ProviderInit,
+        Self::ComponentBuild,
+        Self::EngineSpawn,
+        Self::RpcStart,
+    ];
+
+    /// Returns the name of the stage.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::DatabaseInit => "database init",
+            Self::ProviderInit => "provider init",
+            Self::ComponentBuild => "component build",
+            Self::EngineSpawn => "engine spawn",
+            Self::RpcStart => "rpc start",
+        }
+    }
+}
+
+impl fmt::Display for LaunchStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Everything the [`LaunchStage::EngineSpawn`] stage has access to.
+#[derive(Debug)]
+pub struct EngineSpawnContext<'a, Node> {
+    /// The components of the node.
+    pub node: &'a Node,
+    /// The config of the node.
+    pub config: &'a NodeConfig,
+    /// The head of the chain when the node was launched.
+    pub head: Head,
+    /// The handle to the `ExEx` manager, if any `ExEx`es are installed.
+    pub exex_manager_handle: Option<&'a ExExManagerHandle>,
+}
+
+/// The consensus driver spawned by the [`LaunchStage::EngineSpawn`] stage.
+///
+/// The driver decides which blocks become canonical. The engine API of the node forwards all
+/// `engine_` calls to its [`BeaconConsensusEngineHandle`].
+pub struct ConsensusDriver<Engine: EngineTypes> {
+    /// The handle the engine API forwards `engine_` calls to.
+    pub engine_handle: BeaconConsensusEngineHandle<Engine>,
+    /// The events of the driver, which are logged with the other node events.
+    pub events: BoxStream<'static, NodeEvent>,
+    /// Drives consensus until it stops. The launcher spawns it once the RPC servers are started,
+    /// and the node exits with its result.
+    pub driver: Pin<Box<dyn Future<Output = eyre::Result<()>> + Send>>,
+}
+
+impl<Engine: EngineTypes> fmt::Debug for ConsensusDriver<Engine> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsensusDriver").field("engine_handle", &self.engine_handle).finish()
+    }
+}
+
+/// Hooks into the stages of the [`DefaultNodeLauncher`](super::DefaultNodeLauncher).
+///
+/// A custom launcher installs a hook with
+/// [`DefaultNodeLauncher::with_stage_hook`](super::DefaultNodeLauncher::with_stage_hook) to
+/// observe the launch flow and to replace the consensus driver, e.g. with one that derives blocks
+/// from an external consensus protocol instead of running the beacon consensus engine. All other
+/// stages are reused as they are.
+pub trait LaunchStageHook<Node: FullNodeComponents>: Send {
+    /// Called before a stage starts.
+    fn on_stage_started(&mut self, stage: LaunchStage) {
+        let _ = stage;
+    }
+
+    /// Called after a stage completed.
+    fn on_stage_finished(&mut self, stage: LaunchStage) {
+        let _ = stage;
+    }
+
+    /// Returns whether the hook seals the blocks of the node itself, e.g. from
+    /// [`on_engine_spawned`](Self::on_engine_spawned).
+    ///
+    /// The default driver of a node in dev mode doesn't install the auto-seal miner if it does.
+    fn seals_blocks(&self) -> bool {
+        false
+    }
+
+    /// Creates the consensus driver of the node, which the launcher spawns once the RPC servers
+    /// are started.
+    ///
+    /// Returns `None` to use the default driver, the beacon consensus engine that syncs with the
+    /// pipeline.
+    fn spawn_consensus_driver(
+        &mut self,
+        ctx: EngineSpawnContext<'_, Node>,
+    ) -> eyre::Result<Option<ConsensusDriver<Node::Engine>>> {
+        let _ = ctx;
+        Ok(None)
+    }
+
+    /// Called once the consensus driver was created, with the handle the engine API forwards
+    /// `engine_` calls to.
+    ///
+    /// This is where a hook starts the tasks that submit payloads to the engine themselves, e.g.
+    /// the executor of an external consensus protocol. The driver itself only runs once the RPC
+    /// servers are started, so messages sent to the handle are queued until then.
+    fn on_engine_spawned(
+        &mut self,
+        ctx: EngineSpawnContext<'_, Node>,
+        engine_handle: &BeaconConsensusEngineHandle<Node::Engine>,
+    ) -> eyre::Result<()> {
+        let _ = (ctx, engine_handle);
+        Ok(())
+    }
+}
+
+impl<Node: FullNodeComponents> LaunchStageHook<Node> for () {}
@@ -2,7 +2,7 @@
 
 use std::time::Duration;
 
-use clap::Args;
+use clap::{builder::TypedValueParser, Args};
 use humantime::parse_duration;
 
 /// Parameters for Dev testnet configuration
@@ -39,6 +39,19 @@ pub struct DevArgs {
         verbatim_doc_comment
     )]
     pub block_time: Option<Duration>,
+
+    /// Number of authorities of the narwhal committee that runs in-process in dev mode.
+    ///
+    /// Every authority runs its own primary and workers, connected by local channels instead of
+    /// the network, so a single node exercises the quorum logic of a real committee. The committee
+    /// seals the blocks instead of the auto-seal miner.
+    #[arg(
+        long = "dev.narwhal-committee-size",
+        help_heading = "Dev testnet",
+        value_name = "SIZE",
+        value_parser = clap::value_parser!(u64).range(1..).map(|size| size as usize)
+    )]
+    pub narwhal_committee_size: Option<usize>,
 }
 
 #[cfg(test)]
@@ -56,13 +69,37 @@ mod tests {
     #[test]
     fn test_parse_dev_args() {
         let args = CommandParser::<DevArgs>::parse_from(["reth"]).args;
-        assert_eq!(args, DevArgs { dev: false, block_max_transactions: None, block_time: None });
+        assert_eq!(
+            args,
+            DevArgs {
+                dev: false,
+                block_max_transactions: None,
+                block_time: None,
+                narwhal_committee_size: None
+            }
+        );
 
         let args = CommandParser::<DevArgs>::parse_from(["reth", "--dev"]).args;
-        assert_eq!(args, DevArgs { dev: true, block_max_transactions: None, block_time: None });
+        assert_eq!(
+            args,
+            DevArgs {
+                dev: true,
+                block_max_transactions: None,
+                block_time: None,
+                narwhal_committee_size: None
+            }
+        );
 
         let args = CommandParser::<DevArgs>::parse_from(["reth", "--auto-mine"]).args;
-        assert_eq!(args, DevArgs { dev: true, block_max_transactions: None, block_time: None });
+        assert_eq!(
+            args,
+            DevArgs {
+                dev: true,
+                block_max_transactions: None,
+                block_time: None,
+                narwhal_committee_size: None
+            }
+        );
 
         let args = CommandParser::<DevArgs>::parse_from([
             "reth",
@@ -71,7 +108,15 @@ mod tests {
             "2",
         ])
         .args;
-        assert_eq!(args, DevArgs { dev: true, block_max_transactions: Some(2), block_time: None });
+        assert_eq!(
+            args,
+            DevArgs {
+                dev: true,
+                block_max_transactions: Some(2),
+                block_time: None,
+                narwhal_committee_size: None
+            }
+        );
 
         let args =
             CommandParser::<DevArgs>::parse_from(["reth", "--dev", "--dev.block-time", "1s"]).args;
@@ -80,9 +125,26 @@ mod tests {
             DevArgs {
                 dev: true,
                 block_max_transactions: None,
-                block_time: Some(std::time::Duration::from_secs(1))
+                block_time: Some(std::time::Duration::from_secs(1)),
+                narwhal_committee_size: None
             }
         );
+
+        let args = CommandParser::<DevArgs>::parse_from([
+            "reth",
+            "--dev",
+            "--dev.narwhal-committee-size",
+            "4",
+        ])
+        .args;
+        assert_eq!(args.narwhal_committee_size, Some(4));
+        assert!(CommandParser::<DevArgs>::try_parse_from([
+            "reth",
+            "--dev",
+            "--dev.narwhal-committee-size",
+            "0",
+        ])
+        .is_err());
     }
 
     #[test]
@@ -44,6 +44,13 @@ pub use txpool::TxPoolArgs;
 mod dev;
 pub use dev::DevArgs;
 
+/// NarwhalArgs for configuring narwhal consensus
+mod narwhal;
+pub use narwhal::{
+    NarwhalArgs, DEFAULT_NARWHAL_BATCH_SIZE, DEFAULT_NARWHAL_GC_DEPTH,
+    DEFAULT_NARWHAL_MAX_BATCH_DELAY, DEFAULT_NARWHAL_TARGET_TPS, DEFAULT_NARWHAL_WORKER_COUNT,
+};
+
 /// PruneArgs for configuring the pruning and full node
 mod pruning;
 pub use pruning::PruningArgs;
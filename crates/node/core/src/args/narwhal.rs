@@ -0,0 +1,208 @@
+//! clap [Args](clap::Args) for narwhal configuration
+
+use clap::{builder::TypedValueParser, Args};
+use humantime::parse_duration;
+use std::{path::PathBuf, time::Duration};
+
+/// The default number of workers of a narwhal validator.
+pub const DEFAULT_NARWHAL_WORKER_COUNT: usize = 1;
+
+/// The default size in bytes at which a worker seals a batch.
+pub const DEFAULT_NARWHAL_BATCH_SIZE: usize = 500_000;
+
+/// The default maximum time a batch stays open before it's sealed.
+pub const DEFAULT_NARWHAL_MAX_BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// The default number of rounds kept below the last committed round.
+pub const DEFAULT_NARWHAL_GC_DEPTH: u64 = 50;
+
+/// The default throughput the hardware self-check expects, in transactions per second.
+pub const DEFAULT_NARWHAL_TARGET_TPS: u64 = 1_000;
+
+/// Parameters for narwhal configuration
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+#[command(next_help_heading = "Narwhal")]
+pub struct NarwhalArgs {
+    /// Path to the file with the committee of the current epoch, in TOML or JSON format
+    #[arg(long = "narwhal.committee-file", value_name = "PATH")]
+    pub committee_file: Option<PathBuf>,
+
+    /// Path to the encrypted keystore with the keys of the validator
+    ///
+    /// Takes the place of a plain keypair file: the keys are only stored encrypted with the
+    /// password of `--narwhal.keystore-password-file`.
+    /// Defaults to `<DATADIR>/narwhal/keystore.json`.
+    #[arg(
+        long = "narwhal.keystore",
+        visible_alias = "narwhal.keypair-file",
+        value_name = "PATH",
+        verbatim_doc_comment
+    )]
+    pub keystore: Option<PathBuf>,
+
+    /// Path to the file with the password of the keystore
+    ///
+    /// The keystore is created with new keys if it doesn't exist. The node runs without validator
+    /// keys if not set.
+    #[arg(long = "narwhal.keystore-password-file", value_name = "PATH")]
+    pub keystore_password_file: Option<PathBuf>,
+
+    /// Path to the node-local narwhal configuration, in TOML format
+    ///
+    /// The flags of this section override the values of the file.
+    #[arg(long = "narwhal.config", value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Number of workers that seal and replicate batches
+    #[arg(
+        long = "narwhal.worker-count",
+        value_name = "COUNT",
+        default_value_t = DEFAULT_NARWHAL_WORKER_COUNT,
+        value_parser = clap::value_parser!(u64).range(1..).map(|count| count as usize)
+    )]
+    pub worker_count: usize,
+
+    /// Size of the encoded transactions in bytes at which a worker seals a batch
+    #[arg(
+        long = "narwhal.batch-size",
+        value_name = "BYTES",
+        default_value_t = DEFAULT_NARWHAL_BATCH_SIZE
+    )]
+    pub batch_size: usize,
+
+    /// Maximum time a batch stays open before it's sealed.
+    ///
+    /// Parses strings using [`humantime::parse_duration`]
+    /// --narwhal.max-batch-delay 100ms
+    #[arg(
+        long = "narwhal.max-batch-delay",
+        value_name = "DURATION",
+        default_value = "100ms",
+        value_parser = parse_duration,
+        verbatim_doc_comment
+    )]
+    pub max_batch_delay: Duration,
+
+    /// Number of rounds below the last committed round that are kept before the DAG is pruned
+    #[arg(
+        long = "narwhal.gc-depth",
+        value_name = "ROUNDS",
+        default_value_t = DEFAULT_NARWHAL_GC_DEPTH
+    )]
+    pub gc_depth: u64,
+
+    /// Measures the hardware on startup and warns if it's below the recommendations for the
+    /// committee and the target throughput
+    #[arg(long = "narwhal.self-check")]
+    pub self_check: bool,
+
+    /// Number of transactions per second the hardware self-check expects the node to sustain
+    #[arg(
+        long = "narwhal.target-tps",
+        value_name = "TPS",
+        default_value_t = DEFAULT_NARWHAL_TARGET_TPS
+    )]
+    pub target_tps: u64,
+
+    /// Randomly delays and drops worker messages and restarts the primary at low probability,
+    /// to test monitoring and recovery on staging networks. Never use on production validators
+    #[arg(long = "narwhal.chaos")]
+    pub chaos: bool,
+}
+
+impl Default for NarwhalArgs {
+    fn default() -> Self {
+        Self {
+            committee_file: None,
+            keystore: None,
+            keystore_password_file: None,
+            config: None,
+            worker_count: DEFAULT_NARWHAL_WORKER_COUNT,
+            batch_size: DEFAULT_NARWHAL_BATCH_SIZE,
+            max_batch_delay: DEFAULT_NARWHAL_MAX_BATCH_DELAY,
+            gc_depth: DEFAULT_NARWHAL_GC_DEPTH,
+            self_check: false,
+            target_tps: DEFAULT_NARWHAL_TARGET_TPS,
+            chaos: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// A helper type to parse Args more easily
+    #[derive(Parser)]
+    struct CommandParser<T: Args> {
+        #[command(flatten)]
+        args: T,
+    }
+
+    #[test]
+    fn narwhal_args_default_sanity_check() {
+        let default_args = NarwhalArgs::default();
+        let args = CommandParser::<NarwhalArgs>::parse_from(["reth"]).args;
+        assert_eq!(args, default_args);
+    }
+
+    #[test]
+    fn parse_narwhal_args() {
+        let args = CommandParser::<NarwhalArgs>::parse_from([
+            "reth",
+            "--narwhal.committee-file",
+            "committee.toml",
+            "--narwhal.keystore",
+            "keystore.json",
+            "--narwhal.keystore-password-file",
+            "password.txt",
+            "--narwhal.config",
+            "narwhal.toml",
+            "--narwhal.worker-count",
+            "4",
+            "--narwhal.batch-size",
+            "1000",
+            "--narwhal.max-batch-delay",
+            "1s",
+            "--narwhal.gc-depth",
+            "10",
+            "--narwhal.self-check",
+            "--narwhal.target-tps",
+            "5000",
+            "--narwhal.chaos",
+        ])
+        .args;
+        assert_eq!(
+            args,
+            NarwhalArgs {
+                committee_file: Some(PathBuf::from("committee.toml")),
+                keystore: Some(PathBuf::from("keystore.json")),
+                keystore_password_file: Some(PathBuf::from("password.txt")),
+                config: Some(PathBuf::from("narwhal.toml")),
+                worker_count: 4,
+                batch_size: 1_000,
+                max_batch_delay: Duration::from_secs(1),
+                gc_depth: 10,
+                self_check: true,
+                target_tps: 5_000,
+                chaos: true,
+            }
+        );
+
+        assert!(CommandParser::<NarwhalArgs>::try_parse_from([
+            "reth",
+            "--narwhal.worker-count",
+            "0"
+        ])
+        .is_err());
+
+        let args = CommandParser::<NarwhalArgs>::parse_from([
+            "reth",
+            "--narwhal.keypair-file",
+            "keystore.json",
+        ])
+        .args;
+        assert_eq!(args.keystore, Some(PathBuf::from("keystore.json")));
+    }
+}
@@ -2,8 +2,8 @@
 
 use crate::{
     args::{
-        DatabaseArgs, DatadirArgs, DebugArgs, DevArgs, NetworkArgs, PayloadBuilderArgs,
-        PruningArgs, RpcServerArgs, TxPoolArgs,
+        DatabaseArgs, DatadirArgs, DebugArgs, DevArgs, NarwhalArgs, NetworkArgs,
+        PayloadBuilderArgs, PruningArgs, RpcServerArgs, TxPoolArgs,
     },
     dirs::{ChainPath, DataDirPath},
     utils::get_single_header,
@@ -127,6 +127,9 @@ pub struct NodeConfig {
 
     /// All pruning related arguments
     pub pruning: PruningArgs,
+
+    /// All narwhal related arguments with --narwhal prefix
+    pub narwhal: NarwhalArgs,
 }
 
 impl NodeConfig {
@@ -156,6 +159,13 @@ impl NodeConfig {
         }
     }
 
+    /// Sets --dev mode for the node [`NodeConfig::dev`], with an in-process narwhal committee of
+    /// `committee_size` authorities.
+    pub const fn narwhal(mut self, committee_size: usize) -> Self {
+        self.dev.narwhal_committee_size = Some(committee_size);
+        self.dev()
+    }
+
     /// Set the data directory args for the node
     pub fn with_datadir_args(mut self, datadir_args: DatadirArgs) -> Self {
         self.datadir = datadir_args;
@@ -234,6 +244,12 @@ impl NodeConfig {
         self
     }
 
+    /// Set the narwhal args for the node
+    pub fn with_narwhal(mut self, narwhal: NarwhalArgs) -> Self {
+        self.narwhal = narwhal;
+        self
+    }
+
     /// Returns pruning configuration.
     pub fn prune_config(&self) -> Option<PruneConfig> {
         self.pruning.prune_config(&self.chain)
@@ -412,6 +428,7 @@ impl Default for NodeConfig {
             db: DatabaseArgs::default(),
             dev: DevArgs::default(),
             pruning: PruningArgs::default(),
+            narwhal: NarwhalArgs::default(),
             datadir: DatadirArgs::default(),
         }
     }
@@ -204,6 +204,13 @@ pub mod error;
 pub mod eth;
 pub use eth::EthHandlers;
 
+// Load shedding of expensive requests while catching up
+mod load_shedding;
+pub use load_shedding::{
+    LoadShedder, LoadSheddingConfig, LoadSheddingFuture, LoadSheddingService,
+    CATCHING_UP_ERROR_CODE,
+};
+
 // Rpc server metrics
 mod metrics;
 pub use metrics::{MeteredRequestFuture, RpcRequestMetricsService};
@@ -0,0 +1,198 @@
+use jsonrpsee::{
+    server::middleware::rpc::RpcServiceT,
+    types::{ErrorObject, Request},
+    MethodResponse,
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tower::Layer;
+use tracing::info;
+
+/// Error code of requests that were rejected because the node is catching up.
+///
+/// This is the `limit exceeded` code of EIP-1474, clients are expected to retry later or on
+/// another node.
+pub const CATCHING_UP_ERROR_CODE: i32 = -32005;
+
+/// Configuration of [`LoadShedder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadSheddingConfig {
+    /// Start shedding once the node is more than this many blocks behind.
+    pub max_blocks_behind: u64,
+    /// Resume full service once the node is at most this many blocks behind.
+    pub resume_blocks_behind: u64,
+    /// Prefixes of the expensive methods that are shed.
+    pub methods: Vec<String>,
+}
+
+impl Default for LoadSheddingConfig {
+    fn default() -> Self {
+        Self {
+            max_blocks_behind: 64,
+            resume_blocks_behind: 8,
+            methods: ["trace_", "debug_trace", "eth_getLogs", "ots_"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// Metrics of the [`LoadShedder`].
+#[derive(Metrics)]
+#[metrics(scope = "rpc_server.load_shedding")]
+struct LoadSheddingMetrics {
+    /// The number of requests that were rejected while catching up
+    shed_requests_total: Counter,
+}
+
+/// Rejects expensive RPC requests while the node is far behind, to prioritize catching up.
+///
+/// The sync progress is reported with [`LoadShedder::set_blocks_behind`]. Shedding starts once the
+/// node falls behind by more than [`LoadSheddingConfig::max_blocks_behind`] and stops
+/// automatically once it is back within [`LoadSheddingConfig::resume_blocks_behind`].
+///
+/// Cloning is cheap, all clones share the same state.
+#[derive(Debug, Clone)]
+pub struct LoadShedder {
+    inner: Arc<LoadShedderInner>,
+}
+
+#[derive(Debug)]
+struct LoadShedderInner {
+    config: LoadSheddingConfig,
+    blocks_behind: AtomicU64,
+    shedding: AtomicBool,
+    metrics: LoadSheddingMetrics,
+}
+
+impl LoadShedder {
+    /// Creates a new load shedder that starts out in full service.
+    pub fn new(config: LoadSheddingConfig) -> Self {
+        Self {
+            inner: Arc::new(LoadShedderInner {
+                config,
+                blocks_behind: AtomicU64::new(0),
+                shedding: AtomicBool::new(false),
+                metrics: Default::default(),
+            }),
+        }
+    }
+
+    /// Records how many blocks the node is behind the network.
+    pub fn set_blocks_behind(&self, blocks_behind: u64) {
+        let inner = &self.inner;
+        inner.blocks_behind.store(blocks_behind, Ordering::Relaxed);
+
+        let shedding = inner.shedding.load(Ordering::Relaxed);
+        if !shedding && blocks_behind > inner.config.max_blocks_behind {
+            inner.shedding.store(true, Ordering::Relaxed);
+            info!(target: "rpc", blocks_behind, "Catching up, shedding expensive RPC requests");
+        } else if shedding && blocks_behind <= inner.config.resume_blocks_behind {
+            inner.shedding.store(false, Ordering::Relaxed);
+            info!(target: "rpc", blocks_behind, "Caught up, resuming full RPC service");
+        }
+    }
+
+    /// Returns `true` if expensive requests are currently rejected.
+    pub fn is_shedding(&self) -> bool {
+        self.inner.shedding.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the method is currently rejected.
+    fn should_shed(&self, method: &str) -> bool {
+        self.is_shedding() &&
+            self.inner.config.methods.iter().any(|prefix| method.starts_with(prefix.as_str()))
+    }
+}
+
+impl<S> Layer<S> for LoadShedder {
+    type Service = LoadSheddingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadSheddingService { inner, shedder: self.clone() }
+    }
+}
+
+/// A [`RpcServiceT`] middleware that rejects expensive requests while the node is catching up.
+#[derive(Debug, Clone)]
+pub struct LoadSheddingService<S> {
+    inner: S,
+    shedder: LoadShedder,
+}
+
+impl<'a, S> RpcServiceT<'a> for LoadSheddingService<S>
+where
+    S: RpcServiceT<'a> + Send + Sync + Clone + 'static,
+{
+    type Future = LoadSheddingFuture<S::Future>;
+
+    fn call(&self, req: Request<'a>) -> Self::Future {
+        if !self.shedder.should_shed(req.method.as_ref()) {
+            return LoadSheddingFuture { fut: Some(self.inner.call(req)), rejected: None }
+        }
+
+        self.shedder.inner.metrics.shed_requests_total.increment(1);
+        let blocks_behind = self.shedder.inner.blocks_behind.load(Ordering::Relaxed);
+        let error = ErrorObject::owned(
+            CATCHING_UP_ERROR_CODE,
+            format!("node is catching up ({blocks_behind} blocks behind), try again later"),
+            None::<()>,
+        );
+        LoadSheddingFuture { fut: None, rejected: Some(MethodResponse::error(req.id, error)) }
+    }
+}
+
+/// Response future of [`LoadSheddingService`].
+#[pin_project::pin_project]
+#[derive(Debug)]
+pub struct LoadSheddingFuture<F> {
+    #[pin]
+    fut: Option<F>,
+    /// The response of a rejected request.
+    rejected: Option<MethodResponse>,
+}
+
+impl<F: Future<Output = MethodResponse>> Future for LoadSheddingFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.fut.as_pin_mut() {
+            Some(fut) => fut.poll(cx),
+            None => Poll::Ready(this.rejected.take().expect("polled after completion")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shed_with_hysteresis() {
+        let shedder = LoadShedder::new(LoadSheddingConfig::default());
+        assert!(!shedder.should_shed("eth_getLogs"));
+
+        shedder.set_blocks_behind(65);
+        assert!(shedder.should_shed("eth_getLogs"));
+        assert!(shedder.should_shed("debug_traceTransaction"));
+        assert!(!shedder.should_shed("eth_blockNumber"));
+
+        // still shedding until the node is close to the tip
+        shedder.set_blocks_behind(20);
+        assert!(shedder.is_shedding());
+        shedder.set_blocks_behind(8);
+        assert!(!shedder.is_shedding());
+        shedder.set_blocks_behind(20);
+        assert!(!shedder.is_shedding());
+    }
+}
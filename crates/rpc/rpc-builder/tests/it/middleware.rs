@@ -5,10 +5,14 @@ use jsonrpsee::{
     MethodResponse,
 };
 use reth_rpc::EthApi;
-use reth_rpc_builder::{RpcServerConfig, TransportRpcModuleConfig};
+use reth_rpc_api::EthFilterApiClient;
+use reth_rpc_builder::{
+    LoadShedder, LoadSheddingConfig, RpcServerConfig, TransportRpcModuleConfig,
+    CATCHING_UP_ERROR_CODE,
+};
 use reth_rpc_eth_api::EthApiClient;
 use reth_rpc_server_types::RpcModuleSelection;
-use reth_rpc_types::{Block, Receipt, Transaction};
+use reth_rpc_types::{Block, Filter, Receipt, Transaction};
 use std::{
     future::Future,
     pin::Pin,
@@ -79,3 +83,37 @@ async fn test_rpc_middleware() {
     let count = mylayer.count.load(Ordering::Relaxed);
     assert_eq!(count, 1);
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_load_shedding() {
+    let builder = test_rpc_builder();
+    let modules = builder.build(
+        TransportRpcModuleConfig::set_http(RpcModuleSelection::All),
+        Box::new(EthApi::with_spawner),
+    );
+
+    let shedder = LoadShedder::new(LoadSheddingConfig::default());
+
+    let handle = RpcServerConfig::http(Default::default())
+        .with_http_address(test_address())
+        .set_rpc_middleware(RpcServiceBuilder::new().layer(shedder.clone()))
+        .start(&modules)
+        .await
+        .unwrap();
+    let client = handle.http_client().unwrap();
+
+    shedder.set_blocks_behind(1000);
+    let err =
+        EthFilterApiClient::<Transaction>::logs(&client, Filter::default()).await.unwrap_err();
+    match err {
+        jsonrpsee::core::client::Error::Call(error_obj) => {
+            assert_eq!(error_obj.code(), CATCHING_UP_ERROR_CODE)
+        }
+        err => panic!("unexpected error: {err:?}"),
+    }
+    // cheap requests are still served
+    EthApiClient::<Transaction, Block, Receipt>::protocol_version(&client).await.unwrap();
+
+    shedder.set_blocks_behind(0);
+    EthFilterApiClient::<Transaction>::logs(&client, Filter::default()).await.unwrap();
+}
@@ -0,0 +1,158 @@
+//! Consensus related models and types.
+
+use std::ops::{Range, RangeInclusive};
+
+use crate::{
+    impl_fixed_arbitrary,
+    table::{Decode, Encode},
+    DatabaseError,
+};
+use reth_primitives::BlockNumber;
+use serde::{Deserialize, Serialize};
+
+/// Index of a consensus authority concatenated with a [`BlockNumber`] it led.
+///
+/// Since it's used as a key, it isn't compressed when encoding it.
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Ord, PartialOrd, Hash,
+)]
+pub struct AuthorityBlock(pub (u64, BlockNumber));
+
+impl AuthorityBlock {
+    /// Create a new Range of the blocks of `authority` from `start` to `end`
+    ///
+    /// Note: End is inclusive
+    pub fn range(authority: u64, range: RangeInclusive<BlockNumber>) -> Range<Self> {
+        let end = match range.end().checked_add(1) {
+            Some(end) => (authority, end),
+            None => (authority + 1, 0),
+        };
+        (authority, *range.start()).into()..end.into()
+    }
+
+    /// Return the authority index
+    pub const fn authority(&self) -> u64 {
+        self.0 .0
+    }
+
+    /// Return the block number
+    pub const fn block_number(&self) -> BlockNumber {
+        self.0 .1
+    }
+}
+
+impl From<(u64, BlockNumber)> for AuthorityBlock {
+    fn from(tpl: (u64, BlockNumber)) -> Self {
+        Self(tpl)
+    }
+}
+
+impl Encode for AuthorityBlock {
+    type Encoded = [u8; 16];
+
+    fn encode(self) -> Self::Encoded {
+        let mut buf = [0u8; 16];
+        buf[..8].copy_from_slice(&self.0 .0.to_be_bytes());
+        buf[8..].copy_from_slice(&self.0 .1.to_be_bytes());
+        buf
+    }
+}
+
+impl Decode for AuthorityBlock {
+    fn decode<B: AsRef<[u8]>>(value: B) -> Result<Self, DatabaseError> {
+        let value = value.as_ref();
+        if value.len() != 16 {
+            return Err(DatabaseError::Decode)
+        }
+        let authority = u64::from_be_bytes(value[..8].try_into().unwrap());
+        let number = u64::from_be_bytes(value[8..].try_into().unwrap());
+
+        Ok(Self((authority, number)))
+    }
+}
+
+/// A narwhal round concatenated with the index of a consensus authority.
+///
+/// Keys of the DAG tables, ordered by round so that the DAG can be read and pruned by round.
+#[derive(
+    Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Ord, PartialOrd, Hash,
+)]
+pub struct RoundAuthority(pub (u64, u64));
+
+impl RoundAuthority {
+    /// Return the first key of `round`
+    pub const fn first_of_round(round: u64) -> Self {
+        Self((round, 0))
+    }
+
+    /// Return the round
+    pub const fn round(&self) -> u64 {
+        self.0 .0
+    }
+
+    /// Return the authority index
+    pub const fn authority(&self) -> u64 {
+        self.0 .1
+    }
+}
+
+impl From<(u64, u64)> for RoundAuthority {
+    fn from(tpl: (u64, u64)) -> Self {
+        Self(tpl)
+    }
+}
+
+impl Encode for RoundAuthority {
+    type Encoded = [u8; 16];
+
+    fn encode(self) -> Self::Encoded {
+        let mut buf = [0u8; 16];
+        buf[..8].copy_from_slice(&self.0 .0.to_be_bytes());
+        buf[8..].copy_from_slice(&self.0 .1.to_be_bytes());
+        buf
+    }
+}
+
+impl Decode for RoundAuthority {
+    fn decode<B: AsRef<[u8]>>(value: B) -> Result<Self, DatabaseError> {
+        let value = value.as_ref();
+        if value.len() != 16 {
+            return Err(DatabaseError::Decode)
+        }
+        let round = u64::from_be_bytes(value[..8].try_into().unwrap());
+        let authority = u64::from_be_bytes(value[8..].try_into().unwrap());
+
+        Ok(Self((round, authority)))
+    }
+}
+
+impl_fixed_arbitrary!((AuthorityBlock, 16), (RoundAuthority, 16));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authority_block() {
+        let key = AuthorityBlock((3, 1_000_000));
+        let encoded = Encode::encode(key);
+        assert_eq!(AuthorityBlock::decode(encoded).unwrap(), key);
+
+        // keys of an authority are ordered by block number
+        let next = AuthorityBlock((3, 1_000_001));
+        assert!(Encode::encode(key) < Encode::encode(next));
+        assert!(AuthorityBlock::range(3, 0..=u64::MAX).contains(&key));
+        assert!(!AuthorityBlock::range(4, 0..=u64::MAX).contains(&key));
+    }
+
+    #[test]
+    fn test_round_authority() {
+        let key = RoundAuthority((7, 3));
+        let encoded = Encode::encode(key);
+        assert_eq!(RoundAuthority::decode(encoded).unwrap(), key);
+
+        // keys are ordered by round first
+        assert!(Encode::encode(key) < Encode::encode(RoundAuthority((8, 0))));
+        assert!(Encode::encode(RoundAuthority::first_of_round(7)) < Encode::encode(key));
+    }
+}
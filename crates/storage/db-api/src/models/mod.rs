@@ -14,6 +14,7 @@ use serde::{Deserialize, Serialize};
 pub mod accounts;
 pub mod blocks;
 pub mod client_version;
+pub mod consensus;
 pub mod integer_list;
 pub mod sharded_key;
 pub mod storage_sharded_key;
@@ -21,7 +22,8 @@ pub mod storage_sharded_key;
 pub use accounts::*;
 pub use blocks::*;
 pub use client_version::ClientVersion;
-pub use reth_db_models::{AccountBeforeTx, StoredBlockBodyIndices};
+pub use consensus::{AuthorityBlock, RoundAuthority};
+pub use reth_db_models::{AccountBeforeTx, StoredBlockBodyIndices, StoredConsensusMetadata};
 pub use sharded_key::ShardedKey;
 
 /// Macro that implements [`Encode`] and [`Decode`] for uint types.
@@ -224,6 +226,7 @@ impl_compression_for_compact!(
     PruneCheckpoint,
     ClientVersion,
     Requests,
+    StoredConsensusMetadata,
     // Non-DB
     GenesisAccount
 );
@@ -339,6 +342,7 @@ mod tests {
         assert_eq!(StoredBlockBodyIndices::bitflag_encoded_bytes(), 1);
         assert_eq!(StoredBlockOmmers::bitflag_encoded_bytes(), 0);
         assert_eq!(StoredBlockWithdrawals::bitflag_encoded_bytes(), 0);
+        assert_eq!(StoredConsensusMetadata::bitflag_encoded_bytes(), 4);
         assert_eq!(StorageHashingCheckpoint::bitflag_encoded_bytes(), 1);
         assert_eq!(Withdrawals::bitflag_encoded_bytes(), 0);
     }
@@ -368,6 +372,7 @@ mod tests {
         assert_eq!(StoredBlockBodyIndices::bitflag_encoded_bytes(), 1);
         assert_eq!(StoredBlockOmmers::bitflag_encoded_bytes(), 0);
         assert_eq!(StoredBlockWithdrawals::bitflag_encoded_bytes(), 0);
+        assert_eq!(StoredConsensusMetadata::bitflag_encoded_bytes(), 4);
         assert_eq!(StorageHashingCheckpoint::bitflag_encoded_bytes(), 1);
         assert_eq!(Withdrawals::bitflag_encoded_bytes(), 0);
     }
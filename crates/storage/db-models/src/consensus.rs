@@ -0,0 +1,26 @@
+use reth_codecs::{add_arbitrary_tests, Compact};
+use serde::{Deserialize, Serialize};
+
+/// Metadata of the narwhal commit a block was built from.
+///
+/// It is written when the block is sealed, so explorers and dashboards can attribute blocks to
+/// authorities without reading the consensus store.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize, Deserialize, Compact)]
+#[cfg_attr(any(test, feature = "arbitrary"), derive(arbitrary::Arbitrary))]
+#[add_arbitrary_tests(compact)]
+pub struct StoredConsensusMetadata {
+    /// The index of the leader authority in the committee of its epoch.
+    pub leader: u64,
+    /// The lowest round of the certificates in the commit.
+    pub first_round: u64,
+    /// The round of the leader.
+    pub last_round: u64,
+    /// The number of certificates in the commit.
+    pub certificates: u64,
+    /// The number of batches referenced by the certificates.
+    pub batches: u64,
+    /// The total size of the referenced batches in bytes.
+    pub batch_bytes: u64,
+    /// The epoch of the committee that committed the block.
+    pub epoch: u64,
+}
@@ -7,3 +7,7 @@ pub use accounts::AccountBeforeTx;
 /// Blocks
 pub mod blocks;
 pub use blocks::StoredBlockBodyIndices;
+
+/// Consensus
+pub mod consensus;
+pub use consensus::StoredConsensusMetadata;
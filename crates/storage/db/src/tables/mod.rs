@@ -24,8 +24,10 @@ use reth_db_api::{
         accounts::BlockNumberAddress,
         blocks::{HeaderHash, StoredBlockOmmers},
         client_version::ClientVersion,
+        consensus::{AuthorityBlock, RoundAuthority},
         storage_sharded_key::StorageShardedKey,
         AccountBeforeTx, CompactU256, ShardedKey, StoredBlockBodyIndices, StoredBlockWithdrawals,
+        StoredConsensusMetadata,
     },
     table::{Decode, DupSort, Encode, Table},
 };
@@ -409,6 +411,33 @@ tables! {
 
     /// Stores generic chain state info, like the last finalized block.
     table ChainState<Key = ChainStateKey, Value = BlockNumber>;
+
+    /// Stores the metadata of the narwhal commit each block was built from.
+    table BlockConsensusMetadata<Key = BlockNumber, Value = StoredConsensusMetadata>;
+
+    /// Stores the hashes of the blocks led by each narwhal authority, ordered by block number.
+    table AuthorityBlocks<Key = AuthorityBlock, Value = BlockHash>;
+
+    /// Stores the RLP encoded narwhal certificates of the DAG, by round and author.
+    table NarwhalCertificates<Key = RoundAuthority, Value = Vec<u8>>;
+
+    /// Stores the RLP encoded narwhal batches referenced by the DAG, by batch digest.
+    table NarwhalBatches<Key = B256, Value = Vec<u8>>;
+
+    /// Stores the digest of the header the node voted for, by round and author of the header.
+    table NarwhalVotes<Key = RoundAuthority, Value = B256>;
+
+    /// Stores the round of the last committed certificate of each narwhal authority.
+    table NarwhalLastCommitted<Key = u64, Value = u64>;
+
+    /// Stores the RLP encoded certificate digests of the committed narwhal sub-dags, by index.
+    table NarwhalSubDags<Key = u64, Value = Vec<u8>>;
+
+    /// Stores the index of each committed narwhal sub-dag in `NarwhalSubDags`, by digest.
+    table NarwhalSubDagDigests<Key = B256, Value = u64>;
+
+    /// Stores the RLP encoded entries of the append-only narwhal commit audit log, by index.
+    table NarwhalCommitAudit<Key = u64, Value = Vec<u8>>;
 }
 
 /// Keys for the `ChainState` table.
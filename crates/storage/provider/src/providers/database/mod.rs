@@ -2,14 +2,18 @@ use crate::{
     providers::{state::latest::LatestStateProvider, StaticFileProvider},
     to_range,
     traits::{BlockSource, ReceiptProvider},
-    BlockHashReader, BlockNumReader, BlockReader, ChainSpecProvider, DatabaseProviderFactory,
-    EvmEnvProvider, HeaderProvider, HeaderSyncGap, HeaderSyncGapProvider, ProviderError,
-    PruneCheckpointReader, RequestsProvider, StageCheckpointReader, StateProviderBox,
-    StaticFileProviderFactory, TransactionVariant, TransactionsProvider, WithdrawalsProvider,
+    BlockHashReader, BlockNumReader, BlockReader, ChainSpecProvider, ConsensusMetadataProvider,
+    ConsensusMetadataWriter, DatabaseProviderFactory, EvmEnvProvider, HeaderProvider,
+    HeaderSyncGap, HeaderSyncGapProvider, ProviderError, PruneCheckpointReader, RequestsProvider,
+    StageCheckpointReader, StateProviderBox, StaticFileProviderFactory, TransactionVariant,
+    TransactionsProvider, WithdrawalsProvider,
 };
 use reth_chainspec::{ChainInfo, ChainSpec, EthChainSpec};
 use reth_db::{init_db, mdbx::DatabaseArguments, DatabaseEnv};
-use reth_db_api::{database::Database, models::StoredBlockBodyIndices};
+use reth_db_api::{
+    database::Database,
+    models::{StoredBlockBodyIndices, StoredConsensusMetadata},
+};
 use reth_errors::{RethError, RethResult};
 use reth_evm::ConfigureEvmEnv;
 use reth_primitives::{
@@ -504,6 +508,37 @@ where
     }
 }
 
+impl<DB: Database> ConsensusMetadataProvider for ProviderFactory<DB> {
+    fn consensus_metadata(
+        &self,
+        number: BlockNumber,
+    ) -> ProviderResult<Option<StoredConsensusMetadata>> {
+        self.provider()?.consensus_metadata(number)
+    }
+
+    fn blocks_by_authority(
+        &self,
+        authority: u64,
+        range: RangeInclusive<BlockNumber>,
+    ) -> ProviderResult<Vec<(BlockNumber, BlockHash)>> {
+        self.provider()?.blocks_by_authority(authority, range)
+    }
+}
+
+impl<DB: Database> ConsensusMetadataWriter for ProviderFactory<DB> {
+    fn insert_consensus_metadata(
+        &self,
+        number: BlockNumber,
+        hash: BlockHash,
+        metadata: StoredConsensusMetadata,
+    ) -> ProviderResult<()> {
+        let provider = self.provider_rw()?;
+        provider.insert_consensus_metadata(number, hash, metadata)?;
+        provider.commit()?;
+        Ok(())
+    }
+}
+
 impl<DB: Database> StageCheckpointReader for ProviderFactory<DB> {
     fn get_stage_checkpoint(&self, id: StageId) -> ProviderResult<Option<StageCheckpoint>> {
         self.provider()?.get_stage_checkpoint(id)
@@ -610,7 +645,8 @@ mod tests {
     use crate::{
         providers::{StaticFileProvider, StaticFileWriter},
         test_utils::{blocks::TEST_BLOCK, create_test_provider_factory},
-        BlockHashReader, BlockNumReader, BlockWriter, HeaderSyncGapProvider, TransactionsProvider,
+        BlockHashReader, BlockNumReader, BlockWriter, ConsensusMetadataWriter,
+        HeaderSyncGapProvider, TransactionsProvider,
     };
     use assert_matches::assert_matches;
     use rand::Rng;
@@ -620,6 +656,7 @@ mod tests {
         tables,
         test_utils::{create_test_static_files_dir, ERROR_TEMPDIR},
     };
+    use reth_db_api::models::StoredConsensusMetadata;
     use reth_primitives::{StaticFileSegment, TxNumber, B256, U256};
     use reth_prune_types::{PruneMode, PruneModes};
     use reth_storage_errors::provider::ProviderError;
@@ -780,4 +817,51 @@ mod tests {
         assert_eq!(gap.local_head, head);
         assert_eq!(gap.target.tip(), consensus_tip.into());
     }
+
+    #[test]
+    fn consensus_metadata_by_authority() {
+        let factory = create_test_provider_factory();
+        let provider = factory.provider_rw().unwrap();
+
+        let metadata = |leader| StoredConsensusMetadata { leader, ..Default::default() };
+        for (number, leader) in [(1, 0), (2, 1), (3, 0), (4, 2), (5, 0)] {
+            provider
+                .insert_consensus_metadata(
+                    number,
+                    B256::with_last_byte(number as u8),
+                    metadata(leader),
+                )
+                .unwrap();
+        }
+
+        assert_eq!(provider.consensus_metadata(2).unwrap(), Some(metadata(1)));
+        assert_eq!(
+            provider.blocks_by_authority(0, 2..=5).unwrap(),
+            vec![(3, B256::with_last_byte(3)), (5, B256::with_last_byte(5))]
+        );
+        assert_eq!(provider.blocks_by_authority(3, 0..=u64::MAX).unwrap(), vec![]);
+
+        // unwinding removes the blocks from the index
+        provider.remove_consensus_metadata_range(4..).unwrap();
+        assert_eq!(provider.consensus_metadata(5).unwrap(), None);
+        assert_eq!(
+            provider.blocks_by_authority(0, 0..=u64::MAX).unwrap(),
+            vec![(1, B256::with_last_byte(1)), (3, B256::with_last_byte(3))]
+        );
+        assert_eq!(provider.blocks_by_authority(2, 0..=u64::MAX).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn consensus_metadata_is_committed() {
+        let factory = create_test_provider_factory();
+        let metadata = StoredConsensusMetadata { leader: 3, epoch: 1, ..Default::default() };
+        factory.insert_consensus_metadata(7, B256::with_last_byte(7), metadata.clone()).unwrap();
+
+        // the factory commits the write, so a new transaction reads it back
+        assert_eq!(factory.consensus_metadata(7).unwrap(), Some(metadata));
+        assert_eq!(
+            factory.blocks_by_authority(3, 0..=10).unwrap(),
+            vec![(7, B256::with_last_byte(7))]
+        );
+    }
 }
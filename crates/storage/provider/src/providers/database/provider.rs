@@ -7,13 +7,14 @@ use crate::{
     },
     writer::UnifiedStorageWriter,
     AccountReader, BlockExecutionReader, BlockExecutionWriter, BlockHashReader, BlockNumReader,
-    BlockReader, BlockWriter, BundleStateInit, EvmEnvProvider, FinalizedBlockReader,
-    FinalizedBlockWriter, HashingWriter, HeaderProvider, HeaderSyncGap, HeaderSyncGapProvider,
-    HistoricalStateProvider, HistoryWriter, LatestStateProvider, OriginalValuesKnown,
-    ProviderError, PruneCheckpointReader, PruneCheckpointWriter, RequestsProvider, RevertsInit,
-    StageCheckpointReader, StateChangeWriter, StateProviderBox, StateReader, StateWriter,
-    StatsReader, StorageReader, StorageTrieWriter, TransactionVariant, TransactionsProvider,
-    TransactionsProviderExt, TrieWriter, WithdrawalsProvider,
+    BlockReader, BlockWriter, BundleStateInit, ConsensusMetadataProvider, ConsensusMetadataWriter,
+    EvmEnvProvider, FinalizedBlockReader, FinalizedBlockWriter, HashingWriter, HeaderProvider,
+    HeaderSyncGap, HeaderSyncGapProvider, HistoricalStateProvider, HistoryWriter,
+    LatestStateProvider, OriginalValuesKnown, ProviderError, PruneCheckpointReader,
+    PruneCheckpointWriter, RequestsProvider, RevertsInit, StageCheckpointReader, StateChangeWriter,
+    StateProviderBox, StateReader, StateWriter, StatsReader, StorageReader, StorageTrieWriter,
+    TransactionVariant, TransactionsProvider, TransactionsProviderExt, TrieWriter,
+    WithdrawalsProvider,
 };
 use itertools::{izip, Itertools};
 use rayon::slice::ParallelSliceMut;
@@ -26,8 +27,9 @@ use reth_db_api::{
     cursor::{DbCursorRO, DbCursorRW, DbDupCursorRO, RangeWalker},
     database::Database,
     models::{
-        sharded_key, storage_sharded_key::StorageShardedKey, AccountBeforeTx, BlockNumberAddress,
-        ShardedKey, StoredBlockBodyIndices, StoredBlockOmmers, StoredBlockWithdrawals,
+        sharded_key, storage_sharded_key::StorageShardedKey, AccountBeforeTx, AuthorityBlock,
+        BlockNumberAddress, ShardedKey, StoredBlockBodyIndices, StoredBlockOmmers,
+        StoredBlockWithdrawals, StoredConsensusMetadata,
     },
     table::{Table, TableRow},
     transaction::{DbTx, DbTxMut},
@@ -1316,6 +1318,22 @@ impl<TX: DbTxMut + DbTx> DatabaseProvider<TX> {
         Ok(block_tx)
     }
 
+    /// Remove the consensus metadata of the given range of blocks from
+    /// [`BlockConsensusMetadata`](tables::BlockConsensusMetadata), and the blocks from the index
+    /// of their leaders in [`AuthorityBlocks`](tables::AuthorityBlocks).
+    pub fn remove_consensus_metadata_range(
+        &self,
+        range: impl RangeBounds<BlockNumber>,
+    ) -> ProviderResult<()> {
+        for (number, metadata) in self.take::<tables::BlockConsensusMetadata>(range)? {
+            self.tx.delete::<tables::AuthorityBlocks>(
+                AuthorityBlock((metadata.leader, number)),
+                None,
+            )?;
+        }
+        Ok(())
+    }
+
     /// Remove the given range of blocks, without returning any of the blocks.
     ///
     /// This will remove block data for the given range from the following tables:
@@ -1327,7 +1345,9 @@ impl<TX: DbTxMut + DbTx> DatabaseProvider<TX> {
     /// * [`HeaderTerminalDifficulties`](tables::HeaderTerminalDifficulties)
     ///
     /// This will also remove transaction data according to
-    /// [`remove_block_transaction_range`](Self::remove_block_transaction_range).
+    /// [`remove_block_transaction_range`](Self::remove_block_transaction_range), and consensus
+    /// metadata according to
+    /// [`remove_consensus_metadata_range`](Self::remove_consensus_metadata_range).
     pub fn remove_block_range(
         &self,
         range: impl RangeBounds<BlockNumber> + Clone,
@@ -1345,6 +1365,7 @@ impl<TX: DbTxMut + DbTx> DatabaseProvider<TX> {
         self.remove::<tables::BlockWithdrawals>(range.clone())?;
         self.remove::<tables::BlockRequests>(range.clone())?;
         self.remove_block_transaction_range(range.clone())?;
+        self.remove_consensus_metadata_range(range.clone())?;
         self.remove::<tables::HeaderTerminalDifficulties>(range)?;
 
         Ok(())
@@ -1361,7 +1382,9 @@ impl<TX: DbTxMut + DbTx> DatabaseProvider<TX> {
     /// * [`HeaderTerminalDifficulties`](tables::HeaderTerminalDifficulties)
     ///
     /// This will also remove transaction data according to
-    /// [`take_block_transaction_range`](Self::take_block_transaction_range).
+    /// [`take_block_transaction_range`](Self::take_block_transaction_range), and consensus
+    /// metadata according to
+    /// [`remove_consensus_metadata_range`](Self::remove_consensus_metadata_range).
     pub fn take_block_range(
         &self,
         range: impl RangeBounds<BlockNumber> + Clone,
@@ -1388,6 +1411,7 @@ impl<TX: DbTxMut + DbTx> DatabaseProvider<TX> {
         let block_withdrawals = self.take::<tables::BlockWithdrawals>(range.clone())?;
         let block_requests = self.take::<tables::BlockRequests>(range.clone())?;
         let block_tx = self.take_block_transaction_range(range.clone())?;
+        self.remove_consensus_metadata_range(range.clone())?;
 
         // rm HeaderTerminalDifficulties
         self.remove::<tables::HeaderTerminalDifficulties>(range)?;
@@ -3663,6 +3687,41 @@ impl<TX: DbTxMut> PruneCheckpointWriter for DatabaseProvider<TX> {
     }
 }
 
+impl<TX: DbTx> ConsensusMetadataProvider for DatabaseProvider<TX> {
+    fn consensus_metadata(
+        &self,
+        number: BlockNumber,
+    ) -> ProviderResult<Option<StoredConsensusMetadata>> {
+        Ok(self.tx.get::<tables::BlockConsensusMetadata>(number)?)
+    }
+
+    fn blocks_by_authority(
+        &self,
+        authority: u64,
+        range: RangeInclusive<BlockNumber>,
+    ) -> ProviderResult<Vec<(BlockNumber, BlockHash)>> {
+        self.tx
+            .cursor_read::<tables::AuthorityBlocks>()?
+            .walk_range(AuthorityBlock::range(authority, range))?
+            .map(|entry| entry.map(|(key, hash)| (key.block_number(), hash)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Into::into)
+    }
+}
+
+impl<TX: DbTxMut> ConsensusMetadataWriter for DatabaseProvider<TX> {
+    fn insert_consensus_metadata(
+        &self,
+        number: BlockNumber,
+        hash: BlockHash,
+        metadata: StoredConsensusMetadata,
+    ) -> ProviderResult<()> {
+        self.tx.put::<tables::AuthorityBlocks>(AuthorityBlock((metadata.leader, number)), hash)?;
+        self.tx.put::<tables::BlockConsensusMetadata>(number, metadata)?;
+        Ok(())
+    }
+}
+
 impl<TX: DbTx> StatsReader for DatabaseProvider<TX> {
     fn count_entries<T: Table>(&self) -> ProviderResult<usize> {
         let db_entries = self.tx.entries::<T>()?;
@@ -1,11 +1,12 @@
 use crate::{
     AccountReader, BlockHashReader, BlockIdReader, BlockNumReader, BlockReader, BlockReaderIdExt,
     BlockSource, BlockchainTreePendingStateProvider, CanonChainTracker, CanonStateNotifications,
-    CanonStateSubscriptions, ChainSpecProvider, ChangeSetReader, DatabaseProviderFactory,
-    EvmEnvProvider, FinalizedBlockReader, FullExecutionDataProvider, HeaderProvider, ProviderError,
-    PruneCheckpointReader, ReceiptProvider, ReceiptProviderIdExt, RequestsProvider,
-    StageCheckpointReader, StateProviderBox, StateProviderFactory, StaticFileProviderFactory,
-    TransactionVariant, TransactionsProvider, TreeViewer, WithdrawalsProvider,
+    CanonStateSubscriptions, ChainSpecProvider, ChangeSetReader, ConsensusMetadataProvider,
+    ConsensusMetadataWriter, DatabaseProviderFactory, EvmEnvProvider, FinalizedBlockReader,
+    FullExecutionDataProvider, HeaderProvider, ProviderError, PruneCheckpointReader,
+    ReceiptProvider, ReceiptProviderIdExt, RequestsProvider, StageCheckpointReader,
+    StateProviderBox, StateProviderFactory, StaticFileProviderFactory, TransactionVariant,
+    TransactionsProvider, TreeViewer, WithdrawalsProvider,
 };
 use reth_blockchain_tree_api::{
     error::{CanonicalError, InsertBlockError},
@@ -16,7 +17,7 @@ use reth_chain_state::{ChainInfoTracker, ForkChoiceNotifications, ForkChoiceSubs
 use reth_chainspec::{ChainInfo, ChainSpec, EthChainSpec};
 use reth_db_api::{
     database::Database,
-    models::{AccountBeforeTx, StoredBlockBodyIndices},
+    models::{AccountBeforeTx, StoredBlockBodyIndices, StoredConsensusMetadata},
 };
 use reth_evm::ConfigureEvmEnv;
 use reth_primitives::{
@@ -542,6 +543,40 @@ where
     }
 }
 
+impl<DB> ConsensusMetadataProvider for BlockchainProvider<DB>
+where
+    DB: Database,
+{
+    fn consensus_metadata(
+        &self,
+        number: BlockNumber,
+    ) -> ProviderResult<Option<StoredConsensusMetadata>> {
+        self.database.consensus_metadata(number)
+    }
+
+    fn blocks_by_authority(
+        &self,
+        authority: u64,
+        range: RangeInclusive<BlockNumber>,
+    ) -> ProviderResult<Vec<(BlockNumber, BlockHash)>> {
+        self.database.blocks_by_authority(authority, range)
+    }
+}
+
+impl<DB> ConsensusMetadataWriter for BlockchainProvider<DB>
+where
+    DB: Database,
+{
+    fn insert_consensus_metadata(
+        &self,
+        number: BlockNumber,
+        hash: BlockHash,
+        metadata: StoredConsensusMetadata,
+    ) -> ProviderResult<()> {
+        self.database.insert_consensus_metadata(number, hash, metadata)
+    }
+}
+
 impl<DB> EvmEnvProvider for BlockchainProvider<DB>
 where
     DB: Database,
@@ -1,13 +1,14 @@
 use crate::{
     traits::{BlockSource, ReceiptProvider},
     AccountReader, BlockHashReader, BlockIdReader, BlockNumReader, BlockReader, BlockReaderIdExt,
-    ChainSpecProvider, ChangeSetReader, EvmEnvProvider, HeaderProvider, ReceiptProviderIdExt,
-    RequestsProvider, StateProvider, StateProviderBox, StateProviderFactory, StateRootProvider,
-    TransactionVariant, TransactionsProvider, WithdrawalsProvider,
+    ChainSpecProvider, ChangeSetReader, ConsensusMetadataProvider, ConsensusMetadataWriter,
+    EvmEnvProvider, HeaderProvider, ReceiptProviderIdExt, RequestsProvider, StateProvider,
+    StateProviderBox, StateProviderFactory, StateRootProvider, TransactionVariant,
+    TransactionsProvider, WithdrawalsProvider,
 };
 use parking_lot::Mutex;
 use reth_chainspec::{ChainInfo, ChainSpec};
-use reth_db_api::models::{AccountBeforeTx, StoredBlockBodyIndices};
+use reth_db_api::models::{AccountBeforeTx, StoredBlockBodyIndices, StoredConsensusMetadata};
 use reth_evm::ConfigureEvmEnv;
 use reth_primitives::{
     keccak256, Account, Address, Block, BlockHash, BlockHashOrNumber, BlockId, BlockNumber,
@@ -43,6 +44,8 @@ pub struct MockEthProvider {
     pub chain_spec: Arc<ChainSpec>,
     /// Local state roots
     pub state_roots: Arc<Mutex<Vec<B256>>>,
+    /// Local consensus metadata store, by block number
+    pub consensus_metadata: Arc<Mutex<BTreeMap<BlockNumber, (BlockHash, StoredConsensusMetadata)>>>,
 }
 
 impl Default for MockEthProvider {
@@ -53,6 +56,7 @@ impl Default for MockEthProvider {
             accounts: Default::default(),
             chain_spec: Arc::new(reth_chainspec::ChainSpecBuilder::mainnet().build()),
             state_roots: Default::default(),
+            consensus_metadata: Default::default(),
         }
     }
 }
@@ -567,6 +571,41 @@ impl StageCheckpointReader for MockEthProvider {
     }
 }
 
+impl ConsensusMetadataProvider for MockEthProvider {
+    fn consensus_metadata(
+        &self,
+        number: BlockNumber,
+    ) -> ProviderResult<Option<StoredConsensusMetadata>> {
+        Ok(self.consensus_metadata.lock().get(&number).map(|(_, metadata)| metadata.clone()))
+    }
+
+    fn blocks_by_authority(
+        &self,
+        authority: u64,
+        range: RangeInclusive<BlockNumber>,
+    ) -> ProviderResult<Vec<(BlockNumber, BlockHash)>> {
+        Ok(self
+            .consensus_metadata
+            .lock()
+            .range(range)
+            .filter(|(_, (_, metadata))| metadata.leader == authority)
+            .map(|(number, (hash, _))| (*number, *hash))
+            .collect())
+    }
+}
+
+impl ConsensusMetadataWriter for MockEthProvider {
+    fn insert_consensus_metadata(
+        &self,
+        number: BlockNumber,
+        hash: BlockHash,
+        metadata: StoredConsensusMetadata,
+    ) -> ProviderResult<()> {
+        self.consensus_metadata.lock().insert(number, (hash, metadata));
+        Ok(())
+    }
+}
+
 impl StateRootProvider for MockEthProvider {
     fn state_root(&self, _state: HashedPostState) -> ProviderResult<B256> {
         Ok(self.state_roots.lock().pop().unwrap_or_default())
@@ -0,0 +1,34 @@
+use reth_db_models::StoredConsensusMetadata;
+use reth_primitives::{BlockHash, BlockNumber};
+use reth_storage_errors::provider::ProviderResult;
+use std::ops::RangeInclusive;
+
+/// Client trait for fetching the narwhal consensus metadata of blocks.
+#[auto_impl::auto_impl(&, Arc)]
+pub trait ConsensusMetadataProvider: Send + Sync {
+    /// Get the metadata of the commit the block was built from.
+    fn consensus_metadata(
+        &self,
+        number: BlockNumber,
+    ) -> ProviderResult<Option<StoredConsensusMetadata>>;
+
+    /// Get the numbers and hashes of the blocks in the range that were led by the authority.
+    fn blocks_by_authority(
+        &self,
+        authority: u64,
+        range: RangeInclusive<BlockNumber>,
+    ) -> ProviderResult<Vec<(BlockNumber, BlockHash)>>;
+}
+
+/// Consensus metadata writer
+#[auto_impl::auto_impl(&, Arc)]
+pub trait ConsensusMetadataWriter: Send + Sync {
+    /// Writes the metadata of the commit the block was built from, and indexes the block by its
+    /// leader.
+    fn insert_consensus_metadata(
+        &self,
+        number: BlockNumber,
+        hash: BlockHash,
+        metadata: StoredConsensusMetadata,
+    ) -> ProviderResult<()>;
+}
@@ -22,6 +22,9 @@ pub use block_id::*;
 mod block_hash;
 pub use block_hash::*;
 
+mod consensus;
+pub use consensus::*;
+
 mod header;
 pub use header::*;
 
@@ -13,7 +13,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
 use crate::{
-    metrics::{IncCounterOnDrop, TaskExecutorMetrics},
+    metrics::{IncCounterOnDrop, MeteredTask, TaskExecutorMetrics},
     shutdown::{signal, GracefulShutdown, GracefulShutdownGuard, Shutdown, Signal},
 };
 use dyn_clone::DynClone;
@@ -442,6 +442,20 @@ impl TaskExecutor {
         self.spawn_critical_as(name, fut, TaskKind::Default)
     }
 
+    /// This spawns a critical task onto the runtime and records its
+    /// [`TaskPollMetrics`](metrics::TaskPollMetrics), labeled with the name of the task.
+    /// The given future resolves as soon as the [Shutdown] signal is received.
+    ///
+    /// If this task panics, the [`TaskManager`] is notified.
+    ///
+    /// See also [`MeteredTask`] to record the metrics of tasks spawned with other methods.
+    pub fn spawn_critical_metered<F>(&self, name: &'static str, fut: F) -> JoinHandle<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.spawn_critical_as(name, MeteredTask::new(name, fut), TaskKind::Default)
+    }
+
     /// This spawns a critical task onto the runtime.
     ///
     /// If this task panics, the [`TaskManager`] is notified.
@@ -673,6 +687,24 @@ mod tests {
     }
 
     // Tests that spawned tasks are terminated if the `TaskManager` drops
+    #[test]
+    fn test_critical_metered() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let handle = runtime.handle().clone();
+        let manager = TaskManager::new(handle);
+        let executor = manager.executor();
+
+        assert_eq!(runtime.block_on(MeteredTask::new("metered task", async { 1 })), 1);
+
+        executor.spawn_critical_metered("metered task", async { panic!("intentionally panic") });
+
+        runtime.block_on(async move {
+            let err = manager.await;
+            assert_eq!(err.task_name, "metered task");
+            assert_eq!(err.error, Some("intentionally panic".to_string()));
+        })
+    }
+
     #[test]
     fn test_manager_shutdown_critical() {
         let runtime = tokio::runtime::Runtime::new().unwrap();
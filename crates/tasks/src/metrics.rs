@@ -1,8 +1,18 @@
 //! Task Executor Metrics
 
 use core::fmt;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
 
-use reth_metrics::{metrics::Counter, Metrics};
+use pin_project::pin_project;
+use reth_metrics::{
+    metrics::{Counter, Gauge, Histogram},
+    Metrics,
+};
 
 /// Task Executor Metrics
 #[derive(Metrics, Clone)]
@@ -52,3 +62,57 @@ impl Drop for IncCounterOnDrop {
         self.0.increment(1);
     }
 }
+
+/// Poll metrics of a single task, labeled with the name of the task.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "executor.task")]
+pub struct TaskPollMetrics {
+    /// Number of times the task was polled
+    pub(crate) polls_total: Counter,
+    /// Duration of a single poll of the task
+    pub(crate) poll_duration_seconds: Histogram,
+    /// Total time the task spent being polled, i.e. keeping a runtime thread busy
+    pub(crate) busy_seconds_total: Gauge,
+}
+
+/// A future that records the [`TaskPollMetrics`] of the task it is spawned as.
+///
+/// This makes it possible to tell which task of a component that runs as multiple tasks is the CPU
+/// bottleneck. Recording adds two clock reads to every poll, so this is meant for long running
+/// tasks.
+#[pin_project]
+pub struct MeteredTask<F> {
+    #[pin]
+    fut: F,
+    metrics: TaskPollMetrics,
+}
+
+impl<F> MeteredTask<F> {
+    /// Wraps the future of the task with the given name.
+    pub fn new(name: &'static str, fut: F) -> Self {
+        Self { fut, metrics: TaskPollMetrics::new_with_labels(&[("task", name)]) }
+    }
+}
+
+impl<F> fmt::Debug for MeteredTask<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MeteredTask").finish_non_exhaustive()
+    }
+}
+
+impl<F: Future> Future for MeteredTask<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let start = Instant::now();
+        let poll = this.fut.poll(cx);
+        let elapsed = start.elapsed().as_secs_f64();
+
+        this.metrics.polls_total.increment(1);
+        this.metrics.poll_duration_seconds.record(elapsed);
+        this.metrics.busy_seconds_total.increment(elapsed);
+
+        poll
+    }
+}
@@ -7,10 +7,15 @@ use futures_util::{
 use std::{
     future::Future,
     pin::Pin,
-    sync::{atomic::AtomicUsize, Arc},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
     task::{ready, Context, Poll},
+    time::Duration,
 };
-use tokio::sync::oneshot;
+use tokio::{sync::oneshot, time::Instant};
+use tracing::{debug, warn};
 
 /// A Future that resolves when the shutdown event has been fired.
 #[derive(Debug)]
@@ -103,11 +108,81 @@ pub fn signal() -> (Signal, Shutdown) {
     (Signal(sender), Shutdown(receiver.shared()))
 }
 
+/// Shuts down groups of tasks one after another.
+///
+/// Every stage is a group of tasks that listen on the [`GracefulShutdown`] of that stage, see
+/// [`StagedShutdown::on_shutdown`]. The stages are signaled in order, and a stage is only signaled
+/// once all tasks of the previous stages released their [`GracefulShutdownGuard`]. This allows a
+/// pipeline of tasks to be drained front to back, e.g. to stop accepting new work before the tasks
+/// that process it are stopped.
+#[derive(Debug)]
+pub struct StagedShutdown {
+    stages: Vec<ShutdownStage>,
+}
+
+#[derive(Debug)]
+struct ShutdownStage {
+    signal: Signal,
+    on_shutdown: Shutdown,
+    tasks: Arc<AtomicUsize>,
+}
+
+impl StagedShutdown {
+    /// How often the tasks of a stage are checked while waiting for them to shut down.
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Creates a new instance with the given number of stages.
+    pub fn new(stages: usize) -> Self {
+        let stages = (0..stages)
+            .map(|_| {
+                let (signal, on_shutdown) = signal();
+                ShutdownStage { signal, on_shutdown, tasks: Arc::new(AtomicUsize::new(0)) }
+            })
+            .collect();
+        Self { stages }
+    }
+
+    /// Returns the shutdown signal of the given stage.
+    ///
+    /// # Panics
+    ///
+    /// If the stage does not exist.
+    pub fn on_shutdown(&self, stage: usize) -> GracefulShutdown {
+        let stage = &self.stages[stage];
+        GracefulShutdown::new(
+            stage.on_shutdown.clone(),
+            GracefulShutdownGuard::new(Arc::clone(&stage.tasks)),
+        )
+    }
+
+    /// Shuts down all stages in order.
+    ///
+    /// If the tasks don't shut down within the timeout, all remaining stages are signaled at once
+    /// and `false` is returned.
+    pub async fn shutdown(self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut stages = self.stages.into_iter().enumerate();
+        while let Some((index, stage)) = stages.next() {
+            stage.signal.fire();
+            while stage.tasks.load(Ordering::Relaxed) > 0 {
+                if Instant::now() >= deadline {
+                    warn!(target: "tasks", stage = index, "staged shutdown timed out, aborting");
+                    stages.for_each(|(_, stage)| stage.signal.fire());
+                    return false
+                }
+                tokio::time::sleep(Self::POLL_INTERVAL).await;
+            }
+            debug!(target: "tasks", stage = index, "shutdown stage completed");
+        }
+        true
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use futures_util::future::join_all;
-    use std::time::Duration;
+    use std::sync::Mutex;
 
     #[tokio::test(flavor = "multi_thread")]
     async fn test_shutdown() {
@@ -155,4 +230,38 @@ mod tests {
 
         shutdown.await;
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_staged_shutdown() {
+        let shutdown = StagedShutdown::new(3);
+        let stopped = Arc::new(Mutex::new(Vec::new()));
+
+        // spawn in reverse order, the stages must be stopped in order nevertheless
+        for stage in (0..3).rev() {
+            let on_shutdown = shutdown.on_shutdown(stage);
+            let stopped = Arc::clone(&stopped);
+            tokio::task::spawn(async move {
+                let guard = on_shutdown.await;
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                stopped.lock().unwrap().push(stage);
+                drop(guard);
+            });
+        }
+
+        assert!(shutdown.shutdown(Duration::from_secs(10)).await);
+        assert_eq!(*stopped.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_staged_shutdown_timeout() {
+        let shutdown = StagedShutdown::new(2);
+
+        let stuck = shutdown.on_shutdown(0);
+        let last = tokio::task::spawn(shutdown.on_shutdown(1).ignore_guard());
+
+        assert!(!shutdown.shutdown(Duration::from_millis(50)).await);
+        // the remaining stages are signaled regardless
+        last.await.unwrap();
+        drop(stuck);
+    }
 }
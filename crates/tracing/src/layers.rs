@@ -1,8 +1,11 @@
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use rolling_file::{RollingConditionBasic, RollingFileAppender};
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::{filter::Directive, EnvFilter, Layer, Registry};
+use tracing_subscriber::{filter::Directive, reload, EnvFilter, Layer, Registry};
 
 use crate::formatter::LogFormat;
 
@@ -33,12 +36,13 @@ const DEFAULT_ENV_FILTER_DIRECTIVES: [&str; 5] = [
 /// Each layer can be configured separately and then combined into a tracing subscriber.
 pub(crate) struct Layers {
     inner: Vec<BoxedLayer<Registry>>,
+    filters: Vec<ReloadableFilter>,
 }
 
 impl Layers {
     /// Creates a new `Layers` instance.
     pub(crate) fn new() -> Self {
-        Self { inner: vec![] }
+        Self { inner: vec![], filters: vec![] }
     }
 
     /// Consumes the `Layers` instance, returning the inner vector of layers.
@@ -46,6 +50,11 @@ impl Layers {
         self.inner
     }
 
+    /// Returns a handle to change the filters of the stdout and file layers at runtime.
+    pub(crate) fn filter_handle(&self) -> LogFilterHandle {
+        LogFilterHandle { filters: Arc::new(self.filters.clone()) }
+    }
+
     /// Adds a journald layer to the layers collection.
     ///
     /// # Arguments
@@ -80,7 +89,13 @@ impl Layers {
         filters: &str,
         color: Option<String>,
     ) -> eyre::Result<()> {
-        let filter = build_env_filter(Some(default_directive), filters)?;
+        let filter = build_env_filter(Some(default_directive.clone()), filters)?;
+        let (filter, handle) = reload::Layer::new(filter);
+        self.filters.push(ReloadableFilter {
+            handle,
+            default_directive: Some(default_directive),
+            directives: filters.to_string(),
+        });
         let layer = format.apply(filter, color, None);
         self.inner.push(layer.boxed());
         Ok(())
@@ -103,12 +118,63 @@ impl Layers {
     ) -> eyre::Result<FileWorkerGuard> {
         let (writer, guard) = file_info.create_log_writer();
         let file_filter = build_env_filter(None, filter)?;
+        let (file_filter, handle) = reload::Layer::new(file_filter);
+        self.filters.push(ReloadableFilter {
+            handle,
+            default_directive: None,
+            directives: filter.to_string(),
+        });
         let layer = format.apply(file_filter, None, Some(writer));
         self.inner.push(layer);
         Ok(guard)
     }
 }
 
+/// The filter of a layer that can be replaced at runtime.
+#[derive(Debug, Clone)]
+struct ReloadableFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// The default directive the layer was configured with.
+    default_directive: Option<Directive>,
+    /// The directives the layer was configured with.
+    directives: String,
+}
+
+/// A handle to change the filters of the stdout and file layers at runtime, e.g. to raise the
+/// verbosity of a component during an incident without restarting the node.
+///
+/// Cloning is cheap, all clones change the same filters.
+#[derive(Debug, Clone)]
+pub struct LogFilterHandle {
+    filters: Arc<Vec<ReloadableFilter>>,
+}
+
+impl LogFilterHandle {
+    /// Adds the comma-separated directives to the filters the layers were configured with,
+    /// replacing the directives added before.
+    ///
+    /// The filters are left unchanged if any directive is invalid.
+    pub fn set_directives(&self, directives: &str) -> eyre::Result<()> {
+        let filters = self
+            .filters
+            .iter()
+            .map(|filter| {
+                let directives = format!("{},{directives}", filter.directives);
+                build_env_filter(filter.default_directive.clone(), &directives)
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        for (filter, reloaded) in self.filters.iter().zip(filters) {
+            filter.handle.reload(reloaded)?;
+        }
+        Ok(())
+    }
+
+    /// Restores the filters the layers were configured with.
+    pub fn reset(&self) -> eyre::Result<()> {
+        self.set_directives("")
+    }
+}
+
 /// Holds configuration information for file logging.
 ///
 /// Contains details about the log file's path, name, size, and rotation strategy.
@@ -50,7 +50,7 @@ pub use tracing_subscriber;
 
 // Re-export our types
 pub use formatter::LogFormat;
-pub use layers::{FileInfo, FileWorkerGuard};
+pub use layers::{FileInfo, FileWorkerGuard, LogFilterHandle};
 pub use test_tracer::TestTracer;
 
 mod formatter;
@@ -58,6 +58,7 @@ mod layers;
 mod test_tracer;
 
 use crate::layers::Layers;
+use std::sync::OnceLock;
 use tracing::level_filters::LevelFilter;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -212,11 +213,23 @@ impl Tracer for RethTracer {
 
         // The error is returned if the global default subscriber is already set,
         // so it's safe to ignore it
-        let _ = tracing_subscriber::registry().with(layers.into_inner()).try_init();
+        let filter_handle = layers.filter_handle();
+        if tracing_subscriber::registry().with(layers.into_inner()).try_init().is_ok() {
+            let _ = LOG_FILTER_HANDLE.set(filter_handle);
+        }
         Ok(file_guard)
     }
 }
 
+/// The filter handle of the subscriber installed by [`RethTracer::init`].
+static LOG_FILTER_HANDLE: OnceLock<LogFilterHandle> = OnceLock::new();
+
+/// Returns the handle to change the log filters at runtime, if the global subscriber was installed
+/// by [`RethTracer::init`].
+pub fn log_filter_handle() -> Option<LogFilterHandle> {
+    LOG_FILTER_HANDLE.get().cloned()
+}
+
 ///  Initializes a tracing subscriber for tests.
 ///
 ///  The filter is configurable via `RUST_LOG`.
@@ -0,0 +1,100 @@
+//! Example of a relayer that exports the certified checkpoints of a Narwhal node to a bridge
+//! contract on L1.
+//!
+//! The relayer subscribes to `narwhal_subscribeCheckpoints` on the WS endpoint of a Narwhal node.
+//! Every checkpoint range signed by a quorum of the committee is verified locally and then
+//! submitted to the bridge contract, which verifies the ABI encoded proof with the
+//! `NarwhalCheckpoint` library before accepting the range.
+//!
+//! Run with
+//!
+//! ```not_rust
+//! cargo run -p example-narwhal-bridge-relayer -- \
+//!     --narwhal-ws ws://localhost:8546 \
+//!     --l1-rpc http://localhost:8545 \
+//!     --bridge 0x5FbDB2315678afecb367f032d93F642f64180aa3 \
+//!     --private-key 0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcaa7d3ebf9a5e2aa2
+//! ```
+
+use alloy_network::{EthereumWallet, TransactionBuilder};
+use alloy_primitives::Address;
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::TransactionRequest;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::{sol, SolCall};
+use clap::Parser;
+use jsonrpsee::{core::client::SubscriptionClientT, rpc_params, ws_client::WsClientBuilder};
+use reth_narwhal_consensus::rpc::CertifiedCheckpoint;
+use reth_narwhal_verifier::abi::CheckpointRangeProof;
+
+sol! {
+    /// The entry point of the bridge contract for the checkpoints of the Narwhal chain.
+    interface INarwhalBridge {
+        /// Verifies the proof with `NarwhalCheckpoint.decodeRange` and `checkQuorum`, and
+        /// accepts the range.
+        function submitCheckpointRange(bytes proof) external;
+    }
+}
+
+/// The arguments of the relayer.
+#[derive(Debug, Parser)]
+struct Args {
+    /// The WS endpoint of the Narwhal node.
+    #[arg(long, default_value = "ws://localhost:8546")]
+    narwhal_ws: String,
+    /// The HTTP endpoint of the L1 node.
+    #[arg(long, default_value = "http://localhost:8545")]
+    l1_rpc: String,
+    /// The address of the bridge contract.
+    #[arg(long)]
+    bridge: Address,
+    /// The private key of the account that pays for the submissions.
+    #[arg(long)]
+    private_key: PrivateKeySigner,
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let args = Args::parse();
+
+    let l1 = ProviderBuilder::new()
+        .with_recommended_fillers()
+        .wallet(EthereumWallet::from(args.private_key))
+        .on_http(args.l1_rpc.parse()?);
+
+    let narwhal = WsClientBuilder::default().build(&args.narwhal_ws).await?;
+    let mut checkpoints = narwhal
+        .subscribe::<CertifiedCheckpoint, _>(
+            "narwhal_subscribeCheckpoints",
+            rpc_params![],
+            "narwhal_unsubscribeCheckpoints",
+        )
+        .await?;
+    println!("Subscribed to the checkpoints of {}", args.narwhal_ws);
+
+    while let Some(checkpoint) = checkpoints.next().await {
+        let checkpoint = checkpoint?;
+
+        // the bridge rejects proofs that don't decode, so don't pay for submitting them
+        let proof = CheckpointRangeProof::decode(&checkpoint.proof)?;
+        println!(
+            "Submitting blocks {}..={} of epoch {}, signed by {:?}",
+            checkpoint.first_block,
+            checkpoint.last_block,
+            checkpoint.epoch,
+            proof.signer_indices(),
+        );
+
+        let call = INarwhalBridge::submitCheckpointRangeCall { proof: checkpoint.proof };
+        let tx = TransactionRequest::default().with_to(args.bridge).with_input(call.abi_encode());
+        let receipt = l1.send_transaction(tx).await?.get_receipt().await?;
+        println!(
+            "Submitted in transaction {} with status {}",
+            receipt.transaction_hash,
+            receipt.status()
+        );
+    }
+
+    println!("The Narwhal node closed the subscription");
+    Ok(())
+}
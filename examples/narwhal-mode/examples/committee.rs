@@ -0,0 +1,24 @@
+//! Runs a node with a local committee of four validators and sends a transfer to it.
+//!
+//! ```sh
+//! cargo run -p example-narwhal-mode --example committee
+//! ```
+
+use example_narwhal_mode::committee;
+use reth::tasks::TaskManager;
+use reth_tracing::{RethTracer, Tracer};
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let _guard = RethTracer::new().init()?;
+    let tasks = TaskManager::current();
+
+    let (receipt, committee) = committee::run(tasks.executor()).await?;
+    println!(
+        "transfer {} included in block {:?} by a committee of {} validators",
+        receipt.transaction_hash,
+        receipt.block_number,
+        committee.authorities.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,19 @@
+//! Runs a single node in dev mode and sends a transfer to it.
+//!
+//! ```sh
+//! cargo run -p example-narwhal-mode --example dev
+//! ```
+
+use example_narwhal_mode::dev;
+use reth::tasks::TaskManager;
+use reth_tracing::{RethTracer, Tracer};
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let _guard = RethTracer::new().init()?;
+    let tasks = TaskManager::current();
+
+    let receipt = dev::run(tasks.executor()).await?;
+    println!("transfer {} included in block {:?}", receipt.transaction_hash, receipt.block_number);
+    Ok(())
+}
@@ -0,0 +1,22 @@
+//! Runs a validator and a follower node, and reads a transfer of the validator from the follower.
+//!
+//! ```sh
+//! cargo run -p example-narwhal-mode --example follower
+//! ```
+
+use example_narwhal_mode::follower;
+use reth::tasks::TaskManager;
+use reth_tracing::{RethTracer, Tracer};
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let _guard = RethTracer::new().init()?;
+    let tasks = TaskManager::current();
+
+    let receipt = follower::run(tasks.executor()).await?;
+    println!(
+        "follower serves transfer {} of block {:?}",
+        receipt.transaction_hash, receipt.block_number
+    );
+    Ok(())
+}
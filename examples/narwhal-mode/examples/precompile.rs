@@ -0,0 +1,21 @@
+//! Runs a node with a custom executor whose EVM has an additional precompile, and calls it.
+//!
+//! ```sh
+//! cargo run -p example-narwhal-mode --example precompile
+//! ```
+
+use example_narwhal_mode::precompile::{self, ECHO};
+use reth::{primitives::Bytes, tasks::TaskManager};
+use reth_tracing::{RethTracer, Tracer};
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let _guard = RethTracer::new().init()?;
+    let tasks = TaskManager::current();
+
+    let (output, receipt) =
+        precompile::run(tasks.executor(), Bytes::from_static(b"hello narwhal")).await?;
+    println!("precompile {ECHO} returned {output}");
+    println!("transfer {} included in block {:?}", receipt.transaction_hash, receipt.block_number);
+    Ok(())
+}
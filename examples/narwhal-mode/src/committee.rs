@@ -0,0 +1,95 @@
+//! A node that runs a local committee of four validators.
+//!
+//! The [`NarwhalNodeLauncher`](reth_node_narwhal::NarwhalNodeLauncher) runs the committee of the
+//! node's command line. A node can also be launched with a [`NarwhalLaunchHook`] of its own, here
+//! with the keys of all validators of the committee and two workers per validator. The narwhal RPC
+//! namespace is installed like the launcher installs it.
+//!
+//! The committee of the next epoch is read from `<DATADIR>/narwhal/epoch-change.toml`, like on a
+//! node launched from the command line.
+
+use crate::{node_config, send_transaction, transfer};
+use jsonrpsee::{core::client::ClientT, rpc_params};
+use reth::{
+    builder::{DefaultNodeLauncher, NodeBuilder, NodeHandle},
+    rpc::types::AnyTransactionReceipt,
+    tasks::TaskExecutor,
+};
+use reth_narwhal_consensus::{
+    committee::Committee, dag_store::MemoryDagStore, dev::DevCommittee, epoch::FileCommitteeSource,
+    gc::DEFAULT_GC_DEPTH, verifier::AuthorityIndex, NarwhalConfig,
+};
+use reth_node_narwhal::{
+    install_narwhal_rpc, install_recent_receipts, install_transaction_status, NarwhalLaunchHook,
+    NarwhalNode,
+};
+use std::sync::Arc;
+
+/// The number of validators of the committee.
+pub const VALIDATORS: usize = 4;
+
+/// The number of workers of every validator.
+pub const WORKERS: usize = 2;
+
+/// Launches a node that runs a committee of [`VALIDATORS`], and returns the receipt of a transfer
+/// it included together with the committee its RPC serves.
+pub async fn run(exec: TaskExecutor) -> eyre::Result<(AnyTransactionReceipt, Committee)> {
+    // the keys of a dev committee are derived from fixed seeds, a real committee is created with
+    // `reth narwhal init-chain`
+    let dev = DevCommittee::new(VALIDATORS);
+    let keys = (0..VALIDATORS as AuthorityIndex)
+        .filter_map(|authority| dev.secret_key(authority).cloned())
+        .collect();
+    let committee = dev.committee().clone();
+
+    let NodeHandle { node, node_exit_future: _ } = NodeBuilder::new(node_config())
+        .testing_node(exec)
+        .node(NarwhalNode::default())
+        .launch_with_fn(|builder| {
+            let data_dir = builder.config().datadir();
+            let source = FileCommitteeSource::new(
+                data_dir.data_dir().join("narwhal").join("epoch-change.toml"),
+            );
+            // the DAG of a node that restarts is kept in its database, see `DatabaseDagStore`
+            let hook = NarwhalLaunchHook::new(
+                committee,
+                keys,
+                Arc::new(MemoryDagStore::default()),
+                Arc::new(source),
+                NarwhalConfig::default(),
+                DEFAULT_GC_DEPTH,
+            )
+            .with_worker_count(WORKERS);
+            let (state, receipts, dropped) =
+                (hook.consensus_state(), hook.recent_receipts(), hook.dropped_transactions());
+            let launcher = DefaultNodeLauncher::new(builder.task_executor().clone(), data_dir)
+                .with_stage_hook(hook);
+            builder
+                .extend_rpc_modules(move |mut ctx| {
+                    install_narwhal_rpc(&mut ctx, state)?;
+                    install_recent_receipts(&mut ctx, receipts)?;
+                    install_transaction_status(&mut ctx, dropped)
+                })
+                .launch_with(launcher)
+        })
+        .await?;
+
+    let client = node.rpc_server_handle().http_client().expect("http is enabled");
+    let receipt = send_transaction(&client, transfer(0)).await?;
+    let committee = client.request("narwhal_committee", rpc_params![]).await?;
+    Ok((receipt, committee))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth::tasks::TaskManager;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn committee_includes_transfer() {
+        let tasks = TaskManager::current();
+        let (receipt, committee) = run(tasks.executor()).await.unwrap();
+        assert!(receipt.status());
+        assert_eq!(committee.authorities.len(), VALIDATORS);
+    }
+}
@@ -0,0 +1,49 @@
+//! A single node in dev mode.
+//!
+//! In dev mode the [`NarwhalNodeLauncher`] runs a dev committee instead of the auto-seal miner,
+//! here of a single authority, which seals the transactions of the pool into batches and commits
+//! a block for every sub-dag.
+
+use crate::{node_config, send_transaction, transfer};
+use reth::{
+    builder::{NodeBuilder, NodeHandle},
+    rpc::types::AnyTransactionReceipt,
+    tasks::TaskExecutor,
+};
+use reth_node_narwhal::{NarwhalNode, NarwhalNodeLauncher};
+
+/// Launches a node in dev mode, and returns the receipt of a transfer it included.
+pub async fn run(exec: TaskExecutor) -> eyre::Result<AnyTransactionReceipt> {
+    let mut config = node_config().dev();
+    config.dev.narwhal_committee_size = Some(1);
+
+    let node = NarwhalNode::default();
+    let sequenced = node.sequenced_index();
+    let NodeHandle { node, node_exit_future: _ } = NodeBuilder::new(config)
+        .testing_node(exec)
+        .node(node)
+        .launch_with_fn(|builder| {
+            let launcher = NarwhalNodeLauncher::new(
+                builder.task_executor().clone(),
+                builder.config().datadir(),
+            )
+            .with_sequenced_index(sequenced);
+            builder.launch_with(launcher)
+        })
+        .await?;
+
+    let client = node.rpc_server_handle().http_client().expect("http is enabled");
+    send_transaction(&client, transfer(0)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use reth::tasks::TaskManager;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_node_includes_transfer() {
+        let tasks = TaskManager::current();
+        let receipt = super::run(tasks.executor()).await.unwrap();
+        assert!(receipt.status());
+    }
+}
@@ -0,0 +1,87 @@
+//! A follower node that serves the RPC of the chain of a validator.
+//!
+//! A node without a committee doesn't take part in consensus, and imports the blocks of the
+//! validators through the engine API like a node that is driven by a consensus client. Here the
+//! blocks of a validator in dev mode are handed to the follower with a new payload and a
+//! forkchoice update per block, after which the follower serves the transactions of the
+//! validator.
+
+use crate::{node_config, send_transaction, transfer, wait_for_receipt};
+use reth::{
+    builder::{NodeBuilder, NodeHandle},
+    providers::{BlockNumReader, BlockReader},
+    rpc::{
+        api::EngineApiClient,
+        compat::engine::payload::block_to_payload_v3,
+        types::{engine::ForkchoiceState, AnyTransactionReceipt},
+    },
+    tasks::TaskExecutor,
+};
+use reth_node_narwhal::{NarwhalNode, NarwhalNodeLauncher};
+
+/// Launches a validator in dev mode and a follower, and returns the receipt of a transfer the
+/// validator included, as served by the follower.
+pub async fn run(exec: TaskExecutor) -> eyre::Result<AnyTransactionReceipt> {
+    let mut validator_config = node_config().dev();
+    validator_config.dev.narwhal_committee_size = Some(1);
+    // neither in dev mode nor with a committee file, so the follower has no committee
+    let follower_config = node_config();
+
+    let mut nodes = Vec::new();
+    for config in [validator_config, follower_config] {
+        let node = NarwhalNode::default();
+        let sequenced = node.sequenced_index();
+        let NodeHandle { node, node_exit_future: _ } = NodeBuilder::new(config)
+            .testing_node(exec.clone())
+            .node(node)
+            .launch_with_fn(|builder| {
+                let launcher = NarwhalNodeLauncher::new(
+                    builder.task_executor().clone(),
+                    builder.config().datadir(),
+                )
+                .with_sequenced_index(sequenced);
+                builder.launch_with(launcher)
+            })
+            .await?;
+        nodes.push(node);
+    }
+    let follower = nodes.pop().expect("follower launched");
+    let validator = nodes.pop().expect("validator launched");
+
+    let client = validator.rpc_server_handle().http_client().expect("http is enabled");
+    let receipt = send_transaction(&client, transfer(0)).await?;
+
+    let engine = follower.engine_http_client();
+    for number in 1..=validator.provider.best_block_number()? {
+        let block = validator.provider.block_by_number(number)?.expect("block exists").seal_slow();
+        let hash = block.hash();
+        let parent_beacon_block_root =
+            block.parent_beacon_block_root.expect("blocks of the dev chain are cancun blocks");
+        let versioned_hashes = block.blob_versioned_hashes_iter().copied().collect();
+        let status = engine
+            .new_payload_v3(block_to_payload_v3(block), versioned_hashes, parent_beacon_block_root)
+            .await?;
+        eyre::ensure!(status.status.is_valid(), "block {number} rejected: {status:?}");
+        let state = ForkchoiceState {
+            head_block_hash: hash,
+            safe_block_hash: hash,
+            finalized_block_hash: hash,
+        };
+        engine.fork_choice_updated_v3(state, None).await?;
+    }
+
+    let client = follower.rpc_server_handle().http_client().expect("http is enabled");
+    wait_for_receipt(&client, receipt.transaction_hash).await
+}
+
+#[cfg(test)]
+mod tests {
+    use reth::tasks::TaskManager;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn follower_serves_transfer() {
+        let tasks = TaskManager::current();
+        let receipt = super::run(tasks.executor()).await.unwrap();
+        assert!(receipt.status());
+    }
+}
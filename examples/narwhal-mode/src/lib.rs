@@ -0,0 +1,109 @@
+//! Examples of nodes in narwhal mode.
+//!
+//! Every module is an example that launches its nodes, sends them a transaction and returns once
+//! the transaction is included. They run with `cargo run -p example-narwhal-mode --example
+//! <name>`, and the smoke tests of the modules run them the same way:
+//!
+//! - [`dev`]: a single node in dev mode, whose committee of a single authority seals the blocks
+//! - [`committee`]: a node that runs a local committee of four validators with two workers each
+//! - [`follower`]: a node without a committee that serves the RPC of the chain of a validator
+//! - [`precompile`]: a node with a custom executor whose EVM has an additional precompile
+
+#![cfg_attr(not(test), warn(unused_crate_dependencies))]
+
+use eyre::WrapErr;
+use jsonrpsee::{core::client::ClientT, http_client::HttpClient, rpc_params};
+use reth::{
+    args::{DiscoveryArgs, NetworkArgs, RpcServerArgs},
+    builder::NodeConfig,
+    rpc::types::AnyTransactionReceipt,
+};
+use reth_chainspec::DEV;
+use reth_primitives::{
+    b256, sign_message, Address, Bytes, Transaction, TransactionSigned, TxEip1559, TxKind, B256,
+    U256,
+};
+use std::time::Duration;
+
+pub mod committee;
+pub mod dev;
+pub mod follower;
+pub mod precompile;
+
+/// The key of the first funded account of the dev chain.
+pub const DEV_KEY: B256 = b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+
+/// The recipient of the transfers of the examples.
+pub const RECIPIENT: Address = Address::with_last_byte(0x42);
+
+/// How long a node may take to include a transaction.
+const INCLUSION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Returns the config of a node of the dev chain that serves the Ethereum RPC over HTTP.
+///
+/// The node listens on unused ports and doesn't discover peers, so that the nodes of an example
+/// can run side by side.
+pub fn node_config() -> NodeConfig {
+    let network = NetworkArgs {
+        discovery: DiscoveryArgs { disable_discovery: true, ..DiscoveryArgs::default() },
+        ..NetworkArgs::default()
+    };
+    NodeConfig::test()
+        .with_chain(DEV.clone())
+        .with_network(network)
+        .with_unused_ports()
+        .with_rpc(RpcServerArgs::default().with_unused_ports().with_http())
+}
+
+/// Signs an EIP-1559 transaction of the first funded account of the dev chain, and returns its
+/// network encoding.
+pub fn sign_transaction(nonce: u64, to: TxKind, value: U256, input: Bytes) -> Bytes {
+    let transaction = Transaction::Eip1559(TxEip1559 {
+        chain_id: DEV.chain.id(),
+        nonce,
+        gas_limit: 100_000,
+        max_fee_per_gas: 20_000_000_000,
+        max_priority_fee_per_gas: 1_000_000_000,
+        to,
+        value,
+        input,
+        ..Default::default()
+    });
+    let signature =
+        sign_message(DEV_KEY, transaction.signature_hash()).expect("dev key is a valid key");
+    TransactionSigned::from_transaction_and_signature(transaction, signature).envelope_encoded()
+}
+
+/// Returns the transfer of one ether to the [`RECIPIENT`] with the given nonce.
+pub fn transfer(nonce: u64) -> Bytes {
+    sign_transaction(nonce, TxKind::Call(RECIPIENT), U256::from(10u64.pow(18)), Bytes::new())
+}
+
+/// Sends a transaction to a node, and waits until the node included it in a block.
+pub async fn send_transaction(
+    client: &HttpClient,
+    transaction: Bytes,
+) -> eyre::Result<AnyTransactionReceipt> {
+    let hash: B256 = client.request("eth_sendRawTransaction", rpc_params![transaction]).await?;
+    wait_for_receipt(client, hash).await
+}
+
+/// Polls the receipt of a transaction until the node included it in a block.
+pub async fn wait_for_receipt(
+    client: &HttpClient,
+    hash: B256,
+) -> eyre::Result<AnyTransactionReceipt> {
+    let receipt = async {
+        loop {
+            let receipt: Option<AnyTransactionReceipt> =
+                client.request("eth_getTransactionReceipt", rpc_params![hash]).await?;
+            if let Some(receipt) = receipt {
+                return eyre::Ok(receipt)
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    };
+    tokio::time::timeout(INCLUSION_TIMEOUT, receipt)
+        .await
+        .wrap_err_with(|| format!("transaction {hash} not included in time"))?
+}
@@ -0,0 +1,192 @@
+//! A node with a custom executor whose EVM has an additional precompile.
+//!
+//! The node uses the components of the [`NarwhalNode`] with a custom [`ExecutorBuilder`]. The
+//! blocks of a narwhal node are built by its payload builder from the transactions of a sub-dag,
+//! so the [`NarwhalPayloadBuilder`] must execute them with the same EVM.
+//!
+//! The Ethereum executor checks its results against a parallel execution with the Ethereum
+//! precompiles, which doesn't know the [`ECHO`] precompile. The precompile is served to calls,
+//! but a block with a transaction that calls it halts the executor.
+
+use crate::{node_config, send_transaction, transfer};
+use jsonrpsee::{core::client::ClientT, rpc_params};
+use reth::{
+    api::{ConfigureEvm, ConfigureEvmEnv, FullNodeTypes},
+    builder::{components::ExecutorBuilder, BuilderContext, NodeBuilder, NodeHandle},
+    primitives::{
+        address,
+        revm_primitives::{CfgEnvWithHandlerCfg, Env, PrecompileResult, TxEnv},
+        Address, Bytes, Header, TransactionSigned, U256,
+    },
+    revm::{
+        handler::register::EvmHandler,
+        inspector_handle_register,
+        precompile::{Precompile, PrecompileError, PrecompileOutput, PrecompileSpecId},
+        ContextPrecompiles, Database, Evm, EvmBuilder, GetInspector,
+    },
+    rpc::types::AnyTransactionReceipt,
+    tasks::TaskExecutor,
+};
+use reth_chainspec::ChainSpec;
+use reth_narwhal_consensus::worker::SequencedTransactions;
+use reth_node_ethereum::{node::EthereumAddOns, EthEvmConfig, EthExecutorProvider};
+use reth_node_narwhal::{
+    node::{NarwhalPayloadBuilder, NarwhalPoolBuilder},
+    NarwhalNode, NarwhalNodeLauncher,
+};
+use serde_json::json;
+use std::sync::Arc;
+
+/// The address of the precompile that returns its input.
+pub const ECHO: Address = address!("0000000000000000000000000000000000000999");
+
+/// The gas the [`ECHO`] precompile costs.
+pub const ECHO_GAS: u64 = 15;
+
+/// The EVM configuration of Ethereum with the [`ECHO`] precompile.
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct EchoEvmConfig;
+
+impl EchoEvmConfig {
+    /// Installs the precompiles of the spec of the EVM handler and the [`ECHO`] precompile.
+    pub fn set_precompiles<EXT, DB>(handler: &mut EvmHandler<EXT, DB>)
+    where
+        DB: Database,
+    {
+        let spec_id = handler.cfg.spec_id;
+        handler.pre_execution.load_precompiles = Arc::new(move || {
+            let mut precompiles = ContextPrecompiles::new(PrecompileSpecId::from_spec_id(spec_id));
+            precompiles.extend([(ECHO, Precompile::Env(Self::echo).into())]);
+            precompiles
+        });
+    }
+
+    /// Returns the input.
+    fn echo(input: &Bytes, gas_limit: u64, _env: &Env) -> PrecompileResult {
+        if gas_limit < ECHO_GAS {
+            return Err(PrecompileError::OutOfGas.into())
+        }
+        Ok(PrecompileOutput::new(ECHO_GAS, input.clone()))
+    }
+}
+
+impl ConfigureEvmEnv for EchoEvmConfig {
+    fn fill_tx_env(&self, tx_env: &mut TxEnv, transaction: &TransactionSigned, sender: Address) {
+        EthEvmConfig::default().fill_tx_env(tx_env, transaction, sender)
+    }
+
+    fn fill_tx_env_system_contract_call(
+        &self,
+        env: &mut Env,
+        caller: Address,
+        contract: Address,
+        data: Bytes,
+    ) {
+        EthEvmConfig::default().fill_tx_env_system_contract_call(env, caller, contract, data)
+    }
+
+    fn fill_cfg_env(
+        &self,
+        cfg_env: &mut CfgEnvWithHandlerCfg,
+        chain_spec: &ChainSpec,
+        header: &Header,
+        total_difficulty: U256,
+    ) {
+        EthEvmConfig::default().fill_cfg_env(cfg_env, chain_spec, header, total_difficulty)
+    }
+}
+
+impl ConfigureEvm for EchoEvmConfig {
+    type DefaultExternalContext<'a> = ();
+
+    fn evm<DB: Database>(&self, db: DB) -> Evm<'_, Self::DefaultExternalContext<'_>, DB> {
+        EvmBuilder::default().with_db(db).append_handler_register(Self::set_precompiles).build()
+    }
+
+    fn evm_with_inspector<DB, I>(&self, db: DB, inspector: I) -> Evm<'_, I, DB>
+    where
+        DB: Database,
+        I: GetInspector<DB>,
+    {
+        EvmBuilder::default()
+            .with_db(db)
+            .with_external_context(inspector)
+            .append_handler_register(Self::set_precompiles)
+            .append_handler_register(inspector_handle_register)
+            .build()
+    }
+
+    fn default_external_context<'a>(&self) -> Self::DefaultExternalContext<'a> {}
+}
+
+/// Builds the Ethereum block executor with the [`EchoEvmConfig`].
+#[derive(Debug, Default, Clone, Copy)]
+#[non_exhaustive]
+pub struct EchoExecutorBuilder;
+
+impl<Node> ExecutorBuilder<Node> for EchoExecutorBuilder
+where
+    Node: FullNodeTypes,
+{
+    type EVM = EchoEvmConfig;
+    type Executor = EthExecutorProvider<Self::EVM>;
+
+    async fn build_evm(
+        self,
+        ctx: &BuilderContext<Node>,
+    ) -> eyre::Result<(Self::EVM, Self::Executor)> {
+        Ok((EchoEvmConfig, EthExecutorProvider::new(ctx.chain_spec(), EchoEvmConfig)))
+    }
+}
+
+/// Launches a node in dev mode with the [`ECHO`] precompile, and returns the output of a call to
+/// the precompile and the receipt of a transfer the node included.
+pub async fn run(exec: TaskExecutor, input: Bytes) -> eyre::Result<(Bytes, AnyTransactionReceipt)> {
+    let mut config = node_config().dev();
+    config.dev.narwhal_committee_size = Some(1);
+
+    // the pool rejects the transactions the workers already sequenced
+    let sequenced = SequencedTransactions::default();
+    let NodeHandle { node, node_exit_future: _ } = NodeBuilder::new(config)
+        .testing_node(exec)
+        .with_types::<NarwhalNode>()
+        .with_components(
+            NarwhalNode::components()
+                .pool(NarwhalPoolBuilder::new(sequenced.clone()))
+                .executor(EchoExecutorBuilder)
+                .payload(NarwhalPayloadBuilder::new(EchoEvmConfig)),
+        )
+        .with_add_ons::<EthereumAddOns>()
+        .launch_with_fn(|builder| {
+            let launcher = NarwhalNodeLauncher::new(
+                builder.task_executor().clone(),
+                builder.config().datadir(),
+            )
+            .with_sequenced_index(sequenced);
+            builder.launch_with(launcher)
+        })
+        .await?;
+
+    let client = node.rpc_server_handle().http_client().expect("http is enabled");
+    let output = client
+        .request("eth_call", rpc_params![json!({ "to": ECHO, "input": input }), "latest"])
+        .await?;
+    let receipt = send_transaction(&client, transfer(0)).await?;
+    Ok((output, receipt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth::tasks::TaskManager;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn precompile_serves_calls() {
+        let tasks = TaskManager::current();
+        let input = Bytes::from_static(b"narwhal");
+        let (output, receipt) = run(tasks.executor(), input.clone()).await.unwrap();
+        assert_eq!(output, input);
+        assert!(receipt.status());
+    }
+}